@@ -0,0 +1,74 @@
+#ifndef TAPO_H
+#define TAPO_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * All functions are blocking. Clients and devices must be released with their `_free` function
+ * and strings returned by the library must be released with `tapo_string_free`.
+ */
+
+typedef enum TapoStatus {
+    TAPO_STATUS_OK = 0,
+    /* A pointer was null, a string wasn't valid UTF-8 or an enum value was unknown. */
+    TAPO_STATUS_INVALID_ARGUMENT = 1,
+    /* The device couldn't be reached. */
+    TAPO_STATUS_UNREACHABLE = 2,
+    /* The device rejected the request. */
+    TAPO_STATUS_DEVICE = 3,
+    /* A provided value is out of range. */
+    TAPO_STATUS_VALIDATION = 4,
+    /* The device, or its firmware version, doesn't support the operation. */
+    TAPO_STATUS_NOT_SUPPORTED = 5,
+    /* Any other error. */
+    TAPO_STATUS_OTHER = 6,
+} TapoStatus;
+
+typedef enum TapoDeviceKind {
+    /* Any Tapo device, with on/off and device info support only. */
+    TAPO_DEVICE_KIND_GENERIC = 0,
+    /* Light bulbs (L510, L520, L610). */
+    TAPO_DEVICE_KIND_LIGHT = 1,
+    /* Color light bulbs (L530, L630, L900). */
+    TAPO_DEVICE_KIND_COLOR_LIGHT = 2,
+    /* Color light strips (L920, L930). */
+    TAPO_DEVICE_KIND_COLOR_LIGHT_STRIP = 3,
+    /* Plugs (P100, P105). */
+    TAPO_DEVICE_KIND_PLUG = 4,
+    /* Plugs with energy monitoring (P110, P115). */
+    TAPO_DEVICE_KIND_PLUG_ENERGY_MONITORING = 5,
+} TapoDeviceKind;
+
+typedef struct TapoClient TapoClient;
+typedef struct TapoDevice TapoDevice;
+
+/* Returns the message of the last error on the calling thread, or NULL. Owned by the library. */
+const char *tapo_last_error_message(void);
+
+void tapo_string_free(char *string);
+
+TapoStatus tapo_client_new(const char *username, const char *password, TapoClient **out);
+void tapo_client_free(TapoClient *client);
+
+/* `kind` is one of the TapoDeviceKind values, anything else fails with TAPO_STATUS_INVALID_ARGUMENT. */
+TapoStatus tapo_device_connect(const TapoClient *client, uint32_t kind, const char *ip_address, TapoDevice **out);
+void tapo_device_free(TapoDevice *device);
+
+TapoStatus tapo_device_on(const TapoDevice *device);
+TapoStatus tapo_device_off(const TapoDevice *device);
+TapoStatus tapo_device_set_brightness(const TapoDevice *device, uint8_t brightness);
+TapoStatus tapo_device_set_hue_saturation(const TapoDevice *device, uint16_t hue, uint8_t saturation);
+TapoStatus tapo_device_set_color_temperature(const TapoDevice *device, uint16_t color_temperature);
+TapoStatus tapo_device_get_device_info_json(const TapoDevice *device, char **out);
+TapoStatus tapo_device_get_current_power(const TapoDevice *device, uint64_t *out);
+TapoStatus tapo_device_get_energy_usage_json(const TapoDevice *device, char **out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TAPO_H */
@@ -0,0 +1,44 @@
+use std::ffi::c_char;
+
+use tapo::ApiClient;
+
+use crate::{ffi_call, read_str, write_out, TapoStatus};
+
+/// Opaque handle to a [`tapo::ApiClient`].
+pub struct TapoClient {
+    pub(crate) client: ApiClient,
+}
+
+/// Creates a client with the given Tapo credentials and writes it to `out`.
+/// The client must be released with [`tapo_client_free`].
+///
+/// # Safety
+///
+/// `username` and `password` must be nul-terminated strings and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn tapo_client_new(
+    username: *const c_char,
+    password: *const c_char,
+    out: *mut *mut TapoClient,
+) -> TapoStatus {
+    ffi_call(|| {
+        let username = read_str(username, "username")?;
+        let password = read_str(password, "password")?;
+
+        let client = ApiClient::new(username, password)?;
+
+        write_out(out, Box::into_raw(Box::new(TapoClient { client })))
+    })
+}
+
+/// Releases a client. The devices connected through it remain valid. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `client` must have been returned by [`tapo_client_new`] and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tapo_client_free(client: *mut TapoClient) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
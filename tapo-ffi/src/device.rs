@@ -0,0 +1,334 @@
+use std::ffi::c_char;
+
+use tapo::{
+    ApiClient, ColorLightHandler, ColorLightStripHandler, Error, GenericDeviceHandler,
+    LightHandler, PlugEnergyMonitoringHandler, PlugHandler,
+};
+
+use crate::errors::invalid_argument;
+use crate::{block_on, ffi_call, read_str, write_json, write_out, TapoClient, TapoStatus};
+
+/// The kind of handler used to control a device, see [`tapo_device_connect`],
+/// which takes its value as a `u32`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Enum))]
+pub enum TapoDeviceKind {
+    /// Any Tapo device, with on/off and device info support only.
+    Generic = 0,
+    /// Light bulbs (L510, L520, L610).
+    Light = 1,
+    /// Color light bulbs (L530, L630, L900).
+    ColorLight = 2,
+    /// Color light strips (L920, L930).
+    ColorLightStrip = 3,
+    /// Plugs (P100, P105).
+    Plug = 4,
+    /// Plugs with energy monitoring (P110, P115).
+    PlugEnergyMonitoring = 5,
+}
+
+impl TryFrom<u32> for TapoDeviceKind {
+    type Error = u32;
+
+    /// Returns the kind with the value `kind`, or the value itself if it's unknown.
+    fn try_from(kind: u32) -> Result<Self, Self::Error> {
+        Ok(match kind {
+            0 => Self::Generic,
+            1 => Self::Light,
+            2 => Self::ColorLight,
+            3 => Self::ColorLightStrip,
+            4 => Self::Plug,
+            5 => Self::PlugEnergyMonitoring,
+            _ => return Err(kind),
+        })
+    }
+}
+
+/// Opaque handle to a connected device.
+pub enum TapoDevice {
+    Generic(GenericDeviceHandler),
+    Light(LightHandler),
+    ColorLight(ColorLightHandler),
+    ColorLightStrip(ColorLightStripHandler),
+    Plug(PlugHandler),
+    PlugEnergyMonitoring(PlugEnergyMonitoringHandler),
+}
+
+/// The operations shared by the C and the uniffi bindings.
+impl TapoDevice {
+    pub(crate) async fn connect(
+        client: ApiClient,
+        kind: TapoDeviceKind,
+        ip_address: String,
+    ) -> Result<Self, Error> {
+        Ok(match kind {
+            TapoDeviceKind::Generic => Self::Generic(client.generic_device(ip_address).await?),
+            TapoDeviceKind::Light => Self::Light(client.l510(ip_address).await?),
+            TapoDeviceKind::ColorLight => Self::ColorLight(client.l530(ip_address).await?),
+            TapoDeviceKind::ColorLightStrip => {
+                Self::ColorLightStrip(client.l930(ip_address).await?)
+            }
+            TapoDeviceKind::Plug => Self::Plug(client.p100(ip_address).await?),
+            TapoDeviceKind::PlugEnergyMonitoring => {
+                Self::PlugEnergyMonitoring(client.p110(ip_address).await?)
+            }
+        })
+    }
+
+    pub(crate) async fn on(&self) -> Result<(), Error> {
+        match self {
+            Self::Generic(handler) => handler.on().await,
+            Self::Light(handler) => handler.on().await,
+            Self::ColorLight(handler) => handler.on().await,
+            Self::ColorLightStrip(handler) => handler.on().await,
+            Self::Plug(handler) => handler.on().await,
+            Self::PlugEnergyMonitoring(handler) => handler.on().await,
+        }
+    }
+
+    pub(crate) async fn off(&self) -> Result<(), Error> {
+        match self {
+            Self::Generic(handler) => handler.off().await,
+            Self::Light(handler) => handler.off().await,
+            Self::ColorLight(handler) => handler.off().await,
+            Self::ColorLightStrip(handler) => handler.off().await,
+            Self::Plug(handler) => handler.off().await,
+            Self::PlugEnergyMonitoring(handler) => handler.off().await,
+        }
+    }
+
+    pub(crate) async fn set_brightness(&self, brightness: u8) -> Result<(), Error> {
+        match self {
+            Self::Light(handler) => handler.set_brightness(brightness).await,
+            Self::ColorLight(handler) => handler.set_brightness(brightness).await,
+            Self::ColorLightStrip(handler) => handler.set_brightness(brightness).await,
+            _ => Err(not_supported("Brightness")),
+        }
+    }
+
+    pub(crate) async fn set_hue_saturation(&self, hue: u16, saturation: u8) -> Result<(), Error> {
+        match self {
+            Self::ColorLight(handler) => handler.set_hue_saturation(hue, saturation).await,
+            Self::ColorLightStrip(handler) => handler.set_hue_saturation(hue, saturation).await,
+            _ => Err(not_supported("Color")),
+        }
+    }
+
+    pub(crate) async fn set_color_temperature(&self, color_temperature: u16) -> Result<(), Error> {
+        match self {
+            Self::ColorLight(handler) => handler.set_color_temperature(color_temperature).await,
+            Self::ColorLightStrip(handler) => {
+                handler.set_color_temperature(color_temperature).await
+            }
+            _ => Err(not_supported("Color temperature")),
+        }
+    }
+
+    pub(crate) async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
+        match self {
+            Self::Generic(handler) => handler.get_device_info_json().await,
+            Self::Light(handler) => handler.get_device_info_json().await,
+            Self::ColorLight(handler) => handler.get_device_info_json().await,
+            Self::ColorLightStrip(handler) => handler.get_device_info_json().await,
+            Self::Plug(handler) => handler.get_device_info_json().await,
+            Self::PlugEnergyMonitoring(handler) => handler.get_device_info_json().await,
+        }
+    }
+
+    pub(crate) async fn get_current_power(&self) -> Result<u64, Error> {
+        let Self::PlugEnergyMonitoring(handler) = self else {
+            return Err(not_supported("Energy monitoring"));
+        };
+
+        Ok(handler.get_current_power().await?.current_power)
+    }
+
+    pub(crate) async fn get_energy_usage_json(&self) -> Result<serde_json::Value, Error> {
+        let Self::PlugEnergyMonitoring(handler) = self else {
+            return Err(not_supported("Energy monitoring"));
+        };
+
+        Ok(serde_json::to_value(handler.get_energy_usage().await?)?)
+    }
+
+    /// Returns the device behind `device`.
+    ///
+    /// # Safety
+    ///
+    /// `device` must be null or have been returned by [`tapo_device_connect`].
+    unsafe fn from_ptr<'a>(device: *const TapoDevice) -> Result<&'a Self, TapoStatus> {
+        device
+            .as_ref()
+            .ok_or_else(|| invalid_argument("`device` is null"))
+    }
+}
+
+fn not_supported(feature: &str) -> Error {
+    Error::NotSupported {
+        feature: feature.to_string(),
+    }
+}
+
+/// Connects to the device at `ip_address` with the handler of `kind` and writes it to `out`.
+/// The device must be released with [`tapo_device_free`].
+///
+/// # Safety
+///
+/// `client` must have been returned by [`tapo_client_new`](crate::tapo_client_new),
+/// `ip_address` must be a nul-terminated string and `out` must be valid for writes.
+/// `kind` is taken as a `u32` rather than a [`TapoDeviceKind`], so that any value is safe to pass:
+/// the ones that aren't a [`TapoDeviceKind`] are rejected with [`TapoStatus::InvalidArgument`].
+#[no_mangle]
+pub unsafe extern "C" fn tapo_device_connect(
+    client: *const TapoClient,
+    kind: u32,
+    ip_address: *const c_char,
+    out: *mut *mut TapoDevice,
+) -> TapoStatus {
+    ffi_call(|| {
+        let client = client
+            .as_ref()
+            .ok_or_else(|| invalid_argument("`client` is null"))?
+            .client
+            .clone();
+        let kind = TapoDeviceKind::try_from(kind)
+            .map_err(|kind| invalid_argument(format!("`kind` {kind} is unknown")))?;
+        let ip_address = read_str(ip_address, "ip_address")?;
+
+        let device = block_on(TapoDevice::connect(client, kind, ip_address))?;
+
+        write_out(out, Box::into_raw(Box::new(device)))
+    })
+}
+
+/// Releases a device. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `device` must have been returned by [`tapo_device_connect`] and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tapo_device_free(device: *mut TapoDevice) {
+    if !device.is_null() {
+        drop(Box::from_raw(device));
+    }
+}
+
+/// Turns the device on.
+///
+/// # Safety
+///
+/// `device` must have been returned by [`tapo_device_connect`].
+#[no_mangle]
+pub unsafe extern "C" fn tapo_device_on(device: *const TapoDevice) -> TapoStatus {
+    ffi_call(|| Ok(block_on(TapoDevice::from_ptr(device)?.on())?))
+}
+
+/// Turns the device off.
+///
+/// # Safety
+///
+/// `device` must have been returned by [`tapo_device_connect`].
+#[no_mangle]
+pub unsafe extern "C" fn tapo_device_off(device: *const TapoDevice) -> TapoStatus {
+    ffi_call(|| Ok(block_on(TapoDevice::from_ptr(device)?.off())?))
+}
+
+/// Sets the brightness of a light and turns it on.
+///
+/// # Safety
+///
+/// `device` must have been returned by [`tapo_device_connect`].
+#[no_mangle]
+pub unsafe extern "C" fn tapo_device_set_brightness(
+    device: *const TapoDevice,
+    brightness: u8,
+) -> TapoStatus {
+    ffi_call(|| {
+        Ok(block_on(
+            TapoDevice::from_ptr(device)?.set_brightness(brightness),
+        )?)
+    })
+}
+
+/// Sets the hue (1-360) and the saturation (1-100) of a color light and turns it on.
+///
+/// # Safety
+///
+/// `device` must have been returned by [`tapo_device_connect`].
+#[no_mangle]
+pub unsafe extern "C" fn tapo_device_set_hue_saturation(
+    device: *const TapoDevice,
+    hue: u16,
+    saturation: u8,
+) -> TapoStatus {
+    ffi_call(|| {
+        let device = TapoDevice::from_ptr(device)?;
+        Ok(block_on(device.set_hue_saturation(hue, saturation))?)
+    })
+}
+
+/// Sets the color temperature (2500-6500 K) of a color light and turns it on.
+///
+/// # Safety
+///
+/// `device` must have been returned by [`tapo_device_connect`].
+#[no_mangle]
+pub unsafe extern "C" fn tapo_device_set_color_temperature(
+    device: *const TapoDevice,
+    color_temperature: u16,
+) -> TapoStatus {
+    ffi_call(|| {
+        let device = TapoDevice::from_ptr(device)?;
+        Ok(block_on(device.set_color_temperature(color_temperature))?)
+    })
+}
+
+/// Writes the device info as a JSON string to `out`, to be released with [`tapo_string_free`](crate::tapo_string_free).
+///
+/// # Safety
+///
+/// `device` must have been returned by [`tapo_device_connect`] and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn tapo_device_get_device_info_json(
+    device: *const TapoDevice,
+    out: *mut *mut c_char,
+) -> TapoStatus {
+    ffi_call(|| {
+        let device_info = block_on(TapoDevice::from_ptr(device)?.get_device_info_json())?;
+        write_json(out, device_info)
+    })
+}
+
+/// Writes the current power of a plug with energy monitoring, in watts, to `out`.
+///
+/// # Safety
+///
+/// `device` must have been returned by [`tapo_device_connect`] and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn tapo_device_get_current_power(
+    device: *const TapoDevice,
+    out: *mut u64,
+) -> TapoStatus {
+    ffi_call(|| {
+        let current_power = block_on(TapoDevice::from_ptr(device)?.get_current_power())?;
+        write_out(out, current_power)
+    })
+}
+
+/// Writes the energy usage of a plug with energy monitoring as a JSON string to `out`,
+/// to be released with [`tapo_string_free`](crate::tapo_string_free).
+///
+/// # Safety
+///
+/// `device` must have been returned by [`tapo_device_connect`] and `out` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn tapo_device_get_energy_usage_json(
+    device: *const TapoDevice,
+    out: *mut *mut c_char,
+) -> TapoStatus {
+    ffi_call(|| {
+        let energy_usage = block_on(TapoDevice::from_ptr(device)?.get_energy_usage_json())?;
+        write_json(out, energy_usage)
+    })
+}
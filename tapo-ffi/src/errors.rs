@@ -0,0 +1,68 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::ptr;
+
+use tapo::Error;
+
+/// The result of every fallible function.
+/// The message of the last error is available through [`tapo_last_error_message`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapoStatus {
+    Ok = 0,
+    /// A pointer was null, a string wasn't valid UTF-8 or an enum value was unknown.
+    InvalidArgument = 1,
+    /// The device couldn't be reached.
+    Unreachable = 2,
+    /// The device rejected the request.
+    Device = 3,
+    /// A provided value is out of range.
+    Validation = 4,
+    /// The device, or its firmware version, doesn't support the operation.
+    NotSupported = 5,
+    /// Any other error.
+    Other = 6,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn set_last_error(message: impl Into<String>) {
+    let message =
+        CString::new(message.into().replace('\0', "")).expect("the nul bytes have been removed");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+pub(crate) fn invalid_argument(message: impl Into<String>) -> TapoStatus {
+    set_last_error(message);
+    TapoStatus::InvalidArgument
+}
+
+impl From<Error> for TapoStatus {
+    fn from(err: Error) -> Self {
+        set_last_error(err.to_string());
+
+        match err {
+            Error::Tapo(_) => TapoStatus::Device,
+            Error::Validation { .. } => TapoStatus::Validation,
+            Error::NotSupported { .. } | Error::UnsupportedFirmware { .. } => {
+                TapoStatus::NotSupported
+            }
+            Error::Unreachable(_) => TapoStatus::Unreachable,
+            _ => TapoStatus::Other,
+        }
+    }
+}
+
+/// Returns the message of the last error that occurred on the calling thread, or null if there was none.
+/// The string is owned by the library and stays valid until the next call on the same thread.
+#[no_mangle]
+pub extern "C" fn tapo_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
@@ -0,0 +1,155 @@
+//! C ABI for the `tapo` crate, see `include/tapo.h`.
+//!
+//! Every function is blocking and runs the underlying requests on a shared Tokio runtime.
+//! Clients and devices are opaque pointers that must be released with their `_free` function,
+//! and strings returned by the library must be released with [`tapo_string_free`].
+//!
+//! With the `uniffi` feature, the library also exposes async bindings from which
+//! Kotlin and Swift packages can be generated, see the `uniffi-bindgen` binary.
+
+mod client;
+mod device;
+mod errors;
+#[cfg(feature = "uniffi")]
+mod mobile;
+
+use std::ffi::{c_char, CStr, CString};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::OnceLock;
+
+use tokio::runtime::Runtime;
+
+pub use client::*;
+pub use device::*;
+pub use errors::*;
+#[cfg(feature = "uniffi")]
+pub use mobile::*;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+/// Releases a string returned by the library. Passing null is a no-op.
+///
+/// # Safety
+///
+/// `string` must have been returned by the library and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tapo_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .expect("failed to build the Tokio runtime")
+        })
+        .block_on(future)
+}
+
+/// Runs `f`, making sure that panics don't unwind into the caller.
+fn ffi_call(f: impl FnOnce() -> Result<(), TapoStatus>) -> TapoStatus {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => TapoStatus::Ok,
+        Ok(Err(status)) => status,
+        Err(_) => {
+            set_last_error("panicked");
+            TapoStatus::Other
+        }
+    }
+}
+
+/// Reads the nul-terminated UTF-8 string at `ptr`.
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a nul-terminated string.
+unsafe fn read_str(ptr: *const c_char, name: &str) -> Result<String, TapoStatus> {
+    if ptr.is_null() {
+        return Err(invalid_argument(format!("`{name}` is null")));
+    }
+
+    CStr::from_ptr(ptr)
+        .to_str()
+        .map(ToString::to_string)
+        .map_err(|_| invalid_argument(format!("`{name}` is not valid UTF-8")))
+}
+
+/// Writes `value` to `out`.
+///
+/// # Safety
+///
+/// `out` must be null or valid for writes.
+unsafe fn write_out<T>(out: *mut T, value: T) -> Result<(), TapoStatus> {
+    if out.is_null() {
+        return Err(invalid_argument("`out` is null"));
+    }
+
+    out.write(value);
+    Ok(())
+}
+
+/// Writes `value` to `out` as a newly allocated string, to be released with [`tapo_string_free`].
+///
+/// # Safety
+///
+/// `out` must be null or valid for writes.
+unsafe fn write_json(out: *mut *mut c_char, value: serde_json::Value) -> Result<(), TapoStatus> {
+    let json = CString::new(value.to_string())
+        .map_err(|_| invalid_argument("the JSON contains a nul byte"))?;
+
+    write_out(out, json.into_raw())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::*;
+
+    #[test]
+    fn null_arguments_are_rejected() {
+        let mut device = ptr::null_mut();
+
+        let status = unsafe {
+            tapo_device_connect(
+                ptr::null(),
+                TapoDeviceKind::Plug as u32,
+                ptr::null(),
+                &mut device,
+            )
+        };
+
+        assert_eq!(status, TapoStatus::InvalidArgument);
+        assert!(device.is_null());
+        let message = unsafe { CStr::from_ptr(tapo_last_error_message()) };
+        assert_eq!(message.to_str().unwrap(), "`client` is null");
+    }
+
+    #[test]
+    fn unknown_device_kind_is_rejected() {
+        let username = CString::new("username").unwrap();
+        let password = CString::new("password").unwrap();
+        let mut client = ptr::null_mut();
+        let status = unsafe { tapo_client_new(username.as_ptr(), password.as_ptr(), &mut client) };
+        assert_eq!(status, TapoStatus::Ok);
+
+        let ip_address = CString::new("192.168.1.100").unwrap();
+        let mut device = ptr::null_mut();
+        let status = unsafe { tapo_device_connect(client, 42, ip_address.as_ptr(), &mut device) };
+
+        assert_eq!(status, TapoStatus::InvalidArgument);
+        assert!(device.is_null());
+        let message = unsafe { CStr::from_ptr(tapo_last_error_message()) };
+        assert_eq!(message.to_str().unwrap(), "`kind` 42 is unknown");
+
+        unsafe { tapo_client_free(client) };
+    }
+}
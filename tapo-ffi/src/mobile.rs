@@ -0,0 +1,129 @@
+//! uniffi bindings, used for generating the Kotlin and Swift packages.
+//!
+//! The methods are async and map to suspending functions in Kotlin and to async functions in Swift.
+
+use std::fmt;
+use std::sync::Arc;
+
+use tapo::ApiClient;
+
+use crate::{TapoDevice, TapoDeviceKind};
+
+/// The errors raised by the uniffi bindings, mirroring [`crate::TapoStatus`].
+#[derive(Debug, uniffi::Error)]
+#[uniffi(flat_error)]
+pub enum TapoError {
+    /// The device couldn't be reached.
+    Unreachable(String),
+    /// The device rejected the request.
+    Device(String),
+    /// A provided value is out of range.
+    Validation(String),
+    /// The device, or its firmware version, doesn't support the operation.
+    NotSupported(String),
+    /// Any other error.
+    Other(String),
+}
+
+impl fmt::Display for TapoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreachable(message)
+            | Self::Device(message)
+            | Self::Validation(message)
+            | Self::NotSupported(message)
+            | Self::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl From<tapo::Error> for TapoError {
+    fn from(err: tapo::Error) -> Self {
+        let message = err.to_string();
+
+        match err {
+            tapo::Error::Tapo(_) => Self::Device(message),
+            tapo::Error::Validation { .. } => Self::Validation(message),
+            tapo::Error::NotSupported { .. } | tapo::Error::UnsupportedFirmware { .. } => {
+                Self::NotSupported(message)
+            }
+            tapo::Error::Unreachable(_) => Self::Unreachable(message),
+            _ => Self::Other(message),
+        }
+    }
+}
+
+/// Tapo API Client, see [`tapo::ApiClient`].
+#[derive(uniffi::Object)]
+pub struct Client {
+    client: ApiClient,
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl Client {
+    /// Returns a new client with the given Tapo credentials.
+    #[uniffi::constructor]
+    pub fn new(username: String, password: String) -> Result<Arc<Self>, TapoError> {
+        let client = ApiClient::new(username, password)?;
+        Ok(Arc::new(Self { client }))
+    }
+
+    /// Connects to the device at `ip_address`.
+    pub async fn connect(
+        &self,
+        kind: TapoDeviceKind,
+        ip_address: String,
+    ) -> Result<Arc<Device>, TapoError> {
+        let device = TapoDevice::connect(self.client.clone(), kind, ip_address).await?;
+        Ok(Arc::new(Device { device }))
+    }
+}
+
+/// A connected device, see [`Client::connect`].
+#[derive(uniffi::Object)]
+pub struct Device {
+    device: TapoDevice,
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+impl Device {
+    /// Turns the device on.
+    pub async fn on(&self) -> Result<(), TapoError> {
+        Ok(self.device.on().await?)
+    }
+
+    /// Turns the device off.
+    pub async fn off(&self) -> Result<(), TapoError> {
+        Ok(self.device.off().await?)
+    }
+
+    /// Sets the brightness of a light and turns it on.
+    pub async fn set_brightness(&self, brightness: u8) -> Result<(), TapoError> {
+        Ok(self.device.set_brightness(brightness).await?)
+    }
+
+    /// Sets the hue (1-360) and the saturation (1-100) of a color light and turns it on.
+    pub async fn set_hue_saturation(&self, hue: u16, saturation: u8) -> Result<(), TapoError> {
+        Ok(self.device.set_hue_saturation(hue, saturation).await?)
+    }
+
+    /// Sets the color temperature (2500-6500 K) of a color light and turns it on.
+    pub async fn set_color_temperature(&self, color_temperature: u16) -> Result<(), TapoError> {
+        Ok(self.device.set_color_temperature(color_temperature).await?)
+    }
+
+    /// Returns the device info as a JSON string.
+    pub async fn get_device_info_json(&self) -> Result<String, TapoError> {
+        Ok(self.device.get_device_info_json().await?.to_string())
+    }
+
+    /// Returns the current power of a plug with energy monitoring, in watts.
+    pub async fn get_current_power(&self) -> Result<u64, TapoError> {
+        Ok(self.device.get_current_power().await?)
+    }
+
+    /// Returns the energy usage of a plug with energy monitoring as a JSON string.
+    pub async fn get_energy_usage_json(&self) -> Result<String, TapoError> {
+        Ok(self.device.get_energy_usage_json().await?.to_string())
+    }
+}
@@ -1,3 +1,6 @@
+use std::time::Duration;
+
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use tapo::ApiClient;
 
@@ -14,11 +17,41 @@ pub struct PyApiClient {
 #[pymethods]
 impl PyApiClient {
     #[new]
-    pub fn new(tapo_username: String, tapo_password: String) -> Result<Self, ErrorWrapper> {
-        let client = ApiClient::new(tapo_username, tapo_password)?;
+    #[pyo3(signature = (tapo_username, tapo_password, timeout=None, max_retries=0, retry_delay=0.5))]
+    pub fn new(
+        tapo_username: String,
+        tapo_password: String,
+        timeout: Option<f64>,
+        max_retries: u32,
+        retry_delay: f64,
+    ) -> PyResult<Self> {
+        let mut builder = ApiClient::builder(tapo_username, tapo_password)
+            .max_retries(max_retries)
+            .retry_delay(to_duration(retry_delay)?);
+
+        if let Some(timeout) = timeout {
+            builder = builder.timeout(to_duration(timeout)?);
+        }
+
+        let client = builder.build().map_err(ErrorWrapper)?;
         Ok(Self { client })
     }
 
+    fn __aenter__<'a>(slf: PyRef<'a, Self>, py: Python<'a>) -> PyResult<&'a PyAny> {
+        let slf: Py<Self> = slf.into();
+        pyo3_asyncio::tokio::future_into_py(py, async move { Ok(slf) })
+    }
+
+    fn __aexit__<'a>(
+        &'a self,
+        py: Python<'a>,
+        _exc_type: &'a PyAny,
+        _exc_value: &'a PyAny,
+        _traceback: &'a PyAny,
+    ) -> PyResult<&'a PyAny> {
+        pyo3_asyncio::tokio::future_into_py(py, async move { Ok(false) })
+    }
+
     pub fn generic_device<'a>(&'a self, ip_address: String, py: Python<'a>) -> PyResult<&'a PyAny> {
         let client = self.client.clone();
         pyo3_asyncio::tokio::future_into_py(py, async move {
@@ -86,3 +119,8 @@ impl PyApiClient {
         })
     }
 }
+
+fn to_duration(seconds: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(seconds)
+        .map_err(|_| PyValueError::new_err(format!("{seconds} is not a valid number of seconds")))
+}
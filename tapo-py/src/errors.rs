@@ -1,6 +1,37 @@
 use pyo3::exceptions::PyException;
-use pyo3::PyErr;
-use tapo::Error;
+use pyo3::{create_exception, PyErr, Python};
+use tapo::{Error, TapoResponseError};
+
+create_exception!(
+    tapo,
+    TapoError,
+    PyException,
+    "Base class of all the errors raised by the tapo library."
+);
+create_exception!(
+    tapo,
+    TapoAuthError,
+    TapoError,
+    "The Tapo credentials were rejected by the device."
+);
+create_exception!(
+    tapo,
+    TapoDeviceUnreachable,
+    TapoError,
+    "The device couldn't be reached, e.g. because it's turned off or has been assigned a new IP address."
+);
+create_exception!(
+    tapo,
+    TapoValidationError,
+    TapoError,
+    "A provided value is invalid, e.g. a brightness out of range."
+);
+create_exception!(
+    tapo,
+    TapoNotSupportedError,
+    TapoError,
+    "The device doesn't support the requested functionality."
+);
 
 pub struct ErrorWrapper(pub Error);
 
@@ -12,6 +43,27 @@ impl From<Error> for ErrorWrapper {
 
 impl std::convert::From<ErrorWrapper> for PyErr {
     fn from(err: ErrorWrapper) -> PyErr {
-        PyException::new_err(format!("{:?}", err.0))
+        let message = format!("{:?}", err.0);
+        let code = match &err.0 {
+            Error::Tapo(tapo_error) => tapo_error.code(),
+            _ => None,
+        };
+
+        let py_err = match err.0 {
+            Error::Tapo(TapoResponseError::InvalidCredentials) => TapoAuthError::new_err(message),
+            Error::Unreachable(_) => TapoDeviceUnreachable::new_err(message),
+            Error::Validation { .. } => TapoValidationError::new_err(message),
+            Error::NotSupported { .. } | Error::UnsupportedFirmware { .. } => {
+                TapoNotSupportedError::new_err(message)
+            }
+            _ => TapoError::new_err(message),
+        };
+
+        Python::with_gil(|py| {
+            // The error code sent by the device, if any.
+            let _ = py_err.value(py).setattr("code", code);
+        });
+
+        py_err
     }
 }
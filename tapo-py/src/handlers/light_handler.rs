@@ -92,6 +92,19 @@ impl PyLightHandler {
         })
     }
 
+    pub fn reset_usage_counters<'a>(&'a self, py: Python<'a>) -> PyResult<&'a PyAny> {
+        let handler = self.handler.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            handler
+                .lock()
+                .await
+                .reset_usage_counters()
+                .await
+                .map_err(ErrorWrapper)?;
+            Ok(())
+        })
+    }
+
     pub fn set_brightness<'a>(&'a self, py: Python<'a>, brightness: u8) -> PyResult<&'a PyAny> {
         let handler = self.handler.clone();
         pyo3_asyncio::tokio::future_into_py(py, async move {
@@ -102,6 +102,49 @@ impl PyPlugEnergyMonitoringHandler {
         })
     }
 
+    pub fn reset_usage_counters<'a>(&'a self, py: Python<'a>) -> PyResult<&'a PyAny> {
+        let handler = self.handler.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            handler
+                .lock()
+                .await
+                .reset_usage_counters()
+                .await
+                .map_err(ErrorWrapper)?;
+            Ok(())
+        })
+    }
+
+    pub fn get_child_protection<'a>(&'a self, py: Python<'a>) -> PyResult<&'a PyAny> {
+        let handler = self.handler.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let result = handler
+                .lock()
+                .await
+                .get_child_protection()
+                .await
+                .map_err(ErrorWrapper)?;
+            Ok(result)
+        })
+    }
+
+    pub fn set_child_protection<'a>(
+        &'a self,
+        enabled: bool,
+        py: Python<'a>,
+    ) -> PyResult<&'a PyAny> {
+        let handler = self.handler.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            handler
+                .lock()
+                .await
+                .set_child_protection(enabled)
+                .await
+                .map_err(ErrorWrapper)?;
+            Ok(())
+        })
+    }
+
     pub fn get_current_power<'a>(&'a self, py: Python<'a>) -> PyResult<&'a PyAny> {
         let handler = self.handler.clone();
         pyo3_asyncio::tokio::future_into_py(py, async move {
@@ -115,6 +158,19 @@ impl PyPlugEnergyMonitoringHandler {
         })
     }
 
+    pub fn get_emeter_data<'a>(&'a self, py: Python<'a>) -> PyResult<&'a PyAny> {
+        let handler = self.handler.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let result = handler
+                .lock()
+                .await
+                .get_emeter_data()
+                .await
+                .map_err(ErrorWrapper)?;
+            Ok(result)
+        })
+    }
+
     pub fn get_energy_usage<'a>(&'a self, py: Python<'a>) -> PyResult<&'a PyAny> {
         let handler = self.handler.clone();
         pyo3_asyncio::tokio::future_into_py(py, async move {
@@ -5,13 +5,62 @@ mod handlers;
 use pyo3::prelude::*;
 
 use api_client::PyApiClient;
-use handlers::{PyEnergyDataInterval, PyPlugEnergyMonitoringHandler};
+use errors::{
+    TapoAuthError, TapoDeviceUnreachable, TapoError, TapoNotSupportedError, TapoValidationError,
+};
+use handlers::{
+    PyEnergyDataInterval, PyGenericDeviceHandler, PyLightHandler, PyPlugEnergyMonitoringHandler,
+    PyPlugHandler,
+};
+use tapo::responses::{
+    CurrentPowerResult, DefaultBrightnessState, DefaultLightState, DefaultPlugState,
+    DeviceInfoGenericResult, DeviceInfoLightResult, DeviceInfoPlugResult,
+    DeviceUsageEnergyMonitoringResult, DeviceUsageResult, EmeterDataResult, EnergyDataResult,
+    EnergyUsageResult, PlugState, PyDefaultPowerType, PyDefaultStateType, UsageByPeriodResult,
+};
 
 #[pymodule]
 #[pyo3(name = "tapo")]
-fn tapo_py(_py: Python, m: &PyModule) -> PyResult<()> {
+fn tapo_py(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<PyApiClient>()?;
+
+    // errors
+    m.add("TapoError", py.get_type::<TapoError>())?;
+    m.add("TapoAuthError", py.get_type::<TapoAuthError>())?;
+    m.add(
+        "TapoDeviceUnreachable",
+        py.get_type::<TapoDeviceUnreachable>(),
+    )?;
+    m.add("TapoValidationError", py.get_type::<TapoValidationError>())?;
+    m.add(
+        "TapoNotSupportedError",
+        py.get_type::<TapoNotSupportedError>(),
+    )?;
+
+    // handlers
+    m.add_class::<PyGenericDeviceHandler>()?;
+    m.add_class::<PyLightHandler>()?;
+    m.add_class::<PyPlugHandler>()?;
     m.add_class::<PyPlugEnergyMonitoringHandler>()?;
     m.add_class::<PyEnergyDataInterval>()?;
+
+    // responses
+    m.add_class::<CurrentPowerResult>()?;
+    m.add_class::<DefaultBrightnessState>()?;
+    m.add_class::<DefaultLightState>()?;
+    m.add_class::<DefaultPlugState>()?;
+    m.add_class::<PyDefaultPowerType>()?;
+    m.add_class::<PyDefaultStateType>()?;
+    m.add_class::<DeviceInfoGenericResult>()?;
+    m.add_class::<DeviceInfoLightResult>()?;
+    m.add_class::<DeviceInfoPlugResult>()?;
+    m.add_class::<DeviceUsageEnergyMonitoringResult>()?;
+    m.add_class::<DeviceUsageResult>()?;
+    m.add_class::<EmeterDataResult>()?;
+    m.add_class::<EnergyDataResult>()?;
+    m.add_class::<EnergyUsageResult>()?;
+    m.add_class::<PlugState>()?;
+    m.add_class::<UsageByPeriodResult>()?;
+
     Ok(())
 }
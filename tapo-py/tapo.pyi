@@ -23,7 +23,26 @@ See [more examples](https://github.com/mihai-dinculescu/tapo/tree/main/tapo-py/e
 
 from datetime import datetime
 from enum import StrEnum
-from typing import Optional, List
+from types import TracebackType
+from typing import Optional, List, Type
+
+class TapoError(Exception):
+    """Base class of all the errors raised by the tapo library."""
+
+    code: Optional[int]
+    """The error code sent by the device, if any."""
+
+class TapoAuthError(TapoError):
+    """The Tapo credentials were rejected by the device."""
+
+class TapoDeviceUnreachable(TapoError):
+    """The device couldn't be reached, e.g. because it's turned off or has been assigned a new IP address."""
+
+class TapoValidationError(TapoError):
+    """A provided value is invalid, e.g. a brightness out of range."""
+
+class TapoNotSupportedError(TapoError):
+    """The device doesn't support the requested functionality."""
 
 class ApiClient:
     """Tapo API Client.
@@ -49,12 +68,23 @@ class ApiClient:
     See [more examples](https://github.com/mihai-dinculescu/tapo/tree/main/tapo-py/examples).
     """
 
-    def __init__(self, tapo_username: str, tapo_password: str) -> None:
+    def __init__(
+        self,
+        tapo_username: str,
+        tapo_password: str,
+        timeout: Optional[float] = None,
+        max_retries: int = 0,
+        retry_delay: float = 0.5,
+    ) -> None:
         """Returns a new instance of `ApiClient`.
 
         Args:
             tapo_username (str): The Tapo username
             tapo_password (str): The Tapo password
+            timeout (Optional[float]): The maximum duration of a request in seconds, after which
+                it fails with `TapoDeviceUnreachable`. Defaults to no timeout.
+            max_retries (int): How many times a request is retried when the device is unreachable.
+            retry_delay (float): The delay before the first retry in seconds, which doubles with every retry up to 30 seconds.
 
         Returns:
             ApiClient: Tapo API Client.
@@ -77,6 +107,24 @@ class ApiClient:
 
         See [more examples](https://github.com/mihai-dinculescu/tapo/tree/main/tapo-py/examples).
         """
+    async def __aenter__(self) -> ApiClient:
+        """Allows the client to be used as an asynchronous context manager.
+
+        Example:
+            ```python
+            async with ApiClient("tapo-username@example.com", "tapo-password") as client:
+                device = await client.l530("192.168.1.100")
+
+                await device.on()
+            ```
+        """
+    async def __aexit__(
+        self,
+        exc_type: Optional[Type[BaseException]],
+        exc_value: Optional[BaseException],
+        traceback: Optional[TracebackType],
+    ) -> bool:
+        """Exits the context manager. Exceptions raised within the block are not suppressed."""
     async def generic_device(self, ip_address: str) -> GenericDeviceHandler:
         """Specializes the given `ApiClient` into an authenticated `GenericDeviceHandler`.
 
@@ -296,6 +344,10 @@ class LightHandler:
         Returns:
             DeviceUsageResult: Contains the time usage.
         """
+    async def reset_usage_counters(self) -> None:
+        """Resets the *device usage* counters, e.g. after replacing a bulb or a filter when tracking its lifetime.
+        Raises `TapoNotSupportedError` if the firmware of the device doesn't implement it.
+        """
     async def set_brightness(self, brightness: int) -> None:
         """Sets the *brightness* and turns *on* the device.
 
@@ -341,6 +393,24 @@ class PlugHandler:
         Returns:
             DeviceUsageResult: Contains the time usage.
         """
+    async def reset_usage_counters(self) -> None:
+        """Resets the *device usage* counters, e.g. after replacing a bulb or a filter when tracking its lifetime.
+        Raises `TapoNotSupportedError` if the firmware of the device doesn't implement it.
+        """
+    async def get_child_protection(self) -> bool:
+        """Returns `True` if *child protection* is enabled, i.e. the physical button of the device is disabled.
+        Raises `TapoNotSupportedError` if the device doesn't implement it.
+
+        Returns:
+            bool: Whether child protection is enabled.
+        """
+    async def set_child_protection(self, enabled: bool) -> None:
+        """Enables or disables *child protection*. While enabled, the physical button of the device doesn't turn it on or off.
+        Raises `TapoNotSupportedError` if the device doesn't implement it.
+
+        Args:
+            enabled (bool): Whether to disable the physical button.
+        """
 
 class PlugEnergyMonitoringHandler:
     """Handler for the [P110](https://www.tapo.com/en/search/?q=P110)
@@ -381,12 +451,37 @@ class PlugEnergyMonitoringHandler:
             DeviceUsageEnergyMonitoringResult:
             Contains the time usage, the power consumption, and the energy savings of the device.
         """
+    async def reset_usage_counters(self) -> None:
+        """Resets the *device usage* counters, e.g. after replacing a bulb or a filter when tracking its lifetime.
+        Raises `TapoNotSupportedError` if the firmware of the device doesn't implement it.
+        """
+    async def get_child_protection(self) -> bool:
+        """Returns `True` if *child protection* is enabled, i.e. the physical button of the device is disabled.
+        Raises `TapoNotSupportedError` if the device doesn't implement it.
+
+        Returns:
+            bool: Whether child protection is enabled.
+        """
+    async def set_child_protection(self, enabled: bool) -> None:
+        """Enables or disables *child protection*. While enabled, the physical button of the device doesn't turn it on or off.
+        Raises `TapoNotSupportedError` if the device doesn't implement it.
+
+        Args:
+            enabled (bool): Whether to disable the physical button.
+        """
     async def get_current_power(self) -> CurrentPowerResult:
         """Returns *current power* as `CurrentPowerResult`.
 
         Returns:
             CurrentPowerResult: Contains the current power reading of the device.
         """
+    async def get_emeter_data(self) -> EmeterDataResult:
+        """Returns the *voltage*, *current* and *power* readings as `EmeterDataResult`.
+        Raises `TapoNotSupportedError` if the firmware of the device doesn't report them.
+
+        Returns:
+            EmeterDataResult: Contains the electrical readings of the device.
+        """
     async def get_energy_usage(self) -> EnergyUsageResult:
         """Returns *energy usage* as `EnergyUsageResult`.
 
@@ -407,13 +502,13 @@ class PlugEnergyMonitoringHandler:
         """
 
 class DefaultStateType(StrEnum):
-    """The type of the default state."""
+    """The type of the default state. The values that aren't known by this library are returned as `str`."""
 
     Custom = "custom"
     LastStates = "last_states"
 
 class DefaultPowerType(StrEnum):
-    """The type of the default power state."""
+    """The type of the default power state. The values that aren't known by this library are returned as `str`."""
 
     AlwaysOn = "always_on"
     LastStates = "last_states"
@@ -421,9 +516,16 @@ class DefaultPowerType(StrEnum):
 class DefaultBrightnessState:
     """Default brightness state."""
 
-    type: DefaultStateType
+    type: DefaultStateType | str
     value: int
 
+    def to_dict(self) -> dict:
+        """Gets all the properties of this result as a dictionary.
+
+        Returns:
+            dict: The result as a dictionary.
+        """
+
 class DeviceInfoGenericResult:
     """Device info of a Generic Tapo device."""
 
@@ -512,7 +614,14 @@ class DefaultLightState:
     """Light Default State."""
 
     brightness: DefaultBrightnessState
-    re_power_type: DefaultPowerType
+    re_power_type: DefaultPowerType | str
+
+    def to_dict(self) -> dict:
+        """Gets all the properties of this result as a dictionary.
+
+        Returns:
+            dict: The result as a dictionary.
+        """
 
 class DeviceInfoPlugResult:
     """Device info of Tapo P100, P105, P110 and P115. Superset of `GenericDeviceInfoResult`."""
@@ -545,8 +654,12 @@ class DeviceInfoPlugResult:
     time_diff: Optional[int]
 
     # Unique to this device
-    default_states: PlugDefaultState
+    default_states: DefaultPlugState
     """The default state of a device to be used when internet connectivity is lost after a power cut."""
+    child_protection_on: Optional[bool]
+    """Whether the physical button of the device is disabled.
+    Only reported by the firmware of some devices, see `get_child_protection` otherwise.
+    """
 
     def to_dict(self) -> dict:
         """Gets all the properties of this result as a dictionary.
@@ -555,17 +668,31 @@ class DeviceInfoPlugResult:
             dict: The result as a dictionary.
         """
 
-class PlugDefaultState:
+class DefaultPlugState:
     """Plug Default State."""
 
-    type: DefaultStateType
+    type: DefaultStateType | str
     state: PlugState
 
+    def to_dict(self) -> dict:
+        """Gets all the properties of this result as a dictionary.
+
+        Returns:
+            dict: The result as a dictionary.
+        """
+
 class PlugState:
     """Plug State."""
 
     on: Optional[bool]
 
+    def to_dict(self) -> dict:
+        """Gets all the properties of this result as a dictionary.
+
+        Returns:
+            dict: The result as a dictionary.
+        """
+
 class UsageByPeriodResult:
     """Usage by period result for today, the past 7 days, and the past 30 days."""
 
@@ -576,6 +703,13 @@ class UsageByPeriodResult:
     past30: int
     """Past 30 days."""
 
+    def to_dict(self) -> dict:
+        """Gets all the properties of this result as a dictionary.
+
+        Returns:
+            dict: The result as a dictionary.
+        """
+
 class DeviceUsageResult:
     """Contains the time in use, the power consumption, and the energy savings of the device."""
 
@@ -623,6 +757,31 @@ class CurrentPowerResult:
             dict: The result as a dictionary.
         """
 
+class EmeterDataResult:
+    """Contains the electrical readings of the device.
+    Each reading is only present when the firmware of the device reports it.
+    """
+
+    power_mw: Optional[int]
+    """Current power in milliwatts (mW)."""
+    voltage_mv: Optional[int]
+    """Voltage in millivolts (mV)."""
+    current_ma: Optional[int]
+    """Current in milliamperes (mA)."""
+    energy_wh: Optional[int]
+    """Total energy usage in watt-hours (Wh)."""
+
+    def power_factor(self) -> Optional[float]:
+        """Returns the power factor, i.e. the ratio between the real power and the apparent power,
+        if the power, the voltage and the current are all reported.
+        """
+    def to_dict(self) -> dict:
+        """Gets all the properties of this result as a dictionary.
+
+        Returns:
+            dict: The result as a dictionary.
+        """
+
 class EnergyUsageResult:
     """Contains local time, current power and the energy usage and runtime for today and for the current month."""
 
@@ -0,0 +1,16 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+/// Generates the gRPC service of the `grpc` feature, with a vendored `protoc` so that none needs to be installed.
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    let protoc =
+        protoc_bin_vendored::protoc_bin_path().expect("protoc is vendored for this platform");
+    std::env::set_var("PROTOC", protoc);
+
+    tonic_build::configure()
+        .compile_protos(&["proto/tapo.proto"], &["proto"])
+        .expect("proto/tapo.proto is valid");
+}
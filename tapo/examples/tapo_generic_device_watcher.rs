@@ -0,0 +1,33 @@
+/// Watch Generic Device Example
+use std::{env, time::Duration};
+
+use log::{info, LevelFilter};
+use tapo::watcher::DeviceWatcher;
+use tapo::ApiClient;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let log_level = env::var("RUST_LOG")
+        .unwrap_or_else(|_| "info".to_string())
+        .parse()
+        .unwrap_or(LevelFilter::Info);
+
+    pretty_env_logger::formatted_timed_builder()
+        .filter(Some("tapo"), log_level)
+        .init();
+
+    let tapo_username = env::var("TAPO_USERNAME")?;
+    let tapo_password = env::var("TAPO_PASSWORD")?;
+    let ip_address = env::var("IP_ADDRESS")?;
+
+    let device = ApiClient::new(tapo_username, tapo_password)?
+        .generic_device(ip_address)
+        .await?;
+
+    info!("Watching the device for changes...");
+    DeviceWatcher::new(device, Duration::from_secs(5))
+        .run(|change| info!("Change: {change:?}"))
+        .await;
+
+    Ok(())
+}
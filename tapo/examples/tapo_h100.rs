@@ -33,13 +33,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         match child {
             ChildDeviceResult::KE100(device) => {
                 info!(
-                    "Found KE100 child device with nickname: {}, id: {}, current temperature: {} {:?} and target temperature: {} {:?}.",
+                    "Found KE100 child device with nickname: {}, id: {}, current temperature: {} and target temperature: {}.",
                     device.nickname,
                     device.device_id,
                     device.current_temperature,
-                    device.temperature_unit,
                     device.target_temperature,
-                    device.temperature_unit,
                 );
             }
             ChildDeviceResult::S200B(device) => {
@@ -87,11 +85,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 let temperature_humidity_records = t31x.get_temperature_humidity_records().await?;
 
                 info!(
-                    "Found T31X child device with nickname: {}, id: {}, temperature: {} {:?}, humidity: {}%, 24-hour ago record: {:?}.",
+                    "Found T31X child device with nickname: {}, id: {}, temperature: {}, humidity: {}%, 24-hour ago record: {:?}.",
                     device.nickname,
                     device.device_id,
                     device.current_temperature,
-                    device.temperature_unit,
                     device.current_humidity,
                     temperature_humidity_records.records.first()
                 );
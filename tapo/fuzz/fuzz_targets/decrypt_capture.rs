@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tapo::protocol::decrypt_capture;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decrypt_capture(data, "username", "password");
+});
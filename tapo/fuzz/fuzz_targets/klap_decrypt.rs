@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tapo::protocol::codec::{klap_auth_hash, KlapCodec};
+
+fuzz_target!(|data: &[u8]| {
+    let Some((seq, payload)) = data.split_first_chunk::<4>() else {
+        return;
+    };
+
+    let auth_hash = klap_auth_hash("username", "password");
+    let codec = KlapCodec::new(&[1; 16], &[2; 16], &auth_hash);
+
+    let _ = codec.decrypt(i32::from_be_bytes(*seq), payload);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tapo::protocol::codec::parse_response;
+
+fuzz_target!(|data: &str| {
+    let _ = parse_response(data);
+});
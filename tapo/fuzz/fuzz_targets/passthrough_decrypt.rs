@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tapo::protocol::codec::PassthroughCodec;
+
+fuzz_target!(|data: &str| {
+    let codec = PassthroughCodec::new(&[7; 32]).expect("the session key is 32 bytes long");
+
+    let _ = codec.decrypt(data);
+});
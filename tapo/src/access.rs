@@ -0,0 +1,179 @@
+//! Access tokens and roles for the servers that expose the devices, e.g. [`crate::server::RestServer`]
+//! and [`crate::grpc::DeviceManagerService`].
+//!
+//! An [`AccessPolicy`] grants each token a [`Role`]: [`Role::Read`] for reading the states of the devices,
+//! e.g. for a wall-mounted dashboard, and [`Role::Control`] for changing them as well.
+//! The requests carry the token as a bearer token, i.e. an `Authorization: Bearer <token>` header or metadata.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use tapo::access::{AccessPolicy, Role};
+//! let policy = AccessPolicy::new()
+//!     .token("dashboard-token", Role::Read)
+//!     .token("automation-token", Role::Control);
+//!
+//! assert!(policy.authorize_bearer(Some("Bearer dashboard-token"), Role::Read).is_ok());
+//! assert!(policy.authorize_bearer(Some("Bearer dashboard-token"), Role::Control).is_err());
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::hash::BuildHasher;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// What the holder of a token is allowed to do. [`Role::Control`] includes [`Role::Read`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Reading the devices and their states.
+    Read,
+    /// Reading and changing the states of the devices, e.g. turning them on and off.
+    Control,
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read => write!(f, "read"),
+            Self::Control => write!(f, "control"),
+        }
+    }
+}
+
+/// The [`Role`]s granted to the access tokens.
+///
+/// Deserializes from a table of roles by token, e.g. `{ "dashboard-token": "read" }`.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AccessPolicy {
+    tokens: BTreeMap<String, Role>,
+}
+
+impl std::fmt::Debug for AccessPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AccessPolicy")
+            .field("roles", &self.tokens.values().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl AccessPolicy {
+    /// Returns an [`AccessPolicy`] without tokens, which rejects every request.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Grants `role` to `token`, replacing the role it was granted before.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - the access token, a secret shared with the client
+    /// * `role` - the role granted to the holders of the token
+    pub fn token(mut self, token: impl Into<String>, role: Role) -> Self {
+        self.tokens.insert(token.into(), role);
+        self
+    }
+
+    /// Returns the role granted to `token`, if any.
+    ///
+    /// The tokens are compared by their hashes, keyed anew for every call, and all of them are checked,
+    /// so that the time it takes doesn't tell how much of a guessed token is right.
+    pub fn role_of(&self, token: &str) -> Option<Role> {
+        let state = RandomState::new();
+        let hash = state.hash_one(token);
+
+        self.tokens.iter().fold(None, |granted, (candidate, role)| {
+            if state.hash_one(candidate) == hash && candidate == token {
+                Some(*role)
+            } else {
+                granted
+            }
+        })
+    }
+
+    /// Returns the role granted to `token` if it includes `required`,
+    /// [`Error::Unauthenticated`] if the token is missing or unknown,
+    /// and [`Error::Forbidden`] if its role doesn't include `required`.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - the access token of the request
+    /// * `required` - the role required by the request
+    pub fn authorize(&self, token: Option<&str>, required: Role) -> Result<Role, Error> {
+        let granted = token
+            .and_then(|token| self.role_of(token))
+            .ok_or(Error::Unauthenticated)?;
+
+        if granted < required {
+            return Err(Error::Forbidden { required, granted });
+        }
+
+        Ok(granted)
+    }
+
+    /// Like [`AccessPolicy::authorize`], for the value of an `Authorization: Bearer <token>` header.
+    ///
+    /// # Arguments
+    ///
+    /// * `authorization` - the value of the `Authorization` header of the request
+    /// * `required` - the role required by the request
+    pub fn authorize_bearer(
+        &self,
+        authorization: Option<&str>,
+        required: Role,
+    ) -> Result<Role, Error> {
+        let token = authorization.and_then(|authorization| {
+            let (scheme, token) = authorization.split_once(' ')?;
+            scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+        });
+
+        self.authorize(token, required)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_are_enforced() {
+        let policy: AccessPolicy = serde_json::from_value(serde_json::json!({
+            "dashboard-token": "read",
+            "automation-token": "control",
+        }))
+        .unwrap();
+
+        assert_eq!(
+            policy
+                .authorize(Some("automation-token"), Role::Read)
+                .unwrap(),
+            Role::Control
+        );
+        assert_eq!(
+            policy
+                .authorize_bearer(Some("bearer dashboard-token"), Role::Read)
+                .unwrap(),
+            Role::Read
+        );
+        assert!(matches!(
+            policy.authorize(Some("dashboard-token"), Role::Control),
+            Err(Error::Forbidden {
+                required: Role::Control,
+                granted: Role::Read
+            })
+        ));
+        assert!(matches!(
+            policy.authorize_bearer(Some("Basic dashboard-token"), Role::Read),
+            Err(Error::Unauthenticated)
+        ));
+        assert!(matches!(
+            policy.authorize(None, Role::Read),
+            Err(Error::Unauthenticated)
+        ));
+        assert!(!format!("{policy:?}").contains("token"));
+    }
+}
@@ -0,0 +1,371 @@
+//! Downsampling of sensor and energy series into hourly or daily aggregates.
+//!
+//! The time series exposed by the responses, such as [`crate::responses::TemperatureHumidityRecords::temperature_series`]
+//! or [`crate::responses::EnergyDataResult::series`], are lists of `(timestamp, value)` samples
+//! which can be reduced with [`aggregate`].
+//!
+//! The series have holes where a device was offline. [`fill_gaps`] fills them in with a [`GapFill`] strategy
+//! and reports each of them as a [`Gap`], so that the filled values can be told apart from the measured ones.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use chrono::{FixedOffset, TimeZone, Utc};
+//! # use tapo::aggregation::{aggregate, Resolution};
+//! let samples = vec![
+//!     (Utc.with_ymd_and_hms(2024, 1, 1, 21, 30, 0).unwrap(), 19.5),
+//!     (Utc.with_ymd_and_hms(2024, 1, 1, 22, 45, 0).unwrap(), 20.5),
+//!     (Utc.with_ymd_and_hms(2024, 1, 1, 23, 15, 0).unwrap(), 21.0),
+//! ];
+//! let timezone = FixedOffset::east_opt(2 * 3600).unwrap();
+//!
+//! let daily = aggregate(samples, Resolution::Day, &timezone);
+//!
+//! // 22:00 UTC is already the next day at UTC+2.
+//! assert_eq!(daily.len(), 2);
+//! assert_eq!(daily[1].avg, 20.75);
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, TimeZone, Timelike, Utc};
+
+/// The length of the buckets that samples are grouped into by [`aggregate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// One bucket per hour, starting at the full hour.
+    Hour,
+    /// One bucket per calendar day, starting at midnight.
+    Day,
+}
+
+/// Minimum, maximum and average of the samples within one bucket.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aggregate<Tz: TimeZone> {
+    /// The start of the bucket in the time zone passed to [`aggregate`].
+    pub start: DateTime<Tz>,
+    /// The smallest value of the bucket.
+    pub min: f64,
+    /// The largest value of the bucket.
+    pub max: f64,
+    /// The arithmetic mean of the values of the bucket.
+    pub avg: f64,
+    /// The number of samples in the bucket.
+    pub count: usize,
+}
+
+/// Groups `samples` into buckets of the given `resolution` and returns the aggregate of each non-empty bucket,
+/// in chronological order.
+///
+/// The buckets are aligned to the hours and days of `timezone`, including its daylight saving time transitions:
+/// a day on which the clocks change lasts 23 or 25 hours, and the repeated hour of the fall transition
+/// is kept as a separate bucket.
+///
+/// # Arguments
+///
+/// * `samples` - `(timestamp, value)` pairs, in any order
+/// * `resolution` - the length of the buckets
+/// * `timezone` - the time zone that the buckets are aligned to
+pub fn aggregate<Tz>(
+    samples: impl IntoIterator<Item = (DateTime<Utc>, f64)>,
+    resolution: Resolution,
+    timezone: &Tz,
+) -> Vec<Aggregate<Tz>>
+where
+    Tz: TimeZone,
+{
+    let mut buckets: BTreeMap<DateTime<Tz>, Aggregate<Tz>> = BTreeMap::new();
+
+    for (timestamp, value) in samples {
+        let start = bucket_start(timestamp.with_timezone(timezone), resolution);
+
+        buckets
+            .entry(start.clone())
+            .and_modify(|bucket| {
+                bucket.min = bucket.min.min(value);
+                bucket.max = bucket.max.max(value);
+                bucket.avg += value;
+                bucket.count += 1;
+            })
+            .or_insert(Aggregate {
+                start,
+                min: value,
+                max: value,
+                avg: value,
+                count: 1,
+            });
+    }
+
+    buckets
+        .into_values()
+        .map(|mut bucket| {
+            bucket.avg /= bucket.count as f64;
+            bucket
+        })
+        .collect()
+}
+
+/// How [`fill_gaps`] fills in the missing samples of a series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GapFill {
+    /// Missing samples are `0.0`, e.g. for the energy of a device that was off.
+    Zero,
+    /// Missing samples are [`f64::NAN`], so that they can be told apart from the measured ones.
+    Nan,
+    /// Missing samples are interpolated linearly between the samples around the gap.
+    Linear,
+    /// Missing samples repeat the last sample before the gap.
+    CarryForward,
+}
+
+/// A run of missing samples in a series, found by [`fill_gaps`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gap {
+    /// The timestamp of the first missing sample.
+    pub start: DateTime<Utc>,
+    /// The timestamp of the last missing sample.
+    pub end: DateTime<Utc>,
+    /// The number of missing samples.
+    pub missing: usize,
+}
+
+/// A series with its gaps filled in by [`fill_gaps`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilledSeries {
+    /// The measured and the filled in `(timestamp, value)` samples, in chronological order.
+    pub samples: Vec<(DateTime<Utc>, f64)>,
+    /// The gaps that have been filled in, in chronological order.
+    pub gaps: Vec<Gap>,
+}
+
+/// Fills in the samples missing from a series with one sample every `interval`, e.g. the hourly
+/// [`crate::responses::EnergyDataResult::series`] of a device that was offline for a few hours.
+///
+/// A gap is found wherever two consecutive samples are at least two intervals apart, and is filled in
+/// with one sample every `interval` after the sample before it.
+///
+/// # Arguments
+///
+/// * `samples` - `(timestamp, value)` pairs, in any order
+/// * `interval` - the time between two consecutive samples of the series
+/// * `fill` - how the missing samples are filled in
+///
+/// # Example
+///
+/// ```rust
+/// # use chrono::{Duration, TimeZone, Utc};
+/// # use tapo::aggregation::{fill_gaps, GapFill};
+/// let samples = vec![
+///     (Utc.with_ymd_and_hms(2024, 1, 1, 10, 0, 0).unwrap(), 10.0),
+///     (Utc.with_ymd_and_hms(2024, 1, 1, 13, 0, 0).unwrap(), 40.0),
+/// ];
+///
+/// let filled = fill_gaps(samples, Duration::hours(1), GapFill::Linear);
+///
+/// assert_eq!(filled.samples[1].1, 20.0);
+/// assert_eq!(filled.samples[2].1, 30.0);
+/// assert_eq!(filled.gaps[0].missing, 2);
+/// ```
+pub fn fill_gaps(
+    samples: impl IntoIterator<Item = (DateTime<Utc>, f64)>,
+    interval: Duration,
+    fill: GapFill,
+) -> FilledSeries {
+    let mut samples: Vec<_> = samples.into_iter().collect();
+    samples.sort_by_key(|(timestamp, _)| *timestamp);
+
+    let mut filled = FilledSeries {
+        samples: Vec::with_capacity(samples.len()),
+        gaps: Vec::new(),
+    };
+    if interval <= Duration::zero() {
+        filled.samples = samples;
+        return filled;
+    }
+
+    let mut previous: Option<(DateTime<Utc>, f64)> = None;
+
+    for (timestamp, value) in samples {
+        if let Some((previous_timestamp, previous_value)) = previous {
+            let elapsed = timestamp - previous_timestamp;
+            let missing = (elapsed.num_milliseconds() / interval.num_milliseconds() - 1).max(0);
+
+            for index in 1..=missing {
+                let missing_timestamp = previous_timestamp + interval * index as i32;
+                let missing_value = match fill {
+                    GapFill::Zero => 0.0,
+                    GapFill::Nan => f64::NAN,
+                    GapFill::Linear => {
+                        let progress = (missing_timestamp - previous_timestamp).num_milliseconds()
+                            as f64
+                            / elapsed.num_milliseconds() as f64;
+                        previous_value + (value - previous_value) * progress
+                    }
+                    GapFill::CarryForward => previous_value,
+                };
+                filled.samples.push((missing_timestamp, missing_value));
+            }
+
+            if missing > 0 {
+                filled.gaps.push(Gap {
+                    start: previous_timestamp + interval,
+                    end: previous_timestamp + interval * missing as i32,
+                    missing: missing as usize,
+                });
+            }
+        }
+
+        filled.samples.push((timestamp, value));
+        previous = Some((timestamp, value));
+    }
+
+    filled
+}
+
+fn bucket_start<Tz>(local: DateTime<Tz>, resolution: Resolution) -> DateTime<Tz>
+where
+    Tz: TimeZone,
+{
+    let hour_start = local.clone()
+        - Duration::minutes(local.minute().into())
+        - Duration::seconds(local.second().into())
+        - Duration::nanoseconds(local.nanosecond().into());
+
+    match resolution {
+        Resolution::Hour => hour_start,
+        Resolution::Day => local
+            .timezone()
+            .from_local_datetime(&local.date_naive().and_time(Default::default()))
+            .earliest()
+            // Some time zones skip midnight when switching to daylight saving time.
+            .unwrap_or_else(|| hour_start.clone() - Duration::hours(hour_start.hour().into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+    use chrono_tz::Europe::Berlin;
+
+    use super::*;
+
+    #[test]
+    fn aggregate_hourly() {
+        let samples = vec![
+            (Utc.with_ymd_and_hms(2024, 3, 1, 10, 50, 0).unwrap(), 3.0),
+            (Utc.with_ymd_and_hms(2024, 3, 1, 10, 5, 0).unwrap(), 1.0),
+            (Utc.with_ymd_and_hms(2024, 3, 1, 10, 20, 0).unwrap(), 2.0),
+            (Utc.with_ymd_and_hms(2024, 3, 1, 11, 0, 0).unwrap(), 7.0),
+        ];
+        let timezone = FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap();
+
+        let hourly = aggregate(samples, Resolution::Hour, &timezone);
+
+        assert_eq!(hourly.len(), 2);
+        assert_eq!(
+            hourly[0],
+            Aggregate {
+                start: timezone.with_ymd_and_hms(2024, 3, 1, 15, 0, 0).unwrap(),
+                min: 1.0,
+                max: 2.0,
+                avg: 1.5,
+                count: 2,
+            }
+        );
+        assert_eq!(hourly[1].count, 2);
+        assert_eq!(hourly[1].avg, 5.0);
+    }
+
+    /// Hourly samples from `first` for `hours` hours.
+    fn hourly_samples(first: DateTime<Utc>, hours: i64) -> Vec<(DateTime<Utc>, f64)> {
+        (0..hours)
+            .map(|hour| (first + Duration::hours(hour), 1.0))
+            .collect()
+    }
+
+    #[test]
+    fn aggregate_the_23_hour_day_of_the_spring_transition() {
+        // 00:00 CET on 2024-03-31, when the clocks skip from 02:00 to 03:00, until 00:00 CEST on the next day.
+        let samples = hourly_samples(Utc.with_ymd_and_hms(2024, 3, 30, 23, 0, 0).unwrap(), 24);
+
+        let daily = aggregate(samples.clone(), Resolution::Day, &Berlin);
+        let hourly = aggregate(samples, Resolution::Hour, &Berlin);
+
+        assert_eq!(daily.len(), 2);
+        assert_eq!(
+            daily[0].start,
+            Berlin.with_ymd_and_hms(2024, 3, 31, 0, 0, 0).unwrap()
+        );
+        assert_eq!(daily[0].count, 23);
+        assert_eq!(
+            daily[1].start,
+            Berlin.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(hourly.len(), 24);
+        assert!(hourly.iter().all(|bucket| bucket.start.hour() != 2));
+    }
+
+    #[test]
+    fn aggregate_the_25_hour_day_of_the_fall_transition() {
+        // 00:00 CEST on 2024-10-27, when the clocks go back from 03:00 to 02:00, until 00:00 CET on the next day.
+        let samples = hourly_samples(Utc.with_ymd_and_hms(2024, 10, 26, 22, 0, 0).unwrap(), 26);
+
+        let daily = aggregate(samples.clone(), Resolution::Day, &Berlin);
+        let hourly = aggregate(samples, Resolution::Hour, &Berlin);
+
+        assert_eq!(daily.len(), 2);
+        assert_eq!(
+            daily[0].start,
+            Berlin.with_ymd_and_hms(2024, 10, 27, 0, 0, 0).unwrap()
+        );
+        assert_eq!(daily[0].count, 25);
+        assert_eq!(daily[1].count, 1);
+        assert_eq!(hourly.len(), 26);
+
+        let repeated: Vec<_> = hourly
+            .iter()
+            .filter(|bucket| bucket.start.hour() == 2)
+            .map(|bucket| bucket.start.with_timezone(&Utc))
+            .collect();
+        assert_eq!(
+            repeated,
+            [
+                Utc.with_ymd_and_hms(2024, 10, 27, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2024, 10, 27, 1, 0, 0).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn fill_gaps_with_each_strategy() {
+        let at = |hour| Utc.with_ymd_and_hms(2024, 3, 1, hour, 0, 0).unwrap();
+        let samples = vec![(at(4), 5.0), (at(0), 1.0), (at(1), 2.0), (at(5), 6.0)];
+
+        let values = |fill| {
+            fill_gaps(samples.clone(), Duration::hours(1), fill)
+                .samples
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(values(GapFill::Zero), [1.0, 2.0, 0.0, 0.0, 5.0, 6.0]);
+        assert_eq!(values(GapFill::Linear), [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(
+            values(GapFill::CarryForward),
+            [1.0, 2.0, 2.0, 2.0, 5.0, 6.0]
+        );
+        assert!(values(GapFill::Nan)[2].is_nan());
+
+        let filled = fill_gaps(samples, Duration::hours(1), GapFill::Zero);
+        assert_eq!(filled.samples[3].0, at(3));
+        assert_eq!(
+            filled.gaps,
+            vec![Gap {
+                start: at(2),
+                end: at(3),
+                missing: 2,
+            }]
+        );
+    }
+}
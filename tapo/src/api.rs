@@ -1,20 +1,56 @@
 mod api_client;
+mod api_client_builder;
+mod async_runtime;
 mod child_devices;
 mod color_light_handler;
 mod color_light_strip_handler;
+mod device_discovery;
+mod device_registry;
+mod device_stats;
+mod firmware_requirements;
 mod generic_device_handler;
+mod handler_methods;
 mod hub_handler;
 mod light_handler;
 mod plug_energy_monitoring_handler;
 mod plug_handler;
-mod protocol;
+pub mod protocol;
+mod write_queue;
 
 pub use api_client::*;
+pub use api_client_builder::*;
+pub use async_runtime::*;
 pub use child_devices::*;
 pub use color_light_handler::*;
 pub use color_light_strip_handler::*;
+pub use device_discovery::discover_devices;
+#[cfg(feature = "manager")]
+pub(crate) use device_discovery::normalize_mac;
+pub use device_registry::*;
+pub use device_stats::*;
+pub(crate) use firmware_requirements::*;
 pub use generic_device_handler::*;
+pub(crate) use handler_methods::*;
 pub use hub_handler::*;
 pub use light_handler::*;
 pub use plug_energy_monitoring_handler::*;
 pub use plug_handler::*;
+pub(crate) use write_queue::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+
+    #[test]
+    fn handlers_are_shareable() {
+        assert_shareable::<ColorLightHandler>();
+        assert_shareable::<ColorLightStripHandler>();
+        assert_shareable::<GenericDeviceHandler>();
+        assert_shareable::<HubHandler>();
+        assert_shareable::<LightHandler>();
+        assert_shareable::<PlugEnergyMonitoringHandler>();
+        assert_shareable::<PlugHandler>();
+    }
+}
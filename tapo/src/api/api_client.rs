@@ -1,31 +1,49 @@
 use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use isahc::prelude::Configurable;
 use isahc::HttpClient;
-use log::debug;
+use log::{debug, info, warn};
 use serde::de::DeserializeOwned;
+use tokio::sync::OnceCell;
 
 use crate::api::protocol::{TapoProtocol, TapoProtocolExt};
 use crate::api::{
-    ColorLightHandler, ColorLightStripHandler, GenericDeviceHandler, HubHandler, LightHandler,
-    PlugEnergyMonitoringHandler, PlugHandler,
+    minimum_firmware, ApiClientBuilder, AsyncRuntime, ColorLightHandler, ColorLightStripHandler,
+    DeviceRegistry, DeviceRegistryEntry, DeviceStats, FirmwareVersion, GenericDeviceHandler,
+    HubHandler, LightHandler, PlugEnergyMonitoringHandler, PlugHandler, RequestStats, WriteQueue,
 };
+use crate::audit::{AuditOutcome, AuditRecord, AuditSink};
 use crate::error::{Error, TapoResponseError};
 use crate::requests::{
-    ControlChildParams, EmptyParams, EnergyDataInterval, GetEnergyDataParams, LightingEffect,
-    MultipleRequestParams, TapoParams, TapoRequest,
+    nearest_color_temperature, AutomationRule, ControlChildParams, EditPresetRuleParams,
+    EmptyParams, EnergyDataInterval, GetAutomationListParams, GetEnergyDataParams, LightPreset,
+    LightingEffect, MultipleRequestParams, PlayAlarmParams, RawRequest, Ringtone,
+    SetChildProtectionParams, TapoParams, TapoRequest, DEFAULT_COLOR_TEMPERATURE_RANGE,
 };
 use crate::responses::{
-    validate_response, ControlChildResult, CurrentPowerResult, DecodableResultExt,
-    EnergyDataResult, EnergyUsageResult, TapoMultipleResponse, TapoResponseExt, TapoResult,
+    validate_response, AutomationListResult, Capabilities, ChildProtectionResult,
+    ComponentListResult, ControlChildResult, CurrentPowerResult, DecodableResultExt,
+    DeserializationMode, EmeterDataResult, EnergyDataResult, EnergyUsageResult, LightPresetsResult,
+    SupportAlarmTypeListResult, TapoMultipleResponse, TapoResponseExt, TapoResult,
 };
 
 const TERMINAL_UUID: &str = "00-00-00-00-00-00";
+/// Returned by the firmware for methods that it doesn't implement.
+const METHOD_NOT_SUPPORTED_ERROR_CODE: i32 = -40210;
+/// The delay between two retries stops doubling once it reaches this, unless the first delay is longer.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
 
 #[async_trait]
 pub(crate) trait ApiClientExt: std::fmt::Debug + Send + Sync {
     async fn set_device_info(&self, device_info_params: serde_json::Value) -> Result<(), Error>;
+
+    /// Returns the *color temperatures* in Kelvin accepted by the device.
+    async fn color_temperature_range(&self) -> Result<RangeInclusive<u16>, Error> {
+        Ok(DEFAULT_COLOR_TEMPERATURE_RANGE)
+    }
 }
 
 /// Tapo API Client. See [examples](https://github.com/mihai-dinculescu/tapo/tree/main/tapo/examples).
@@ -49,6 +67,53 @@ pub(crate) trait ApiClientExt: std::fmt::Debug + Send + Sync {
 #[derive(Debug, Clone)]
 pub struct ApiClient {
     protocol: TapoProtocol,
+    registry: Option<DeviceRegistry>,
+    device_mac: Option<String>,
+    /// `None` once the component list has turned out to be unavailable, see [`ApiClient::known_capabilities`].
+    capabilities: OnceCell<Option<Capabilities>>,
+    deserialization_mode: DeserializationMode,
+    retry_policy: RetryPolicy,
+    runtime: Arc<dyn AsyncRuntime>,
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    dry_run: bool,
+    url: Option<String>,
+    /// The last *device info* as returned by the device, updated with the changes made since.
+    shadow: Arc<Mutex<Option<serde_json::Value>>>,
+    coalesce_interval: Option<Duration>,
+    write_queue: Option<Arc<WriteQueue>>,
+    stats: Arc<Mutex<RequestStats>>,
+    color_temperature_fallback: bool,
+    /// Overrides the range reported by the device, see [`ApiClientBuilder::color_temperature_range`].
+    color_temperature_range: Option<RangeInclusive<u16>>,
+    device_color_temperature_range: OnceCell<RangeInclusive<u16>>,
+}
+
+/// How many times and how often the requests to an unreachable device are retried, see [`ApiClientBuilder::max_retries`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RetryPolicy {
+    pub max_retries: u32,
+    pub retry_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Returns the delay before the retry that follows `attempt` retries,
+    /// which doubles with every retry up to [`MAX_RETRY_DELAY`].
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.checked_pow(attempt).unwrap_or(u32::MAX);
+
+        self.retry_delay
+            .saturating_mul(factor)
+            .min(MAX_RETRY_DELAY.max(self.retry_delay))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            retry_delay: Duration::from_millis(500),
+        }
+    }
 }
 
 /// Tapo API Client constructor.
@@ -65,10 +130,116 @@ impl ApiClient {
         tapo_username: impl Into<String>,
         tapo_password: impl Into<String>,
     ) -> Result<ApiClient, Error> {
-        let client = HttpClient::builder().title_case_headers(true).build()?;
-        Ok(Self {
-            protocol: TapoProtocol::new(client, tapo_username.into(), tapo_password.into()),
-        })
+        Self::builder(tapo_username, tapo_password).build()
+    }
+
+    /// Returns an [`ApiClientBuilder`], which allows customizing the HTTP requests sent to the devices,
+    /// e.g. the `User-Agent` or additional headers required by an inspection proxy.
+    ///
+    /// # Arguments
+    ///
+    /// * `tapo_username` - the Tapo username
+    /// * `tapo_password` - the Tapo password
+    pub fn builder(
+        tapo_username: impl Into<String>,
+        tapo_password: impl Into<String>,
+    ) -> ApiClientBuilder {
+        ApiClientBuilder::new(tapo_username.into(), tapo_password.into())
+    }
+
+    pub(crate) fn from_http_client(
+        client: HttpClient,
+        tapo_username: String,
+        tapo_password: String,
+        retry_policy: RetryPolicy,
+        runtime: Arc<dyn AsyncRuntime>,
+        audit_sink: Option<Arc<dyn AuditSink>>,
+        dry_run: bool,
+    ) -> Self {
+        Self {
+            protocol: TapoProtocol::new(client, tapo_username, tapo_password),
+            registry: None,
+            device_mac: None,
+            capabilities: OnceCell::new(),
+            deserialization_mode: DeserializationMode::default(),
+            retry_policy,
+            runtime,
+            audit_sink,
+            dry_run,
+            url: None,
+            shadow: Arc::new(Mutex::new(None)),
+            coalesce_interval: None,
+            write_queue: None,
+            stats: Arc::new(Mutex::new(RequestStats::default())),
+            color_temperature_fallback: false,
+            color_temperature_range: None,
+            device_color_temperature_range: OnceCell::new(),
+        }
+    }
+
+    /// Coalesces the *device info* changes, see [`ApiClientBuilder::coalesce_writes`].
+    pub(crate) fn with_coalesce_interval(mut self, coalesce_interval: Option<Duration>) -> Self {
+        self.coalesce_interval = coalesce_interval;
+        self
+    }
+
+    /// Converts the colors sent to white-only lights, see [`ApiClientBuilder::color_temperature_fallback`].
+    pub(crate) fn with_color_temperature_fallback(
+        mut self,
+        color_temperature_fallback: bool,
+    ) -> Self {
+        self.color_temperature_fallback = color_temperature_fallback;
+        self
+    }
+
+    /// Overrides the *color temperature* range of the devices, see [`ApiClientBuilder::color_temperature_range`].
+    pub(crate) fn with_color_temperature_range(
+        mut self,
+        color_temperature_range: Option<RangeInclusive<u16>>,
+    ) -> Self {
+        self.color_temperature_range = color_temperature_range;
+        self
+    }
+
+    /// Attaches a [`DeviceRegistry`] to the client.
+    /// Every device handler created from this client records its device in the registry and,
+    /// when the device stops accepting connections, re-runs the discovery and reconnects to the new address of the device.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry` - the registry to record devices in and to resolve their addresses from
+    pub fn with_registry(mut self, registry: DeviceRegistry) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Sets how the responses of the devices are deserialized. Defaults to [`DeserializationMode::Lenient`].
+    ///
+    /// # Arguments
+    ///
+    /// * `deserialization_mode` - whether values unknown to this crate are tolerated or treated as errors
+    pub fn with_deserialization_mode(mut self, deserialization_mode: DeserializationMode) -> Self {
+        self.deserialization_mode = deserialization_mode;
+        self
+    }
+
+    /// Returns how many times a device has invalidated the session of this client, and it has logged in again.
+    /// The requests that fail because of that are transparently retried after logging in again,
+    /// which explains the occasional latency spikes.
+    ///
+    /// The devices report an expired session and a session taken over by another client,
+    /// usually the Tapo app, with the same error, so both are counted.
+    /// A count that grows while the app is in use points to the latter.
+    ///
+    /// The count is shared by the clones of the client and by the device handlers created from them.
+    pub fn session_relogins(&self) -> u64 {
+        self.protocol.session_relogins()
+    }
+
+    /// Returns the [`AsyncRuntime`] that the client runs on, see [`ApiClientBuilder::runtime`].
+    /// Useful for spawning background tasks without depending on a specific executor.
+    pub fn runtime(&self) -> &dyn AsyncRuntime {
+        self.runtime.as_ref()
     }
 }
 
@@ -459,13 +630,58 @@ impl ApiClient {
 /// Tapo API Client private methods.
 impl ApiClient {
     pub(crate) async fn login(&mut self, url: String) -> Result<(), Error> {
-        self.protocol.login(url).await
+        self.protocol.login(url.clone()).await?;
+        self.capabilities = OnceCell::new();
+        self.device_color_temperature_range = OnceCell::new();
+        self.shadow = Arc::new(Mutex::new(None));
+        self.stats = Arc::new(Mutex::new(RequestStats::default()));
+        self.write_queue = self
+            .coalesce_interval
+            .map(|interval| Arc::new(WriteQueue::new(interval)));
+        self.url.replace(url);
+
+        if let Some(registry) = self.registry.clone() {
+            self.register_device(&registry).await?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn stats(&self) -> DeviceStats {
+        self.stats
+            .lock()
+            .expect("the lock is never poisoned")
+            .snapshot()
     }
 
     pub(crate) async fn refresh_session(&mut self) -> Result<(), Error> {
         self.protocol.refresh_session().await
     }
 
+    /// Returns an [`ApiClient`] that shares the authenticated session of `self`.
+    /// Used for cloning device handlers, whereas [`ApiClient::clone`] is meant for connecting to another device.
+    pub(crate) fn share(&self) -> Self {
+        Self {
+            protocol: self.protocol.share(),
+            registry: self.registry.clone(),
+            device_mac: self.device_mac.clone(),
+            capabilities: self.capabilities.clone(),
+            deserialization_mode: self.deserialization_mode,
+            retry_policy: self.retry_policy,
+            runtime: self.runtime.clone(),
+            audit_sink: self.audit_sink.clone(),
+            dry_run: self.dry_run,
+            url: self.url.clone(),
+            shadow: self.shadow.clone(),
+            coalesce_interval: self.coalesce_interval,
+            write_queue: self.write_queue.clone(),
+            stats: self.stats.clone(),
+            color_temperature_fallback: self.color_temperature_fallback,
+            color_temperature_range: self.color_temperature_range.clone(),
+            device_color_temperature_range: self.device_color_temperature_range.clone(),
+        }
+    }
+
     pub(crate) async fn get_device_info<R>(&self) -> Result<R, Error>
     where
         R: fmt::Debug + DeserializeOwned + TapoResponseExt + DecodableResultExt,
@@ -473,11 +689,60 @@ impl ApiClient {
         debug!("Get Device info...");
         let request = TapoRequest::GetDeviceInfo(TapoParams::new(EmptyParams));
 
-        self.protocol
-            .execute_request::<R>(request, true)
+        let value = self
+            .execute_request::<serde_json::Value>(request, true)
             .await?
-            .map(|result| result.decode())
-            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))?
+            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))?;
+        let result = self.deserialize_device_info(&value);
+
+        self.shadow
+            .lock()
+            .expect("the lock is never poisoned")
+            .replace(value);
+
+        result
+    }
+
+    /// Returns the last *device info* returned by [`ApiClient::get_device_info`],
+    /// updated with the changes made since by [`ApiClientExt::set_device_info`],
+    /// or `None` if the *device info* hasn't been requested yet or can't be deserialized as `R`.
+    pub(crate) fn last_known_device_info<R>(&self) -> Option<R>
+    where
+        R: fmt::Debug + DeserializeOwned + TapoResponseExt + DecodableResultExt,
+    {
+        let shadow = self.shadow.lock().expect("the lock is never poisoned");
+
+        self.deserialize_device_info(shadow.as_ref()?).ok()
+    }
+
+    fn deserialize_device_info<R>(&self, value: &serde_json::Value) -> Result<R, Error>
+    where
+        R: fmt::Debug + DeserializeOwned + TapoResponseExt + DecodableResultExt,
+    {
+        self.deserialization_mode
+            .deserialize::<R>(value)
+            .map_err(|source| Error::Deserialization {
+                source,
+                response: value.to_string(),
+            })?
+            .decode()
+    }
+
+    pub(crate) async fn get_component_list(&self) -> Result<ComponentListResult, Error> {
+        debug!("Get Component list...");
+        let request = TapoRequest::ComponentNegotiation(TapoParams::new(EmptyParams));
+
+        self.execute_request::<ComponentListResult>(request, true)
+            .await?
+            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
+    }
+
+    pub(crate) async fn get_capabilities(&self) -> Result<Capabilities, Error> {
+        self.known_capabilities()
+            .await?
+            .ok_or_else(|| Error::NotSupported {
+                feature: "component negotiation".to_string(),
+            })
     }
 
     pub(crate) async fn get_device_usage<R>(&self) -> Result<R, Error>
@@ -487,17 +752,32 @@ impl ApiClient {
         debug!("Get Device usage...");
         let request = TapoRequest::GetDeviceUsage(TapoParams::new(EmptyParams));
 
-        self.protocol
-            .execute_request::<R>(request, true)
+        self.execute_request::<R>(request, true)
             .await?
             .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
     }
 
+    pub(crate) async fn reset_device_usage(&self) -> Result<(), Error> {
+        debug!("Reset device usage...");
+        let request = TapoRequest::ResetDeviceUsage(TapoParams::new(EmptyParams));
+
+        match self.execute_request::<TapoResult>(request, true).await {
+            Err(Error::Tapo(TapoResponseError::Unknown(METHOD_NOT_SUPPORTED_ERROR_CODE))) => {
+                Err(Error::NotSupported {
+                    feature: "usage counters reset".to_string(),
+                })
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
     pub(crate) async fn set_lighting_effect(
         &self,
         lighting_effect: LightingEffect,
     ) -> Result<(), Error> {
         debug!("Lighting effect will change to: {lighting_effect:?}");
+        self.ensure_supported("lighting effects", |c| c.has_effects)
+            .await?;
 
         let request = TapoRequest::SetLightingEffect(Box::new(
             TapoParams::new(lighting_effect)
@@ -505,19 +785,18 @@ impl ApiClient {
                 .set_terminal_uuid(TERMINAL_UUID),
         ));
 
-        self.protocol
-            .execute_request::<TapoResult>(request, true)
-            .await?;
+        self.execute_request::<TapoResult>(request, true).await?;
 
         Ok(())
     }
 
     pub(crate) async fn get_energy_usage(&self) -> Result<EnergyUsageResult, Error> {
         debug!("Get Energy usage...");
+        self.ensure_supported("energy monitoring", |c| c.has_energy_monitoring)
+            .await?;
         let request = TapoRequest::GetEnergyUsage(TapoParams::new(EmptyParams));
 
-        self.protocol
-            .execute_request::<EnergyUsageResult>(request, true)
+        self.execute_request::<EnergyUsageResult>(request, true)
             .await?
             .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
     }
@@ -527,25 +806,122 @@ impl ApiClient {
         interval: EnergyDataInterval,
     ) -> Result<EnergyDataResult, Error> {
         debug!("Get Energy data...");
+        self.ensure_supported("energy monitoring", |c| c.has_energy_monitoring)
+            .await?;
         let params = GetEnergyDataParams::new(interval);
         let request = TapoRequest::GetEnergyData(TapoParams::new(params));
 
-        self.protocol
-            .execute_request::<EnergyDataResult>(request, true)
+        self.execute_request::<EnergyDataResult>(request, true)
             .await?
             .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
     }
 
     pub(crate) async fn get_current_power(&self) -> Result<CurrentPowerResult, Error> {
         debug!("Get Current power...");
+        self.ensure_supported("energy monitoring", |c| c.has_energy_monitoring)
+            .await?;
         let request = TapoRequest::GetCurrentPower(TapoParams::new(EmptyParams));
 
-        self.protocol
-            .execute_request::<CurrentPowerResult>(request, true)
+        self.execute_request::<CurrentPowerResult>(request, true)
+            .await?
+            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
+    }
+
+    pub(crate) async fn get_emeter_data(&self) -> Result<EmeterDataResult, Error> {
+        debug!("Get Emeter data...");
+        self.ensure_supported("energy monitoring", |c| c.has_energy_monitoring)
+            .await?;
+        let request = TapoRequest::GetEmeterData(TapoParams::new(EmptyParams));
+
+        match self
+            .execute_request::<EmeterDataResult>(request, true)
+            .await
+        {
+            Err(Error::Tapo(TapoResponseError::Unknown(METHOD_NOT_SUPPORTED_ERROR_CODE))) => {
+                Err(Error::NotSupported {
+                    feature: "emeter data".to_string(),
+                })
+            }
+            result => result?.ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult)),
+        }
+    }
+
+    pub(crate) async fn get_child_protection(&self) -> Result<bool, Error> {
+        debug!("Get Child protection...");
+        self.ensure_supported("child protection", |c| c.has_child_protection)
+            .await?;
+        let request = TapoRequest::GetChildProtection(TapoParams::new(EmptyParams));
+
+        self.execute_request::<ChildProtectionResult>(request, true)
+            .await?
+            .map(|result| result.child_protection)
+            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
+    }
+
+    pub(crate) async fn set_child_protection(&self, enabled: bool) -> Result<(), Error> {
+        debug!("Child protection will change to: {enabled}");
+        self.ensure_supported("child protection", |c| c.has_child_protection)
+            .await?;
+        let request = TapoRequest::SetChildProtection(TapoParams::new(
+            SetChildProtectionParams::new(enabled),
+        ));
+
+        self.execute_request::<TapoResult>(request, true).await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn get_light_presets(&self) -> Result<Vec<LightPreset>, Error> {
+        debug!("Get Light presets...");
+        self.ensure_supported("presets", |c| c.has_presets).await?;
+        let request = TapoRequest::GetPresetRules(TapoParams::new(EmptyParams));
+
+        self.execute_request::<LightPresetsResult>(request, true)
             .await?
+            .map(LightPresetsResult::into_presets)
             .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
     }
 
+    pub(crate) async fn set_light_preset(
+        &self,
+        index: usize,
+        preset: LightPreset,
+    ) -> Result<(), Error> {
+        debug!("Light preset {index} will change to: {preset:?}");
+        preset.validate()?;
+
+        if preset.is_color() {
+            self.ensure_supported("color", |c| c.has_color).await?;
+        } else if let Some(color_temperature) = preset.color_temperature.filter(|ct| *ct > 0) {
+            self.ensure_supported("color temperature", |c| c.has_color_temperature)
+                .await?;
+
+            let range = self.color_temperature_range().await?;
+            if !range.contains(&color_temperature) {
+                return Err(Error::Validation {
+                    field: "color_temperature".to_string(),
+                    message: format!("must be between {} and {}", range.start(), range.end()),
+                });
+            }
+        }
+
+        let presets = self.get_light_presets().await?;
+        if index >= presets.len() {
+            return Err(Error::Validation {
+                field: "index".to_string(),
+                message: format!("must be less than the number of presets, {}", presets.len()),
+            });
+        }
+
+        let request = TapoRequest::EditPresetRules(Box::new(TapoParams::new(
+            EditPresetRuleParams::new(index, preset),
+        )));
+
+        self.execute_request::<TapoResult>(request, true).await?;
+
+        Ok(())
+    }
+
     pub(crate) async fn get_child_device_list<R>(&self) -> Result<R, Error>
     where
         R: fmt::Debug + DeserializeOwned + TapoResponseExt + DecodableResultExt,
@@ -553,8 +929,7 @@ impl ApiClient {
         debug!("Get Child device list...");
         let request = TapoRequest::GetChildDeviceList(TapoParams::new(EmptyParams));
 
-        self.protocol
-            .execute_request::<R>(request, true)
+        self.execute_request::<R>(request, true)
             .await?
             .map(|result| result.decode())
             .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))?
@@ -567,13 +942,173 @@ impl ApiClient {
         debug!("Get Child device component list...");
         let request = TapoRequest::GetChildDeviceComponentList(TapoParams::new(EmptyParams));
 
-        self.protocol
-            .execute_request::<R>(request, true)
+        self.execute_request::<R>(request, true)
             .await?
             .map(|result| result.decode())
             .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))?
     }
 
+    pub(crate) async fn send_raw(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, Error> {
+        debug!("Send raw request: {method}...");
+        let params = TapoParams::new(params)
+            .set_request_time_mils()?
+            .set_terminal_uuid(TERMINAL_UUID);
+        let request = TapoRequest::Raw(Box::new(RawRequest::new(method, params)));
+
+        self.execute_request::<serde_json::Value>(request, true)
+            .await
+            .map(Option::unwrap_or_default)
+    }
+
+    pub(crate) async fn get_automation_list(
+        &self,
+        start_index: u64,
+    ) -> Result<AutomationListResult, Error> {
+        debug!("Get Automation list...");
+        let params = GetAutomationListParams::new(start_index);
+        let request = TapoRequest::GetAutomationList(TapoParams::new(params));
+
+        self.execute_request::<AutomationListResult>(request, true)
+            .await?
+            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
+    }
+
+    pub(crate) async fn add_automation(&self, rule: AutomationRule) -> Result<(), Error> {
+        debug!("Add Automation: {rule:?}");
+        rule.validate()?;
+
+        let request = TapoRequest::AddAutomation(Box::new(
+            TapoParams::new(rule)
+                .set_request_time_mils()?
+                .set_terminal_uuid(TERMINAL_UUID),
+        ));
+
+        self.execute_request::<TapoResult>(request, true).await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn get_led_info<R>(&self) -> Result<R, Error>
+    where
+        R: fmt::Debug + DeserializeOwned + TapoResponseExt,
+    {
+        debug!("Get LED info...");
+        self.ensure_supported("LED", |c| c.has_led).await?;
+        let request = TapoRequest::GetLedInfo(TapoParams::new(EmptyParams));
+
+        self.execute_request::<R>(request, true)
+            .await?
+            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
+    }
+
+    pub(crate) async fn set_led_info(&self, led_info: serde_json::Value) -> Result<(), Error> {
+        debug!("LED info will change to: {led_info:?}");
+        let request = TapoRequest::SetLedInfo(Box::new(TapoParams::new(led_info)));
+
+        self.execute_request::<TapoResult>(request, true).await?;
+
+        Ok(())
+    }
+
+    /// Switches the status LED `times` times away from its current rule and back, `interval` apart,
+    /// then restores the original LED settings, which the firmware expects to be sent back in full.
+    /// They're also restored, as far as possible, when one of the switches fails.
+    pub(crate) async fn flash_led(&self, times: u32, interval: Duration) -> Result<(), Error> {
+        let led_info: serde_json::Value = self.get_led_info().await?;
+        let lit = led_info["led_status"].as_bool().unwrap_or(true);
+
+        let mut flashed = led_info.clone();
+        flashed["led_rule"] = serde_json::Value::from(if lit { "never" } else { "always" });
+
+        let flashing = async {
+            for time in 0..times {
+                if time > 0 {
+                    self.runtime.sleep(interval).await;
+                }
+                self.set_led_info(flashed.clone()).await?;
+                self.runtime.sleep(interval).await;
+                self.set_led_info(led_info.clone()).await?;
+            }
+
+            Ok::<_, Error>(())
+        };
+
+        if let Err(err) = flashing.await {
+            if let Err(restore_err) = self.set_led_info(led_info).await {
+                warn!("Failed to restore the LED settings: {restore_err:?}");
+            }
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn get_alarm_configuration<R>(&self) -> Result<R, Error>
+    where
+        R: fmt::Debug + DeserializeOwned + TapoResponseExt,
+    {
+        debug!("Get Alarm configuration...");
+        self.ensure_supported("alarm", |c| c.has_alarm).await?;
+        let request = TapoRequest::GetAlarmConfigure(TapoParams::new(EmptyParams));
+
+        self.execute_request::<R>(request, true)
+            .await?
+            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
+    }
+
+    pub(crate) async fn set_alarm_configuration(
+        &self,
+        configuration: serde_json::Value,
+    ) -> Result<(), Error> {
+        debug!("Alarm configuration will change to: {configuration:?}");
+        let request = TapoRequest::SetAlarmConfigure(Box::new(TapoParams::new(configuration)));
+
+        self.execute_request::<TapoResult>(request, true).await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn get_support_alarm_type_list(&self) -> Result<Vec<Ringtone>, Error> {
+        debug!("Get Support alarm type list...");
+        self.ensure_supported("alarm", |c| c.has_alarm).await?;
+        let request = TapoRequest::GetSupportAlarmTypeList(TapoParams::new(EmptyParams));
+
+        self.execute_request::<SupportAlarmTypeListResult>(request, true)
+            .await?
+            .map(|result| result.alarm_type_list)
+            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
+    }
+
+    pub(crate) async fn play_alarm(
+        &self,
+        ringtone: Option<Ringtone>,
+        duration: Option<u64>,
+    ) -> Result<(), Error> {
+        debug!("Play alarm {ringtone:?} for {duration:?} seconds...");
+        self.ensure_supported("alarm", |c| c.has_alarm).await?;
+        let request = TapoRequest::PlayAlarm(Box::new(TapoParams::new(PlayAlarmParams::new(
+            ringtone, duration,
+        ))));
+
+        self.execute_request::<TapoResult>(request, true).await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn stop_alarm(&self) -> Result<(), Error> {
+        debug!("Stop alarm...");
+        self.ensure_supported("alarm", |c| c.has_alarm).await?;
+        let request = TapoRequest::StopAlarm(TapoParams::new(EmptyParams));
+
+        self.execute_request::<TapoResult>(request, true).await?;
+
+        Ok(())
+    }
+
     pub(crate) async fn control_child<R>(
         &self,
         device_id: String,
@@ -583,6 +1118,10 @@ impl ApiClient {
         R: fmt::Debug + DeserializeOwned + TapoResponseExt,
     {
         debug!("Control child...");
+        if self.is_skipped_by_dry_run(&child_request)? {
+            return Ok(None);
+        }
+
         let params = MultipleRequestParams::new(vec![child_request]);
         let request = TapoRequest::MultipleRequest(Box::new(TapoParams::new(params)));
 
@@ -590,7 +1129,6 @@ impl ApiClient {
         let request = TapoRequest::ControlChild(Box::new(TapoParams::new(params)));
 
         let responses = self
-            .protocol
             .execute_request::<ControlChildResult<TapoMultipleResponse<R>>>(request, true)
             .await?
             .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))?
@@ -609,23 +1147,405 @@ impl ApiClient {
     }
 }
 
-#[async_trait]
-impl ApiClientExt for ApiClient {
-    async fn set_device_info(&self, device_info_params: serde_json::Value) -> Result<(), Error> {
-        debug!("Device info will change to: {device_info_params:?}");
+/// Capability checks.
+impl ApiClient {
+    /// Returns the capabilities of the device, or `None` if they can't be negotiated,
+    /// e.g. because the firmware doesn't implement `component_nego`,
+    /// in which case the capability checks are skipped and the requests are sent regardless.
+    /// Either outcome is cached until the next login, so that the request isn't repeated before every check.
+    /// Failures to reach the device, or to authenticate, are returned instead, as they say nothing about the firmware.
+    async fn known_capabilities(&self) -> Result<Option<Capabilities>, Error> {
+        self.capabilities
+            .get_or_try_init(|| async {
+                match self.get_component_list().await {
+                    Ok(component_list) => Ok(Some(component_list.capabilities())),
+                    Err(err) if is_component_list_unavailable(&err) => {
+                        debug!("Skipping the capability checks, the component list is unavailable: {err}");
+                        Ok(None)
+                    }
+                    Err(err) => Err(err),
+                }
+            })
+            .await
+            .cloned()
+    }
 
+    async fn ensure_supported(
+        &self,
+        feature: &str,
+        is_supported: impl FnOnce(&Capabilities) -> bool,
+    ) -> Result<(), Error> {
+        match self.known_capabilities().await? {
+            Some(capabilities) if !is_supported(&capabilities) => Err(Error::NotSupported {
+                feature: feature.to_string(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    async fn ensure_device_info_supported(
+        &self,
+        device_info_params: &serde_json::Value,
+    ) -> Result<(), Error> {
+        let has_param = |name: &str| device_info_params.get(name).is_some();
+        let color_temperature = device_info_params
+            .get("color_temp")
+            .and_then(|value| value.as_u64())
+            .unwrap_or_default();
+
+        if has_param("brightness") {
+            self.ensure_supported("brightness", |c| c.has_brightness)
+                .await?;
+        }
+
+        if color_temperature > 0 {
+            self.ensure_supported("color temperature", |c| c.has_color_temperature)
+                .await?;
+        } else if has_param("hue") || has_param("saturation") {
+            let Some(capabilities) = self.known_capabilities().await? else {
+                return Ok(());
+            };
+
+            if !capabilities.has_color && capabilities.has_brightness {
+                return Err(Error::Validation {
+                    field: "color".to_string(),
+                    message:
+                        "The device only supports white light. Control it with a `LightHandler`, \
+                              or convert the colors to the nearest color temperature with \
+                              `ApiClientBuilder::color_temperature_fallback`"
+                            .to_string(),
+                });
+            }
+            self.ensure_supported("color", |c| c.has_color).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the *hue* and *saturation* with the nearest *color temperature*
+    /// for the lights that support the latter but not the former, see [`ApiClientBuilder::color_temperature_fallback`].
+    async fn apply_color_temperature_fallback(
+        &self,
+        mut device_info_params: serde_json::Value,
+    ) -> Result<serde_json::Value, Error> {
+        let param = |name: &str| {
+            device_info_params
+                .get(name)
+                .and_then(|value| value.as_u64())
+        };
+
+        let (Some(hue), Some(saturation), None | Some(0)) =
+            (param("hue"), param("saturation"), param("color_temp"))
+        else {
+            return Ok(device_info_params);
+        };
+
+        if !self.color_temperature_fallback {
+            return Ok(device_info_params);
+        }
+
+        let Some(capabilities) = self.known_capabilities().await? else {
+            return Ok(device_info_params);
+        };
+        if capabilities.has_color || !capabilities.has_color_temperature {
+            return Ok(device_info_params);
+        }
+
+        let color_temperature = nearest_color_temperature(
+            hue as u16,
+            saturation as u8,
+            self.color_temperature_range().await?,
+        );
+        debug!("Converting hue {hue} and saturation {saturation} to {color_temperature}K...");
+
+        if let Some(params) = device_info_params.as_object_mut() {
+            params.remove("hue");
+            params.remove("saturation");
+            params.insert("color_temp".to_string(), color_temperature.into());
+        }
+
+        Ok(device_info_params)
+    }
+}
+
+/// Device registry support.
+impl ApiClient {
+    async fn register_device(&mut self, registry: &DeviceRegistry) -> Result<(), Error> {
+        let device_info: serde_json::Value = self.get_device_info().await?;
+
+        let field = |name: &str| {
+            device_info
+                .get(name)
+                .and_then(|value| value.as_str())
+                .map(str::to_string)
+        };
+
+        let (Some(mac), Some(ip_address)) = (field("mac"), field("ip")) else {
+            warn!("The device info doesn't contain a MAC and IP address, skipping registration");
+            return Ok(());
+        };
+
+        registry.insert(DeviceRegistryEntry {
+            mac: mac.clone(),
+            ip_address,
+            model: field("model"),
+        })?;
+        self.device_mac.replace(mac);
+
+        Ok(())
+    }
+
+    async fn execute_request<R>(
+        &self,
+        request: TapoRequest,
+        with_token: bool,
+    ) -> Result<Option<R>, Error>
+    where
+        R: fmt::Debug + DeserializeOwned + TapoResponseExt,
+    {
+        if self.is_skipped_by_dry_run(&request)? {
+            return Ok(None);
+        }
+
+        let result = match self.execute_request_with_audit(&request, with_token).await {
+            Err(Error::Tapo(TapoResponseError::Unknown(code))) => {
+                return Err(self
+                    .unsupported_firmware_error(&request)
+                    .await
+                    .unwrap_or(Error::Tapo(TapoResponseError::Unknown(code))));
+            }
+            result => result?,
+        };
+
+        result
+            .map(|value| {
+                let result =
+                    self.deserialization_mode
+                        .deserialize::<R>(&value)
+                        .map_err(|source| Error::Deserialization {
+                            source,
+                            response: value.to_string(),
+                        })?;
+                debug!("Device result: {result:?}");
+                Ok(result)
+            })
+            .transpose()
+    }
+
+    /// Sends the *device info* changes and applies them to the last known *device info*.
+    pub(crate) async fn send_device_info(
+        &self,
+        device_info_params: serde_json::Value,
+    ) -> Result<(), Error> {
         let set_device_info_request = TapoRequest::SetDeviceInfo(Box::new(
-            TapoParams::new(device_info_params)
+            TapoParams::new(device_info_params.clone())
                 .set_request_time_mils()?
                 .set_terminal_uuid(TERMINAL_UUID),
         ));
 
-        self.protocol
-            .execute_request::<TapoResult>(set_device_info_request, true)
+        self.execute_request::<TapoResult>(set_device_info_request, true)
             .await?;
 
+        if !self.dry_run {
+            let mut shadow = self.shadow.lock().expect("the lock is never poisoned");
+            if let (Some(serde_json::Value::Object(state)), serde_json::Value::Object(changes)) =
+                (shadow.as_mut(), device_info_params)
+            {
+                state.extend(changes);
+            }
+        }
+
         Ok(())
     }
+
+    /// Returns whether the `request` must not be sent because it changes the state of the device
+    /// and the client is in dry-run mode, see [`ApiClientBuilder::dry_run`]. Logs the request if so.
+    fn is_skipped_by_dry_run(&self, request: &TapoRequest) -> Result<bool, Error> {
+        if !self.dry_run || request.is_read_only() {
+            return Ok(false);
+        }
+
+        info!(
+            "Dry run, not sending to {}: {}",
+            self.url.as_deref().unwrap_or_default(),
+            serde_json::to_string(request)?
+        );
+
+        Ok(true)
+    }
+
+    /// Sends the `request` and records it in the [`DeviceStats`] and, if any, in the [`AuditSink`].
+    async fn execute_request_with_audit(
+        &self,
+        request: &TapoRequest,
+        with_token: bool,
+    ) -> Result<Option<serde_json::Value>, Error> {
+        let start = Instant::now();
+        let result = self
+            .execute_request_with_retries(request.clone(), with_token)
+            .await;
+        let latency = start.elapsed();
+
+        self.stats
+            .lock()
+            .expect("the lock is never poisoned")
+            .record(latency, result.as_ref().err());
+
+        if let Some(audit_sink) = &self.audit_sink {
+            let outcome = match &result {
+                Ok(_) => AuditOutcome::Success,
+                Err(err) => AuditOutcome::Failure {
+                    error: err.to_string(),
+                },
+            };
+            audit_sink.record(&AuditRecord::new(
+                self.url.as_deref().unwrap_or_default(),
+                &serde_json::to_value(request)?,
+                outcome,
+                latency,
+            ));
+        }
+
+        result
+    }
+
+    /// Returns [`Error::UnsupportedFirmware`] if the method of the failed `request` has a known minimum firmware version
+    /// that the device doesn't have. The firmware version is read from the *device info*.
+    async fn unsupported_firmware_error(&self, request: &TapoRequest) -> Option<Error> {
+        let method = serde_json::to_value(request)
+            .ok()?
+            .get("method")?
+            .as_str()?
+            .to_string();
+        let required = minimum_firmware(&method)?;
+
+        let device_info = self
+            .execute_request_with_retries(
+                TapoRequest::GetDeviceInfo(TapoParams::new(EmptyParams)),
+                true,
+            )
+            .await
+            .ok()??;
+        let fw_ver = device_info.get("fw_ver")?.as_str()?;
+
+        if FirmwareVersion::parse(fw_ver)?.satisfies(&required) {
+            return None;
+        }
+
+        Some(Error::UnsupportedFirmware {
+            method,
+            required: required.to_string(),
+            actual: fw_ver.to_string(),
+        })
+    }
+
+    async fn execute_request_with_retries(
+        &self,
+        request: TapoRequest,
+        with_token: bool,
+    ) -> Result<Option<serde_json::Value>, Error> {
+        let mut attempt = 0;
+
+        loop {
+            match self
+                .execute_request_with_rediscovery(request.clone(), with_token)
+                .await
+            {
+                Err(err) if err.is_unreachable() && attempt < self.retry_policy.max_retries => {
+                    debug!("The device is unreachable ({err}), retrying...");
+                    self.runtime.sleep(self.retry_policy.delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn execute_request_with_rediscovery(
+        &self,
+        request: TapoRequest,
+        with_token: bool,
+    ) -> Result<Option<serde_json::Value>, Error> {
+        let (Some(registry), Some(mac)) = (&self.registry, &self.device_mac) else {
+            return self.protocol.execute_request(request, with_token).await;
+        };
+
+        match self
+            .protocol
+            .execute_request(request.clone(), with_token)
+            .await
+        {
+            Err(Error::Unreachable(err)) => {
+                warn!("Device {mac} is unreachable ({err}), re-running the discovery...");
+
+                let ip_address = match registry.resolve(mac).await? {
+                    Some(ip_address) => ip_address,
+                    None => return Err(Error::Unreachable(err)),
+                };
+
+                self.protocol.relogin(build_url(&ip_address)).await?;
+                self.protocol.execute_request(request, with_token).await
+            }
+            result => result,
+        }
+    }
+}
+
+#[async_trait]
+impl ApiClientExt for ApiClient {
+    async fn set_device_info(&self, device_info_params: serde_json::Value) -> Result<(), Error> {
+        debug!("Device info will change to: {device_info_params:?}");
+        let device_info_params = self
+            .apply_color_temperature_fallback(device_info_params)
+            .await?;
+        self.ensure_device_info_supported(&device_info_params)
+            .await?;
+
+        match (&self.write_queue, device_info_params) {
+            (Some(write_queue), serde_json::Value::Object(changes)) => {
+                write_queue.clone().push(self, changes).await
+            }
+            (_, device_info_params) => self.send_device_info(device_info_params).await,
+        }
+    }
+
+    async fn color_temperature_range(&self) -> Result<RangeInclusive<u16>, Error> {
+        if let Some(range) = &self.color_temperature_range {
+            return Ok(range.clone());
+        }
+
+        self.device_color_temperature_range
+            .get_or_try_init(|| async {
+                let device_info = self.get_device_info::<serde_json::Value>().await?;
+                let range =
+                    serde_json::from_value::<[u16; 2]>(device_info["color_temp_range"].clone())
+                        .ok()
+                        .filter(|[min, max]| *min > 0 && min <= max)
+                        .map(|[min, max]| min..=max)
+                        .unwrap_or(DEFAULT_COLOR_TEMPERATURE_RANGE);
+                debug!("Color temperature range: {range:?}");
+
+                Ok::<_, Error>(range)
+            })
+            .await
+            .cloned()
+    }
+}
+
+/// Returns `true` if the device has processed the `component_nego` request and rejected it, or answered it with
+/// something that can't be read, which is the case on the firmware versions that don't implement it.
+fn is_component_list_unavailable(error: &Error) -> bool {
+    match error {
+        Error::Tapo(TapoResponseError::SessionTimeout | TapoResponseError::InvalidCredentials) => {
+            false
+        }
+        Error::Tapo(_)
+        | Error::NotSupported { .. }
+        | Error::UnsupportedFirmware { .. }
+        | Error::Serde(_)
+        | Error::Deserialization { .. } => true,
+        _ => false,
+    }
 }
 
 fn build_url(ip_address: &str) -> String {
@@ -634,3 +1554,153 @@ fn build_url(ip_address: &str) -> String {
 
     url
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::responses::ComponentListResult;
+
+    use super::*;
+
+    fn white_light_client(color_temperature_fallback: bool) -> ApiClient {
+        let component_list: ComponentListResult = serde_json::from_value(serde_json::json!({
+            "component_list": [
+                { "id": "brightness", "ver_code": 1 },
+                { "id": "color_temperature", "ver_code": 1 },
+            ]
+        }))
+        .unwrap();
+
+        let mut client = ApiClient::new("username", "password")
+            .unwrap()
+            .with_color_temperature_fallback(color_temperature_fallback)
+            .with_color_temperature_range(Some(2500..=6500));
+        client.capabilities = OnceCell::new_with(Some(Some(component_list.capabilities())));
+        client
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy {
+            max_retries: u32::MAX,
+            retry_delay: Duration::from_millis(500),
+        };
+
+        assert_eq!(policy.delay(0), Duration::from_millis(500));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(10), MAX_RETRY_DELAY);
+        assert_eq!(policy.delay(40), MAX_RETRY_DELAY);
+
+        let policy = RetryPolicy {
+            max_retries: u32::MAX,
+            retry_delay: Duration::MAX,
+        };
+        assert_eq!(policy.delay(1), Duration::MAX);
+    }
+
+    #[tokio::test]
+    async fn capability_checks_are_skipped_without_a_component_list() {
+        let mut client = ApiClient::new("username", "password").unwrap();
+        client.capabilities = OnceCell::new_with(Some(None));
+
+        assert!(matches!(
+            client.get_capabilities().await,
+            Err(Error::NotSupported { .. })
+        ));
+        assert!(client
+            .ensure_supported("LED", |capabilities| capabilities.has_led)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn capability_checks_fail_when_the_component_list_is_unreachable() {
+        // Without a session, the request fails before reaching the device.
+        let client = ApiClient::new("username", "password").unwrap();
+
+        assert!(client
+            .ensure_supported("LED", |capabilities| capabilities.has_led)
+            .await
+            .is_err());
+        assert!(client.capabilities.get().is_none());
+    }
+
+    #[test]
+    fn only_the_rejections_of_the_firmware_make_the_component_list_unavailable() {
+        assert!(is_component_list_unavailable(&Error::Tapo(
+            TapoResponseError::Unknown(METHOD_NOT_SUPPORTED_ERROR_CODE)
+        )));
+        assert!(is_component_list_unavailable(&Error::from(
+            serde_json::from_str::<u8>("-1").unwrap_err()
+        )));
+        assert!(!is_component_list_unavailable(&Error::Tapo(
+            TapoResponseError::SessionTimeout
+        )));
+        assert!(!is_component_list_unavailable(&Error::Unauthenticated));
+        assert!(!is_component_list_unavailable(&Error::from(
+            isahc::Error::from(isahc::error::ErrorKind::ConnectionFailed)
+        )));
+    }
+
+    #[tokio::test]
+    async fn colors_are_rejected_by_white_lights() {
+        let client = white_light_client(false);
+        let params = serde_json::json!({ "hue": 30, "saturation": 100, "color_temp": 0 });
+
+        let params = client
+            .apply_color_temperature_fallback(params)
+            .await
+            .unwrap();
+        let result = client.ensure_device_info_supported(&params).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation { field, .. }) if field == "color"
+        ));
+    }
+
+    #[tokio::test]
+    async fn colors_are_converted_for_white_lights() {
+        let client = white_light_client(true);
+        let params = serde_json::json!({ "hue": 30, "saturation": 100, "color_temp": 0 });
+
+        let params = client
+            .apply_color_temperature_fallback(params)
+            .await
+            .unwrap();
+
+        assert_eq!(params, serde_json::json!({ "color_temp": 2500 }));
+        assert!(client.ensure_device_info_supported(&params).await.is_ok());
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn requests_are_counted_in_the_stats() {
+        let (_fleet, device) = crate::simulator::connected_plug().await;
+        let before = device.stats().requests;
+
+        device.clone().on().await.unwrap();
+        device.get_device_info().await.unwrap();
+
+        let stats = device.stats();
+        assert_eq!(stats.requests, before + 2);
+        assert_eq!(stats.errors, 0);
+        assert!(stats.latency_p50_ms.is_some());
+        assert_eq!(stats.last_error, None);
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn invalidated_session_is_recovered() {
+        let fleet = crate::simulator::SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        let client = ApiClient::new("username", "password").unwrap();
+
+        let device = client.clone().p110(&fleet.addresses()[0]).await.unwrap();
+        let _app = client.clone().p110(&fleet.addresses()[0]).await.unwrap();
+
+        device.on().await.unwrap();
+        assert_eq!(client.session_relogins(), 1);
+    }
+}
@@ -0,0 +1,337 @@
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::time::Duration;
+
+use isahc::http::header::{HeaderName, HeaderValue, USER_AGENT};
+use isahc::prelude::Configurable;
+use isahc::HttpClient;
+
+use crate::api::{ApiClient, AsyncRuntime, RetryPolicy};
+use crate::audit::AuditSink;
+use crate::error::Error;
+
+/// Builder for an [`ApiClient`] with a customized HTTP client, see [`ApiClient::builder`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use tapo::ApiClient;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let device = ApiClient::builder("tapo-username@example.com", "tapo-password")
+///     .user_agent("Tapo CameraClient Android")
+///     .header("X-Proxy-Tag", "home-automation")
+///     .build()?
+///     .p110("192.168.1.100")
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ApiClientBuilder {
+    runtime: Option<Arc<dyn AsyncRuntime>>,
+    audit_sink: Option<Arc<dyn AuditSink>>,
+    dry_run: bool,
+    coalesce_interval: Option<Duration>,
+    color_temperature_fallback: bool,
+    color_temperature_range: Option<RangeInclusive<u16>>,
+    tapo_username: String,
+    tapo_password: String,
+    headers: Vec<(String, String)>,
+    timeout: Option<Duration>,
+    retry_policy: RetryPolicy,
+}
+
+impl ApiClientBuilder {
+    pub(crate) fn new(tapo_username: String, tapo_password: String) -> Self {
+        Self {
+            runtime: None,
+            audit_sink: None,
+            dry_run: false,
+            coalesce_interval: None,
+            color_temperature_fallback: false,
+            color_temperature_range: None,
+            tapo_username,
+            tapo_password,
+            headers: Vec::new(),
+            timeout: None,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets the `User-Agent` header sent with every request.
+    /// Defaults to the `User-Agent` of the underlying HTTP client.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_agent` - the value of the `User-Agent` header
+    pub fn user_agent(self, user_agent: impl Into<String>) -> Self {
+        self.header(USER_AGENT.as_str(), user_agent)
+    }
+
+    /// Adds a header to every request.
+    /// Setting the same header more than once replaces the previous value.
+    /// The headers required by the Tapo protocols, such as `Cookie` and `Content-Type`, always take precedence.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the header
+    /// * `value` - the value of the header
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        self.headers
+            .retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Sets the maximum duration of a request, after which it fails with [`Error::Unreachable`].
+    /// Defaults to no timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - the maximum duration of a request
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how many times a request is retried when the device is unreachable. Defaults to 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_retries` - the maximum number of retries of a request
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.retry_policy.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry, which doubles with every retry up to 30 seconds. Defaults to 500 milliseconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `retry_delay` - the delay before the first retry
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_policy.retry_delay = retry_delay;
+        self
+    }
+
+    /// Sets the executor that the retries and the other waits run on,
+    /// which allows using the client outside of a Tokio runtime.
+    /// Defaults to `TokioRuntime` with the default `tokio-runtime` feature, and is required without it.
+    ///
+    /// # Arguments
+    ///
+    /// * `runtime` - the [`AsyncRuntime`] of the executor, e.g. [`crate::SmolRuntime`] with the `smol` feature
+    pub fn runtime(mut self, runtime: impl AsyncRuntime + 'static) -> Self {
+        self.runtime = Some(Arc::new(runtime));
+        self
+    }
+
+    /// Records every request sent to the devices, with its outcome and latency, see [`crate::audit`].
+    /// Defaults to no audit log.
+    ///
+    /// # Arguments
+    ///
+    /// * `audit_sink` - where the [`crate::audit::AuditRecord`]s are sent, e.g. [`crate::audit::FileAuditSink`]
+    pub fn audit_sink(mut self, audit_sink: impl AuditSink + 'static) -> Self {
+        self.audit_sink = Some(Arc::new(audit_sink));
+        self
+    }
+
+    /// Enables the dry-run mode, in which the requests that change the state of the devices are validated and serialized,
+    /// then logged at the `info` level instead of being sent. They succeed without a result.
+    /// The requests that only read the state of the devices, and the login, are still sent. Defaults to `false`.
+    ///
+    /// # Arguments
+    ///
+    /// * `dry_run` - whether the requests that change the state of the devices are only logged
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Queues the changes of the *device info*, e.g. `on`, `off` or `set_brightness`, in a queue per device,
+    /// which combines the changes made in quick succession into a single request, the later changes replacing the earlier ones,
+    /// and leaves at least `min_interval` between two requests.
+    /// Useful when the changes come from a UI control, such as a slider, that fires many updates per second.
+    /// A change returns once the request containing it has been sent. Defaults to sending every change immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_interval` - the minimum time between two requests that change the *device info* of a device
+    pub fn coalesce_writes(mut self, min_interval: Duration) -> Self {
+        self.coalesce_interval = Some(min_interval);
+        self
+    }
+
+    /// Converts the *hue* and *saturation* sent to the lights that only support white light, such as the L510
+    /// when controlled through a [`crate::ColorLightHandler`], to the nearest *color temperature*,
+    /// provided that the light supports *color temperature*.
+    /// Defaults to `false`, which rejects the colors with [`Error::Validation`].
+    pub fn color_temperature_fallback(mut self, color_temperature_fallback: bool) -> Self {
+        self.color_temperature_fallback = color_temperature_fallback;
+        self
+    }
+
+    /// Overrides the *color temperatures* in Kelvin accepted by the lights, which otherwise come from
+    /// the `color_temp_range` reported in their *device info*, or default to 2500-6500 when it isn't reported.
+    /// Useful for the models whose firmware reports a wider range than they support, or a fixed white point.
+    ///
+    /// # Arguments
+    ///
+    /// * `color_temperature_range` - the lowest and the highest *color temperature*, e.g. `2700..=6500`
+    pub fn color_temperature_range(mut self, color_temperature_range: RangeInclusive<u16>) -> Self {
+        self.color_temperature_range = Some(color_temperature_range);
+        self
+    }
+
+    /// Builds the [`ApiClient`].
+    /// Returns [`Error::Validation`] if the name or the value of a header is invalid,
+    /// or if no [`AsyncRuntime`] has been set without the `tokio-runtime` feature.
+    pub fn build(self) -> Result<ApiClient, Error> {
+        #[cfg(feature = "tokio-runtime")]
+        let runtime = self
+            .runtime
+            .unwrap_or_else(|| Arc::new(crate::TokioRuntime));
+        #[cfg(not(feature = "tokio-runtime"))]
+        let runtime = self.runtime.ok_or_else(|| Error::Validation {
+            field: "runtime".to_string(),
+            message: "must be set without the `tokio-runtime` feature".to_string(),
+        })?;
+
+        if let Some(range) = &self.color_temperature_range {
+            if range.is_empty() {
+                return Err(Error::Validation {
+                    field: "color_temperature_range".to_string(),
+                    message: "The lowest color temperature must not be greater than the highest"
+                        .to_string(),
+                });
+            }
+        }
+
+        let mut builder = HttpClient::builder().title_case_headers(true);
+
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        for (name, value) in self.headers {
+            let header_name =
+                HeaderName::from_bytes(name.as_bytes()).map_err(|_| Error::Validation {
+                    field: "headers".to_string(),
+                    message: format!("`{name}` is not a valid header name"),
+                })?;
+            let header_value = HeaderValue::from_str(&value).map_err(|_| Error::Validation {
+                field: "headers".to_string(),
+                message: format!("The value of the `{name}` header is not valid"),
+            })?;
+
+            builder = builder.default_header(header_name, header_value);
+        }
+
+        Ok(ApiClient::from_http_client(
+            builder.build()?,
+            self.tapo_username,
+            self.tapo_password,
+            self.retry_policy,
+            runtime,
+            self.audit_sink,
+            self.dry_run,
+        )
+        .with_coalesce_interval(self.coalesce_interval)
+        .with_color_temperature_fallback(self.color_temperature_fallback)
+        .with_color_temperature_range(self.color_temperature_range))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_rejects_invalid_header() {
+        let result = ApiClient::builder("username", "password")
+            .header("X-Tag", "line\nbreak")
+            .build();
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation { field, .. }) if field == "headers"
+        ));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn dry_run_doesnt_change_the_device() {
+        let fleet = crate::simulator::SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+
+        let device = ApiClient::builder("username", "password")
+            .dry_run(true)
+            .build()
+            .unwrap()
+            .p110(&fleet.addresses()[0])
+            .await
+            .unwrap();
+        let device_on = device.get_device_info().await.unwrap().device_on;
+
+        if device_on {
+            device.off().await.unwrap();
+        } else {
+            device.on().await.unwrap();
+        }
+
+        assert_eq!(device.get_device_info().await.unwrap().device_on, device_on);
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn rapid_writes_are_coalesced() {
+        use std::sync::{Arc, Mutex};
+
+        use crate::audit::{AuditRecord, AuditSink};
+
+        #[derive(Debug, Default)]
+        struct MethodsAuditSink(Mutex<Vec<String>>);
+
+        impl AuditSink for Arc<MethodsAuditSink> {
+            fn record(&self, record: &AuditRecord) {
+                self.0.lock().unwrap().push(record.method.clone());
+            }
+        }
+
+        let fleet = crate::simulator::SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        let sink = Arc::new(MethodsAuditSink::default());
+
+        let device = ApiClient::builder("username", "password")
+            .audit_sink(sink.clone())
+            .coalesce_writes(Duration::from_millis(100))
+            .build()
+            .unwrap()
+            .p110(&fleet.addresses()[0])
+            .await
+            .unwrap();
+
+        let (first, second, third) = tokio::join!(device.on(), device.off(), device.on());
+        first.unwrap();
+        second.unwrap();
+        third.unwrap();
+        device.off().await.unwrap();
+
+        assert!(!device.get_device_info().await.unwrap().device_on);
+        let writes = sink
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|method| *method == "set_device_info")
+            .count();
+        assert_eq!(writes, 2);
+    }
+}
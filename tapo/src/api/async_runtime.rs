@@ -0,0 +1,97 @@
+use std::fmt;
+use std::time::Duration;
+
+use futures_lite::future::Boxed;
+
+/// The executor-specific operations of the [`crate::ApiClient`] and the device handlers,
+/// which allow them to run under an executor other than Tokio, e.g. `smol` or `async-std`.
+/// Set with [`crate::ApiClientBuilder::runtime`]. Defaults to `TokioRuntime` with the default `tokio-runtime` feature.
+///
+/// The HTTP requests and [`crate::discover_devices`] don't depend on the executor,
+/// but [`crate::watcher::DeviceWatcher`] and the `manager` feature still require Tokio.
+pub trait AsyncRuntime: fmt::Debug + Send + Sync {
+    /// Runs `task` in the background, without waiting for it to finish.
+    fn spawn(&self, task: Boxed<()>);
+
+    /// Returns a future that completes after `duration`.
+    /// Used to wait between the retries of a request and between polls.
+    fn sleep(&self, duration: Duration) -> Boxed<()>;
+}
+
+/// The [`AsyncRuntime`] of Tokio, which is the default.
+/// The methods must be called from within a Tokio runtime.
+#[cfg(feature = "tokio-runtime")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "tokio-runtime")]
+impl AsyncRuntime for TokioRuntime {
+    fn spawn(&self, task: Boxed<()>) {
+        tokio::spawn(task);
+    }
+
+    fn sleep(&self, duration: Duration) -> Boxed<()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// The [`AsyncRuntime`] of `smol`, which also works under `async-std`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use tapo::{ApiClient, SmolRuntime};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// smol::block_on(async {
+///     let device = ApiClient::builder("tapo-username@example.com", "tapo-password")
+///         .runtime(SmolRuntime)
+///         .max_retries(3)
+///         .build()?
+///         .p110("192.168.1.100")
+///         .await?;
+///
+///     device.on().await?;
+///
+///     Ok(())
+/// })
+/// # }
+/// ```
+#[cfg(feature = "smol")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmolRuntime;
+
+#[cfg(feature = "smol")]
+impl AsyncRuntime for SmolRuntime {
+    fn spawn(&self, task: Boxed<()>) {
+        smol::spawn(task).detach();
+    }
+
+    fn sleep(&self, duration: Duration) -> Boxed<()> {
+        Box::pin(async move {
+            smol::Timer::after(duration).await;
+        })
+    }
+}
+
+#[cfg(all(test, feature = "smol"))]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn smol_runtime_runs_without_tokio() {
+        let (sender, receiver) = smol::channel::bounded(1);
+
+        smol::block_on(async {
+            let start = Instant::now();
+            SmolRuntime.sleep(Duration::from_millis(20)).await;
+            assert!(start.elapsed() >= Duration::from_millis(20));
+
+            SmolRuntime.spawn(Box::pin(async move {
+                sender.send(()).await.unwrap();
+            }));
+            receiver.recv().await.unwrap();
+        });
+    }
+}
@@ -4,6 +4,7 @@ mod t100_handler;
 mod t110_handler;
 mod t300_handler;
 mod t31x_handler;
+mod trigger_log_pager;
 
 pub use ke100_handler::*;
 pub use s200b_handler::*;
@@ -11,3 +12,5 @@ pub use t100_handler::*;
 pub use t110_handler::*;
 pub use t300_handler::*;
 pub use t31x_handler::*;
+
+pub(crate) use trigger_log_pager::*;
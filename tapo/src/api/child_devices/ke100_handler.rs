@@ -1,7 +1,15 @@
+use log::warn;
+
 use crate::api::HubHandler;
 use crate::error::{Error, TapoResponseError};
-use crate::requests::{EmptyParams, TapoParams, TapoRequest, TrvSetDeviceInfoParams};
-use crate::responses::{DecodableResultExt, KE100Result, TemperatureUnitKE100};
+use crate::requests::{
+    EmptyParams, GetScheduleRulesParams, KE100ScheduleRuleParams, KE100WeeklySchedule,
+    RemoveScheduleRulesParams, TapoParams, TapoRequest, TrvSetDeviceInfoParams,
+};
+use crate::responses::{
+    DecodableResultExt, KE100Result, ScheduleRuleResult, ScheduleRulesResult, Temperature,
+    TemperatureUnitKE100,
+};
 
 /// Handler for the [KE100](https://www.tp-link.com/en/search/?q=KE100) devices.
 pub struct KE100Handler<'h> {
@@ -53,8 +61,10 @@ impl<'h> KE100Handler<'h> {
     ) -> Result<(), Error> {
         let device_info = self.get_device_info().await?;
 
-        if target_temperature < device_info.min_control_temperature
-            || target_temperature > device_info.max_control_temperature
+        let target = Temperature::from_celsius(target_temperature.into());
+
+        if target < device_info.min_control_temperature
+            || target > device_info.max_control_temperature
         {
             return Err(Error::Validation {
                 field: "target_temperature".to_string(),
@@ -182,4 +192,138 @@ impl<'h> KE100Handler<'h> {
 
         Ok(())
     }
+
+    /// Returns the *weekly schedule* stored on the device as [`KE100WeeklySchedule`].
+    /// Disabled schedule rules are left out.
+    pub async fn get_weekly_schedule(&self) -> Result<KE100WeeklySchedule, Error> {
+        let schedule = self
+            .get_schedule_rules()
+            .await?
+            .into_iter()
+            .filter(|rule| rule.enable)
+            .fold(KE100WeeklySchedule::new(), |schedule, rule| {
+                schedule.add_rule(
+                    rule.week_day,
+                    rule.s_min,
+                    rule.e_min,
+                    rule.desired_states.target_temp,
+                )
+            });
+
+        Ok(schedule)
+    }
+
+    /// Replaces the *weekly schedule* stored on the device with the given one.
+    /// The schedule is validated before any change is made: the slots of a day must not overlap,
+    /// and every target temperature must be between `min_control_temperature` and `max_control_temperature`.
+    ///
+    /// The replacement isn't atomic: the device only supports removing all the rules and adding them one by one.
+    /// If a request fails along the way, the previous rules are put back before the error is returned,
+    /// on a best-effort basis, since that can fail too, e.g. while the hub is unreachable.
+    ///
+    /// # Arguments
+    ///
+    /// * `schedule` - the new schedule, see [`KE100WeeklySchedule`]
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use chrono::{NaiveTime, Weekday};
+    /// # use tapo::ApiClient;
+    /// # use tapo::requests::KE100WeeklySchedule;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let hub = ApiClient::new("tapo-username@example.com", "tapo-password")?
+    /// #     .h100("192.168.1.100")
+    /// #     .await?;
+    /// let trv = hub.ke100("device-id");
+    ///
+    /// let schedule = KE100WeeklySchedule::new().slot(
+    ///     &[Weekday::Sat, Weekday::Sun],
+    ///     NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+    ///     NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+    ///     21,
+    /// );
+    ///
+    /// trv.set_weekly_schedule(&schedule).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_weekly_schedule(&self, schedule: &KE100WeeklySchedule) -> Result<(), Error> {
+        schedule.validate()?;
+
+        let rules = schedule.to_rules();
+        let device_info = self.get_device_info().await?;
+
+        if let Some(rule) = rules.iter().find(|rule| {
+            let target = Temperature::from_celsius(rule.target_temperature().into());
+            target < device_info.min_control_temperature
+                || target > device_info.max_control_temperature
+        }) {
+            return Err(Error::Validation {
+                field: "target_temperature".to_string(),
+                message: format!("Target temperature {} must be between {} (min_control_temperature) and {} (max_control_temperature)", rule.target_temperature(), device_info.min_control_temperature, device_info.max_control_temperature),
+            });
+        }
+
+        let previous_rules: Vec<_> = self
+            .get_schedule_rules()
+            .await?
+            .iter()
+            .map(KE100ScheduleRuleParams::from_rule)
+            .collect();
+
+        if let Err(err) = self.replace_schedule_rules(&rules).await {
+            if let Err(restore_err) = self.replace_schedule_rules(&previous_rules).await {
+                warn!(
+                    "Failed to restore the previous schedule of {}: {restore_err:?}",
+                    self.device_id
+                );
+            }
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Returns all the schedule rules stored on the device, enabled or not, reading them page by page.
+    async fn get_schedule_rules(&self) -> Result<Vec<ScheduleRuleResult>, Error> {
+        let mut rules = Vec::new();
+
+        loop {
+            let request = TapoRequest::GetScheduleRules(TapoParams::new(
+                GetScheduleRulesParams::new(rules.len() as u64),
+            ));
+            let result = self
+                .hub_handler
+                .control_child::<ScheduleRulesResult>(self.device_id.clone(), request)
+                .await?
+                .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))?;
+
+            let is_last_page = result.rule_list.is_empty();
+            rules.extend(result.rule_list);
+
+            if is_last_page || rules.len() as u64 >= result.sum {
+                return Ok(rules);
+            }
+        }
+    }
+
+    /// Removes all the schedule rules stored on the device and adds `rules`, one request at a time.
+    async fn replace_schedule_rules(&self, rules: &[KE100ScheduleRuleParams]) -> Result<(), Error> {
+        let request =
+            TapoRequest::RemoveScheduleRules(TapoParams::new(RemoveScheduleRulesParams::all()));
+        self.hub_handler
+            .control_child::<serde_json::Value>(self.device_id.clone(), request)
+            .await?;
+
+        for rule in rules {
+            let request = TapoRequest::AddScheduleRule(Box::new(TapoParams::new(rule.clone())));
+            self.hub_handler
+                .control_child::<serde_json::Value>(self.device_id.clone(), request)
+                .await?;
+        }
+
+        Ok(())
+    }
 }
@@ -1,6 +1,8 @@
-use crate::api::HubHandler;
+use futures_lite::Stream;
+
+use crate::api::{HubHandler, TriggerLogPager};
 use crate::error::{Error, TapoResponseError};
-use crate::requests::{EmptyParams, GetTriggerLogsParams, TapoParams, TapoRequest};
+use crate::requests::{EmptyParams, TapoParams, TapoRequest};
 use crate::responses::{DecodableResultExt, T110Result};
 use crate::responses::{T110Log, TriggerLogsResult};
 
@@ -43,12 +45,23 @@ impl<'h> T110Handler<'h> {
         page_size: u64,
         start_id: u64,
     ) -> Result<TriggerLogsResult<T110Log>, Error> {
-        let child_params = GetTriggerLogsParams::new(page_size, start_id);
-        let child_request = TapoRequest::GetTriggerLogs(Box::new(TapoParams::new(child_params)));
+        TriggerLogPager::new(self.hub_handler, self.device_id.clone())
+            .fetch_page(page_size, start_id)
+            .await
+    }
 
-        self.hub_handler
-            .control_child(self.device_id.clone(), child_request)
-            .await?
-            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
+    /// Returns all the trigger logs that the hub holds for this device, newest first.
+    /// The logs are fetched page by page, see [`T110Handler::get_trigger_logs`].
+    pub async fn fetch_all_trigger_logs(&self) -> Result<Vec<T110Log>, Error> {
+        TriggerLogPager::new(self.hub_handler, self.device_id.clone())
+            .fetch_all()
+            .await
+    }
+
+    /// Returns a [`Stream`] of all the trigger logs that the hub holds for this device, newest first.
+    /// The next page is only fetched once the log items of the current one have been consumed.
+    /// The stream ends after the first error.
+    pub fn trigger_logs_stream(&self) -> impl Stream<Item = Result<T110Log, Error>> + 'h {
+        TriggerLogPager::new(self.hub_handler, self.device_id.clone()).into_stream()
     }
 }
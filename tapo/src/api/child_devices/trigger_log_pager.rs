@@ -0,0 +1,226 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use futures_lite::{stream, Stream};
+use serde::de::DeserializeOwned;
+
+use crate::api::HubHandler;
+use crate::error::{Error, TapoResponseError};
+use crate::requests::{GetTriggerLogsParams, TapoParams, TapoRequest};
+use crate::responses::{TriggerLog, TriggerLogsResult};
+
+/// The number of log items requested per page when fetching all the trigger logs of a device.
+const PAGE_SIZE: u64 = 50;
+
+/// Walks the trigger logs of a child device page by page, from the newest to the oldest log item.
+pub(crate) struct TriggerLogPager<'h> {
+    hub_handler: &'h HubHandler,
+    device_id: String,
+    cursor: PageCursor,
+}
+
+/// The position of a [`TriggerLogPager`] in the trigger logs, apart from the requests.
+#[derive(Debug)]
+struct PageCursor {
+    next_start_id: Option<u64>,
+    fetched: u64,
+    oldest_id: Option<u64>,
+}
+
+impl<'h> TriggerLogPager<'h> {
+    pub fn new(hub_handler: &'h HubHandler, device_id: String) -> Self {
+        Self {
+            hub_handler,
+            device_id,
+            cursor: PageCursor::new(),
+        }
+    }
+
+    /// Fetches the trigger logs with the given `start_id`.
+    pub async fn fetch_page<L>(
+        &self,
+        page_size: u64,
+        start_id: u64,
+    ) -> Result<TriggerLogsResult<L>, Error>
+    where
+        L: fmt::Debug + DeserializeOwned,
+    {
+        let child_params = GetTriggerLogsParams::new(page_size, start_id);
+        let child_request = TapoRequest::GetTriggerLogs(Box::new(TapoParams::new(child_params)));
+
+        self.hub_handler
+            .control_child(self.device_id.clone(), child_request)
+            .await?
+            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
+    }
+
+    /// Fetches the next page, or returns `None` once all the log items have been fetched.
+    async fn next_page<L>(&mut self) -> Option<Result<Vec<L>, Error>>
+    where
+        L: fmt::Debug + DeserializeOwned + TriggerLog,
+    {
+        let start_id = self.cursor.next_start_id?;
+
+        match self.fetch_page::<L>(PAGE_SIZE, start_id).await {
+            Ok(page) => Some(Ok(self.cursor.advance(page))),
+            Err(err) => {
+                self.cursor.next_start_id = None;
+                Some(Err(err))
+            }
+        }
+    }
+
+    /// Fetches all the log items, newest first.
+    pub async fn fetch_all<L>(mut self) -> Result<Vec<L>, Error>
+    where
+        L: fmt::Debug + DeserializeOwned + TriggerLog,
+    {
+        let mut logs = Vec::new();
+
+        while let Some(page) = self.next_page().await {
+            logs.extend(page?);
+        }
+
+        Ok(logs)
+    }
+
+    /// Returns a stream of all the log items, newest first, which fetches the next page when the current one is exhausted.
+    /// The stream ends after the first error.
+    pub fn into_stream<L>(self) -> impl Stream<Item = Result<L, Error>> + 'h
+    where
+        L: fmt::Debug + DeserializeOwned + TriggerLog + 'h,
+    {
+        stream::unfold(
+            (self, VecDeque::new()),
+            |(mut pager, mut buffer)| async move {
+                loop {
+                    if let Some(log) = buffer.pop_front() {
+                        return Some((Ok(log), (pager, buffer)));
+                    }
+
+                    match pager.next_page().await? {
+                        Ok(logs) => buffer.extend(logs),
+                        Err(err) => return Some((Err(err), (pager, buffer))),
+                    }
+                }
+            },
+        )
+    }
+}
+
+impl PageCursor {
+    fn new() -> Self {
+        Self {
+            next_start_id: Some(0),
+            fetched: 0,
+            oldest_id: None,
+        }
+    }
+
+    /// Moves past `page` and returns its log items, without those already returned with a previous page,
+    /// e.g. when the logs have shifted between the requests.
+    fn advance<L>(&mut self, page: TriggerLogsResult<L>) -> Vec<L>
+    where
+        L: TriggerLog,
+    {
+        let mut logs = page.logs;
+        if let Some(oldest_id) = self.oldest_id {
+            logs.retain(|log| log.id() < oldest_id);
+        }
+
+        self.fetched += logs.len() as u64;
+        self.oldest_id = logs.last().map(|oldest| oldest.id()).or(self.oldest_id);
+        self.next_start_id = logs
+            .last()
+            .map(|oldest| oldest.id())
+            .filter(|_| self.fetched < page.sum)
+            .and_then(|oldest_id| oldest_id.checked_sub(1))
+            .filter(|start_id| *start_id > 0);
+
+        logs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Log(u64);
+
+    impl TriggerLog for Log {
+        fn id(&self) -> u64 {
+            self.0
+        }
+
+        fn timestamp(&self) -> u64 {
+            0
+        }
+    }
+
+    fn page(sum: u64, ids: impl IntoIterator<Item = u64>) -> TriggerLogsResult<Log> {
+        let logs: Vec<_> = ids.into_iter().map(Log).collect();
+
+        TriggerLogsResult {
+            start_id: logs.first().map(|log| log.0).unwrap_or_default(),
+            sum,
+            logs,
+        }
+    }
+
+    fn ids(logs: Vec<Log>) -> Vec<u64> {
+        logs.into_iter().map(|log| log.0).collect()
+    }
+
+    #[test]
+    fn pages_are_walked_until_the_last_one() {
+        let mut cursor = PageCursor::new();
+
+        assert_eq!(ids(cursor.advance(page(5, [10, 9, 8]))), [10, 9, 8]);
+        assert_eq!(cursor.next_start_id, Some(7));
+        assert_eq!(ids(cursor.advance(page(5, [7, 6]))), [7, 6]);
+        assert_eq!(cursor.next_start_id, None, "all the log items are fetched");
+    }
+
+    #[test]
+    fn last_page_ends_at_the_first_log_item() {
+        let mut cursor = PageCursor::new();
+
+        assert_eq!(ids(cursor.advance(page(10, [3, 2, 1]))), [3, 2, 1]);
+        assert_eq!(cursor.next_start_id, None);
+    }
+
+    #[test]
+    fn empty_page_ends_the_walk() {
+        let mut cursor = PageCursor::new();
+        assert!(cursor.advance(page(0, [])).is_empty());
+        assert_eq!(cursor.next_start_id, None);
+
+        let mut cursor = PageCursor::new();
+        cursor.advance(page(10, [10, 9]));
+        assert!(
+            cursor.advance(page(10, [])).is_empty(),
+            "the hub has fewer log items than it reported"
+        );
+        assert_eq!(cursor.next_start_id, None);
+    }
+
+    #[test]
+    fn overlap_at_page_boundaries_is_skipped() {
+        let mut cursor = PageCursor::new();
+
+        assert_eq!(ids(cursor.advance(page(6, [10, 9, 8]))), [10, 9, 8]);
+        assert_eq!(
+            ids(cursor.advance(page(6, [9, 8, 7, 6]))),
+            [7, 6],
+            "the log items already returned are skipped"
+        );
+        assert_eq!(cursor.next_start_id, Some(5));
+
+        assert!(cursor.advance(page(6, [8, 7])).is_empty());
+        assert_eq!(
+            cursor.next_start_id, None,
+            "a page with no new log items ends the walk"
+        );
+    }
+}
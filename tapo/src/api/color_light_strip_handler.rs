@@ -1,6 +1,15 @@
-use crate::api::ApiClient;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use log::warn;
+
+use crate::api::{impl_handler_methods, impl_switch_methods, ApiClient, ApiClientExt};
 use crate::error::Error;
-use crate::requests::{Color, ColorLightSetDeviceInfoParams, LightingEffect};
+use crate::requests::{
+    adjusted_brightness, nearest_color_temperature, stepped_color_temperature, Brightness, Color,
+    ColorLightSetDeviceInfoParams, Hue, IntoLightProperty, Kelvin, LightPreset, LightingEffect,
+    Saturation,
+};
 use crate::responses::{DeviceInfoColorLightStripResult, DeviceUsageEnergyMonitoringResult};
 
 /// Handler for the [L920](https://www.tapo.com/en/search/?q=L920) and [L930](https://www.tapo.com/en/search/?q=L930) devices.
@@ -8,17 +17,16 @@ pub struct ColorLightStripHandler {
     client: ApiClient,
 }
 
+impl_handler_methods!(ColorLightStripHandler);
+impl_switch_methods!(ColorLightStripHandler, |device_info| Some(
+    device_info.device_on
+));
+
 impl ColorLightStripHandler {
     pub(crate) fn new(client: ApiClient) -> Self {
         Self { client }
     }
 
-    /// Refreshes the authentication session.
-    pub async fn refresh_session(&mut self) -> Result<&mut Self, Error> {
-        self.client.refresh_session().await?;
-        Ok(self)
-    }
-
     /// Turns *on* the device.
     pub async fn on(&self) -> Result<(), Error> {
         ColorLightSetDeviceInfoParams::new(&self.client)
@@ -35,6 +43,42 @@ impl ColorLightStripHandler {
             .await
     }
 
+    /// Flashes the light `times` times, `interval` apart, to find it among others, e.g. while installing it.
+    /// The light is left *on* or *off*, as it was before, even if one of the flashes fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `times` - how many times the light is flashed
+    /// * `interval` - how long the light stays switched, and then back, at every flash
+    pub async fn identify(&self, times: u32, interval: Duration) -> Result<(), Error> {
+        let device_on = self.get_device_info().await?.device_on;
+
+        let flashing = async {
+            for time in 0..times {
+                if time > 0 {
+                    self.client.runtime().sleep(interval).await;
+                }
+                self.set_toggled(!device_on).await?;
+                self.client.runtime().sleep(interval).await;
+                self.set_toggled(device_on).await?;
+            }
+
+            Ok::<_, Error>(())
+        };
+
+        if let Err(err) = flashing.await {
+            if let Err(restore_err) = self.set_toggled(device_on).await {
+                warn!(
+                    "Failed to turn the light back {}: {restore_err:?}",
+                    if device_on { "on" } else { "off" }
+                );
+            }
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
     /// Returns *device info* as [`DeviceInfoColorLightStripResult`].
     /// It is not guaranteed to contain all the properties returned from the Tapo API.
     /// If the deserialization fails, or if a property that you care about it's not present, try [`ColorLightStripHandler::get_device_info_json`].
@@ -42,10 +86,13 @@ impl ColorLightStripHandler {
         self.client.get_device_info().await
     }
 
-    /// Returns *device info* as [`serde_json::Value`].
-    /// It contains all the properties returned from the Tapo API.
-    pub async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
-        self.client.get_device_info().await
+    /// Returns the last known *device info*, without a request to the device:
+    /// the result of the last `get_device_info`, updated with the changes made since
+    /// by this handler and its clones.
+    /// Useful for reflecting a change immediately, e.g. in a UI.
+    /// Returns `None` if `get_device_info` hasn't been called yet.
+    pub fn last_known_state(&self) -> Option<DeviceInfoColorLightStripResult> {
+        self.client.last_known_device_info()
     }
 
     /// Returns *device usage* as [`DeviceUsageEnergyMonitoringResult`].
@@ -53,6 +100,38 @@ impl ColorLightStripHandler {
         self.client.get_device_usage().await
     }
 
+    /// Resets the *device usage* counters (time usage and, where reported, power usage and saved power),
+    /// e.g. after replacing a bulb or a filter when tracking its lifetime.
+    /// Returns [`Error::NotSupported`] if the firmware of the device doesn't implement it.
+    pub async fn reset_usage_counters(&self) -> Result<(), Error> {
+        self.client.reset_device_usage().await
+    }
+
+    /// Returns the *presets* stored on the device, in the order shown by the Tapo app.
+    /// Returns [`Error::NotSupported`] if the device doesn't implement them.
+    pub async fn get_presets(&self) -> Result<Vec<LightPreset>, Error> {
+        self.client.get_light_presets().await
+    }
+
+    /// Replaces the *preset* in the given slot.
+    /// Returns [`Error::NotSupported`] if the device doesn't implement presets,
+    /// or the colors of `preset`.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - the slot of the preset, as returned by [`Self::get_presets`]
+    /// * `preset` - the new preset, e.g. [`LightPreset::brightness`]
+    pub async fn set_preset(&self, index: usize, preset: LightPreset) -> Result<(), Error> {
+        self.client.set_light_preset(index, preset).await
+    }
+
+    /// Returns the *color temperatures* in Kelvin accepted by the device: the range set with
+    /// [`crate::ApiClientBuilder::color_temperature_range`], else the one reported in the *device info*,
+    /// else 2500-6500.
+    pub async fn get_color_temperature_range(&self) -> Result<RangeInclusive<u16>, Error> {
+        self.client.color_temperature_range().await
+    }
+
     /// Returns a [`ColorLightSetDeviceInfoParams`] builder that allows multiple properties to be set in a single request.
     /// [`ColorLightSetDeviceInfoParams::send`] must be called at the end to apply the changes.
     /// For *lighting effects*, use [`ColorLightStripHandler::set_lighting_effect`] instead.
@@ -85,14 +164,59 @@ impl ColorLightStripHandler {
     ///
     /// # Arguments
     ///
-    /// * `brightness` - between 1 and 100
-    pub async fn set_brightness(&self, brightness: u8) -> Result<(), Error> {
+    /// * `brightness` - between 1 and 100, see [`Brightness::RANGE`]
+    pub async fn set_brightness(
+        &self,
+        brightness: impl IntoLightProperty<Brightness>,
+    ) -> Result<(), Error> {
         ColorLightSetDeviceInfoParams::new(&self.client)
             .brightness(brightness)
             .send()
             .await
     }
 
+    /// Sets the *brightness* and turns *on* the device, unless the *device info* reports
+    /// that it's already *on* with the same *brightness*.
+    /// Returns whether the *brightness* has been set.
+    ///
+    /// # Arguments
+    ///
+    /// * `brightness` - between 1 and 100, see [`Brightness::RANGE`]
+    pub async fn ensure_brightness(
+        &self,
+        brightness: impl IntoLightProperty<Brightness>,
+    ) -> Result<bool, Error> {
+        let brightness = brightness.into_raw();
+        let device_info = self.get_device_info().await?;
+        if device_info.device_on && device_info.brightness == brightness {
+            return Ok(false);
+        }
+
+        self.set_brightness(brightness).await?;
+        Ok(true)
+    }
+
+    /// Changes the *brightness* by `delta` percentage points, clamped between 1 and 100, and turns *on* the device,
+    /// e.g. for the steps of a rotary dimmer or a keyboard shortcut. Returns the new *brightness*.
+    ///
+    /// The firmware has no method for relative changes, so the current *brightness* is read from the *device info* first
+    /// and concurrent adjustments can overwrite each other.
+    /// No request is sent if the device is already *on* with the clamped *brightness*.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - the change of the *brightness*, negative to dim the light
+    pub async fn adjust_brightness(&self, delta: i8) -> Result<u8, Error> {
+        let device_info = self.get_device_info().await?;
+        let brightness = adjusted_brightness(device_info.brightness, delta);
+
+        if !(device_info.device_on && device_info.brightness == brightness) {
+            self.set_brightness(brightness).await?;
+        }
+
+        Ok(brightness)
+    }
+
     /// Sets the *color* and turns *on* the device.
     /// Pre-existing *lighting effect* will be removed.
     ///
@@ -111,9 +235,13 @@ impl ColorLightStripHandler {
     ///
     /// # Arguments
     ///
-    /// * `hue` - between 1 and 360
-    /// * `saturation` - between 1 and 100
-    pub async fn set_hue_saturation(&self, hue: u16, saturation: u8) -> Result<(), Error> {
+    /// * `hue` - between 1 and 360, see [`Hue::RANGE`]
+    /// * `saturation` - between 1 and 100, see [`Saturation::RANGE`]
+    pub async fn set_hue_saturation(
+        &self,
+        hue: impl IntoLightProperty<Hue>,
+        saturation: impl IntoLightProperty<Saturation>,
+    ) -> Result<(), Error> {
         ColorLightSetDeviceInfoParams::new(&self.client)
             .hue_saturation(hue, saturation)
             .send()
@@ -125,14 +253,48 @@ impl ColorLightStripHandler {
     ///
     /// # Arguments
     ///
-    /// * `color_temperature` - between 2500 and 6500
-    pub async fn set_color_temperature(&self, color_temperature: u16) -> Result<(), Error> {
+    /// * `color_temperature` - within the range of the device, see [`Self::get_color_temperature_range`]
+    pub async fn set_color_temperature(
+        &self,
+        color_temperature: impl IntoLightProperty<Kelvin>,
+    ) -> Result<(), Error> {
         ColorLightSetDeviceInfoParams::new(&self.client)
             .color_temperature(color_temperature)
             .send()
             .await
     }
 
+    /// Changes the *color temperature* by `delta` Kelvin, clamped to the range of the device, and turns *on* the device.
+    /// Returns the new *color temperature*.
+    ///
+    /// The current *color temperature* is read from the *device info* first, like the *brightness* of
+    /// [`Self::adjust_brightness`], so concurrent steps can overwrite each other. When the device shows a color,
+    /// the nearest *color temperature* to that color is changed instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - the change of the *color temperature*, negative for a warmer light
+    pub async fn step_color_temperature(&self, delta: i16) -> Result<u16, Error> {
+        let device_info = self.get_device_info().await?;
+        let range = self.get_color_temperature_range().await?;
+
+        let current = match device_info.color_temp {
+            0 => nearest_color_temperature(
+                device_info.hue.unwrap_or_default(),
+                device_info.saturation.unwrap_or_default().min(100) as u8,
+                range.clone(),
+            ),
+            color_temperature => color_temperature,
+        };
+        let color_temperature = stepped_color_temperature(current, delta, &range);
+
+        if !(device_info.device_on && device_info.color_temp == color_temperature) {
+            self.set_color_temperature(color_temperature).await?;
+        }
+
+        Ok(color_temperature)
+    }
+
     /// Sets a *lighting effect* and turns *on* the device.
     ///
     /// # Arguments
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use log::debug;
+use tokio::sync::oneshot;
+
+use crate::error::{Error, TapoResponseError};
+use crate::responses::{validate_response, DiscoveryResult, TapoResponse};
+
+const DISCOVERY_PORT: u16 = 20002;
+const DISCOVERY_HEADER_LEN: usize = 16;
+
+/// Payload-less discovery probe, understood by all devices that speak the Tapo protocols.
+const DISCOVERY_QUERY: [u8; 16] = [
+    0x02, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46, 0x3c, 0xb5, 0xd3,
+];
+
+/// Discovers the Tapo devices on the local network by broadcasting a discovery probe and
+/// collecting the answers that arrive before `timeout` elapses.
+///
+/// The probe is sent and the answers are collected on a dedicated thread,
+/// so the discovery doesn't depend on the executor, see [`crate::AsyncRuntime`].
+///
+/// # Arguments
+///
+/// * `target` - the broadcast address of the network, e.g. `192.168.1.255` or `255.255.255.255`
+/// * `timeout` - how long to wait for devices to respond
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use tapo::discover_devices;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let devices = discover_devices("255.255.255.255", Duration::from_secs(3)).await?;
+///
+/// for device in devices {
+///     println!("{} ({}) is at {}", device.device_model, device.mac, device.ip);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn discover_devices(
+    target: impl Into<String>,
+    timeout: Duration,
+) -> Result<Vec<DiscoveryResult>, Error> {
+    discover_devices_on_port(target.into(), DISCOVERY_PORT, timeout).await
+}
+
+async fn discover_devices_on_port(
+    target: String,
+    port: u16,
+    timeout: Duration,
+) -> Result<Vec<DiscoveryResult>, Error> {
+    debug!("Discovering devices via {target}...");
+
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(collect_discovery_responses(&target, port, timeout));
+    });
+
+    receiver
+        .await
+        .map_err(|_| anyhow::anyhow!("the discovery thread has panicked"))?
+}
+
+fn collect_discovery_responses(
+    target: &str,
+    port: u16,
+    timeout: Duration,
+) -> Result<Vec<DiscoveryResult>, Error> {
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(anyhow::Error::from)?;
+    socket.set_broadcast(true).map_err(anyhow::Error::from)?;
+    socket
+        .send_to(&DISCOVERY_QUERY, (target, port))
+        .map_err(anyhow::Error::from)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut buffer = vec![0u8; 4096];
+    let mut seen = HashSet::new();
+    let mut devices = Vec::new();
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket
+            .set_read_timeout(Some(remaining))
+            .map_err(anyhow::Error::from)?;
+
+        let (length, address) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => break,
+            Err(err) => return Err(anyhow::Error::from(err).into()),
+        };
+
+        match parse_discovery_response(&buffer[..length]) {
+            Ok(device) => {
+                debug!("Discovered {} at {address}", device.mac);
+                if seen.insert(device.mac.clone()) {
+                    devices.push(device);
+                }
+            }
+            Err(err) => debug!("Ignoring invalid discovery response from {address}: {err:?}"),
+        }
+    }
+
+    Ok(devices)
+}
+
+fn parse_discovery_response(datagram: &[u8]) -> Result<DiscoveryResult, Error> {
+    let payload = datagram
+        .get(DISCOVERY_HEADER_LEN..)
+        .ok_or_else(|| anyhow::anyhow!("discovery response is shorter than its header"))?;
+
+    let response: TapoResponse<DiscoveryResult> = serde_json::from_slice(payload)?;
+    validate_response(&response)?;
+
+    let mut device = response
+        .result
+        .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))?;
+    device.mac = normalize_mac(&device.mac);
+
+    Ok(device)
+}
+
+/// Formats a MAC address the same way the devices report it in their device info, e.g. `AA-BB-CC-DD-EE-FF`.
+pub(crate) fn normalize_mac(mac: &str) -> String {
+    mac.trim().to_uppercase().replace(':', "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAYLOAD: &[u8] = br#"{"error_code":0,"result":{"device_id":"0000","device_type":"SMART.TAPOPLUG","device_model":"P110(EU)","ip":"192.168.1.100","mac":"aa:bb:cc:dd:ee:ff","factory_default":false,"mgt_encrypt_schm":{"is_support_https":false,"encrypt_type":"KLAP","http_port":80,"lv":2}}}"#;
+
+    #[test]
+    fn parse_discovery_response_skips_header() {
+        let datagram = [DISCOVERY_QUERY.as_slice(), PAYLOAD].concat();
+
+        let device = parse_discovery_response(&datagram).unwrap();
+
+        assert_eq!(device.ip, "192.168.1.100");
+        assert_eq!(device.mac, "AA-BB-CC-DD-EE-FF");
+        assert_eq!(device.device_model, "P110(EU)");
+        assert_eq!(
+            device.encryption_scheme.unwrap().encrypt_type.as_deref(),
+            Some("KLAP")
+        );
+    }
+
+    #[test]
+    fn parse_discovery_response_rejects_truncated_datagram() {
+        assert!(parse_discovery_response(&DISCOVERY_QUERY[..8]).is_err());
+    }
+
+    #[test]
+    fn discovery_runs_without_tokio() {
+        let device = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = device.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let mut buffer = [0u8; 64];
+            let (_, address) = device.recv_from(&mut buffer).unwrap();
+            let datagram = [DISCOVERY_QUERY.as_slice(), PAYLOAD].concat();
+            device.send_to(&datagram, address).unwrap();
+            device.send_to(&datagram, address).unwrap();
+        });
+
+        let devices = futures_lite::future::block_on(discover_devices_on_port(
+            "127.0.0.1".to_string(),
+            port,
+            Duration::from_millis(500),
+        ))
+        .unwrap();
+
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].mac, "AA-BB-CC-DD-EE-FF");
+    }
+}
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::api::device_discovery::normalize_mac;
+use crate::api::discover_devices;
+use crate::error::Error;
+use crate::responses::DiscoveryResult;
+
+const DEFAULT_BROADCAST_ADDRESS: &str = "255.255.255.255";
+const DEFAULT_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The last known address of a device, as stored by [`DeviceRegistry`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceRegistryEntry {
+    /// MAC address in the `AA-BB-CC-DD-EE-FF` format.
+    pub mac: String,
+    /// The IP address the device was last seen at.
+    pub ip_address: String,
+    /// Device model, if known.
+    pub model: Option<String>,
+}
+
+/// Keeps track of the IP address of each device, keyed by MAC address.
+///
+/// When a [`DeviceRegistry`] is attached to an [`crate::ApiClient`] via [`crate::ApiClient::with_registry`],
+/// every device handler created from that client records its device in the registry and,
+/// when the device can no longer be reached, re-runs the discovery to find its new address
+/// and transparently reconnects to it.
+///
+/// Cloning a [`DeviceRegistry`] is cheap and the clones share the same entries.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use tapo::{ApiClient, DeviceRegistry};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let registry = DeviceRegistry::from_file("devices.json")?;
+///
+/// let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+///     .with_registry(registry)
+///     .p110("192.168.1.100")
+///     .await?;
+///
+/// // Keeps working after the device is assigned a new address by DHCP.
+/// device.on().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeviceRegistry {
+    entries: Arc<RwLock<HashMap<String, DeviceRegistryEntry>>>,
+    path: Option<PathBuf>,
+    broadcast_address: String,
+    discovery_timeout: Duration,
+}
+
+impl Default for DeviceRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Device registry constructors.
+impl DeviceRegistry {
+    /// Returns a new, in-memory, [`DeviceRegistry`].
+    pub fn new() -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(HashMap::new())),
+            path: None,
+            broadcast_address: DEFAULT_BROADCAST_ADDRESS.to_string(),
+            discovery_timeout: DEFAULT_DISCOVERY_TIMEOUT,
+        }
+    }
+
+    /// Returns a [`DeviceRegistry`] that is persisted as JSON to `path` every time it changes.
+    /// The existing entries are loaded from `path` if the file exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the location of the registry file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let mut registry = Self::new();
+
+        if path.exists() {
+            let contents = std::fs::read_to_string(&path).map_err(anyhow::Error::from)?;
+            let entries: Vec<DeviceRegistryEntry> = serde_json::from_str(&contents)?;
+
+            registry.entries = Arc::new(RwLock::new(
+                entries
+                    .into_iter()
+                    .map(|entry| (normalize_mac(&entry.mac), entry))
+                    .collect(),
+            ));
+        }
+
+        registry.path = Some(path);
+
+        Ok(registry)
+    }
+
+    /// Sets the broadcast address used when re-running the discovery. Defaults to `255.255.255.255`.
+    pub fn with_broadcast_address(mut self, broadcast_address: impl Into<String>) -> Self {
+        self.broadcast_address = broadcast_address.into();
+        self
+    }
+
+    /// Sets how long to wait for devices to respond when re-running the discovery. Defaults to 3 seconds.
+    pub fn with_discovery_timeout(mut self, discovery_timeout: Duration) -> Self {
+        self.discovery_timeout = discovery_timeout;
+        self
+    }
+}
+
+/// Device registry methods.
+impl DeviceRegistry {
+    /// Returns the entry of the device with the given `mac`, if known.
+    pub fn get(&self, mac: &str) -> Option<DeviceRegistryEntry> {
+        self.read_entries().get(&normalize_mac(mac)).cloned()
+    }
+
+    /// Returns all the known entries, ordered by MAC address.
+    pub fn entries(&self) -> Vec<DeviceRegistryEntry> {
+        let mut entries: Vec<_> = self.read_entries().values().cloned().collect();
+        entries.sort_by(|a, b| a.mac.cmp(&b.mac));
+        entries
+    }
+
+    /// Adds or updates the entry of a device.
+    pub fn insert(&self, mut entry: DeviceRegistryEntry) -> Result<(), Error> {
+        entry.mac = normalize_mac(&entry.mac);
+
+        let changed = {
+            let mut entries = self.write_entries();
+            entries.insert(entry.mac.clone(), entry.clone()) != Some(entry)
+        };
+
+        if changed {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+
+    /// Removes the entry of the device with the given `mac`.
+    pub fn remove(&self, mac: &str) -> Result<Option<DeviceRegistryEntry>, Error> {
+        let removed = self.write_entries().remove(&normalize_mac(mac));
+
+        if removed.is_some() {
+            self.save()?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Runs the discovery and updates the entries of every device that answered.
+    pub async fn rediscover(&self) -> Result<Vec<DiscoveryResult>, Error> {
+        let devices = discover_devices(&self.broadcast_address, self.discovery_timeout).await?;
+
+        {
+            let mut entries = self.write_entries();
+            for device in &devices {
+                entries.insert(
+                    device.mac.clone(),
+                    DeviceRegistryEntry {
+                        mac: device.mac.clone(),
+                        ip_address: device.ip.clone(),
+                        model: Some(device.device_model.clone()),
+                    },
+                );
+            }
+        }
+
+        self.save()?;
+
+        Ok(devices)
+    }
+
+    /// Runs the discovery and returns the current IP address of the device with the given `mac`,
+    /// or `None` if the device did not answer.
+    pub async fn resolve(&self, mac: &str) -> Result<Option<String>, Error> {
+        let mac = normalize_mac(mac);
+        debug!("Resolving the address of {mac}...");
+
+        let ip_address = self
+            .rediscover()
+            .await?
+            .into_iter()
+            .find(|device| device.mac == mac)
+            .map(|device| device.ip);
+
+        debug!("Resolved {mac} to {ip_address:?}");
+
+        Ok(ip_address)
+    }
+}
+
+/// Device registry private methods.
+impl DeviceRegistry {
+    fn save(&self) -> Result<(), Error> {
+        if let Some(path) = &self.path {
+            let contents = serde_json::to_string_pretty(&self.entries())?;
+            std::fs::write(path, contents).map_err(anyhow::Error::from)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_entries(&self) -> RwLockReadGuard<'_, HashMap<String, DeviceRegistryEntry>> {
+        self.entries.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_entries(&self) -> RwLockWriteGuard<'_, HashMap<String, DeviceRegistryEntry>> {
+        self.entries.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_normalizes_mac() {
+        let registry = DeviceRegistry::new();
+
+        registry
+            .insert(DeviceRegistryEntry {
+                mac: "aa:bb:cc:dd:ee:ff".to_string(),
+                ip_address: "192.168.1.100".to_string(),
+                model: None,
+            })
+            .unwrap();
+
+        let entry = registry.get("AA-BB-CC-DD-EE-FF").unwrap();
+        assert_eq!(entry.mac, "AA-BB-CC-DD-EE-FF");
+        assert_eq!(entry.ip_address, "192.168.1.100");
+    }
+
+    #[test]
+    fn from_file_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "tapo-device-registry-{}.json",
+            uuid::Uuid::new_v4().simple()
+        ));
+
+        let registry = DeviceRegistry::from_file(&path).unwrap();
+        registry
+            .insert(DeviceRegistryEntry {
+                mac: "AA-BB-CC-DD-EE-FF".to_string(),
+                ip_address: "192.168.1.100".to_string(),
+                model: Some("P110".to_string()),
+            })
+            .unwrap();
+
+        let reloaded = DeviceRegistry::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(reloaded.entries(), registry.entries());
+    }
+}
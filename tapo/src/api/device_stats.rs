@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// How many of the latest requests the latency percentiles are computed from.
+const LATENCY_WINDOW: usize = 100;
+
+/// The latency and error statistics of the requests sent to a device by a handler and its clones,
+/// e.g. [`crate::PlugHandler::stats`].
+/// Useful for spotting flaky devices and Wi-Fi dead zones.
+///
+/// The latencies include the retries and are those of the latest 100 requests,
+/// whereas the counts cover all the requests since the handler was created.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct DeviceStats {
+    /// How many requests have been sent.
+    pub requests: u64,
+    /// How many of the requests have failed.
+    pub errors: u64,
+    /// How many of the latest requests have failed in a row.
+    pub consecutive_errors: u64,
+    /// The median latency in milliseconds. `None` if no request has been sent yet.
+    pub latency_p50_ms: Option<u64>,
+    /// The 90th percentile of the latency in milliseconds.
+    pub latency_p90_ms: Option<u64>,
+    /// The 99th percentile of the latency in milliseconds.
+    pub latency_p99_ms: Option<u64>,
+    /// The description of the latest error, if any.
+    pub last_error: Option<String>,
+    /// When the latest error occurred, if any.
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+impl DeviceStats {
+    /// Returns the share of the requests that have failed, between 0 and 1.
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+
+        self.errors as f64 / self.requests as f64
+    }
+}
+
+/// Collects the [`DeviceStats`] of an [`crate::ApiClient`].
+#[derive(Debug, Default)]
+pub(crate) struct RequestStats {
+    stats: DeviceStats,
+    latencies: VecDeque<Duration>,
+}
+
+impl RequestStats {
+    pub fn record(&mut self, latency: Duration, error: Option<&Error>) {
+        if self.latencies.len() == LATENCY_WINDOW {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+
+        self.stats.requests += 1;
+        match error {
+            Some(error) => {
+                self.stats.errors += 1;
+                self.stats.consecutive_errors += 1;
+                self.stats.last_error = Some(error.to_string());
+                self.stats.last_error_at = Some(Utc::now());
+            }
+            None => self.stats.consecutive_errors = 0,
+        }
+    }
+
+    pub fn snapshot(&self) -> DeviceStats {
+        let mut latencies = self.latencies.iter().copied().collect::<Vec<_>>();
+        latencies.sort_unstable();
+
+        DeviceStats {
+            latency_p50_ms: percentile(&latencies, 50),
+            latency_p90_ms: percentile(&latencies, 90),
+            latency_p99_ms: percentile(&latencies, 99),
+            ..self.stats.clone()
+        }
+    }
+}
+
+/// Returns the nearest-rank `percentile` of the `sorted` latencies in milliseconds.
+fn percentile(sorted: &[Duration], percentile: usize) -> Option<u64> {
+    let rank = (sorted.len() * percentile).div_ceil(100).max(1);
+
+    sorted
+        .get(rank - 1)
+        .map(|latency| latency.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_cover_the_latest_requests() {
+        let mut stats = RequestStats::default();
+        for millis in 1..=150 {
+            stats.record(Duration::from_millis(millis), None);
+        }
+
+        let snapshot = stats.snapshot();
+
+        assert_eq!(snapshot.requests, 150);
+        assert_eq!(snapshot.latency_p50_ms, Some(100));
+        assert_eq!(snapshot.latency_p90_ms, Some(140));
+        assert_eq!(snapshot.latency_p99_ms, Some(149));
+    }
+
+    #[test]
+    fn errors_are_counted() {
+        let mut stats = RequestStats::default();
+        let error = Error::Other(anyhow::anyhow!("Unreachable"));
+
+        stats.record(Duration::from_millis(10), Some(&error));
+        stats.record(Duration::from_millis(10), None);
+        stats.record(Duration::from_millis(10), Some(&error));
+        stats.record(Duration::from_millis(10), Some(&error));
+
+        let snapshot = stats.snapshot();
+
+        assert_eq!(snapshot.errors, 3);
+        assert_eq!(snapshot.consecutive_errors, 2);
+        assert_eq!(snapshot.error_rate(), 0.75);
+        assert!(snapshot.last_error.unwrap().contains("Unreachable"));
+        assert_eq!(DeviceStats::default().latency_p50_ms, None);
+    }
+}
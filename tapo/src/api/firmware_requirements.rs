@@ -0,0 +1,86 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The oldest firmware versions known to implement the methods that older firmware rejects
+/// with an error code rather than reporting them as missing from the component list.
+const FIRMWARE_REQUIREMENTS: &[(&str, FirmwareVersion)] = &[
+    ("get_emeter_data", FirmwareVersion::new(1, 1, 0)),
+    ("get_preset_rules", FirmwareVersion::new(1, 1, 0)),
+    ("edit_preset_rules", FirmwareVersion::new(1, 1, 0)),
+    ("get_led_info", FirmwareVersion::new(1, 0, 7)),
+    ("set_led_info", FirmwareVersion::new(1, 0, 7)),
+    ("get_support_alarm_type_list", FirmwareVersion::new(1, 2, 0)),
+    ("reset_device_usage", FirmwareVersion::new(1, 2, 0)),
+];
+
+/// Returns the minimum firmware version that implements `method`, if it's known.
+pub(crate) fn minimum_firmware(method: &str) -> Option<FirmwareVersion> {
+    FIRMWARE_REQUIREMENTS
+        .iter()
+        .find(|(name, _)| *name == method)
+        .map(|(_, version)| *version)
+}
+
+/// The numeric part of a firmware version, e.g. `1.1.0` in `1.1.0 Build 231024 Rel.175834`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct FirmwareVersion {
+    major: u32,
+    minor: u32,
+    patch: u32,
+}
+
+impl FirmwareVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses the `fw_ver` reported in the *device info*. Missing components are taken to be 0.
+    pub fn parse(fw_ver: &str) -> Option<Self> {
+        let mut components = fw_ver
+            .split_whitespace()
+            .next()?
+            .split('.')
+            .map(str::parse::<u32>);
+
+        let major = components.next()?.ok()?;
+        let minor = components.next().unwrap_or(Ok(0)).ok()?;
+        let patch = components.next().unwrap_or(Ok(0)).ok()?;
+
+        Some(Self::new(major, minor, patch))
+    }
+
+    /// Returns `true` if `self` is at least `required`.
+    pub fn satisfies(&self, required: &Self) -> bool {
+        self.cmp(required) != Ordering::Less
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn firmware_versions_are_compared_numerically() {
+        let version = FirmwareVersion::parse("1.10.2 Build 231024 Rel.175834").unwrap();
+
+        assert_eq!(version, FirmwareVersion::new(1, 10, 2));
+        assert!(version.satisfies(&FirmwareVersion::new(1, 9, 0)));
+        assert!(!version.satisfies(&FirmwareVersion::new(1, 11, 0)));
+        assert_eq!(
+            FirmwareVersion::parse("2"),
+            Some(FirmwareVersion::new(2, 0, 0))
+        );
+        assert_eq!(FirmwareVersion::parse("Build 231024"), None);
+        assert_eq!(minimum_firmware("set_device_info"), None);
+    }
+}
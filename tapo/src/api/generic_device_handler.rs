@@ -1,4 +1,4 @@
-use crate::api::{ApiClient, ApiClientExt};
+use crate::api::{impl_handler_methods, impl_switch_methods, ApiClient, ApiClientExt};
 use crate::error::Error;
 use crate::requests::GenericSetDeviceInfoParams;
 use crate::responses::DeviceInfoGenericResult;
@@ -8,17 +8,14 @@ pub struct GenericDeviceHandler {
     client: ApiClient,
 }
 
+impl_handler_methods!(GenericDeviceHandler);
+impl_switch_methods!(GenericDeviceHandler, |device_info| device_info.device_on);
+
 impl GenericDeviceHandler {
     pub(crate) fn new(client: ApiClient) -> Self {
         Self { client }
     }
 
-    /// Refreshes the authentication session.
-    pub async fn refresh_session(&mut self) -> Result<&mut Self, Error> {
-        self.client.refresh_session().await?;
-        Ok(self)
-    }
-
     /// Turns *on* the device.
     pub async fn on(&self) -> Result<(), Error> {
         let json = serde_json::to_value(GenericSetDeviceInfoParams::device_on(true)?)?;
@@ -38,9 +35,12 @@ impl GenericDeviceHandler {
         self.client.get_device_info().await
     }
 
-    /// Returns *device info* as [`serde_json::Value`].
-    /// It contains all the properties returned from the Tapo API.
-    pub async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
-        self.client.get_device_info().await
+    /// Returns the last known *device info*, without a request to the device:
+    /// the result of the last `get_device_info`, updated with the changes made since
+    /// by this handler and its clones.
+    /// Useful for reflecting a change immediately, e.g. in a UI.
+    /// Returns `None` if `get_device_info` hasn't been called yet.
+    pub fn last_known_state(&self) -> Option<DeviceInfoGenericResult> {
+        self.client.last_known_device_info()
     }
 }
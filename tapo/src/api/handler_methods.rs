@@ -0,0 +1,131 @@
+/// Implements [`Clone`] and the methods common to all the device handlers,
+/// which forward to the [`crate::ApiClient`] held in their `client` field.
+macro_rules! impl_handler_methods {
+    ($handler:ty) => {
+        impl Clone for $handler {
+            /// Returns a handler that shares the authenticated session of `self`,
+            /// so that the same device can be controlled from multiple tasks.
+            fn clone(&self) -> Self {
+                Self {
+                    client: self.client.share(),
+                }
+            }
+        }
+
+        impl $handler {
+            /// Refreshes the authentication session.
+            pub async fn refresh_session(&mut self) -> Result<&mut Self, $crate::Error> {
+                self.client.refresh_session().await?;
+                Ok(self)
+            }
+
+            /// Returns the latency and error statistics of the requests sent to the device
+            /// by this handler and its clones, see [`DeviceStats`]($crate::DeviceStats).
+            pub fn stats(&self) -> $crate::DeviceStats {
+                self.client.stats()
+            }
+
+            /// Returns *device info* as [`serde_json::Value`].
+            /// It contains all the properties returned from the Tapo API.
+            pub async fn get_device_info_json(&self) -> Result<serde_json::Value, $crate::Error> {
+                self.client.get_device_info().await
+            }
+
+            /// Returns *component list* as [`ComponentListResult`]($crate::responses::ComponentListResult).
+            /// It lists the functionality implemented by the firmware of the device.
+            pub async fn get_component_list(
+                &self,
+            ) -> Result<$crate::responses::ComponentListResult, $crate::Error> {
+                self.client.get_component_list().await
+            }
+
+            /// Returns the [`Capabilities`]($crate::responses::Capabilities) of the device, derived from its *component list*.
+            /// The result is cached for the lifetime of the handler.
+            pub async fn get_capabilities(
+                &self,
+            ) -> Result<$crate::responses::Capabilities, $crate::Error> {
+                self.client.get_capabilities().await
+            }
+
+            /// Sends a request for the firmware `method` with the given `params`, reusing the authenticated session,
+            /// and returns its result as [`serde_json::Value`].
+            /// This is useful for calling methods that are not modeled by this crate.
+            ///
+            /// # Arguments
+            ///
+            /// * `method` - the name of the firmware method, e.g. `get_device_time`
+            /// * `params` - the parameters of the method, or [`serde_json::Value::Null`] if it doesn't take any
+            pub async fn send_raw(
+                &self,
+                method: &str,
+                params: serde_json::Value,
+            ) -> Result<serde_json::Value, $crate::Error> {
+                self.client.send_raw(method, params).await
+            }
+        }
+    };
+}
+
+/// Implements the methods of the handlers of the devices that can be turned *on* and *off*
+/// on top of their `on`, `off`, `get_device_info` and `last_known_state` methods,
+/// given how to read whether the device is *on* from its *device info*.
+macro_rules! impl_switch_methods {
+    ($handler:ty, |$info:ident| $device_on:expr) => {
+        impl $handler {
+            /// Turns *on* the device, unless the *device info* reports that it's already *on*,
+            /// which avoids needless writes in reconciliation loops.
+            /// Returns whether the device has been turned *on*.
+            pub async fn ensure_on(&self) -> Result<bool, $crate::Error> {
+                let $info = self.get_device_info().await?;
+                if $device_on == Some(true) {
+                    return Ok(false);
+                }
+
+                self.on().await?;
+                Ok(true)
+            }
+
+            /// Turns *off* the device, unless the *device info* reports that it's already *off*.
+            /// Returns whether the device has been turned *off*.
+            pub async fn ensure_off(&self) -> Result<bool, $crate::Error> {
+                let $info = self.get_device_info().await?;
+                if $device_on == Some(false) {
+                    return Ok(false);
+                }
+
+                self.off().await?;
+                Ok(true)
+            }
+
+            /// Turns the device *off* if the *device info* reports that it's *on*, and *on* otherwise,
+            /// e.g. for a button or a keyboard shortcut. Returns whether the device has been turned *on*.
+            pub async fn toggle(&self) -> Result<bool, $crate::Error> {
+                let $info = self.get_device_info().await?;
+                self.set_toggled($device_on != Some(true)).await
+            }
+
+            /// Same as [`Self::toggle`], but inverts the [`Self::last_known_state`] when there is one,
+            /// which saves the request for the *device info*. The device ends up in the wrong state
+            /// if it has been switched by something else since, e.g. the Tapo app or its physical button.
+            pub async fn toggle_optimistic(&self) -> Result<bool, $crate::Error> {
+                match self.last_known_state() {
+                    Some($info) => self.set_toggled($device_on != Some(true)).await,
+                    None => self.toggle().await,
+                }
+            }
+
+            async fn set_toggled(&self, device_on: bool) -> Result<bool, $crate::Error> {
+                if device_on {
+                    self.on().await?;
+                } else {
+                    self.off().await?;
+                }
+
+                Ok(device_on)
+            }
+        }
+    };
+}
+
+pub(crate) use impl_handler_methods;
+pub(crate) use impl_switch_methods;
@@ -1,32 +1,39 @@
+use std::collections::BTreeMap;
 use std::fmt;
+use std::time::Duration;
 
 use serde::de::DeserializeOwned;
+use serde_json::json;
 
-use crate::api::ApiClient;
+use crate::api::{impl_handler_methods, ApiClient};
 use crate::api::{KE100Handler, S200BHandler, T100Handler, T110Handler, T300Handler, T31XHandler};
-use crate::error::Error;
-use crate::requests::TapoRequest;
+use crate::error::{Error, TapoResponseError};
+use crate::requests::{
+    AutomationRule, EmptyParams, LedNightMode, Ringtone, TapoParams, TapoRequest,
+};
 use crate::responses::{
-    ChildDeviceListResult, ChildDeviceResult, DeviceInfoHubResult, TapoResponseExt,
+    AlarmConfigurationResult, AlarmVolume, AutomationResult, BatteryStatus, ChildDeviceListResult,
+    ChildDeviceResult, DeviceInfoHubResult, FirmwareDownloadStateResult, LatestFirmwareResult,
+    LedInfoResult, TapoResponseExt,
 };
 
+/// The number of polls during which the firmware download may not have started yet,
+/// as the hub can still report no update in progress right after it has been started.
+const DOWNLOAD_START_POLLS: u32 = 3;
+
 /// Handler for the [H100](https://www.tapo.com/en/search/?q=H100) hubs.
 pub struct HubHandler {
     client: ApiClient,
 }
 
+impl_handler_methods!(HubHandler);
+
 /// Hub handler methods.
 impl HubHandler {
     pub(crate) fn new(client: ApiClient) -> Self {
         Self { client }
     }
 
-    /// Refreshes the authentication session.
-    pub async fn refresh_session(&mut self) -> Result<&mut Self, Error> {
-        self.client.refresh_session().await?;
-        Ok(self)
-    }
-
     /// Returns *device info* as [`DeviceInfoHubResult`].
     /// It is not guaranteed to contain all the properties returned from the Tapo API.
     /// If the deserialization fails, or if a property that you care about it's not present, try [`HubHandler::get_device_info_json`].
@@ -34,12 +41,6 @@ impl HubHandler {
         self.client.get_device_info().await
     }
 
-    /// Returns *device info* as [`serde_json::Value`].
-    /// It contains all the properties returned from the Tapo API.
-    pub async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
-        self.client.get_device_info().await
-    }
-
     /// Returns *child device list* as [`ChildDeviceListResult`].
     /// It is not guaranteed to contain all the properties returned from the Tapo API
     /// or to support all the possible devices connected to the hub.
@@ -56,12 +57,355 @@ impl HubHandler {
         self.client.get_child_device_list().await
     }
 
+    /// Returns the [`BatteryStatus`] of every child device with a battery, by Device ID.
+    /// Read from the *child device list*, so that the models that aren't supported by this crate are included.
+    pub async fn get_child_battery_statuses(
+        &self,
+    ) -> Result<BTreeMap<String, BatteryStatus>, Error> {
+        let list = self.get_child_device_list_json().await?;
+
+        Ok(list["child_device_list"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|device| {
+                let device_id = device["device_id"].as_str()?;
+                let battery = BatteryStatus::from_device_info(device)?;
+                Some((device_id.to_string(), battery))
+            })
+            .collect())
+    }
+
     /// Returns *child device component list* as [`serde_json::Value`].
     /// This information is useful in debugging or when investigating new functionality to add.
     pub async fn get_child_device_component_list_json(&self) -> Result<serde_json::Value, Error> {
         self.client.get_child_device_component_list().await
     }
 
+    /// Returns the automations stored on the hub as a list of [`AutomationResult`].
+    /// Requires a hub firmware that supports on-device automations.
+    pub async fn get_automation_list(&self) -> Result<Vec<AutomationResult>, Error> {
+        let mut automations = Vec::new();
+
+        loop {
+            let result = self
+                .client
+                .get_automation_list(automations.len() as u64)
+                .await?;
+            let is_last_page = result.rule_list.is_empty()
+                || automations.len() + result.rule_list.len() >= result.sum as usize;
+
+            automations.extend(result.rule_list);
+
+            if is_last_page {
+                return Ok(automations);
+            }
+        }
+    }
+
+    /// Stores the given [`AutomationRule`] on the hub.
+    /// The hub runs the automation on its own, which means that it keeps working when this application is not running.
+    /// Requires a hub firmware that supports on-device automations.
+    ///
+    /// # Arguments
+    ///
+    /// * `rule` - the automation to add, see [`AutomationRule`]
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use tapo::ApiClient;
+    /// # use tapo::requests::{AutomationEvent, AutomationRule};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let hub = ApiClient::new("tapo-username@example.com", "tapo-password")?
+    /// #     .h100("192.168.1.100")
+    /// #     .await?;
+    /// let rule = AutomationRule::new("Water leak shutoff")
+    ///     .when("water-sensor-device-id", AutomationEvent::WaterLeak)
+    ///     .turn_off("plug-device-id");
+    ///
+    /// hub.add_automation(rule).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn add_automation(&self, rule: AutomationRule) -> Result<(), Error> {
+        self.client.add_automation(rule).await
+    }
+
+    /// Returns the status LED settings of the hub as [`LedInfoResult`].
+    pub async fn get_led_info(&self) -> Result<LedInfoResult, Error> {
+        self.client.get_led_info().await
+    }
+
+    /// Turns the status LED of the hub permanently on or off, disabling its night mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - true/false
+    pub async fn set_led(&self, enabled: bool) -> Result<(), Error> {
+        let rule = if enabled { "always" } else { "never" };
+
+        self.update_led_info(json!({ "led_rule": rule })).await
+    }
+
+    /// Turns the status LED of the hub off during the given [`LedNightMode`] hours, and on otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `night_mode` - when the LED is off
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use chrono::NaiveTime;
+    /// # use tapo::ApiClient;
+    /// # use tapo::requests::LedNightMode;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let hub = ApiClient::new("tapo-username@example.com", "tapo-password")?
+    /// #     .h100("192.168.1.100")
+    /// #     .await?;
+    /// hub.set_led_night_mode(LedNightMode::Custom {
+    ///     start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+    ///     end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+    /// })
+    /// .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_led_night_mode(&self, night_mode: LedNightMode) -> Result<(), Error> {
+        let night_mode = night_mode.to_params()?;
+
+        self.update_led_info(json!({ "led_rule": "auto", "night_mode": night_mode }))
+            .await
+    }
+
+    /// Returns the alarm settings of the hub as [`AlarmConfigurationResult`].
+    pub async fn get_alarm_configuration(&self) -> Result<AlarmConfigurationResult, Error> {
+        self.client.get_alarm_configuration().await
+    }
+
+    /// Returns the ringtones that the alarm of the hub can play.
+    pub async fn get_supported_ringtones(&self) -> Result<Vec<Ringtone>, Error> {
+        self.client.get_support_alarm_type_list().await
+    }
+
+    /// Sets the ringtone of the alarm of the hub, used when the alarm is triggered without a ringtone of its own.
+    /// Automations can play other ringtones for specific events, see [`AutomationRule::play_ringtone`].
+    ///
+    /// # Arguments
+    ///
+    /// * `ringtone` - one of the ringtones returned by [`HubHandler::get_supported_ringtones`]
+    pub async fn set_alarm_ringtone(&self, ringtone: Ringtone) -> Result<(), Error> {
+        let supported_ringtones = self.get_supported_ringtones().await?;
+        if !supported_ringtones.contains(&ringtone) {
+            return Err(Error::Validation {
+                field: "ringtone".to_string(),
+                message: format!("`{ringtone}` isn't supported by the hub"),
+            });
+        }
+
+        let mut configuration: serde_json::Value = self.client.get_alarm_configuration().await?;
+        merge(&mut configuration, json!({ "type": ringtone }));
+
+        self.client.set_alarm_configuration(configuration).await
+    }
+
+    /// Sets the volume of the alarm of the hub. The hub has no volume schedule of its own,
+    /// so silencing it overnight means calling this with [`AlarmVolume::Mute`] in the evening
+    /// and with the usual volume in the morning.
+    ///
+    /// # Arguments
+    ///
+    /// * `volume` - the volume of the alarm
+    pub async fn set_alarm_volume(&self, volume: AlarmVolume) -> Result<(), Error> {
+        if let AlarmVolume::Other(value) = &volume {
+            return Err(Error::Validation {
+                field: "volume".to_string(),
+                message: format!("unknown volume `{value}`"),
+            });
+        }
+
+        let mut configuration: serde_json::Value = self.client.get_alarm_configuration().await?;
+        merge(&mut configuration, json!({ "volume": volume }));
+
+        self.client.set_alarm_configuration(configuration).await
+    }
+
+    /// Plays the alarm of the hub, e.g. as a chime when a door opens, or as a siren.
+    ///
+    /// # Arguments
+    ///
+    /// * `ringtone` - one of the ringtones returned by [`HubHandler::get_supported_ringtones`],
+    ///   or `None` for the ringtone of the alarm configuration
+    /// * `duration` - how long the alarm plays for, in seconds, or `None` for the duration of the alarm configuration
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use tapo::ApiClient;
+    /// # use tapo::requests::Ringtone;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let hub = ApiClient::new("tapo-username@example.com", "tapo-password")?
+    /// #     .h100("192.168.1.100")
+    /// #     .await?;
+    /// hub.play_alarm(Some(Ringtone::DoorbellRing(1)), Some(2)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn play_alarm(
+        &self,
+        ringtone: Option<Ringtone>,
+        duration: Option<u64>,
+    ) -> Result<(), Error> {
+        self.client.play_alarm(ringtone, duration).await
+    }
+
+    /// Stops the alarm of the hub, if it's playing.
+    pub async fn stop_alarm(&self) -> Result<(), Error> {
+        self.client.stop_alarm().await
+    }
+
+    /// Applies `changes` on top of the current LED settings, which the firmware expects to be sent back in full.
+    async fn update_led_info(&self, changes: serde_json::Value) -> Result<(), Error> {
+        let mut led_info: serde_json::Value = self.client.get_led_info().await?;
+        merge(&mut led_info, changes);
+
+        self.client.set_led_info(led_info).await
+    }
+
+    /// Returns the latest firmware available for the child device with the given `device_id` as [`LatestFirmwareResult`].
+    ///
+    /// # Arguments
+    ///
+    /// * `device_id` - the Device ID of the child device
+    pub async fn get_child_latest_firmware(
+        &self,
+        device_id: impl Into<String>,
+    ) -> Result<LatestFirmwareResult, Error> {
+        let request = TapoRequest::GetLatestFirmware(TapoParams::new(EmptyParams));
+
+        self.control_child(device_id.into(), request)
+            .await?
+            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
+    }
+
+    /// Starts the firmware update of the child device with the given `device_id`.
+    /// The progress can be followed with [`HubHandler::get_child_firmware_download_state`]
+    /// or [`HubHandler::wait_for_child_firmware_download`].
+    ///
+    /// # Arguments
+    ///
+    /// * `device_id` - the Device ID of the child device
+    pub async fn start_child_firmware_update(
+        &self,
+        device_id: impl Into<String>,
+    ) -> Result<(), Error> {
+        let request = TapoRequest::FwDownload(TapoParams::new(EmptyParams));
+
+        self.control_child::<serde_json::Value>(device_id.into(), request)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the progress of the firmware update of the child device with the given `device_id`
+    /// as [`FirmwareDownloadStateResult`].
+    ///
+    /// # Arguments
+    ///
+    /// * `device_id` - the Device ID of the child device
+    pub async fn get_child_firmware_download_state(
+        &self,
+        device_id: impl Into<String>,
+    ) -> Result<FirmwareDownloadStateResult, Error> {
+        let request = TapoRequest::GetFwDownloadState(TapoParams::new(EmptyParams));
+
+        self.control_child(device_id.into(), request)
+            .await?
+            .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))
+    }
+
+    /// Polls the progress of the firmware update of the child device with the given `device_id`
+    /// every `interval` until the firmware has been downloaded, calling `on_progress` after every poll.
+    ///
+    /// Returns an error if no update is in progress, e.g. because the download has failed or never started,
+    /// or if the firmware hasn't been downloaded within `timeout`. The hub can report no update in progress
+    /// right after [`HubHandler::start_child_firmware_update`], so that's only an error once the download
+    /// has been seen in progress or after the first few polls.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_id` - the Device ID of the child device
+    /// * `interval` - the time between two polls
+    /// * `timeout` - the maximum time to wait for the download
+    /// * `on_progress` - called with the progress after every poll
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use std::time::Duration;
+    /// # use tapo::ApiClient;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let hub = ApiClient::new("tapo-username@example.com", "tapo-password")?
+    /// #     .h100("192.168.1.100")
+    /// #     .await?;
+    /// let device_id = "ke100-device-id";
+    ///
+    /// if hub.get_child_latest_firmware(device_id).await?.need_to_upgrade {
+    ///     hub.start_child_firmware_update(device_id).await?;
+    ///     hub.wait_for_child_firmware_download(
+    ///         device_id,
+    ///         Duration::from_secs(5),
+    ///         Duration::from_secs(10 * 60),
+    ///         |state| println!("Downloaded {}%", state.download_progress),
+    ///     )
+    ///     .await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn wait_for_child_firmware_download(
+        &self,
+        device_id: impl Into<String>,
+        interval: Duration,
+        timeout: Duration,
+        mut on_progress: impl FnMut(&FirmwareDownloadStateResult),
+    ) -> Result<FirmwareDownloadStateResult, Error> {
+        let device_id = device_id.into();
+        let started = std::time::Instant::now();
+        let mut download = DownloadWatch::new();
+
+        loop {
+            let state = self
+                .get_child_firmware_download_state(device_id.clone())
+                .await?;
+            on_progress(&state);
+
+            match download.observe(&state) {
+                DownloadPoll::Downloaded => return Ok(state),
+                DownloadPoll::NotInProgress => {
+                    return Err(anyhow::anyhow!(
+                        "No firmware update is in progress on the child device {device_id}"
+                    )
+                    .into());
+                }
+                DownloadPoll::Pending => {}
+            }
+            if started.elapsed() + interval > timeout {
+                return Err(anyhow::anyhow!(
+                    "The firmware download of the child device {device_id} has timed out"
+                )
+                .into());
+            }
+
+            self.client.runtime().sleep(interval).await;
+        }
+    }
+
     /// Internal method that's called by functions of the child devices.
     pub(crate) async fn control_child<R>(
         &self,
@@ -75,6 +419,55 @@ impl HubHandler {
     }
 }
 
+/// Follows the states polled while waiting for a firmware download, apart from the requests.
+#[derive(Debug)]
+struct DownloadWatch {
+    polls: u32,
+    seen_in_progress: bool,
+}
+
+/// What a polled [`FirmwareDownloadStateResult`] means for the wait, see [`DownloadWatch::observe`].
+#[derive(Debug, PartialEq)]
+enum DownloadPoll {
+    Downloaded,
+    Pending,
+    NotInProgress,
+}
+
+impl DownloadWatch {
+    fn new() -> Self {
+        Self {
+            polls: 0,
+            seen_in_progress: false,
+        }
+    }
+
+    /// Returns whether the wait is over after the poll that returned `state`.
+    /// No update in progress is tolerated during the first [`DOWNLOAD_START_POLLS`] polls,
+    /// unless the download has already been seen in progress.
+    fn observe(&mut self, state: &FirmwareDownloadStateResult) -> DownloadPoll {
+        self.polls += 1;
+
+        if state.is_downloaded() {
+            DownloadPoll::Downloaded
+        } else if state.is_in_progress() {
+            self.seen_in_progress = true;
+            DownloadPoll::Pending
+        } else if self.seen_in_progress || self.polls >= DOWNLOAD_START_POLLS {
+            DownloadPoll::NotInProgress
+        } else {
+            DownloadPoll::Pending
+        }
+    }
+}
+
+/// Overwrites the top-level fields of `target` with the ones of `changes`.
+fn merge(target: &mut serde_json::Value, changes: serde_json::Value) {
+    if let (Some(target), serde_json::Value::Object(changes)) = (target.as_object_mut(), changes) {
+        target.extend(changes);
+    }
+}
+
 /// Child device handler builders.
 impl HubHandler {
     /// Returns a [`S200BHandler`] for the given `device_id`.
@@ -266,3 +659,49 @@ impl HubHandler {
         KE100Handler::new(self, device_id.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(status: u8, download_progress: u8) -> FirmwareDownloadStateResult {
+        FirmwareDownloadStateResult {
+            status,
+            download_progress,
+            reboot_time: 0,
+            upgrade_time: 0,
+            auto_upgrade: false,
+        }
+    }
+
+    #[test]
+    fn download_that_starts_late_is_waited_for() {
+        let mut download = DownloadWatch::new();
+
+        assert_eq!(download.observe(&state(0, 0)), DownloadPoll::Pending);
+        assert_eq!(download.observe(&state(2, 40)), DownloadPoll::Pending);
+        assert_eq!(download.observe(&state(2, 100)), DownloadPoll::Downloaded);
+    }
+
+    #[test]
+    fn download_that_never_starts_fails() {
+        let mut download = DownloadWatch::new();
+
+        for _ in 1..DOWNLOAD_START_POLLS {
+            assert_eq!(download.observe(&state(0, 0)), DownloadPoll::Pending);
+        }
+        assert_eq!(download.observe(&state(0, 0)), DownloadPoll::NotInProgress);
+    }
+
+    #[test]
+    fn download_that_stops_fails() {
+        let mut download = DownloadWatch::new();
+
+        assert_eq!(download.observe(&state(2, 40)), DownloadPoll::Pending);
+        assert_eq!(
+            download.observe(&state(0, 40)),
+            DownloadPoll::NotInProgress,
+            "the download has failed once it has been seen in progress"
+        );
+    }
+}
@@ -1,6 +1,12 @@
-use crate::api::ApiClient;
+use std::time::Duration;
+
+use log::warn;
+
+use crate::api::{impl_handler_methods, impl_switch_methods, ApiClient};
 use crate::error::Error;
-use crate::requests::LightSetDeviceInfoParams;
+use crate::requests::{
+    adjusted_brightness, Brightness, IntoLightProperty, LightPreset, LightSetDeviceInfoParams,
+};
 use crate::responses::{DeviceInfoLightResult, DeviceUsageEnergyMonitoringResult};
 
 /// Handler for the [L510](https://www.tapo.com/en/search/?q=L510), [L520](https://www.tapo.com/en/search/?q=L520)
@@ -9,17 +15,14 @@ pub struct LightHandler {
     client: ApiClient,
 }
 
+impl_handler_methods!(LightHandler);
+impl_switch_methods!(LightHandler, |device_info| Some(device_info.device_on));
+
 impl LightHandler {
     pub(crate) fn new(client: ApiClient) -> Self {
         Self { client }
     }
 
-    /// Refreshes the authentication session.
-    pub async fn refresh_session(&mut self) -> Result<&mut Self, Error> {
-        self.client.refresh_session().await?;
-        Ok(self)
-    }
-
     /// Turns *on* the device.
     pub async fn on(&self) -> Result<(), Error> {
         LightSetDeviceInfoParams::new(&self.client)
@@ -36,6 +39,42 @@ impl LightHandler {
             .await
     }
 
+    /// Flashes the light `times` times, `interval` apart, to find it among others, e.g. while installing it.
+    /// The light is left *on* or *off*, as it was before, even if one of the flashes fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `times` - how many times the light is flashed
+    /// * `interval` - how long the light stays switched, and then back, at every flash
+    pub async fn identify(&self, times: u32, interval: Duration) -> Result<(), Error> {
+        let device_on = self.get_device_info().await?.device_on;
+
+        let flashing = async {
+            for time in 0..times {
+                if time > 0 {
+                    self.client.runtime().sleep(interval).await;
+                }
+                self.set_toggled(!device_on).await?;
+                self.client.runtime().sleep(interval).await;
+                self.set_toggled(device_on).await?;
+            }
+
+            Ok::<_, Error>(())
+        };
+
+        if let Err(err) = flashing.await {
+            if let Err(restore_err) = self.set_toggled(device_on).await {
+                warn!(
+                    "Failed to turn the light back {}: {restore_err:?}",
+                    if device_on { "on" } else { "off" }
+                );
+            }
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
     /// Returns *device info* as [`DeviceInfoLightResult`].
     /// It is not guaranteed to contain all the properties returned from the Tapo API.
     /// If the deserialization fails, or if a property that you care about it's not present, try [`LightHandler::get_device_info_json`].
@@ -43,10 +82,13 @@ impl LightHandler {
         self.client.get_device_info().await
     }
 
-    /// Returns *device info* as [`serde_json::Value`].
-    /// It contains all the properties returned from the Tapo API.
-    pub async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
-        self.client.get_device_info().await
+    /// Returns the last known *device info*, without a request to the device:
+    /// the result of the last `get_device_info`, updated with the changes made since
+    /// by this handler and its clones.
+    /// Useful for reflecting a change immediately, e.g. in a UI.
+    /// Returns `None` if `get_device_info` hasn't been called yet.
+    pub fn last_known_state(&self) -> Option<DeviceInfoLightResult> {
+        self.client.last_known_device_info()
     }
 
     /// Returns *device usage* as [`DeviceUsageEnergyMonitoringResult`].
@@ -54,15 +96,85 @@ impl LightHandler {
         self.client.get_device_usage().await
     }
 
+    /// Resets the *device usage* counters (time usage and, where reported, power usage and saved power),
+    /// e.g. after replacing a bulb or a filter when tracking its lifetime.
+    /// Returns [`Error::NotSupported`] if the firmware of the device doesn't implement it.
+    pub async fn reset_usage_counters(&self) -> Result<(), Error> {
+        self.client.reset_device_usage().await
+    }
+
+    /// Returns the *presets* stored on the device, in the order shown by the Tapo app.
+    /// Returns [`Error::NotSupported`] if the device doesn't implement them.
+    pub async fn get_presets(&self) -> Result<Vec<LightPreset>, Error> {
+        self.client.get_light_presets().await
+    }
+
+    /// Replaces the *preset* in the given slot.
+    /// Returns [`Error::NotSupported`] if the device doesn't implement presets,
+    /// or the colors of `preset`.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - the slot of the preset, as returned by [`Self::get_presets`]
+    /// * `preset` - the new preset, e.g. [`LightPreset::brightness`]
+    pub async fn set_preset(&self, index: usize, preset: LightPreset) -> Result<(), Error> {
+        self.client.set_light_preset(index, preset).await
+    }
+
     /// Sets the *brightness* and turns *on* the device.
     ///
     /// # Arguments
     ///
-    /// * `brightness` - between 1 and 100
-    pub async fn set_brightness(&self, brightness: u8) -> Result<(), Error> {
+    /// * `brightness` - between 1 and 100, see [`Brightness::RANGE`]
+    pub async fn set_brightness(
+        &self,
+        brightness: impl IntoLightProperty<Brightness>,
+    ) -> Result<(), Error> {
         LightSetDeviceInfoParams::new(&self.client)
-            .brightness(brightness)
+            .brightness(brightness.into_raw())
             .send()
             .await
     }
+
+    /// Sets the *brightness* and turns *on* the device, unless the *device info* reports
+    /// that it's already *on* with the same *brightness*.
+    /// Returns whether the *brightness* has been set.
+    ///
+    /// # Arguments
+    ///
+    /// * `brightness` - between 1 and 100, see [`Brightness::RANGE`]
+    pub async fn ensure_brightness(
+        &self,
+        brightness: impl IntoLightProperty<Brightness>,
+    ) -> Result<bool, Error> {
+        let brightness = brightness.into_raw();
+        let device_info = self.get_device_info().await?;
+        if device_info.device_on && device_info.brightness == brightness {
+            return Ok(false);
+        }
+
+        self.set_brightness(brightness).await?;
+        Ok(true)
+    }
+
+    /// Changes the *brightness* by `delta` percentage points, clamped between 1 and 100, and turns *on* the device,
+    /// e.g. for the steps of a rotary dimmer or a keyboard shortcut. Returns the new *brightness*.
+    ///
+    /// The firmware has no method for relative changes, so the current *brightness* is read from the *device info* first
+    /// and concurrent adjustments can overwrite each other.
+    /// No request is sent if the device is already *on* with the clamped *brightness*.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - the change of the *brightness*, negative to dim the light
+    pub async fn adjust_brightness(&self, delta: i8) -> Result<u8, Error> {
+        let device_info = self.get_device_info().await?;
+        let brightness = adjusted_brightness(device_info.brightness, delta);
+
+        if !(device_info.device_on && device_info.brightness == brightness) {
+            self.set_brightness(brightness).await?;
+        }
+
+        Ok(brightness)
+    }
 }
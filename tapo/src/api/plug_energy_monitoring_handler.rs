@@ -1,9 +1,14 @@
-use crate::api::{ApiClient, ApiClientExt};
+use std::time::Duration;
+
+use chrono::NaiveDate;
+
+use crate::api::{impl_handler_methods, impl_switch_methods, ApiClient, ApiClientExt};
 use crate::error::Error;
+use crate::report::EnergyReport;
 use crate::requests::{EnergyDataInterval, GenericSetDeviceInfoParams};
 use crate::responses::{
-    CurrentPowerResult, DeviceInfoPlugResult, DeviceUsageEnergyMonitoringResult, EnergyDataResult,
-    EnergyUsageResult,
+    CurrentPowerResult, DeviceInfoPlugResult, DeviceUsageEnergyMonitoringResult, EmeterDataResult,
+    EnergyDataResult, EnergyUsageResult,
 };
 
 /// Handler for the [P110](https://www.tapo.com/en/search/?q=P110) & [P115](https://www.tapo.com/en/search/?q=P115) devices.
@@ -11,17 +16,16 @@ pub struct PlugEnergyMonitoringHandler {
     client: ApiClient,
 }
 
+impl_handler_methods!(PlugEnergyMonitoringHandler);
+impl_switch_methods!(PlugEnergyMonitoringHandler, |device_info| Some(
+    device_info.device_on
+));
+
 impl PlugEnergyMonitoringHandler {
     pub(crate) fn new(client: ApiClient) -> Self {
         Self { client }
     }
 
-    /// Refreshes the authentication session.
-    pub async fn refresh_session(&mut self) -> Result<&mut Self, Error> {
-        self.client.refresh_session().await?;
-        Ok(self)
-    }
-
     /// Turns *on* the device.
     pub async fn on(&self) -> Result<(), Error> {
         let json = serde_json::to_value(GenericSetDeviceInfoParams::device_on(true)?)?;
@@ -34,6 +38,19 @@ impl PlugEnergyMonitoringHandler {
         self.client.set_device_info(json).await
     }
 
+    /// Flashes the status LED `times` times, `interval` apart, to find the device among others, e.g. while installing it.
+    /// Unlike turning the plug *on* and *off*, this doesn't interrupt what it powers.
+    /// The LED settings are restored afterwards.
+    /// Returns [`Error::NotSupported`] if the device has no controllable LED.
+    ///
+    /// # Arguments
+    ///
+    /// * `times` - how many times the LED is flashed
+    /// * `interval` - how long the LED stays switched, and then back, at every flash
+    pub async fn identify(&self, times: u32, interval: Duration) -> Result<(), Error> {
+        self.client.flash_led(times, interval).await
+    }
+
     /// Returns *device info* as [`DeviceInfoPlugResult`].
     /// It is not guaranteed to contain all the properties returned from the Tapo API.
     /// If the deserialization fails, or if a property that you care about it's not present, try [`PlugEnergyMonitoringHandler::get_device_info_json`].
@@ -41,10 +58,13 @@ impl PlugEnergyMonitoringHandler {
         self.client.get_device_info().await
     }
 
-    /// Returns *device info* as [`serde_json::Value`].
-    /// It contains all the properties returned from the Tapo API.
-    pub async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
-        self.client.get_device_info().await
+    /// Returns the last known *device info*, without a request to the device:
+    /// the result of the last `get_device_info`, updated with the changes made since
+    /// by this handler and its clones.
+    /// Useful for reflecting a change immediately, e.g. in a UI.
+    /// Returns `None` if `get_device_info` hasn't been called yet.
+    pub fn last_known_state(&self) -> Option<DeviceInfoPlugResult> {
+        self.client.last_known_device_info()
     }
 
     /// Returns *device usage* as [`DeviceUsageEnergyMonitoringResult`].
@@ -52,6 +72,30 @@ impl PlugEnergyMonitoringHandler {
         self.client.get_device_usage().await
     }
 
+    /// Resets the *device usage* counters (time usage and, where reported, power usage and saved power),
+    /// e.g. after replacing a bulb or a filter when tracking its lifetime.
+    /// Returns [`Error::NotSupported`] if the firmware of the device doesn't implement it.
+    pub async fn reset_usage_counters(&self) -> Result<(), Error> {
+        self.client.reset_device_usage().await
+    }
+
+    /// Returns `true` if *child protection* is enabled, i.e. the physical button of the device is disabled.
+    /// Returns [`Error::NotSupported`] if the device doesn't implement it.
+    pub async fn get_child_protection(&self) -> Result<bool, Error> {
+        self.client.get_child_protection().await
+    }
+
+    /// Enables or disables *child protection*. While enabled, the physical button of the device
+    /// doesn't turn it on or off, which is useful for childproofing and kiosk scenarios.
+    /// Returns [`Error::NotSupported`] if the device doesn't implement it.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - true/false
+    pub async fn set_child_protection(&self, enabled: bool) -> Result<(), Error> {
+        self.client.set_child_protection(enabled).await
+    }
+
     /// Returns *energy usage* as [`EnergyUsageResult`].
     pub async fn get_energy_usage(&self) -> Result<EnergyUsageResult, Error> {
         self.client.get_energy_usage().await
@@ -65,8 +109,114 @@ impl PlugEnergyMonitoringHandler {
         self.client.get_energy_data(interval).await
     }
 
+    /// Returns the energy used between `start_date` and `end_date`, inclusive, as [`EnergyReport`].
+    /// Fetches the daily and monthly *energy data* of every quarter and year overlapping the dates,
+    /// see [`crate::report`].
+    ///
+    /// # Arguments
+    ///
+    /// * `start_date` - the first day of the report
+    /// * `end_date` - the last day of the report, inclusive
+    pub async fn get_energy_report(
+        &self,
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<EnergyReport, Error> {
+        let mut results = Vec::new();
+        for interval in EnergyReport::intervals(start_date, end_date)? {
+            results.push(self.client.get_energy_data(interval).await?);
+        }
+
+        EnergyReport::from_energy_data(start_date, end_date, &results)
+    }
+
     /// Returns *current power* as [`CurrentPowerResult`].
     pub async fn get_current_power(&self) -> Result<CurrentPowerResult, Error> {
         self.client.get_current_power().await
     }
+
+    /// Returns the *voltage*, *current* and *power* readings as [`EmeterDataResult`],
+    /// e.g. to compute the power factor with [`EmeterDataResult::power_factor`].
+    /// Returns [`Error::NotSupported`] if the firmware of the device doesn't report them,
+    /// which is the case for most P110 and P115 variants.
+    pub async fn get_emeter_data(&self) -> Result<EmeterDataResult, Error> {
+        self.client.get_emeter_data().await
+    }
+}
+
+#[cfg(all(test, feature = "simulator"))]
+mod tests {
+    use serde_json::json;
+
+    use crate::simulator::connected_plug;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn ensure_skips_satisfied_states() {
+        let (_fleet, device) = connected_plug().await;
+        device.off().await.unwrap();
+
+        assert!(device.ensure_on().await.unwrap());
+        assert!(!device.ensure_on().await.unwrap());
+        assert!(device.get_device_info().await.unwrap().device_on);
+        assert!(device.ensure_off().await.unwrap());
+        assert!(!device.ensure_off().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn toggle_inverts_the_state() {
+        let (_fleet, device) = connected_plug().await;
+        assert!(!device.toggle().await.unwrap());
+        assert!(device.toggle_optimistic().await.unwrap());
+        assert!(!device.toggle_optimistic().await.unwrap());
+        assert!(!device.get_device_info().await.unwrap().device_on);
+    }
+
+    #[tokio::test]
+    async fn identify_restores_the_led() {
+        let (_fleet, device) = connected_plug().await;
+        device.identify(2, Duration::from_millis(10)).await.unwrap();
+
+        let led_info = device.send_raw("get_led_info", json!({})).await.unwrap();
+        assert_eq!(led_info["led_rule"], "always");
+        assert!(device.get_device_info().await.unwrap().device_on);
+    }
+
+    #[tokio::test]
+    async fn last_known_state_is_updated_optimistically() {
+        let (_fleet, device) = connected_plug().await;
+        assert!(device.last_known_state().is_none());
+
+        device.on().await.unwrap();
+        device.get_device_info().await.unwrap();
+        device.clone().off().await.unwrap();
+
+        let state = device.last_known_state().unwrap();
+        assert!(!state.device_on);
+        assert_eq!(state.nickname, "Simulated Plug 1");
+    }
+
+    #[tokio::test]
+    async fn child_protection_is_configurable() {
+        let (_fleet, device) = connected_plug().await;
+        assert!(!device.get_child_protection().await.unwrap());
+
+        device.set_child_protection(true).await.unwrap();
+        assert!(device.get_child_protection().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn unsupported_methods_are_reported() {
+        let (_fleet, device) = connected_plug().await;
+
+        assert!(matches!(
+            device.reset_usage_counters().await,
+            Err(crate::Error::NotSupported { .. })
+        ));
+        assert!(matches!(
+            device.get_emeter_data().await,
+            Err(crate::Error::NotSupported { .. })
+        ));
+    }
 }
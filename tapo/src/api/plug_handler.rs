@@ -1,4 +1,6 @@
-use crate::api::{ApiClient, ApiClientExt};
+use std::time::Duration;
+
+use crate::api::{impl_handler_methods, impl_switch_methods, ApiClient, ApiClientExt};
 use crate::error::Error;
 use crate::requests::GenericSetDeviceInfoParams;
 use crate::responses::{DeviceInfoPlugResult, DeviceUsageResult};
@@ -8,17 +10,14 @@ pub struct PlugHandler {
     client: ApiClient,
 }
 
+impl_handler_methods!(PlugHandler);
+impl_switch_methods!(PlugHandler, |device_info| Some(device_info.device_on));
+
 impl PlugHandler {
     pub(crate) fn new(client: ApiClient) -> Self {
         Self { client }
     }
 
-    /// Refreshes the authentication session.
-    pub async fn refresh_session(&mut self) -> Result<&mut Self, Error> {
-        self.client.refresh_session().await?;
-        Ok(self)
-    }
-
     /// Turns *on* the device.
     pub async fn on(&self) -> Result<(), Error> {
         let json = serde_json::to_value(GenericSetDeviceInfoParams::device_on(true)?)?;
@@ -31,6 +30,19 @@ impl PlugHandler {
         self.client.set_device_info(json).await
     }
 
+    /// Flashes the status LED `times` times, `interval` apart, to find the device among others, e.g. while installing it.
+    /// Unlike turning the plug *on* and *off*, this doesn't interrupt what it powers.
+    /// The LED settings are restored afterwards.
+    /// Returns [`Error::NotSupported`] if the device has no controllable LED.
+    ///
+    /// # Arguments
+    ///
+    /// * `times` - how many times the LED is flashed
+    /// * `interval` - how long the LED stays switched, and then back, at every flash
+    pub async fn identify(&self, times: u32, interval: Duration) -> Result<(), Error> {
+        self.client.flash_led(times, interval).await
+    }
+
     /// Returns *device info* as [`DeviceInfoPlugResult`].
     /// It is not guaranteed to contain all the properties returned from the Tapo API.
     /// If the deserialization fails, or if a property that you care about it's not present, try [`PlugHandler::get_device_info_json`].
@@ -38,14 +50,41 @@ impl PlugHandler {
         self.client.get_device_info().await
     }
 
-    /// Returns *device info* as [`serde_json::Value`].
-    /// It contains all the properties returned from the Tapo API.
-    pub async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
-        self.client.get_device_info().await
+    /// Returns the last known *device info*, without a request to the device:
+    /// the result of the last `get_device_info`, updated with the changes made since
+    /// by this handler and its clones.
+    /// Useful for reflecting a change immediately, e.g. in a UI.
+    /// Returns `None` if `get_device_info` hasn't been called yet.
+    pub fn last_known_state(&self) -> Option<DeviceInfoPlugResult> {
+        self.client.last_known_device_info()
     }
 
     /// Returns *device usage* as [`DeviceUsageResult`].
     pub async fn get_device_usage(&self) -> Result<DeviceUsageResult, Error> {
         self.client.get_device_usage().await
     }
+
+    /// Resets the *device usage* counters (time usage and, where reported, power usage and saved power),
+    /// e.g. after replacing a bulb or a filter when tracking its lifetime.
+    /// Returns [`Error::NotSupported`] if the firmware of the device doesn't implement it.
+    pub async fn reset_usage_counters(&self) -> Result<(), Error> {
+        self.client.reset_device_usage().await
+    }
+
+    /// Returns `true` if *child protection* is enabled, i.e. the physical button of the device is disabled.
+    /// Returns [`Error::NotSupported`] if the device doesn't implement it.
+    pub async fn get_child_protection(&self) -> Result<bool, Error> {
+        self.client.get_child_protection().await
+    }
+
+    /// Enables or disables *child protection*. While enabled, the physical button of the device
+    /// doesn't turn it on or off, which is useful for childproofing and kiosk scenarios.
+    /// Returns [`Error::NotSupported`] if the device doesn't implement it.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - true/false
+    pub async fn set_child_protection(&self, enabled: bool) -> Result<(), Error> {
+        self.client.set_child_protection(enabled).await
+    }
 }
@@ -1,3 +1,8 @@
+//! The transport protocols used to talk to the devices.
+
+pub mod codec;
+
+mod capture;
 mod discovery_protocol;
 mod klap_cipher;
 mod klap_protocol;
@@ -5,4 +10,6 @@ mod passthrough_cipher;
 mod passthrough_protocol;
 mod tapo_protocol;
 
+pub use capture::*;
+
 pub(crate) use tapo_protocol::*;
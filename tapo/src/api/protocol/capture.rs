@@ -0,0 +1,628 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use chrono::{DateTime, Utc};
+use log::debug;
+
+use crate::{Error, TapoResponseError};
+
+use super::codec::{klap_auth_hash, klap_server_hash, KlapCodec};
+
+const PCAPNG_MAGIC: u32 = 0x0a0d_0d0a;
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const IP_PROTOCOL_TCP: u8 = 6;
+const TCP_FLAG_SYN: u8 = 0x02;
+const SESSION_COOKIE: &str = "TP_SESSIONID";
+
+/// A KLAP request and its response, decrypted from a packet capture by [`decrypt_capture`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapturedExchange {
+    /// When the request was captured.
+    pub timestamp: DateTime<Utc>,
+    /// The IP address of the device.
+    pub device: Ipv4Addr,
+    /// The sequence number of the request.
+    pub seq: i32,
+    /// The decrypted JSON request.
+    pub request: String,
+    /// The decrypted JSON response, if it was captured and could be decrypted.
+    pub response: Option<String>,
+}
+
+/// Reconstructs and decrypts the KLAP exchanges of a packet capture, for debugging device behavior,
+/// reverse-engineering firmware methods that aren't supported yet and reporting firmware quirks.
+///
+/// The capture must be in the pcap format (e.g. `tcpdump -w` or `editcap -F pcap` for pcapng captures)
+/// and must contain the handshakes of the sessions, whose keys are derived from the seeds exchanged in them.
+/// Exchanges of sessions that were established before the capture started,
+/// or with devices that have been set up with other credentials, are skipped.
+///
+/// # Arguments
+///
+/// * `capture` - the content of the pcap file
+/// * `tapo_username` - the Tapo username the devices have been set up with
+/// * `tapo_password` - the Tapo password the devices have been set up with
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let capture = std::fs::read("tapo.pcap")?;
+///
+/// for exchange in tapo::protocol::decrypt_capture(&capture, "tapo-username", "tapo-password")? {
+///     println!("{} {}: {}", exchange.device, exchange.seq, exchange.request);
+///     println!("{:?}", exchange.response);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn decrypt_capture(
+    capture: &[u8],
+    tapo_username: &str,
+    tapo_password: &str,
+) -> Result<Vec<CapturedExchange>, Error> {
+    let packets = read_packets(capture)?;
+    let http_exchanges = reassemble_http_exchanges(packets);
+
+    let auth_hash = klap_auth_hash(tapo_username, tapo_password);
+    let mut sessions = HashMap::new();
+    let mut rejected_handshakes = 0;
+    let mut exchanges = Vec::new();
+
+    for exchange in http_exchanges {
+        let HttpExchange {
+            device,
+            request,
+            response,
+        } = exchange;
+        let (path, query) = request
+            .target
+            .split_once('?')
+            .unwrap_or((request.target.as_str(), ""));
+
+        match path {
+            "/app/handshake1" => {
+                let Some(response) = response else {
+                    continue;
+                };
+                if response.body.len() != 48 {
+                    continue;
+                }
+
+                let (remote_seed, server_hash) = response.body.split_at(16);
+                if klap_server_hash(&request.body, remote_seed, &auth_hash) != server_hash {
+                    debug!("Skipping the session with {device}: the credentials don't match");
+                    rejected_handshakes += 1;
+                    continue;
+                }
+
+                if let Some(session_id) = response.session_id("set-cookie") {
+                    let codec = KlapCodec::new(&request.body, remote_seed, &auth_hash);
+                    sessions.insert((device, session_id), codec);
+                }
+            }
+            "/app/request" => {
+                let Some(seq) = query
+                    .strip_prefix("seq=")
+                    .and_then(|seq| seq.parse::<i32>().ok())
+                else {
+                    continue;
+                };
+                let Some(codec) = request
+                    .session_id("cookie")
+                    .and_then(|session_id| sessions.get(&(device, session_id)))
+                else {
+                    debug!("Skipping request {seq} to {device}: its handshake wasn't captured");
+                    continue;
+                };
+
+                let Ok(decrypted_request) = codec.decrypt(seq, &request.body) else {
+                    debug!("Skipping request {seq} to {device}: it couldn't be decrypted");
+                    continue;
+                };
+                let decrypted_response = response
+                    .filter(|response| response.status == Some(200))
+                    .and_then(|response| codec.decrypt(seq, &response.body).ok());
+
+                exchanges.push(CapturedExchange {
+                    timestamp: request.timestamp,
+                    device,
+                    seq,
+                    request: decrypted_request,
+                    response: decrypted_response,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    if sessions.is_empty() && rejected_handshakes > 0 {
+        return Err(Error::Tapo(TapoResponseError::InvalidCredentials));
+    }
+
+    Ok(exchanges)
+}
+
+#[derive(Debug)]
+struct Packet<'a> {
+    timestamp: DateTime<Utc>,
+    src: SocketAddrV4,
+    dst: SocketAddrV4,
+    seq: u32,
+    syn: bool,
+    payload: &'a [u8],
+}
+
+fn read_packets(capture: &[u8]) -> Result<Vec<Packet<'_>>, Error> {
+    let header = capture
+        .get(..24)
+        .ok_or_else(|| invalid_capture("truncated header"))?;
+    let magic = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+
+    let (little_endian, nanoseconds) = match magic {
+        0xa1b2_c3d4 => (true, false),
+        0xd4c3_b2a1 => (false, false),
+        0xa1b2_3c4d => (true, true),
+        0x4d3c_b2a1 => (false, true),
+        PCAPNG_MAGIC => {
+            return Err(invalid_capture(
+                "pcapng captures aren't supported, convert them with `editcap -F pcap`",
+            ))
+        }
+        _ => return Err(invalid_capture("not a pcap file")),
+    };
+    let read_u32 = |bytes: &[u8]| {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    };
+    let link_type = read_u32(&header[20..24]);
+
+    let mut packets = Vec::new();
+    let mut rest = &capture[24..];
+
+    while rest.len() >= 16 {
+        let seconds = read_u32(&rest[0..4]);
+        let fraction = read_u32(&rest[4..8]);
+        let captured_length = read_u32(&rest[8..12]) as usize;
+        let data = rest
+            .get(16..16 + captured_length)
+            .ok_or_else(|| invalid_capture("truncated packet"))?;
+        rest = &rest[16 + captured_length..];
+
+        let nanoseconds = if nanoseconds {
+            fraction
+        } else {
+            fraction.saturating_mul(1000)
+        };
+        let Some(timestamp) = DateTime::from_timestamp(seconds.into(), nanoseconds) else {
+            continue;
+        };
+
+        if let Some(packet) = parse_link_layer(link_type, data, timestamp) {
+            packets.push(packet);
+        }
+    }
+
+    Ok(packets)
+}
+
+fn parse_link_layer(link_type: u32, data: &[u8], timestamp: DateTime<Utc>) -> Option<Packet<'_>> {
+    let ip = match link_type {
+        LINKTYPE_NULL => data.get(4..)?,
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            let mut ether_type = read_u16(data, offset)?;
+            while ether_type == ETHERTYPE_VLAN {
+                offset += 4;
+                ether_type = read_u16(data, offset)?;
+            }
+            (ether_type == ETHERTYPE_IPV4).then_some(())?;
+            data.get(offset + 2..)?
+        }
+        LINKTYPE_RAW | LINKTYPE_IPV4 => data,
+        LINKTYPE_LINUX_SLL => {
+            (read_u16(data, 14)? == ETHERTYPE_IPV4).then_some(())?;
+            data.get(16..)?
+        }
+        LINKTYPE_LINUX_SLL2 => {
+            (read_u16(data, 0)? == ETHERTYPE_IPV4).then_some(())?;
+            data.get(20..)?
+        }
+        _ => return None,
+    };
+
+    parse_ipv4(ip, timestamp)
+}
+
+fn parse_ipv4(ip: &[u8], timestamp: DateTime<Utc>) -> Option<Packet<'_>> {
+    let version_ihl = *ip.first()?;
+    if version_ihl >> 4 != 4 || *ip.get(9)? != IP_PROTOCOL_TCP {
+        return None;
+    }
+
+    let header_length = usize::from(version_ihl & 0x0f) * 4;
+    let total_length = usize::from(read_u16(ip, 2)?).min(ip.len());
+    let src = Ipv4Addr::from(<[u8; 4]>::try_from(ip.get(12..16)?).ok()?);
+    let dst = Ipv4Addr::from(<[u8; 4]>::try_from(ip.get(16..20)?).ok()?);
+    let tcp = ip.get(header_length..total_length)?;
+
+    let data_offset = usize::from(*tcp.get(12)? >> 4) * 4;
+
+    Some(Packet {
+        timestamp,
+        src: SocketAddrV4::new(src, read_u16(tcp, 0)?),
+        dst: SocketAddrV4::new(dst, read_u16(tcp, 2)?),
+        seq: u32::from_be_bytes(tcp.get(4..8)?.try_into().ok()?),
+        syn: tcp.get(13)? & TCP_FLAG_SYN != 0,
+        payload: tcp.get(data_offset..)?,
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+/// One direction of a TCP connection.
+#[derive(Debug, Default)]
+struct Stream<'a> {
+    initial_seq: Option<u32>,
+    segments: Vec<(u32, DateTime<Utc>, &'a [u8])>,
+}
+
+impl Stream<'_> {
+    /// Returns the payload in order, up to the first missing segment,
+    /// together with the offsets at which each segment starts.
+    fn reassemble(mut self) -> (Vec<u8>, Vec<(usize, DateTime<Utc>)>) {
+        let initial_seq = self.initial_seq.unwrap_or_else(|| {
+            let first_seq = self
+                .segments
+                .first()
+                .map(|(seq, _, _)| *seq)
+                .unwrap_or_default();
+            self.segments
+                .iter()
+                .map(|(seq, _, _)| *seq)
+                .min_by_key(|seq| seq.wrapping_sub(first_seq) as i32)
+                .unwrap_or_default()
+        });
+        self.segments
+            .sort_by_key(|(seq, _, _)| seq.wrapping_sub(initial_seq));
+
+        let mut data = Vec::new();
+        let mut timestamps = Vec::new();
+
+        for (seq, timestamp, payload) in self.segments {
+            let offset = seq.wrapping_sub(initial_seq) as usize;
+            if offset > data.len() {
+                break;
+            }
+
+            let skip = data.len() - offset;
+            if skip < payload.len() {
+                timestamps.push((data.len(), timestamp));
+                data.extend_from_slice(&payload[skip..]);
+            }
+        }
+
+        (data, timestamps)
+    }
+}
+
+#[derive(Debug)]
+struct HttpMessage {
+    timestamp: DateTime<Utc>,
+    /// The request target, empty for responses.
+    target: String,
+    /// The status code, `None` for requests.
+    status: Option<u16>,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpMessage {
+    fn session_id(&self, header: &str) -> Option<String> {
+        self.headers
+            .iter()
+            .filter(|(name, _)| name == header)
+            .flat_map(|(_, value)| value.split(';'))
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .find(|(name, _)| *name == SESSION_COOKIE)
+            .map(|(_, value)| value.to_string())
+    }
+}
+
+#[derive(Debug)]
+struct HttpExchange {
+    device: Ipv4Addr,
+    request: HttpMessage,
+    response: Option<HttpMessage>,
+}
+
+/// Reassembles the TCP connections and pairs each HTTP request with its response, ordered by time.
+fn reassemble_http_exchanges(packets: Vec<Packet>) -> Vec<HttpExchange> {
+    let mut streams: HashMap<(SocketAddrV4, SocketAddrV4), Vec<Stream>> = HashMap::new();
+
+    for packet in packets {
+        let connection = streams.entry((packet.src, packet.dst)).or_default();
+
+        if packet.syn {
+            connection.push(Stream {
+                initial_seq: Some(packet.seq.wrapping_add(1)),
+                segments: Vec::new(),
+            });
+        } else if !packet.payload.is_empty() {
+            if connection.is_empty() {
+                connection.push(Stream::default());
+            }
+            if let Some(stream) = connection.last_mut() {
+                stream
+                    .segments
+                    .push((packet.seq, packet.timestamp, packet.payload));
+            }
+        }
+    }
+
+    let mut messages: HashMap<_, Vec<Vec<HttpMessage>>> = streams
+        .into_iter()
+        .map(|(key, connection)| {
+            let connection = connection
+                .into_iter()
+                .map(|stream| {
+                    let (data, timestamps) = stream.reassemble();
+                    parse_http_messages(&data, &timestamps)
+                })
+                .collect();
+            (key, connection)
+        })
+        .collect();
+
+    let mut exchanges = Vec::new();
+    let keys: Vec<_> = messages.keys().copied().collect();
+
+    for (src, dst) in keys {
+        // The key has already been removed when visiting the client side of the connection.
+        let is_client = messages.get(&(src, dst)).is_some_and(|connection| {
+            connection
+                .iter()
+                .flatten()
+                .next()
+                .is_some_and(|message| message.status.is_none())
+        });
+        if !is_client {
+            continue;
+        }
+
+        let requests = messages.remove(&(src, dst)).unwrap_or_default();
+        let mut responses = messages.remove(&(dst, src)).unwrap_or_default().into_iter();
+
+        for requests in requests {
+            let mut responses = responses.next().unwrap_or_default().into_iter();
+
+            for request in requests {
+                exchanges.push(HttpExchange {
+                    device: *dst.ip(),
+                    request,
+                    response: responses.next(),
+                });
+            }
+        }
+    }
+
+    exchanges.sort_by_key(|exchange| exchange.request.timestamp);
+    exchanges
+}
+
+fn parse_http_messages(data: &[u8], timestamps: &[(usize, DateTime<Utc>)]) -> Vec<HttpMessage> {
+    let mut messages = Vec::new();
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let rest = &data[offset..];
+        let Some(header_length) = rest.windows(4).position(|window| window == b"\r\n\r\n") else {
+            break;
+        };
+        let Ok(head) = std::str::from_utf8(&rest[..header_length]) else {
+            break;
+        };
+
+        let mut lines = head.split("\r\n");
+        let start_line = lines.next().unwrap_or_default();
+        let headers: Vec<_> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+
+        let content_length = headers
+            .iter()
+            .find(|(name, _)| name == "content-length")
+            .and_then(|(_, value)| value.parse::<usize>().ok())
+            .unwrap_or_default();
+        let body_start = header_length + 4;
+        // The length comes from the capture, so it can be anything.
+        let Some(body_end) = body_start.checked_add(content_length) else {
+            break;
+        };
+        let Some(body) = rest.get(body_start..body_end) else {
+            break;
+        };
+
+        let (target, status) = match start_line.strip_prefix("HTTP/") {
+            Some(status_line) => (
+                String::new(),
+                status_line
+                    .split(' ')
+                    .nth(1)
+                    .and_then(|status| status.parse().ok())
+                    .or(Some(0)),
+            ),
+            None => (
+                start_line.split(' ').nth(1).unwrap_or_default().to_string(),
+                None,
+            ),
+        };
+        let timestamp = timestamps
+            .iter()
+            .take_while(|(start, _)| *start <= offset)
+            .last()
+            .map(|(_, timestamp)| *timestamp)
+            .unwrap_or_default();
+
+        messages.push(HttpMessage {
+            timestamp,
+            target,
+            status,
+            headers,
+            body: body.to_vec(),
+        });
+
+        offset += body_end;
+    }
+
+    messages
+}
+
+fn invalid_capture(message: &str) -> Error {
+    Error::Validation {
+        field: "capture".to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENT: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 2), 50000);
+    const DEVICE: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(192, 168, 1, 100), 80);
+
+    fn ethernet_packet(src: SocketAddrV4, dst: SocketAddrV4, seq: u32, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0; 12];
+        packet.extend_from_slice(&ETHERTYPE_IPV4.to_be_bytes());
+
+        let total_length = (20 + 20 + payload.len()) as u16;
+        packet.extend_from_slice(&[0x45, 0]);
+        packet.extend_from_slice(&total_length.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 0, 64, IP_PROTOCOL_TCP, 0, 0]);
+        packet.extend_from_slice(&src.ip().octets());
+        packet.extend_from_slice(&dst.ip().octets());
+
+        packet.extend_from_slice(&src.port().to_be_bytes());
+        packet.extend_from_slice(&dst.port().to_be_bytes());
+        packet.extend_from_slice(&seq.to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 0, 0x50, 0x18, 0, 0, 0, 0, 0, 0]);
+        packet.extend_from_slice(payload);
+
+        packet
+    }
+
+    fn pcap(packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut capture = Vec::new();
+        capture.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+        capture.extend_from_slice(&[2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0]);
+        capture.extend_from_slice(&LINKTYPE_ETHERNET.to_le_bytes());
+
+        for (index, packet) in packets.iter().enumerate() {
+            capture.extend_from_slice(&(1_700_000_000 + index as u32).to_le_bytes());
+            capture.extend_from_slice(&0u32.to_le_bytes());
+            capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            capture.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            capture.extend_from_slice(packet);
+        }
+
+        capture
+    }
+
+    fn http(head: &str, body: &[u8]) -> Vec<u8> {
+        [
+            format!("{head}\r\nContent-Length: {}\r\n\r\n", body.len()).as_bytes(),
+            body,
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn decrypts_klap_exchanges() {
+        let auth_hash = klap_auth_hash("username", "password");
+        let (local_seed, remote_seed) = ([1; 16], [2; 16]);
+        let server_hash = klap_server_hash(&local_seed, &remote_seed, &auth_hash);
+        let codec = KlapCodec::new(&local_seed, &remote_seed, &auth_hash);
+        let seq = codec.initial_seq().wrapping_add(1);
+
+        let handshake1_request = http("POST /app/handshake1 HTTP/1.1", &local_seed);
+        let handshake1_response = http(
+            "HTTP/1.1 200 OK\r\nSet-Cookie: TP_SESSIONID=ABC;TIMEOUT=86400",
+            &[remote_seed.as_slice(), &server_hash].concat(),
+        );
+        let request = http(
+            &format!("POST /app/request?seq={seq} HTTP/1.1\r\nCookie: TP_SESSIONID=ABC"),
+            &codec
+                .encrypt(seq, b"{\"method\":\"get_device_info\"}")
+                .unwrap(),
+        );
+        let response = http(
+            "HTTP/1.1 200 OK",
+            &codec.encrypt(seq, b"{\"error_code\":0}").unwrap(),
+        );
+
+        let request_seq = 1 + handshake1_request.len() as u32;
+        let (request_start, request_end) = request.split_at(40);
+        let capture = pcap(&[
+            ethernet_packet(CLIENT, DEVICE, 1, &handshake1_request),
+            ethernet_packet(DEVICE, CLIENT, 1, &handshake1_response),
+            // Split over two segments, received out of order.
+            ethernet_packet(CLIENT, DEVICE, request_seq + 40, request_end),
+            ethernet_packet(CLIENT, DEVICE, request_seq, request_start),
+            ethernet_packet(
+                DEVICE,
+                CLIENT,
+                1 + handshake1_response.len() as u32,
+                &response,
+            ),
+        ]);
+
+        let exchanges = decrypt_capture(&capture, "username", "password").unwrap();
+
+        assert_eq!(exchanges.len(), 1);
+        assert_eq!(exchanges[0].device, *DEVICE.ip());
+        assert_eq!(exchanges[0].seq, seq);
+        assert_eq!(exchanges[0].request, "{\"method\":\"get_device_info\"}");
+        assert_eq!(exchanges[0].response.as_deref(), Some("{\"error_code\":0}"));
+
+        assert!(matches!(
+            decrypt_capture(&capture, "username", "wrong-password"),
+            Err(Error::Tapo(TapoResponseError::InvalidCredentials))
+        ));
+    }
+
+    #[test]
+    fn oversized_content_length_stops_the_parsing() {
+        let mut data = http("POST /app/request?seq=1 HTTP/1.1", b"a");
+        data.extend_from_slice(b"HTTP/1.1 200 OK\r\nContent-Length: 18446744073709551615\r\n\r\nb");
+
+        let messages = parse_http_messages(&data, &[]);
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].body, b"a");
+    }
+
+    #[test]
+    fn rejects_other_formats() {
+        assert!(matches!(
+            decrypt_capture(b"not a capture at all", "username", "password"),
+            Err(Error::Validation { .. })
+        ));
+    }
+}
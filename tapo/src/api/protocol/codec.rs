@@ -0,0 +1,272 @@
+//! The encryption, decryption and parsing of the messages exchanged with the devices.
+//!
+//! These are pure functions over byte slices, without any I/O, so they can be fuzzed
+//! and used to decode packet captures offline. They never panic, whatever the input.
+
+use base64::{engine::general_purpose, Engine as _};
+use openssl::sha::{Sha1, Sha256};
+use openssl::symm::{decrypt, encrypt, Cipher};
+use serde::de::DeserializeOwned;
+
+use crate::responses::{validate_response, TapoResponse, TapoResponseExt};
+use crate::{Error, ProtocolError};
+
+const KLAP_SIGNATURE_LEN: usize = 32;
+const PASSTHROUGH_KEY_LEN: usize = 16;
+
+/// Returns the hash of the Tapo credentials used by the KLAP handshakes.
+///
+/// # Arguments
+///
+/// * `username` - the Tapo username
+/// * `password` - the Tapo password
+pub fn klap_auth_hash(username: &str, password: &str) -> [u8; 32] {
+    sha256(&[sha1(username.as_bytes()), sha1(password.as_bytes())].concat())
+}
+
+/// Returns the hash sent by the device in response to the first KLAP handshake.
+/// It only matches when the device has been set up with the same credentials.
+pub fn klap_server_hash(local_seed: &[u8], remote_seed: &[u8], auth_hash: &[u8]) -> [u8; 32] {
+    sha256(&[local_seed, remote_seed, auth_hash].concat())
+}
+
+/// Returns the hash sent by the client in the second KLAP handshake.
+pub fn klap_client_hash(local_seed: &[u8], remote_seed: &[u8], auth_hash: &[u8]) -> [u8; 32] {
+    sha256(&[remote_seed, local_seed, auth_hash].concat())
+}
+
+/// The keys of a KLAP session, derived from the seeds exchanged during the handshakes.
+#[derive(Debug, Clone)]
+pub struct KlapCodec {
+    key: Vec<u8>,
+    iv: Vec<u8>,
+    sig: Vec<u8>,
+    initial_seq: i32,
+}
+
+impl KlapCodec {
+    /// Derives the keys of a KLAP session.
+    ///
+    /// # Arguments
+    ///
+    /// * `local_seed` - the seed sent by the client in the first handshake
+    /// * `remote_seed` - the seed sent by the device in response to the first handshake
+    /// * `auth_hash` - the hash of the credentials, see [`klap_auth_hash`]
+    pub fn new(local_seed: &[u8], remote_seed: &[u8], auth_hash: &[u8]) -> Self {
+        let local_hash = [local_seed, remote_seed, auth_hash].concat();
+
+        let iv_hash = sha256(&[b"iv".as_slice(), &local_hash].concat());
+        let mut initial_seq = [0; 4];
+        initial_seq.copy_from_slice(&iv_hash[iv_hash.len() - 4..]);
+
+        Self {
+            key: sha256(&[b"lsk".as_slice(), &local_hash].concat())[..16].to_vec(),
+            iv: iv_hash[..12].to_vec(),
+            sig: sha256(&[b"ldk".as_slice(), &local_hash].concat())[..28].to_vec(),
+            initial_seq: i32::from_be_bytes(initial_seq),
+        }
+    }
+
+    /// Returns the sequence number that precedes the one of the first request of the session.
+    pub fn initial_seq(&self) -> i32 {
+        self.initial_seq
+    }
+
+    /// Encrypts and signs `data` with the given `seq`.
+    /// Both the requests and the responses are encrypted with the `seq` of the request.
+    pub fn encrypt(&self, seq: i32, data: &[u8]) -> Result<Vec<u8>, Error> {
+        let cipher_bytes = encrypt(
+            Cipher::aes_128_cbc(),
+            &self.key,
+            Some(&self.iv_seq(seq)),
+            data,
+        )
+        .map_err(anyhow::Error::from)?;
+
+        let signature = self.signature(seq, &cipher_bytes);
+
+        Ok([signature.as_slice(), &cipher_bytes].concat())
+    }
+
+    /// Decrypts a request or a response with the given `seq`.
+    ///
+    /// The IV is derived from `seq`, but a wrong one only garbles the first block in CBC mode,
+    /// so a stale, out-of-order or retransmitted response could decrypt into almost valid JSON.
+    /// The signature is therefore checked first, and a payload that isn't signed for `seq`
+    /// is reported as [`ProtocolError::SequenceMismatch`] without being decrypted.
+    pub fn decrypt(&self, seq: i32, data: &[u8]) -> Result<String, ProtocolError> {
+        if data.len() <= KLAP_SIGNATURE_LEN {
+            return Err(ProtocolError::TruncatedResponse);
+        }
+
+        let (signature, cipher_bytes) = data.split_at(KLAP_SIGNATURE_LEN);
+        if signature != self.signature(seq, cipher_bytes) {
+            return Err(ProtocolError::SequenceMismatch { expected: seq });
+        }
+
+        decrypt(
+            Cipher::aes_128_cbc(),
+            &self.key,
+            Some(&self.iv_seq(seq)),
+            cipher_bytes,
+        )
+        .ok()
+        .and_then(|decrypted_bytes| String::from_utf8(decrypted_bytes).ok())
+        .ok_or(ProtocolError::DecryptionFailed)
+    }
+
+    fn signature(&self, seq: i32, cipher_bytes: &[u8]) -> [u8; 32] {
+        sha256(&[self.sig.as_slice(), &seq.to_be_bytes(), cipher_bytes].concat())
+    }
+
+    fn iv_seq(&self, seq: i32) -> Vec<u8> {
+        [self.iv.as_slice(), &seq.to_be_bytes()].concat()
+    }
+}
+
+/// The key of a Passthrough session, as decrypted with the RSA private key of the client
+/// from the response to the handshake.
+#[derive(Debug, Clone)]
+pub struct PassthroughCodec {
+    key: Vec<u8>,
+    iv: Vec<u8>,
+}
+
+impl PassthroughCodec {
+    /// Returns the codec of a Passthrough session.
+    ///
+    /// # Arguments
+    ///
+    /// * `session_key` - the 32 bytes decrypted from the handshake key, made of the AES key followed by the IV
+    pub fn new(session_key: &[u8]) -> Result<Self, Error> {
+        if session_key.len() != PASSTHROUGH_KEY_LEN * 2 {
+            return Err(Error::Validation {
+                field: "session_key".to_string(),
+                message: format!(
+                    "Expected {} bytes, got {}",
+                    PASSTHROUGH_KEY_LEN * 2,
+                    session_key.len()
+                ),
+            });
+        }
+
+        let (key, iv) = session_key.split_at(PASSTHROUGH_KEY_LEN);
+
+        Ok(Self {
+            key: key.to_vec(),
+            iv: iv.to_vec(),
+        })
+    }
+
+    /// Encrypts `data` into the base64 string sent in a `securePassthrough` request.
+    pub fn encrypt(&self, data: &str) -> Result<String, Error> {
+        let cipher_bytes = encrypt(
+            Cipher::aes_128_cbc(),
+            &self.key,
+            Some(&self.iv),
+            data.as_bytes(),
+        )
+        .map_err(anyhow::Error::from)?;
+
+        Ok(general_purpose::STANDARD.encode(cipher_bytes))
+    }
+
+    /// Decrypts the base64 string of a `securePassthrough` request or response.
+    pub fn decrypt(&self, data: &str) -> Result<String, ProtocolError> {
+        let cipher_bytes = general_purpose::STANDARD
+            .decode(data)
+            .map_err(|_| ProtocolError::DecryptionFailed)?;
+
+        decrypt(
+            Cipher::aes_128_cbc(),
+            &self.key,
+            Some(&self.iv),
+            &cipher_bytes,
+        )
+        .ok()
+        .and_then(|decrypted_bytes| String::from_utf8(decrypted_bytes).ok())
+        .ok_or(ProtocolError::DecryptionFailed)
+    }
+}
+
+/// Parses a decrypted response and returns its `result`,
+/// or the error corresponding to its `error_code` when it's not 0.
+pub fn parse_response(payload: &str) -> Result<Option<serde_json::Value>, Error> {
+    parse_response_as(payload.to_string())
+}
+
+pub(crate) fn parse_response_as<R>(payload: String) -> Result<Option<R>, Error>
+where
+    R: DeserializeOwned + TapoResponseExt,
+{
+    let response: TapoResponse<R> =
+        serde_json::from_str(&payload).map_err(|source| Error::Deserialization {
+            source,
+            response: payload,
+        })?;
+
+    validate_response(&response)?;
+
+    Ok(response.result)
+}
+
+pub(crate) fn sha256(value: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(value);
+    hasher.finish()
+}
+
+pub(crate) fn sha1(value: &[u8]) -> [u8; 20] {
+    let mut hasher = Sha1::new();
+    hasher.update(value);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TapoResponseError;
+
+    use super::*;
+
+    #[test]
+    fn klap_handshake_hashes_depend_on_the_credentials() {
+        let auth_hash = klap_auth_hash("username", "password");
+
+        assert_ne!(auth_hash, klap_auth_hash("username", "wrong-password"));
+        assert_ne!(
+            klap_server_hash(&[1; 16], &[2; 16], &auth_hash),
+            klap_client_hash(&[1; 16], &[2; 16], &auth_hash)
+        );
+    }
+
+    #[test]
+    fn passthrough_round_trip() {
+        let codec = PassthroughCodec::new(&[7; 32]).unwrap();
+        let encrypted = codec.encrypt("{\"method\":\"get_device_info\"}").unwrap();
+
+        assert_eq!(
+            codec.decrypt(&encrypted).unwrap(),
+            "{\"method\":\"get_device_info\"}"
+        );
+        assert!(matches!(
+            codec.decrypt("not base64"),
+            Err(ProtocolError::DecryptionFailed)
+        ));
+    }
+
+    #[test]
+    fn parse_response_maps_error_codes() {
+        assert_eq!(
+            parse_response("{\"error_code\":0,\"result\":{\"a\":1}}").unwrap(),
+            Some(serde_json::json!({ "a": 1 }))
+        );
+        assert!(matches!(
+            parse_response("{\"error_code\":-1501}"),
+            Err(Error::Tapo(TapoResponseError::InvalidCredentials))
+        ));
+        assert!(matches!(
+            parse_response("{"),
+            Err(Error::Deserialization { .. })
+        ));
+    }
+}
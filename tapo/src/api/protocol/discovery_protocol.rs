@@ -1,18 +1,34 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
 use isahc::{AsyncReadResponseExt, HttpClient, Request};
 use log::debug;
+use tokio::sync::OnceCell;
 
 use crate::api::protocol::klap_protocol::KlapProtocol;
 use crate::requests::{EmptyParams, TapoParams, TapoRequest};
 use crate::responses::{validate_response, TapoResponse};
 use crate::{Error, TapoResponseError};
 
+use super::passthrough_cipher::PassthroughKeyPair;
 use super::{passthrough_protocol::PassthroughProtocol, TapoProtocolType};
 
+/// Clones share the RSA key pair and the protocols discovered so far,
+/// which makes reconnecting to a known device cheaper.
 #[derive(Debug, Clone)]
 pub(crate) struct DiscoveryProtocol {
     client: HttpClient,
     username: String,
     password: String,
+    key_pair: Arc<OnceCell<PassthroughKeyPair>>,
+    known_protocols: Arc<Mutex<HashMap<String, ProtocolKind>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProtocolKind {
+    Passthrough,
+    Klap,
 }
 
 impl DiscoveryProtocol {
@@ -21,20 +37,30 @@ impl DiscoveryProtocol {
             client,
             username,
             password,
+            key_pair: Arc::new(OnceCell::new()),
+            known_protocols: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
+    #[tracing::instrument(level = "debug", skip(self), fields(protocol, elapsed_ms))]
     pub async fn discover(&mut self, url: &str) -> Result<TapoProtocolType, Error> {
-        debug!("Testing the Passthrough protocol...");
-        if self.is_passthrough_supported(url).await? {
-            debug!("Supported. Setting up the Passthrough protocol...");
+        let started = Instant::now();
+        let protocol_kind = self.get_protocol_kind(url).await?;
+
+        let span = tracing::Span::current();
+        span.record("protocol", format!("{protocol_kind:?}"));
+        span.record("elapsed_ms", started.elapsed().as_millis() as u64);
+
+        if protocol_kind == ProtocolKind::Passthrough {
+            debug!("Setting up the Passthrough protocol...");
             Ok(TapoProtocolType::Passthrough(PassthroughProtocol::new(
                 self.client.clone(),
                 self.username.clone(),
                 self.password.clone(),
-            )?))
+                self.get_key_pair().await?,
+            )))
         } else {
-            debug!("Not supported. Setting up the Klap protocol...");
+            debug!("Setting up the Klap protocol...");
             Ok(TapoProtocolType::Klap(KlapProtocol::new(
                 self.client.clone(),
                 self.username.clone(),
@@ -43,6 +69,45 @@ impl DiscoveryProtocol {
         }
     }
 
+    /// Forgets the protocol discovered for `url`, so that the next discovery tests it again.
+    pub fn forget(&self, url: &str) {
+        self.lock_known_protocols().remove(url);
+    }
+
+    async fn get_protocol_kind(&self, url: &str) -> Result<ProtocolKind, Error> {
+        if let Some(protocol_kind) = self.lock_known_protocols().get(url) {
+            debug!("Reusing the previously discovered {protocol_kind:?} protocol");
+            return Ok(*protocol_kind);
+        }
+
+        debug!("Testing the Passthrough protocol...");
+        let protocol_kind = if self.is_passthrough_supported(url).await? {
+            ProtocolKind::Passthrough
+        } else {
+            ProtocolKind::Klap
+        };
+
+        self.lock_known_protocols()
+            .insert(url.to_string(), protocol_kind);
+
+        Ok(protocol_kind)
+    }
+
+    async fn get_key_pair(&self) -> Result<PassthroughKeyPair, Error> {
+        let key_pair = self
+            .key_pair
+            .get_or_try_init(|| async { PassthroughKeyPair::new() })
+            .await?;
+
+        Ok(key_pair.clone())
+    }
+
+    fn lock_known_protocols(&self) -> std::sync::MutexGuard<'_, HashMap<String, ProtocolKind>> {
+        self.known_protocols
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+    }
+
     async fn is_passthrough_supported(&self, url: &str) -> Result<bool, Error> {
         if let Err(Error::Tapo(TapoResponseError::Unknown(code))) = self.test_passthrough(url).await
         {
@@ -1,112 +1,79 @@
 use std::sync::atomic::{AtomicI32, Ordering};
 
-use openssl::sha::{Sha1, Sha256};
-use openssl::symm::{decrypt, encrypt, Cipher};
+use crate::{Error, ProtocolError};
 
+use super::codec::KlapCodec;
+
+/// A [`KlapCodec`] that keeps track of the sequence number of the requests.
 #[derive(Debug)]
-pub(super) struct KlapCipher {
-    key: Vec<u8>,
-    iv: Vec<u8>,
+pub(crate) struct KlapCipher {
+    codec: KlapCodec,
     seq: AtomicI32,
-    sig: Vec<u8>,
 }
 
 impl KlapCipher {
-    pub fn new(
-        local_seed: Vec<u8>,
-        remote_seed: Vec<u8>,
-        user_hash: Vec<u8>,
-    ) -> anyhow::Result<Self> {
-        let local_hash = &[local_seed, remote_seed, user_hash].concat();
-
-        let (iv, seq) = Self::iv_derive(local_hash)?;
-
-        Ok(Self {
-            key: Self::key_derive(local_hash),
-            iv,
-            seq: AtomicI32::new(seq),
-            sig: Self::sig_derive(local_hash),
-        })
-    }
-
-    pub fn encrypt(&self, data: String) -> anyhow::Result<(Vec<u8>, i32)> {
-        let seq = self.seq.fetch_add(1, Ordering::Relaxed) + 1;
+    pub fn new(local_seed: &[u8], remote_seed: &[u8], auth_hash: &[u8]) -> Self {
+        let codec = KlapCodec::new(local_seed, remote_seed, auth_hash);
+        let seq = AtomicI32::new(codec.initial_seq());
 
-        let cipher_bytes = encrypt(
-            Cipher::aes_128_cbc(),
-            &self.key,
-            Some(&self.iv_seq(seq)),
-            data.as_bytes(),
-        )?;
-
-        let signature = Self::sha256(
-            &[
-                self.sig.as_slice(),
-                &seq.to_be_bytes(),
-                cipher_bytes.as_slice(),
-            ]
-            .concat(),
-        );
+        Self { codec, seq }
+    }
 
-        let result = [&signature, cipher_bytes.as_slice()].concat();
+    pub fn encrypt(&self, data: String) -> Result<(Vec<u8>, i32), Error> {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
 
-        Ok((result, seq))
+        Ok((self.codec.encrypt(seq, data.as_bytes())?, seq))
     }
 
-    pub fn decrypt(&self, seq: i32, cipher_bytes: Vec<u8>) -> anyhow::Result<String> {
-        let decrypted_bytes = decrypt(
-            Cipher::aes_128_cbc(),
-            &self.key,
-            Some(&self.iv_seq(seq)),
-            &cipher_bytes[32..],
-        )?;
-        let decrypted = std::str::from_utf8(&decrypted_bytes)?.to_string();
-
-        Ok(decrypted)
+    /// Decrypts the response to the request with the given `seq`, see [`KlapCodec::decrypt`].
+    pub fn decrypt(&self, seq: i32, cipher_bytes: Vec<u8>) -> Result<String, ProtocolError> {
+        self.codec.decrypt(seq, &cipher_bytes)
     }
 }
 
-impl KlapCipher {
-    fn key_derive(local_hash: &Vec<u8>) -> Vec<u8> {
-        let local_hash = &["lsk".as_bytes(), local_hash].concat();
-        let hash = Self::sha256(local_hash);
-        let key = &hash[..16];
-        key.to_vec()
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    fn iv_derive(local_hash: &[u8]) -> anyhow::Result<(Vec<u8>, i32)> {
-        let local_hash = &["iv".as_bytes(), local_hash].concat();
-        let hash = Self::sha256(local_hash);
-        let iv = &hash[..12];
-        let seq: [u8; 4] = hash[hash.len() - 4..].try_into()?;
-        let seq = i32::from_be_bytes(seq);
-        Ok((iv.to_vec(), seq))
+    fn cipher() -> KlapCipher {
+        KlapCipher::new(&[1; 16], &[2; 16], &[3; 32])
     }
 
-    fn sig_derive(local_hash: &[u8]) -> Vec<u8> {
-        let local_hash = &["ldk".as_bytes(), local_hash].concat();
-        let hash = Self::sha256(local_hash);
-        let key = &hash[..28];
-        key.to_vec()
-    }
+    #[test]
+    fn decrypt_accepts_response_signed_for_seq() {
+        let cipher = cipher();
+        let (payload, seq) = cipher.encrypt("{\"error_code\":0}".to_string()).unwrap();
 
-    fn iv_seq(&self, seq: i32) -> Vec<u8> {
-        let mut iv_seq = self.iv.clone();
-        iv_seq.extend_from_slice(&seq.to_be_bytes());
-        iv_seq
+        assert_eq!(cipher.decrypt(seq, payload).unwrap(), "{\"error_code\":0}");
     }
-}
 
-impl KlapCipher {
-    pub fn sha256(value: &[u8]) -> [u8; 32] {
-        let mut hasher = Sha256::new();
-        hasher.update(value);
-        hasher.finish()
+    #[test]
+    fn decrypt_rejects_response_signed_for_other_seq() {
+        let cipher = cipher();
+        let (stale_payload, _) = cipher.encrypt("{}".to_string()).unwrap();
+        let (_, seq) = cipher.encrypt("{}".to_string()).unwrap();
+
+        assert!(matches!(
+            cipher.decrypt(seq, stale_payload),
+            Err(ProtocolError::SequenceMismatch { expected }) if expected == seq
+        ));
+
+        // A wrong IV only garbles the first block, so the rest of a longer payload would decrypt fine.
+        let response = r#"{"error_code":0,"result":{"device_on":true}}"#;
+        let (stale_payload, _) = cipher.encrypt(response.to_string()).unwrap();
+        let (_, seq) = cipher.encrypt(response.to_string()).unwrap();
+
+        assert!(matches!(
+            cipher.decrypt(seq, stale_payload),
+            Err(ProtocolError::SequenceMismatch { expected }) if expected == seq
+        ));
     }
 
-    pub fn sha1(value: &[u8]) -> [u8; 20] {
-        let mut hasher = Sha1::new();
-        hasher.update(value);
-        hasher.finish()
+    #[test]
+    fn decrypt_rejects_truncated_response() {
+        assert!(matches!(
+            cipher().decrypt(1, vec![0; 32]),
+            Err(ProtocolError::TruncatedResponse)
+        ));
     }
 }
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::time::Instant;
 
 use async_trait::async_trait;
 use isahc::cookies::CookieJar;
@@ -10,9 +11,10 @@ use rand::{RngCore, SeedableRng};
 use serde::de::DeserializeOwned;
 
 use crate::requests::TapoRequest;
-use crate::responses::{validate_response, TapoResponse, TapoResponseExt};
-use crate::{Error, TapoResponseError};
+use crate::responses::TapoResponseExt;
+use crate::{Error, ProtocolError, TapoResponseError};
 
+use super::codec::{klap_auth_hash, klap_client_hash, klap_server_hash, parse_response_as};
 use super::discovery_protocol::DiscoveryProtocol;
 use super::klap_cipher::KlapCipher;
 use super::TapoProtocolExt;
@@ -23,6 +25,7 @@ pub(crate) struct KlapProtocol {
     cookie_jar: CookieJar,
     username: String,
     password: String,
+    auth_hash: Vec<u8>,
     rng: StdRng,
     url: Option<String>,
     cipher: Option<KlapCipher>,
@@ -69,24 +72,26 @@ impl TapoProtocolExt for KlapProtocol {
 
             let error = match response.status() {
                 isahc::http::StatusCode::UNAUTHORIZED | isahc::http::StatusCode::FORBIDDEN => {
-                    TapoResponseError::SessionTimeout
+                    Error::Tapo(TapoResponseError::SessionTimeout)
                 }
-                _ => TapoResponseError::InvalidResponse,
+                isahc::http::StatusCode::NOT_FOUND => {
+                    Error::Protocol(ProtocolError::EndpointNotFound)
+                }
+                _ => Error::Tapo(TapoResponseError::InvalidResponse),
             };
 
-            return Err(Error::Tapo(error));
+            return Err(error);
         }
 
         let response_body = response.bytes().await.map_err(anyhow::Error::from)?;
 
-        let response_decrypted = cipher.decrypt(seq, response_body)?;
+        let response_decrypted = cipher
+            .decrypt(seq, response_body)
+            .map_err(Error::Protocol)?;
         debug!("Device responded with: {response_decrypted:?}");
 
-        let inner_response: TapoResponse<R> = serde_json::from_str(&response_decrypted)?;
-        debug!("Device inner response: {inner_response:?}");
-
-        validate_response(&inner_response)?;
-        let result = inner_response.result;
+        let result = parse_response_as(response_decrypted)?;
+        debug!("Device inner response: {result:?}");
 
         Ok(result)
     }
@@ -102,28 +107,26 @@ impl TapoProtocolExt for KlapProtocol {
 
 impl KlapProtocol {
     pub fn new(client: HttpClient, username: String, password: String) -> Self {
+        let auth_hash = klap_auth_hash(&username, &password).to_vec();
+
         Self {
             client,
             cookie_jar: CookieJar::new(),
             username,
             password,
+            auth_hash,
             rng: StdRng::from_entropy(),
             url: None,
             cipher: None,
         }
     }
 
+    #[tracing::instrument(level = "debug", name = "klap_handshake", skip_all, fields(elapsed_ms))]
     async fn handshake(&mut self, url: String) -> Result<(), Error> {
+        let started = Instant::now();
         self.cookie_jar.clear();
 
-        let auth_hash = KlapCipher::sha256(
-            &[
-                KlapCipher::sha1(self.username.as_bytes()),
-                KlapCipher::sha1(self.password.as_bytes()),
-            ]
-            .concat(),
-        )
-        .to_vec();
+        let auth_hash = self.auth_hash.clone();
 
         let local_seed = self.get_local_seed().to_vec();
         let remote_seed = self.handshake1(&url, &local_seed, &auth_hash).await?;
@@ -131,11 +134,13 @@ impl KlapProtocol {
         self.handshake2(&url, &local_seed, &remote_seed, &auth_hash)
             .await?;
 
-        let cipher = KlapCipher::new(local_seed, remote_seed, auth_hash)?;
+        let cipher = KlapCipher::new(&local_seed, &remote_seed, &auth_hash);
 
         self.url.replace(url);
         self.cipher.replace(cipher);
 
+        tracing::Span::current().record("elapsed_ms", started.elapsed().as_millis() as u64);
+
         Ok(())
     }
 
@@ -155,6 +160,10 @@ impl KlapProtocol {
 
         let mut response = self.client.send_async(request).await?;
 
+        if response.status() == isahc::http::StatusCode::NOT_FOUND {
+            warn!("Handshake1 error: the device doesn't serve KLAP");
+            return Err(Error::Protocol(ProtocolError::EndpointNotFound));
+        }
         if !response.status().is_success() {
             warn!("Handshake1 error: {}", response.status());
             return Err(Error::Tapo(TapoResponseError::InvalidResponse));
@@ -163,7 +172,7 @@ impl KlapProtocol {
         let response_body = response.bytes().await.map_err(anyhow::Error::from)?;
 
         let (remote_seed, server_hash) = response_body.split_at(16);
-        let local_hash = KlapCipher::sha256(&[local_seed, remote_seed, auth_hash].concat());
+        let local_hash = klap_server_hash(local_seed, remote_seed, auth_hash);
 
         if local_hash != server_hash {
             warn!("Local hash does not match server hash");
@@ -185,7 +194,7 @@ impl KlapProtocol {
         debug!("Performing handshake2...");
         let url = format!("{url}/handshake2");
 
-        let payload = KlapCipher::sha256(&[remote_seed, local_seed, auth_hash].concat());
+        let payload = klap_client_hash(local_seed, remote_seed, auth_hash);
 
         let request = Request::post(&url)
             .cookie_jar(self.cookie_jar.clone())
@@ -204,6 +213,10 @@ impl KlapProtocol {
         Ok(())
     }
 
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
     fn get_local_seed(&mut self) -> [u8; 16] {
         let mut buffer = [0u8; 16];
         self.rng.fill_bytes(&mut buffer);
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::time::Instant;
 
 use async_trait::async_trait;
 use base64::{engine::general_purpose, Engine as _};
@@ -17,6 +18,7 @@ use crate::responses::{
 
 use crate::{Error, TapoResponseError};
 
+use super::codec::parse_response_as;
 use super::discovery_protocol::DiscoveryProtocol;
 use super::passthrough_cipher::{PassthroughCipher, PassthroughKeyPair};
 use super::tapo_protocol::TapoProtocolExt;
@@ -101,17 +103,16 @@ impl TapoProtocolExt for PassthroughProtocol {
             .ok_or_else(|| Error::Tapo(TapoResponseError::EmptyResult))?
             .response;
 
-        let inner_response_decrypted = session.cipher.decrypt(&inner_response_encrypted)?;
+        let inner_response_decrypted = session
+            .cipher
+            .decrypt(&inner_response_encrypted)
+            .map_err(Error::Protocol)?;
 
         debug!("Device inner response decrypted: {inner_response_decrypted}");
 
-        let inner_response: TapoResponse<R> = serde_json::from_str(&inner_response_decrypted)?;
+        let result = parse_response_as(inner_response_decrypted)?;
 
-        debug!("Device inner response: {inner_response:?}");
-
-        validate_response(&inner_response)?;
-
-        let result = inner_response.result;
+        debug!("Device inner response: {result:?}");
 
         Ok(result)
     }
@@ -126,21 +127,33 @@ impl TapoProtocolExt for PassthroughProtocol {
 }
 
 impl PassthroughProtocol {
-    pub fn new(client: HttpClient, username: String, password: String) -> Result<Self, Error> {
+    pub fn new(
+        client: HttpClient,
+        username: String,
+        password: String,
+        key_pair: PassthroughKeyPair,
+    ) -> Self {
         let username_digest = PassthroughCipher::sha1_digest_username(username);
         debug!("Username digest: {username_digest}");
 
-        Ok(Self {
+        Self {
             client,
             username: general_purpose::STANDARD.encode(username_digest),
             password: general_purpose::STANDARD.encode(password),
-            key_pair: PassthroughKeyPair::new()?,
+            key_pair,
             session: None,
-        })
+        }
     }
 
+    #[tracing::instrument(
+        level = "debug",
+        name = "passthrough_handshake",
+        skip_all,
+        fields(elapsed_ms)
+    )]
     async fn handshake(&mut self, url: String) -> Result<(), Error> {
         debug!("Performing handshake...");
+        let started = Instant::now();
 
         let cookie_jar = CookieJar::new();
 
@@ -164,6 +177,7 @@ impl PassthroughProtocol {
             .key;
 
         debug!("Handshake OK");
+        tracing::Span::current().record("elapsed_ms", started.elapsed().as_millis() as u64);
 
         let cipher = PassthroughCipher::new(&handshake_key, &self.key_pair)?;
 
@@ -177,8 +191,15 @@ impl PassthroughProtocol {
         Ok(())
     }
 
+    #[tracing::instrument(
+        level = "debug",
+        name = "passthrough_login",
+        skip_all,
+        fields(elapsed_ms)
+    )]
     async fn login_request(&mut self) -> Result<(), Error> {
         debug!("Will login with username '{}'...", self.username);
+        let started = Instant::now();
 
         let params = TapoParams::new(LoginDeviceParams::new(&self.username, &self.password))
             .set_request_time_mils()?;
@@ -191,10 +212,15 @@ impl PassthroughProtocol {
 
         let session = self.get_session_mut();
         session.token.replace(result.token);
+        tracing::Span::current().record("elapsed_ms", started.elapsed().as_millis() as u64);
 
         Ok(())
     }
 
+    pub fn url(&self) -> Option<&str> {
+        self.session.as_ref().map(|session| session.url.as_str())
+    }
+
     fn get_session_ref(&self) -> &Session {
         self.session.as_ref().expect("This should never happen")
     }
@@ -1,21 +1,31 @@
 use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use isahc::HttpClient;
+use log::warn;
 use serde::de::DeserializeOwned;
+use tokio::sync::RwLock;
 
 use crate::requests::TapoRequest;
 use crate::responses::TapoResponseExt;
-use crate::Error;
+use crate::{Error, ProtocolError, TapoResponseError};
 
 use super::{
     discovery_protocol::DiscoveryProtocol, klap_protocol::KlapProtocol,
     passthrough_protocol::PassthroughProtocol,
 };
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub(crate) struct TapoProtocol {
-    protocol: TapoProtocolType,
+    protocol: Arc<RwLock<TapoProtocolType>>,
+    discovery: DiscoveryProtocol,
+    /// Incremented on every login, so that concurrent requests that find their session invalidated
+    /// can tell whether another request has already logged in again.
+    generation: Arc<AtomicU64>,
+    /// Shared by all the clones, see [`TapoProtocol::session_relogins`].
+    session_relogins: Arc<AtomicU64>,
 }
 
 #[async_trait]
@@ -39,12 +49,78 @@ pub(crate) enum TapoProtocolType {
     Klap(KlapProtocol),
 }
 
-impl Clone for TapoProtocolType {
-    fn clone(&self) -> Self {
+impl TapoProtocolType {
+    async fn execute_request<R>(
+        &self,
+        request: TapoRequest,
+        with_token: bool,
+    ) -> Result<Option<R>, Error>
+    where
+        R: fmt::Debug + DeserializeOwned + TapoResponseExt,
+    {
         match self {
-            Self::Discovery(protocol) => Self::Discovery(protocol.clone()),
-            Self::Passthrough(protocol) => Self::Discovery(protocol.clone_as_discovery()),
-            Self::Klap(protocol) => Self::Discovery(protocol.clone_as_discovery()),
+            TapoProtocolType::Passthrough(protocol) => {
+                protocol.execute_request(request, with_token).await
+            }
+            TapoProtocolType::Klap(protocol) => protocol.execute_request(request, with_token).await,
+            _ => Err(anyhow::anyhow!("The protocol discovery should have happened already").into()),
+        }
+    }
+
+    /// Returns the URL of the device if `error` is the failure signature of a device
+    /// that has switched to a different protocol, e.g. after a firmware update.
+    fn mismatch_url(&self, error: &Error) -> Option<String> {
+        let url = match (self, error) {
+            // "Transport not available", returned by devices that have moved to KLAP.
+            (
+                TapoProtocolType::Passthrough(protocol),
+                Error::Tapo(TapoResponseError::Unknown(1003)),
+            ) => protocol.url(),
+            // The KLAP endpoints are missing on devices that have moved to Passthrough.
+            // Other failures, e.g. 5xx statuses, keep the negotiated protocol.
+            (
+                TapoProtocolType::Klap(protocol),
+                Error::Protocol(ProtocolError::EndpointNotFound),
+            ) => protocol.url(),
+            _ => None,
+        };
+
+        url.map(str::to_string)
+    }
+
+    /// Returns the URL of the device if `error` means that the session has been invalidated,
+    /// which happens when it expires or when the Tapo app, or another client, logs into the device.
+    fn invalidated_session_url(&self, error: &Error) -> Option<String> {
+        let url = match (self, error) {
+            (
+                TapoProtocolType::Passthrough(protocol),
+                Error::Tapo(TapoResponseError::SessionTimeout),
+            ) => protocol.url(),
+            (TapoProtocolType::Klap(protocol), Error::Tapo(TapoResponseError::SessionTimeout)) => {
+                protocol.url()
+            }
+            _ => None,
+        };
+
+        url.map(str::to_string)
+    }
+}
+
+/// How to recover from a failed request.
+enum Recovery {
+    Renegotiate(String),
+    Relogin { url: String, generation: u64 },
+}
+
+impl Clone for TapoProtocol {
+    fn clone(&self) -> Self {
+        let discovery = self.clone_as_discovery();
+
+        Self {
+            protocol: Arc::new(RwLock::new(TapoProtocolType::Discovery(discovery.clone()))),
+            discovery,
+            generation: Arc::new(AtomicU64::new(0)),
+            session_relogins: self.session_relogins.clone(),
         }
     }
 }
@@ -52,23 +128,34 @@ impl Clone for TapoProtocolType {
 #[async_trait]
 impl TapoProtocolExt for TapoProtocol {
     async fn login(&mut self, url: String) -> Result<(), Error> {
-        if let TapoProtocolType::Discovery(protocol) = &mut self.protocol {
-            self.protocol = protocol.discover(&url).await?;
-        }
-
-        match &mut self.protocol {
-            TapoProtocolType::Passthrough(protocol) => protocol.login(url).await,
-            TapoProtocolType::Klap(protocol) => protocol.login(url).await,
-            _ => Err(anyhow::anyhow!("The protocol discovery should have happened already").into()),
-        }
+        self.relogin(url).await
     }
 
     async fn refresh_session(&mut self) -> Result<(), Error> {
-        match &mut self.protocol {
-            TapoProtocolType::Passthrough(protocol) => protocol.refresh_session().await,
-            TapoProtocolType::Klap(protocol) => protocol.refresh_session().await,
-            _ => Err(anyhow::anyhow!("The protocol discovery should have happened already").into()),
-        }
+        let mismatch_url = {
+            let mut protocol = self.protocol.write().await;
+
+            let result = match &mut *protocol {
+                TapoProtocolType::Passthrough(protocol) => protocol.refresh_session().await,
+                TapoProtocolType::Klap(protocol) => protocol.refresh_session().await,
+                _ => Err(
+                    anyhow::anyhow!("The protocol discovery should have happened already").into(),
+                ),
+            };
+
+            match result {
+                Err(err) => match protocol.mismatch_url(&err) {
+                    Some(url) => url,
+                    None => return Err(err),
+                },
+                Ok(()) => {
+                    self.generation.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+            }
+        };
+
+        self.renegotiate(mismatch_url).await
     }
 
     async fn execute_request<R>(
@@ -79,30 +166,200 @@ impl TapoProtocolExt for TapoProtocol {
     where
         R: fmt::Debug + DeserializeOwned + TapoResponseExt,
     {
-        match &self.protocol {
-            TapoProtocolType::Passthrough(protocol) => {
-                protocol.execute_request(request, with_token).await
+        let recovery = {
+            let protocol = self.protocol.read().await;
+            let generation = self.generation.load(Ordering::Relaxed);
+
+            match protocol.execute_request(request.clone(), with_token).await {
+                Err(err) => {
+                    if let Some(url) = protocol.mismatch_url(&err) {
+                        Recovery::Renegotiate(url)
+                    } else if let Some(url) = protocol.invalidated_session_url(&err) {
+                        Recovery::Relogin { url, generation }
+                    } else {
+                        return Err(err);
+                    }
+                }
+                result => return result,
             }
-            TapoProtocolType::Klap(protocol) => protocol.execute_request(request, with_token).await,
-            _ => Err(anyhow::anyhow!("The protocol discovery should have happened already").into()),
+        };
+
+        match recovery {
+            Recovery::Renegotiate(url) => self.renegotiate(url).await?,
+            Recovery::Relogin { url, generation } => self.recover_session(url, generation).await?,
         }
+
+        self.protocol
+            .read()
+            .await
+            .execute_request(request, with_token)
+            .await
     }
 
     fn clone_as_discovery(&self) -> DiscoveryProtocol {
-        match &self.protocol {
-            TapoProtocolType::Discovery(protocol) => protocol.clone(),
-            TapoProtocolType::Passthrough(protocol) => protocol.clone_as_discovery(),
-            TapoProtocolType::Klap(protocol) => protocol.clone_as_discovery(),
-        }
+        self.discovery.clone()
     }
 }
 
 impl TapoProtocol {
+    /// Returns a [`TapoProtocol`] that shares the session of `self`, unlike [`TapoProtocol::clone`],
+    /// which starts over with the protocol discovery.
+    pub fn share(&self) -> Self {
+        Self {
+            protocol: self.protocol.clone(),
+            discovery: self.discovery.clone(),
+            generation: self.generation.clone(),
+            session_relogins: self.session_relogins.clone(),
+        }
+    }
+
     pub fn new(client: HttpClient, username: String, password: String) -> Self {
+        let discovery = DiscoveryProtocol::new(client, username, password);
+
         Self {
-            protocol: TapoProtocolType::Discovery(DiscoveryProtocol::new(
-                client, username, password,
-            )),
+            protocol: Arc::new(RwLock::new(TapoProtocolType::Discovery(discovery.clone()))),
+            discovery,
+            generation: Arc::new(AtomicU64::new(0)),
+            session_relogins: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns how many times an invalidated session has been recovered by logging in again,
+    /// across this protocol and all its clones. The session either expired or was taken over by another client.
+    pub fn session_relogins(&self) -> u64 {
+        self.session_relogins.load(Ordering::Relaxed)
+    }
+
+    /// Logs into the device found at `url` without requiring exclusive access to the protocol.
+    /// Requests that are in flight are allowed to finish before the session is replaced.
+    pub async fn relogin(&self, url: String) -> Result<(), Error> {
+        let mut protocol = self.protocol.write().await;
+        Self::login_protocol(&mut protocol, url).await?;
+        self.generation.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Logs in again after the session has been invalidated, because it expired or was taken over
+    /// by another client, unless another request has already done so since `generation`.
+    async fn recover_session(&self, url: String, generation: u64) -> Result<(), Error> {
+        let mut protocol = self.protocol.write().await;
+        if self.generation.load(Ordering::Relaxed) != generation {
+            return Ok(());
+        }
+
+        self.session_relogins.fetch_add(1, Ordering::Relaxed);
+        warn!("The session with the device at {url} has expired or been taken over by another client, logging in again...");
+
+        Self::login_protocol(&mut protocol, url).await?;
+        self.generation.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Discards the current protocol and negotiates a new one with the device found at `url`.
+    /// Needed when a firmware update switches the device to a different protocol mid-session.
+    async fn renegotiate(&self, url: String) -> Result<(), Error> {
+        warn!("The device at {url} no longer accepts the current protocol, renegotiating...");
+
+        let mut protocol = self.protocol.write().await;
+        self.discovery.forget(&url);
+        *protocol = TapoProtocolType::Discovery(self.discovery.clone());
+
+        Self::login_protocol(&mut protocol, url).await?;
+        self.generation.fetch_add(1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    async fn login_protocol(protocol: &mut TapoProtocolType, url: String) -> Result<(), Error> {
+        if let TapoProtocolType::Discovery(discovery) = protocol {
+            *protocol = discovery.discover(&url).await?;
+        }
+
+        match protocol {
+            TapoProtocolType::Passthrough(protocol) => protocol.login(url).await,
+            TapoProtocolType::Klap(protocol) => protocol.login(url).await,
+            _ => Err(anyhow::anyhow!("The protocol discovery should have happened already").into()),
         }
     }
 }
+
+#[cfg(all(test, feature = "simulator"))]
+mod tests {
+    use crate::requests::{EmptyParams, TapoParams};
+    use crate::simulator::{SimulatedFleet, SimulatedProtocol};
+
+    use super::*;
+
+    async fn connected_protocol(fleet: &SimulatedFleet) -> TapoProtocol {
+        let protocol = TapoProtocol::new(
+            HttpClient::new().unwrap(),
+            "username".to_string(),
+            "password".to_string(),
+        );
+        protocol
+            .relogin(format!("http://{}/app", fleet.addresses()[0]))
+            .await
+            .unwrap();
+
+        protocol
+    }
+
+    async fn get_device_info(protocol: &TapoProtocol) -> Result<serde_json::Value, Error> {
+        let request = TapoRequest::GetDeviceInfo(TapoParams::new(EmptyParams));
+
+        Ok(protocol.execute_request(request, true).await?.unwrap())
+    }
+
+    #[tokio::test]
+    async fn klap_device_switched_to_passthrough_is_renegotiated() {
+        let fleet = SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        let protocol = connected_protocol(&fleet).await;
+        assert!(matches!(
+            *protocol.protocol.read().await,
+            TapoProtocolType::Klap(_)
+        ));
+
+        fleet
+            .set_protocol(0, SimulatedProtocol::Passthrough)
+            .unwrap();
+
+        let device_info = get_device_info(&protocol).await.unwrap();
+        assert_eq!(device_info["model"], "P110");
+        assert!(matches!(
+            *protocol.protocol.read().await,
+            TapoProtocolType::Passthrough(_)
+        ));
+        assert_eq!(protocol.session_relogins(), 0);
+    }
+
+    #[tokio::test]
+    async fn passthrough_device_switched_to_klap_is_renegotiated() {
+        let fleet = SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        fleet
+            .set_protocol(0, SimulatedProtocol::Passthrough)
+            .unwrap();
+        let protocol = connected_protocol(&fleet).await;
+        assert!(matches!(
+            *protocol.protocol.read().await,
+            TapoProtocolType::Passthrough(_)
+        ));
+
+        fleet.set_protocol(0, SimulatedProtocol::Klap).unwrap();
+
+        let device_info = get_device_info(&protocol).await.unwrap();
+        assert_eq!(device_info["model"], "P110");
+        assert!(matches!(
+            *protocol.protocol.read().await,
+            TapoProtocolType::Klap(_)
+        ));
+        assert_eq!(protocol.session_relogins(), 0);
+    }
+}
@@ -0,0 +1,177 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::debug;
+use tokio::sync::oneshot;
+
+use crate::api::ApiClient;
+use crate::error::Error;
+
+/// The groups of *device info* properties that contradict each other: a color is either
+/// a *hue* and a *saturation* or a *color temperature*.
+const CONFLICTING_PROPERTIES: [&[&str]; 2] = [&["hue", "saturation"], &["color_temp"]];
+
+/// Coalesces the *device info* changes of a device that are made in quick succession into a single request,
+/// and leaves at least `min_interval` between two requests, see [`crate::ApiClientBuilder::coalesce_writes`].
+#[derive(Debug)]
+pub(crate) struct WriteQueue {
+    min_interval: Duration,
+    state: Mutex<WriteQueueState>,
+}
+
+#[derive(Debug, Default)]
+struct WriteQueueState {
+    /// The changes that haven't been sent yet. Later changes replace the earlier ones of the same properties,
+    /// and of the properties that contradict them, see [`CONFLICTING_PROPERTIES`].
+    changes: serde_json::Map<String, serde_json::Value>,
+    /// The callers waiting for `changes` to be sent.
+    waiters: Vec<oneshot::Sender<Result<(), Arc<Error>>>>,
+    /// Whether a task is sending the changes.
+    draining: bool,
+    last_write: Option<Instant>,
+}
+
+impl WriteQueue {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            state: Mutex::new(WriteQueueState::default()),
+        }
+    }
+
+    /// Queues `changes` and waits until they have been sent, together with the changes queued in the meantime.
+    /// The error of the request is reported to all the callers whose changes it contained, as the same variant.
+    pub async fn push(
+        self: Arc<Self>,
+        client: &ApiClient,
+        changes: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<(), Error> {
+        let (sender, receiver) = oneshot::channel();
+
+        let start_draining = {
+            let mut state = self.state.lock().expect("the lock is never poisoned");
+            merge_changes(&mut state.changes, changes);
+            state.waiters.push(sender);
+
+            !std::mem::replace(&mut state.draining, true)
+        };
+
+        if start_draining {
+            client.runtime().spawn(Box::pin(self.drain(client.share())));
+        }
+
+        receiver
+            .await
+            .map_err(|_| anyhow::anyhow!("The write queue has stopped"))?
+            .map_err(|err| Arc::try_unwrap(err).unwrap_or_else(|err| err.duplicate()))
+    }
+
+    async fn drain(self: Arc<Self>, client: ApiClient) {
+        loop {
+            let last_write = self
+                .state
+                .lock()
+                .expect("the lock is never poisoned")
+                .last_write;
+            if let Some(last_write) = last_write {
+                let delay = self.min_interval.saturating_sub(last_write.elapsed());
+                if !delay.is_zero() {
+                    client.runtime().sleep(delay).await;
+                }
+            }
+
+            let (changes, waiters) = {
+                let mut state = self.state.lock().expect("the lock is never poisoned");
+                if state.waiters.is_empty() {
+                    state.draining = false;
+                    return;
+                }
+
+                (
+                    std::mem::take(&mut state.changes),
+                    std::mem::take(&mut state.waiters),
+                )
+            };
+
+            debug!("Sending {} coalesced device info changes...", waiters.len());
+            let result = client
+                .send_device_info(serde_json::Value::Object(changes))
+                .await
+                .map_err(Arc::new);
+
+            self.state
+                .lock()
+                .expect("the lock is never poisoned")
+                .last_write
+                .replace(Instant::now());
+
+            for waiter in waiters {
+                let _ = waiter.send(result.clone());
+            }
+        }
+    }
+}
+
+/// Queues `changes` after `queued`, removing the queued properties that contradict them.
+fn merge_changes(
+    queued: &mut serde_json::Map<String, serde_json::Value>,
+    changes: serde_json::Map<String, serde_json::Value>,
+) {
+    for group in CONFLICTING_PROPERTIES {
+        if group.iter().any(|property| changes.contains_key(*property)) {
+            for other in CONFLICTING_PROPERTIES
+                .iter()
+                .filter(|other| **other != group)
+            {
+                for property in other
+                    .iter()
+                    .filter(|property| !changes.contains_key(**property))
+                {
+                    queued.remove(*property);
+                }
+            }
+        }
+    }
+
+    queued.extend(changes);
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn object(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        match value {
+            serde_json::Value::Object(object) => object,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn later_changes_replace_contradicting_ones() {
+        let mut queued = object(json!({"brightness": 50, "hue": 195, "saturation": 100}));
+
+        merge_changes(&mut queued, object(json!({"color_temp": 2700})));
+        assert_eq!(
+            serde_json::Value::Object(queued.clone()),
+            json!({"brightness": 50, "color_temp": 2700})
+        );
+
+        merge_changes(&mut queued, object(json!({"hue": 30})));
+        assert_eq!(
+            serde_json::Value::Object(queued.clone()),
+            json!({"brightness": 50, "hue": 30})
+        );
+
+        merge_changes(
+            &mut queued,
+            object(json!({"hue": 0, "saturation": 100, "color_temp": 6500})),
+        );
+        assert_eq!(
+            serde_json::Value::Object(queued),
+            json!({"brightness": 50, "hue": 0, "saturation": 100, "color_temp": 6500})
+        );
+    }
+}
@@ -0,0 +1,291 @@
+//! An audit log of the requests sent to the devices.
+//!
+//! Every request sent by an [`crate::ApiClient`] with an [`AuditSink`], see [`crate::ApiClientBuilder::audit_sink`],
+//! is recorded as an [`AuditRecord`] once it has completed.
+//! The credentials, keys and other sensitive parameters are replaced with [`REDACTED`] before they reach the sink.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use tapo::ApiClient;
+//! # use tapo::audit::FileAuditSink;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let device = ApiClient::builder("tapo-username@example.com", "tapo-password")
+//!     .audit_sink(FileAuditSink::open("audit.jsonl")?)
+//!     .build()?
+//!     .p110("192.168.1.100")
+//!     .await?;
+//!
+//! device.on().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+#[cfg(feature = "client")]
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// The value that replaces the sensitive parameters of an [`AuditRecord`].
+pub const REDACTED: &str = "[REDACTED]";
+
+/// The parameters that are redacted, at any depth.
+#[cfg(feature = "client")]
+const SENSITIVE_PARAMS: &[&str] = &[
+    "key",
+    "latitude",
+    "longitude",
+    "password",
+    "request",
+    "ssid",
+    "token",
+    "username",
+];
+
+/// Receives the [`AuditRecord`] of every request sent by an [`crate::ApiClient`].
+pub trait AuditSink: fmt::Debug + Send + Sync {
+    /// Records a completed request. Called before the result is returned to the caller,
+    /// so it shouldn't block for long.
+    fn record(&self, record: &AuditRecord);
+}
+
+/// A request sent to a device, see [`AuditSink`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// When the request completed.
+    pub timestamp: DateTime<Utc>,
+    /// The URL of the device.
+    pub device: String,
+    /// The method of the request, e.g. `set_device_info`.
+    pub method: String,
+    /// The parameters of the request, with the sensitive ones replaced with [`REDACTED`].
+    pub params: serde_json::Value,
+    /// Whether the request succeeded.
+    pub outcome: AuditOutcome,
+    /// How long the request took in milliseconds, including the retries.
+    pub latency_ms: u64,
+}
+
+/// The outcome of an [`AuditRecord`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// The device has accepted the request.
+    Success,
+    /// The request has failed.
+    Failure {
+        /// The description of the error.
+        error: String,
+    },
+}
+
+impl AuditRecord {
+    /// Returns the record of the serialized `request`, with its sensitive parameters redacted.
+    #[cfg(feature = "client")]
+    pub(crate) fn new(
+        device: &str,
+        request: &serde_json::Value,
+        outcome: AuditOutcome,
+        latency: Duration,
+    ) -> Self {
+        let mut params = request
+            .get("params")
+            .cloned()
+            .unwrap_or(serde_json::Value::Null);
+        redact(&mut params);
+
+        Self {
+            timestamp: Utc::now(),
+            device: device.to_string(),
+            method: request
+                .get("method")
+                .and_then(|method| method.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            params,
+            outcome,
+            latency_ms: latency.as_millis() as u64,
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (name, value) in map.iter_mut() {
+                if SENSITIVE_PARAMS.contains(&name.as_str()) {
+                    *value = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// An [`AuditSink`] that logs every record as JSON at the `info` level, with the `tapo::audit` target.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogAuditSink;
+
+impl AuditSink for LogAuditSink {
+    fn record(&self, record: &AuditRecord) {
+        match serde_json::to_string(record) {
+            Ok(record) => info!(target: "tapo::audit", "{record}"),
+            Err(err) => warn!("Failed to serialize the audit record: {err}"),
+        }
+    }
+}
+
+/// An [`AuditSink`] that appends every record to a file, one JSON object per line.
+#[derive(Debug)]
+pub struct FileAuditSink {
+    file: Mutex<File>,
+}
+
+impl FileAuditSink {
+    /// Opens the file at `path` for appending, creating it if it doesn't exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path of the file
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(anyhow::Error::from)?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+impl AuditSink for FileAuditSink {
+    fn record(&self, record: &AuditRecord) {
+        let mut file = self.file.lock().expect("the lock is never poisoned");
+
+        let result = serde_json::to_string(record)
+            .map_err(anyhow::Error::from)
+            .and_then(|record| writeln!(file, "{record}").map_err(anyhow::Error::from));
+        if let Err(err) = result {
+            warn!("Failed to write the audit record: {err}");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sensitive_params_are_redacted() {
+        let request = serde_json::json!({
+            "method": "set_device_info",
+            "params": {
+                "device_on": true,
+                "ssid": "bmV0d29yaw==",
+                "requests": [{ "method": "login", "params": { "password": "secret" } }],
+            },
+        });
+
+        let record = AuditRecord::new(
+            "http://192.168.1.100/app",
+            &request,
+            AuditOutcome::Success,
+            Duration::from_millis(42),
+        );
+
+        assert_eq!(record.method, "set_device_info");
+        assert_eq!(
+            record.params,
+            serde_json::json!({
+                "device_on": true,
+                "ssid": REDACTED,
+                "requests": [{ "method": "login", "params": { "password": REDACTED } }],
+            })
+        );
+        assert_eq!(record.latency_ms, 42);
+    }
+
+    #[test]
+    fn file_sink_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("tapo-audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let sink = FileAuditSink::open(&path).unwrap();
+        let record = AuditRecord::new(
+            "http://192.168.1.100/app",
+            &serde_json::json!({ "method": "get_device_info" }),
+            AuditOutcome::Failure {
+                error: "Unreachable".to_string(),
+            },
+            Duration::ZERO,
+        );
+
+        sink.record(&record);
+        sink.record(&record);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records: Vec<AuditRecord> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records, vec![record.clone(), record]);
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn client_records_requests() {
+        use std::sync::Arc;
+
+        use crate::simulator::SimulatedFleet;
+        use crate::ApiClient;
+
+        #[derive(Debug, Default)]
+        struct MemoryAuditSink(Mutex<Vec<AuditRecord>>);
+
+        impl AuditSink for Arc<MemoryAuditSink> {
+            fn record(&self, record: &AuditRecord) {
+                self.0.lock().unwrap().push(record.clone());
+            }
+        }
+
+        let fleet = SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        let sink = Arc::new(MemoryAuditSink::default());
+        let device = ApiClient::builder("username", "password")
+            .audit_sink(sink.clone())
+            .build()
+            .unwrap()
+            .p110(&fleet.addresses()[0])
+            .await
+            .unwrap();
+
+        device.on().await.unwrap();
+
+        let records = sink.0.lock().unwrap();
+        let record = records.last().unwrap();
+        assert_eq!(record.method, "set_device_info");
+        assert_eq!(record.params["device_on"], true);
+        assert_eq!(record.outcome, AuditOutcome::Success);
+        assert_eq!(
+            record.device,
+            format!("http://{}/app", fleet.addresses()[0])
+        );
+    }
+}
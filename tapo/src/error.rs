@@ -1,5 +1,7 @@
+use crate::access::Role;
+
 /// Response Error from the Tapo API.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[non_exhaustive]
 pub enum TapoResponseError {
     /// Invalid request.
@@ -21,6 +23,41 @@ pub enum TapoResponseError {
     Unknown(i32),
 }
 
+impl TapoResponseError {
+    /// Returns the error code sent by the device,
+    /// or `None` for the errors detected by this crate ([`TapoResponseError::InvalidResponse`] and [`TapoResponseError::EmptyResult`]).
+    pub fn code(&self) -> Option<i32> {
+        match self {
+            TapoResponseError::InvalidRequest => Some(-1002),
+            TapoResponseError::MalformedRequest => Some(-1003),
+            TapoResponseError::InvalidPublicKey => Some(-1010),
+            TapoResponseError::InvalidCredentials => Some(-1501),
+            TapoResponseError::SessionTimeout => Some(9999),
+            TapoResponseError::Unknown(code) => Some(*code),
+            TapoResponseError::InvalidResponse | TapoResponseError::EmptyResult => None,
+        }
+    }
+}
+
+/// Error in the transport protocol used to talk to the device.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ProtocolError {
+    /// The response is too short to contain a signature and a payload.
+    TruncatedResponse,
+    /// The signature of the response doesn't match the sequence number of the request.
+    /// This happens when a stale, out-of-order or retransmitted response is received.
+    SequenceMismatch {
+        /// The sequence number of the request.
+        expected: i32,
+    },
+    /// The payload of the response couldn't be decrypted.
+    DecryptionFailed,
+    /// The device doesn't serve the endpoints of the protocol (404 Not Found),
+    /// e.g. because a firmware update has switched it to a different protocol.
+    EndpointNotFound,
+}
+
 /// Tapo API Client Error.
 #[derive(thiserror::Error, Debug)]
 #[non_exhaustive]
@@ -28,6 +65,9 @@ pub enum Error {
     /// Response Error from the Tapo API.
     #[error("Tapo: {0:?}")]
     Tapo(TapoResponseError),
+    /// Protocol Error.
+    #[error("Protocol: {0:?}")]
+    Protocol(ProtocolError),
     /// Validation Error of a provided field.
     #[error("Validation: {field} {message}")]
     Validation {
@@ -36,13 +76,333 @@ pub enum Error {
         /// The validation error message.
         message: String,
     },
+    /// The device doesn't support the requested functionality, according to its [`crate::responses::Capabilities`].
+    #[error("NotSupported: {feature} is not supported by this device")]
+    NotSupported {
+        /// The functionality that isn't supported.
+        feature: String,
+    },
+    /// The firmware of the device is older than the first version known to implement the requested method.
+    /// Returned instead of the error code sent by the device.
+    #[error("UnsupportedFirmware: {method} requires firmware {required} or later, the device has {actual}")]
+    UnsupportedFirmware {
+        /// The method of the Tapo API.
+        method: String,
+        /// The minimum firmware version.
+        required: String,
+        /// The firmware version of the device.
+        actual: String,
+    },
+    /// The request has no access token, or one that isn't known to the [`crate::access::AccessPolicy`].
+    #[error("Unauthenticated: a valid access token is required")]
+    Unauthenticated,
+    /// The access token of the request doesn't grant the role required by the request.
+    #[error("Forbidden: {required} access is required, the token only grants {granted} access")]
+    Forbidden {
+        /// The role required by the request.
+        required: Role,
+        /// The role granted to the token.
+        granted: Role,
+    },
+    /// The device is in maintenance mode, so the request hasn't been sent to it,
+    /// see [`crate::manager::DeviceManager::set_maintenance`].
+    #[error("Maintenance: `{device}` is in maintenance mode")]
+    Maintenance {
+        /// The name of the device.
+        device: String,
+    },
+    /// Another instance of the application holds the [`crate::instance_lock::InstanceLock`].
+    #[error("InstanceLocked: {lock} is held by another instance{}", owner.map(|owner| format!(" (process {owner})")).unwrap_or_default())]
+    InstanceLocked {
+        /// The lock, e.g. the path of the lock file.
+        lock: String,
+        /// The ID of the process that holds the lock, if known.
+        owner: Option<u32>,
+    },
     /// Serialization/Deserialization Error.
     #[error("Serde: {0}")]
     Serde(#[from] serde_json::Error),
+    /// The response of the device couldn't be deserialized into the expected type.
+    /// It contains the raw (decrypted) JSON payload, which is useful for reporting unsupported firmware
+    /// or for falling back to manual parsing.
+    #[error("Deserialization: {source} in {response}")]
+    Deserialization {
+        /// The underlying deserialization error.
+        #[source]
+        source: serde_json::Error,
+        /// The raw JSON payload of the response.
+        response: String,
+    },
+    /// The device couldn't be reached, e.g. because it's turned off, disconnected from the network or has been assigned a new IP address.
+    /// As opposed to the other variants, it means that the device never got to process the request.
+    #[cfg(feature = "client")]
+    #[error("Unreachable: {0}")]
+    Unreachable(#[source] isahc::Error),
     /// HTTP Error.
+    #[cfg(feature = "client")]
     #[error("Http: {0}")]
-    Http(#[from] isahc::Error),
+    Http(#[source] isahc::Error),
     /// Other Error. This is a catch-all for errors that don't fit into the other categories.
     #[error(transparent)]
     Other(#[from] anyhow::Error),
 }
+
+impl Error {
+    /// Returns `true` if the device couldn't be reached (connection refused or timed out)
+    /// and `false` if the device was reached but the request failed, e.g. because it was rejected.
+    /// Useful for deciding whether to retry a request later or to consider a device offline.
+    pub fn is_unreachable(&self) -> bool {
+        match self {
+            #[cfg(feature = "client")]
+            Error::Unreachable(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if the request hasn't been sent because the device is in maintenance mode.
+    /// Such errors are expected and usually not worth reporting.
+    pub fn is_maintenance(&self) -> bool {
+        matches!(self, Error::Maintenance { .. })
+    }
+
+    /// Returns the same variant as `self`, for reporting one error to several callers.
+    /// The sources that can't be cloned are replaced by their message.
+    #[cfg(feature = "client")]
+    pub(crate) fn duplicate(&self) -> Error {
+        let serde_error = |err: &serde_json::Error| {
+            <serde_json::Error as serde::de::Error>::custom(err.to_string())
+        };
+
+        match self {
+            Error::Tapo(err) => Error::Tapo(err.clone()),
+            Error::Protocol(err) => Error::Protocol(err.clone()),
+            Error::Validation { field, message } => Error::Validation {
+                field: field.clone(),
+                message: message.clone(),
+            },
+            Error::NotSupported { feature } => Error::NotSupported {
+                feature: feature.clone(),
+            },
+            Error::UnsupportedFirmware {
+                method,
+                required,
+                actual,
+            } => Error::UnsupportedFirmware {
+                method: method.clone(),
+                required: required.clone(),
+                actual: actual.clone(),
+            },
+            Error::Unauthenticated => Error::Unauthenticated,
+            Error::Forbidden { required, granted } => Error::Forbidden {
+                required: *required,
+                granted: *granted,
+            },
+            Error::Maintenance { device } => Error::Maintenance {
+                device: device.clone(),
+            },
+            Error::InstanceLocked { lock, owner } => Error::InstanceLocked {
+                lock: lock.clone(),
+                owner: *owner,
+            },
+            Error::Serde(err) => Error::Serde(serde_error(err)),
+            Error::Deserialization { source, response } => Error::Deserialization {
+                source: serde_error(source),
+                response: response.clone(),
+            },
+            #[cfg(feature = "client")]
+            Error::Unreachable(err) => Error::Unreachable(err.clone()),
+            #[cfg(feature = "client")]
+            Error::Http(err) => Error::Http(err.clone()),
+            Error::Other(err) => Error::Other(anyhow::anyhow!("{err:#}")),
+        }
+    }
+}
+
+#[cfg(feature = "client")]
+impl From<isahc::Error> for Error {
+    fn from(err: isahc::Error) -> Self {
+        match err.kind() {
+            isahc::error::ErrorKind::ConnectionFailed | isahc::error::ErrorKind::Timeout => {
+                Error::Unreachable(err)
+            }
+            _ => Error::Http(err),
+        }
+    }
+}
+
+#[cfg(feature = "miette")]
+const INVALID_CREDENTIALS_HELP: &str = "error -1501: wrong credentials. \
+    The devices authenticate with the email and password of the Tapo account, as synced by the Tapo app, \
+    not with a local or device-specific password. \
+    If the password has been changed recently, open the Tapo app so that the devices get the new one.";
+#[cfg(feature = "miette")]
+const SESSION_TIMEOUT_HELP: &str = "error 9999: the session has expired or has been taken over \
+    by another client, e.g. the Tapo app. Call `refresh_session` and retry.";
+#[cfg(feature = "miette")]
+const TRANSPORT_HELP: &str = "error -1003: the device doesn't accept this protocol. \
+    Devices with recent firmware use KLAP, which is negotiated automatically on login.";
+#[cfg(feature = "miette")]
+const DESERIALIZATION_HELP: &str = "The response doesn't match the types of this crate, \
+    which often happens after a firmware update. Please report it together with the response.";
+#[cfg(all(feature = "miette", feature = "client"))]
+const UNREACHABLE_HELP: &str = "Check that the device is powered on, \
+    connected to the same network and still has the same IP address.";
+#[cfg(feature = "miette")]
+const INSTANCE_LOCKED_HELP: &str =
+    "Another instance is already controlling the devices. Stop it first.";
+#[cfg(feature = "miette")]
+const PROTOCOL_HELP: &str = "The response couldn't be decrypted or didn't match the request. \
+    This is usually transient, retry the request.";
+
+/// Rich diagnostics for CLI applications that report errors with [`miette`].
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        let code = match self {
+            Error::Tapo(TapoResponseError::InvalidCredentials) => "tapo::invalid_credentials",
+            Error::Tapo(TapoResponseError::SessionTimeout) => "tapo::session_timeout",
+            Error::Tapo(_) => "tapo::device_error",
+            Error::Protocol(_) => "tapo::protocol",
+            Error::Validation { .. } => "tapo::validation",
+            Error::NotSupported { .. } => "tapo::not_supported",
+            Error::UnsupportedFirmware { .. } => "tapo::unsupported_firmware",
+            Error::Unauthenticated => "tapo::unauthenticated",
+            Error::Forbidden { .. } => "tapo::forbidden",
+            Error::Maintenance { .. } => "tapo::maintenance",
+            Error::InstanceLocked { .. } => "tapo::instance_locked",
+            Error::Serde(_) | Error::Deserialization { .. } => "tapo::deserialization",
+            #[cfg(feature = "client")]
+            Error::Unreachable(_) => "tapo::unreachable",
+            #[cfg(feature = "client")]
+            Error::Http(_) => "tapo::http",
+            Error::Other(_) => "tapo::other",
+        };
+
+        Some(Box::new(code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        let help: std::borrow::Cow<'static, str> = match self {
+            Error::Tapo(error) => match error.code() {
+                Some(-1501) => INVALID_CREDENTIALS_HELP.into(),
+                Some(9999) => SESSION_TIMEOUT_HELP.into(),
+                Some(-1002) => {
+                    "error -1002: the device has rejected the request as invalid.".into()
+                }
+                Some(-1003) => TRANSPORT_HELP.into(),
+                Some(-1008) => "error -1008: the device has rejected the parameters.".into(),
+                Some(-1010) => "error -1010: the device has rejected the handshake key.".into(),
+                Some(-40210) => {
+                    "error -40210: the method is not implemented by the firmware.".into()
+                }
+                Some(code) => format!(
+                    "error {code}: unknown to this crate, please report it \
+                    with the model and the firmware version of the device."
+                )
+                .into(),
+                None => "The response of the device couldn't be made sense of.".into(),
+            },
+            Error::Protocol(_) => PROTOCOL_HELP.into(),
+            Error::NotSupported { .. } => {
+                "Check the capabilities of the device with `get_capabilities`.".into()
+            }
+            Error::UnsupportedFirmware { .. } => {
+                "Update the firmware of the device from the Tapo app.".into()
+            }
+            Error::Maintenance { .. } => {
+                "Take the device out of maintenance mode with `set_maintenance`.".into()
+            }
+            Error::InstanceLocked { .. } => INSTANCE_LOCKED_HELP.into(),
+            Error::Serde(_) | Error::Deserialization { .. } => DESERIALIZATION_HELP.into(),
+            #[cfg(feature = "client")]
+            Error::Unreachable(_) => UNREACHABLE_HELP.into(),
+            #[cfg(feature = "client")]
+            Error::Http(_) => return None,
+            Error::Validation { .. }
+            | Error::Unauthenticated
+            | Error::Forbidden { .. }
+            | Error::Other(_) => return None,
+        };
+
+        Some(Box::new(help))
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        match self {
+            Error::Serde(_)
+            | Error::Deserialization { .. }
+            | Error::Tapo(TapoResponseError::Unknown(_)) => {
+                Some(Box::new("https://github.com/mihai-dinculescu/tapo/issues"))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(all(test, any(feature = "client", feature = "miette")))]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn duplicate_keeps_the_variant() {
+        let error = Error::Tapo(TapoResponseError::SessionTimeout);
+        assert!(matches!(
+            error.duplicate(),
+            Error::Tapo(TapoResponseError::SessionTimeout)
+        ));
+
+        let error = Error::Validation {
+            field: "brightness".to_string(),
+            message: "must be between 1 and 100".to_string(),
+        };
+        assert!(matches!(
+            error.duplicate(),
+            Error::Validation { field, .. } if field == "brightness"
+        ));
+
+        let error = Error::from(serde_json::from_str::<u8>("-1").unwrap_err());
+        assert_eq!(error.duplicate().to_string(), error.to_string());
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn only_connection_failures_are_unreachable() {
+        use isahc::error::ErrorKind;
+
+        for kind in [ErrorKind::ConnectionFailed, ErrorKind::Timeout] {
+            let error = Error::from(isahc::Error::from(kind));
+            assert!(matches!(error, Error::Unreachable(_)));
+            assert!(error.is_unreachable());
+        }
+
+        for kind in [
+            ErrorKind::BadServerCertificate,
+            ErrorKind::Io,
+            ErrorKind::NameResolution,
+        ] {
+            let error = Error::from(isahc::Error::from(kind));
+            assert!(matches!(error, Error::Http(_)));
+            assert!(!error.is_unreachable());
+        }
+    }
+
+    #[cfg(feature = "miette")]
+    #[test]
+    fn diagnostics_explain_the_error_codes() {
+        use miette::Diagnostic;
+
+        let error = Error::Tapo(TapoResponseError::InvalidCredentials);
+
+        assert_eq!(
+            error.code().unwrap().to_string(),
+            "tapo::invalid_credentials"
+        );
+        assert!(error
+            .help()
+            .unwrap()
+            .to_string()
+            .starts_with("error -1501: wrong credentials"));
+        assert!(Error::Tapo(TapoResponseError::Unknown(-1)).url().is_some());
+    }
+}
@@ -0,0 +1,249 @@
+//! A single event type for everything that happens to a device, whatever its source.
+//!
+//! [`DeviceEvent`] can be built from the [`DeviceChange`] reported by [`crate::watcher::DeviceWatcher`]
+//! and from the trigger logs of the child devices of a hub, e.g. [`crate::T100Handler::trigger_logs_stream`],
+//! so that automation engines only have to handle one type.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use futures_lite::StreamExt;
+//! # use tapo::ApiClient;
+//! # use tapo::events::DeviceEvent;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let hub = ApiClient::new("tapo-username@example.com", "tapo-password")?
+//!     .h100("192.168.1.100")
+//!     .await?;
+//! let sensor = hub.t110("contact-sensor-device-id");
+//!
+//! let mut events = Box::pin(sensor.trigger_logs_stream()).map(|log| log.map(DeviceEvent::from));
+//! while let Some(event) = events.next().await {
+//!     println!("{:?}", event?);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use serde::Serialize;
+
+use crate::responses::{S200BLog, T100Log, T110Log, T300Log};
+#[cfg(feature = "client")]
+use crate::watcher::DeviceChange;
+
+/// Something that has happened to a device.
+///
+/// Serialized with its variant name in a `type` field, e.g. `{"type":"power_state_changed","device_on":true}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DeviceEvent {
+    /// The device has been turned on or off.
+    PowerStateChanged {
+        /// Whether the device is now on.
+        device_on: bool,
+    },
+    /// The light settings of the device have changed. Only the settings that have changed are set.
+    LightStateChanged {
+        /// The current brightness.
+        brightness: Option<u8>,
+        /// The current color temperature.
+        color_temperature: Option<u16>,
+        /// The current hue.
+        hue: Option<u16>,
+        /// The current saturation.
+        saturation: Option<u8>,
+    },
+    /// A sensor has been triggered.
+    SensorTriggered {
+        /// What the sensor has detected.
+        trigger: SensorTrigger,
+        /// The time of the event as a Unix timestamp in seconds.
+        timestamp: u64,
+    },
+    /// A button has been pressed or rotated.
+    ButtonPressed {
+        /// How the button has been used.
+        press: ButtonPress,
+        /// The time of the event as a Unix timestamp in seconds.
+        timestamp: u64,
+    },
+    /// A reading has crossed a threshold.
+    ThresholdAlert {
+        /// What has been measured, e.g. `temperature` or `current_power`.
+        metric: String,
+        /// The reading.
+        value: f64,
+        /// The threshold that has been crossed.
+        threshold: f64,
+    },
+    /// The device can no longer be reached.
+    Offline,
+    /// The device can be reached again after having been offline.
+    Online,
+    /// The battery of the device has become low.
+    BatteryLow {
+        /// The remaining charge, between 0 and 100, for the devices that report it.
+        percentage: Option<u8>,
+    },
+}
+
+/// What a sensor has detected, see [`DeviceEvent::SensorTriggered`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorTrigger {
+    /// T100 motion sensor has detected motion.
+    Motion,
+    /// T110 contact sensor has been opened.
+    Open,
+    /// T110 contact sensor has been closed.
+    Close,
+    /// T110 contact sensor has been open for more than 1 minute.
+    KeepOpen,
+    /// T300 water sensor has detected a leak.
+    WaterLeak,
+    /// T300 water sensor is dry again.
+    WaterDry,
+}
+
+/// How a button has been used, see [`DeviceEvent::ButtonPressed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ButtonPress {
+    /// Clicked once.
+    SingleClick,
+    /// Clicked twice.
+    DoubleClick,
+    /// Rotated by the given number of degrees, negative for counterclockwise.
+    Rotation {
+        /// The rotation in degrees.
+        degrees: i16,
+    },
+}
+
+#[cfg(feature = "client")]
+impl From<DeviceChange> for DeviceEvent {
+    fn from(change: DeviceChange) -> Self {
+        match change {
+            DeviceChange::TurnedOn => DeviceEvent::PowerStateChanged { device_on: true },
+            DeviceChange::TurnedOff => DeviceEvent::PowerStateChanged { device_on: false },
+            DeviceChange::BrightnessChanged { current, .. } => DeviceEvent::LightStateChanged {
+                brightness: Some(current),
+                color_temperature: None,
+                hue: None,
+                saturation: None,
+            },
+            DeviceChange::ColorTemperatureChanged { current, .. } => {
+                DeviceEvent::LightStateChanged {
+                    brightness: None,
+                    color_temperature: Some(current),
+                    hue: None,
+                    saturation: None,
+                }
+            }
+            DeviceChange::HueSaturationChanged { hue, saturation } => {
+                DeviceEvent::LightStateChanged {
+                    brightness: None,
+                    color_temperature: None,
+                    hue,
+                    saturation,
+                }
+            }
+            DeviceChange::BecameUnreachable => DeviceEvent::Offline,
+            DeviceChange::BecameReachable => DeviceEvent::Online,
+            DeviceChange::BatteryLow { percentage } => DeviceEvent::BatteryLow { percentage },
+        }
+    }
+}
+
+impl From<T100Log> for DeviceEvent {
+    fn from(log: T100Log) -> Self {
+        match log {
+            T100Log::Motion { timestamp, .. } => sensor_triggered(SensorTrigger::Motion, timestamp),
+        }
+    }
+}
+
+impl From<T110Log> for DeviceEvent {
+    fn from(log: T110Log) -> Self {
+        match log {
+            T110Log::Open { timestamp, .. } => sensor_triggered(SensorTrigger::Open, timestamp),
+            T110Log::Close { timestamp, .. } => sensor_triggered(SensorTrigger::Close, timestamp),
+            T110Log::KeepOpen { timestamp, .. } => {
+                sensor_triggered(SensorTrigger::KeepOpen, timestamp)
+            }
+        }
+    }
+}
+
+impl From<T300Log> for DeviceEvent {
+    fn from(log: T300Log) -> Self {
+        match log {
+            T300Log::WaterLeak { timestamp, .. } => {
+                sensor_triggered(SensorTrigger::WaterLeak, timestamp)
+            }
+            T300Log::WaterDry { timestamp, .. } => {
+                sensor_triggered(SensorTrigger::WaterDry, timestamp)
+            }
+        }
+    }
+}
+
+impl From<S200BLog> for DeviceEvent {
+    fn from(log: S200BLog) -> Self {
+        let (press, timestamp) = match log {
+            S200BLog::SingleClick { timestamp, .. } => (ButtonPress::SingleClick, timestamp),
+            S200BLog::DoubleClick { timestamp, .. } => (ButtonPress::DoubleClick, timestamp),
+            S200BLog::Rotation {
+                timestamp, params, ..
+            } => (
+                ButtonPress::Rotation {
+                    degrees: params.degrees,
+                },
+                timestamp,
+            ),
+        };
+
+        DeviceEvent::ButtonPressed { press, timestamp }
+    }
+}
+
+fn sensor_triggered(trigger: SensorTrigger, timestamp: u64) -> DeviceEvent {
+    DeviceEvent::SensorTriggered { trigger, timestamp }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watcher_changes_and_trigger_logs_are_unified() {
+        assert_eq!(
+            DeviceEvent::from(DeviceChange::TurnedOn),
+            DeviceEvent::PowerStateChanged { device_on: true }
+        );
+        assert_eq!(
+            DeviceEvent::from(DeviceChange::BecameUnreachable),
+            DeviceEvent::Offline
+        );
+        assert_eq!(
+            DeviceEvent::from(T110Log::Open {
+                id: 1,
+                timestamp: 1700000000
+            }),
+            DeviceEvent::SensorTriggered {
+                trigger: SensorTrigger::Open,
+                timestamp: 1700000000
+            }
+        );
+        assert_eq!(
+            DeviceEvent::from(S200BLog::DoubleClick {
+                id: 2,
+                timestamp: 1700000001
+            }),
+            DeviceEvent::ButtonPressed {
+                press: ButtonPress::DoubleClick,
+                timestamp: 1700000001
+            }
+        );
+    }
+}
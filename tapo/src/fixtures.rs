@@ -0,0 +1,198 @@
+//! Validation of stored device responses against the response structs of this crate.
+//!
+//! Requires the `fixtures` feature.
+//!
+//! The fixtures of the crate live in the `fixtures` directory of the repository, laid out as
+//! `<model>/<firmware version>/<method>.json`, and are validated by `cargo test --features fixtures`.
+//! See its `README.md` for how to contribute the responses of your devices.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use tapo::fixtures::Fixture;
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! for fixture in Fixture::load_dir("fixtures")? {
+//!     if let Err(err) = fixture.validate() {
+//!         println!("{}: {err}", fixture.path.display());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+
+use crate::error::Error;
+use crate::responses::{
+    ChildDeviceListResult, ComponentListResult, CurrentPowerResult, DecodableResultExt,
+    DeserializationMode, DeviceInfoColorLightResult, DeviceInfoColorLightStripResult,
+    DeviceInfoGenericResult, DeviceInfoHubResult, DeviceInfoLightResult, DeviceInfoPlugResult,
+    DeviceUsageEnergyMonitoringResult, DeviceUsageResult, EnergyUsageResult, TapoResponseExt,
+};
+
+/// The stored `result` of a response of a device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fixture {
+    /// The model of the device, e.g. `P110`.
+    pub model: String,
+    /// The firmware version of the device, e.g. `1.3.0`.
+    pub firmware: String,
+    /// The method of the Tapo API, e.g. `get_device_info`.
+    pub method: String,
+    /// The location of the fixture.
+    pub path: PathBuf,
+    /// The `result` of the response.
+    pub response: serde_json::Value,
+}
+
+impl Fixture {
+    /// Loads all the fixtures stored in `dir` as `<model>/<firmware version>/<method>.json`.
+    /// The fixtures are sorted by path, and the files that don't follow the layout are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - the root directory of the fixtures
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<Self>, Error> {
+        let mut fixtures = Vec::new();
+
+        for model in read_dir(dir.as_ref())?
+            .into_iter()
+            .filter(|path| path.is_dir())
+        {
+            for firmware in read_dir(&model)?.into_iter().filter(|path| path.is_dir()) {
+                for path in read_dir(&firmware)? {
+                    if path
+                        .extension()
+                        .is_some_and(|extension| extension == "json")
+                    {
+                        fixtures.push(Self::load(&model, &firmware, path)?);
+                    }
+                }
+            }
+        }
+
+        Ok(fixtures)
+    }
+
+    /// Deserializes the response into the struct returned by the handlers of the model for the method,
+    /// with [`DeserializationMode::Strict`], so that unknown enum values are reported too.
+    ///
+    /// The supported methods are `component_nego`, `get_device_info`, `get_device_usage`,
+    /// `get_energy_usage`, `get_current_power` and `get_child_device_list`.
+    /// Returns [`Error::NotSupported`] for the others.
+    pub fn validate(&self) -> Result<(), Error> {
+        let model = self.model.to_uppercase();
+
+        match (self.method.as_str(), model.as_str()) {
+            ("component_nego", _) => self.check::<ComponentListResult>(),
+            ("get_device_info", "L510" | "L520" | "L610") => {
+                self.check_decodable::<DeviceInfoLightResult>()
+            }
+            ("get_device_info", "L530" | "L535" | "L630") => {
+                self.check_decodable::<DeviceInfoColorLightResult>()
+            }
+            ("get_device_info", "L900" | "L920" | "L930") => {
+                self.check_decodable::<DeviceInfoColorLightStripResult>()
+            }
+            ("get_device_info", "P100" | "P105" | "P110" | "P115") => {
+                self.check_decodable::<DeviceInfoPlugResult>()
+            }
+            ("get_device_info", "H100") => self.check_decodable::<DeviceInfoHubResult>(),
+            ("get_device_info", _) => self.check_decodable::<DeviceInfoGenericResult>(),
+            ("get_device_usage", "P100" | "P105") => self.check::<DeviceUsageResult>(),
+            ("get_device_usage", _) => self.check::<DeviceUsageEnergyMonitoringResult>(),
+            ("get_energy_usage", _) => self.check::<EnergyUsageResult>(),
+            ("get_current_power", _) => self.check::<CurrentPowerResult>(),
+            ("get_child_device_list", _) => self.check_decodable::<ChildDeviceListResult>(),
+            (method, _) => Err(Error::NotSupported {
+                feature: format!("fixtures of {method}"),
+            }),
+        }
+    }
+
+    fn load(model: &Path, firmware: &Path, path: PathBuf) -> Result<Self, Error> {
+        let name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let contents = std::fs::read_to_string(&path).map_err(anyhow::Error::from)?;
+
+        Ok(Self {
+            model: name(model),
+            firmware: name(firmware),
+            method: path
+                .file_stem()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            response: serde_json::from_str(&contents)?,
+            path,
+        })
+    }
+
+    fn check<R>(&self) -> Result<(), Error>
+    where
+        R: DeserializeOwned + TapoResponseExt,
+    {
+        self.deserialize::<R>().map(|_| ())
+    }
+
+    fn check_decodable<R>(&self) -> Result<(), Error>
+    where
+        R: DeserializeOwned + TapoResponseExt + DecodableResultExt,
+    {
+        self.deserialize::<R>()?.decode().map(|_| ())
+    }
+
+    fn deserialize<R>(&self) -> Result<R, Error>
+    where
+        R: DeserializeOwned + TapoResponseExt,
+    {
+        DeserializationMode::Strict
+            .deserialize::<R>(&self.response)
+            .map_err(|source| Error::Deserialization {
+                source,
+                response: self.response.to_string(),
+            })
+    }
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut paths = std::fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(anyhow::Error::from)?;
+    paths.sort();
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_match_the_response_structs() {
+        let fixtures = Fixture::load_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures")).unwrap();
+        assert!(!fixtures.is_empty());
+
+        let failures: Vec<_> = fixtures
+            .iter()
+            .filter_map(|fixture| {
+                fixture.validate().err().map(|err| {
+                    format!(
+                        "{} {} {}: {err}",
+                        fixture.model, fixture.firmware, fixture.method
+                    )
+                })
+            })
+            .collect();
+
+        assert!(failures.is_empty(), "{failures:#?}");
+    }
+}
@@ -0,0 +1,17 @@
+//! A gRPC service for controlling the devices of a [`crate::manager::DeviceManager`] remotely,
+//! so that the crate can run as a LAN daemon for remote apps.
+//!
+//! The service is defined in `proto/tapo.proto`, from which the clients in other languages can be generated.
+//! It lists the registered devices, returns and changes their state and streams their events.
+//!
+//! Requires the `grpc` feature. The `protoc` compiler is vendored, so it doesn't need to be installed.
+
+mod device_manager_service;
+
+pub use device_manager_service::*;
+
+/// The messages, the server and the client generated from `proto/tapo.proto`.
+#[allow(missing_docs, clippy::all)]
+pub mod proto {
+    tonic::include_proto!("tapo.v1");
+}
@@ -0,0 +1,353 @@
+use std::net::SocketAddr;
+use std::pin::Pin;
+
+use log::info;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+use crate::access::{AccessPolicy, Role};
+use crate::error::Error;
+use crate::grpc::proto;
+use crate::grpc::proto::device_manager_server::DeviceManagerServer;
+use crate::manager::{DeviceKind, DeviceManager};
+use crate::scenes::SceneState;
+
+/// Serves the `tapo.v1.DeviceManager` gRPC service of `proto/tapo.proto` for the devices of a [`DeviceManager`].
+///
+/// The errors of the devices are returned as gRPC statuses: `NOT_FOUND` for the devices that aren't registered,
+/// `INVALID_ARGUMENT` for the invalid states, `UNIMPLEMENTED` for the properties that the device doesn't support,
+/// and `UNAVAILABLE` for the other errors, e.g. an unreachable device.
+///
+/// With [`DeviceManagerService::with_access_policy`], every call requires an access token as `authorization: Bearer <token>`
+/// metadata: [`Role::Read`] for `ListDevices`, `GetState` and `StreamEvents`, and [`Role::Control`] for `SetState`.
+/// The calls without a valid token are rejected with `UNAUTHENTICATED`, and those with a token that doesn't grant
+/// the required role with `PERMISSION_DENIED`.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use tapo::grpc::DeviceManagerService;
+/// # use tapo::manager::DeviceManager;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+///
+/// DeviceManagerService::new(manager)
+///     .serve("0.0.0.0:50051".parse()?)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeviceManagerService {
+    manager: DeviceManager,
+    access_policy: Option<AccessPolicy>,
+}
+
+impl DeviceManagerService {
+    /// Returns a [`DeviceManagerService`] for the devices of `manager`.
+    pub fn new(manager: DeviceManager) -> Self {
+        Self {
+            manager,
+            access_policy: None,
+        }
+    }
+
+    /// Requires the access tokens of `policy` for every call, see [`crate::access`].
+    pub fn with_access_policy(mut self, policy: AccessPolicy) -> Self {
+        self.access_policy = Some(policy);
+        self
+    }
+
+    /// Returns the service wrapped for [`tonic::transport::Server::add_service`],
+    /// to serve it together with other services.
+    pub fn into_server(self) -> DeviceManagerServer<Self> {
+        DeviceManagerServer::new(self)
+    }
+
+    /// Serves the service on `address` until the server fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - the address to listen on, e.g. `0.0.0.0:50051`
+    pub async fn serve(self, address: SocketAddr) -> Result<(), Error> {
+        info!("Serving the device manager over gRPC on {address}...");
+
+        tonic::transport::Server::builder()
+            .add_service(self.into_server())
+            .serve(address)
+            .await
+            .map_err(anyhow::Error::from)?;
+
+        Ok(())
+    }
+
+    fn authorize<T>(&self, request: &Request<T>, required: Role) -> Result<(), Error> {
+        let Some(policy) = &self.access_policy else {
+            return Ok(());
+        };
+
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|authorization| authorization.to_str().ok());
+        policy.authorize_bearer(authorization, required)?;
+
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl proto::device_manager_server::DeviceManager for DeviceManagerService {
+    async fn list_devices(
+        &self,
+        request: Request<proto::ListDevicesRequest>,
+    ) -> Result<Response<proto::ListDevicesResponse>, Status> {
+        self.authorize(&request, Role::Read).map_err(to_status)?;
+
+        let devices = self
+            .manager
+            .devices()
+            .await
+            .map_err(to_status)?
+            .into_iter()
+            .map(|(name, kind)| proto::Device {
+                name,
+                kind: proto::DeviceKind::from(kind).into(),
+            })
+            .collect();
+
+        Ok(Response::new(proto::ListDevicesResponse { devices }))
+    }
+
+    async fn get_state(
+        &self,
+        request: Request<proto::GetStateRequest>,
+    ) -> Result<Response<proto::DeviceState>, Status> {
+        self.authorize(&request, Role::Read).map_err(to_status)?;
+
+        let device_info = self
+            .manager
+            .device(request.into_inner().name)
+            .get_device_info_json()
+            .await
+            .map_err(to_status)?;
+
+        let number = |name: &str| {
+            device_info
+                .get(name)
+                .and_then(|value| value.as_u64())
+                .map(|value| value as u32)
+        };
+
+        Ok(Response::new(proto::DeviceState {
+            device_on: device_info
+                .get("device_on")
+                .and_then(|value| value.as_bool()),
+            brightness: number("brightness"),
+            hue: number("hue"),
+            saturation: number("saturation"),
+            color_temperature: number("color_temp"),
+            device_info_json: device_info.to_string(),
+        }))
+    }
+
+    async fn set_state(
+        &self,
+        request: Request<proto::SetStateRequest>,
+    ) -> Result<Response<proto::SetStateResponse>, Status> {
+        self.authorize(&request, Role::Control).map_err(to_status)?;
+
+        let request = request.into_inner();
+        let state = SceneState {
+            device_on: request.device_on,
+            brightness: narrow("brightness", request.brightness).map_err(to_status)?,
+            hue: narrow("hue", request.hue).map_err(to_status)?,
+            saturation: narrow("saturation", request.saturation).map_err(to_status)?,
+            color_temperature: narrow("color_temperature", request.color_temperature)
+                .map_err(to_status)?,
+        };
+        state.validate().map_err(to_status)?;
+
+        state
+            .apply(&self.manager.device(request.name))
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(proto::SetStateResponse {}))
+    }
+
+    type StreamEventsStream =
+        Pin<Box<dyn Stream<Item = Result<proto::DeviceEvent, Status>> + Send>>;
+
+    async fn stream_events(
+        &self,
+        request: Request<proto::StreamEventsRequest>,
+    ) -> Result<Response<Self::StreamEventsStream>, Status> {
+        self.authorize(&request, Role::Read).map_err(to_status)?;
+
+        // The events missed by a lagging client are skipped.
+        let events = BroadcastStream::new(self.manager.subscribe()).filter_map(|event| {
+            let event = event.ok()?;
+            let json = serde_json::to_value(&event.event).ok()?;
+
+            Some(Ok(proto::DeviceEvent {
+                name: event.name,
+                r#type: json["type"].as_str().unwrap_or_default().to_string(),
+                event_json: json.to_string(),
+            }))
+        });
+
+        Ok(Response::new(Box::pin(events)))
+    }
+}
+
+impl From<DeviceKind> for proto::DeviceKind {
+    fn from(kind: DeviceKind) -> Self {
+        match kind {
+            DeviceKind::Generic => Self::Generic,
+            DeviceKind::Light => Self::Light,
+            DeviceKind::ColorLight => Self::ColorLight,
+            DeviceKind::ColorLightStrip => Self::ColorLightStrip,
+            DeviceKind::Plug => Self::Plug,
+            DeviceKind::PlugEnergyMonitoring => Self::PlugEnergyMonitoring,
+            DeviceKind::Hub => Self::Hub,
+        }
+    }
+}
+
+fn narrow<T: TryFrom<u32>>(field: &str, value: Option<u32>) -> Result<Option<T>, Error> {
+    value
+        .map(|value| {
+            T::try_from(value).map_err(|_| Error::Validation {
+                field: field.to_string(),
+                message: format!("{value} is out of range"),
+            })
+        })
+        .transpose()
+}
+
+fn to_status(err: Error) -> Status {
+    match err {
+        Error::Validation { field, message } if field == "name" => Status::not_found(message),
+        Error::Validation { .. } => Status::invalid_argument(err.to_string()),
+        Error::Unauthenticated => Status::unauthenticated(err.to_string()),
+        Error::Forbidden { .. } => Status::permission_denied(err.to_string()),
+        Error::NotSupported { .. } | Error::UnsupportedFirmware { .. } => {
+            Status::unimplemented(err.to_string())
+        }
+        Error::Maintenance { .. } => Status::failed_precondition(err.to_string()),
+        err => Status::unavailable(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn devices_are_controlled_through_the_service() {
+        use crate::grpc::proto::device_manager_server::DeviceManager as _;
+        use crate::simulator::SimulatedFleet;
+        use crate::ApiClient;
+
+        let fleet = SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        manager
+            .register("kitchen", DeviceKind::Plug, &fleet.addresses()[0])
+            .await
+            .unwrap();
+        let service = DeviceManagerService::new(manager);
+
+        let devices = service
+            .list_devices(Request::new(proto::ListDevicesRequest {}))
+            .await
+            .unwrap()
+            .into_inner()
+            .devices;
+        assert_eq!(
+            devices,
+            vec![proto::Device {
+                name: "kitchen".to_string(),
+                kind: proto::DeviceKind::Plug.into(),
+            }]
+        );
+
+        service
+            .set_state(Request::new(proto::SetStateRequest {
+                name: "kitchen".to_string(),
+                device_on: Some(false),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        let state = service
+            .get_state(Request::new(proto::GetStateRequest {
+                name: "kitchen".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(state.device_on, Some(false));
+
+        let status = service
+            .set_state(Request::new(proto::SetStateRequest {
+                name: "kitchen".to_string(),
+                brightness: Some(50),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unimplemented);
+    }
+
+    #[test]
+    fn errors_are_mapped_to_statuses() {
+        let status = to_status(Error::Validation {
+            field: "name".to_string(),
+            message: "No device is registered as `attic`".to_string(),
+        });
+        assert_eq!(status.code(), tonic::Code::NotFound);
+
+        let status = to_status(narrow::<u8>("brightness", Some(300)).unwrap_err());
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn calls_require_a_token_with_the_role() {
+        use crate::grpc::proto::device_manager_server::DeviceManager as _;
+        use crate::ApiClient;
+
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        let service = DeviceManagerService::new(manager)
+            .with_access_policy(AccessPolicy::new().token("dashboard-token", Role::Read));
+        let request = |token: &str| {
+            let mut request = Request::new(proto::SetStateRequest {
+                name: "freezer".to_string(),
+                device_on: Some(false),
+                ..Default::default()
+            });
+            request
+                .metadata_mut()
+                .insert("authorization", token.parse().unwrap());
+            request
+        };
+
+        let status = service
+            .list_devices(Request::new(proto::ListDevicesRequest {}))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let status = service
+            .set_state(request("Bearer dashboard-token"))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::PermissionDenied);
+    }
+}
@@ -0,0 +1,398 @@
+//! HomeKit Accessory Protocol (HAP) descriptors of the devices, for bridges built on e.g. `hap-rs`.
+//!
+//! [`AccessoryDescriptor::from_capabilities`] maps the [`Capabilities`] of a device to the HAP services
+//! and characteristics that represent it: a *Lightbulb* with *Brightness*, *Hue*, *Saturation* and *Color Temperature*
+//! for the lights, and an *Outlet* with *Outlet In Use* for the plugs.
+//! [`CharacteristicType::read`] converts the *device info* of the device to the values of the characteristics,
+//! including the conversion of the *color temperature* from Kelvin to mireds.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use tapo::ApiClient;
+//! # use tapo::homekit::AccessoryDescriptor;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+//!     .l530("192.168.1.100")
+//!     .await?;
+//!
+//! let capabilities = device.get_capabilities().await?;
+//! let device_info = device.get_device_info_json().await?;
+//!
+//! if let Some(accessory) = AccessoryDescriptor::from_capabilities(&capabilities) {
+//!     for service in &accessory.services {
+//!         for characteristic in &service.characteristics {
+//!             let value = characteristic.characteristic_type.read(&device_info);
+//!             println!("{:?}: {value:?}", characteristic.characteristic_type);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use crate::requests::Kelvin;
+use crate::responses::Capabilities;
+
+/// The suffix of the UUIDs of the services and characteristics defined by Apple.
+const HAP_UUID_SUFFIX: &str = "-0000-1000-8000-0026BB765291";
+
+/// The HAP representation of a device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccessoryDescriptor {
+    /// The category of the accessory, which decides its icon in the Home app.
+    pub category: AccessoryCategory,
+    /// The services of the accessory, besides the *Accessory Information* service that every accessory has.
+    pub services: Vec<ServiceDescriptor>,
+}
+
+/// The category of an [`AccessoryDescriptor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessoryCategory {
+    /// A light bulb or light strip.
+    Lightbulb,
+    /// A plug.
+    Outlet,
+}
+
+/// A HAP service of an [`AccessoryDescriptor`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServiceDescriptor {
+    /// The type of the service.
+    pub service_type: ServiceType,
+    /// The characteristics of the service, the required ones first.
+    pub characteristics: Vec<CharacteristicDescriptor>,
+}
+
+/// The type of a [`ServiceDescriptor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceType {
+    /// The *Lightbulb* service.
+    Lightbulb,
+    /// The *Outlet* service.
+    Outlet,
+}
+
+/// A HAP characteristic of a [`ServiceDescriptor`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CharacteristicDescriptor {
+    /// The type of the characteristic.
+    pub characteristic_type: CharacteristicType,
+    /// The format of the values.
+    pub format: CharacteristicFormat,
+    /// The unit of the values, if any.
+    pub unit: Option<CharacteristicUnit>,
+    /// The minimum value, for the numeric formats.
+    pub min_value: Option<f64>,
+    /// The maximum value, for the numeric formats.
+    pub max_value: Option<f64>,
+    /// The step between two values, for the numeric formats.
+    pub min_step: Option<f64>,
+    /// Whether the controllers can change the value, as opposed to only reading it and being notified of its changes.
+    pub writable: bool,
+}
+
+/// The type of a [`CharacteristicDescriptor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CharacteristicType {
+    /// *On*, whether the device is *on*.
+    On,
+    /// *Brightness*, between 0 and 100. The devices can't be set to 0, which should turn them *off* instead.
+    Brightness,
+    /// *Hue*, between 0 and 360.
+    Hue,
+    /// *Saturation*, between 0 and 100.
+    Saturation,
+    /// *Color Temperature*, in mireds, i.e. 1,000,000 divided by the temperature in Kelvin.
+    ColorTemperature,
+    /// *Outlet In Use*, whether something is drawing power from the plug.
+    OutletInUse,
+}
+
+/// The format of a [`CharacteristicDescriptor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CharacteristicFormat {
+    /// A boolean.
+    Bool,
+    /// A signed 32-bit integer.
+    Int,
+    /// An unsigned 32-bit integer.
+    Uint32,
+    /// A floating point number.
+    Float,
+}
+
+/// The unit of a [`CharacteristicDescriptor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CharacteristicUnit {
+    /// A percentage.
+    Percentage,
+    /// An angle in degrees.
+    Arcdegrees,
+}
+
+impl AccessoryDescriptor {
+    /// Returns the HAP representation of a device with the given `capabilities`,
+    /// or `None` for the devices that aren't accessories themselves, i.e. the hubs.
+    ///
+    /// The devices with a *brightness* are lights, and the other ones are plugs.
+    /// The *color temperature* is limited to 2500-6500 K, see [`AccessoryDescriptor::with_color_temperature_range`].
+    pub fn from_capabilities(capabilities: &Capabilities) -> Option<Self> {
+        if capabilities.has_child_devices {
+            return None;
+        }
+
+        if !capabilities.has_brightness {
+            return Some(Self {
+                category: AccessoryCategory::Outlet,
+                services: vec![ServiceDescriptor {
+                    service_type: ServiceType::Outlet,
+                    characteristics: vec![
+                        CharacteristicType::On.descriptor(),
+                        CharacteristicType::OutletInUse.descriptor(),
+                    ],
+                }],
+            });
+        }
+
+        let mut characteristics = vec![
+            CharacteristicType::On.descriptor(),
+            CharacteristicType::Brightness.descriptor(),
+        ];
+        if capabilities.has_color {
+            characteristics.push(CharacteristicType::Hue.descriptor());
+            characteristics.push(CharacteristicType::Saturation.descriptor());
+        }
+        if capabilities.has_color_temperature {
+            characteristics.push(CharacteristicType::ColorTemperature.descriptor());
+        }
+
+        Some(
+            Self {
+                category: AccessoryCategory::Lightbulb,
+                services: vec![ServiceDescriptor {
+                    service_type: ServiceType::Lightbulb,
+                    characteristics,
+                }],
+            }
+            // The range of the color lights that don't report their own, until it's known.
+            .with_color_temperature_range(Kelvin::RANGE),
+        )
+    }
+
+    /// Sets the bounds of the *Color Temperature* characteristic, if any,
+    /// e.g. to the range returned by [`crate::ColorLightHandler::get_color_temperature_range`].
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - the color temperature range of the device, in Kelvin
+    pub fn with_color_temperature_range(mut self, range: RangeInclusive<u16>) -> Self {
+        let characteristics = self
+            .services
+            .iter_mut()
+            .flat_map(|service| service.characteristics.iter_mut())
+            .filter(|characteristic| {
+                characteristic.characteristic_type == CharacteristicType::ColorTemperature
+            });
+
+        for characteristic in characteristics {
+            // The warmest color has the highest mired value.
+            characteristic.min_value = Some(kelvin_to_mired(*range.end()) as f64);
+            characteristic.max_value = Some(kelvin_to_mired(*range.start()) as f64);
+        }
+
+        self
+    }
+}
+
+impl ServiceType {
+    /// Returns the UUID that identifies the service type in HAP.
+    pub fn uuid(&self) -> String {
+        let short_uuid = match self {
+            Self::Lightbulb => 0x43,
+            Self::Outlet => 0x47,
+        };
+
+        format!("{short_uuid:08X}{HAP_UUID_SUFFIX}")
+    }
+}
+
+impl CharacteristicType {
+    /// Returns the UUID that identifies the characteristic type in HAP.
+    pub fn uuid(&self) -> String {
+        let short_uuid = match self {
+            Self::On => 0x25,
+            Self::Brightness => 0x08,
+            Self::Hue => 0x13,
+            Self::Saturation => 0x2F,
+            Self::ColorTemperature => 0xCE,
+            Self::OutletInUse => 0x26,
+        };
+
+        format!("{short_uuid:08X}{HAP_UUID_SUFFIX}")
+    }
+
+    /// Returns the value of the characteristic from the *device info* of a device,
+    /// e.g. from [`crate::ColorLightHandler::get_device_info_json`].
+    /// Returns `None` if the device info doesn't contain it,
+    /// and for the *Color Temperature* of the lights that are showing a color.
+    ///
+    /// *Outlet In Use* follows the *on* state of the plug,
+    /// since the *device info* doesn't contain the current power of the plugs with energy monitoring.
+    pub fn read(&self, device_info: &serde_json::Value) -> Option<serde_json::Value> {
+        let value = match self {
+            Self::On | Self::OutletInUse => device_info.get("device_on")?.as_bool()?.into(),
+            Self::Brightness => device_info.get("brightness")?.as_u64()?.into(),
+            Self::Hue => (device_info.get("hue")?.as_u64()? as f64).into(),
+            Self::Saturation => (device_info.get("saturation")?.as_u64()? as f64).into(),
+            Self::ColorTemperature => match device_info.get("color_temp")?.as_u64()? {
+                0 => return None,
+                kelvin => kelvin_to_mired(kelvin.min(u16::MAX as u64) as u16).into(),
+            },
+        };
+
+        Some(value)
+    }
+
+    fn descriptor(self) -> CharacteristicDescriptor {
+        let (format, unit, bounds, writable) = match self {
+            Self::On => (CharacteristicFormat::Bool, None, None, true),
+            Self::Brightness => (
+                CharacteristicFormat::Int,
+                Some(CharacteristicUnit::Percentage),
+                Some((0.0, 100.0)),
+                true,
+            ),
+            Self::Hue => (
+                CharacteristicFormat::Float,
+                Some(CharacteristicUnit::Arcdegrees),
+                Some((0.0, 360.0)),
+                true,
+            ),
+            Self::Saturation => (
+                CharacteristicFormat::Float,
+                Some(CharacteristicUnit::Percentage),
+                Some((0.0, 100.0)),
+                true,
+            ),
+            Self::ColorTemperature => (
+                CharacteristicFormat::Uint32,
+                None,
+                Some((140.0, 500.0)),
+                true,
+            ),
+            Self::OutletInUse => (CharacteristicFormat::Bool, None, None, false),
+        };
+
+        CharacteristicDescriptor {
+            characteristic_type: self,
+            format,
+            unit,
+            min_value: bounds.map(|(min, _)| min),
+            max_value: bounds.map(|(_, max)| max),
+            min_step: bounds.map(|_| 1.0),
+            writable,
+        }
+    }
+}
+
+/// Converts a color temperature from Kelvin to mireds, the unit of the *Color Temperature* characteristic.
+///
+/// # Arguments
+///
+/// * `kelvin` - the color temperature in Kelvin, greater than 0
+pub fn kelvin_to_mired(kelvin: u16) -> u32 {
+    (1_000_000.0 / kelvin.max(1) as f64).round() as u32
+}
+
+/// Converts a color temperature from mireds, the unit of the *Color Temperature* characteristic, to Kelvin.
+///
+/// # Arguments
+///
+/// * `mired` - the color temperature in mireds, greater than 0
+pub fn mired_to_kelvin(mired: u32) -> u16 {
+    (1_000_000.0 / mired.max(1) as f64)
+        .round()
+        .min(u16::MAX as f64) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lights_and_plugs_are_mapped_to_their_services() {
+        let mut color_light = Capabilities::default();
+        color_light.has_brightness = true;
+        color_light.has_color = true;
+        color_light.has_color_temperature = true;
+
+        let accessory = AccessoryDescriptor::from_capabilities(&color_light)
+            .unwrap()
+            .with_color_temperature_range(2700..=6500);
+
+        assert_eq!(accessory.category, AccessoryCategory::Lightbulb);
+        let characteristics = &accessory.services[0].characteristics;
+        assert_eq!(
+            characteristics
+                .iter()
+                .map(|characteristic| characteristic.characteristic_type)
+                .collect::<Vec<_>>(),
+            vec![
+                CharacteristicType::On,
+                CharacteristicType::Brightness,
+                CharacteristicType::Hue,
+                CharacteristicType::Saturation,
+                CharacteristicType::ColorTemperature,
+            ]
+        );
+        assert_eq!(characteristics[4].min_value, Some(154.0));
+        assert_eq!(characteristics[4].max_value, Some(370.0));
+
+        let plug = AccessoryDescriptor::from_capabilities(&Capabilities::default()).unwrap();
+        assert_eq!(plug.services[0].service_type, ServiceType::Outlet);
+        assert_eq!(
+            plug.services[0].service_type.uuid(),
+            "00000047-0000-1000-8000-0026BB765291"
+        );
+    }
+
+    #[test]
+    fn device_info_is_read_as_characteristic_values() {
+        let device_info = serde_json::json!({
+            "device_on": true,
+            "brightness": 40,
+            "hue": 0,
+            "saturation": 100,
+            "color_temp": 2700,
+        });
+
+        assert_eq!(
+            CharacteristicType::On.read(&device_info),
+            Some(serde_json::json!(true))
+        );
+        assert_eq!(
+            CharacteristicType::Saturation.read(&device_info),
+            Some(serde_json::json!(100.0))
+        );
+        assert_eq!(
+            CharacteristicType::ColorTemperature.read(&device_info),
+            Some(serde_json::json!(370))
+        );
+        assert_eq!(
+            CharacteristicType::ColorTemperature.read(&serde_json::json!({ "color_temp": 0 })),
+            None
+        );
+        assert_eq!(mired_to_kelvin(370), 2703);
+    }
+}
@@ -0,0 +1,301 @@
+//! Storage of [`Reading`]s in InfluxDB, through the HTTP API of InfluxDB 2.x.
+//!
+//! Requires the `influxdb` feature.
+//!
+//! The readings are written in the line protocol, one measurement per [`crate::readings::ReadingKind`]
+//! with the name of the device as the `device` tag and the value as the `value` field:
+//!
+//! ```text
+//! power_w,device=kitchen value=12 1704110400000000000
+//! ```
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use chrono::Utc;
+//! # use tapo::ApiClient;
+//! # use tapo::influxdb::InfluxDbWriter;
+//! # use tapo::readings::Reading;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let writer = InfluxDbWriter::builder("http://localhost:8086", "tapo")
+//!     .org("home")
+//!     .token("influxdb-token")
+//!     .batch_size(100)
+//!     .build()?;
+//!
+//! let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+//!     .p110("192.168.1.100")
+//!     .await?;
+//!
+//! loop {
+//!     let energy_usage = device.get_energy_usage().await?;
+//!     writer
+//!         .write(Reading::from_energy_usage("kitchen", Utc::now(), &energy_usage))
+//!         .await?;
+//!     tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+//! }
+//! # }
+//! ```
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use isahc::http::header::{AUTHORIZATION, CONTENT_TYPE};
+use isahc::http::{Request, Uri};
+use isahc::prelude::Configurable;
+use isahc::{AsyncReadResponseExt, HttpClient};
+use log::{debug, warn};
+
+use crate::error::Error;
+use crate::readings::{Reading, ReadingSink};
+
+/// Writes [`Reading`]s to an InfluxDB bucket in batches, see [`crate::influxdb`].
+#[derive(Debug)]
+pub struct InfluxDbWriter {
+    client: HttpClient,
+    url: String,
+    token: Option<String>,
+    batch_size: usize,
+    buffer: Mutex<Vec<Reading>>,
+}
+
+impl InfluxDbWriter {
+    /// Returns an [`InfluxDbWriterBuilder`] for `bucket` of the InfluxDB server at `url`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - the base URL of the server, e.g. `http://localhost:8086`
+    /// * `bucket` - the bucket that the readings are written to
+    pub fn builder(url: impl Into<String>, bucket: impl Into<String>) -> InfluxDbWriterBuilder {
+        InfluxDbWriterBuilder {
+            url: url.into(),
+            bucket: bucket.into(),
+            org: None,
+            token: None,
+            batch_size: 1000,
+            timeout: Duration::from_secs(10),
+        }
+    }
+
+    /// Adds `readings` to the current batch, and writes the batch once it has [`InfluxDbWriterBuilder::batch_size`]
+    /// readings. A batch that fails to be written is kept and written again together with the next one.
+    pub async fn write(&self, readings: impl IntoIterator<Item = Reading>) -> Result<(), Error> {
+        let is_full = {
+            let mut buffer = self.buffer.lock().expect("the lock is never poisoned");
+            buffer.extend(readings);
+            buffer.len() >= self.batch_size
+        };
+
+        if is_full {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the current batch, however many readings it has.
+    pub async fn flush(&self) -> Result<(), Error> {
+        let batch = std::mem::take(&mut *self.buffer.lock().expect("the lock is never poisoned"));
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        if let Err(err) = self.send(&batch).await {
+            warn!(
+                "Failed to write {} readings to InfluxDB: {err}",
+                batch.len()
+            );
+            let mut buffer = self.buffer.lock().expect("the lock is never poisoned");
+            buffer.splice(0..0, batch);
+            return Err(err);
+        }
+
+        debug!("Wrote {} readings to InfluxDB", batch.len());
+        Ok(())
+    }
+
+    async fn send(&self, batch: &[Reading]) -> Result<(), Error> {
+        let mut request =
+            Request::post(&self.url).header(CONTENT_TYPE, "text/plain; charset=utf-8");
+        if let Some(token) = &self.token {
+            request = request.header(AUTHORIZATION, format!("Token {token}"));
+        }
+        let request = request
+            .body(to_line_protocol(batch))
+            .map_err(anyhow::Error::from)?;
+
+        let mut response = self.client.send_async(request).await?;
+        let status = response.status();
+        // Drain the body so that the connection can be reused.
+        let body = response.text().await.unwrap_or_default();
+
+        if !status.is_success() {
+            return Err(Error::Other(anyhow::anyhow!(
+                "InfluxDB responded with {status}: {body}"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes the batches of a [`crate::readings::SinkPipeline`] directly, without the batching of [`InfluxDbWriter::write`].
+#[async_trait]
+impl ReadingSink for InfluxDbWriter {
+    async fn write_batch(&self, readings: &[Reading]) -> Result<(), Error> {
+        self.send(readings).await
+    }
+}
+
+/// Builder for an [`InfluxDbWriter`], see [`InfluxDbWriter::builder`].
+#[derive(Debug, Clone)]
+pub struct InfluxDbWriterBuilder {
+    url: String,
+    bucket: String,
+    org: Option<String>,
+    token: Option<String>,
+    batch_size: usize,
+    timeout: Duration,
+}
+
+impl InfluxDbWriterBuilder {
+    /// Sets the organization of the bucket. Not needed with the tokens that are scoped to a single organization.
+    pub fn org(mut self, org: impl Into<String>) -> Self {
+        self.org = Some(org.into());
+        self
+    }
+
+    /// Sets the API token, sent as `Authorization: Token <token>`.
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Sets how many readings are written together. Defaults to 1000.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Sets the maximum duration of a request. Defaults to 10 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Builds the [`InfluxDbWriter`].
+    /// Returns [`Error::Validation`] if the URL is invalid or the batch size is zero.
+    pub fn build(self) -> Result<InfluxDbWriter, Error> {
+        if self.batch_size == 0 {
+            return Err(Error::Validation {
+                field: "batch_size".to_string(),
+                message: "must be greater than 0".to_string(),
+            });
+        }
+
+        let mut url = format!(
+            "{}/api/v2/write?bucket={}&precision=ns",
+            self.url.trim_end_matches('/'),
+            self.bucket
+        );
+        if let Some(org) = &self.org {
+            url.push_str(&format!("&org={org}"));
+        }
+
+        let is_http = url
+            .parse::<Uri>()
+            .ok()
+            .and_then(|uri| uri.scheme_str().map(str::to_string))
+            .is_some_and(|scheme| scheme == "http" || scheme == "https");
+        if !is_http {
+            return Err(Error::Validation {
+                field: "url".to_string(),
+                message: format!("`{}` is not a valid HTTP URL", self.url),
+            });
+        }
+
+        let client = HttpClient::builder().timeout(self.timeout).build()?;
+
+        Ok(InfluxDbWriter {
+            client,
+            url,
+            token: self.token,
+            batch_size: self.batch_size,
+            buffer: Mutex::new(Vec::new()),
+        })
+    }
+}
+
+/// Returns `readings` in the InfluxDB line protocol, one line per reading, see [`crate::influxdb`].
+pub fn to_line_protocol(readings: &[Reading]) -> String {
+    readings
+        .iter()
+        .map(|reading| {
+            format!(
+                "{},device={} value={} {}",
+                reading.kind.name(),
+                escape(&reading.device),
+                reading.value,
+                reading.timestamp.timestamp_nanos_opt().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escapes the commas, equal signs and spaces of a tag value.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        if matches!(character, ',' | '=' | ' ' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(character);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::readings::ReadingKind;
+
+    use super::*;
+
+    #[test]
+    fn readings_are_converted_to_line_protocol() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let readings = [
+            Reading::new("kitchen", timestamp, ReadingKind::PowerW, 12.0),
+            Reading::new(
+                "living room, east",
+                timestamp,
+                ReadingKind::TemperatureCelsius,
+                21.5,
+            ),
+        ];
+
+        assert_eq!(
+            to_line_protocol(&readings),
+            "power_w,device=kitchen value=12 1704110400000000000\n\
+            temperature_celsius,device=living\\ room\\,\\ east value=21.5 1704110400000000000"
+        );
+    }
+
+    #[test]
+    fn build_rejects_invalid_settings() {
+        assert!(matches!(
+            InfluxDbWriter::builder("not a url", "tapo").build(),
+            Err(Error::Validation { field, .. }) if field == "url"
+        ));
+        assert!(matches!(
+            InfluxDbWriter::builder("http://localhost:8086", "tapo")
+                .batch_size(0)
+                .build(),
+            Err(Error::Validation { field, .. }) if field == "batch_size"
+        ));
+    }
+}
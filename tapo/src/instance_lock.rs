@@ -0,0 +1,166 @@
+//! Guard against running two instances of an application that controls the same devices,
+//! e.g. a bridge started twice by a service manager, which would otherwise send every schedule and command twice.
+//!
+//! An [`InstanceLock`] is held for as long as it's alive, and acquiring it while another instance holds it
+//! fails with [`Error::InstanceLocked`]. It's usually handed to [`crate::manager::DeviceManagerBuilder::instance_lock`],
+//! which keeps it until the manager stops.
+//!
+//! Two kinds of locks are available:
+//!
+//! * [`InstanceLock::file`] - an advisory lock (`flock` on Unix, `LockFileEx` on Windows) on a file
+//!   holding the process ID of the instance, which the operating system releases whenever the instance exits,
+//!   even when it crashes. The file itself is left in place.
+//! * [`InstanceLock::port`] - a TCP port bound on the loopback interface,
+//!   which the operating system releases whenever the instance exits, even when it crashes.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use tapo::instance_lock::InstanceLock;
+//! # fn main() -> Result<(), tapo::Error> {
+//! let _lock = InstanceLock::file("/run/tapo-bridge.lock")?;
+//!
+//! // Control the devices...
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::Path;
+
+use fs4::FileExt;
+use log::debug;
+
+use crate::error::Error;
+
+/// A lock held by a single instance of an application, see [`crate::instance_lock`].
+#[derive(Debug)]
+pub struct InstanceLock {
+    _kind: LockKind,
+}
+
+#[derive(Debug)]
+enum LockKind {
+    File { _file: File },
+    Port { _listener: TcpListener },
+}
+
+impl InstanceLock {
+    /// Acquires the lock by locking the file at `path`, created if needed, and writing the ID of the current process to it.
+    ///
+    /// Returns [`Error::InstanceLocked`] if another process holds the lock. A file left behind by an instance
+    /// that is no longer running, or that is empty because it crashed while writing it, is simply locked again.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the location of the lock file, usually in `/run` or the data directory of the application
+    pub fn file(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(anyhow::Error::from)?;
+
+        if let Err(err) = file.try_lock_exclusive() {
+            if err.raw_os_error() != fs4::lock_contended_error().raw_os_error() {
+                return Err(anyhow::Error::from(err).into());
+            }
+
+            let owner = std::fs::read_to_string(path)
+                .ok()
+                .and_then(|contents| contents.trim().parse().ok());
+
+            return Err(Error::InstanceLocked {
+                lock: path.display().to_string(),
+                owner,
+            });
+        }
+
+        file.set_len(0)
+            .and_then(|_| writeln!(file, "{}", std::process::id()))
+            .map_err(anyhow::Error::from)?;
+
+        debug!("Acquired the lock {}", path.display());
+
+        Ok(Self {
+            _kind: LockKind::File { _file: file },
+        })
+    }
+
+    /// Acquires the lock by binding `port` on the loopback interface.
+    ///
+    /// Returns [`Error::InstanceLocked`] if the port is already in use, by another instance or by another application.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - a port that is free on the host, the same for all the instances
+    pub fn port(port: u16) -> Result<Self, Error> {
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)) {
+            Err(err) if err.kind() == ErrorKind::AddrInUse => {
+                return Err(Error::InstanceLocked {
+                    lock: format!("port {port}"),
+                    owner: None,
+                });
+            }
+            result => result.map_err(anyhow::Error::from)?,
+        };
+
+        debug!("Acquired the lock on port {port}");
+
+        Ok(Self {
+            _kind: LockKind::Port {
+                _listener: listener,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_lock_is_held_until_dropped() {
+        let path = std::env::temp_dir().join(format!("tapo-lock-{}.lock", uuid::Uuid::new_v4()));
+
+        let lock = InstanceLock::file(&path).unwrap();
+        assert!(matches!(
+            InstanceLock::file(&path),
+            Err(Error::InstanceLocked { owner, .. }) if owner == Some(std::process::id())
+        ));
+
+        drop(lock);
+        assert!(path.exists());
+        let _lock = InstanceLock::file(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn file_left_behind_is_locked_again() {
+        let path = std::env::temp_dir().join(format!("tapo-lock-{}.lock", uuid::Uuid::new_v4()));
+
+        for contents in ["", "4194304000\n"] {
+            std::fs::write(&path, contents).unwrap();
+
+            let lock = InstanceLock::file(&path).unwrap();
+            assert_eq!(
+                std::fs::read_to_string(&path).unwrap(),
+                format!("{}\n", std::process::id())
+            );
+            drop(lock);
+        }
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
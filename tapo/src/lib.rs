@@ -70,7 +70,16 @@
 //! ```
 //!
 //! See [more examples](https://github.com/mihai-dinculescu/tapo/tree/main/tapo/examples).
+//!
+//! # Without the client
+//!
+//! The HTTP client, and with it the dependency on `tokio`, `isahc` and `openssl`, is behind the default `client` feature.
+//! The runtime of Tokio is behind the default `tokio-runtime` feature, see [`AsyncRuntime`].
+//! With `default-features = false`, the crate only contains the request and response models, their validation,
+//! and the modules that build on them, e.g. [`report`] and [`sun`],
+//! so that they can be reused by gateways that have their own transport.
 
+#[cfg(feature = "client")]
 mod api;
 mod error;
 mod tapo_date_format;
@@ -78,8 +87,52 @@ mod tapo_date_format;
 #[cfg(feature = "python")]
 pub mod python;
 
+pub mod access;
+pub mod aggregation;
+pub mod audit;
+pub mod events;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod homekit;
+#[cfg(feature = "influxdb")]
+pub mod influxdb;
+#[cfg(feature = "instance-lock")]
+pub mod instance_lock;
+#[cfg(feature = "manager")]
+pub mod manager;
+pub mod matter;
+pub mod persistence;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod readings;
+pub mod report;
 pub mod requests;
 pub mod responses;
+#[cfg(feature = "rules")]
+pub mod rules;
+#[cfg(feature = "manager")]
+pub mod runtime;
+#[cfg(feature = "scenes")]
+pub mod scenes;
+#[cfg(feature = "scheduler")]
+pub mod scheduler;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod signal;
+#[cfg(feature = "simulator")]
+pub mod simulator;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod sun;
+#[cfg(feature = "tariff")]
+pub mod tariff;
+#[cfg(feature = "client")]
+pub mod watcher;
+#[cfg(feature = "webhook")]
+pub mod webhook;
 
+#[cfg(feature = "client")]
 pub use api::*;
 pub use error::*;
@@ -0,0 +1,30 @@
+//! Centralized control of many devices through a single task that owns all the device sessions.
+//!
+//! Requires the `manager` feature.
+
+mod config;
+mod config_watcher;
+mod device_handle;
+mod device_kind;
+mod device_labels;
+mod device_manager;
+mod firmware_rollout;
+mod inventory;
+mod managed_device;
+mod power_budget;
+mod solar_surplus;
+mod watchdog;
+
+pub use config::*;
+pub use config_watcher::*;
+pub use device_handle::*;
+pub use device_kind::*;
+pub use device_labels::*;
+pub use device_manager::*;
+pub use firmware_rollout::*;
+pub use inventory::*;
+pub use power_budget::*;
+pub use solar_surplus::*;
+pub use watchdog::*;
+
+pub(crate) use managed_device::*;
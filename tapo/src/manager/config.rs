@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::manager::{DeviceKind, DeviceLabels};
+use crate::readings::Calibration;
+
+/// Describes the devices of a [`crate::manager::DeviceManager`] by name, see [`crate::manager::DeviceManager::from_config`].
+///
+/// The devices bound to other Tapo accounts, e.g. those of several households, refer to one of the
+/// named `accounts` or have their own `credentials`.
+///
+/// # Example
+///
+/// ```json
+/// {
+///     "credentials": {
+///         "username": "tapo-username@example.com",
+///         "password": "tapo-password"
+///     },
+///     "devices": {
+///         "kitchen": { "model": "P110", "address": "192.168.1.100", "room": "kitchen", "tags": ["heating"] },
+///         "living-room": { "model": "L530", "address": "192.168.1.101", "room": "living-room" },
+///         "hub": { "model": "H100", "address": "192.168.1.103", "calibration": { "temperature_offset": -1.5 } },
+///         "garage": {
+///             "model": "P100",
+///             "address": "192.168.1.102",
+///             "credentials": {
+///                 "username": "other-tapo-username@example.com",
+///                 "password": "other-tapo-password"
+///             }
+///         },
+///         "flat-2-heater": { "model": "P110", "address": "192.168.2.100", "account": "flat-2" },
+///         "flat-2-hallway": { "model": "L510", "address": "192.168.2.101", "account": "flat-2" }
+///     },
+///     "accounts": {
+///         "flat-2": {
+///             "username": "flat-2-tapo-username@example.com",
+///             "password": "flat-2-tapo-password"
+///         }
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// The credentials used for the devices that don't have their own.
+    pub credentials: Credentials,
+    /// The devices, by name.
+    pub devices: BTreeMap<String, DeviceConfig>,
+    /// The credentials of other Tapo accounts, by name, see [`DeviceConfig::account`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub accounts: BTreeMap<String, Credentials>,
+}
+
+/// A device of a [`Config`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceConfig {
+    /// The model of the device, e.g. `P110` or `L530`. See [`DeviceKind::from_model`].
+    pub model: String,
+    /// The IP address or hostname of the device.
+    pub address: String,
+    /// The credentials of the device, if they differ from [`Config::credentials`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials: Option<Credentials>,
+    /// The name of the account of the device in [`Config::accounts`], if it differs from [`Config::credentials`].
+    /// The devices of the same account share its client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// The room and the tags of the device.
+    #[serde(flatten)]
+    pub labels: DeviceLabels,
+    /// The corrections applied to the readings of the device, see [`crate::manager::DeviceManager::calibrate`].
+    #[serde(default, skip_serializing_if = "Calibration::is_identity")]
+    pub calibration: Calibration,
+}
+
+/// Tapo credentials.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credentials {
+    /// The Tapo username.
+    pub username: String,
+    /// The Tapo password.
+    pub password: String,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+impl Config {
+    /// Reads a [`Config`] from the JSON file at `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let contents = std::fs::read_to_string(path.as_ref())
+            .map_err(|err| anyhow::anyhow!("Failed to read {}: {err}", path.as_ref().display()))?;
+
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Checks that the model and the account of every device are known, and that their calibrations are valid.
+    pub fn validate(&self) -> Result<(), Error> {
+        for (name, device) in &self.devices {
+            device.kind().map_err(|err| match err {
+                Error::Validation { message, .. } => Error::Validation {
+                    field: format!("devices.{name}.model"),
+                    message,
+                },
+                err => err,
+            })?;
+
+            device.calibration.validate().map_err(|err| match err {
+                Error::Validation { field, message } => Error::Validation {
+                    field: format!("devices.{name}.calibration.{field}"),
+                    message,
+                },
+                err => err,
+            })?;
+
+            if let Some(account) = &device.account {
+                let message = if device.credentials.is_some() {
+                    Some("A device can't have both an account and credentials".to_string())
+                } else if !self.accounts.contains_key(account) {
+                    Some(format!("Unknown account `{account}`"))
+                } else {
+                    None
+                };
+
+                if let Some(message) = message {
+                    return Err(Error::Validation {
+                        field: format!("devices.{name}.account"),
+                        message,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the credentials of `device`, those of its account or the default ones.
+    pub(crate) fn credentials_of<'c>(&'c self, device: &'c DeviceConfig) -> &'c Credentials {
+        device
+            .account
+            .as_ref()
+            .and_then(|account| self.accounts.get(account))
+            .or(device.credentials.as_ref())
+            .unwrap_or(&self.credentials)
+    }
+}
+
+impl DeviceConfig {
+    /// Returns the [`DeviceKind`] for the model of the device.
+    pub fn kind(&self) -> Result<DeviceKind, Error> {
+        DeviceKind::from_model(&self.model).ok_or_else(|| Error::Validation {
+            field: "model".to_string(),
+            message: format!("Unknown model `{}`", self.model),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_rejects_unknown_model() {
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "credentials": { "username": "username", "password": "password" },
+            "devices": {
+                "kitchen": { "model": "p110", "address": "192.168.1.100" },
+                "attic": { "model": "X999", "address": "192.168.1.101" }
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(
+            config.devices["kitchen"].kind().unwrap(),
+            DeviceKind::PlugEnergyMonitoring
+        );
+        assert!(matches!(
+            config.validate(),
+            Err(Error::Validation { field, .. }) if field == "devices.attic.model"
+        ));
+    }
+
+    #[test]
+    fn devices_use_the_credentials_of_their_account() {
+        let mut config: Config = serde_json::from_value(serde_json::json!({
+            "credentials": { "username": "username", "password": "password" },
+            "devices": {
+                "kitchen": { "model": "P110", "address": "192.168.1.100" },
+                "flat-2-heater": {
+                    "model": "P110",
+                    "address": "192.168.2.100",
+                    "account": "flat-2",
+                    "room": "flat-2-kitchen",
+                    "tags": ["heating"]
+                }
+            },
+            "accounts": {
+                "flat-2": { "username": "flat-2-username", "password": "flat-2-password" }
+            }
+        }))
+        .unwrap();
+
+        config.validate().unwrap();
+        assert_eq!(
+            config.devices["flat-2-heater"].labels,
+            DeviceLabels::in_room("flat-2-kitchen").with_tag("heating")
+        );
+        assert_eq!(
+            config.credentials_of(&config.devices["kitchen"]).username,
+            "username"
+        );
+        assert_eq!(
+            config
+                .credentials_of(&config.devices["flat-2-heater"])
+                .username,
+            "flat-2-username"
+        );
+
+        config.accounts.clear();
+        assert!(matches!(
+            config.validate(),
+            Err(Error::Validation { field, .. }) if field == "devices.flat-2-heater.account"
+        ));
+    }
+}
@@ -0,0 +1,301 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use log::{debug, warn};
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::error::Error;
+use crate::manager::{Config, DeviceManager};
+use crate::ApiClient;
+
+/// A change to the devices of a [`DeviceManager`], applied by a [`ConfigWatcher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceLifecycleEvent {
+    /// A device has been added to the configuration and registered.
+    Added {
+        /// The name of the device.
+        name: String,
+    },
+    /// A device has been removed from the configuration and unregistered.
+    Removed {
+        /// The name of the device.
+        name: String,
+    },
+    /// The address of a device has changed and the device has been registered again.
+    Readdressed {
+        /// The name of the device.
+        name: String,
+        /// The previous address of the device.
+        previous_address: String,
+        /// The new address of the device.
+        current_address: String,
+    },
+    /// The model or the credentials of a device have changed and the device has been registered again.
+    Modified {
+        /// The name of the device.
+        name: String,
+    },
+    /// A device couldn't be registered after a change. It stays unregistered until the next change.
+    Failed {
+        /// The name of the device.
+        name: String,
+        /// The reason of the failure.
+        message: String,
+    },
+}
+
+impl DeviceLifecycleEvent {
+    fn name(&self) -> &str {
+        match self {
+            Self::Added { name }
+            | Self::Removed { name }
+            | Self::Readdressed { name, .. }
+            | Self::Modified { name }
+            | Self::Failed { name, .. } => name,
+        }
+    }
+
+    /// Returns the events that turn the devices of `previous` into the devices of `current`.
+    pub(crate) fn diff(previous: &Config, current: &Config) -> Vec<Self> {
+        let mut events = Vec::new();
+
+        for name in previous.devices.keys() {
+            if !current.devices.contains_key(name) {
+                events.push(Self::Removed { name: name.clone() });
+            }
+        }
+
+        for (name, device) in &current.devices {
+            let name = name.clone();
+
+            let Some(previous_device) = previous.devices.get(&name) else {
+                events.push(Self::Added { name });
+                continue;
+            };
+
+            if previous_device.model != device.model
+                || previous.credentials_of(previous_device) != current.credentials_of(device)
+            {
+                events.push(Self::Modified { name });
+            } else if previous_device.address != device.address {
+                events.push(Self::Readdressed {
+                    name,
+                    previous_address: previous_device.address.clone(),
+                    current_address: device.address.clone(),
+                });
+            }
+        }
+
+        events
+    }
+}
+
+/// Watches the configuration file of a [`DeviceManager`] and applies the changes to its devices at runtime,
+/// so that long-running applications don't need to be restarted when devices are added, removed or re-addressed.
+///
+/// The file is checked for modifications at a fixed interval.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use tapo::manager::{ConfigWatcher, DeviceManager};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+///
+/// let watcher = ConfigWatcher::new(manager.clone(), "devices.json", Duration::from_secs(10))?;
+/// tokio::spawn(watcher.run(|event| println!("Device change: {event:?}")));
+/// # Ok(())
+/// # }
+/// ```
+pub struct ConfigWatcher {
+    manager: DeviceManager,
+    path: PathBuf,
+    interval: Interval,
+    config: Config,
+    modified: Option<SystemTime>,
+}
+
+impl ConfigWatcher {
+    /// Returns a new [`ConfigWatcher`] for the configuration file at `path`.
+    /// The current contents of the file are expected to be registered with `manager` already,
+    /// e.g. by [`DeviceManager::from_config`].
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager to apply the changes to
+    /// * `path` - the path of the configuration file
+    /// * `interval` - the time between two checks of the file
+    pub fn new(
+        manager: DeviceManager,
+        path: impl Into<PathBuf>,
+        interval: Duration,
+    ) -> Result<Self, Error> {
+        let path = path.into();
+        let modified = modified(&path);
+        let config = Config::load(&path)?;
+
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        Ok(Self {
+            manager,
+            path,
+            interval,
+            config,
+            modified,
+        })
+    }
+
+    /// Returns the configuration that is currently applied.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Waits for the next check and applies the changes made to the file since the previous one.
+    /// An invalid file is reported as an error and ignored until it's modified again.
+    pub async fn poll(&mut self) -> Result<Vec<DeviceLifecycleEvent>, Error> {
+        self.interval.tick().await;
+
+        let modified = modified(&self.path);
+        if modified == self.modified {
+            return Ok(Vec::new());
+        }
+        self.modified = modified;
+
+        debug!("{} has been modified, reloading...", self.path.display());
+        let config = Config::load(&self.path)?;
+        config.validate()?;
+
+        let mut events = Vec::new();
+
+        for event in DeviceLifecycleEvent::diff(&self.config, &config) {
+            let result = match &event {
+                DeviceLifecycleEvent::Removed { name } => {
+                    self.manager.unregister(name.as_str()).await.map(|_| ())
+                }
+                _ => self.register(&config, event.name()).await,
+            };
+
+            match result {
+                Ok(()) => events.push(event),
+                Err(err) => {
+                    let name = event.name().to_string();
+                    self.manager.unregister(name.as_str()).await?;
+
+                    events.push(DeviceLifecycleEvent::Failed {
+                        name,
+                        message: err.to_string(),
+                    });
+                }
+            }
+        }
+
+        // The room, the tags and the calibration are changed without registering the device again.
+        for (name, device) in &config.devices {
+            let Some(previous) = self.config.devices.get(name) else {
+                continue;
+            };
+            if events.iter().any(|event| event.name() == name) {
+                continue;
+            }
+
+            if previous.labels != device.labels {
+                if let Err(err) = self.manager.set_labels(name, device.labels.clone()).await {
+                    warn!("Failed to change the labels of device `{name}`: {err:?}");
+                }
+            }
+            if previous.calibration != device.calibration {
+                if let Err(err) = self.manager.set_calibration(name, device.calibration).await {
+                    warn!("Failed to change the calibration of device `{name}`: {err:?}");
+                }
+            }
+        }
+
+        self.config = config;
+
+        Ok(events)
+    }
+
+    /// Watches the file forever and calls `on_event` for every change.
+    /// Errors are logged and the watching continues.
+    pub async fn run(mut self, mut on_event: impl FnMut(DeviceLifecycleEvent)) {
+        loop {
+            match self.poll().await {
+                Ok(events) => events.into_iter().for_each(&mut on_event),
+                Err(err) => warn!("Failed to reload {}: {err:?}", self.path.display()),
+            }
+        }
+    }
+
+    async fn register(&self, config: &Config, name: &str) -> Result<(), Error> {
+        let device = &config.devices[name];
+        let credentials = config.credentials_of(device);
+        let client = ApiClient::new(&credentials.username, &credentials.password)?;
+
+        self.manager
+            .register_with_client(
+                client,
+                name.to_string(),
+                device.kind()?,
+                device.address.clone(),
+            )
+            .await?;
+        self.manager.set_labels(name, device.labels.clone()).await?;
+        self.manager.set_calibration(name, device.calibration).await
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(devices: serde_json::Value) -> Config {
+        serde_json::from_value(serde_json::json!({
+            "credentials": { "username": "username", "password": "password" },
+            "devices": devices,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn diff_reports_device_changes() {
+        let previous = config(serde_json::json!({
+            "kitchen": { "model": "P110", "address": "192.168.1.100" },
+            "hallway": { "model": "L510", "address": "192.168.1.101" },
+            "garage": { "model": "P100", "address": "192.168.1.102" },
+        }));
+        let current = config(serde_json::json!({
+            "kitchen": { "model": "P110", "address": "192.168.1.110" },
+            "hallway": { "model": "L530", "address": "192.168.1.101" },
+            "office": { "model": "P100", "address": "192.168.1.103" },
+        }));
+
+        assert_eq!(
+            DeviceLifecycleEvent::diff(&previous, &current),
+            vec![
+                DeviceLifecycleEvent::Removed {
+                    name: "garage".to_string()
+                },
+                DeviceLifecycleEvent::Modified {
+                    name: "hallway".to_string()
+                },
+                DeviceLifecycleEvent::Readdressed {
+                    name: "kitchen".to_string(),
+                    previous_address: "192.168.1.100".to_string(),
+                    current_address: "192.168.1.110".to_string(),
+                },
+                DeviceLifecycleEvent::Added {
+                    name: "office".to_string()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use tokio::sync::{mpsc, oneshot};
+
+use crate::error::Error;
+use crate::manager::{stopped, Action, Command};
+use crate::requests::{
+    adjusted_brightness, nearest_color_temperature, stepped_color_temperature, Brightness, Hue,
+    IntoLightProperty, Kelvin, Ringtone, Saturation,
+};
+use crate::responses::{
+    Capabilities, EnergyUsageResult, FirmwareDownloadStateResult, LatestFirmwareResult,
+};
+
+/// Handle for controlling a device registered with a [`crate::manager::DeviceManager`],
+/// see [`crate::manager::DeviceManager::device`].
+///
+/// Requests are sent to the task of the manager, which applies rate limiting and retries before running them.
+#[derive(Debug, Clone)]
+pub struct DeviceHandle {
+    name: String,
+    sender: mpsc::Sender<Command>,
+}
+
+impl DeviceHandle {
+    pub(crate) fn new(name: String, sender: mpsc::Sender<Command>) -> Self {
+        Self { name, sender }
+    }
+
+    /// Returns the name that the device is registered under.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Turns *on* the device.
+    /// Returns [`Error::NotSupported`] for hubs.
+    pub async fn on(&self) -> Result<(), Error> {
+        self.execute(Action::On).await.map(|_| ())
+    }
+
+    /// Turns *off* the device.
+    /// Returns [`Error::NotSupported`] for hubs.
+    pub async fn off(&self) -> Result<(), Error> {
+        self.execute(Action::Off).await.map(|_| ())
+    }
+
+    /// Turns the device *off* if the *device info* reports that it's *on*, and *on* otherwise.
+    /// Returns whether the device has been turned *on*, or [`Error::NotSupported`] for hubs.
+    pub async fn toggle(&self) -> Result<bool, Error> {
+        let device_info = self.get_device_info_json().await?;
+        let device_on = device_info["device_on"] != true;
+
+        if device_on {
+            self.on().await?;
+        } else {
+            self.off().await?;
+        }
+
+        Ok(device_on)
+    }
+
+    /// Sets the *brightness* and turns *on* the device.
+    /// Returns [`Error::NotSupported`] for the devices other than lights.
+    ///
+    /// # Arguments
+    ///
+    /// * `brightness` - between 1 and 100, see [`Brightness::RANGE`]
+    pub async fn set_brightness(
+        &self,
+        brightness: impl IntoLightProperty<Brightness>,
+    ) -> Result<(), Error> {
+        self.execute(Action::SetBrightness(brightness.into_raw()))
+            .await
+            .map(|_| ())
+    }
+
+    /// Sets the *hue*, *saturation* and turns *on* the device.
+    /// Returns [`Error::NotSupported`] for the devices other than color lights.
+    ///
+    /// # Arguments
+    ///
+    /// * `hue` - between 1 and 360, see [`Hue::RANGE`]
+    /// * `saturation` - between 1 and 100, see [`Saturation::RANGE`]
+    pub async fn set_hue_saturation(
+        &self,
+        hue: impl IntoLightProperty<Hue>,
+        saturation: impl IntoLightProperty<Saturation>,
+    ) -> Result<(), Error> {
+        self.execute(Action::SetHueSaturation(
+            hue.into_raw(),
+            saturation.into_raw(),
+        ))
+        .await
+        .map(|_| ())
+    }
+
+    /// Sets the *color temperature* and turns *on* the device.
+    /// Returns [`Error::NotSupported`] for the devices other than color lights.
+    ///
+    /// # Arguments
+    ///
+    /// * `color_temperature` - in Kelvin, within the range supported by the device
+    pub async fn set_color_temperature(
+        &self,
+        color_temperature: impl IntoLightProperty<Kelvin>,
+    ) -> Result<(), Error> {
+        self.execute(Action::SetColorTemperature(color_temperature.into_raw()))
+            .await
+            .map(|_| ())
+    }
+
+    /// Changes the *brightness* by `delta` percentage points, clamped between 1 and 100, and turns *on* the device.
+    /// Returns the new *brightness*, or [`Error::NotSupported`] for the devices other than lights.
+    ///
+    /// The firmware has no method for relative changes, and the requests to a device run concurrently,
+    /// so the current *brightness* is read from the *device info* first and concurrent adjustments can overwrite each other.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - the change of the *brightness*, negative to dim the light
+    pub async fn adjust_brightness(&self, delta: i8) -> Result<u8, Error> {
+        let device_info = self.get_device_info_json().await?;
+        let current = device_info["brightness"]
+            .as_u64()
+            .ok_or_else(|| Error::NotSupported {
+                feature: "Brightness".to_string(),
+            })?;
+
+        let brightness = adjusted_brightness(current.min(u8::MAX.into()) as u8, delta);
+        self.set_brightness(brightness).await?;
+
+        Ok(brightness)
+    }
+
+    /// Changes the *color temperature* by `delta` Kelvin, clamped to the range reported by the device, and turns *on* the device.
+    /// Returns the new *color temperature*, or [`Error::NotSupported`] for the devices other than color lights.
+    ///
+    /// Like [`DeviceHandle::adjust_brightness`], concurrent steps can overwrite each other.
+    /// When the device shows a color, the nearest *color temperature* to that color is changed instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta` - the change of the *color temperature*, negative for a warmer light
+    pub async fn step_color_temperature(&self, delta: i16) -> Result<u16, Error> {
+        let device_info = self.get_device_info_json().await?;
+        let value = |field: &str| device_info[field].as_u64().map(|value| value as u16);
+
+        let current = value("color_temp").ok_or_else(|| Error::NotSupported {
+            feature: "Color temperature".to_string(),
+        })?;
+        let range = serde_json::from_value::<[u16; 2]>(device_info["color_temp_range"].clone())
+            .ok()
+            .filter(|[min, max]| *min > 0 && min <= max)
+            .map(|[min, max]| min..=max)
+            .unwrap_or(Kelvin::RANGE);
+        let current = match current {
+            0 => nearest_color_temperature(
+                value("hue").unwrap_or_default(),
+                value("saturation").unwrap_or_default().min(100) as u8,
+                range.clone(),
+            ),
+            color_temperature => color_temperature,
+        };
+
+        let color_temperature = stepped_color_temperature(current, delta, &range);
+        self.set_color_temperature(color_temperature).await?;
+
+        Ok(color_temperature)
+    }
+
+    /// Returns *device info* as [`serde_json::Value`].
+    /// It contains all the properties returned from the Tapo API.
+    pub async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
+        self.execute(Action::GetDeviceInfo).await
+    }
+
+    /// Returns the [`Capabilities`] of the device, derived from its *component list*.
+    pub async fn get_capabilities(&self) -> Result<Capabilities, Error> {
+        let capabilities = self.execute(Action::GetCapabilities).await?;
+
+        Ok(serde_json::from_value(capabilities)?)
+    }
+
+    /// Returns the *energy usage* of the device.
+    /// Returns [`Error::NotSupported`] for the devices other than plugs with energy monitoring.
+    pub async fn get_energy_usage(&self) -> Result<EnergyUsageResult, Error> {
+        let energy_usage = self.execute(Action::GetEnergyUsage).await?;
+
+        Ok(serde_json::from_value(energy_usage)?)
+    }
+
+    /// Returns the latest firmware available for the device as [`LatestFirmwareResult`].
+    pub async fn get_latest_firmware(&self) -> Result<LatestFirmwareResult, Error> {
+        let latest_firmware = self.execute(Action::GetLatestFirmware).await?;
+
+        Ok(serde_json::from_value(latest_firmware)?)
+    }
+
+    /// Starts the firmware update of the device.
+    /// The progress can be followed with [`DeviceHandle::get_firmware_download_state`].
+    pub async fn start_firmware_update(&self) -> Result<(), Error> {
+        self.execute(Action::StartFirmwareUpdate).await.map(|_| ())
+    }
+
+    /// Returns the progress of the firmware update of the device as [`FirmwareDownloadStateResult`].
+    pub async fn get_firmware_download_state(&self) -> Result<FirmwareDownloadStateResult, Error> {
+        let state = self.execute(Action::GetFirmwareDownloadState).await?;
+
+        Ok(serde_json::from_value(state)?)
+    }
+
+    /// Plays the alarm of the hub. Returns [`Error::NotSupported`] for the devices other than hubs.
+    ///
+    /// # Arguments
+    ///
+    /// * `ringtone` - the ringtone, or `None` for the ringtone of the alarm configuration of the hub
+    /// * `duration` - how long the alarm plays for, in seconds, or `None` for the duration of the alarm configuration
+    pub async fn play_alarm(
+        &self,
+        ringtone: Option<Ringtone>,
+        duration: Option<u64>,
+    ) -> Result<(), Error> {
+        self.execute(Action::PlayAlarm(ringtone, duration))
+            .await
+            .map(|_| ())
+    }
+
+    /// Stops the alarm of the hub. Returns [`Error::NotSupported`] for the devices other than hubs.
+    pub async fn stop_alarm(&self) -> Result<(), Error> {
+        self.execute(Action::StopAlarm).await.map(|_| ())
+    }
+
+    async fn execute(&self, action: Action) -> Result<serde_json::Value, Error> {
+        let (reply, response) = oneshot::channel();
+
+        self.sender
+            .send(Command::Execute {
+                name: self.name.clone(),
+                action,
+                reply,
+            })
+            .await
+            .map_err(|_| stopped())?;
+
+        response.await.map_err(|_| stopped())?
+    }
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+/// The kind of handler used by the [`crate::manager::DeviceManager`] to control a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceKind {
+    /// A device controlled with a [`crate::GenericDeviceHandler`].
+    Generic,
+    /// A light bulb controlled with a [`crate::LightHandler`] (L510, L520, L610).
+    Light,
+    /// A color light bulb controlled with a [`crate::ColorLightHandler`] (L530, L630, L900).
+    ColorLight,
+    /// A color light strip controlled with a [`crate::ColorLightStripHandler`] (L920, L930).
+    ColorLightStrip,
+    /// A plug controlled with a [`crate::PlugHandler`] (P100, P105).
+    Plug,
+    /// A plug with energy monitoring controlled with a [`crate::PlugEnergyMonitoringHandler`] (P110, P115).
+    PlugEnergyMonitoring,
+    /// A hub controlled with a [`crate::HubHandler`] (H100).
+    Hub,
+}
+
+impl DeviceKind {
+    /// Returns the [`DeviceKind`] for the given model, e.g. `P110` or `L530`, or `None` if the model is unknown.
+    /// Use `generic` for the devices that aren't supported by a specific handler.
+    pub fn from_model(model: &str) -> Option<Self> {
+        let kind = match model.to_ascii_uppercase().as_str() {
+            "GENERIC" => Self::Generic,
+            "L510" | "L520" | "L610" => Self::Light,
+            "L530" | "L630" | "L900" => Self::ColorLight,
+            "L920" | "L930" => Self::ColorLightStrip,
+            "P100" | "P105" => Self::Plug,
+            "P110" | "P115" => Self::PlugEnergyMonitoring,
+            "H100" => Self::Hub,
+            _ => return None,
+        };
+
+        Some(kind)
+    }
+}
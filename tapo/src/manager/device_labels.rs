@@ -0,0 +1,41 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+/// The room and the tags of a device of a [`crate::manager::DeviceManager`], for addressing groups of devices,
+/// see [`crate::manager::DeviceManager::devices_in_room`] and [`crate::manager::DeviceManager::devices_with_tag`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceLabels {
+    /// The room of the device, e.g. `kitchen`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
+    /// The tags of the device, e.g. `heating`.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+}
+
+impl DeviceLabels {
+    /// Returns [`DeviceLabels`] for the devices in `room`.
+    pub fn in_room(room: impl Into<String>) -> Self {
+        Self {
+            room: Some(room.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Adds `tag` to the tags.
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.insert(tag.into());
+        self
+    }
+
+    /// Returns `true` if the device is in `room`.
+    pub fn is_in_room(&self, room: &str) -> bool {
+        self.room.as_deref() == Some(room)
+    }
+
+    /// Returns `true` if the device has `tag`.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+}
@@ -0,0 +1,1507 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+#[cfg(feature = "instance-lock")]
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{debug, info, warn};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+use crate::error::{Error, TapoResponseError};
+#[cfg(feature = "instance-lock")]
+use crate::instance_lock::InstanceLock;
+use crate::manager::{
+    update_firmware, Action, Config, DeviceHandle, DeviceKind, DeviceLabels, FirmwareOutcome,
+    FirmwareRolloutEntry, FirmwareRolloutReport, Inventory, InventoryEntry, ManagedDevice,
+    ManagedDeviceEvent, Watchdog, WatchdogPolicy,
+};
+use crate::readings::{Calibration, Reading};
+#[cfg(feature = "simulator")]
+use crate::simulator::FakeDevice;
+use crate::ApiClient;
+
+const COMMAND_BUFFER: usize = 64;
+const EVENT_BUFFER: usize = 64;
+
+/// Controls many devices through a single task that owns all the device sessions.
+///
+/// The [`DeviceManager`] is a cheap handle to that task and can be cloned and shared freely.
+/// Every request goes through the task, which centralizes:
+///
+/// * rate limiting - requests to the same device are spaced by at least [`DeviceManagerBuilder::min_request_interval`]
+/// * retries - requests failing because the device is unreachable or the session has expired are retried
+/// * address re-resolution - when the [`ApiClient`] has a [`crate::DeviceRegistry`] attached,
+///   devices that get a new IP address are rediscovered before the request is retried
+/// * supervision - with a [`WatchdogPolicy`], see [`DeviceManagerBuilder::watchdog`],
+///   the devices are probed periodically and reconnected to once they have become unreachable
+/// * maintenance mode - requests to the devices in maintenance are rejected without being sent,
+///   see [`DeviceManager::set_maintenance`]
+///
+/// The task stops once all the clones of the [`DeviceManager`] and its [`DeviceHandle`]s have been dropped.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use tapo::ApiClient;
+/// # use tapo::manager::{DeviceKind, DeviceManager};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = ApiClient::new("tapo-username@example.com", "tapo-password")?;
+/// let manager = DeviceManager::new(client);
+///
+/// manager
+///     .register("kitchen", DeviceKind::Plug, "192.168.1.100")
+///     .await?;
+///
+/// manager.device("kitchen").on().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeviceManager {
+    client: ApiClient,
+    sender: mpsc::Sender<Command>,
+    watchdog: Option<WatchdogPolicy>,
+    events: broadcast::Sender<ManagedDeviceEvent>,
+}
+
+impl DeviceManager {
+    /// Returns a new [`DeviceManager`] with the default options and spawns its task.
+    /// Must be called from within a Tokio runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - the client used to connect to the registered devices
+    pub fn new(client: ApiClient) -> Self {
+        Self::builder(client).build()
+    }
+
+    /// Returns a [`DeviceManagerBuilder`] for customizing the rate limiting and the retries.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - the client used to connect to the registered devices
+    pub fn builder(client: ApiClient) -> DeviceManagerBuilder {
+        DeviceManagerBuilder {
+            client,
+            min_request_interval: Duration::from_millis(100),
+            max_retries: 2,
+            retry_delay: Duration::from_millis(500),
+            watchdog: None,
+            #[cfg(feature = "instance-lock")]
+            instance_lock: None,
+        }
+    }
+
+    /// Reads the [`Config`] from the JSON file at `path`, then returns a new [`DeviceManager`] with all its devices registered.
+    /// Must be called from within a Tokio runtime.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the path of the configuration file, see [`Config`] for its format
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use tapo::manager::DeviceManager;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let manager = DeviceManager::from_config("devices.json").await?;
+    ///
+    /// manager.device("kitchen").on().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn from_config(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_parsed_config(Config::load(path)?).await
+    }
+
+    /// Returns a new [`DeviceManager`] with all the devices of `config` registered.
+    /// The devices are connected to concurrently. Must be called from within a Tokio runtime.
+    pub async fn from_parsed_config(config: Config) -> Result<Self, Error> {
+        config.validate()?;
+
+        let client = ApiClient::new(config.credentials.username, config.credentials.password)?;
+        let manager = Self::new(client);
+        let mut accounts = HashMap::new();
+        let mut registrations = JoinSet::new();
+
+        for (account, credentials) in config.accounts {
+            let client = ApiClient::new(credentials.username, credentials.password)?;
+            accounts.insert(account, client);
+        }
+
+        for (name, device) in config.devices {
+            let manager = manager.clone();
+            let account = device
+                .account
+                .as_ref()
+                .and_then(|account| accounts.get(account))
+                .cloned();
+
+            registrations.spawn(async move {
+                let kind = device.kind()?;
+                let client = match (account, device.credentials) {
+                    (Some(client), _) => client,
+                    (None, Some(credentials)) => {
+                        ApiClient::new(credentials.username, credentials.password)?
+                    }
+                    (None, None) => manager.client.clone(),
+                };
+
+                manager
+                    .register_with_client(client, name.clone(), kind, device.address)
+                    .await
+                    .map_err(|err| {
+                        warn!("Failed to register device `{name}`: {err:?}");
+                        err
+                    })?;
+                manager.set_labels(name.clone(), device.labels).await?;
+                manager.set_calibration(name, device.calibration).await
+            });
+        }
+
+        while let Some(result) = registrations.join_next().await {
+            result.map_err(anyhow::Error::from)??;
+        }
+
+        Ok(manager)
+    }
+
+    /// Connects to the device at `ip_address` and registers it under `name`.
+    /// A device that is already registered under the same name is replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name used to refer to the device, see [`DeviceManager::device`]
+    /// * `kind` - the kind of handler used to control the device
+    /// * `ip_address` - the IP address of the device
+    pub async fn register(
+        &self,
+        name: impl Into<String>,
+        kind: DeviceKind,
+        ip_address: impl Into<String>,
+    ) -> Result<(), Error> {
+        self.register_with_client(self.client.clone(), name, kind, ip_address)
+            .await
+    }
+
+    /// Like [`DeviceManager::register`], but connects to the device with `client` instead of the client of the manager,
+    /// for the devices bound to other Tapo accounts.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - the client with the credentials of the account of the device
+    /// * `name` - the name used to refer to the device, see [`DeviceManager::device`]
+    /// * `kind` - the kind of handler used to control the device
+    /// * `ip_address` - the IP address of the device
+    pub async fn register_with_client(
+        &self,
+        client: ApiClient,
+        name: impl Into<String>,
+        kind: DeviceKind,
+        ip_address: impl Into<String>,
+    ) -> Result<(), Error> {
+        let (name, ip_address) = (name.into(), ip_address.into());
+        let device = ManagedDevice::connect(client.clone(), kind, ip_address.clone()).await?;
+
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Register {
+            name: name.clone(),
+            device,
+            reply,
+        })
+        .await?;
+        let registration = response.await.map_err(|_| stopped())?;
+
+        if let Some(policy) = self.watchdog.clone() {
+            let watchdog = Watchdog {
+                policy,
+                name,
+                registration,
+                client,
+                kind,
+                ip_address,
+                commands: self.sender.downgrade(),
+                events: self.events.clone(),
+            };
+            tokio::spawn(watchdog.run());
+        }
+
+        Ok(())
+    }
+
+    /// Registers a [`FakeDevice`] under `name`, to test the automations built on the manager without real devices.
+    /// A device that is already registered under the same name is replaced.
+    ///
+    /// The fake isn't supervised by the watchdog, see [`DeviceManagerBuilder::watchdog`], since it never needs to reconnect,
+    /// and `kind` restricts the requests it accepts like it does for the real devices.
+    /// Requires the `simulator` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name used to refer to the device, see [`DeviceManager::device`]
+    /// * `kind` - the kind of handler that the device stands in for
+    /// * `device` - the fake device, whose clones share its state
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use tapo::manager::{DeviceKind, DeviceManager};
+    /// # use tapo::simulator::FakeDevice;
+    /// # use tapo::ApiClient;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let manager = DeviceManager::new(ApiClient::new("username", "password")?);
+    /// let lamp = FakeDevice::light();
+    /// manager
+    ///     .register_fake("lamp", DeviceKind::ColorLight, lamp.clone())
+    ///     .await?;
+    ///
+    /// manager.device("lamp").set_brightness(30).await?;
+    /// assert_eq!(lamp.device_info()["brightness"], 30);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "simulator")]
+    pub async fn register_fake(
+        &self,
+        name: impl Into<String>,
+        kind: DeviceKind,
+        device: FakeDevice,
+    ) -> Result<(), Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Register {
+            name: name.into(),
+            device: ManagedDevice::Fake(kind, device),
+            reply,
+        })
+        .await?;
+        response.await.map_err(|_| stopped())?;
+
+        Ok(())
+    }
+
+    /// Removes the device registered under `name`.
+    /// Returns `true` if such a device was registered.
+    pub async fn unregister(&self, name: impl Into<String>) -> Result<bool, Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Unregister {
+            name: name.into(),
+            reply,
+        })
+        .await?;
+
+        response.await.map_err(|_| stopped())
+    }
+
+    /// Replaces the room and the tags of the device registered under `name`.
+    /// They are kept when the device is registered again under the same name and dropped when it's unregistered.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the device
+    /// * `labels` - the room and the tags of the device
+    pub async fn set_labels(
+        &self,
+        name: impl Into<String>,
+        labels: DeviceLabels,
+    ) -> Result<(), Error> {
+        let name = name.into();
+        let (reply, response) = oneshot::channel();
+        self.send(Command::SetLabels {
+            name: name.clone(),
+            labels,
+            reply,
+        })
+        .await?;
+
+        if !response.await.map_err(|_| stopped())? {
+            return Err(Error::Validation {
+                field: "name".to_string(),
+                message: format!("No device is registered as `{name}`"),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the room and the tags of every registered device, by name.
+    pub async fn labels(&self) -> Result<BTreeMap<String, DeviceLabels>, Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Labels { reply }).await?;
+
+        response.await.map_err(|_| stopped())
+    }
+
+    /// Replaces the corrections applied to the readings of the device registered under `name`, see [`DeviceManager::calibrate`].
+    /// They are kept when the device is registered again under the same name and dropped when it's unregistered.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the device
+    /// * `calibration` - the corrections, see [`Calibration::validate`]
+    pub async fn set_calibration(
+        &self,
+        name: impl Into<String>,
+        calibration: Calibration,
+    ) -> Result<(), Error> {
+        calibration.validate()?;
+
+        let name = name.into();
+        let (reply, response) = oneshot::channel();
+        self.send(Command::SetCalibration {
+            name: name.clone(),
+            calibration,
+            reply,
+        })
+        .await?;
+
+        if !response.await.map_err(|_| stopped())? {
+            return Err(Error::Validation {
+                field: "name".to_string(),
+                message: format!("No device is registered as `{name}`"),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the calibration of every registered device, by name.
+    pub async fn calibrations(&self) -> Result<BTreeMap<String, Calibration>, Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Calibrations { reply }).await?;
+
+        response.await.map_err(|_| stopped())
+    }
+
+    /// Puts the device registered under `name` in or out of maintenance mode,
+    /// e.g. while it's unplugged for cleaning or moved to another room.
+    ///
+    /// While in maintenance, the requests to the device fail with [`Error::Maintenance`] without being sent,
+    /// and the watchdog stops probing and reconnecting to it, so that it isn't reported offline.
+    /// The scheduler, the rule engine and the scenes skip the device instead of reporting failures.
+    /// The flag is kept when the device is registered again under the same name and dropped when it's unregistered.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the device
+    /// * `enabled` - `true` to put the device in maintenance, `false` to take it out
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use tapo::manager::DeviceManager;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let manager = DeviceManager::from_config("devices.json").await?;
+    ///
+    /// manager.set_maintenance("dehumidifier", true).await?;
+    /// // The dehumidifier is unplugged and cleaned...
+    /// manager.set_maintenance("dehumidifier", false).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_maintenance(
+        &self,
+        name: impl Into<String>,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        let name = name.into();
+        let (reply, response) = oneshot::channel();
+        self.send(Command::SetMaintenance {
+            name: name.clone(),
+            enabled,
+            reply,
+        })
+        .await?;
+
+        if !response.await.map_err(|_| stopped())? {
+            return Err(Error::Validation {
+                field: "name".to_string(),
+                message: format!("No device is registered as `{name}`"),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the names of the registered devices that are in maintenance mode, see [`DeviceManager::set_maintenance`].
+    pub async fn devices_in_maintenance(&self) -> Result<BTreeSet<String>, Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Maintenance { reply }).await?;
+
+        response.await.map_err(|_| stopped())
+    }
+
+    /// Returns `readings` corrected with the calibration of their device, see [`DeviceManager::set_calibration`].
+    /// The readings of the devices that aren't registered are returned unchanged.
+    pub async fn calibrate(
+        &self,
+        readings: impl IntoIterator<Item = Reading>,
+    ) -> Result<Vec<Reading>, Error> {
+        let calibrations = self.calibrations().await?;
+
+        Ok(readings
+            .into_iter()
+            .map(|reading| match calibrations.get(&reading.device) {
+                Some(calibration) => calibration.apply(reading),
+                None => reading,
+            })
+            .collect())
+    }
+
+    /// Returns a [`DeviceHandle`] for every registered device in `room`, ordered by name,
+    /// e.g. for turning off all the lights of a room.
+    pub async fn devices_in_room(&self, room: &str) -> Result<Vec<DeviceHandle>, Error> {
+        self.devices_labelled(|labels| labels.is_in_room(room))
+            .await
+    }
+
+    /// Returns a [`DeviceHandle`] for every registered device with `tag`, ordered by name.
+    pub async fn devices_with_tag(&self, tag: &str) -> Result<Vec<DeviceHandle>, Error> {
+        self.devices_labelled(|labels| labels.has_tag(tag)).await
+    }
+
+    async fn devices_labelled(
+        &self,
+        predicate: impl Fn(&DeviceLabels) -> bool,
+    ) -> Result<Vec<DeviceHandle>, Error> {
+        Ok(self
+            .labels()
+            .await?
+            .into_iter()
+            .filter(|(_, labels)| predicate(labels))
+            .map(|(name, _)| self.device(name))
+            .collect())
+    }
+
+    /// Returns the kind of every registered device, by name.
+    pub async fn devices(&self) -> Result<BTreeMap<String, DeviceKind>, Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::List { reply }).await?;
+
+        response.await.map_err(|_| stopped())
+    }
+
+    /// Returns the [`Inventory`] of the registered devices, with their model, versions, addresses,
+    /// capabilities and labels. The devices are queried concurrently.
+    /// The devices that fail to answer are still listed, with the reason in [`InventoryEntry::error`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use tapo::manager::DeviceManager;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let manager = DeviceManager::from_config("devices.json").await?;
+    ///
+    /// let inventory = manager.inventory().await?;
+    /// println!("{}", serde_json::to_string_pretty(&inventory)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn inventory(&self) -> Result<Inventory, Error> {
+        let mut labels = self.labels().await?;
+        let mut queries = JoinSet::new();
+
+        for (name, kind) in self.devices().await? {
+            let entry =
+                InventoryEntry::new(name.clone(), kind, labels.remove(&name).unwrap_or_default());
+            let device = self.device(name);
+
+            queries.spawn(async move {
+                let result = async {
+                    let device_info = device.get_device_info_json().await?;
+                    let capabilities = device.get_capabilities().await?;
+                    Ok::<_, Error>((device_info, capabilities))
+                };
+
+                match result.await {
+                    Ok((device_info, capabilities)) => InventoryEntry {
+                        capabilities: Some(capabilities),
+                        ..entry.with_device_info(&device_info)
+                    },
+                    Err(err) => {
+                        debug!("Failed to query device `{}`: {err:?}", entry.name);
+                        InventoryEntry {
+                            error: Some(err.to_string()),
+                            ..entry
+                        }
+                    }
+                }
+            });
+        }
+
+        let mut devices = Vec::new();
+        while let Some(entry) = queries.join_next().await {
+            devices.push(entry.map_err(anyhow::Error::from)?);
+        }
+        devices.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Inventory {
+            generated_at: Utc::now(),
+            devices,
+        })
+    }
+
+    /// Updates the firmware of the devices selected by `filter` in waves of `max_concurrent` devices,
+    /// waiting for every device of a wave to have downloaded its firmware before starting the next one.
+    /// The devices install the firmware and reboot once it's downloaded.
+    ///
+    /// The rollout stops after the wave at which more than `abort_on_failure_rate` of the attempted updates
+    /// have failed, and the remaining devices are reported as [`FirmwareOutcome::Skipped`],
+    /// as are the devices in maintenance mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - selects the devices to update from their [`InventoryEntry`], e.g. by model or room
+    /// * `max_concurrent` - how many devices are updated at the same time
+    /// * `abort_on_failure_rate` - between 0 and 1, e.g. `0.2` to stop once more than 20% of the updates have failed
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use tapo::manager::DeviceManager;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let manager = DeviceManager::from_config("devices.json").await?;
+    ///
+    /// let report = manager
+    ///     .rollout_firmware(|device| device.model.as_deref() == Some("P110"), 2, 0.2)
+    ///     .await?;
+    /// for device in &report.devices {
+    ///     println!("{}: {:?}", device.name, device.outcome);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn rollout_firmware(
+        &self,
+        filter: impl Fn(&InventoryEntry) -> bool,
+        max_concurrent: usize,
+        abort_on_failure_rate: f64,
+    ) -> Result<FirmwareRolloutReport, Error> {
+        if max_concurrent == 0 {
+            return Err(Error::Validation {
+                field: "max_concurrent".to_string(),
+                message: "must be greater than 0".to_string(),
+            });
+        }
+        if !(0.0..=1.0).contains(&abort_on_failure_rate) {
+            return Err(Error::Validation {
+                field: "abort_on_failure_rate".to_string(),
+                message: "must be between 0 and 1".to_string(),
+            });
+        }
+
+        let names: Vec<_> = self
+            .inventory()
+            .await?
+            .devices
+            .into_iter()
+            .filter(|device| filter(device))
+            .map(|device| device.name)
+            .collect();
+        let mut report = FirmwareRolloutReport {
+            devices: Vec::new(),
+            aborted: false,
+        };
+
+        for (wave, names) in names.chunks(max_concurrent).enumerate() {
+            if report.aborted {
+                report
+                    .devices
+                    .extend(names.iter().map(|name| FirmwareRolloutEntry {
+                        name: name.clone(),
+                        wave,
+                        outcome: FirmwareOutcome::Skipped,
+                    }));
+                continue;
+            }
+
+            debug!("Updating the firmware of wave {wave}: {names:?}");
+            let mut updates = JoinSet::new();
+            for name in names {
+                let device = self.device(name.as_str());
+                updates.spawn(async move {
+                    let outcome = match update_firmware(&device).await {
+                        Ok(outcome) => outcome,
+                        Err(err) if err.is_maintenance() => FirmwareOutcome::Skipped,
+                        Err(err) => {
+                            warn!(
+                                "Failed to update the firmware of `{}`: {err:?}",
+                                device.name()
+                            );
+                            FirmwareOutcome::Failed {
+                                message: err.to_string(),
+                            }
+                        }
+                    };
+
+                    FirmwareRolloutEntry {
+                        name: device.name().to_string(),
+                        wave,
+                        outcome,
+                    }
+                });
+            }
+
+            let mut entries = Vec::new();
+            while let Some(entry) = updates.join_next().await {
+                entries.push(entry.map_err(anyhow::Error::from)?);
+            }
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            report.devices.extend(entries);
+
+            report.aborted = report.failure_rate() > abort_on_failure_rate;
+            if report.aborted {
+                warn!("Aborting the firmware rollout after wave {wave}");
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Stops the task of the manager once the requests that have already been sent have completed,
+    /// then drops the sessions of all the devices. Later requests, from any clone or handle, fail.
+    pub async fn shutdown(&self) -> Result<(), Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Shutdown { reply }).await?;
+
+        response.await.map_err(|_| stopped())
+    }
+
+    /// Returns a [`DeviceHandle`] for the device registered under `name`.
+    /// The device doesn't need to be registered yet, but requests made through the handle fail until it is.
+    pub fn device(&self, name: impl Into<String>) -> DeviceHandle {
+        DeviceHandle::new(name.into(), self.sender.clone())
+    }
+
+    /// Returns a receiver of the events of the registered devices.
+    /// A [`crate::events::DeviceEvent::PowerStateChanged`] or a [`crate::events::DeviceEvent::LightStateChanged`]
+    /// is sent whenever a request made through the manager changes the state of a device.
+    /// With a [`WatchdogPolicy`], a [`crate::events::DeviceEvent::Offline`] is sent when a device becomes unreachable
+    /// and a [`crate::events::DeviceEvent::Online`] once it has been reconnected to.
+    /// Events sent before the call aren't received, and a receiver that lags behind by more than 64 events misses the oldest ones.
+    pub fn subscribe(&self) -> broadcast::Receiver<ManagedDeviceEvent> {
+        self.events.subscribe()
+    }
+
+    async fn send(&self, command: Command) -> Result<(), Error> {
+        self.sender.send(command).await.map_err(|_| stopped())
+    }
+}
+
+/// Builder for a [`DeviceManager`], see [`DeviceManager::builder`].
+#[derive(Debug, Clone)]
+pub struct DeviceManagerBuilder {
+    client: ApiClient,
+    min_request_interval: Duration,
+    max_retries: u32,
+    retry_delay: Duration,
+    watchdog: Option<WatchdogPolicy>,
+    #[cfg(feature = "instance-lock")]
+    instance_lock: Option<Arc<InstanceLock>>,
+}
+
+impl DeviceManagerBuilder {
+    /// Sets the minimum time between the start of two requests to the same device. Defaults to 100 milliseconds.
+    pub fn min_request_interval(mut self, min_request_interval: Duration) -> Self {
+        self.min_request_interval = min_request_interval;
+        self
+    }
+
+    /// Sets how many times a request is retried when the device is unreachable or the session has expired. Defaults to 2.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry, which doubles with every retry. Defaults to 500 milliseconds.
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Supervises the registered devices according to `policy`,
+    /// which replaces the reconnection logic that would otherwise be needed in every consumer.
+    /// Defaults to no supervision: an unreachable device is only retried when a request is made to it.
+    pub fn watchdog(mut self, policy: WatchdogPolicy) -> Self {
+        self.watchdog = Some(policy);
+        self
+    }
+
+    /// Keeps `lock` until the task of the manager stops, so that no other instance of the application
+    /// can control the same devices in the meantime, see [`crate::instance_lock`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// # use tapo::ApiClient;
+    /// # use tapo::instance_lock::InstanceLock;
+    /// # use tapo::manager::DeviceManager;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let client = ApiClient::new("tapo-username@example.com", "tapo-password")?;
+    /// let manager = DeviceManager::builder(client)
+    ///     .instance_lock(InstanceLock::port(47_123)?)
+    ///     .build();
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "instance-lock")]
+    pub fn instance_lock(mut self, lock: InstanceLock) -> Self {
+        self.instance_lock = Some(Arc::new(lock));
+        self
+    }
+
+    /// Builds the [`DeviceManager`] and spawns its task.
+    /// Must be called from within a Tokio runtime.
+    pub fn build(self) -> DeviceManager {
+        let (sender, receiver) = mpsc::channel(COMMAND_BUFFER);
+
+        let events = broadcast::channel(EVENT_BUFFER).0;
+
+        let (in_flight, in_flight_receiver) = mpsc::channel(1);
+        let actor = Actor {
+            devices: HashMap::new(),
+            in_flight: Some(in_flight),
+            in_flight_receiver,
+            min_request_interval: self.min_request_interval,
+            max_retries: self.max_retries,
+            retry_delay: self.retry_delay,
+            next_registration: 0,
+            events: events.clone(),
+            #[cfg(feature = "instance-lock")]
+            _instance_lock: self.instance_lock,
+        };
+        tokio::spawn(actor.run(receiver));
+
+        DeviceManager {
+            client: self.client,
+            sender,
+            watchdog: self.watchdog,
+            events,
+        }
+    }
+}
+
+pub(crate) enum Command {
+    /// Replies with the ID of the registration.
+    Register {
+        name: String,
+        device: ManagedDevice,
+        reply: oneshot::Sender<u64>,
+    },
+    /// Replaces the device of a registration. Replies whether the registration is still current.
+    Reconnect {
+        name: String,
+        registration: u64,
+        device: ManagedDevice,
+        reply: oneshot::Sender<bool>,
+    },
+    /// Replies whether the registration is still current.
+    IsRegistered {
+        name: String,
+        registration: u64,
+        reply: oneshot::Sender<bool>,
+    },
+    Unregister {
+        name: String,
+        reply: oneshot::Sender<bool>,
+    },
+    List {
+        reply: oneshot::Sender<BTreeMap<String, DeviceKind>>,
+    },
+    /// Replies whether the device is registered.
+    SetLabels {
+        name: String,
+        labels: DeviceLabels,
+        reply: oneshot::Sender<bool>,
+    },
+    Labels {
+        reply: oneshot::Sender<BTreeMap<String, DeviceLabels>>,
+    },
+    /// Replies whether the device is registered.
+    SetCalibration {
+        name: String,
+        calibration: Calibration,
+        reply: oneshot::Sender<bool>,
+    },
+    Calibrations {
+        reply: oneshot::Sender<BTreeMap<String, Calibration>>,
+    },
+    /// Replies whether the device is registered.
+    SetMaintenance {
+        name: String,
+        enabled: bool,
+        reply: oneshot::Sender<bool>,
+    },
+    /// Replies with the names of the devices in maintenance.
+    Maintenance {
+        reply: oneshot::Sender<BTreeSet<String>>,
+    },
+    Execute {
+        name: String,
+        action: Action,
+        reply: oneshot::Sender<Result<serde_json::Value, Error>>,
+    },
+    Shutdown {
+        reply: oneshot::Sender<()>,
+    },
+}
+
+struct ManagedDeviceSlot {
+    device: ManagedDevice,
+    labels: DeviceLabels,
+    calibration: Calibration,
+    maintenance: bool,
+    registration: u64,
+    next_request_at: Instant,
+}
+
+struct Actor {
+    devices: HashMap<String, ManagedDeviceSlot>,
+    /// Cloned into every request task, so that [`Actor::in_flight_receiver`] is closed once they have all completed.
+    in_flight: Option<mpsc::Sender<()>>,
+    in_flight_receiver: mpsc::Receiver<()>,
+    min_request_interval: Duration,
+    max_retries: u32,
+    retry_delay: Duration,
+    next_registration: u64,
+    events: broadcast::Sender<ManagedDeviceEvent>,
+    /// Released when the task stops.
+    #[cfg(feature = "instance-lock")]
+    _instance_lock: Option<Arc<InstanceLock>>,
+}
+
+impl Actor {
+    async fn run(mut self, mut receiver: mpsc::Receiver<Command>) {
+        while let Some(command) = receiver.recv().await {
+            match command {
+                Command::Register {
+                    name,
+                    device,
+                    reply,
+                } => {
+                    debug!("Registering device `{name}`");
+                    let registration = self.next_registration;
+                    self.next_registration += 1;
+                    let (labels, calibration, maintenance) = self
+                        .devices
+                        .remove(&name)
+                        .map(|slot| (slot.labels, slot.calibration, slot.maintenance))
+                        .unwrap_or_default();
+                    let slot = ManagedDeviceSlot {
+                        device,
+                        labels,
+                        calibration,
+                        maintenance,
+                        registration,
+                        next_request_at: Instant::now(),
+                    };
+                    self.devices.insert(name, slot);
+                    let _ = reply.send(registration);
+                }
+                Command::Reconnect {
+                    name,
+                    registration,
+                    device,
+                    reply,
+                } => {
+                    let slot = self
+                        .devices
+                        .get_mut(&name)
+                        .filter(|slot| slot.registration == registration);
+                    let is_current = slot.is_some();
+                    if let Some(slot) = slot {
+                        debug!("Replacing the session of device `{name}`");
+                        slot.device = device;
+                    }
+                    let _ = reply.send(is_current);
+                }
+                Command::IsRegistered {
+                    name,
+                    registration,
+                    reply,
+                } => {
+                    let _ = reply.send(
+                        self.devices
+                            .get(&name)
+                            .is_some_and(|slot| slot.registration == registration),
+                    );
+                }
+                Command::Unregister { name, reply } => {
+                    debug!("Unregistering device `{name}`");
+                    let _ = reply.send(self.devices.remove(&name).is_some());
+                }
+                Command::List { reply } => {
+                    let _ = reply.send(
+                        self.devices
+                            .iter()
+                            .map(|(name, slot)| (name.clone(), slot.device.kind()))
+                            .collect(),
+                    );
+                }
+                Command::SetLabels {
+                    name,
+                    labels,
+                    reply,
+                } => {
+                    let slot = self.devices.get_mut(&name);
+                    let is_registered = slot.is_some();
+                    if let Some(slot) = slot {
+                        slot.labels = labels;
+                    }
+                    let _ = reply.send(is_registered);
+                }
+                Command::Labels { reply } => {
+                    let _ = reply.send(
+                        self.devices
+                            .iter()
+                            .map(|(name, slot)| (name.clone(), slot.labels.clone()))
+                            .collect(),
+                    );
+                }
+                Command::SetCalibration {
+                    name,
+                    calibration,
+                    reply,
+                } => {
+                    let slot = self.devices.get_mut(&name);
+                    let is_registered = slot.is_some();
+                    if let Some(slot) = slot {
+                        slot.calibration = calibration;
+                    }
+                    let _ = reply.send(is_registered);
+                }
+                Command::Calibrations { reply } => {
+                    let _ = reply.send(
+                        self.devices
+                            .iter()
+                            .map(|(name, slot)| (name.clone(), slot.calibration))
+                            .collect(),
+                    );
+                }
+                Command::SetMaintenance {
+                    name,
+                    enabled,
+                    reply,
+                } => {
+                    let slot = self.devices.get_mut(&name);
+                    let is_registered = slot.is_some();
+                    if let Some(slot) = slot {
+                        if slot.maintenance != enabled {
+                            info!(
+                                "Device `{name}` has {} maintenance",
+                                if enabled { "entered" } else { "left" }
+                            );
+                        }
+                        slot.maintenance = enabled;
+                    }
+                    let _ = reply.send(is_registered);
+                }
+                Command::Maintenance { reply } => {
+                    let _ = reply.send(
+                        self.devices
+                            .iter()
+                            .filter(|(_, slot)| slot.maintenance)
+                            .map(|(name, _)| name.clone())
+                            .collect(),
+                    );
+                }
+                Command::Execute {
+                    name,
+                    action,
+                    reply,
+                } => self.execute(name, action, reply),
+                Command::Shutdown { reply } => {
+                    debug!("Shutting down the device manager");
+                    receiver.close();
+                    let mut replies = vec![reply];
+                    self.shutdown(&mut receiver, &mut replies).await;
+                    replies.into_iter().for_each(|reply| {
+                        let _ = reply.send(());
+                    });
+                    return;
+                }
+            }
+        }
+
+        debug!("All the handles have been dropped, stopping the device manager");
+    }
+
+    /// Runs the commands sent before the shutdown, waits for all the requests to complete and drops the devices.
+    async fn shutdown(
+        &mut self,
+        receiver: &mut mpsc::Receiver<Command>,
+        replies: &mut Vec<oneshot::Sender<()>>,
+    ) {
+        while let Some(command) = receiver.recv().await {
+            match command {
+                Command::Execute {
+                    name,
+                    action,
+                    reply,
+                } => self.execute(name, action, reply),
+                Command::Shutdown { reply } => replies.push(reply),
+                Command::IsRegistered { reply, .. }
+                | Command::Reconnect { reply, .. }
+                | Command::SetLabels { reply, .. }
+                | Command::SetCalibration { reply, .. }
+                | Command::SetMaintenance { reply, .. } => {
+                    let _ = reply.send(false);
+                }
+                Command::Register { .. }
+                | Command::Unregister { .. }
+                | Command::List { .. }
+                | Command::Labels { .. }
+                | Command::Calibrations { .. }
+                | Command::Maintenance { .. } => {}
+            }
+        }
+
+        self.in_flight.take();
+        while self.in_flight_receiver.recv().await.is_some() {}
+
+        self.devices.clear();
+    }
+
+    /// Schedules `action` according to the rate limit of the device and runs it on a separate task,
+    /// so that a slow or unreachable device doesn't hold up the requests to the other devices.
+    fn execute(
+        &mut self,
+        name: String,
+        action: Action,
+        reply: oneshot::Sender<Result<serde_json::Value, Error>>,
+    ) {
+        let Some(slot) = self.devices.get_mut(&name) else {
+            let _ = reply.send(Err(Error::Validation {
+                field: "name".to_string(),
+                message: format!("No device is registered as `{name}`"),
+            }));
+            return;
+        };
+        if slot.maintenance {
+            debug!("Skipping {action:?} of device `{name}` in maintenance");
+            let _ = reply.send(Err(Error::Maintenance { device: name }));
+            return;
+        }
+
+        let start_at = slot.next_request_at.max(Instant::now());
+        slot.next_request_at = start_at + self.min_request_interval;
+
+        let device = slot.device.clone();
+        let max_retries = self.max_retries;
+        let retry_delay = self.retry_delay;
+        let in_flight = self.in_flight.clone();
+        let events = self.events.clone();
+
+        tokio::spawn(async move {
+            let _in_flight = in_flight;
+            tokio::time::sleep_until(start_at).await;
+            let event = action.event();
+            let result = execute_with_retries(device, action, max_retries, retry_delay).await;
+
+            if let (Ok(_), Some(event)) = (&result, event) {
+                let _ = events.send(ManagedDeviceEvent { name, event });
+            }
+            let _ = reply.send(result);
+        });
+    }
+}
+
+async fn execute_with_retries(
+    mut device: ManagedDevice,
+    action: Action,
+    max_retries: u32,
+    retry_delay: Duration,
+) -> Result<serde_json::Value, Error> {
+    let mut attempt = 0;
+
+    loop {
+        let error = match device.execute(action.clone()).await {
+            Ok(result) => return Ok(result),
+            Err(error) => error,
+        };
+
+        let is_session_timeout = matches!(error, Error::Tapo(TapoResponseError::SessionTimeout));
+        if attempt >= max_retries || !(is_session_timeout || error.is_unreachable()) {
+            return Err(error);
+        }
+
+        debug!("{action:?} failed with {error:?}, retrying...");
+        tokio::time::sleep(retry_delay * 2u32.pow(attempt)).await;
+        attempt += 1;
+
+        if is_session_timeout {
+            device.refresh_session().await?;
+        }
+    }
+}
+
+pub(crate) fn stopped() -> Error {
+    Error::Other(anyhow::anyhow!("The device manager has stopped"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unknown_device_is_rejected() {
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+
+        let result = manager.device("garage").on().await;
+
+        assert!(matches!(
+            result,
+            Err(Error::Validation { field, .. }) if field == "name"
+        ));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn devices_are_grouped_by_room_and_tag() {
+        use crate::simulator::SimulatedFleet;
+
+        let fleet = SimulatedFleet::builder("username", "password")
+            .devices(3)
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        let labels = [
+            DeviceLabels::in_room("kitchen").with_tag("heating"),
+            DeviceLabels::in_room("kitchen"),
+            DeviceLabels::in_room("bathroom").with_tag("heating"),
+        ];
+        for ((name, labels), address) in ["oven", "kettle", "towel-rail"]
+            .into_iter()
+            .zip(labels)
+            .zip(fleet.addresses())
+        {
+            manager
+                .register(name, DeviceKind::Plug, address)
+                .await
+                .unwrap();
+            manager.set_labels(name, labels).await.unwrap();
+        }
+
+        let names = |devices: Vec<DeviceHandle>| {
+            devices
+                .iter()
+                .map(|device| device.name().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(manager.devices_in_room("kitchen").await.unwrap()),
+            ["kettle", "oven"]
+        );
+        assert_eq!(
+            names(manager.devices_with_tag("heating").await.unwrap()),
+            ["oven", "towel-rail"]
+        );
+
+        manager
+            .register("oven", DeviceKind::Plug, &fleet.addresses()[0])
+            .await
+            .unwrap();
+        manager.unregister("towel-rail").await.unwrap();
+        assert_eq!(
+            names(manager.devices_with_tag("heating").await.unwrap()),
+            ["oven"]
+        );
+        assert!(matches!(
+            manager.set_labels("towel-rail", DeviceLabels::default()).await,
+            Err(Error::Validation { field, .. }) if field == "name"
+        ));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn inventory_lists_the_registered_devices() {
+        use crate::simulator::SimulatedFleet;
+
+        let fleet = SimulatedFleet::builder("username", "password")
+            .devices(2)
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        for (name, address) in ["kitchen", "garage"].into_iter().zip(fleet.addresses()) {
+            manager
+                .register(name, DeviceKind::PlugEnergyMonitoring, address)
+                .await
+                .unwrap();
+        }
+        manager
+            .set_labels("kitchen", DeviceLabels::in_room("kitchen"))
+            .await
+            .unwrap();
+
+        let inventory = manager.inventory().await.unwrap();
+
+        let names: Vec<_> = inventory
+            .devices
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(names, ["garage", "kitchen"]);
+
+        let kitchen = &inventory.devices[1];
+        assert_eq!(kitchen.error, None);
+        assert_eq!(kitchen.model.as_deref(), Some("P110"));
+        assert!(kitchen.mac.is_some());
+        assert!(kitchen.labels.is_in_room("kitchen"));
+        assert!(kitchen.capabilities.as_ref().unwrap().has_energy_monitoring);
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn devices_in_maintenance_are_not_sent_requests() {
+        use crate::simulator::SimulatedFleet;
+
+        let fleet = SimulatedFleet::builder("username", "password")
+            .devices(1)
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        manager
+            .register(
+                "dehumidifier",
+                DeviceKind::Plug,
+                fleet.addresses()[0].clone(),
+            )
+            .await
+            .unwrap();
+
+        manager.set_maintenance("dehumidifier", true).await.unwrap();
+        assert_eq!(
+            manager.devices_in_maintenance().await.unwrap(),
+            BTreeSet::from(["dehumidifier".to_string()])
+        );
+        assert!(manager
+            .device("dehumidifier")
+            .on()
+            .await
+            .is_err_and(|err| err.is_maintenance()));
+
+        manager
+            .set_maintenance("dehumidifier", false)
+            .await
+            .unwrap();
+        manager.device("dehumidifier").on().await.unwrap();
+        assert!(manager.devices_in_maintenance().await.unwrap().is_empty());
+        assert!(matches!(
+            manager.set_maintenance("garage", true).await,
+            Err(Error::Validation { field, .. }) if field == "name"
+        ));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn fake_devices_are_controlled_like_real_ones() {
+        use crate::simulator::FakeDevice;
+
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        let heater = FakeDevice::plug();
+        manager
+            .register_fake("heater", DeviceKind::Plug, heater.clone())
+            .await
+            .unwrap();
+
+        heater.fail_next(Error::Tapo(TapoResponseError::SessionTimeout));
+        manager.device("heater").on().await.unwrap();
+        assert_eq!(heater.device_info()["device_on"], true);
+        assert_eq!(
+            heater.requests().len(),
+            2,
+            "retried after the session timeout"
+        );
+
+        assert!(matches!(
+            manager.device("heater").set_brightness(50).await,
+            Err(Error::NotSupported { .. })
+        ));
+        assert_eq!(
+            manager.devices().await.unwrap(),
+            BTreeMap::from([("heater".to_string(), DeviceKind::Plug)])
+        );
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn lights_are_adjusted_relatively() {
+        use crate::simulator::FakeDevice;
+
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        let lamp = FakeDevice::light();
+        lamp.set("color_temp_range", serde_json::json!([2700, 6500]));
+        manager
+            .register_fake("lamp", DeviceKind::ColorLight, lamp.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            manager.device("lamp").adjust_brightness(-30).await.unwrap(),
+            70
+        );
+        assert_eq!(
+            manager.device("lamp").adjust_brightness(50).await.unwrap(),
+            100
+        );
+        assert_eq!(
+            manager
+                .device("lamp")
+                .step_color_temperature(-500)
+                .await
+                .unwrap(),
+            2700
+        );
+        assert_eq!(lamp.device_info()["color_temp"], 2700);
+        assert_eq!(lamp.device_info()["device_on"], true);
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn firmware_rollout_is_aborted_on_failures() {
+        use crate::simulator::SimulatedFleet;
+
+        // The simulated devices don't implement the firmware methods, so every update fails.
+        let fleet = SimulatedFleet::builder("username", "password")
+            .devices(3)
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        for (name, address) in ["hallway", "kitchen", "office"]
+            .into_iter()
+            .zip(fleet.addresses())
+        {
+            manager
+                .register(name, DeviceKind::PlugEnergyMonitoring, address)
+                .await
+                .unwrap();
+        }
+
+        let report = manager
+            .rollout_firmware(|device| device.name != "office", 1, 0.5)
+            .await
+            .unwrap();
+
+        assert!(report.aborted);
+        assert_eq!(report.devices.len(), 2);
+        assert!(matches!(
+            report.devices[0].outcome,
+            FirmwareOutcome::Failed { .. }
+        ));
+        assert_eq!(report.devices[1].name, "kitchen");
+        assert_eq!(report.devices[1].wave, 1);
+        assert_eq!(report.devices[1].outcome, FirmwareOutcome::Skipped);
+        assert!(matches!(
+            manager.rollout_firmware(|_| true, 0, 0.5).await,
+            Err(Error::Validation { field, .. }) if field == "max_concurrent"
+        ));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn readings_are_calibrated_per_device() {
+        use chrono::Utc;
+
+        use crate::readings::ReadingKind;
+        use crate::simulator::SimulatedFleet;
+
+        let fleet = SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        manager
+            .register("radiator", DeviceKind::Plug, &fleet.addresses()[0])
+            .await
+            .unwrap();
+        let calibration = Calibration {
+            temperature_offset: -2.0,
+            ..Default::default()
+        };
+        manager
+            .set_calibration("radiator", calibration)
+            .await
+            .unwrap();
+
+        let readings = manager
+            .calibrate([
+                Reading::new(
+                    "radiator",
+                    Utc::now(),
+                    ReadingKind::TemperatureCelsius,
+                    23.0,
+                ),
+                Reading::new("window", Utc::now(), ReadingKind::TemperatureCelsius, 18.0),
+            ])
+            .await
+            .unwrap();
+
+        let values: Vec<_> = readings.iter().map(|reading| reading.value).collect();
+        assert_eq!(values, [21.0, 18.0]);
+        assert!(matches!(
+            manager
+                .set_calibration(
+                    "radiator",
+                    Calibration {
+                        power_factor: -1.0,
+                        ..Default::default()
+                    }
+                )
+                .await,
+            Err(Error::Validation { field, .. }) if field == "power_factor"
+        ));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn watchdog_reconnects_to_unreachable_devices() {
+        use crate::events::DeviceEvent;
+        use crate::simulator::SimulatedFleet;
+
+        // Every request fails, but the handshake still succeeds, so the reconnections do.
+        let fleet = SimulatedFleet::builder("username", "password")
+            .error_rate(1.0)
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::builder(ApiClient::new("username", "password").unwrap())
+            .max_retries(0)
+            .watchdog(
+                WatchdogPolicy::default()
+                    .with_probe_interval(Duration::from_millis(20))
+                    .with_failure_threshold(2)
+                    .with_retry_schedule(vec![Duration::from_millis(20)]),
+            )
+            .build();
+        let mut events = manager.subscribe();
+
+        manager
+            .register(
+                "kitchen",
+                DeviceKind::PlugEnergyMonitoring,
+                &fleet.addresses()[0],
+            )
+            .await
+            .unwrap();
+
+        let offline = events.recv().await.unwrap();
+        assert_eq!(offline.name, "kitchen");
+        assert_eq!(offline.event, DeviceEvent::Offline);
+        assert_eq!(events.recv().await.unwrap().event, DeviceEvent::Online);
+
+        manager.unregister("kitchen").await.unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::manager::DeviceHandle;
+
+/// How often the progress of a firmware download is polled during a rollout.
+const DOWNLOAD_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// How long a device has to download its firmware before it's considered failed.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// What happened to a device during a firmware rollout, see [`crate::manager::DeviceManager::rollout_firmware`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum FirmwareOutcome {
+    /// The device already had the latest firmware.
+    UpToDate,
+    /// The device has downloaded the firmware and is installing it.
+    Updated {
+        /// The version of the new firmware.
+        firmware_version: String,
+    },
+    /// The update has failed.
+    Failed {
+        /// The reason of the failure.
+        message: String,
+    },
+    /// The device hasn't been updated because the rollout was aborted before its wave,
+    /// or because it's in maintenance mode.
+    Skipped,
+}
+
+/// A device of a [`FirmwareRolloutReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareRolloutEntry {
+    /// The name of the device.
+    pub name: String,
+    /// The wave that the device is part of, starting at 0.
+    pub wave: usize,
+    /// What happened to the device.
+    #[serde(flatten)]
+    pub outcome: FirmwareOutcome,
+}
+
+/// The outcome of a firmware rollout for every selected device, see [`crate::manager::DeviceManager::rollout_firmware`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareRolloutReport {
+    /// The selected devices, by wave, then by name.
+    pub devices: Vec<FirmwareRolloutEntry>,
+    /// Whether the rollout has stopped early because too many updates had failed.
+    pub aborted: bool,
+}
+
+impl FirmwareRolloutReport {
+    /// Returns the share of the attempted updates that have failed, between 0 and 1.
+    pub fn failure_rate(&self) -> f64 {
+        let attempted = self
+            .devices
+            .iter()
+            .filter(|device| device.outcome != FirmwareOutcome::Skipped)
+            .count();
+        if attempted == 0 {
+            return 0.0;
+        }
+
+        let failed = self
+            .devices
+            .iter()
+            .filter(|device| matches!(device.outcome, FirmwareOutcome::Failed { .. }))
+            .count();
+
+        failed as f64 / attempted as f64
+    }
+}
+
+/// Updates the firmware of `device` if a newer one is available and waits for it to be downloaded.
+pub(crate) async fn update_firmware(device: &DeviceHandle) -> Result<FirmwareOutcome, Error> {
+    let latest_firmware = device.get_latest_firmware().await?;
+    if !latest_firmware.need_to_upgrade {
+        return Ok(FirmwareOutcome::UpToDate);
+    }
+
+    device.start_firmware_update().await?;
+
+    let download = async {
+        loop {
+            if device.get_firmware_download_state().await?.is_downloaded() {
+                return Ok::<_, Error>(());
+            }
+            tokio::time::sleep(DOWNLOAD_POLL_INTERVAL).await;
+        }
+    };
+    tokio::time::timeout(DOWNLOAD_TIMEOUT, download)
+        .await
+        .map_err(|_| anyhow::anyhow!("The firmware download has timed out"))??;
+
+    Ok(FirmwareOutcome::Updated {
+        firmware_version: latest_firmware.fw_ver,
+    })
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::manager::{DeviceKind, DeviceLabels};
+use crate::responses::Capabilities;
+
+/// Every device registered with a [`crate::manager::DeviceManager`], for asset tracking and support tickets,
+/// see [`crate::manager::DeviceManager::inventory`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    /// When the inventory was taken.
+    pub generated_at: DateTime<Utc>,
+    /// The devices, ordered by name.
+    pub devices: Vec<InventoryEntry>,
+}
+
+/// A device of an [`Inventory`].
+///
+/// The properties read from the device are `None` if it couldn't be reached, see [`InventoryEntry::error`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InventoryEntry {
+    /// The name that the device is registered under.
+    pub name: String,
+    /// The kind of handler used to control the device.
+    pub kind: DeviceKind,
+    /// The room and the tags of the device.
+    #[serde(flatten)]
+    pub labels: DeviceLabels,
+    /// The model of the device, e.g. `P110`.
+    pub model: Option<String>,
+    /// The hardware version, e.g. `1.0`.
+    pub hardware_version: Option<String>,
+    /// The firmware version, e.g. `1.3.0 Build 230905 Rel.152200`.
+    pub firmware_version: Option<String>,
+    /// The MAC address, e.g. `5C-E9-31-00-00-00`.
+    pub mac: Option<String>,
+    /// The IP address.
+    pub ip: Option<String>,
+    /// What the device supports.
+    pub capabilities: Option<Capabilities>,
+    /// Why the device couldn't be queried, if it couldn't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl InventoryEntry {
+    /// Returns an [`InventoryEntry`] without the properties read from the device.
+    pub(crate) fn new(name: String, kind: DeviceKind, labels: DeviceLabels) -> Self {
+        Self {
+            name,
+            kind,
+            labels,
+            model: None,
+            hardware_version: None,
+            firmware_version: None,
+            mac: None,
+            ip: None,
+            capabilities: None,
+            error: None,
+        }
+    }
+
+    /// Fills in the properties found in the *device info* of the device.
+    pub(crate) fn with_device_info(mut self, device_info: &serde_json::Value) -> Self {
+        let field = |name: &str| {
+            device_info
+                .get(name)
+                .and_then(serde_json::Value::as_str)
+                .map(str::to_string)
+        };
+
+        self.model = field("model");
+        self.hardware_version = field("hw_ver");
+        self.firmware_version = field("fw_ver");
+        self.mac = field("mac");
+        self.ip = field("ip");
+        self
+    }
+}
@@ -0,0 +1,326 @@
+use crate::error::Error;
+use crate::events::DeviceEvent;
+use crate::manager::DeviceKind;
+#[cfg(feature = "simulator")]
+use crate::requests::PlayAlarmParams;
+use crate::requests::Ringtone;
+use crate::responses::Capabilities;
+#[cfg(feature = "simulator")]
+use crate::simulator::FakeDevice;
+use crate::{
+    ApiClient, ColorLightHandler, ColorLightStripHandler, GenericDeviceHandler, HubHandler,
+    LightHandler, PlugEnergyMonitoringHandler, PlugHandler,
+};
+
+/// The handler of a device owned by the [`crate::manager::DeviceManager`].
+#[derive(Clone)]
+pub(crate) enum ManagedDevice {
+    Generic(GenericDeviceHandler),
+    Light(LightHandler),
+    ColorLight(ColorLightHandler),
+    ColorLightStrip(ColorLightStripHandler),
+    Plug(PlugHandler),
+    PlugEnergyMonitoring(PlugEnergyMonitoringHandler),
+    Hub(HubHandler),
+    /// An in-memory device registered with [`crate::manager::DeviceManager::register_fake`].
+    #[cfg(feature = "simulator")]
+    Fake(DeviceKind, FakeDevice),
+}
+
+/// An operation that the [`crate::manager::DeviceManager`] performs on a device.
+#[derive(Debug, Clone)]
+pub(crate) enum Action {
+    On,
+    Off,
+    SetBrightness(u8),
+    SetHueSaturation(u16, u8),
+    SetColorTemperature(u16),
+    GetDeviceInfo,
+    GetCapabilities,
+    GetEnergyUsage,
+    GetLatestFirmware,
+    StartFirmwareUpdate,
+    GetFirmwareDownloadState,
+    PlayAlarm(Option<Ringtone>, Option<u64>),
+    StopAlarm,
+}
+
+impl Action {
+    /// Returns the event that a successful `action` causes, if it changes the state of the device.
+    pub fn event(&self) -> Option<DeviceEvent> {
+        let light_state = |brightness, color_temperature, hue, saturation| {
+            Some(DeviceEvent::LightStateChanged {
+                brightness,
+                color_temperature,
+                hue,
+                saturation,
+            })
+        };
+
+        match *self {
+            Self::On => Some(DeviceEvent::PowerStateChanged { device_on: true }),
+            Self::Off => Some(DeviceEvent::PowerStateChanged { device_on: false }),
+            Self::SetBrightness(brightness) => light_state(Some(brightness), None, None, None),
+            Self::SetHueSaturation(hue, saturation) => {
+                light_state(None, None, Some(hue), Some(saturation))
+            }
+            Self::SetColorTemperature(color_temperature) => {
+                light_state(None, Some(color_temperature), None, None)
+            }
+            Self::GetDeviceInfo
+            | Self::GetCapabilities
+            | Self::GetEnergyUsage
+            | Self::GetLatestFirmware
+            | Self::StartFirmwareUpdate
+            | Self::GetFirmwareDownloadState
+            | Self::PlayAlarm(..)
+            | Self::StopAlarm => None,
+        }
+    }
+}
+
+impl ManagedDevice {
+    pub async fn connect(
+        client: ApiClient,
+        kind: DeviceKind,
+        ip_address: String,
+    ) -> Result<Self, Error> {
+        Ok(match kind {
+            DeviceKind::Generic => Self::Generic(client.generic_device(ip_address).await?),
+            DeviceKind::Light => Self::Light(client.l510(ip_address).await?),
+            DeviceKind::ColorLight => Self::ColorLight(client.l530(ip_address).await?),
+            DeviceKind::ColorLightStrip => Self::ColorLightStrip(client.l930(ip_address).await?),
+            DeviceKind::Plug => Self::Plug(client.p100(ip_address).await?),
+            DeviceKind::PlugEnergyMonitoring => {
+                Self::PlugEnergyMonitoring(client.p110(ip_address).await?)
+            }
+            DeviceKind::Hub => Self::Hub(client.h100(ip_address).await?),
+        })
+    }
+
+    pub fn kind(&self) -> DeviceKind {
+        match self {
+            Self::Generic(_) => DeviceKind::Generic,
+            Self::Light(_) => DeviceKind::Light,
+            Self::ColorLight(_) => DeviceKind::ColorLight,
+            Self::ColorLightStrip(_) => DeviceKind::ColorLightStrip,
+            Self::Plug(_) => DeviceKind::Plug,
+            Self::PlugEnergyMonitoring(_) => DeviceKind::PlugEnergyMonitoring,
+            Self::Hub(_) => DeviceKind::Hub,
+            #[cfg(feature = "simulator")]
+            Self::Fake(kind, _) => *kind,
+        }
+    }
+
+    /// Performs `action` and returns its result, or [`serde_json::Value::Null`] for the actions that don't return anything.
+    pub async fn execute(&self, action: Action) -> Result<serde_json::Value, Error> {
+        match action {
+            Action::On => self.on().await.map(|_| serde_json::Value::Null),
+            Action::Off => self.off().await.map(|_| serde_json::Value::Null),
+            Action::SetBrightness(brightness) => self
+                .set_brightness(brightness)
+                .await
+                .map(|_| serde_json::Value::Null),
+            Action::SetHueSaturation(hue, saturation) => self
+                .set_hue_saturation(hue, saturation)
+                .await
+                .map(|_| serde_json::Value::Null),
+            Action::SetColorTemperature(color_temperature) => self
+                .set_color_temperature(color_temperature)
+                .await
+                .map(|_| serde_json::Value::Null),
+            Action::GetDeviceInfo => self.get_device_info_json().await,
+            Action::GetCapabilities => Ok(serde_json::to_value(self.get_capabilities().await?)?),
+            Action::GetEnergyUsage => self.get_energy_usage_json().await,
+            Action::GetLatestFirmware => self.send_raw("get_latest_firmware").await,
+            Action::StartFirmwareUpdate => self
+                .send_raw("fw_download")
+                .await
+                .map(|_| serde_json::Value::Null),
+            Action::GetFirmwareDownloadState => self.send_raw("get_fw_download_state").await,
+            Action::PlayAlarm(ringtone, duration) => self
+                .play_alarm(ringtone, duration)
+                .await
+                .map(|_| serde_json::Value::Null),
+            Action::StopAlarm => self.stop_alarm().await.map(|_| serde_json::Value::Null),
+        }
+    }
+
+    pub async fn refresh_session(&mut self) -> Result<(), Error> {
+        match self {
+            Self::Generic(handler) => handler.refresh_session().await.map(|_| ()),
+            Self::Light(handler) => handler.refresh_session().await.map(|_| ()),
+            Self::ColorLight(handler) => handler.refresh_session().await.map(|_| ()),
+            Self::ColorLightStrip(handler) => handler.refresh_session().await.map(|_| ()),
+            Self::Plug(handler) => handler.refresh_session().await.map(|_| ()),
+            Self::PlugEnergyMonitoring(handler) => handler.refresh_session().await.map(|_| ()),
+            Self::Hub(handler) => handler.refresh_session().await.map(|_| ()),
+            #[cfg(feature = "simulator")]
+            Self::Fake(..) => Ok(()),
+        }
+    }
+
+    async fn on(&self) -> Result<(), Error> {
+        match self {
+            Self::Generic(handler) => handler.on().await,
+            Self::Light(handler) => handler.on().await,
+            Self::ColorLight(handler) => handler.on().await,
+            Self::ColorLightStrip(handler) => handler.on().await,
+            Self::Plug(handler) => handler.on().await,
+            Self::PlugEnergyMonitoring(handler) => handler.on().await,
+            Self::Hub(_) => Err(not_supported("Turning on")),
+            #[cfg(feature = "simulator")]
+            Self::Fake(DeviceKind::Hub, _) => Err(not_supported("Turning on")),
+            #[cfg(feature = "simulator")]
+            Self::Fake(_, device) => device.on().await,
+        }
+    }
+
+    async fn off(&self) -> Result<(), Error> {
+        match self {
+            Self::Generic(handler) => handler.off().await,
+            Self::Light(handler) => handler.off().await,
+            Self::ColorLight(handler) => handler.off().await,
+            Self::ColorLightStrip(handler) => handler.off().await,
+            Self::Plug(handler) => handler.off().await,
+            Self::PlugEnergyMonitoring(handler) => handler.off().await,
+            Self::Hub(_) => Err(not_supported("Turning off")),
+            #[cfg(feature = "simulator")]
+            Self::Fake(DeviceKind::Hub, _) => Err(not_supported("Turning off")),
+            #[cfg(feature = "simulator")]
+            Self::Fake(_, device) => device.off().await,
+        }
+    }
+
+    async fn set_brightness(&self, brightness: u8) -> Result<(), Error> {
+        match self {
+            Self::Light(handler) => handler.set_brightness(brightness).await,
+            Self::ColorLight(handler) => handler.set_brightness(brightness).await,
+            Self::ColorLightStrip(handler) => handler.set_brightness(brightness).await,
+            #[cfg(feature = "simulator")]
+            Self::Fake(
+                DeviceKind::Light | DeviceKind::ColorLight | DeviceKind::ColorLightStrip,
+                device,
+            ) => device.set_brightness(brightness).await,
+            _ => Err(not_supported("Brightness")),
+        }
+    }
+
+    async fn set_hue_saturation(&self, hue: u16, saturation: u8) -> Result<(), Error> {
+        match self {
+            Self::ColorLight(handler) => handler.set_hue_saturation(hue, saturation).await,
+            Self::ColorLightStrip(handler) => handler.set_hue_saturation(hue, saturation).await,
+            #[cfg(feature = "simulator")]
+            Self::Fake(DeviceKind::ColorLight | DeviceKind::ColorLightStrip, device) => {
+                device.set_hue_saturation(hue, saturation).await
+            }
+            _ => Err(not_supported("Color")),
+        }
+    }
+
+    async fn set_color_temperature(&self, color_temperature: u16) -> Result<(), Error> {
+        match self {
+            Self::ColorLight(handler) => handler.set_color_temperature(color_temperature).await,
+            Self::ColorLightStrip(handler) => {
+                handler.set_color_temperature(color_temperature).await
+            }
+            #[cfg(feature = "simulator")]
+            Self::Fake(DeviceKind::ColorLight | DeviceKind::ColorLightStrip, device) => {
+                device.set_color_temperature(color_temperature).await
+            }
+            _ => Err(not_supported("Color temperature")),
+        }
+    }
+
+    async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
+        match self {
+            Self::Generic(handler) => handler.get_device_info_json().await,
+            Self::Light(handler) => handler.get_device_info_json().await,
+            Self::ColorLight(handler) => handler.get_device_info_json().await,
+            Self::ColorLightStrip(handler) => handler.get_device_info_json().await,
+            Self::Plug(handler) => handler.get_device_info_json().await,
+            Self::PlugEnergyMonitoring(handler) => handler.get_device_info_json().await,
+            Self::Hub(handler) => handler.get_device_info_json().await,
+            #[cfg(feature = "simulator")]
+            Self::Fake(_, device) => device.get_device_info_json().await,
+        }
+    }
+
+    async fn get_capabilities(&self) -> Result<Capabilities, Error> {
+        match self {
+            Self::Generic(handler) => handler.get_capabilities().await,
+            Self::Light(handler) => handler.get_capabilities().await,
+            Self::ColorLight(handler) => handler.get_capabilities().await,
+            Self::ColorLightStrip(handler) => handler.get_capabilities().await,
+            Self::Plug(handler) => handler.get_capabilities().await,
+            Self::PlugEnergyMonitoring(handler) => handler.get_capabilities().await,
+            Self::Hub(handler) => handler.get_capabilities().await,
+            #[cfg(feature = "simulator")]
+            Self::Fake(_, device) => device.get_capabilities().await,
+        }
+    }
+
+    /// Sends a request without parameters for the firmware `method`, which the handlers don't model.
+    async fn send_raw(&self, method: &str) -> Result<serde_json::Value, Error> {
+        let params = serde_json::Value::Null;
+
+        match self {
+            Self::Generic(handler) => handler.send_raw(method, params).await,
+            Self::Light(handler) => handler.send_raw(method, params).await,
+            Self::ColorLight(handler) => handler.send_raw(method, params).await,
+            Self::ColorLightStrip(handler) => handler.send_raw(method, params).await,
+            Self::Plug(handler) => handler.send_raw(method, params).await,
+            Self::PlugEnergyMonitoring(handler) => handler.send_raw(method, params).await,
+            Self::Hub(handler) => handler.send_raw(method, params).await,
+            #[cfg(feature = "simulator")]
+            Self::Fake(_, device) => device.send_raw(method, params).await,
+        }
+    }
+
+    async fn play_alarm(
+        &self,
+        ringtone: Option<Ringtone>,
+        duration: Option<u64>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Hub(handler) => handler.play_alarm(ringtone, duration).await,
+            #[cfg(feature = "simulator")]
+            Self::Fake(DeviceKind::Hub, device) => {
+                let params = serde_json::to_value(PlayAlarmParams::new(ringtone, duration))?;
+                device.send_raw("play_alarm", params).await.map(|_| ())
+            }
+            _ => Err(not_supported("Alarm")),
+        }
+    }
+
+    async fn stop_alarm(&self) -> Result<(), Error> {
+        match self {
+            Self::Hub(handler) => handler.stop_alarm().await,
+            #[cfg(feature = "simulator")]
+            Self::Fake(DeviceKind::Hub, device) => device
+                .send_raw("stop_alarm", serde_json::Value::Null)
+                .await
+                .map(|_| ()),
+            _ => Err(not_supported("Alarm")),
+        }
+    }
+
+    async fn get_energy_usage_json(&self) -> Result<serde_json::Value, Error> {
+        match self {
+            Self::PlugEnergyMonitoring(handler) => {
+                Ok(serde_json::to_value(handler.get_energy_usage().await?)?)
+            }
+            #[cfg(feature = "simulator")]
+            Self::Fake(DeviceKind::PlugEnergyMonitoring, device) => {
+                Ok(serde_json::to_value(device.get_energy_usage().await?)?)
+            }
+            _ => Err(not_supported("Energy monitoring")),
+        }
+    }
+}
+
+fn not_supported(feature: &str) -> Error {
+    Error::NotSupported {
+        feature: feature.to_string(),
+    }
+}
@@ -0,0 +1,328 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use tokio::task::JoinHandle;
+
+use crate::error::Error;
+use crate::manager::DeviceManager;
+
+/// The power, in W, above which a shed device is considered to have been turned on again by someone else.
+const STANDBY_POWER_W: f64 = 5.0;
+
+/// Keeps the combined power of the plugs on a circuit under a budget, e.g. 2300 W for a 10 A circuit at 230 V,
+/// by turning off the least important loads when it's exceeded.
+///
+/// The plugs of the circuit are the devices of a [`DeviceManager`] with a given tag, see [`crate::manager::DeviceLabels`].
+/// Their *energy usage* is polled at [`PowerBudget::with_poll_interval`]. While the combined power exceeds the budget,
+/// the drawing devices with the lowest [`PowerBudget::with_priority`] are turned off, the biggest loads first among
+/// devices of the same priority. The shed devices are turned on again, the most important first,
+/// once their power fits under the budget minus [`PowerBudget::with_hysteresis`],
+/// so that they don't flap around the budget.
+///
+/// The devices that can't be polled, e.g. those in maintenance mode, are left alone.
+/// A shed device that is turned on by someone else is no longer managed until the budget is exceeded again.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use tapo::manager::{DeviceManager, PowerBudget};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+///
+/// let budget = PowerBudget::new(manager, "kitchen-circuit", 2300.0)
+///     .with_priority("fridge", 100)
+///     .with_priority("kettle", 10)
+///     .with_hysteresis(300.0)
+///     .start()?;
+///
+/// tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+/// println!("Shed: {:?}", budget.shed_devices());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PowerBudget {
+    manager: DeviceManager,
+    tag: String,
+    budget_w: f64,
+    hysteresis_w: f64,
+    poll_interval: Duration,
+    priorities: BTreeMap<String, i32>,
+}
+
+/// The coordinator of a started [`PowerBudget`]. It stops when it is dropped, leaving the shed devices off.
+#[derive(Debug)]
+pub struct RunningPowerBudget {
+    task: JoinHandle<()>,
+    circuit: Arc<Mutex<Circuit>>,
+}
+
+impl PowerBudget {
+    /// Returns a [`PowerBudget`] with a hysteresis of 10% of the budget, which polls the devices every 5 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager of the devices
+    /// * `tag` - the tag of the plugs on the circuit
+    /// * `budget_w` - the maximum combined power of the plugs, in W
+    pub fn new(manager: DeviceManager, tag: impl Into<String>, budget_w: f64) -> Self {
+        Self {
+            manager,
+            tag: tag.into(),
+            budget_w,
+            hysteresis_w: budget_w / 10.0,
+            poll_interval: Duration::from_secs(5),
+            priorities: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the priority of a device. The devices with the lowest priority are shed first. Defaults to 0.
+    pub fn with_priority(mut self, device: impl Into<String>, priority: i32) -> Self {
+        self.priorities.insert(device.into(), priority);
+        self
+    }
+
+    /// Sets the margin under the budget, in W, that the combined power must still have with a shed device
+    /// turned on before it's turned on again. Defaults to 10% of the budget.
+    pub fn with_hysteresis(mut self, hysteresis_w: f64) -> Self {
+        self.hysteresis_w = hysteresis_w;
+        self
+    }
+
+    /// Sets how often the power of the devices is polled. Defaults to 5 seconds.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Validates the budget and starts enforcing it. Must be called from within a Tokio runtime.
+    pub fn start(self) -> Result<RunningPowerBudget, Error> {
+        if !(self.budget_w.is_finite() && self.budget_w > 0.0) {
+            return Err(Error::Validation {
+                field: "budget_w".to_string(),
+                message: "must be greater than 0".to_string(),
+            });
+        }
+        if !(0.0..self.budget_w).contains(&self.hysteresis_w) {
+            return Err(Error::Validation {
+                field: "hysteresis_w".to_string(),
+                message: "must be between 0 and the budget".to_string(),
+            });
+        }
+
+        let circuit = Arc::new(Mutex::new(Circuit {
+            budget_w: self.budget_w,
+            hysteresis_w: self.hysteresis_w,
+            priorities: self.priorities.clone(),
+            shed: Vec::new(),
+        }));
+        let task = tokio::spawn(self.run(circuit.clone()));
+
+        Ok(RunningPowerBudget { task, circuit })
+    }
+
+    async fn run(self, circuit: Arc<Mutex<Circuit>>) {
+        let mut interval = tokio::time::interval(self.poll_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            let devices = match self.manager.devices_with_tag(&self.tag).await {
+                Ok(devices) => devices,
+                Err(err) => {
+                    warn!("Failed to list the devices tagged `{}`: {err:?}", self.tag);
+                    continue;
+                }
+            };
+
+            let mut powers = BTreeMap::new();
+            for device in &devices {
+                match device.get_energy_usage().await {
+                    Ok(energy_usage) => {
+                        powers.insert(
+                            device.name().to_string(),
+                            energy_usage.current_power as f64 / 1000.0,
+                        );
+                    }
+                    Err(err) => debug!("Failed to poll the power of `{}`: {err}", device.name()),
+                }
+            }
+
+            let switches = circuit
+                .lock()
+                .expect("the lock is never poisoned")
+                .update(&powers);
+
+            for switch in switches {
+                let result = match &switch {
+                    Switch::Shed(name, _) => {
+                        info!(
+                            "Shedding `{name}` to keep circuit `{}` under budget",
+                            self.tag
+                        );
+                        self.manager.device(name.as_str()).off().await
+                    }
+                    Switch::Restore(name, _) => {
+                        info!("Restoring `{name}` on circuit `{}`", self.tag);
+                        self.manager.device(name.as_str()).on().await
+                    }
+                };
+
+                if let Err(err) = result {
+                    warn!("Failed to switch `{switch:?}`: {err:?}");
+                    circuit
+                        .lock()
+                        .expect("the lock is never poisoned")
+                        .revert(&switch);
+                }
+            }
+        }
+    }
+}
+
+impl RunningPowerBudget {
+    /// Returns the names of the devices that are currently shed, in the order in which they have been shed.
+    pub fn shed_devices(&self) -> Vec<String> {
+        self.circuit
+            .lock()
+            .expect("the lock is never poisoned")
+            .shed
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Stops enforcing the budget. The shed devices are left off.
+    pub fn stop(self) {}
+}
+
+impl Drop for RunningPowerBudget {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// A device to turn off or on again.
+#[derive(Debug, Clone, PartialEq)]
+enum Switch {
+    /// The name of the device and the power it draws, in W.
+    Shed(String, f64),
+    /// The name of the device and the power it drew before being shed, in W.
+    Restore(String, f64),
+}
+
+/// The shedding decisions of a [`PowerBudget`], apart from the devices.
+#[derive(Debug)]
+struct Circuit {
+    budget_w: f64,
+    hysteresis_w: f64,
+    priorities: BTreeMap<String, i32>,
+    /// The shed devices, with the power they drew before being shed.
+    shed: Vec<(String, f64)>,
+}
+
+impl Circuit {
+    /// Returns the devices to switch given the current power of the devices of the circuit, in W, by name.
+    fn update(&mut self, powers: &BTreeMap<String, f64>) -> Vec<Switch> {
+        // A shed device that draws power again has been turned on by someone else.
+        self.shed.retain(|(name, _)| {
+            !powers
+                .get(name)
+                .is_some_and(|&power_w| power_w >= STANDBY_POWER_W)
+        });
+
+        let mut total_w: f64 = powers.values().sum();
+        let mut switches = Vec::new();
+
+        if total_w > self.budget_w {
+            let mut candidates: Vec<_> = powers
+                .iter()
+                .filter(|(_, &power_w)| power_w >= STANDBY_POWER_W)
+                .collect();
+            candidates.sort_by(|(a, a_power), (b, b_power)| {
+                self.priority(a)
+                    .cmp(&self.priority(b))
+                    .then(b_power.total_cmp(a_power))
+            });
+
+            for (name, &power_w) in candidates {
+                if total_w <= self.budget_w {
+                    break;
+                }
+
+                total_w -= power_w;
+                self.shed.push((name.clone(), power_w));
+                switches.push(Switch::Shed(name.clone(), power_w));
+            }
+
+            return switches;
+        }
+
+        // The most important shed device first, the last shed first among devices of the same priority.
+        while let Some(index) =
+            (0..self.shed.len()).max_by_key(|&index| (self.priority(&self.shed[index].0), index))
+        {
+            let power_w = self.shed[index].1;
+            if total_w + power_w > self.budget_w - self.hysteresis_w {
+                break;
+            }
+
+            total_w += power_w;
+            let (name, power_w) = self.shed.remove(index);
+            switches.push(Switch::Restore(name, power_w));
+        }
+
+        switches
+    }
+
+    /// Undoes a switch that has failed, so that it's retried at the next poll.
+    fn revert(&mut self, switch: &Switch) {
+        match switch {
+            Switch::Shed(name, _) => self.shed.retain(|(shed, _)| shed != name),
+            Switch::Restore(name, power_w) => self.shed.push((name.clone(), *power_w)),
+        }
+    }
+
+    fn priority(&self, name: &str) -> i32 {
+        self.priorities.get(name).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowest_priority_loads_are_shed_and_restored_with_hysteresis() {
+        let mut circuit = Circuit {
+            budget_w: 2300.0,
+            hysteresis_w: 200.0,
+            priorities: BTreeMap::from([("fridge".to_string(), 100), ("kettle".to_string(), 10)]),
+            shed: Vec::new(),
+        };
+        let powers = |fridge: f64, kettle: f64, heater: f64| {
+            BTreeMap::from([
+                ("fridge".to_string(), fridge),
+                ("kettle".to_string(), kettle),
+                ("heater".to_string(), heater),
+            ])
+        };
+
+        assert_eq!(circuit.update(&powers(150.0, 0.0, 1500.0)), vec![]);
+        assert_eq!(
+            circuit.update(&powers(150.0, 2000.0, 1500.0)),
+            vec![Switch::Shed("heater".to_string(), 1500.0)]
+        );
+        // Under the budget, but not by the hysteresis.
+        assert_eq!(circuit.update(&powers(150.0, 700.0, 0.0)), vec![]);
+        assert_eq!(
+            circuit.update(&powers(150.0, 0.0, 0.0)),
+            vec![Switch::Restore("heater".to_string(), 1500.0)]
+        );
+        assert!(circuit.shed.is_empty());
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::error::Error;
+use crate::manager::DeviceManager;
+
+/// A meter of the power exchanged with the grid, e.g. a smart meter or the API of a PV inverter,
+/// that drives a [`SolarSurplus`].
+#[async_trait]
+pub trait GridMeter: Send + Sync {
+    /// Returns the power exchanged with the grid, in W: positive when importing, negative when exporting.
+    async fn grid_power_w(&self) -> Result<f64, Error>;
+}
+
+/// Turns on plugs when the PV surplus, exported to the grid, is enough to power them,
+/// and turns them off again once the household imports from the grid.
+///
+/// The plugs are the devices of a [`DeviceManager`] with a given tag, see [`crate::manager::DeviceLabels`],
+/// that have a [`SolarSurplus::with_nominal_power`]. At every poll of the [`GridMeter`], at most one device is switched:
+///
+/// * while exporting, the biggest device that is off and whose nominal power fits in the surplus is turned on
+/// * while importing more than [`SolarSurplus::with_import_tolerance`],
+///   the device turned on last is turned off
+///
+/// A device isn't switched again before [`SolarSurplus::with_min_switch_interval`], so that passing clouds don't
+/// make it flap. Only the devices turned on by the controller are turned off by it.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use async_trait::async_trait;
+/// # use tapo::manager::{DeviceManager, GridMeter, SolarSurplus};
+/// struct Inverter;
+///
+/// #[async_trait]
+/// impl GridMeter for Inverter {
+///     async fn grid_power_w(&self) -> Result<f64, tapo::Error> {
+///         // Read the grid power from the inverter...
+///         Ok(-1200.0)
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+///
+/// let _controller = SolarSurplus::new(manager, Inverter, "solar")
+///     .with_nominal_power("water-heater", 1000.0)
+///     .with_nominal_power("dehumidifier", 300.0)
+///     .start()?;
+///
+/// std::future::pending::<()>().await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct SolarSurplus<M> {
+    manager: DeviceManager,
+    meter: M,
+    tag: String,
+    nominal_powers: BTreeMap<String, f64>,
+    import_tolerance_w: f64,
+    poll_interval: Duration,
+    min_switch_interval: Duration,
+}
+
+/// The controller of a started [`SolarSurplus`]. It stops when it is dropped, leaving the devices as they are.
+#[derive(Debug)]
+pub struct RunningSolarSurplus {
+    task: JoinHandle<()>,
+    surplus: Arc<Mutex<Surplus>>,
+}
+
+impl<M> SolarSurplus<M>
+where
+    M: GridMeter + 'static,
+{
+    /// Returns a [`SolarSurplus`] without devices, which polls `meter` every 30 seconds
+    /// and doesn't switch a device more than once every 5 minutes.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager of the devices
+    /// * `meter` - the meter of the power exchanged with the grid
+    /// * `tag` - the tag of the plugs powered by the surplus
+    pub fn new(manager: DeviceManager, meter: M, tag: impl Into<String>) -> Self {
+        Self {
+            manager,
+            meter,
+            tag: tag.into(),
+            nominal_powers: BTreeMap::new(),
+            import_tolerance_w: 0.0,
+            poll_interval: Duration::from_secs(30),
+            min_switch_interval: Duration::from_secs(300),
+        }
+    }
+
+    /// Sets the power drawn by the load of a device when it's on, in W.
+    /// The tagged devices without a nominal power are ignored.
+    pub fn with_nominal_power(mut self, device: impl Into<String>, power_w: f64) -> Self {
+        self.nominal_powers.insert(device.into(), power_w);
+        self
+    }
+
+    /// Sets how much power, in W, can be imported from the grid before a device is turned off. Defaults to 0.
+    pub fn with_import_tolerance(mut self, import_tolerance_w: f64) -> Self {
+        self.import_tolerance_w = import_tolerance_w;
+        self
+    }
+
+    /// Sets how often the meter is polled. Defaults to 30 seconds.
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets the minimum time between two switches of the same device. Defaults to 5 minutes.
+    pub fn with_min_switch_interval(mut self, min_switch_interval: Duration) -> Self {
+        self.min_switch_interval = min_switch_interval;
+        self
+    }
+
+    /// Validates the nominal powers and starts the controller. Must be called from within a Tokio runtime.
+    pub fn start(self) -> Result<RunningSolarSurplus, Error> {
+        for (device, power_w) in &self.nominal_powers {
+            if !(power_w.is_finite() && *power_w > 0.0) {
+                return Err(Error::Validation {
+                    field: format!("nominal_powers.{device}"),
+                    message: "must be greater than 0".to_string(),
+                });
+            }
+        }
+        if !(self.import_tolerance_w.is_finite() && self.import_tolerance_w >= 0.0) {
+            return Err(Error::Validation {
+                field: "import_tolerance_w".to_string(),
+                message: "must not be negative".to_string(),
+            });
+        }
+
+        let surplus = Arc::new(Mutex::new(Surplus {
+            import_tolerance_w: self.import_tolerance_w,
+            min_switch_interval: self.min_switch_interval,
+            switched_at: BTreeMap::new(),
+            turned_on: Vec::new(),
+        }));
+        let task = tokio::spawn(self.run(surplus.clone()));
+
+        Ok(RunningSolarSurplus { task, surplus })
+    }
+
+    async fn run(self, surplus: Arc<Mutex<Surplus>>) {
+        let mut interval = tokio::time::interval(self.poll_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+
+            let grid_power_w = match self.meter.grid_power_w().await {
+                Ok(grid_power_w) => grid_power_w,
+                Err(err) => {
+                    warn!("Failed to read the grid power: {err:?}");
+                    continue;
+                }
+            };
+            let devices = match self.manager.devices_with_tag(&self.tag).await {
+                Ok(devices) => devices,
+                Err(err) => {
+                    warn!("Failed to list the devices tagged `{}`: {err:?}", self.tag);
+                    continue;
+                }
+            };
+            let candidates: BTreeMap<_, _> = devices
+                .iter()
+                .filter_map(|device| {
+                    let power_w = self.nominal_powers.get(device.name())?;
+                    Some((device.name().to_string(), *power_w))
+                })
+                .collect();
+
+            let Some(step) = surplus.lock().expect("the lock is never poisoned").update(
+                grid_power_w,
+                &candidates,
+                Instant::now(),
+            ) else {
+                continue;
+            };
+
+            let result = match &step {
+                Step::On(name) => {
+                    info!("Turning on `{name}` with {:.0} W of surplus", -grid_power_w);
+                    self.manager.device(name.as_str()).on().await
+                }
+                Step::Off(name) => {
+                    info!("Turning off `{name}` while importing {grid_power_w:.0} W");
+                    self.manager.device(name.as_str()).off().await
+                }
+            };
+
+            if let Err(err) = result {
+                if err.is_maintenance() {
+                    debug!("Not switching {step:?}: {err}");
+                } else {
+                    warn!("Failed to switch {step:?}: {err:?}");
+                }
+                surplus
+                    .lock()
+                    .expect("the lock is never poisoned")
+                    .revert(&step);
+            }
+        }
+    }
+}
+
+impl RunningSolarSurplus {
+    /// Returns the names of the devices turned on by the controller, in the order in which they have been turned on.
+    pub fn powered_devices(&self) -> Vec<String> {
+        self.surplus
+            .lock()
+            .expect("the lock is never poisoned")
+            .turned_on
+            .clone()
+    }
+
+    /// Stops the controller. The devices are left as they are.
+    pub fn stop(self) {}
+}
+
+impl Drop for RunningSolarSurplus {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// A device to turn on or off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    On(String),
+    Off(String),
+}
+
+/// The switching decisions of a [`SolarSurplus`], apart from the meter and the devices.
+#[derive(Debug)]
+struct Surplus {
+    import_tolerance_w: f64,
+    min_switch_interval: Duration,
+    /// When each device has last been switched.
+    switched_at: BTreeMap<String, Instant>,
+    /// The devices turned on by the controller, in order.
+    turned_on: Vec<String>,
+}
+
+impl Surplus {
+    /// Returns the device to switch given the grid power, in W,
+    /// and the nominal power of the devices that can be switched, in W, by name.
+    fn update(
+        &mut self,
+        grid_power_w: f64,
+        candidates: &BTreeMap<String, f64>,
+        now: Instant,
+    ) -> Option<Step> {
+        // The devices that are no longer tagged are forgotten, and left as they are.
+        self.turned_on.retain(|name| candidates.contains_key(name));
+
+        let can_switch = |name: &str| match self.switched_at.get(name) {
+            Some(at) => now.duration_since(*at) >= self.min_switch_interval,
+            None => true,
+        };
+
+        let step = if grid_power_w > self.import_tolerance_w {
+            let name = self.turned_on.last()?;
+            can_switch(name).then(|| Step::Off(name.clone()))
+        } else {
+            let surplus_w = -grid_power_w;
+            candidates
+                .iter()
+                .filter(|(name, &power_w)| {
+                    power_w <= surplus_w && !self.turned_on.contains(name) && can_switch(name)
+                })
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(name, _)| Step::On(name.clone()))
+        }?;
+
+        match &step {
+            Step::On(name) => self.turned_on.push(name.clone()),
+            Step::Off(name) => self.turned_on.retain(|on| on != name),
+        }
+        let (Step::On(name) | Step::Off(name)) = &step;
+        self.switched_at.insert(name.clone(), now);
+
+        Some(step)
+    }
+
+    /// Undoes a step that has failed, so that it can be retried at the next poll.
+    fn revert(&mut self, step: &Step) {
+        match step {
+            Step::On(name) => {
+                self.turned_on.retain(|on| on != name);
+                self.switched_at.remove(name);
+            }
+            Step::Off(name) => {
+                self.turned_on.push(name.clone());
+                self.switched_at.remove(name);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devices_follow_the_surplus() {
+        let mut surplus = Surplus {
+            import_tolerance_w: 100.0,
+            min_switch_interval: Duration::from_secs(300),
+            switched_at: BTreeMap::new(),
+            turned_on: Vec::new(),
+        };
+        let candidates = BTreeMap::from([
+            ("water-heater".to_string(), 1000.0),
+            ("dehumidifier".to_string(), 300.0),
+        ]);
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+
+        assert_eq!(
+            surplus.update(-1200.0, &candidates, at(0)),
+            Some(Step::On("water-heater".to_string()))
+        );
+        assert_eq!(
+            surplus.update(-200.0, &candidates, at(30)),
+            None,
+            "not enough surplus for the dehumidifier"
+        );
+        assert_eq!(
+            surplus.update(-400.0, &candidates, at(60)),
+            Some(Step::On("dehumidifier".to_string()))
+        );
+        assert_eq!(
+            surplus.update(50.0, &candidates, at(90)),
+            None,
+            "within the import tolerance"
+        );
+        assert_eq!(
+            surplus.update(500.0, &candidates, at(120)),
+            None,
+            "the dehumidifier has been switched too recently"
+        );
+        assert_eq!(
+            surplus.update(500.0, &candidates, at(360)),
+            Some(Step::Off("dehumidifier".to_string()))
+        );
+        assert_eq!(surplus.turned_on, ["water-heater"]);
+    }
+}
@@ -0,0 +1,324 @@
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use crate::events::DeviceEvent;
+use crate::manager::{Command, DeviceHandle, DeviceKind, ManagedDevice};
+use crate::{discover_devices, normalize_mac, ApiClient};
+
+/// What the [`crate::manager::DeviceManager`] does about the devices that stop responding,
+/// see [`crate::manager::DeviceManagerBuilder::watchdog`].
+///
+/// Every registered device is probed with a *device info* request at [`WatchdogPolicy::with_probe_interval`].
+/// Once enough probes have failed in a row, the device is considered unreachable: a [`DeviceEvent::Offline`] is sent
+/// to the subscribers of [`crate::manager::DeviceManager::subscribe`], and the manager reconnects to the device
+/// according to [`WatchdogPolicy::with_retry_schedule`], optionally rediscovering its address first.
+/// A [`DeviceEvent::Online`] is sent once it has reconnected.
+/// The devices in maintenance mode, see [`crate::manager::DeviceManager::set_maintenance`],
+/// are neither reported offline nor reconnected to.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use tapo::ApiClient;
+/// # use tapo::manager::{DeviceKind, DeviceManager, WatchdogPolicy};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = ApiClient::new("tapo-username@example.com", "tapo-password")?;
+/// let manager = DeviceManager::builder(client)
+///     .watchdog(
+///         WatchdogPolicy::default()
+///             .with_probe_interval(Duration::from_secs(30))
+///             .with_rediscovery("192.168.1.255"),
+///     )
+///     .build();
+/// let mut events = manager.subscribe();
+///
+/// manager
+///     .register("kitchen", DeviceKind::Plug, "192.168.1.100")
+///     .await?;
+///
+/// while let Ok(event) = events.recv().await {
+///     println!("{}: {:?}", event.name, event.event);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct WatchdogPolicy {
+    probe_interval: Duration,
+    failure_threshold: u32,
+    retry_schedule: Vec<Duration>,
+    broadcast_address: Option<String>,
+    discovery_timeout: Duration,
+}
+
+impl Default for WatchdogPolicy {
+    fn default() -> Self {
+        Self {
+            probe_interval: Duration::from_secs(60),
+            failure_threshold: 2,
+            retry_schedule: vec![
+                Duration::from_secs(10),
+                Duration::from_secs(30),
+                Duration::from_secs(60),
+                Duration::from_secs(300),
+            ],
+            broadcast_address: None,
+            discovery_timeout: Duration::from_secs(3),
+        }
+    }
+}
+
+impl WatchdogPolicy {
+    /// Sets how often every device is probed. Defaults to 60 seconds.
+    pub fn with_probe_interval(mut self, probe_interval: Duration) -> Self {
+        self.probe_interval = probe_interval;
+        self
+    }
+
+    /// Sets how many probes in a row must fail, after their retries, before the device is considered unreachable.
+    /// Defaults to 2.
+    pub fn with_failure_threshold(mut self, failure_threshold: u32) -> Self {
+        self.failure_threshold = failure_threshold.max(1);
+        self
+    }
+
+    /// Sets the delays before the reconnection attempts to an unreachable device.
+    /// The last delay is repeated until the device can be reached again,
+    /// and an empty schedule retries at the probe interval.
+    /// Defaults to 10 seconds, 30 seconds, 1 minute, then every 5 minutes.
+    pub fn with_retry_schedule(mut self, retry_schedule: Vec<Duration>) -> Self {
+        self.retry_schedule = retry_schedule;
+        self
+    }
+
+    /// Re-runs the discovery on `broadcast_address` before every reconnection attempt,
+    /// and reconnects to the new IP address of the device if it has changed, e.g. after a DHCP lease has expired.
+    /// Not needed when the [`ApiClient`] has a [`crate::DeviceRegistry`] attached, which already re-resolves the addresses.
+    pub fn with_rediscovery(mut self, broadcast_address: impl Into<String>) -> Self {
+        self.broadcast_address = Some(broadcast_address.into());
+        self
+    }
+
+    /// Sets how long the rediscovery waits for the devices to answer. Defaults to 3 seconds.
+    pub fn with_discovery_timeout(mut self, discovery_timeout: Duration) -> Self {
+        self.discovery_timeout = discovery_timeout;
+        self
+    }
+
+    /// Returns the delay before the given reconnection `attempt`, starting from 0.
+    fn retry_delay(&self, attempt: usize) -> Duration {
+        self.retry_schedule
+            .get(attempt)
+            .or(self.retry_schedule.last())
+            .copied()
+            .unwrap_or(self.probe_interval)
+    }
+}
+
+/// A [`DeviceEvent`] of a device registered with a [`crate::manager::DeviceManager`],
+/// see [`crate::manager::DeviceManager::subscribe`].
+///
+/// Serialized as e.g. `{"name":"kitchen","event":{"type":"power_state_changed","device_on":true}}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ManagedDeviceEvent {
+    /// The name that the device is registered under.
+    pub name: String,
+    /// What has happened to the device.
+    pub event: DeviceEvent,
+}
+
+/// Supervises a single registration of a device, until the device is unregistered or replaced, or the manager stops.
+pub(crate) struct Watchdog {
+    pub policy: WatchdogPolicy,
+    pub name: String,
+    pub registration: u64,
+    pub client: ApiClient,
+    pub kind: DeviceKind,
+    pub ip_address: String,
+    /// Weak, so that the watchdog doesn't keep the manager running.
+    pub commands: mpsc::WeakSender<Command>,
+    pub events: broadcast::Sender<ManagedDeviceEvent>,
+}
+
+impl Watchdog {
+    pub async fn run(mut self) {
+        let mut mac = None;
+        let mut failures = 0;
+
+        loop {
+            tokio::time::sleep(self.policy.probe_interval).await;
+
+            let Some(commands) = self.commands.upgrade() else {
+                return;
+            };
+            if !self.is_registered(&commands).await {
+                return;
+            }
+
+            match DeviceHandle::new(self.name.clone(), commands)
+                .get_device_info_json()
+                .await
+            {
+                Ok(device_info) => {
+                    failures = 0;
+                    mac = device_info["mac"].as_str().map(normalize_mac).or(mac);
+                    continue;
+                }
+                Err(err) if err.is_maintenance() => {
+                    failures = 0;
+                    continue;
+                }
+                Err(err) => {
+                    failures += 1;
+                    debug!("Probe {failures} of `{}` failed: {err}", self.name);
+                }
+            }
+
+            if failures < self.policy.failure_threshold {
+                continue;
+            }
+
+            warn!("Device `{}` is unreachable, reconnecting...", self.name);
+            self.notify(DeviceEvent::Offline);
+
+            if !self.reconnect(mac.as_deref()).await {
+                return;
+            }
+
+            info!("Device `{}` has been reconnected", self.name);
+            self.notify(DeviceEvent::Online);
+            failures = 0;
+        }
+    }
+
+    /// Reconnects according to the retry schedule.
+    /// Returns `false` if the registration has ended in the meantime.
+    async fn reconnect(&mut self, mac: Option<&str>) -> bool {
+        let mut attempt = 0;
+
+        loop {
+            tokio::time::sleep(self.policy.retry_delay(attempt)).await;
+
+            let Some(commands) = self.commands.upgrade() else {
+                return false;
+            };
+            if !self.is_registered(&commands).await {
+                return false;
+            }
+            if self.is_in_maintenance(&commands).await {
+                debug!("Not reconnecting to `{}` while in maintenance", self.name);
+                continue;
+            }
+
+            if let (Some(broadcast_address), Some(mac)) = (&self.policy.broadcast_address, mac) {
+                self.rediscover(broadcast_address.clone(), mac).await;
+            }
+
+            let device = match ManagedDevice::connect(
+                self.client.clone(),
+                self.kind,
+                self.ip_address.clone(),
+            )
+            .await
+            {
+                Ok(device) => device,
+                Err(err) => {
+                    attempt += 1;
+                    debug!(
+                        "Reconnection attempt {attempt} to `{}` failed: {err}",
+                        self.name
+                    );
+                    continue;
+                }
+            };
+
+            let (reply, response) = oneshot::channel();
+            let command = Command::Reconnect {
+                name: self.name.clone(),
+                registration: self.registration,
+                device,
+                reply,
+            };
+            if commands.send(command).await.is_err() {
+                return false;
+            }
+
+            return response.await.unwrap_or(false);
+        }
+    }
+
+    /// Updates the IP address of the device if the discovery finds it at a new one.
+    async fn rediscover(&mut self, broadcast_address: String, mac: &str) {
+        let devices =
+            match discover_devices(&broadcast_address, self.policy.discovery_timeout).await {
+                Ok(devices) => devices,
+                Err(err) => {
+                    debug!("Rediscovery of `{}` failed: {err}", self.name);
+                    return;
+                }
+            };
+
+        if let Some(device) = devices.into_iter().find(|device| device.mac == mac) {
+            if device.ip != self.ip_address {
+                info!(
+                    "Device `{}` has moved from {} to {}",
+                    self.name, self.ip_address, device.ip
+                );
+                self.ip_address = device.ip;
+            }
+        }
+    }
+
+    async fn is_registered(&self, commands: &mpsc::Sender<Command>) -> bool {
+        let (reply, response) = oneshot::channel();
+        let command = Command::IsRegistered {
+            name: self.name.clone(),
+            registration: self.registration,
+            reply,
+        };
+
+        commands.send(command).await.is_ok() && response.await.unwrap_or(false)
+    }
+
+    async fn is_in_maintenance(&self, commands: &mpsc::Sender<Command>) -> bool {
+        let (reply, response) = oneshot::channel();
+        let command = Command::Maintenance { reply };
+
+        commands.send(command).await.is_ok()
+            && response
+                .await
+                .is_ok_and(|devices| devices.contains(&self.name))
+    }
+
+    fn notify(&self, event: DeviceEvent) {
+        let _ = self.events.send(ManagedDeviceEvent {
+            name: self.name.clone(),
+            event,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_retry_delay_is_repeated() {
+        let policy = WatchdogPolicy::default()
+            .with_retry_schedule(vec![Duration::from_secs(1), Duration::from_secs(5)]);
+
+        assert_eq!(policy.retry_delay(0), Duration::from_secs(1));
+        assert_eq!(policy.retry_delay(1), Duration::from_secs(5));
+        assert_eq!(policy.retry_delay(7), Duration::from_secs(5));
+
+        let policy = policy
+            .with_retry_schedule(Vec::new())
+            .with_probe_interval(Duration::from_secs(42));
+        assert_eq!(policy.retry_delay(0), Duration::from_secs(42));
+    }
+}
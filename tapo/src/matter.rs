@@ -0,0 +1,416 @@
+//! Matter data model descriptors of the devices, for bridges that expose them as Matter endpoints.
+//!
+//! [`EndpointDescriptor::from_capabilities`] maps the [`Capabilities`] of a device to the Matter device type
+//! and the clusters of the endpoint that represents it: *On/Off*, *Level Control* and *Color Control* for the lights,
+//! and *On/Off* with *Electrical Measurement* for the plugs with energy monitoring.
+//! [`EndpointDescriptor::read`] converts the *device info* of the device to the values of the attributes,
+//! e.g. the *brightness* between 1 and 100 to a *current level* between 1 and 254.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use tapo::ApiClient;
+//! # use tapo::matter::EndpointDescriptor;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+//!     .l530("192.168.1.100")
+//!     .await?;
+//!
+//! let capabilities = device.get_capabilities().await?;
+//! let device_info = device.get_device_info_json().await?;
+//!
+//! if let Some(endpoint) = EndpointDescriptor::from_capabilities(&capabilities) {
+//!     println!("Device type: {:#06x}", endpoint.device_type.id());
+//!
+//!     for cluster in &endpoint.clusters {
+//!         for attribute in &cluster.attributes {
+//!             let value = endpoint.read(*attribute, &device_info);
+//!             println!("{:?}/{attribute:?}: {value:?}", cluster.cluster);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use crate::requests::Kelvin;
+use crate::responses::Capabilities;
+
+/// The highest *current level* of the *Level Control* cluster, and of the *current hue* and *current saturation*
+/// of the *Color Control* cluster.
+const MAX_LEVEL: u8 = 254;
+
+/// The Matter representation of a device, as a single endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointDescriptor {
+    /// The device type of the endpoint.
+    pub device_type: DeviceType,
+    /// The server clusters of the endpoint, besides the *Descriptor* and *Identify* clusters that every endpoint has.
+    pub clusters: Vec<ClusterDescriptor>,
+    /// The color temperature range of the device in Kelvin, for the lights with a *color temperature*.
+    pub color_temperature_range: Option<RangeInclusive<u16>>,
+}
+
+/// The device type of an [`EndpointDescriptor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceType {
+    /// A light with a *brightness*.
+    DimmableLight,
+    /// A light with a *brightness* and a *color temperature*.
+    ColorTemperatureLight,
+    /// A light with a *brightness*, a *color* and possibly a *color temperature*.
+    ExtendedColorLight,
+    /// A plug.
+    OnOffPlugInUnit,
+}
+
+/// A server cluster of an [`EndpointDescriptor`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClusterDescriptor {
+    /// The cluster.
+    pub cluster: Cluster,
+    /// The value of the *FeatureMap* global attribute.
+    pub feature_map: u32,
+    /// The attributes of the cluster that are backed by the device.
+    pub attributes: Vec<Attribute>,
+}
+
+/// A cluster of a [`ClusterDescriptor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cluster {
+    /// The *On/Off* cluster.
+    OnOff,
+    /// The *Level Control* cluster.
+    LevelControl,
+    /// The *Color Control* cluster.
+    ColorControl,
+    /// The *Electrical Measurement* cluster.
+    ElectricalMeasurement,
+}
+
+/// An attribute of a [`ClusterDescriptor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Attribute {
+    /// *OnOff* of the *On/Off* cluster.
+    OnOff,
+    /// *CurrentLevel* of the *Level Control* cluster, between 1 and 254.
+    CurrentLevel,
+    /// *MinLevel* of the *Level Control* cluster.
+    MinLevel,
+    /// *MaxLevel* of the *Level Control* cluster.
+    MaxLevel,
+    /// *CurrentHue* of the *Color Control* cluster, between 0 and 254.
+    CurrentHue,
+    /// *CurrentSaturation* of the *Color Control* cluster, between 0 and 254.
+    CurrentSaturation,
+    /// *ColorTemperatureMireds* of the *Color Control* cluster.
+    ColorTemperatureMireds,
+    /// *ColorMode* of the *Color Control* cluster: 0 for *hue* and *saturation* and 2 for *color temperature*.
+    ColorMode,
+    /// *ColorTempPhysicalMinMireds* of the *Color Control* cluster, i.e. the coolest color temperature.
+    ColorTempPhysicalMinMireds,
+    /// *ColorTempPhysicalMaxMireds* of the *Color Control* cluster, i.e. the warmest color temperature.
+    ColorTempPhysicalMaxMireds,
+    /// *ActivePower* of the *Electrical Measurement* cluster, in watts.
+    ActivePower,
+}
+
+impl EndpointDescriptor {
+    /// Returns the Matter representation of a device with the given `capabilities`,
+    /// or `None` for the devices that aren't endpoints themselves, i.e. the hubs.
+    ///
+    /// The devices with a *brightness* are lights, and the other ones are plugs.
+    /// The *color temperature* is limited to 2500-6500 K, see [`EndpointDescriptor::with_color_temperature_range`].
+    pub fn from_capabilities(capabilities: &Capabilities) -> Option<Self> {
+        if capabilities.has_child_devices {
+            return None;
+        }
+
+        if !capabilities.has_brightness {
+            let mut clusters = vec![ClusterDescriptor {
+                cluster: Cluster::OnOff,
+                feature_map: 0,
+                attributes: vec![Attribute::OnOff],
+            }];
+            if capabilities.has_energy_monitoring {
+                clusters.push(ClusterDescriptor {
+                    cluster: Cluster::ElectricalMeasurement,
+                    feature_map: 0,
+                    attributes: vec![Attribute::ActivePower],
+                });
+            }
+
+            return Some(Self {
+                device_type: DeviceType::OnOffPlugInUnit,
+                clusters,
+                color_temperature_range: None,
+            });
+        }
+
+        let mut clusters = vec![
+            ClusterDescriptor {
+                cluster: Cluster::OnOff,
+                // Lighting
+                feature_map: 0x01,
+                attributes: vec![Attribute::OnOff],
+            },
+            ClusterDescriptor {
+                cluster: Cluster::LevelControl,
+                // OnOff | Lighting
+                feature_map: 0x03,
+                attributes: vec![
+                    Attribute::CurrentLevel,
+                    Attribute::MinLevel,
+                    Attribute::MaxLevel,
+                ],
+            },
+        ];
+
+        let device_type = match (capabilities.has_color, capabilities.has_color_temperature) {
+            (false, false) => DeviceType::DimmableLight,
+            (false, true) => DeviceType::ColorTemperatureLight,
+            (true, _) => DeviceType::ExtendedColorLight,
+        };
+
+        if capabilities.has_color || capabilities.has_color_temperature {
+            let mut feature_map = 0;
+            let mut attributes = Vec::new();
+
+            if capabilities.has_color {
+                // HueSaturation
+                feature_map |= 0x01;
+                attributes.extend([Attribute::CurrentHue, Attribute::CurrentSaturation]);
+            }
+            if capabilities.has_color_temperature {
+                // ColorTemperature
+                feature_map |= 0x10;
+                attributes.extend([
+                    Attribute::ColorTemperatureMireds,
+                    Attribute::ColorTempPhysicalMinMireds,
+                    Attribute::ColorTempPhysicalMaxMireds,
+                ]);
+            }
+            attributes.push(Attribute::ColorMode);
+
+            clusters.push(ClusterDescriptor {
+                cluster: Cluster::ColorControl,
+                feature_map,
+                attributes,
+            });
+        }
+
+        Some(Self {
+            device_type,
+            clusters,
+            // The range of the color lights that don't report their own, until it's known.
+            color_temperature_range: capabilities.has_color_temperature.then_some(Kelvin::RANGE),
+        })
+    }
+
+    /// Sets the color temperature range of a light with a *color temperature*,
+    /// e.g. to the range returned by [`crate::ColorLightHandler::get_color_temperature_range`].
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - the color temperature range of the device, in Kelvin
+    pub fn with_color_temperature_range(mut self, range: RangeInclusive<u16>) -> Self {
+        if self.color_temperature_range.is_some() {
+            self.color_temperature_range = Some(range);
+        }
+        self
+    }
+
+    /// Returns the value of `attribute` from the *device info* of the device,
+    /// e.g. from [`crate::ColorLightHandler::get_device_info_json`].
+    /// Returns `None` if the device info doesn't contain it.
+    ///
+    /// *ActivePower* isn't part of the device info: it is the `current_power` of
+    /// [`crate::PlugEnergyMonitoringHandler::get_current_power`], which is already in watts.
+    pub fn read(
+        &self,
+        attribute: Attribute,
+        device_info: &serde_json::Value,
+    ) -> Option<serde_json::Value> {
+        let color_temp = || device_info.get("color_temp")?.as_u64();
+
+        let value = match attribute {
+            Attribute::OnOff => device_info.get("device_on")?.as_bool()?.into(),
+            Attribute::CurrentLevel => {
+                let brightness = device_info.get("brightness")?.as_u64()?;
+                brightness_to_level(brightness.min(100) as u8).into()
+            }
+            Attribute::MinLevel => 1.into(),
+            Attribute::MaxLevel => MAX_LEVEL.into(),
+            Attribute::CurrentHue => {
+                let hue = device_info.get("hue")?.as_u64()?.min(360) as f64;
+                ((hue * MAX_LEVEL as f64 / 360.0).round() as u8).into()
+            }
+            Attribute::CurrentSaturation => {
+                let saturation = device_info.get("saturation")?.as_u64()?.min(100) as f64;
+                ((saturation * MAX_LEVEL as f64 / 100.0).round() as u8).into()
+            }
+            Attribute::ColorTemperatureMireds => match color_temp()? {
+                0 => return None,
+                kelvin => kelvin_to_mired(kelvin.min(u16::MAX as u64) as u16).into(),
+            },
+            Attribute::ColorMode => match color_temp()? {
+                0 => 0.into(),
+                _ => 2.into(),
+            },
+            Attribute::ColorTempPhysicalMinMireds => {
+                kelvin_to_mired(*self.color_temperature_range.as_ref()?.end()).into()
+            }
+            Attribute::ColorTempPhysicalMaxMireds => {
+                kelvin_to_mired(*self.color_temperature_range.as_ref()?.start()).into()
+            }
+            Attribute::ActivePower => return None,
+        };
+
+        Some(value)
+    }
+}
+
+impl DeviceType {
+    /// Returns the identifier of the device type in the Matter specification.
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::DimmableLight => 0x0101,
+            Self::ColorTemperatureLight => 0x010C,
+            Self::ExtendedColorLight => 0x010D,
+            Self::OnOffPlugInUnit => 0x010A,
+        }
+    }
+}
+
+impl Cluster {
+    /// Returns the identifier of the cluster in the Matter specification.
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::OnOff => 0x0006,
+            Self::LevelControl => 0x0008,
+            Self::ColorControl => 0x0300,
+            Self::ElectricalMeasurement => 0x0B04,
+        }
+    }
+}
+
+impl Attribute {
+    /// Returns the identifier of the attribute within its cluster in the Matter specification.
+    pub fn id(&self) -> u32 {
+        match self {
+            Self::OnOff => 0x0000,
+            Self::CurrentLevel => 0x0000,
+            Self::MinLevel => 0x0002,
+            Self::MaxLevel => 0x0003,
+            Self::CurrentHue => 0x0000,
+            Self::CurrentSaturation => 0x0001,
+            Self::ColorTemperatureMireds => 0x0007,
+            Self::ColorMode => 0x0008,
+            Self::ColorTempPhysicalMinMireds => 0x400B,
+            Self::ColorTempPhysicalMaxMireds => 0x400C,
+            Self::ActivePower => 0x050B,
+        }
+    }
+}
+
+/// Converts a *brightness* between 1 and 100 to a *current level* between 1 and 254.
+///
+/// # Arguments
+///
+/// * `brightness` - between 1 and 100
+pub fn brightness_to_level(brightness: u8) -> u8 {
+    (brightness.min(100) as f64 * MAX_LEVEL as f64 / 100.0)
+        .round()
+        .max(1.0) as u8
+}
+
+/// Converts a *current level* between 1 and 254 to a *brightness* between 1 and 100.
+/// A level of 0, which Matter uses for *off*, is converted to the lowest brightness.
+///
+/// # Arguments
+///
+/// * `level` - between 0 and 254
+pub fn level_to_brightness(level: u8) -> u8 {
+    (level.min(MAX_LEVEL) as f64 * 100.0 / MAX_LEVEL as f64)
+        .round()
+        .max(1.0) as u8
+}
+
+fn kelvin_to_mired(kelvin: u16) -> u16 {
+    (1_000_000.0 / kelvin.max(1) as f64).round() as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lights_and_plugs_are_mapped_to_their_clusters() {
+        let mut color_light = Capabilities::default();
+        color_light.has_brightness = true;
+        color_light.has_color = true;
+        color_light.has_color_temperature = true;
+
+        let endpoint = EndpointDescriptor::from_capabilities(&color_light).unwrap();
+        assert_eq!(endpoint.device_type, DeviceType::ExtendedColorLight);
+        assert_eq!(
+            endpoint
+                .clusters
+                .iter()
+                .map(|cluster| cluster.cluster)
+                .collect::<Vec<_>>(),
+            vec![Cluster::OnOff, Cluster::LevelControl, Cluster::ColorControl]
+        );
+        assert_eq!(endpoint.clusters[2].feature_map, 0x11);
+
+        let mut plug = Capabilities::default();
+        plug.has_energy_monitoring = true;
+        let endpoint = EndpointDescriptor::from_capabilities(&plug).unwrap();
+        assert_eq!(endpoint.device_type.id(), 0x010A);
+        assert_eq!(endpoint.clusters[1].cluster, Cluster::ElectricalMeasurement);
+
+        let mut hub = Capabilities::default();
+        hub.has_child_devices = true;
+        assert_eq!(EndpointDescriptor::from_capabilities(&hub), None);
+    }
+
+    #[test]
+    fn device_info_is_read_as_attribute_values() {
+        let mut capabilities = Capabilities::default();
+        capabilities.has_brightness = true;
+        capabilities.has_color_temperature = true;
+        let endpoint = EndpointDescriptor::from_capabilities(&capabilities)
+            .unwrap()
+            .with_color_temperature_range(2700..=6500);
+        let device_info = serde_json::json!({
+            "device_on": true,
+            "brightness": 50,
+            "color_temp": 2700,
+        });
+
+        let read = |attribute| endpoint.read(attribute, &device_info);
+        assert_eq!(read(Attribute::OnOff), Some(serde_json::json!(true)));
+        assert_eq!(read(Attribute::CurrentLevel), Some(serde_json::json!(127)));
+        assert_eq!(read(Attribute::ColorMode), Some(serde_json::json!(2)));
+        assert_eq!(
+            read(Attribute::ColorTemperatureMireds),
+            Some(serde_json::json!(370))
+        );
+        assert_eq!(
+            read(Attribute::ColorTempPhysicalMinMireds),
+            Some(serde_json::json!(154))
+        );
+
+        assert_eq!(brightness_to_level(1), 3);
+        assert_eq!(level_to_brightness(0), 1);
+        assert_eq!(level_to_brightness(254), 100);
+    }
+}
@@ -0,0 +1,208 @@
+//! Persistence of the last known state of devices across restarts,
+//! so that the changes that happened while the application was down can be detected.
+//!
+//! [`crate::watcher::DeviceWatcher::with_persistence`] restores the last snapshot of a device before its first poll,
+//! which is then compared to it like any other poll.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use std::sync::Arc;
+//! # use std::time::Duration;
+//! # use tapo::ApiClient;
+//! # use tapo::persistence::FilePersistence;
+//! # use tapo::watcher::DeviceWatcher;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let persistence = Arc::new(FilePersistence::open("state.json")?);
+//!
+//! let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+//!     .l530("192.168.1.100")
+//!     .await?;
+//!
+//! DeviceWatcher::new(device, Duration::from_secs(5))
+//!     .with_persistence(persistence, "living-room")?
+//!     .run(|change| println!("Change: {change:?}"))
+//!     .await;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Stores the [`PersistedState`] of devices by key, usually the name of the device.
+pub trait Persistence: Send + Sync {
+    /// Returns the state saved under `key`, if any.
+    fn load(&self, key: &str) -> Result<Option<PersistedState>, Error>;
+
+    /// Saves `state` under `key`, replacing the previous state.
+    fn save(&self, key: &str, state: &PersistedState) -> Result<(), Error>;
+}
+
+/// The last known state of a device.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PersistedState {
+    /// The last *device info* snapshot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_info: Option<serde_json::Value>,
+    /// Whether the device was reachable at the last poll.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reachable: Option<bool>,
+    /// The energy used by the device, accumulated across the daily resets of its counters.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy: Option<EnergyAccumulator>,
+    /// When the state was last updated.
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Accumulates the readings of a device energy counter that resets, such as `today_energy`,
+/// into a total that only grows.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnergyAccumulator {
+    /// The accumulated energy in watt-hours (Wh).
+    pub total_wh: u64,
+    /// The last reading of the counter in watt-hours (Wh).
+    pub last_reading_wh: Option<u64>,
+}
+
+impl EnergyAccumulator {
+    /// Adds the energy used since the previous reading and returns the new total.
+    /// A reading lower than the previous one is taken to mean that the counter has been reset, e.g. at midnight.
+    ///
+    /// # Arguments
+    ///
+    /// * `reading_wh` - the current value of the counter in watt-hours (Wh)
+    pub fn update(&mut self, reading_wh: u64) -> u64 {
+        let used = match self.last_reading_wh {
+            Some(last_reading) if reading_wh >= last_reading => reading_wh - last_reading,
+            Some(_) => reading_wh,
+            // The first reading only establishes the baseline.
+            None => 0,
+        };
+
+        self.total_wh += used;
+        self.last_reading_wh = Some(reading_wh);
+
+        self.total_wh
+    }
+}
+
+/// [`Persistence`] backed by a JSON file, which is rewritten on every save.
+#[derive(Debug)]
+pub struct FilePersistence {
+    path: PathBuf,
+    states: Mutex<BTreeMap<String, PersistedState>>,
+}
+
+impl FilePersistence {
+    /// Returns a [`FilePersistence`] that saves to `path`.
+    /// The existing states are loaded from `path` if the file exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the location of the state file
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+
+        let states = if path.exists() {
+            let contents = std::fs::read_to_string(&path).map_err(anyhow::Error::from)?;
+            serde_json::from_str(&contents)?
+        } else {
+            BTreeMap::new()
+        };
+
+        Ok(Self {
+            path,
+            states: Mutex::new(states),
+        })
+    }
+}
+
+impl Persistence for FilePersistence {
+    fn load(&self, key: &str) -> Result<Option<PersistedState>, Error> {
+        let states = self.states.lock().expect("the lock is never poisoned");
+
+        Ok(states.get(key).cloned())
+    }
+
+    fn save(&self, key: &str, state: &PersistedState) -> Result<(), Error> {
+        let mut states = self.states.lock().expect("the lock is never poisoned");
+        states.insert(key.to_string(), state.clone());
+
+        write_atomically(&self.path, serde_json::to_string_pretty(&*states)?)?;
+
+        Ok(())
+    }
+}
+
+/// Replaces the file at `path` with `contents`.
+/// They're written next to the file and renamed, so that a crash can't leave a truncated file behind.
+pub(crate) fn write_atomically(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), Error> {
+    let temporary_path = path.with_extension("tmp");
+    std::fs::write(&temporary_path, contents)
+        .and_then(|_| std::fs::rename(&temporary_path, path))
+        .map_err(anyhow::Error::from)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn energy_accumulator_survives_counter_resets() {
+        let mut accumulator = EnergyAccumulator::default();
+
+        assert_eq!(accumulator.update(100), 0);
+        assert_eq!(accumulator.update(150), 50);
+        assert_eq!(accumulator.update(20), 70);
+        assert_eq!(accumulator.update(20), 70);
+    }
+
+    #[test]
+    fn file_persistence_is_reloaded() {
+        let path =
+            std::env::temp_dir().join(format!("tapo-persistence-{}.json", uuid::Uuid::new_v4()));
+        let state = PersistedState {
+            device_info: Some(json!({ "device_on": true })),
+            reachable: Some(true),
+            energy: None,
+            updated_at: Some(Utc::now()),
+        };
+
+        FilePersistence::open(&path)
+            .unwrap()
+            .save("kitchen", &state)
+            .unwrap();
+        let reloaded = FilePersistence::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(reloaded.load("kitchen").unwrap(), Some(state));
+        assert_eq!(reloaded.load("garage").unwrap(), None);
+    }
+
+    #[test]
+    fn atomic_write_replaces_the_file_without_leaving_the_temporary_file() {
+        let path =
+            std::env::temp_dir().join(format!("tapo-persistence-{}.json", uuid::Uuid::new_v4()));
+
+        write_atomically(&path, "{}").unwrap();
+        write_atomically(&path, r#"{ "kitchen": {} }"#).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let temporary_file_exists = path.with_extension("tmp").exists();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(contents, r#"{ "kitchen": {} }"#);
+        assert!(!temporary_file_exists);
+    }
+}
@@ -0,0 +1,324 @@
+//! Storage of [`Reading`]s and [`DeviceEvent`]s in PostgreSQL, optionally with TimescaleDB, for the larger installations.
+//!
+//! Requires the `postgres` feature.
+//!
+//! [`PostgresSink::migrate`] creates the tables of [`SCHEMA`], and turns them into hypertables when the `timescaledb`
+//! extension is installed. The readings are written in batches, and the connection is re-established
+//! when it's lost, e.g. when the database restarts.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use chrono::Utc;
+//! # use tapo::ApiClient;
+//! # use tapo::postgres::PostgresSink;
+//! # use tapo::readings::Reading;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let sink = PostgresSink::builder("host=localhost user=tapo password=tapo dbname=tapo")
+//!     .batch_size(500)
+//!     .connect()
+//!     .await?;
+//! sink.migrate().await?;
+//!
+//! let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+//!     .p110("192.168.1.100")
+//!     .await?;
+//!
+//! loop {
+//!     let energy_usage = device.get_energy_usage().await?;
+//!     sink.write(Reading::from_energy_usage("kitchen", Utc::now(), &energy_usage))
+//!         .await?;
+//!     tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+//! }
+//! # }
+//! ```
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, NoTls};
+
+use crate::error::Error;
+use crate::events::DeviceEvent;
+use crate::readings::{Reading, ReadingSink};
+
+/// The tables that the readings and the events are written to, created by [`PostgresSink::migrate`].
+pub const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tapo_readings (
+        time TIMESTAMPTZ NOT NULL,
+        device TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        value DOUBLE PRECISION NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS tapo_readings_by_device ON tapo_readings (device, kind, time DESC);
+    CREATE TABLE IF NOT EXISTS tapo_events (
+        time TIMESTAMPTZ NOT NULL,
+        device TEXT NOT NULL,
+        event JSONB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS tapo_events_by_device ON tapo_events (device, time DESC);
+";
+
+/// Turns the tables of [`SCHEMA`] into TimescaleDB hypertables, if the extension is installed.
+const HYPERTABLES: &str = "
+    DO $$
+    BEGIN
+        IF EXISTS (SELECT 1 FROM pg_extension WHERE extname = 'timescaledb') THEN
+            PERFORM create_hypertable('tapo_readings', 'time', if_not_exists => TRUE, migrate_data => TRUE);
+            PERFORM create_hypertable('tapo_events', 'time', if_not_exists => TRUE, migrate_data => TRUE);
+        END IF;
+    END
+    $$;
+";
+
+const INSERT_READINGS: &str = "
+    INSERT INTO tapo_readings (time, device, kind, value)
+    SELECT * FROM UNNEST($1::TIMESTAMPTZ[], $2::TEXT[], $3::TEXT[], $4::DOUBLE PRECISION[])
+";
+
+const INSERT_EVENT: &str =
+    "INSERT INTO tapo_events (time, device, event) VALUES ($1, $2, $3::TEXT::JSONB)";
+
+/// Writes [`Reading`]s and [`DeviceEvent`]s to PostgreSQL, see [`crate::postgres`].
+#[derive(Debug)]
+pub struct PostgresSink {
+    config: String,
+    client: tokio::sync::Mutex<Client>,
+    batch_size: usize,
+    max_retries: u32,
+    retry_delay: Duration,
+    buffer: Mutex<Vec<Reading>>,
+}
+
+impl PostgresSink {
+    /// Returns a [`PostgresSinkBuilder`] for the database described by `config`.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - the connection string, e.g. `host=localhost user=tapo dbname=tapo` or `postgresql://tapo@localhost/tapo`
+    pub fn builder(config: impl Into<String>) -> PostgresSinkBuilder {
+        PostgresSinkBuilder {
+            config: config.into(),
+            batch_size: 1000,
+            max_retries: 3,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Creates the tables of [`SCHEMA`] if they don't exist yet, as TimescaleDB hypertables if the extension is installed.
+    pub async fn migrate(&self) -> Result<(), Error> {
+        let client = self.client.lock().await;
+        client
+            .batch_execute(SCHEMA)
+            .await
+            .map_err(anyhow::Error::from)?;
+        client
+            .batch_execute(HYPERTABLES)
+            .await
+            .map_err(anyhow::Error::from)?;
+
+        Ok(())
+    }
+
+    /// Adds `readings` to the current batch, and writes the batch once it has [`PostgresSinkBuilder::batch_size`]
+    /// readings. A batch that fails to be written is kept and written again together with the next one.
+    pub async fn write(&self, readings: impl IntoIterator<Item = Reading>) -> Result<(), Error> {
+        let is_full = {
+            let mut buffer = self.buffer.lock().expect("the lock is never poisoned");
+            buffer.extend(readings);
+            buffer.len() >= self.batch_size
+        };
+
+        if is_full {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the current batch, however many readings it has.
+    pub async fn flush(&self) -> Result<(), Error> {
+        let batch = std::mem::take(&mut *self.buffer.lock().expect("the lock is never poisoned"));
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        if let Err(err) = self.insert_readings(&batch).await {
+            warn!(
+                "Failed to write {} readings to PostgreSQL: {err}",
+                batch.len()
+            );
+            let mut buffer = self.buffer.lock().expect("the lock is never poisoned");
+            buffer.splice(0..0, batch);
+            return Err(err);
+        }
+
+        debug!("Wrote {} readings to PostgreSQL", batch.len());
+        Ok(())
+    }
+
+    async fn insert_readings(&self, readings: &[Reading]) -> Result<(), Error> {
+        let times: Vec<_> = readings.iter().map(|reading| reading.timestamp).collect();
+        let devices: Vec<_> = readings
+            .iter()
+            .map(|reading| reading.device.as_str())
+            .collect();
+        let kinds: Vec<_> = readings.iter().map(|reading| reading.kind.name()).collect();
+        let values: Vec<_> = readings.iter().map(|reading| reading.value).collect();
+
+        self.execute(INSERT_READINGS, &[&times, &devices, &kinds, &values])
+            .await
+    }
+
+    /// Writes an event of `device`, without batching.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the device
+    /// * `timestamp` - when the event happened
+    /// * `event` - the event
+    pub async fn write_event(
+        &self,
+        device: &str,
+        timestamp: DateTime<Utc>,
+        event: &DeviceEvent,
+    ) -> Result<(), Error> {
+        let event = serde_json::to_string(event)?;
+
+        self.execute(INSERT_EVENT, &[&timestamp, &device, &event])
+            .await
+    }
+
+    /// Runs `statement`, reconnecting and retrying when the connection has been lost.
+    async fn execute(&self, statement: &str, params: &[&(dyn ToSql + Sync)]) -> Result<(), Error> {
+        let mut client = self.client.lock().await;
+        let mut attempt = 0;
+        let mut delay = self.retry_delay;
+
+        loop {
+            let err = match client.execute(statement, params).await {
+                Ok(_) => return Ok(()),
+                Err(err) => err,
+            };
+
+            if !client.is_closed() || attempt >= self.max_retries {
+                return Err(anyhow::Error::from(err).into());
+            }
+
+            attempt += 1;
+            debug!(
+                "The connection to PostgreSQL has been lost, reconnecting in {delay:?} ({attempt}/{})",
+                self.max_retries
+            );
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+
+            match connect(&self.config).await {
+                Ok(reconnected) => *client = reconnected,
+                Err(err) => warn!("Failed to reconnect to PostgreSQL: {err}"),
+            }
+        }
+    }
+}
+
+/// Writes the batches of a [`crate::readings::SinkPipeline`] directly, without the batching of [`PostgresSink::write`].
+#[async_trait]
+impl ReadingSink for PostgresSink {
+    async fn write_batch(&self, readings: &[Reading]) -> Result<(), Error> {
+        self.insert_readings(readings).await
+    }
+}
+
+/// Builder for a [`PostgresSink`], see [`PostgresSink::builder`].
+#[derive(Debug, Clone)]
+pub struct PostgresSinkBuilder {
+    config: String,
+    batch_size: usize,
+    max_retries: u32,
+    retry_delay: Duration,
+}
+
+impl PostgresSinkBuilder {
+    /// Sets how many readings are written together. Defaults to 1000.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Sets how many times a write is retried after the connection has been lost. Defaults to 3.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first reconnection, which doubles with every retry. Defaults to 1 second.
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Connects to the database.
+    /// Returns [`Error::Validation`] if the connection string is invalid or the batch size is zero.
+    pub async fn connect(self) -> Result<PostgresSink, Error> {
+        if self.batch_size == 0 {
+            return Err(Error::Validation {
+                field: "batch_size".to_string(),
+                message: "must be greater than 0".to_string(),
+            });
+        }
+        if let Err(err) = self.config.parse::<tokio_postgres::Config>() {
+            return Err(Error::Validation {
+                field: "config".to_string(),
+                message: err.to_string(),
+            });
+        }
+
+        let client = connect(&self.config).await?;
+
+        Ok(PostgresSink {
+            config: self.config,
+            client: tokio::sync::Mutex::new(client),
+            batch_size: self.batch_size,
+            max_retries: self.max_retries,
+            retry_delay: self.retry_delay,
+            buffer: Mutex::new(Vec::new()),
+        })
+    }
+}
+
+/// Connects to the database and drives the connection on a separate task.
+async fn connect(config: &str) -> Result<Client, Error> {
+    let (client, connection) = tokio_postgres::connect(config, NoTls)
+        .await
+        .map_err(anyhow::Error::from)?;
+
+    tokio::spawn(async move {
+        if let Err(err) = connection.await {
+            warn!("The connection to PostgreSQL has failed: {err}");
+        }
+    });
+
+    Ok(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn connect_rejects_invalid_settings() {
+        assert!(matches!(
+            PostgresSink::builder("host=localhost").batch_size(0).connect().await,
+            Err(Error::Validation { field, .. }) if field == "batch_size"
+        ));
+        assert!(matches!(
+            PostgresSink::builder("host=localhost port=not-a-port").connect().await,
+            Err(Error::Validation { field, .. }) if field == "config"
+        ));
+    }
+}
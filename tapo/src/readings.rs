@@ -0,0 +1,40 @@
+//! Timestamped measurements of the devices, in the shape expected by time series storage, e.g. [`crate::influxdb`].
+//!
+//! A [`Reading`] is a single value of a [`ReadingKind`], such as the current power of a plug
+//! or the temperature of a sensor, from a named device.
+//! A [`Calibration`] corrects the readings of a device that measures consistently off.
+//!
+//! A [`SinkPipeline`] writes the readings to a [`ReadingSink`], e.g. [`crate::influxdb::InfluxDbWriter`],
+//! in batches on a separate task, so that a slow or unavailable storage can't hold up the polling of the devices.
+//! It buffers up to a fixed number of readings and drops the others according to its [`OverflowPolicy`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use chrono::Utc;
+//! # use tapo::ApiClient;
+//! # use tapo::readings::Reading;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+//!     .p110("192.168.1.100")
+//!     .await?;
+//!
+//! let readings = Reading::from_energy_usage("kitchen", Utc::now(), &device.get_energy_usage().await?);
+//! # Ok(())
+//! # }
+//! ```
+
+mod calibration;
+mod reading;
+#[cfg(feature = "client")]
+mod reading_sink;
+#[cfg(feature = "tokio-runtime")]
+mod sink_pipeline;
+
+pub use calibration::*;
+pub use reading::*;
+#[cfg(feature = "client")]
+pub use reading_sink::*;
+#[cfg(feature = "tokio-runtime")]
+pub use sink_pipeline::*;
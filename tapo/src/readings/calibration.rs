@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::readings::{Reading, ReadingKind};
+
+/// Corrections applied to the [`Reading`]s of a device that measures consistently off,
+/// e.g. a T310 placed next to a radiator.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    /// Added to the [`ReadingKind::TemperatureCelsius`] readings, in degrees Celsius. Defaults to 0.
+    #[serde(default)]
+    pub temperature_offset: f64,
+    /// Added to the [`ReadingKind::HumidityPercent`] readings, in percentage points. Defaults to 0.
+    #[serde(default)]
+    pub humidity_offset: f64,
+    /// Multiplies the power and energy readings. Defaults to 1.
+    #[serde(default = "default_power_factor")]
+    pub power_factor: f64,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self {
+            temperature_offset: 0.0,
+            humidity_offset: 0.0,
+            power_factor: default_power_factor(),
+        }
+    }
+}
+
+impl Calibration {
+    /// Returns `true` if the calibration leaves the readings unchanged.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Returns [`Error::Validation`] if an offset isn't finite or the power factor isn't positive.
+    pub fn validate(&self) -> Result<(), Error> {
+        let invalid = |field: &str, message: &str| Error::Validation {
+            field: field.to_string(),
+            message: message.to_string(),
+        };
+
+        if !self.temperature_offset.is_finite() {
+            return Err(invalid("temperature_offset", "must be a finite number"));
+        }
+        if !self.humidity_offset.is_finite() {
+            return Err(invalid("humidity_offset", "must be a finite number"));
+        }
+        if !(self.power_factor.is_finite() && self.power_factor > 0.0) {
+            return Err(invalid("power_factor", "must be greater than 0"));
+        }
+
+        Ok(())
+    }
+
+    /// Returns `reading` corrected according to its [`ReadingKind`].
+    /// The humidity is kept between 0 and 100 percent.
+    pub fn apply(&self, mut reading: Reading) -> Reading {
+        reading.value = match reading.kind {
+            ReadingKind::TemperatureCelsius => reading.value + self.temperature_offset,
+            ReadingKind::HumidityPercent => {
+                (reading.value + self.humidity_offset).clamp(0.0, 100.0)
+            }
+            ReadingKind::PowerW | ReadingKind::TodayEnergyWh | ReadingKind::MonthEnergyWh => {
+                reading.value * self.power_factor
+            }
+        };
+
+        reading
+    }
+}
+
+fn default_power_factor() -> f64 {
+    1.0
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    #[test]
+    fn apply_corrects_each_kind() {
+        let calibration = Calibration {
+            temperature_offset: -1.5,
+            humidity_offset: 5.0,
+            power_factor: 0.5,
+        };
+        let corrected = [
+            (ReadingKind::TemperatureCelsius, 22.0, 20.5),
+            (ReadingKind::HumidityPercent, 98.0, 100.0),
+            (ReadingKind::PowerW, 120.0, 60.0),
+            (ReadingKind::TodayEnergyWh, 1000.0, 500.0),
+        ];
+
+        for (kind, value, expected) in corrected {
+            let reading = Reading::new("bedroom", Utc::now(), kind, value);
+            assert_eq!(calibration.apply(reading).value, expected, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn missing_fields_leave_readings_unchanged() {
+        let calibration: Calibration =
+            serde_json::from_value(serde_json::json!({ "temperature_offset": -1.0 })).unwrap();
+
+        assert_eq!(calibration.power_factor, 1.0);
+        assert!(!calibration.is_identity());
+        assert!(Calibration::default().is_identity());
+        assert!(matches!(
+            Calibration {
+                power_factor: 0.0,
+                ..calibration
+            }
+            .validate(),
+            Err(Error::Validation { field, .. }) if field == "power_factor"
+        ));
+    }
+}
@@ -0,0 +1,164 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::responses::{CurrentPowerResult, EnergyUsageResult};
+
+/// What a [`Reading`] measures, and in which unit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadingKind {
+    /// The current power in watts (W).
+    PowerW,
+    /// The energy used since midnight in watt-hours (Wh).
+    TodayEnergyWh,
+    /// The energy used since the start of the month in watt-hours (Wh).
+    MonthEnergyWh,
+    /// The temperature in degrees Celsius.
+    TemperatureCelsius,
+    /// The relative humidity in percent.
+    HumidityPercent,
+}
+
+impl ReadingKind {
+    /// Returns the name of the kind, e.g. `power_w`, as used for the measurement names and the columns of the storage.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PowerW => "power_w",
+            Self::TodayEnergyWh => "today_energy_wh",
+            Self::MonthEnergyWh => "month_energy_wh",
+            Self::TemperatureCelsius => "temperature_celsius",
+            Self::HumidityPercent => "humidity_percent",
+        }
+    }
+
+    /// Returns the kind called `name`, see [`ReadingKind::name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::PowerW,
+            Self::TodayEnergyWh,
+            Self::MonthEnergyWh,
+            Self::TemperatureCelsius,
+            Self::HumidityPercent,
+        ]
+        .into_iter()
+        .find(|kind| kind.name() == name)
+    }
+}
+
+/// A single measurement of a device.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reading {
+    /// The name of the device.
+    pub device: String,
+    /// When the measurement was taken.
+    pub timestamp: DateTime<Utc>,
+    /// What was measured.
+    pub kind: ReadingKind,
+    /// The measured value, in the unit of [`Reading::kind`].
+    pub value: f64,
+}
+
+impl Reading {
+    /// Returns a [`Reading`] of `kind` with `value`.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the device
+    /// * `timestamp` - when the measurement was taken
+    /// * `kind` - what was measured
+    /// * `value` - the measured value
+    pub fn new(
+        device: impl Into<String>,
+        timestamp: DateTime<Utc>,
+        kind: ReadingKind,
+        value: f64,
+    ) -> Self {
+        Self {
+            device: device.into(),
+            timestamp,
+            kind,
+            value,
+        }
+    }
+
+    /// Returns the [`ReadingKind::PowerW`] reading of a [`CurrentPowerResult`].
+    pub fn from_current_power(
+        device: impl Into<String>,
+        timestamp: DateTime<Utc>,
+        current_power: &CurrentPowerResult,
+    ) -> Self {
+        Self::new(
+            device,
+            timestamp,
+            ReadingKind::PowerW,
+            current_power.current_power as f64,
+        )
+    }
+
+    /// Returns the power, today energy and month energy readings of an [`EnergyUsageResult`].
+    /// Its power is reported in milliwatts and converted to watts.
+    pub fn from_energy_usage(
+        device: impl Into<String>,
+        timestamp: DateTime<Utc>,
+        energy_usage: &EnergyUsageResult,
+    ) -> Vec<Self> {
+        let device = device.into();
+
+        [
+            (
+                ReadingKind::PowerW,
+                energy_usage.current_power as f64 / 1000.0,
+            ),
+            (ReadingKind::TodayEnergyWh, energy_usage.today_energy as f64),
+            (ReadingKind::MonthEnergyWh, energy_usage.month_energy as f64),
+        ]
+        .into_iter()
+        .map(|(kind, value)| Self::new(device.clone(), timestamp, kind, value))
+        .collect()
+    }
+
+    /// Returns a reading of `kind` for every `(timestamp, value)` sample of a series,
+    /// e.g. [`crate::responses::TemperatureHumidityRecords::temperature_series`].
+    pub fn from_series(
+        device: impl Into<String>,
+        kind: ReadingKind,
+        series: impl IntoIterator<Item = (DateTime<Utc>, f64)>,
+    ) -> Vec<Self> {
+        let device = device.into();
+
+        series
+            .into_iter()
+            .map(|(timestamp, value)| Self::new(device.clone(), timestamp, kind, value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn energy_usage_power_is_converted_to_watts() {
+        let energy_usage: EnergyUsageResult = serde_json::from_value(serde_json::json!({
+            "local_time": "2024-03-01 12:00:00",
+            "current_power": 12_345,
+            "today_runtime": 60,
+            "today_energy": 250,
+            "month_runtime": 600,
+            "month_energy": 4_000,
+        }))
+        .unwrap();
+        let timestamp = Utc::now();
+
+        let readings = Reading::from_energy_usage("plug", timestamp, &energy_usage);
+
+        assert_eq!(
+            readings,
+            vec![
+                Reading::new("plug", timestamp, ReadingKind::PowerW, 12.345),
+                Reading::new("plug", timestamp, ReadingKind::TodayEnergyWh, 250.0),
+                Reading::new("plug", timestamp, ReadingKind::MonthEnergyWh, 4_000.0),
+            ]
+        );
+    }
+}
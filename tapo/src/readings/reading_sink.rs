@@ -0,0 +1,13 @@
+use async_trait::async_trait;
+
+use crate::error::Error;
+use crate::readings::Reading;
+
+/// A storage that [`Reading`]s are written to in batches, usually through a [`crate::readings::SinkPipeline`].
+///
+/// Implemented by [`crate::influxdb::InfluxDbWriter`], [`crate::sqlite::SqliteStore`] and [`crate::postgres::PostgresSink`].
+#[async_trait]
+pub trait ReadingSink: Send + Sync {
+    /// Writes `readings` at once. The readings of a batch that fails are not written again.
+    async fn write_batch(&self, readings: &[Reading]) -> Result<(), Error>;
+}
@@ -0,0 +1,346 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, warn};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use crate::readings::{Reading, ReadingSink};
+
+/// What a [`SinkPipeline`] does with the readings pushed while its buffer is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The pushed readings are dropped, keeping the older ones.
+    DropNewest,
+    /// The oldest readings of the buffer are dropped to make room for the pushed ones.
+    #[default]
+    DropOldest,
+}
+
+/// The number of readings that went through a [`SinkPipeline`], see [`SinkPipeline::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SinkPipelineStats {
+    /// The readings written to the sink.
+    pub written: u64,
+    /// The readings dropped because the buffer was full.
+    pub dropped: u64,
+    /// The readings of the batches that the sink failed to write.
+    pub failed: u64,
+}
+
+/// Writes [`Reading`]s to a [`ReadingSink`] in batches on a separate Tokio task, see [`crate::readings`].
+///
+/// [`SinkPipeline::push`] never waits for the sink: the readings are buffered, up to the capacity of the pipeline,
+/// and written once there are enough of them for a batch or the flush interval has elapsed.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use std::sync::Arc;
+/// # use chrono::Utc;
+/// # use tapo::readings::{Reading, ReadingKind, ReadingSink, SinkPipeline};
+/// # use tapo::Error;
+/// #[derive(Debug)]
+/// struct LogSink;
+///
+/// #[async_trait::async_trait]
+/// impl ReadingSink for LogSink {
+///     async fn write_batch(&self, readings: &[Reading]) -> Result<(), Error> {
+///         println!("{readings:?}");
+///         Ok(())
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let pipeline = SinkPipeline::builder(Arc::new(LogSink))
+///     .capacity(10_000)
+///     .batch_size(500)
+///     .start();
+///
+/// pipeline.push([Reading::new("kitchen", Utc::now(), ReadingKind::PowerW, 12.0)]);
+/// pipeline.close().await;
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SinkPipeline {
+    shared: Arc<Shared>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    buffer: Mutex<VecDeque<Reading>>,
+    notify: Notify,
+    closed: AtomicBool,
+    capacity: usize,
+    batch_size: usize,
+    overflow_policy: OverflowPolicy,
+    written: AtomicU64,
+    dropped: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl SinkPipeline {
+    /// Returns a [`SinkPipelineBuilder`] for writing to `sink`.
+    pub fn builder(sink: Arc<dyn ReadingSink>) -> SinkPipelineBuilder {
+        SinkPipelineBuilder {
+            sink,
+            capacity: 10_000,
+            batch_size: 1000,
+            flush_interval: Duration::from_secs(10),
+            overflow_policy: OverflowPolicy::default(),
+        }
+    }
+
+    /// Adds `readings` to the buffer without waiting, dropping readings according to the [`OverflowPolicy`]
+    /// if the buffer is full. Returns how many readings have been dropped.
+    pub fn push(&self, readings: impl IntoIterator<Item = Reading>) -> usize {
+        let shared = &self.shared;
+        let mut dropped = 0;
+
+        let is_batch_ready = {
+            let mut buffer = shared.buffer.lock().expect("the lock is never poisoned");
+
+            for reading in readings {
+                if buffer.len() >= shared.capacity {
+                    dropped += 1;
+                    match shared.overflow_policy {
+                        OverflowPolicy::DropNewest => continue,
+                        OverflowPolicy::DropOldest => {
+                            buffer.pop_front();
+                        }
+                    }
+                }
+                buffer.push_back(reading);
+            }
+
+            buffer.len() >= shared.batch_size
+        };
+
+        if dropped > 0 {
+            debug!("The buffer of the sink pipeline is full, dropped {dropped} readings");
+            shared.dropped.fetch_add(dropped as u64, Ordering::Relaxed);
+        }
+        if is_batch_ready {
+            shared.notify.notify_one();
+        }
+
+        dropped
+    }
+
+    /// Returns the number of readings that are waiting to be written.
+    pub fn len(&self) -> usize {
+        self.shared
+            .buffer
+            .lock()
+            .expect("the lock is never poisoned")
+            .len()
+    }
+
+    /// Returns `true` if no readings are waiting to be written.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of readings that have been written, dropped or failed so far.
+    pub fn stats(&self) -> SinkPipelineStats {
+        SinkPipelineStats {
+            written: self.shared.written.load(Ordering::Relaxed),
+            dropped: self.shared.dropped.load(Ordering::Relaxed),
+            failed: self.shared.failed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Writes the buffered readings and stops the task of the pipeline.
+    /// The readings pushed afterwards are never written.
+    pub async fn close(&self) {
+        self.shared.closed.store(true, Ordering::Relaxed);
+        self.shared.notify.notify_one();
+
+        let worker = self
+            .worker
+            .lock()
+            .expect("the lock is never poisoned")
+            .take();
+        if let Some(worker) = worker {
+            let _ = worker.await;
+        }
+    }
+}
+
+/// Builder for a [`SinkPipeline`], see [`SinkPipeline::builder`].
+pub struct SinkPipelineBuilder {
+    sink: Arc<dyn ReadingSink>,
+    capacity: usize,
+    batch_size: usize,
+    flush_interval: Duration,
+    overflow_policy: OverflowPolicy,
+}
+
+impl SinkPipelineBuilder {
+    /// Sets how many readings can be buffered. Defaults to 10000.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Sets how many readings are written together. Defaults to 1000.
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets the maximum time that a reading waits for its batch to be complete. Defaults to 10 seconds.
+    pub fn flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// Sets what happens to the readings pushed while the buffer is full. Defaults to [`OverflowPolicy::DropOldest`].
+    pub fn overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.overflow_policy = overflow_policy;
+        self
+    }
+
+    /// Starts the task of the [`SinkPipeline`]. Must be called from within a Tokio runtime.
+    pub fn start(self) -> SinkPipeline {
+        let shared = Arc::new(Shared {
+            buffer: Mutex::new(VecDeque::new()),
+            notify: Notify::new(),
+            closed: AtomicBool::new(false),
+            capacity: self.capacity,
+            batch_size: self.batch_size,
+            overflow_policy: self.overflow_policy,
+            written: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        });
+
+        let worker = tokio::spawn(run(shared.clone(), self.sink, self.flush_interval));
+
+        SinkPipeline {
+            shared,
+            worker: Mutex::new(Some(worker)),
+        }
+    }
+}
+
+async fn run(shared: Arc<Shared>, sink: Arc<dyn ReadingSink>, flush_interval: Duration) {
+    loop {
+        let is_timeout = tokio::time::timeout(flush_interval, shared.notify.notified())
+            .await
+            .is_err();
+        let is_closed = shared.closed.load(Ordering::Relaxed);
+
+        loop {
+            let batch: Vec<_> = {
+                let mut buffer = shared.buffer.lock().expect("the lock is never poisoned");
+                let is_complete = buffer.len() >= shared.batch_size;
+                if buffer.is_empty() || !(is_complete || is_timeout || is_closed) {
+                    break;
+                }
+
+                let size = buffer.len().min(shared.batch_size);
+                buffer.drain(..size).collect()
+            };
+
+            match sink.write_batch(&batch).await {
+                Ok(()) => {
+                    shared
+                        .written
+                        .fetch_add(batch.len() as u64, Ordering::Relaxed);
+                }
+                Err(err) => {
+                    warn!("Failed to write {} readings: {err}", batch.len());
+                    shared
+                        .failed
+                        .fetch_add(batch.len() as u64, Ordering::Relaxed);
+                }
+            }
+        }
+
+        if is_closed {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use chrono::Utc;
+
+    use crate::error::Error;
+    use crate::readings::ReadingKind;
+
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct RecordingSink {
+        batches: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl ReadingSink for RecordingSink {
+        async fn write_batch(&self, readings: &[Reading]) -> Result<(), Error> {
+            self.batches.lock().unwrap().push(readings.len());
+            Ok(())
+        }
+    }
+
+    fn readings(count: usize) -> Vec<Reading> {
+        (0..count)
+            .map(|index| Reading::new("kitchen", Utc::now(), ReadingKind::PowerW, index as f64))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn readings_are_written_in_batches() {
+        let sink = Arc::new(RecordingSink::default());
+        let pipeline = SinkPipeline::builder(sink.clone())
+            .batch_size(4)
+            .flush_interval(Duration::from_secs(3600))
+            .start();
+
+        assert_eq!(pipeline.push(readings(10)), 0);
+        pipeline.close().await;
+
+        assert_eq!(*sink.batches.lock().unwrap(), [4, 4, 2]);
+        assert_eq!(pipeline.stats().written, 10);
+    }
+
+    #[tokio::test]
+    async fn full_buffer_drops_readings() {
+        let sink = Arc::new(RecordingSink::default());
+        let pipeline = SinkPipeline::builder(sink.clone())
+            .capacity(3)
+            .batch_size(100)
+            .flush_interval(Duration::from_secs(3600))
+            .overflow_policy(OverflowPolicy::DropOldest)
+            .start();
+
+        assert_eq!(pipeline.push(readings(5)), 2);
+        let values: Vec<_> = pipeline
+            .shared
+            .buffer
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|reading| reading.value)
+            .collect();
+        assert_eq!(values, [2.0, 3.0, 4.0]);
+
+        pipeline.close().await;
+        assert_eq!(
+            pipeline.stats(),
+            SinkPipelineStats {
+                written: 3,
+                dropped: 2,
+                failed: 0,
+            }
+        );
+    }
+}
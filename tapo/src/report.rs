@@ -0,0 +1,296 @@
+//! Daily and monthly energy reports over arbitrary date ranges.
+//!
+//! The devices only return daily data for a whole quarter and monthly data for a whole year,
+//! see [`EnergyDataInterval`]. An [`EnergyReport`] hides these windows: it's built from the
+//! results of the [`EnergyReport::intervals`] covering the requested range, and only keeps
+//! the days and months within that range.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use chrono::NaiveDate;
+//! # use tapo::ApiClient;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+//!     .p110("192.168.1.100")
+//!     .await?;
+//!
+//! let report = device
+//!     .get_energy_report(
+//!         NaiveDate::from_ymd_opt(2023, 11, 15).unwrap(),
+//!         NaiveDate::from_ymd_opt(2024, 2, 14).unwrap(),
+//!     )
+//!     .await?;
+//!
+//! for month in &report.months {
+//!     println!("{}: {} Wh", month.start_date.format("%Y-%m"), month.energy_wh);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::requests::EnergyDataInterval;
+use crate::responses::EnergyDataResult;
+
+/// The `interval` reported for [`EnergyDataInterval::Daily`].
+const DAILY_INTERVAL: u64 = 1440;
+/// The `interval` reported for [`EnergyDataInterval::Monthly`].
+const MONTHLY_INTERVAL: u64 = 43200;
+
+/// The energy used by a device between two dates, inclusive, broken down by day and by month.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnergyReport {
+    /// The first day of the report.
+    pub start_date: NaiveDate,
+    /// The last day of the report. Inclusive.
+    pub end_date: NaiveDate,
+    /// The energy used on each day of the report, in chronological order.
+    /// Days for which the device has returned no data are omitted.
+    pub days: Vec<EnergyReportEntry>,
+    /// The energy used in each month overlapping the report, in chronological order.
+    pub months: Vec<EnergyReportEntry>,
+    /// The energy used over the whole report in watt-hours (Wh).
+    pub total_energy_wh: u64,
+}
+
+/// The energy used on a day or in a month of an [`EnergyReport`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnergyReportEntry {
+    /// The day, or the first day of the month.
+    pub start_date: NaiveDate,
+    /// The energy used in watt-hours (Wh).
+    pub energy_wh: u64,
+    /// Whether only part of the month is within the report,
+    /// in which case `energy_wh` is the sum of the days within the report. Always `false` for days.
+    pub partial: bool,
+}
+
+impl EnergyReport {
+    /// Returns the [`EnergyDataInterval::Daily`] and [`EnergyDataInterval::Monthly`] intervals,
+    /// aligned on quarters and years respectively, whose data covers the given dates.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_date` - the first day of the report
+    /// * `end_date` - the last day of the report, inclusive
+    pub fn intervals(
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+    ) -> Result<Vec<EnergyDataInterval>, Error> {
+        validate_range(start_date, end_date)?;
+
+        let mut intervals = Vec::new();
+
+        let mut quarter_start = start_date
+            .with_day(1)
+            .and_then(|date| date.with_month0(date.month0() / 3 * 3))
+            .expect("the first day of a quarter is a valid date");
+        while quarter_start <= end_date {
+            intervals.push(EnergyDataInterval::Daily {
+                start_date: quarter_start,
+            });
+            quarter_start = next_date(quarter_start, 3)?;
+        }
+
+        for year in start_date.year()..=end_date.year() {
+            intervals.push(EnergyDataInterval::Monthly {
+                start_date: NaiveDate::from_ymd_opt(year, 1, 1)
+                    .expect("the first day of a year is a valid date"),
+            });
+        }
+
+        Ok(intervals)
+    }
+
+    /// Builds a report from the energy data returned for the [`EnergyReport::intervals`] of the given dates.
+    /// Results with other intervals, e.g. hourly, are ignored.
+    ///
+    /// The months that are entirely within the report use the monthly data, which the devices keep for longer.
+    /// The others use the sum of their days within the report.
+    ///
+    /// # Arguments
+    ///
+    /// * `start_date` - the first day of the report
+    /// * `end_date` - the last day of the report, inclusive
+    /// * `results` - the energy data of the daily and monthly intervals
+    pub fn from_energy_data(
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        results: &[EnergyDataResult],
+    ) -> Result<Self, Error> {
+        validate_range(start_date, end_date)?;
+
+        let mut days = Vec::new();
+        let mut monthly = Vec::new();
+
+        for result in results {
+            let samples = result
+                .series()
+                .into_iter()
+                .map(|(datetime, value)| (datetime.date_naive(), value as u64));
+
+            match result.interval {
+                DAILY_INTERVAL => {
+                    days.extend(samples.filter(|(date, _)| (start_date..=end_date).contains(date)))
+                }
+                MONTHLY_INTERVAL => monthly.extend(samples),
+                _ => {}
+            }
+        }
+
+        days.sort_by_key(|(date, _)| *date);
+        days.dedup_by_key(|(date, _)| *date);
+
+        let mut months = Vec::new();
+        let mut month_start = start_date.with_day(1).expect("day 1 is always valid");
+        while month_start <= end_date {
+            let next_month_start = next_date(month_start, 1)?;
+            let partial = month_start < start_date || next_month_start.pred_opt() > Some(end_date);
+
+            let energy_wh = if partial {
+                None
+            } else {
+                monthly
+                    .iter()
+                    .find(|(date, _)| *date == month_start)
+                    .map(|(_, value)| *value)
+            }
+            .unwrap_or_else(|| {
+                days.iter()
+                    .filter(|(date, _)| (month_start..next_month_start).contains(date))
+                    .map(|(_, value)| value)
+                    .sum()
+            });
+
+            months.push(EnergyReportEntry {
+                start_date: month_start,
+                energy_wh,
+                partial,
+            });
+            month_start = next_month_start;
+        }
+
+        Ok(Self {
+            start_date,
+            end_date,
+            days: days
+                .into_iter()
+                .map(|(date, energy_wh)| EnergyReportEntry {
+                    start_date: date,
+                    energy_wh,
+                    partial: false,
+                })
+                .collect(),
+            total_energy_wh: months.iter().map(|month| month.energy_wh).sum(),
+            months,
+        })
+    }
+}
+
+fn validate_range(start_date: NaiveDate, end_date: NaiveDate) -> Result<(), Error> {
+    if start_date > end_date {
+        return Err(Error::Validation {
+            field: "end_date".to_string(),
+            message: "Must not be before the start date".to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+fn next_date(date: NaiveDate, months: u32) -> Result<NaiveDate, Error> {
+    date.checked_add_months(Months::new(months))
+        .ok_or_else(|| Error::Validation {
+            field: "end_date".to_string(),
+            message: "Out of range".to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn energy_data(start_date: NaiveDate, interval: u64, data: Vec<u64>) -> EnergyDataResult {
+        let timestamp = start_date
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc()
+            .timestamp() as u64;
+
+        EnergyDataResult {
+            local_time: start_date.and_hms_opt(0, 0, 0).unwrap(),
+            data,
+            start_timestamp: timestamp,
+            end_timestamp: timestamp,
+            interval,
+        }
+    }
+
+    #[test]
+    fn intervals_are_aligned_on_quarters_and_years() {
+        let intervals = EnergyReport::intervals(date(2023, 11, 15), date(2024, 2, 14)).unwrap();
+
+        let start_dates: Vec<_> = intervals
+            .iter()
+            .map(|interval| match interval {
+                EnergyDataInterval::Daily { start_date } => ("daily", *start_date),
+                EnergyDataInterval::Monthly { start_date } => ("monthly", *start_date),
+                EnergyDataInterval::Hourly { start_date, .. } => ("hourly", *start_date),
+            })
+            .collect();
+
+        assert_eq!(
+            start_dates,
+            vec![
+                ("daily", date(2023, 10, 1)),
+                ("daily", date(2024, 1, 1)),
+                ("monthly", date(2023, 1, 1)),
+                ("monthly", date(2024, 1, 1)),
+            ]
+        );
+        assert!(EnergyReport::intervals(date(2024, 2, 14), date(2023, 11, 15)).is_err());
+    }
+
+    #[test]
+    fn report_is_trimmed_to_the_requested_dates() {
+        let results = vec![
+            energy_data(date(2023, 10, 1), DAILY_INTERVAL, vec![1; 92]),
+            energy_data(date(2024, 1, 1), DAILY_INTERVAL, vec![2; 91]),
+            energy_data(date(2023, 1, 1), MONTHLY_INTERVAL, vec![100; 12]),
+            energy_data(date(2024, 1, 1), MONTHLY_INTERVAL, vec![50, 60]),
+        ];
+
+        let report =
+            EnergyReport::from_energy_data(date(2023, 11, 15), date(2024, 2, 14), &results)
+                .unwrap();
+
+        assert_eq!(report.days.len(), 92);
+        assert_eq!(report.days[0].start_date, date(2023, 11, 15));
+        assert_eq!(report.days[91].start_date, date(2024, 2, 14));
+
+        let months: Vec<_> = report
+            .months
+            .iter()
+            .map(|month| (month.start_date, month.energy_wh, month.partial))
+            .collect();
+        assert_eq!(
+            months,
+            vec![
+                (date(2023, 11, 1), 16, true),
+                (date(2023, 12, 1), 100, false),
+                (date(2024, 1, 1), 50, false),
+                (date(2024, 2, 1), 28, true),
+            ]
+        );
+        assert_eq!(report.total_energy_wh, 194);
+    }
+}
@@ -1,28 +1,70 @@
 //! Tapo request objects.
 
+mod automation;
+#[cfg(feature = "client")]
+mod child_protection;
 mod color;
+#[cfg(feature = "client")]
+mod color_space;
+#[cfg(feature = "client")]
 mod control_child;
 mod energy_data_interval;
+#[cfg(feature = "client")]
 mod get_energy_data;
+#[cfg(feature = "client")]
 mod get_trigger_logs;
+#[cfg(feature = "client")]
 mod handshake;
+mod ke100_schedule;
+mod led_night_mode;
+mod light_preset;
+mod light_properties;
 mod lighting_effect;
+#[cfg(feature = "client")]
 mod login_device;
+#[cfg(feature = "client")]
 mod multiple_request;
+#[cfg(feature = "client")]
+mod play_alarm;
+mod ringtone;
+#[cfg(feature = "client")]
 mod secure_passthrough;
+#[cfg(feature = "client")]
 mod set_device_info;
+#[cfg(feature = "client")]
 mod tapo_request;
 
+pub use automation::*;
 pub use color::*;
 pub use energy_data_interval::*;
+pub use ke100_schedule::*;
+pub use led_night_mode::*;
+pub use light_preset::*;
+pub use light_properties::*;
 pub use lighting_effect::*;
+pub use ringtone::*;
+#[cfg(feature = "client")]
 pub use set_device_info::*;
 
+#[cfg(feature = "client")]
+pub(crate) use child_protection::*;
+#[cfg(feature = "client")]
+pub(crate) use color_space::*;
+#[cfg(feature = "client")]
 pub(crate) use control_child::*;
+#[cfg(feature = "client")]
 pub(crate) use get_energy_data::*;
+#[cfg(feature = "client")]
 pub(crate) use get_trigger_logs::*;
+#[cfg(feature = "client")]
 pub(crate) use handshake::*;
+#[cfg(feature = "client")]
 pub(crate) use login_device::*;
+#[cfg(feature = "client")]
 pub(crate) use multiple_request::*;
+#[cfg(feature = "client")]
+pub(crate) use play_alarm::*;
+#[cfg(feature = "client")]
 pub(crate) use secure_passthrough::*;
+#[cfg(feature = "client")]
 pub(crate) use tapo_request::*;
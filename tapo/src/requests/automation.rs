@@ -0,0 +1,231 @@
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::requests::Ringtone;
+
+/// Event reported by a child device of the hub that can trigger an [`AutomationRule`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum AutomationEvent {
+    /// T110 contact sensor has been opened.
+    Open,
+    /// T110 contact sensor has been closed.
+    Close,
+    /// T100 motion sensor has detected motion.
+    Motion,
+    /// T300 water sensor has detected a leak.
+    WaterLeak,
+    /// T300 water sensor is dry again.
+    WaterDry,
+    /// S200B button switch has been clicked once.
+    SingleClick,
+    /// S200B button switch has been clicked twice.
+    DoubleClick,
+}
+
+/// Automation that is stored and run by the hub itself, so it keeps working
+/// when the application that created it is not running.
+/// It is added with [`crate::HubHandler::add_automation`].
+///
+/// # Example
+///
+/// ```rust
+/// # use tapo::requests::{AutomationEvent, AutomationRule};
+/// let rule = AutomationRule::new("Water leak shutoff")
+///     .when("water-sensor-device-id", AutomationEvent::WaterLeak)
+///     .turn_off("plug-device-id");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AutomationRule {
+    name: String,
+    enable: bool,
+    triggers: Vec<AutomationTrigger>,
+    actions: Vec<AutomationAction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+struct AutomationTrigger {
+    device_id: String,
+    event: AutomationEvent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+enum AutomationAction {
+    Device {
+        device_id: String,
+        device_on: bool,
+    },
+    Alarm {
+        alarm_type: Ringtone,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        alarm_duration: Option<u64>,
+    },
+}
+
+impl AutomationRule {
+    /// Creates a new, enabled, [`AutomationRule`] without triggers or actions.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the automation, as shown in the Tapo app
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            enable: true,
+            triggers: Vec::new(),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Runs the automation when the child device with the given `device_id` reports `event`.
+    /// Multiple triggers can be added, in which case any of them runs the automation.
+    pub fn when(mut self, device_id: impl Into<String>, event: AutomationEvent) -> Self {
+        self.triggers.push(AutomationTrigger {
+            device_id: device_id.into(),
+            event,
+        });
+        self
+    }
+
+    /// Turns *on* the device with the given `device_id` when the automation runs.
+    pub fn turn_on(mut self, device_id: impl Into<String>) -> Self {
+        self.actions.push(AutomationAction::Device {
+            device_id: device_id.into(),
+            device_on: true,
+        });
+        self
+    }
+
+    /// Turns *off* the device with the given `device_id` when the automation runs.
+    pub fn turn_off(mut self, device_id: impl Into<String>) -> Self {
+        self.actions.push(AutomationAction::Device {
+            device_id: device_id.into(),
+            device_on: false,
+        });
+        self
+    }
+
+    /// Plays `ringtone` on the hub when the automation runs, e.g. to use the hub as a door chime
+    /// with a different ringtone for the doorbell button and for the contact sensors.
+    ///
+    /// # Arguments
+    ///
+    /// * `ringtone` - one of the ringtones returned by [`crate::HubHandler::get_supported_ringtones`]
+    /// * `duration` - how long the ringtone plays for, in seconds, or `None` for the duration
+    ///   of the alarm configuration of the hub
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use tapo::requests::{AutomationEvent, AutomationRule, Ringtone};
+    /// let doorbell = AutomationRule::new("Doorbell")
+    ///     .when("button-device-id", AutomationEvent::SingleClick)
+    ///     .play_ringtone(Ringtone::DoorbellRing(1), Some(5));
+    ///
+    /// let front_door = AutomationRule::new("Front door chime")
+    ///     .when("contact-sensor-device-id", AutomationEvent::Open)
+    ///     .play_ringtone(Ringtone::Connection(1), Some(2));
+    /// ```
+    pub fn play_ringtone(mut self, ringtone: Ringtone, duration: Option<u64>) -> Self {
+        self.actions.push(AutomationAction::Alarm {
+            alarm_type: ringtone,
+            alarm_duration: duration,
+        });
+        self
+    }
+
+    /// Sets whether the automation is enabled. Defaults to `true`.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enable = enabled;
+        self
+    }
+
+    /// Returns [`Error::Validation`] if the rule would be rejected by the device, e.g. because it has no triggers.
+    /// Called by the handlers before sending the rule.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.name.is_empty() {
+            return Err(Error::Validation {
+                field: "name".to_string(),
+                message: "must not be empty".to_string(),
+            });
+        }
+
+        if self.triggers.is_empty() {
+            return Err(Error::Validation {
+                field: "triggers".to_string(),
+                message: "must contain at least one trigger".to_string(),
+            });
+        }
+
+        if self.actions.is_empty() {
+            return Err(Error::Validation {
+                field: "actions".to_string(),
+                message: "must contain at least one action".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct GetAutomationListParams {
+    start_index: u64,
+}
+
+#[cfg(feature = "client")]
+impl GetAutomationListParams {
+    pub fn new(start_index: u64) -> Self {
+        Self { start_index }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize() {
+        let rule = AutomationRule::new("Water leak shutoff")
+            .when("sensor", AutomationEvent::WaterLeak)
+            .turn_off("plug");
+
+        assert_eq!(
+            serde_json::to_value(&rule).unwrap(),
+            serde_json::json!({
+                "name": "Water leak shutoff",
+                "enable": true,
+                "triggers": [{ "device_id": "sensor", "event": "waterLeak" }],
+                "actions": [{ "device_id": "plug", "device_on": false }],
+            })
+        );
+    }
+
+    #[test]
+    fn serialize_ringtone_action() {
+        let rule = AutomationRule::new("Doorbell")
+            .when("button", AutomationEvent::SingleClick)
+            .play_ringtone(Ringtone::DoorbellRing(2), None);
+
+        assert_eq!(
+            serde_json::to_value(&rule).unwrap()["actions"],
+            serde_json::json!([{ "alarm_type": "Doorbell Ring 2" }])
+        );
+    }
+
+    #[test]
+    fn validate_requires_action() {
+        let rule = AutomationRule::new("No action").when("sensor", AutomationEvent::Motion);
+
+        assert!(matches!(
+            rule.validate(),
+            Err(Error::Validation { field, .. }) if field == "actions"
+        ));
+    }
+}
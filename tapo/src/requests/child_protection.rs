@@ -0,0 +1,12 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SetChildProtectionParams {
+    enable: bool,
+}
+
+impl SetChildProtectionParams {
+    pub fn new(enable: bool) -> Self {
+        Self { enable }
+    }
+}
@@ -1,11 +1,14 @@
+#[cfg(feature = "client")]
 use std::collections::HashMap;
 
+#[cfg(feature = "client")]
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 
 /// List of preset colors as defined in the Google Home app.
 #[allow(missing_docs)]
 #[derive(Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum Color {
     CoolWhite,
     Daylight,
@@ -50,8 +53,10 @@ pub enum Color {
     ForestGreen,
 }
 
+#[cfg(feature = "client")]
 type ColorConfig = (Option<u16>, Option<u8>, Option<u16>);
 
+#[cfg(feature = "client")]
 lazy_static! {
     pub(crate) static ref COLOR_MAP: HashMap<Color, ColorConfig> = {
         let mut map = HashMap::new();
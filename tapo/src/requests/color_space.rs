@@ -0,0 +1,204 @@
+//! Conversions between the *hue* and *saturation* of the devices and the CIE color spaces.
+//! The *hue* and *saturation* are interpreted as sRGB colors at full value.
+
+use std::ops::RangeInclusive;
+
+/// The step of the search for the nearest color temperature, in Kelvin.
+const COLOR_TEMPERATURE_STEP: u16 = 10;
+
+/// Returns the CIE 1931 chromaticity coordinates of the color.
+///
+/// # Arguments
+///
+/// * `hue` - between 0 and 360
+/// * `saturation` - between 0 and 100
+pub(crate) fn hue_saturation_to_xy(hue: u16, saturation: u8) -> (f64, f64) {
+    let (r, g, b) = hsv_to_rgb(hue as f64, saturation as f64 / 100.0);
+    let (r, g, b) = (srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b));
+
+    let x = 0.4124 * r + 0.3576 * g + 0.1805 * b;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = 0.0193 * r + 0.1192 * g + 0.9505 * b;
+    let sum = x + y + z;
+
+    (x / sum, y / sum)
+}
+
+/// Returns the *hue* and *saturation* of the CIE 1931 chromaticity coordinates, at full brightness.
+/// The coordinates outside of the sRGB gamut are mapped to the nearest color that the devices can show.
+/// Returns `None` unless `x` and `y` are between 0 and 1 and `y` is greater than 0.
+///
+/// # Arguments
+///
+/// * `x` - the x coordinate
+/// * `y` - the y coordinate
+pub(crate) fn xy_to_hue_saturation(x: f64, y: f64) -> Option<(u16, u8)> {
+    if !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y) || y == 0.0 {
+        return None;
+    }
+
+    let (cx, cy, cz) = (x / y, 1.0, (1.0 - x - y) / y);
+    let r = 3.2406 * cx - 1.5372 * cy - 0.4986 * cz;
+    let g = -0.9689 * cx + 1.8758 * cy + 0.0415 * cz;
+    let b = 0.0557 * cx - 0.2040 * cy + 1.0570 * cz;
+
+    let (r, g, b) = (r.max(0.0), g.max(0.0), b.max(0.0));
+    let max = r.max(g).max(b);
+    if max == 0.0 {
+        return None;
+    }
+    let (r, g, b) = (
+        linear_to_srgb(r / max),
+        linear_to_srgb(g / max),
+        linear_to_srgb(b / max),
+    );
+
+    let (hue, saturation) = rgb_to_hue_saturation(r, g, b);
+
+    // The devices expect a hue between 1 and 360 and a saturation between 1 and 100.
+    let hue = match hue.round() as u16 {
+        0 => 360,
+        hue => hue,
+    };
+    let saturation = (saturation * 100.0).round().clamp(1.0, 100.0) as u8;
+
+    Some((hue, saturation))
+}
+
+/// Returns the color temperature within `range` whose point on the Planckian locus is the nearest to the color,
+/// measured in the CIE 1960 UCS.
+/// The saturated colors far from the locus are mapped to the warm or cool end of the range.
+pub(crate) fn nearest_color_temperature(
+    hue: u16,
+    saturation: u8,
+    range: RangeInclusive<u16>,
+) -> u16 {
+    let (x, y) = hue_saturation_to_xy(hue, saturation);
+    let (u, v) = xy_to_uv(x, y);
+
+    (*range.start()..=*range.end())
+        .step_by(COLOR_TEMPERATURE_STEP as usize)
+        .chain([*range.end()])
+        .min_by(|a, b| {
+            let distance = |temperature: u16| {
+                let (locus_u, locus_v) = planckian_locus_uv(temperature as f64);
+                (u - locus_u).powi(2) + (v - locus_v).powi(2)
+            };
+            distance(*a).total_cmp(&distance(*b))
+        })
+        .unwrap_or(*range.start())
+}
+
+fn hsv_to_rgb(hue: f64, saturation: f64) -> (f64, f64, f64) {
+    let chroma = saturation;
+    let sector = (hue % 360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let min = 1.0 - chroma;
+
+    let (r, g, b) = match sector as u8 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    (r + min, g + min, b + min)
+}
+
+fn rgb_to_hue_saturation(r: f64, g: f64, b: f64) -> (f64, f64) {
+    let max = r.max(g).max(b);
+    let chroma = max - r.min(g).min(b);
+
+    let hue = if chroma == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+
+    (hue, saturation)
+}
+
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn xy_to_uv(x: f64, y: f64) -> (f64, f64) {
+    let denominator = -2.0 * x + 12.0 * y + 3.0;
+    (4.0 * x / denominator, 6.0 * y / denominator)
+}
+
+/// The approximation of Krystek (1985), accurate between 1000 and 15000 Kelvin.
+fn planckian_locus_uv(temperature: f64) -> (f64, f64) {
+    let t = temperature;
+    let u = (0.860117757 + 1.54118254e-4 * t + 1.28641212e-7 * t * t)
+        / (1.0 + 8.42420235e-4 * t + 7.08145163e-7 * t * t);
+    let v = (0.317398726 + 4.22806245e-5 * t + 4.20481691e-8 * t * t)
+        / (1.0 - 2.89741816e-5 * t + 1.61456053e-7 * t * t);
+
+    (u, v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn white_is_d65() {
+        let (x, y) = hue_saturation_to_xy(0, 0);
+
+        assert!((x - 0.3127).abs() < 0.001);
+        assert!((y - 0.3290).abs() < 0.001);
+    }
+
+    #[test]
+    fn xy_is_converted_to_hue_saturation() {
+        // The primaries of sRGB.
+        assert_eq!(xy_to_hue_saturation(0.64, 0.33), Some((360, 100)));
+        assert_eq!(xy_to_hue_saturation(0.30, 0.60), Some((120, 100)));
+        assert_eq!(xy_to_hue_saturation(0.15, 0.06), Some((240, 100)));
+        // D65 white.
+        assert_eq!(
+            xy_to_hue_saturation(0.3127, 0.3290).map(|(_, s)| s),
+            Some(1)
+        );
+
+        for (hue, saturation) in [(30, 80), (200, 50), (300, 100)] {
+            let (x, y) = hue_saturation_to_xy(hue, saturation);
+            assert_eq!(xy_to_hue_saturation(x, y), Some((hue, saturation)));
+        }
+
+        assert_eq!(xy_to_hue_saturation(0.3, 0.0), None);
+        assert_eq!(xy_to_hue_saturation(1.5, 0.3), None);
+    }
+
+    #[test]
+    fn colors_map_to_the_nearest_color_temperature() {
+        let temperature = nearest_color_temperature(0, 0, 2500..=6500);
+        assert!((6400..=6500).contains(&temperature));
+
+        assert_eq!(nearest_color_temperature(30, 100, 2500..=6500), 2500);
+        assert_eq!(nearest_color_temperature(240, 100, 2500..=6500), 6500);
+
+        let warm_white = nearest_color_temperature(30, 40, 2500..=6500);
+        assert!((2500..4000).contains(&warm_white));
+    }
+}
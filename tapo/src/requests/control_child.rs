@@ -2,7 +2,7 @@ use serde::Serialize;
 
 use crate::requests::tapo_request::TapoRequest;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub(crate) struct ControlChildParams {
     device_id: String,
     #[serde(rename = "requestData")]
@@ -2,7 +2,7 @@ use serde::Serialize;
 
 use crate::requests::EnergyDataInterval;
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub(crate) struct GetEnergyDataParams {
     start_timestamp: u64,
     end_timestamp: u64,
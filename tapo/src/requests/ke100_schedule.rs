@@ -0,0 +1,343 @@
+use std::fmt;
+
+use chrono::{NaiveTime, Timelike, Weekday};
+#[cfg(feature = "client")]
+use serde::Serialize;
+
+use crate::error::Error;
+#[cfg(feature = "client")]
+use crate::responses::ScheduleRuleResult;
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Time slot of a [`KE100WeeklySchedule`], during which the valve aims for `target_temperature`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KE100ScheduleSlot {
+    /// Start of the slot, with minute precision.
+    pub start: NaiveTime,
+    /// End of the slot, with minute precision. Midnight (`00:00`) means the end of the day.
+    pub end: NaiveTime,
+    /// The target temperature during the slot.
+    pub target_temperature: u8,
+}
+
+impl KE100ScheduleSlot {
+    fn start_minute(&self) -> u16 {
+        minute_of_day(self.start)
+    }
+
+    fn end_minute(&self) -> u16 {
+        match minute_of_day(self.end) {
+            0 => MINUTES_PER_DAY,
+            minute => minute,
+        }
+    }
+}
+
+impl fmt::Display for KE100ScheduleSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} ({})",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            self.target_temperature
+        )
+    }
+}
+
+/// Weekly heating schedule of a KE100 device: a list of time slots with a target temperature for each weekday.
+///
+/// # Example
+///
+/// ```rust
+/// # use chrono::{NaiveTime, Weekday};
+/// # use tapo::requests::KE100WeeklySchedule;
+/// let morning = NaiveTime::from_hms_opt(6, 0, 0).unwrap();
+/// let evening = NaiveTime::from_hms_opt(17, 0, 0).unwrap();
+/// let night = NaiveTime::from_hms_opt(22, 0, 0).unwrap();
+///
+/// let schedule = KE100WeeklySchedule::new()
+///     .slot(&[Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri], morning, night, 21)
+///     .slot(&[Weekday::Sat, Weekday::Sun], evening, night, 22);
+///
+/// assert!(schedule.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KE100WeeklySchedule {
+    days: [Vec<KE100ScheduleSlot>; 7],
+}
+
+impl KE100WeeklySchedule {
+    /// Creates an empty [`KE100WeeklySchedule`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a slot from `start` to `end` with the given `target_temperature` to each of the given `days`.
+    pub fn slot(
+        mut self,
+        days: &[Weekday],
+        start: NaiveTime,
+        end: NaiveTime,
+        target_temperature: u8,
+    ) -> Self {
+        for day in days {
+            self.days[day.num_days_from_monday() as usize].push(KE100ScheduleSlot {
+                start,
+                end,
+                target_temperature,
+            });
+        }
+        self
+    }
+
+    /// Returns the slots of the given `day`, ordered by their start time.
+    pub fn slots(&self, day: Weekday) -> Vec<KE100ScheduleSlot> {
+        let mut slots = self.days[day.num_days_from_monday() as usize].clone();
+        slots.sort_by_key(KE100ScheduleSlot::start_minute);
+        slots
+    }
+
+    /// Checks that every slot ends after it starts and that the slots of a day don't overlap.
+    pub fn validate(&self) -> Result<(), Error> {
+        for day in WEEKDAYS {
+            let slots = self.slots(day);
+
+            for slot in &slots {
+                if slot.start_minute() >= slot.end_minute() {
+                    return Err(Error::Validation {
+                        field: "slots".to_string(),
+                        message: format!("{day} {slot} must end after it starts"),
+                    });
+                }
+            }
+
+            for pair in slots.windows(2) {
+                if pair[0].end_minute() > pair[1].start_minute() {
+                    return Err(Error::Validation {
+                        field: "slots".to_string(),
+                        message: format!("{day} {} overlaps {day} {}", pair[0], pair[1]),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns one schedule rule for each distinct slot, covering all the days it's used on.
+    #[cfg(feature = "client")]
+    pub(crate) fn to_rules(&self) -> Vec<KE100ScheduleRuleParams> {
+        let mut rules: Vec<KE100ScheduleRuleParams> = Vec::new();
+
+        for day in WEEKDAYS {
+            for slot in self.slots(day) {
+                let day_bit = 1 << day.num_days_from_sunday();
+                let existing = rules.iter_mut().find(|rule| {
+                    rule.s_min == slot.start_minute()
+                        && rule.e_min == slot.end_minute()
+                        && rule.desired_states.target_temp == slot.target_temperature
+                });
+
+                match existing {
+                    Some(rule) => rule.week_day |= day_bit,
+                    None => rules.push(KE100ScheduleRuleParams {
+                        enable: true,
+                        mode: "repeat",
+                        week_day: day_bit,
+                        s_min: slot.start_minute(),
+                        e_min: slot.end_minute(),
+                        desired_states: KE100DesiredStates {
+                            target_temp: slot.target_temperature,
+                        },
+                    }),
+                }
+            }
+        }
+
+        rules
+    }
+
+    /// Adds the slot described by a schedule rule to all the days it's used on.
+    #[cfg(feature = "client")]
+    pub(crate) fn add_rule(
+        self,
+        week_day: u8,
+        s_min: u16,
+        e_min: u16,
+        target_temperature: u8,
+    ) -> Self {
+        let days: Vec<Weekday> = WEEKDAYS
+            .into_iter()
+            .filter(|day| week_day & (1 << day.num_days_from_sunday()) != 0)
+            .collect();
+
+        self.slot(
+            &days,
+            time_of_day(s_min),
+            time_of_day(e_min % MINUTES_PER_DAY),
+            target_temperature,
+        )
+    }
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct KE100ScheduleRuleParams {
+    enable: bool,
+    mode: &'static str,
+    /// Bit `n` is set when the rule applies to the `n`th day of the week, starting with Sunday.
+    week_day: u8,
+    s_min: u16,
+    e_min: u16,
+    desired_states: KE100DesiredStates,
+}
+
+#[cfg(feature = "client")]
+impl KE100ScheduleRuleParams {
+    /// Returns the parameters that add `rule` back as it was read from the device.
+    pub fn from_rule(rule: &ScheduleRuleResult) -> Self {
+        Self {
+            enable: rule.enable,
+            mode: "repeat",
+            week_day: rule.week_day,
+            s_min: rule.s_min,
+            e_min: rule.e_min,
+            desired_states: KE100DesiredStates {
+                target_temp: rule.desired_states.target_temp,
+            },
+        }
+    }
+
+    pub fn target_temperature(&self) -> u8 {
+        self.desired_states.target_temp
+    }
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct KE100DesiredStates {
+    target_temp: u8,
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct GetScheduleRulesParams {
+    start_index: u64,
+}
+
+#[cfg(feature = "client")]
+impl GetScheduleRulesParams {
+    pub fn new(start_index: u64) -> Self {
+        Self { start_index }
+    }
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct RemoveScheduleRulesParams {
+    remove_all: bool,
+}
+
+#[cfg(feature = "client")]
+impl RemoveScheduleRulesParams {
+    pub fn all() -> Self {
+        Self { remove_all: true }
+    }
+}
+
+fn minute_of_day(time: NaiveTime) -> u16 {
+    (time.hour() * 60 + time.minute()) as u16
+}
+
+#[cfg(feature = "client")]
+fn time_of_day(minute: u16) -> NaiveTime {
+    NaiveTime::from_hms_opt(minute as u32 / 60, minute as u32 % 60, 0).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn validate_rejects_overlapping_slots() {
+        let schedule = KE100WeeklySchedule::new()
+            .slot(&[Weekday::Mon], time(6, 0), time(9, 0), 21)
+            .slot(&[Weekday::Mon], time(8, 0), time(10, 0), 19);
+
+        assert!(matches!(
+            schedule.validate(),
+            Err(Error::Validation { message, .. }) if message == "Mon 06:00-09:00 (21) overlaps Mon 08:00-10:00 (19)"
+        ));
+    }
+
+    #[test]
+    fn validate_accepts_slot_ending_at_midnight() {
+        let schedule = KE100WeeklySchedule::new()
+            .slot(&[Weekday::Sun], time(6, 0), time(22, 0), 21)
+            .slot(&[Weekday::Sun], time(22, 0), time(0, 0), 17);
+
+        assert!(schedule.validate().is_ok());
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn rules_round_trip() {
+        let schedule = KE100WeeklySchedule::new()
+            .slot(&[Weekday::Mon, Weekday::Sun], time(6, 0), time(0, 0), 21)
+            .slot(&[Weekday::Tue], time(7, 30), time(9, 0), 20);
+
+        let rules = schedule.to_rules();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].week_day, 0b0000011);
+        assert_eq!(rules[0].e_min, MINUTES_PER_DAY);
+
+        let round_trip = rules
+            .iter()
+            .fold(KE100WeeklySchedule::new(), |schedule, rule| {
+                schedule.add_rule(
+                    rule.week_day,
+                    rule.s_min,
+                    rule.e_min,
+                    rule.desired_states.target_temp,
+                )
+            });
+        assert_eq!(round_trip, schedule);
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn rules_read_from_the_device_are_added_back_as_they_were() {
+        let rule = KE100WeeklySchedule::new()
+            .slot(&[Weekday::Wed], time(18, 0), time(23, 0), 22)
+            .to_rules()
+            .remove(0);
+
+        let mut json = serde_json::to_value(&rule).unwrap();
+        json["id"] = "S1".into();
+        json["enable"] = false.into();
+        let read_back: ScheduleRuleResult = serde_json::from_value(json).unwrap();
+
+        assert_eq!(
+            KE100ScheduleRuleParams::from_rule(&read_back),
+            KE100ScheduleRuleParams {
+                enable: false,
+                ..rule
+            }
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use chrono::NaiveTime;
+#[cfg(feature = "client")]
+use chrono::Timelike;
+#[cfg(feature = "client")]
+use serde_json::json;
+
+#[cfg(feature = "client")]
+use crate::error::Error;
+
+/// The hours during which the status LED is off, see [`crate::HubHandler::set_led_night_mode`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LedNightMode {
+    /// From sunset to sunrise at the location of the device.
+    SunriseSunset {
+        /// Minutes to shift sunrise by, negative for earlier.
+        sunrise_offset: i16,
+        /// Minutes to shift sunset by, negative for earlier.
+        sunset_offset: i16,
+    },
+    /// Between two times of the day. `end` can be earlier than `start` to span midnight.
+    Custom {
+        /// The time the LED turns off.
+        start: NaiveTime,
+        /// The time the LED turns back on.
+        end: NaiveTime,
+    },
+}
+
+impl LedNightMode {
+    #[cfg(feature = "client")]
+    pub(crate) fn to_params(&self) -> Result<serde_json::Value, Error> {
+        let minutes = |time: &NaiveTime| time.hour() * 60 + time.minute();
+
+        match self {
+            LedNightMode::SunriseSunset {
+                sunrise_offset,
+                sunset_offset,
+            } => {
+                if sunrise_offset.abs() > 720 || sunset_offset.abs() > 720 {
+                    return Err(Error::Validation {
+                        field: "offset".to_string(),
+                        message: "must be between -720 and 720 minutes".to_string(),
+                    });
+                }
+
+                Ok(json!({
+                    "night_mode_type": "sunrise_sunset",
+                    "sunrise_offset": sunrise_offset,
+                    "sunset_offset": sunset_offset,
+                }))
+            }
+            LedNightMode::Custom { start, end } => {
+                if minutes(start) == minutes(end) {
+                    return Err(Error::Validation {
+                        field: "end".to_string(),
+                        message: "must be different from the start".to_string(),
+                    });
+                }
+
+                Ok(json!({
+                    "night_mode_type": "custom",
+                    "start_time": minutes(start),
+                    "end_time": minutes(end),
+                }))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_night_mode_is_sent_in_minutes() {
+        let night_mode = LedNightMode::Custom {
+            start: NaiveTime::from_hms_opt(22, 30, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+        };
+
+        assert_eq!(
+            night_mode.to_params().unwrap(),
+            json!({ "night_mode_type": "custom", "start_time": 1350, "end_time": 420 })
+        );
+        assert!(LedNightMode::Custom {
+            start: NaiveTime::MIN,
+            end: NaiveTime::MIN,
+        }
+        .to_params()
+        .is_err());
+    }
+}
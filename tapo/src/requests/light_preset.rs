@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::requests::{must_be_within, Brightness, Hue, Kelvin, Saturation};
+
+/// A preset stored on a light, i.e. one of the states that the Tapo app offers as a shortcut
+/// and that some devices cycle through when their physical switch is double-tapped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct LightPreset {
+    /// Brightness between 1 and 100.
+    pub brightness: u8,
+    /// Hue between 0 and 360, for color presets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hue: Option<u16>,
+    /// Saturation between 1 and 100, for color presets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saturation: Option<u8>,
+    /// Color temperature in Kelvin between 2500 and 6500, for white presets. 0 for color presets.
+    #[serde(
+        default,
+        rename = "color_temp",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub color_temperature: Option<u16>,
+}
+
+impl LightPreset {
+    /// Returns a preset that only sets the *brightness*.
+    ///
+    /// # Arguments
+    ///
+    /// * `brightness` - between 1 and 100
+    pub fn brightness(brightness: u8) -> Self {
+        Self {
+            brightness,
+            hue: None,
+            saturation: None,
+            color_temperature: None,
+        }
+    }
+
+    /// Returns a preset that sets the *brightness*, *hue* and *saturation*.
+    ///
+    /// # Arguments
+    ///
+    /// * `brightness` - between 1 and 100
+    /// * `hue` - between 0 and 360
+    /// * `saturation` - between 1 and 100
+    pub fn hue_saturation(brightness: u8, hue: u16, saturation: u8) -> Self {
+        Self {
+            brightness,
+            hue: Some(hue),
+            saturation: Some(saturation),
+            color_temperature: Some(0),
+        }
+    }
+
+    /// Returns a preset that sets the *brightness* and *color temperature*.
+    ///
+    /// # Arguments
+    ///
+    /// * `brightness` - between 1 and 100
+    /// * `color_temperature` - between 2500 and 6500
+    pub fn color_temperature(brightness: u8, color_temperature: u16) -> Self {
+        Self {
+            brightness,
+            hue: Some(0),
+            saturation: Some(100),
+            color_temperature: Some(color_temperature),
+        }
+    }
+
+    /// Returns `true` if the preset sets the *hue* and *saturation*.
+    pub fn is_color(&self) -> bool {
+        self.color_temperature.unwrap_or_default() == 0
+            && (self.hue.is_some() || self.saturation.is_some())
+    }
+
+    /// Returns [`Error::Validation`] if a value is out of range.
+    /// Called by the handlers before storing the preset,
+    /// which also check the *color temperature* against the range of the device.
+    pub fn validate(&self) -> Result<(), Error> {
+        if !Brightness::RANGE.contains(&self.brightness) {
+            return Err(Error::Validation {
+                field: "brightness".to_string(),
+                message: must_be_within(&Brightness::RANGE),
+            });
+        }
+
+        if self.is_color() {
+            // The hue 0 is accepted, since the white presets read from the devices store it.
+            let hue_range = 0..=*Hue::RANGE.end();
+            if !hue_range.contains(&self.hue.unwrap_or_default()) {
+                return Err(Error::Validation {
+                    field: "hue".to_string(),
+                    message: must_be_within(&hue_range),
+                });
+            }
+
+            if !Saturation::RANGE.contains(&self.saturation.unwrap_or_default()) {
+                return Err(Error::Validation {
+                    field: "saturation".to_string(),
+                    message: must_be_within(&Saturation::RANGE),
+                });
+            }
+        } else if let Some(color_temperature) = self.color_temperature {
+            if !Kelvin::RANGE.contains(&color_temperature) {
+                return Err(Error::Validation {
+                    field: "color_temperature".to_string(),
+                    message: must_be_within(&Kelvin::RANGE),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "client")]
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct EditPresetRuleParams {
+    index: usize,
+    state: LightPreset,
+}
+
+#[cfg(feature = "client")]
+impl EditPresetRuleParams {
+    pub fn new(index: usize, state: LightPreset) -> Self {
+        Self { index, state }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preset_validation() {
+        assert!(LightPreset::brightness(50).validate().is_ok());
+        assert!(LightPreset::hue_saturation(50, 0, 100).validate().is_ok());
+        assert!(LightPreset::color_temperature(50, 2700).validate().is_ok());
+
+        assert!(matches!(
+            LightPreset::brightness(0).validate(),
+            Err(Error::Validation { field, .. }) if field == "brightness"
+        ));
+        assert!(matches!(
+            LightPreset::hue_saturation(50, 361, 100).validate(),
+            Err(Error::Validation { field, .. }) if field == "hue"
+        ));
+        assert!(matches!(
+            LightPreset::color_temperature(50, 1000).validate(),
+            Err(Error::Validation { field, .. }) if field == "color_temperature"
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// The *brightness* of a light, in percent, validated when it's built with [`Brightness::new`] or `TryFrom<u8>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct Brightness(u8);
+
+impl Brightness {
+    /// The values accepted by the devices.
+    pub const RANGE: RangeInclusive<u8> = 1..=100;
+
+    /// Returns a [`Brightness`], or `None` if `value` is outside of [`Brightness::RANGE`].
+    pub fn new(value: u8) -> Option<Self> {
+        Self::RANGE.contains(&value).then_some(Self(value))
+    }
+
+    /// Returns the value, in percent.
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+/// The *hue* of a color light, in degrees, validated when it's built with [`Hue::new`] or `TryFrom<u16>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub struct Hue(u16);
+
+impl Hue {
+    /// The values accepted by the devices. The hue 0 is only stored in white presets, see [`crate::requests::LightPreset`].
+    pub const RANGE: RangeInclusive<u16> = 1..=360;
+
+    /// Returns a [`Hue`], or `None` if `value` is outside of [`Hue::RANGE`].
+    pub fn new(value: u16) -> Option<Self> {
+        Self::RANGE.contains(&value).then_some(Self(value))
+    }
+
+    /// Returns the value, in degrees.
+    pub fn get(self) -> u16 {
+        self.0
+    }
+}
+
+/// The *saturation* of a color light, in percent, validated when it's built with [`Saturation::new`] or `TryFrom<u8>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct Saturation(u8);
+
+impl Saturation {
+    /// The values accepted by the devices.
+    pub const RANGE: RangeInclusive<u8> = 1..=100;
+
+    /// Returns a [`Saturation`], or `None` if `value` is outside of [`Saturation::RANGE`].
+    pub fn new(value: u8) -> Option<Self> {
+        Self::RANGE.contains(&value).then_some(Self(value))
+    }
+
+    /// Returns the value, in percent.
+    pub fn get(self) -> u8 {
+        self.0
+    }
+}
+
+/// The *color temperature* of a white light, in Kelvin, validated when it's built with [`Kelvin::new`] or `TryFrom<u16>`.
+///
+/// The devices that report a narrower range still check it when the request is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u16", into = "u16")]
+pub struct Kelvin(u16);
+
+impl Kelvin {
+    /// The values accepted by the color lights that don't report their own range,
+    /// see [`crate::ColorLightHandler::get_color_temperature_range`].
+    pub const RANGE: RangeInclusive<u16> = 2500..=6500;
+
+    /// Returns a [`Kelvin`], or `None` if `value` is outside of [`Kelvin::RANGE`].
+    pub fn new(value: u16) -> Option<Self> {
+        Self::RANGE.contains(&value).then_some(Self(value))
+    }
+
+    /// Returns the value, in Kelvin.
+    pub fn get(self) -> u16 {
+        self.0
+    }
+}
+
+/// Implemented by the newtypes of the properties of the lights, e.g. [`Brightness`].
+pub trait LightProperty: Copy {
+    /// The type of the value sent to the devices.
+    type Raw;
+}
+
+/// Implemented by the values that can be passed where a [`LightProperty`] is expected:
+/// the property itself, already validated, or its raw value, validated when the request is sent.
+pub trait IntoLightProperty<P: LightProperty> {
+    /// Returns the raw value.
+    fn into_raw(self) -> P::Raw;
+}
+
+macro_rules! impl_light_property {
+    ($($property:ident($value:ty, $field:literal)),+) => {
+        $(
+            impl TryFrom<$value> for $property {
+                type Error = Error;
+
+                /// Returns [`Error::Validation`] if `value` is outside of the range of the property.
+                fn try_from(value: $value) -> Result<Self, Self::Error> {
+                    Self::new(value).ok_or_else(|| Error::Validation {
+                        field: $field.to_string(),
+                        message: must_be_within(&Self::RANGE),
+                    })
+                }
+            }
+
+            impl From<$property> for $value {
+                fn from(property: $property) -> Self {
+                    property.get()
+                }
+            }
+
+            impl LightProperty for $property {
+                type Raw = $value;
+            }
+
+            impl IntoLightProperty<$property> for $property {
+                fn into_raw(self) -> $value {
+                    self.get()
+                }
+            }
+
+            impl IntoLightProperty<$property> for $value {
+                fn into_raw(self) -> $value {
+                    self
+                }
+            }
+        )+
+    };
+}
+
+impl_light_property!(
+    Brightness(u8, "brightness"),
+    Hue(u16, "hue"),
+    Saturation(u8, "saturation"),
+    Kelvin(u16, "color_temperature")
+);
+
+/// Returns the *brightness* `delta` percentage points away from `current`, clamped to [`Brightness::RANGE`].
+#[cfg(feature = "client")]
+pub(crate) fn adjusted_brightness(current: u8, delta: i8) -> u8 {
+    current
+        .saturating_add_signed(delta)
+        .clamp(*Brightness::RANGE.start(), *Brightness::RANGE.end())
+}
+
+/// Returns the *color temperature* `delta` Kelvin away from `current`, clamped to `range`.
+#[cfg(feature = "client")]
+pub(crate) fn stepped_color_temperature(
+    current: u16,
+    delta: i16,
+    range: &RangeInclusive<u16>,
+) -> u16 {
+    current
+        .saturating_add_signed(delta)
+        .clamp(*range.start(), *range.end())
+}
+
+/// Returns the message of the [`crate::Error::Validation`] for a value outside of `range`.
+pub(crate) fn must_be_within<T: Display>(range: &RangeInclusive<T>) -> String {
+    format!("must be between {} and {}", range.start(), range.end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_checked_against_the_ranges() {
+        assert_eq!(Brightness::new(1).map(Brightness::get), Some(1));
+        assert_eq!(Brightness::new(100).map(Brightness::get), Some(100));
+        assert_eq!(Brightness::new(0), None);
+        assert_eq!(Brightness::new(101), None);
+        assert_eq!(Hue::new(0), None);
+        assert_eq!(Hue::new(360).map(Hue::get), Some(360));
+        assert_eq!(Saturation::new(0), None);
+        assert_eq!(Kelvin::new(2499), None);
+        assert_eq!(Kelvin::new(6500).map(Kelvin::get), Some(6500));
+        assert_eq!(
+            must_be_within(&Kelvin::RANGE),
+            "must be between 2500 and 6500"
+        );
+
+        assert_eq!(u8::from(Brightness::try_from(30).unwrap()), 30);
+        assert!(matches!(
+            Hue::try_from(361),
+            Err(Error::Validation { field, message }) if field == "hue" && message == "must be between 1 and 360"
+        ));
+        assert!(serde_json::from_value::<Saturation>(serde_json::json!(0)).is_err());
+        assert_eq!(
+            serde_json::to_value(Kelvin::try_from(2700).unwrap()).unwrap(),
+            serde_json::json!(2700)
+        );
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn adjustments_are_clamped_to_the_ranges() {
+        assert_eq!(adjusted_brightness(50, -10), 40);
+        assert_eq!(adjusted_brightness(95, 10), 100);
+        assert_eq!(adjusted_brightness(5, -10), 1);
+        assert_eq!(stepped_color_temperature(2700, -500, &Kelvin::RANGE), 2500);
+        assert_eq!(stepped_color_temperature(4000, 500, &(2700..=6500)), 4500);
+    }
+}
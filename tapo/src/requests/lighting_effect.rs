@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, BoolFromInt};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 #[allow(missing_docs)]
 pub enum LightingEffectType {
@@ -12,17 +13,20 @@ pub enum LightingEffectType {
 }
 
 #[serde_as]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct LightingEffect {
     // Mandatory
     pub brightness: u8,
     #[serde_as(as = "BoolFromInt")]
+    #[cfg_attr(feature = "schemars", schemars(with = "u8"))]
     #[serde(rename = "custom")]
     pub is_custom: bool,
     /// The colors that will be displayed in the Tapo app.
     pub display_colors: Vec<[u16; 3]>,
     #[serde_as(as = "BoolFromInt")]
+    #[cfg_attr(feature = "schemars", schemars(with = "u8"))]
     #[serde(rename = "enable")]
     pub enabled: bool,
     pub id: String,
@@ -264,6 +268,7 @@ impl LightingEffect {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[non_exhaustive]
 #[allow(missing_docs)]
 pub enum LightingEffectPreset {
@@ -2,7 +2,7 @@ use serde::Serialize;
 
 use crate::requests::TapoRequest;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub(crate) struct MultipleRequestParams {
     requests: Vec<TapoRequest>,
 }
@@ -0,0 +1,20 @@
+use serde::Serialize;
+
+use crate::requests::Ringtone;
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct PlayAlarmParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alarm_type: Option<Ringtone>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alarm_duration: Option<u64>,
+}
+
+impl PlayAlarmParams {
+    pub fn new(ringtone: Option<Ringtone>, duration: Option<u64>) -> Self {
+        Self {
+            alarm_type: ringtone,
+            alarm_duration: duration,
+        }
+    }
+}
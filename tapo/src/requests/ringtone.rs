@@ -0,0 +1,141 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A ringtone of the hub, played by its alarm, see [`crate::HubHandler::get_supported_ringtones`].
+///
+/// The firmware identifies ringtones by name. The names known by this crate are mapped to their own variants,
+/// numeric identifiers, which some firmware versions use for additional ringtones, to [`Ringtone::Id`],
+/// and anything else to [`Ringtone::Other`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Ringtone {
+    /// `Doorbell Ring 1` to `Doorbell Ring 10`.
+    DoorbellRing(u8),
+    /// `Phone Ring`.
+    PhoneRing,
+    /// `Alarm 1` to `Alarm 5`.
+    Alarm(u8),
+    /// `Dripping Tap`.
+    DrippingTap,
+    /// `Connection 1` and `Connection 2`.
+    Connection(u8),
+    /// A ringtone identified by number.
+    Id(u32),
+    /// A ringtone that isn't known by this crate, identified by its name.
+    Other(String),
+}
+
+impl fmt::Display for Ringtone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ringtone::DoorbellRing(number) => write!(f, "Doorbell Ring {number}"),
+            Ringtone::PhoneRing => write!(f, "Phone Ring"),
+            Ringtone::Alarm(number) => write!(f, "Alarm {number}"),
+            Ringtone::DrippingTap => write!(f, "Dripping Tap"),
+            Ringtone::Connection(number) => write!(f, "Connection {number}"),
+            Ringtone::Id(id) => write!(f, "{id}"),
+            Ringtone::Other(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl FromStr for Ringtone {
+    type Err = std::convert::Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let numbered = |prefix: &str| {
+            value
+                .strip_prefix(prefix)
+                .and_then(|number| number.parse::<u8>().ok())
+        };
+
+        let ringtone = if let Some(number) = numbered("Doorbell Ring ") {
+            Ringtone::DoorbellRing(number)
+        } else if let Some(number) = numbered("Alarm ") {
+            Ringtone::Alarm(number)
+        } else if let Some(number) = numbered("Connection ") {
+            Ringtone::Connection(number)
+        } else if value == "Phone Ring" {
+            Ringtone::PhoneRing
+        } else if value == "Dripping Tap" {
+            Ringtone::DrippingTap
+        } else if let Ok(id) = value.parse() {
+            Ringtone::Id(id)
+        } else {
+            Ringtone::Other(value.to_string())
+        };
+
+        Ok(ringtone)
+    }
+}
+
+impl Serialize for Ringtone {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ringtone {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawRingtone {
+            Name(String),
+            Id(u32),
+        }
+
+        Ok(match RawRingtone::deserialize(deserializer)? {
+            RawRingtone::Name(name) => name.parse().unwrap_or(Ringtone::Other(name)),
+            RawRingtone::Id(id) => Ringtone::Id(id),
+        })
+    }
+}
+
+/// Ringtones are serialized as their names, see [`Ringtone`].
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for Ringtone {
+    fn schema_name() -> String {
+        "Ringtone".to_string()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        String::json_schema(gen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_names_and_ids_are_parsed() {
+        let ringtones: Vec<Ringtone> = serde_json::from_str(
+            r#"["Doorbell Ring 3", "Alarm 1", "Dripping Tap", "Connection 2", "17", 18, "Siren"]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            ringtones,
+            vec![
+                Ringtone::DoorbellRing(3),
+                Ringtone::Alarm(1),
+                Ringtone::DrippingTap,
+                Ringtone::Connection(2),
+                Ringtone::Id(17),
+                Ringtone::Id(18),
+                Ringtone::Other("Siren".to_string()),
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(Ringtone::DoorbellRing(3)).unwrap(),
+            "Doorbell Ring 3"
+        );
+    }
+}
@@ -1,11 +1,19 @@
+use std::ops::RangeInclusive;
+
 use serde::Serialize;
 
 use crate::api::ApiClientExt;
 use crate::error::Error;
 use crate::requests::color::{Color, COLOR_MAP};
+use crate::requests::color_space::xy_to_hue_saturation;
+use crate::requests::{must_be_within, Brightness, Hue, IntoLightProperty, Kelvin, Saturation};
+
+/// The *color temperatures* in Kelvin accepted by the color lights that don't report their range.
+pub(crate) const DEFAULT_COLOR_TEMPERATURE_RANGE: RangeInclusive<u16> = Kelvin::RANGE;
 
 /// Builder that is used by the [`crate::ColorLightHandler::set`] API to set multiple properties in a single request.
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ColorLightSetDeviceInfoParams<'a> {
     #[serde(skip)]
     client: &'a dyn ApiClientExt,
@@ -20,6 +28,9 @@ pub struct ColorLightSetDeviceInfoParams<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "color_temp")]
     color_temperature: Option<u16>,
+    /// The coordinates set with [`ColorLightSetDeviceInfoParams::xy`], kept for the validation.
+    #[serde(skip)]
+    xy: Option<(f64, f64)>,
 }
 
 impl<'a> ColorLightSetDeviceInfoParams<'a> {
@@ -40,9 +51,9 @@ impl<'a> ColorLightSetDeviceInfoParams<'a> {
     ///
     /// # Arguments
     ///
-    /// * `brightness` - between 1 and 100
-    pub fn brightness(mut self, value: u8) -> Self {
-        self.brightness = Some(value);
+    /// * `brightness` - between 1 and 100, see [`Brightness::RANGE`]
+    pub fn brightness(mut self, value: impl IntoLightProperty<Brightness>) -> Self {
+        self.brightness = Some(value.into_raw());
         self
     }
 
@@ -60,6 +71,7 @@ impl<'a> ColorLightSetDeviceInfoParams<'a> {
         self.hue = hue;
         self.saturation = saturation;
         self.color_temperature = color_temperature;
+        self.xy = None;
 
         self
     }
@@ -69,12 +81,40 @@ impl<'a> ColorLightSetDeviceInfoParams<'a> {
     ///
     /// # Arguments
     ///
-    /// * `hue` - between 1 and 360
-    /// * `saturation` - between 1 and 100
-    pub fn hue_saturation(mut self, hue: u16, saturation: u8) -> Self {
-        self.hue = Some(hue);
-        self.saturation = Some(saturation);
+    /// * `hue` - between 1 and 360, see [`Hue::RANGE`]
+    /// * `saturation` - between 1 and 100, see [`Saturation::RANGE`]
+    pub fn hue_saturation(
+        mut self,
+        hue: impl IntoLightProperty<Hue>,
+        saturation: impl IntoLightProperty<Saturation>,
+    ) -> Self {
+        self.hue = Some(hue.into_raw());
+        self.saturation = Some(saturation.into_raw());
         self.color_temperature = Some(0);
+        self.xy = None;
+
+        self
+    }
+
+    /// Sets the *color* from its [CIE 1931](https://en.wikipedia.org/wiki/CIE_1931_color_space) chromaticity coordinates,
+    /// as used by Hue bridges and Matter controllers, converted to the nearest *hue* and *saturation*.
+    /// [`ColorLightSetDeviceInfoParams::send`] must be called at the end to apply the changes.
+    /// The device will also be turned *on*, unless [`ColorLightSetDeviceInfoParams::off`] is called.
+    ///
+    /// The coordinates don't carry the brightness, which can be set with [`ColorLightSetDeviceInfoParams::brightness`].
+    /// The colors outside of the gamut of the device are mapped to the nearest one that it can show.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - between 0 and 1
+    /// * `y` - between 0 and 1, greater than 0
+    pub fn xy(mut self, x: f64, y: f64) -> Self {
+        if let Some((hue, saturation)) = xy_to_hue_saturation(x, y) {
+            self.hue = Some(hue);
+            self.saturation = Some(saturation);
+            self.color_temperature = Some(0);
+        }
+        self.xy = Some((x, y));
 
         self
     }
@@ -84,18 +124,20 @@ impl<'a> ColorLightSetDeviceInfoParams<'a> {
     ///
     /// # Arguments
     ///
-    /// * `color_temperature` - between 2500 and 6500
-    pub fn color_temperature(mut self, value: u16) -> Self {
+    /// * `color_temperature` - within the range of the device, usually between 2500 and 6500,
+    ///   see [`crate::ColorLightHandler::get_color_temperature_range`]
+    pub fn color_temperature(mut self, value: impl IntoLightProperty<Kelvin>) -> Self {
         self.hue = Some(0);
         self.saturation = Some(100);
-        self.color_temperature = Some(value);
+        self.color_temperature = Some(value.into_raw());
+        self.xy = None;
 
         self
     }
 
     /// Performs a request to apply the changes to the device.
     pub async fn send(self) -> Result<(), Error> {
-        self.validate()?;
+        self.validate().await?;
         let json = serde_json::to_value(&self)?;
         self.client.set_device_info(json).await
     }
@@ -110,10 +152,20 @@ impl<'a> ColorLightSetDeviceInfoParams<'a> {
             hue: None,
             saturation: None,
             color_temperature: None,
+            xy: None,
         }
     }
 
-    fn validate(&self) -> Result<(), Error> {
+    async fn validate(&self) -> Result<(), Error> {
+        if let Some((x, y)) = self.xy {
+            if xy_to_hue_saturation(x, y).is_none() {
+                return Err(Error::Validation {
+                    field: "xy".to_string(),
+                    message: "x and y must be between 0 and 1, and y greater than 0".to_string(),
+                });
+            }
+        }
+
         if self.device_on.is_none()
             && self.brightness.is_none()
             && self.hue.is_none()
@@ -127,41 +179,41 @@ impl<'a> ColorLightSetDeviceInfoParams<'a> {
         }
 
         if let Some(brightness) = self.brightness {
-            if !(1..=100).contains(&brightness) {
+            if !Brightness::RANGE.contains(&brightness) {
                 return Err(Error::Validation {
                     field: "brightness".to_string(),
-                    message: "must be between 1 and 100".to_string(),
+                    message: must_be_within(&Brightness::RANGE),
                 });
             }
         }
 
         if let Some(hue) = self.hue {
-            if self.color_temperature.unwrap_or_default() == 0 && !(1..=360).contains(&hue) {
+            if self.color_temperature.unwrap_or_default() == 0 && !Hue::RANGE.contains(&hue) {
                 return Err(Error::Validation {
                     field: "hue".to_string(),
-                    message: "must be between 1 and 360".to_string(),
+                    message: must_be_within(&Hue::RANGE),
                 });
             }
         }
 
         if let Some(saturation) = self.saturation {
-            if !(1..=100).contains(&saturation) {
+            if !Saturation::RANGE.contains(&saturation) {
                 return Err(Error::Validation {
                     field: "saturation".to_string(),
-                    message: "must be between 1 and 100".to_string(),
+                    message: must_be_within(&Saturation::RANGE),
                 });
             }
         }
 
         if let Some(color_temperature) = self.color_temperature {
-            if self.hue.unwrap_or_default() == 0
-                && self.saturation.unwrap_or(100) == 100
-                && !(2500..=6500).contains(&color_temperature)
-            {
-                return Err(Error::Validation {
-                    field: "color_temperature".to_string(),
-                    message: "must be between 2500 and 6500".to_string(),
-                });
+            if self.hue.unwrap_or_default() == 0 && self.saturation.unwrap_or(100) == 100 {
+                let range = self.client.color_temperature_range().await?;
+                if !range.contains(&color_temperature) {
+                    return Err(Error::Validation {
+                        field: "color_temperature".to_string(),
+                        message: must_be_within(&range),
+                    });
+                }
             }
         }
 
@@ -213,6 +265,50 @@ mod tests {
         assert!(params.send().await.is_ok())
     }
 
+    #[tokio::test]
+    async fn validated_properties_are_accepted() {
+        let params = ColorLightSetDeviceInfoParams::new(&MockApiClient)
+            .brightness(Brightness::try_from(30).unwrap())
+            .hue_saturation(
+                Hue::try_from(120).unwrap(),
+                Saturation::try_from(80).unwrap(),
+            );
+
+        assert_eq!(params.brightness, Some(30));
+        assert_eq!(params.hue, Some(120));
+        assert_eq!(params.saturation, Some(80));
+
+        assert!(params.send().await.is_ok())
+    }
+
+    #[tokio::test]
+    async fn xy_is_converted_to_hue_saturation() {
+        let params = ColorLightSetDeviceInfoParams::new(&MockApiClient);
+
+        let params = params.color_temperature(3000);
+        let params = params.xy(0.15, 0.06);
+
+        assert_eq!(params.hue, Some(240));
+        assert_eq!(params.saturation, Some(100));
+        assert_eq!(params.color_temperature, Some(0));
+
+        assert!(params.send().await.is_ok())
+    }
+
+    #[tokio::test]
+    async fn xy_validation() {
+        let params = ColorLightSetDeviceInfoParams::new(&MockApiClient);
+        let result = params.xy(0.3, 0.0).send().await;
+        assert!(matches!(
+            result.err(),
+            Some(Error::Validation { field, .. }) if field == "xy"
+        ));
+
+        let params = ColorLightSetDeviceInfoParams::new(&MockApiClient);
+        let result = params.xy(0.3, 0.0).hue_saturation(50, 50).send().await;
+        assert!(result.is_ok());
+    }
+
     #[tokio::test]
     async fn no_property_validation() {
         let params = ColorLightSetDeviceInfoParams::new(&MockApiClient);
@@ -291,4 +387,31 @@ mod tests {
             Some(Error::Validation { field, message }) if field == "color_temperature" && message == "must be between 2500 and 6500"
         ));
     }
+
+    #[tokio::test]
+    async fn color_temperature_validation_uses_the_range_of_the_device() {
+        #[derive(Debug)]
+        struct NarrowRangeApiClient;
+
+        #[async_trait]
+        impl ApiClientExt for NarrowRangeApiClient {
+            async fn set_device_info(&self, _: serde_json::Value) -> Result<(), Error> {
+                Ok(())
+            }
+
+            async fn color_temperature_range(&self) -> Result<RangeInclusive<u16>, Error> {
+                Ok(2700..=6500)
+            }
+        }
+
+        let params = ColorLightSetDeviceInfoParams::new(&NarrowRangeApiClient);
+        let result = params.color_temperature(2500).send().await;
+        assert!(matches!(
+            result.err(),
+            Some(Error::Validation { field, message }) if field == "color_temperature" && message == "must be between 2700 and 6500"
+        ));
+
+        let params = ColorLightSetDeviceInfoParams::new(&NarrowRangeApiClient);
+        assert!(params.color_temperature(2700).send().await.is_ok());
+    }
 }
@@ -2,6 +2,7 @@ use serde::Serialize;
 
 use crate::api::ApiClientExt;
 use crate::error::Error;
+use crate::requests::{must_be_within, Brightness};
 
 /// Builder that is used by the [`crate::LightHandler::set`] API to set multiple properties in a single request.
 #[derive(Debug, Serialize)]
@@ -32,7 +33,7 @@ impl<'a> LightSetDeviceInfoParams<'a> {
     ///
     /// # Arguments
     ///
-    /// * `brightness` - between 1 and 100
+    /// * `brightness` - between 1 and 100, see [`crate::requests::Brightness::RANGE`]
     pub fn brightness(mut self, value: u8) -> Self {
         self.brightness = Some(value);
         self
@@ -64,10 +65,10 @@ impl<'a> LightSetDeviceInfoParams<'a> {
         }
 
         if let Some(brightness) = self.brightness {
-            if !(1..=100).contains(&brightness) {
+            if !Brightness::RANGE.contains(&brightness) {
                 return Err(Error::Validation {
                     field: "brightness".to_string(),
-                    message: "must be between 1 and 100".to_string(),
+                    message: must_be_within(&Brightness::RANGE),
                 });
             }
         }
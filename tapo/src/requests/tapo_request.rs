@@ -3,11 +3,13 @@ use std::time::{SystemTime, UNIX_EPOCH};
 use serde::Serialize;
 
 use crate::requests::{
-    ControlChildParams, GetEnergyDataParams, GetTriggerLogsParams, HandshakeParams, LightingEffect,
-    LoginDeviceParams, MultipleRequestParams, SecurePassthroughParams,
+    AutomationRule, ControlChildParams, EditPresetRuleParams, GetAutomationListParams,
+    GetEnergyDataParams, GetScheduleRulesParams, GetTriggerLogsParams, HandshakeParams,
+    KE100ScheduleRuleParams, LightingEffect, LoginDeviceParams, MultipleRequestParams,
+    PlayAlarmParams, RemoveScheduleRulesParams, SecurePassthroughParams, SetChildProtectionParams,
 };
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "method")]
 pub(crate) enum TapoRequest {
@@ -21,24 +23,87 @@ pub(crate) enum TapoRequest {
     SetLightingEffect(Box<TapoParams<LightingEffect>>),
     GetDeviceInfo(TapoParams<EmptyParams>),
     GetDeviceUsage(TapoParams<EmptyParams>),
+    ResetDeviceUsage(TapoParams<EmptyParams>),
     GetEnergyUsage(TapoParams<EmptyParams>),
     GetEnergyData(TapoParams<GetEnergyDataParams>),
     GetCurrentPower(TapoParams<EmptyParams>),
+    GetEmeterData(TapoParams<EmptyParams>),
+    GetChildProtection(TapoParams<EmptyParams>),
+    SetChildProtection(TapoParams<SetChildProtectionParams>),
+    GetPresetRules(TapoParams<EmptyParams>),
+    EditPresetRules(Box<TapoParams<EditPresetRuleParams>>),
+    GetLedInfo(TapoParams<EmptyParams>),
+    SetLedInfo(Box<TapoParams<serde_json::Value>>),
+    GetAlarmConfigure(TapoParams<EmptyParams>),
+    SetAlarmConfigure(Box<TapoParams<serde_json::Value>>),
+    GetSupportAlarmTypeList(TapoParams<EmptyParams>),
+    PlayAlarm(Box<TapoParams<PlayAlarmParams>>),
+    StopAlarm(TapoParams<EmptyParams>),
     GetChildDeviceList(TapoParams<EmptyParams>),
     GetChildDeviceComponentList(TapoParams<EmptyParams>),
     ControlChild(Box<TapoParams<ControlChildParams>>),
+    GetAutomationList(TapoParams<GetAutomationListParams>),
+    AddAutomation(Box<TapoParams<AutomationRule>>),
+    GetLatestFirmware(TapoParams<EmptyParams>),
+    FwDownload(TapoParams<EmptyParams>),
+    GetFwDownloadState(TapoParams<EmptyParams>),
     // Child requests
     #[serde(rename = "multipleRequest")]
     MultipleRequest(Box<TapoParams<MultipleRequestParams>>),
     GetTriggerLogs(Box<TapoParams<GetTriggerLogsParams>>),
     #[serde(rename = "get_temp_humidity_records")]
     GetTemperatureHumidityRecords(Box<TapoParams<EmptyParams>>),
+    GetScheduleRules(TapoParams<GetScheduleRulesParams>),
+    AddScheduleRule(Box<TapoParams<KE100ScheduleRuleParams>>),
+    RemoveScheduleRules(TapoParams<RemoveScheduleRulesParams>),
+    // Methods that aren't modeled by this crate
+    #[serde(untagged)]
+    Raw(Box<RawRequest>),
 }
 
-#[derive(Debug, Serialize)]
+impl TapoRequest {
+    /// Whether the request only reads the state of the device, which is the case for the `get_` methods,
+    /// and for the child and multiple requests that only contain `get_` methods.
+    pub fn is_read_only(&self) -> bool {
+        serde_json::to_value(self)
+            .map(|request| is_read_only(&request))
+            .unwrap_or_default()
+    }
+}
+
+fn is_read_only(request: &serde_json::Value) -> bool {
+    let params = &request["params"];
+
+    match request["method"].as_str().unwrap_or_default() {
+        "component_nego" => true,
+        "control_child" => is_read_only(&params["requestData"]),
+        "multipleRequest" => params["requests"]
+            .as_array()
+            .is_some_and(|requests| requests.iter().all(is_read_only)),
+        method => method.starts_with("get_"),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct RawRequest {
+    method: String,
+    #[serde(flatten)]
+    params: TapoParams<serde_json::Value>,
+}
+
+impl RawRequest {
+    pub fn new(method: impl Into<String>, params: TapoParams<serde_json::Value>) -> Self {
+        Self {
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub(crate) struct EmptyParams;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct TapoParams<T> {
     params: T,
@@ -68,3 +133,43 @@ impl<T> TapoParams<T> {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_request_serializes_method_and_params() {
+        let params = TapoParams::new(serde_json::json!({ "start_index": 0 }));
+        let request = TapoRequest::Raw(Box::new(RawRequest::new("get_schedule_rules", params)));
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            serde_json::json!({ "method": "get_schedule_rules", "params": { "start_index": 0 } })
+        );
+    }
+
+    #[test]
+    fn read_only_requests_are_detected() {
+        let child_request = |request| {
+            let params = MultipleRequestParams::new(vec![request]);
+            let request = TapoRequest::MultipleRequest(Box::new(TapoParams::new(params)));
+            let params = ControlChildParams::new("child".to_string(), request);
+            TapoRequest::ControlChild(Box::new(TapoParams::new(params)))
+        };
+
+        assert!(TapoRequest::GetDeviceInfo(TapoParams::new(EmptyParams)).is_read_only());
+        assert!(
+            child_request(TapoRequest::GetDeviceInfo(TapoParams::new(EmptyParams))).is_read_only()
+        );
+
+        let set_device_info = || {
+            TapoRequest::SetDeviceInfo(Box::new(TapoParams::new(
+                serde_json::json!({ "device_on": true }),
+            )))
+        };
+        assert!(!set_device_info().is_read_only());
+        assert!(!child_request(set_device_info()).is_read_only());
+        assert!(!TapoRequest::FwDownload(TapoParams::new(EmptyParams)).is_read_only());
+    }
+}
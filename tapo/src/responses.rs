@@ -1,32 +1,95 @@
 //! Tapo response objects.
 
+mod alarm_configuration_result;
+mod automation_list_result;
+mod battery_status;
 mod child_device_list_result;
+#[cfg(feature = "client")]
+mod child_protection_result;
+mod component_list_result;
+#[cfg(feature = "client")]
 mod control_child_result;
 mod current_power_result;
 mod decodable_result_ext;
+mod deserialization_mode;
 mod device_info_result;
 mod device_usage_energy_monitoring_result;
 mod device_usage_result;
+mod discovery_result;
+mod emeter_data_result;
 mod energy_data_result;
 mod energy_usage_result;
+mod firmware_result;
+#[cfg(feature = "client")]
 mod handshake_result;
+mod led_info_result;
+#[cfg(feature = "client")]
+mod light_presets_result;
+mod openmetrics;
+#[cfg(feature = "client")]
+mod schedule_rules_result;
 mod tapo_response;
+#[cfg(feature = "client")]
 mod tapo_result;
+mod temperature;
+#[cfg(feature = "client")]
 mod token_result;
 mod trigger_logs_result;
 
+pub use alarm_configuration_result::*;
+pub use automation_list_result::*;
+pub use battery_status::*;
 pub use child_device_list_result::*;
+pub use component_list_result::*;
 pub use current_power_result::*;
+pub use deserialization_mode::DeserializationMode;
 pub use device_info_result::*;
 pub use device_usage_energy_monitoring_result::*;
 pub use device_usage_result::*;
+pub use discovery_result::*;
+pub use emeter_data_result::*;
 pub use energy_data_result::*;
 pub use energy_usage_result::*;
+pub use firmware_result::*;
+pub use led_info_result::*;
+pub use temperature::*;
 pub use trigger_logs_result::*;
 
+#[cfg(feature = "client")]
+pub(crate) use child_protection_result::*;
+#[cfg(feature = "client")]
 pub(crate) use control_child_result::*;
 pub(crate) use decodable_result_ext::*;
+pub(crate) use deserialization_mode::*;
+#[cfg(feature = "client")]
 pub(crate) use handshake_result::*;
+#[cfg(feature = "client")]
+pub(crate) use light_presets_result::*;
+pub(crate) use openmetrics::*;
+#[cfg(feature = "client")]
+pub(crate) use schedule_rules_result::*;
 pub(crate) use tapo_response::*;
+#[cfg(feature = "client")]
 pub(crate) use tapo_result::*;
+#[cfg(feature = "client")]
 pub(crate) use token_result::*;
+
+#[cfg(all(test, feature = "schemars"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schemas_are_generated() {
+        let schema = serde_json::to_value(schemars::schema_for!(NormalizedDeviceInfo)).unwrap();
+
+        assert!(schema["properties"]["model_info"].is_object());
+        assert!(
+            schema["definitions"]["DeviceInfoPlugResult"]["properties"]["default_states"]
+                .is_object()
+        );
+        assert_eq!(
+            schema["definitions"]["LightingEffect"]["properties"]["custom"]["type"],
+            "integer"
+        );
+    }
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+use crate::requests::Ringtone;
+use crate::responses::{TapoResponseExt, UnknownValue};
+
+/// The volume of the alarm of the hub.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum AlarmVolume {
+    Mute,
+    Low,
+    Normal,
+    High,
+    /// Catch-all for values that aren't known by this crate, see [`crate::responses::DeserializationMode`].
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// Alarm settings of the hub, used when an automation or the Tapo app triggers the alarm.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AlarmConfigurationResult {
+    /// The ringtone of the alarm.
+    pub r#type: Ringtone,
+    /// The volume of the alarm.
+    pub volume: AlarmVolume,
+    /// How long the alarm rings for, in seconds.
+    pub duration: u64,
+}
+impl TapoResponseExt for AlarmConfigurationResult {
+    fn unknown_values(&self) -> Vec<&str> {
+        self.volume.unknown_value().into_iter().collect()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct SupportAlarmTypeListResult {
+    pub alarm_type_list: Vec<Ringtone>,
+}
+impl TapoResponseExt for SupportAlarmTypeListResult {}
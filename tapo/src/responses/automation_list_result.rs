@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+use crate::responses::TapoResponseExt;
+
+/// Automation list result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AutomationListResult {
+    /// Automations stored on the hub.
+    pub rule_list: Vec<AutomationResult>,
+    /// The total number of automations stored on the hub.
+    pub sum: u64,
+}
+
+impl TapoResponseExt for AutomationListResult {}
+
+/// Automation stored on the hub.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AutomationResult {
+    /// The identifier of the automation.
+    pub id: String,
+    /// The name of the automation.
+    pub name: String,
+    /// Whether the automation is enabled.
+    #[serde(rename = "enable")]
+    pub enabled: bool,
+}
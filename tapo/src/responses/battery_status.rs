@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::responses::ChildDeviceResult;
+
+/// The battery of a child device, normalized across the models that report a percentage
+/// and those that only report whether the battery is low.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BatteryStatus {
+    /// The remaining charge, between 0 and 100. `None` for the models that don't report it.
+    pub percentage: Option<u8>,
+    /// Whether the device reports its battery as low.
+    pub low: bool,
+}
+
+impl BatteryStatus {
+    /// Returns the [`BatteryStatus`] found in the *device info* of a device,
+    /// or `None` if the device doesn't have a battery.
+    pub fn from_device_info(device_info: &serde_json::Value) -> Option<Self> {
+        let percentage = device_info
+            .get("battery_percentage")
+            .and_then(serde_json::Value::as_u64)
+            .map(|percentage| percentage.min(100) as u8);
+        let low = device_info
+            .get("at_low_battery")
+            .and_then(serde_json::Value::as_bool);
+
+        if percentage.is_none() && low.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            percentage,
+            low: low.unwrap_or(false),
+        })
+    }
+
+    /// Returns `true` if the device reports its battery as low,
+    /// or if its charge is at or below `threshold` percent.
+    pub fn is_low(&self, threshold: u8) -> bool {
+        self.low
+            || self
+                .percentage
+                .is_some_and(|percentage| percentage <= threshold)
+    }
+}
+
+impl ChildDeviceResult {
+    /// Returns the [`BatteryStatus`] of the child device, or `None` for the models that aren't supported.
+    pub fn battery(&self) -> Option<BatteryStatus> {
+        let low = match self {
+            Self::KE100(device) => device.at_low_battery,
+            Self::S200B(device) => device.at_low_battery,
+            Self::T100(device) => device.at_low_battery,
+            Self::T110(device) => device.at_low_battery,
+            Self::T300(device) => device.at_low_battery,
+            Self::T310(device) | Self::T315(device) => device.at_low_battery,
+            Self::Other => return None,
+        };
+
+        Some(BatteryStatus {
+            percentage: None,
+            low,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn battery_is_read_from_the_device_info() {
+        let percentage = BatteryStatus::from_device_info(&json!({ "battery_percentage": 15 }));
+        let flag = BatteryStatus::from_device_info(&json!({ "at_low_battery": true }));
+
+        assert_eq!(
+            percentage,
+            Some(BatteryStatus {
+                percentage: Some(15),
+                low: false
+            })
+        );
+        assert!(percentage.unwrap().is_low(20));
+        assert!(!percentage.unwrap().is_low(10));
+        assert!(flag.unwrap().is_low(0));
+        assert_eq!(
+            BatteryStatus::from_device_info(&json!({ "device_on": true })),
+            None
+        );
+    }
+}
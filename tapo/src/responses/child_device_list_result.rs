@@ -18,7 +18,8 @@ use crate::error::Error;
 use crate::responses::{DecodableResultExt, TapoResponseExt};
 
 /// Child device list result.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ChildDeviceListResult {
     /// Child devices
     #[serde(rename = "child_device_list")]
@@ -37,19 +38,31 @@ impl DecodableResultExt for ChildDeviceListResult {
     }
 }
 
-impl TapoResponseExt for ChildDeviceListResult {}
+impl TapoResponseExt for ChildDeviceListResult {
+    fn unknown_values(&self) -> Vec<&str> {
+        self.devices
+            .iter()
+            .flat_map(ChildDeviceResult::unknown_values)
+            .collect()
+    }
+}
 
 /// Device status.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 #[allow(missing_docs)]
 pub enum Status {
     Online,
     Offline,
+    /// Catch-all for values that aren't known by this crate, see [`crate::responses::DeserializationMode`].
+    #[serde(untagged)]
+    Other(String),
 }
 
 /// Child device result.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "model")]
 pub enum ChildDeviceResult {
     /// KE100 thermostatic radiator valve (TRV).
@@ -72,6 +85,22 @@ pub enum ChildDeviceResult {
     Other,
 }
 
+impl TapoResponseExt for ChildDeviceResult {
+    fn unknown_values(&self) -> Vec<&str> {
+        match self {
+            ChildDeviceResult::KE100(device) => device.unknown_values(),
+            ChildDeviceResult::S200B(device) => device.unknown_values(),
+            ChildDeviceResult::T100(device) => device.unknown_values(),
+            ChildDeviceResult::T110(device) => device.unknown_values(),
+            ChildDeviceResult::T300(device) => device.unknown_values(),
+            ChildDeviceResult::T310(device) | ChildDeviceResult::T315(device) => {
+                device.unknown_values()
+            }
+            ChildDeviceResult::Other => Vec::new(),
+        }
+    }
+}
+
 impl DecodableResultExt for ChildDeviceResult {
     fn decode(self) -> Result<Self, Error> {
         match self {
@@ -1,15 +1,22 @@
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
-use crate::responses::{decode_value, DecodableResultExt, Status, TapoResponseExt};
+use crate::responses::{
+    decode_value, DecodableResultExt, Status, TapoResponseExt, Temperature, TemperatureDelta,
+    UnknownValue,
+};
 
 /// Temperature unit for KE100 devices.
 /// Currently *Celsius* is the only unit supported by KE100.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 #[allow(missing_docs)]
 pub enum TemperatureUnitKE100 {
     Celsius,
+    /// Catch-all for values that aren't known by this crate, see [`crate::responses::DeserializationMode`].
+    #[serde(untagged)]
+    Other(String),
 }
 
 /// KE100 thermostatic radiator valve (TRV).
@@ -17,7 +24,8 @@ pub enum TemperatureUnitKE100 {
 /// Specific properties: `temperature_unit`, `current_temperature`, `target_temperature`,
 /// `min_control_temperature, `max_control_temperature`, `temperature_offset`,
 /// `child_protection_on`, `frost_protection_on`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct KE100Result {
     pub at_low_battery: bool,
@@ -27,7 +35,7 @@ pub struct KE100Result {
     #[serde(rename = "child_protection")]
     pub child_protection_on: bool,
     #[serde(rename = "current_temp")]
-    pub current_temperature: f32,
+    pub current_temperature: Temperature,
     pub device_id: String,
     pub frost_protection_on: bool,
     pub fw_ver: String,
@@ -38,9 +46,9 @@ pub struct KE100Result {
     pub location: String,
     pub mac: String,
     #[serde(rename = "max_control_temp")]
-    pub max_control_temperature: u8,
+    pub max_control_temperature: Temperature,
     #[serde(rename = "min_control_temp")]
-    pub min_control_temperature: u8,
+    pub min_control_temperature: Temperature,
     pub nickname: String,
     pub oem_id: String,
     pub parent_device_id: String,
@@ -51,14 +59,24 @@ pub struct KE100Result {
     pub specs: String,
     pub status: Status,
     #[serde(rename = "target_temp")]
-    pub target_temperature: f32,
+    pub target_temperature: Temperature,
     #[serde(rename = "temp_offset")]
-    pub temperature_offset: i8,
+    pub temperature_offset: TemperatureDelta,
     #[serde(rename = "temp_unit")]
     pub temperature_unit: TemperatureUnitKE100,
 }
 
-impl TapoResponseExt for KE100Result {}
+impl TapoResponseExt for KE100Result {
+    fn unknown_values(&self) -> Vec<&str> {
+        [
+            self.status.unknown_value(),
+            self.temperature_unit.unknown_value(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
 
 impl DecodableResultExt for KE100Result {
     fn decode(mut self) -> Result<Self, Error> {
@@ -1,12 +1,15 @@
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
-use crate::responses::{decode_value, DecodableResultExt, Status, TapoResponseExt};
+use crate::responses::{
+    decode_value, DecodableResultExt, Status, TapoResponseExt, TriggerLog, UnknownValue,
+};
 
 /// S200B button switch.
 ///
 /// Specific properties: none.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct S200BResult {
     pub at_low_battery: bool,
@@ -36,7 +39,11 @@ pub struct S200BResult {
     pub r#type: String,
 }
 
-impl TapoResponseExt for S200BResult {}
+impl TapoResponseExt for S200BResult {
+    fn unknown_values(&self) -> Vec<&str> {
+        self.status.unknown_value().into_iter().collect()
+    }
+}
 
 impl DecodableResultExt for S200BResult {
     fn decode(mut self) -> Result<Self, Error> {
@@ -46,7 +53,8 @@ impl DecodableResultExt for S200BResult {
 }
 
 /// S200B Rotation log params.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct S200BRotationParams {
     #[serde(rename = "rotate_deg")]
@@ -54,7 +62,8 @@ pub struct S200BRotationParams {
 }
 
 /// S200B Log.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase", tag = "event")]
 #[allow(missing_docs)]
 pub enum S200BLog {
@@ -72,3 +81,21 @@ pub enum S200BLog {
         timestamp: u64,
     },
 }
+
+impl TriggerLog for S200BLog {
+    fn id(&self) -> u64 {
+        match self {
+            Self::Rotation { id, .. }
+            | Self::SingleClick { id, .. }
+            | Self::DoubleClick { id, .. } => *id,
+        }
+    }
+
+    fn timestamp(&self) -> u64 {
+        match self {
+            Self::Rotation { timestamp, .. }
+            | Self::SingleClick { timestamp, .. }
+            | Self::DoubleClick { timestamp, .. } => *timestamp,
+        }
+    }
+}
@@ -1,12 +1,15 @@
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
-use crate::responses::{decode_value, DecodableResultExt, Status, TapoResponseExt};
+use crate::responses::{
+    decode_value, DecodableResultExt, Status, TapoResponseExt, TriggerLog, UnknownValue,
+};
 
 /// T100 motion sensor.
 ///
 /// Specific properties: `detected`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct T100Result {
     pub at_low_battery: bool,
@@ -37,7 +40,11 @@ pub struct T100Result {
     pub r#type: String,
 }
 
-impl TapoResponseExt for T100Result {}
+impl TapoResponseExt for T100Result {
+    fn unknown_values(&self) -> Vec<&str> {
+        self.status.unknown_value().into_iter().collect()
+    }
+}
 
 impl DecodableResultExt for T100Result {
     fn decode(mut self) -> Result<Self, Error> {
@@ -47,9 +54,24 @@ impl DecodableResultExt for T100Result {
 }
 
 /// T100 Log.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase", tag = "event")]
 #[allow(missing_docs)]
 pub enum T100Log {
     Motion { id: u64, timestamp: u64 },
 }
+
+impl TriggerLog for T100Log {
+    fn id(&self) -> u64 {
+        match self {
+            Self::Motion { id, .. } => *id,
+        }
+    }
+
+    fn timestamp(&self) -> u64 {
+        match self {
+            Self::Motion { timestamp, .. } => *timestamp,
+        }
+    }
+}
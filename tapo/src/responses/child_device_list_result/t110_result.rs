@@ -1,12 +1,15 @@
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
-use crate::responses::{decode_value, DecodableResultExt, Status, TapoResponseExt};
+use crate::responses::{
+    decode_value, DecodableResultExt, Status, TapoResponseExt, TriggerLog, UnknownValue,
+};
 
 /// T110 contact sensor.
 ///
 /// Specific properties: `open`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct T110Result {
     pub at_low_battery: bool,
@@ -37,7 +40,11 @@ pub struct T110Result {
     pub r#type: String,
 }
 
-impl TapoResponseExt for T110Result {}
+impl TapoResponseExt for T110Result {
+    fn unknown_values(&self) -> Vec<&str> {
+        self.status.unknown_value().into_iter().collect()
+    }
+}
 
 impl DecodableResultExt for T110Result {
     fn decode(mut self) -> Result<Self, Error> {
@@ -47,7 +54,8 @@ impl DecodableResultExt for T110Result {
 }
 
 /// T110 Log.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase", tag = "event")]
 #[allow(missing_docs)]
 pub enum T110Log {
@@ -65,3 +73,19 @@ pub enum T110Log {
         timestamp: u64,
     },
 }
+
+impl TriggerLog for T110Log {
+    fn id(&self) -> u64 {
+        match self {
+            Self::Close { id, .. } | Self::Open { id, .. } | Self::KeepOpen { id, .. } => *id,
+        }
+    }
+
+    fn timestamp(&self) -> u64 {
+        match self {
+            Self::Close { timestamp, .. }
+            | Self::Open { timestamp, .. }
+            | Self::KeepOpen { timestamp, .. } => *timestamp,
+        }
+    }
+}
@@ -1,22 +1,29 @@
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
-use crate::responses::{decode_value, DecodableResultExt, Status, TapoResponseExt};
+use crate::responses::{
+    decode_value, DecodableResultExt, Status, TapoResponseExt, TriggerLog, UnknownValue,
+};
 
 /// Water leak status.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 #[allow(missing_docs)]
 pub enum WaterLeakStatus {
     Normal,
     WaterDry,
     WaterLeak,
+    /// Catch-all for values that aren't known by this crate, see [`crate::responses::DeserializationMode`].
+    #[serde(untagged)]
+    Other(String),
 }
 
 /// T300 water sensor.
 ///
 /// Specific properties: `in_alarm`, `water_leak_status`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct T300Result {
     pub at_low_battery: bool,
@@ -48,7 +55,17 @@ pub struct T300Result {
     pub water_leak_status: WaterLeakStatus,
 }
 
-impl TapoResponseExt for T300Result {}
+impl TapoResponseExt for T300Result {
+    fn unknown_values(&self) -> Vec<&str> {
+        [
+            self.status.unknown_value(),
+            self.water_leak_status.unknown_value(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
 
 impl DecodableResultExt for T300Result {
     fn decode(mut self) -> Result<Self, Error> {
@@ -58,10 +75,25 @@ impl DecodableResultExt for T300Result {
 }
 
 /// T300 Log.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase", tag = "event")]
 #[allow(missing_docs)]
 pub enum T300Log {
     WaterDry { id: u64, timestamp: u64 },
     WaterLeak { id: u64, timestamp: u64 },
 }
+
+impl TriggerLog for T300Log {
+    fn id(&self) -> u64 {
+        match self {
+            Self::WaterDry { id, .. } | Self::WaterLeak { id, .. } => *id,
+        }
+    }
+
+    fn timestamp(&self) -> u64 {
+        match self {
+            Self::WaterDry { timestamp, .. } | Self::WaterLeak { timestamp, .. } => *timestamp,
+        }
+    }
+}
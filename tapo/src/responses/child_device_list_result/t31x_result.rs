@@ -3,21 +3,29 @@ use itertools::izip;
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
-use crate::responses::{decode_value, DecodableResultExt, Status, TapoResponseExt};
+use crate::responses::{
+    decode_value, DecodableResultExt, Status, TapoResponseExt, Temperature, TemperatureDelta,
+    UnknownValue,
+};
 
 /// Temperature unit.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "camelCase")]
 #[allow(missing_docs)]
 pub enum TemperatureUnit {
     Celsius,
     Fahrenheit,
+    /// Catch-all for values that aren't known by this crate, see [`crate::responses::DeserializationMode`].
+    #[serde(untagged)]
+    Other(String),
 }
 
 /// T310/T315 temperature & humidity sensor.
 ///
 /// Specific properties: `current_humidity`, `current_temperature`, `temperature_unit`, `current_humidity_exception`, `current_temperature_exception`.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct T31XResult {
     pub at_low_battery: bool,
@@ -33,9 +41,9 @@ pub struct T31XResult {
     /// When the current temperature value falls outside the comfort zone, this value
     /// will be the difference between the current temperature and the lower or upper bound of the comfort zone.
     #[serde(rename = "current_temp_exception")]
-    pub current_temperature_exception: f32,
+    pub current_temperature_exception: TemperatureDelta,
     #[serde(rename = "current_temp")]
-    pub current_temperature: f32,
+    pub current_temperature: Temperature,
     pub device_id: String,
     pub fw_ver: String,
     pub hw_id: String,
@@ -56,21 +64,46 @@ pub struct T31XResult {
     pub specs: String,
     pub status_follow_edge: bool,
     pub status: Status,
+    /// The unit of the temperatures. It's always [`TemperatureUnit::Celsius`] once the result has been decoded,
+    /// because the temperatures are normalized to Celsius, see [`Temperature`].
     #[serde(rename = "temp_unit")]
     pub temperature_unit: TemperatureUnit,
     pub r#type: String,
 }
 
-impl TapoResponseExt for T31XResult {}
+impl TapoResponseExt for T31XResult {
+    fn unknown_values(&self) -> Vec<&str> {
+        [
+            self.status.unknown_value(),
+            self.temperature_unit.unknown_value(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
 
 impl DecodableResultExt for T31XResult {
     fn decode(mut self) -> Result<Self, Error> {
         self.nickname = decode_value(&self.nickname)?;
+        self.current_temperature = self.current_temperature.normalize(&self.temperature_unit);
+        self.current_temperature_exception = self
+            .current_temperature_exception
+            .normalize(&self.temperature_unit);
+        if self.temperature_unit == TemperatureUnit::Fahrenheit {
+            self.temperature_unit = TemperatureUnit::Celsius;
+        }
         Ok(self)
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// The length of the interval that each record is averaged over.
+const RECORD_INTERVAL_MINUTES: u32 = 15;
+/// The value reported instead of a reading for the intervals in which the sensor didn't report,
+/// e.g. because it was offline or had just been paired.
+const GAP_SENTINEL: i16 = -1000;
+
+#[derive(Debug, PartialEq, Eq, Deserialize)]
 pub(crate) struct TemperatureHumidityRecordsRaw {
     pub local_time: i64,
     pub past24h_humidity_exception: Vec<i16>,
@@ -80,10 +113,15 @@ pub(crate) struct TemperatureHumidityRecordsRaw {
     pub temp_unit: TemperatureUnit,
 }
 
-impl TapoResponseExt for TemperatureHumidityRecordsRaw {}
+impl TapoResponseExt for TemperatureHumidityRecordsRaw {
+    fn unknown_values(&self) -> Vec<&str> {
+        self.temp_unit.unknown_value().into_iter().collect()
+    }
+}
 
 /// Temperature and Humidity record as an average over a 15 minute interval.
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct TemperatureHumidityRecord {
     /// Record's DateTime in UTC.
@@ -96,20 +134,46 @@ pub struct TemperatureHumidityRecord {
     /// This value will be `0.0` when the current temperature is within the comfort zone.
     /// When the current temperature value falls outside the comfort zone, this value
     /// will be the difference between the current temperature and the lower or upper bound of the comfort zone.
-    pub temperature_exception: f32,
-    pub temperature: f32,
+    pub temperature_exception: TemperatureDelta,
+    pub temperature: Temperature,
 }
 
 /// Temperature and Humidity records for the last 24 hours at 15 minute intervals.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct TemperatureHumidityRecords {
     /// The datetime in UTC of when this response was generated.
     pub datetime: DateTime<Utc>,
+    /// The records in chronological order. Intervals without a reading are left out, see `gaps`.
     pub records: Vec<TemperatureHumidityRecord>,
+    /// The start of the intervals in which the sensor didn't report a reading, in chronological order.
+    pub gaps: Vec<DateTime<Utc>>,
+    /// The unit of the temperatures. It's never [`TemperatureUnit::Fahrenheit`],
+    /// because the temperatures are normalized to Celsius, see [`Temperature`].
     pub temperature_unit: TemperatureUnit,
 }
 
+impl TemperatureHumidityRecords {
+    /// Returns the temperature of each record in degrees Celsius as `(datetime, value)` pairs,
+    /// e.g. to be downsampled with [`crate::aggregation::aggregate`].
+    pub fn temperature_series(&self) -> Vec<(DateTime<Utc>, f64)> {
+        self.records
+            .iter()
+            .map(|record| (record.datetime, record.temperature.celsius().into()))
+            .collect()
+    }
+
+    /// Returns the humidity of each record in percent as `(datetime, value)` pairs,
+    /// e.g. to be downsampled with [`crate::aggregation::aggregate`].
+    pub fn humidity_series(&self) -> Vec<(DateTime<Utc>, f64)> {
+        self.records
+            .iter()
+            .map(|record| (record.datetime, record.humidity.into()))
+            .collect()
+    }
+}
+
 impl TryFrom<TemperatureHumidityRecordsRaw> for TemperatureHumidityRecords {
     type Error = anyhow::Error;
 
@@ -118,15 +182,7 @@ impl TryFrom<TemperatureHumidityRecordsRaw> for TemperatureHumidityRecords {
             .unwrap_or_default()
             .and_utc();
 
-        let interval_minute = if datetime.minute() >= 45 {
-            45
-        } else if datetime.minute() >= 30 {
-            30
-        } else if datetime.minute() >= 15 {
-            15
-        } else {
-            0
-        };
+        let interval_minute = datetime.minute() / RECORD_INTERVAL_MINUTES * RECORD_INTERVAL_MINUTES;
 
         let mut interval_time = datetime
             .with_minute(interval_minute)
@@ -135,41 +191,57 @@ impl TryFrom<TemperatureHumidityRecordsRaw> for TemperatureHumidityRecords {
             .unwrap_or_default();
 
         let mut records = Vec::with_capacity(raw.past24h_temp.len());
+        let mut gaps = Vec::new();
 
+        // The last value of each array belongs to the current interval,
+        // so the arrays are aligned from the end in case their lengths differ.
         let iter = izip!(
-            raw.past24h_humidity_exception.into_iter(),
-            raw.past24h_humidity.into_iter(),
-            raw.past24h_temp_exception.into_iter(),
-            raw.past24h_temp.into_iter(),
-        )
-        .rev();
+            raw.past24h_humidity_exception.into_iter().rev(),
+            raw.past24h_humidity.into_iter().rev(),
+            raw.past24h_temp_exception.into_iter().rev(),
+            raw.past24h_temp.into_iter().rev(),
+        );
 
         for (humidity_exception, humidity, temperature_exception, temperature) in iter {
-            if temperature != -1000
-                && temperature_exception != -1000
-                && humidity != -1000
-                && humidity_exception != -1000
+            if [
+                humidity_exception,
+                humidity,
+                temperature_exception,
+                temperature,
+            ]
+            .contains(&GAP_SENTINEL)
             {
+                gaps.push(interval_time);
+            } else {
                 records.push(TemperatureHumidityRecord {
                     humidity_exception: humidity_exception as i8,
                     humidity: humidity as u8,
                     datetime: interval_time,
-                    temperature_exception: temperature_exception as f32 / 10.0,
-                    temperature: temperature as f32 / 10.0,
+                    temperature_exception: TemperatureDelta::from_celsius(
+                        temperature_exception as f32 / 10.0,
+                    )
+                    .normalize(&raw.temp_unit),
+                    temperature: Temperature::from_celsius(temperature as f32 / 10.0)
+                        .normalize(&raw.temp_unit),
                 });
             }
 
             interval_time = interval_time
-                .checked_sub_signed(Duration::minutes(15))
+                .checked_sub_signed(Duration::minutes(RECORD_INTERVAL_MINUTES.into()))
                 .ok_or_else(|| anyhow::anyhow!("Failed to subtract from interval"))?;
         }
 
         records.reverse();
+        gaps.reverse();
 
         Ok(Self {
             datetime,
-            temperature_unit: raw.temp_unit,
+            temperature_unit: match raw.temp_unit {
+                TemperatureUnit::Fahrenheit => TemperatureUnit::Celsius,
+                unit => unit,
+            },
             records,
+            gaps,
         })
     }
 }
@@ -207,8 +279,8 @@ mod tests {
                 datetime: NaiveDateTime::parse_from_str("2023-05-29 13:30:00", "%Y-%m-%d %H:%M:%S")
                     .unwrap()
                     .and_utc(),
-                temperature_exception: 0.0,
-                temperature: 19.6,
+                temperature_exception: TemperatureDelta::from_celsius(0.0),
+                temperature: Temperature::from_celsius(19.6),
             }
         );
         assert_eq!(
@@ -219,8 +291,8 @@ mod tests {
                 datetime: NaiveDateTime::parse_from_str("2023-05-29 13:45:00", "%Y-%m-%d %H:%M:%S")
                     .unwrap()
                     .and_utc(),
-                temperature_exception: 0.0,
-                temperature: 19.5,
+                temperature_exception: TemperatureDelta::from_celsius(0.0),
+                temperature: Temperature::from_celsius(19.5),
             }
         );
         assert_eq!(
@@ -231,8 +303,8 @@ mod tests {
                 datetime: NaiveDateTime::parse_from_str("2023-05-29 14:00:00", "%Y-%m-%d %H:%M:%S")
                     .unwrap()
                     .and_utc(),
-                temperature_exception: 0.0,
-                temperature: 19.4,
+                temperature_exception: TemperatureDelta::from_celsius(0.0),
+                temperature: Temperature::from_celsius(19.4),
             }
         );
         assert_eq!(
@@ -243,8 +315,8 @@ mod tests {
                 datetime: NaiveDateTime::parse_from_str("2023-05-29 14:15:00", "%Y-%m-%d %H:%M:%S")
                     .unwrap()
                     .and_utc(),
-                temperature_exception: 0.0,
-                temperature: 16.2,
+                temperature_exception: TemperatureDelta::from_celsius(0.0),
+                temperature: Temperature::from_celsius(16.2),
             }
         );
         assert_eq!(
@@ -255,8 +327,8 @@ mod tests {
                 datetime: NaiveDateTime::parse_from_str("2023-05-29 14:30:00", "%Y-%m-%d %H:%M:%S")
                     .unwrap()
                     .and_utc(),
-                temperature_exception: 0.0,
-                temperature: 16.4,
+                temperature_exception: TemperatureDelta::from_celsius(0.0),
+                temperature: Temperature::from_celsius(16.4),
             }
         );
         assert_eq!(
@@ -267,8 +339,8 @@ mod tests {
                 datetime: NaiveDateTime::parse_from_str("2023-05-29 14:45:00", "%Y-%m-%d %H:%M:%S")
                     .unwrap()
                     .and_utc(),
-                temperature_exception: 0.0,
-                temperature: 16.5,
+                temperature_exception: TemperatureDelta::from_celsius(0.0),
+                temperature: Temperature::from_celsius(16.5),
             }
         );
     }
@@ -294,6 +366,14 @@ mod tests {
         );
         assert_eq!(parsed.temperature_unit, TemperatureUnit::Celsius);
         assert_eq!(parsed.records.len(), 5);
+        assert_eq!(
+            parsed.gaps,
+            vec![
+                NaiveDateTime::parse_from_str("2023-05-29 14:45:00", "%Y-%m-%d %H:%M:%S")
+                    .unwrap()
+                    .and_utc()
+            ]
+        );
         assert_eq!(
             parsed.records[0],
             TemperatureHumidityRecord {
@@ -302,8 +382,8 @@ mod tests {
                 datetime: NaiveDateTime::parse_from_str("2023-05-29 13:30:00", "%Y-%m-%d %H:%M:%S")
                     .unwrap()
                     .and_utc(),
-                temperature_exception: 0.0,
-                temperature: 19.6,
+                temperature_exception: TemperatureDelta::from_celsius(0.0),
+                temperature: Temperature::from_celsius(19.6),
             }
         );
         assert_eq!(
@@ -314,8 +394,8 @@ mod tests {
                 datetime: NaiveDateTime::parse_from_str("2023-05-29 13:45:00", "%Y-%m-%d %H:%M:%S")
                     .unwrap()
                     .and_utc(),
-                temperature_exception: 0.0,
-                temperature: 19.5,
+                temperature_exception: TemperatureDelta::from_celsius(0.0),
+                temperature: Temperature::from_celsius(19.5),
             }
         );
         assert_eq!(
@@ -326,8 +406,8 @@ mod tests {
                 datetime: NaiveDateTime::parse_from_str("2023-05-29 14:00:00", "%Y-%m-%d %H:%M:%S")
                     .unwrap()
                     .and_utc(),
-                temperature_exception: 0.0,
-                temperature: 19.4,
+                temperature_exception: TemperatureDelta::from_celsius(0.0),
+                temperature: Temperature::from_celsius(19.4),
             }
         );
         assert_eq!(
@@ -338,8 +418,8 @@ mod tests {
                 datetime: NaiveDateTime::parse_from_str("2023-05-29 14:15:00", "%Y-%m-%d %H:%M:%S")
                     .unwrap()
                     .and_utc(),
-                temperature_exception: 0.0,
-                temperature: 16.2,
+                temperature_exception: TemperatureDelta::from_celsius(0.0),
+                temperature: Temperature::from_celsius(16.2),
             }
         );
         assert_eq!(
@@ -350,9 +430,86 @@ mod tests {
                 datetime: NaiveDateTime::parse_from_str("2023-05-29 14:30:00", "%Y-%m-%d %H:%M:%S")
                     .unwrap()
                     .and_utc(),
-                temperature_exception: 0.0,
-                temperature: 16.4,
+                temperature_exception: TemperatureDelta::from_celsius(0.0),
+                temperature: Temperature::from_celsius(16.4),
             }
         );
     }
+
+    #[test]
+    fn test_temperature_humidity_records_parse_aligns_arrays_from_the_end() {
+        let raw = TemperatureHumidityRecordsRaw {
+            local_time: 1685371944,
+            past24h_humidity_exception: vec![0, 0, 0],
+            past24h_humidity: vec![49, 50, 50],
+            past24h_temp_exception: vec![0, 0],
+            past24h_temp: vec![-1000, 195, 194],
+            temp_unit: TemperatureUnit::Fahrenheit,
+        };
+
+        let parsed = TemperatureHumidityRecords::try_from(raw).unwrap();
+
+        assert_eq!(parsed.records.len(), 2);
+        assert!(parsed.gaps.is_empty());
+        assert_eq!(
+            parsed.records[1].datetime,
+            NaiveDateTime::parse_from_str("2023-05-29 14:45:00", "%Y-%m-%d %H:%M:%S")
+                .unwrap()
+                .and_utc()
+        );
+        assert_eq!(parsed.records[1].humidity, 50);
+        assert!((parsed.records[1].temperature.fahrenheit() - 19.4).abs() < 0.001);
+        assert_eq!(parsed.temperature_unit, TemperatureUnit::Celsius);
+    }
+
+    #[test]
+    fn decoded_result_round_trips_without_converting_again() {
+        let result: T31XResult = serde_json::from_value(serde_json::json!({
+            "at_low_battery": false,
+            "avatar": "sensor_t310",
+            "bind_count": 1,
+            "category": "subg.trigger.temp-hmdt-sensor",
+            "current_humidity_exception": 0,
+            "current_humidity": 48,
+            "current_temp_exception": 9.0,
+            "current_temp": 68.0,
+            "device_id": "0000000000000000000000000000000000000000",
+            "fw_ver": "1.8.0 Build 230921 Rel.091519",
+            "hw_id": "00000000000000000000000000000000",
+            "hw_ver": "1.0",
+            "jamming_rssi": -113,
+            "jamming_signal_level": 1,
+            "lastOnboardingTimestamp": 1685371944,
+            "mac": "000000000000",
+            "nickname": "TGl2aW5nIHJvb20=",
+            "oem_id": "00000000000000000000000000000000",
+            "parent_device_id": "0000000000000000000000000000000000000000",
+            "region": "Europe/London",
+            "report_interval": 16,
+            "rssi": -50,
+            "signal_level": 3,
+            "specs": "EU",
+            "status_follow_edge": false,
+            "status": "online",
+            "temp_unit": "fahrenheit",
+            "type": "SMART.TAPOSENSOR",
+        }))
+        .unwrap();
+
+        let decoded = result.decode().unwrap();
+        let mut round_tripped: T31XResult =
+            serde_json::from_value(serde_json::to_value(&decoded).unwrap()).unwrap();
+        // The nickname is serialized decoded, and it isn't what this test is about.
+        round_tripped.nickname = "TGl2aW5nIHJvb20=".to_string();
+        let redecoded = round_tripped.decode().unwrap();
+
+        assert_eq!(decoded.nickname, "Living room");
+        assert_eq!(decoded.current_temperature, Temperature::from_celsius(20.0));
+        assert_eq!(
+            decoded.current_temperature_exception,
+            TemperatureDelta::from_celsius(5.0)
+        );
+        assert_eq!(decoded.temperature_unit, TemperatureUnit::Celsius);
+        assert_eq!(redecoded, decoded);
+    }
 }
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+use crate::responses::TapoResponseExt;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ChildProtectionResult {
+    pub child_protection: bool,
+}
+impl TapoResponseExt for ChildProtectionResult {}
@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::responses::TapoResponseExt;
+
+/// The components that a device has negotiated, i.e. the functionality that its firmware implements.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ComponentListResult {
+    /// List of components.
+    pub component_list: Vec<ComponentResult>,
+}
+
+impl TapoResponseExt for ComponentListResult {}
+
+impl ComponentListResult {
+    /// Returns the [`Capabilities`] derived from this component list.
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities::from(self)
+    }
+}
+
+/// A single component of a device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ComponentResult {
+    /// The component identifier, e.g. `energy_monitoring`.
+    pub id: String,
+    /// The version of the component.
+    pub ver_code: u32,
+}
+
+/// Capability matrix of a device, derived from its [`ComponentListResult`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Capabilities {
+    /// The *brightness* can be changed.
+    pub has_brightness: bool,
+    /// The *hue* and *saturation* can be changed.
+    pub has_color: bool,
+    /// The *color temperature* can be changed.
+    pub has_color_temperature: bool,
+    /// *Lighting effects* can be set.
+    pub has_effects: bool,
+    /// The device reports *energy usage*, *energy data* and *current power*.
+    pub has_energy_monitoring: bool,
+    /// The physical controls of the device can be disabled.
+    pub has_child_protection: bool,
+    /// The status LED of the device can be configured.
+    pub has_led: bool,
+    /// The device has a siren (e.g. the H100 hub).
+    pub has_alarm: bool,
+    /// The device has child devices (e.g. the H100 hub).
+    pub has_child_devices: bool,
+    /// The device supports countdown rules.
+    pub supports_countdown: bool,
+    /// The device supports schedule rules.
+    pub supports_schedule: bool,
+    /// The state the device will have after a power cut can be configured.
+    pub supports_default_states: bool,
+    /// *Presets* can be stored on the device.
+    pub has_presets: bool,
+    components: BTreeMap<String, u32>,
+}
+
+impl Capabilities {
+    /// Returns `true` if the device has negotiated the component with the given `id`.
+    pub fn has_component(&self, id: &str) -> bool {
+        self.components.contains_key(id)
+    }
+
+    /// Returns the version of the component with the given `id`, if the device has negotiated it.
+    pub fn component_version(&self, id: &str) -> Option<u32> {
+        self.components.get(id).copied()
+    }
+}
+
+impl From<&ComponentListResult> for Capabilities {
+    fn from(component_list: &ComponentListResult) -> Self {
+        let components: BTreeMap<String, u32> = component_list
+            .component_list
+            .iter()
+            .map(|component| (component.id.clone(), component.ver_code))
+            .collect();
+
+        let has = |id: &str| components.contains_key(id);
+
+        Self {
+            has_brightness: has("brightness"),
+            has_color: has("color"),
+            has_color_temperature: has("color_temperature"),
+            has_effects: has("light_strip_lighting_effect") || has("light_effect"),
+            has_energy_monitoring: has("energy_monitoring"),
+            has_child_protection: has("child_protection"),
+            has_led: has("led"),
+            has_alarm: has("alarm"),
+            has_child_devices: has("child_device") || has("control_child"),
+            supports_countdown: has("countdown"),
+            supports_schedule: has("schedule"),
+            supports_default_states: has("default_states"),
+            has_presets: has("preset"),
+            components,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_from_component_list() {
+        let component_list: ComponentListResult = serde_json::from_str(
+            r#"{"component_list":[
+                {"id":"device","ver_code":2},
+                {"id":"countdown","ver_code":2},
+                {"id":"energy_monitoring","ver_code":2},
+                {"id":"led","ver_code":1}
+            ]}"#,
+        )
+        .unwrap();
+
+        let capabilities = component_list.capabilities();
+
+        assert!(capabilities.has_energy_monitoring);
+        assert!(capabilities.supports_countdown);
+        assert!(capabilities.has_led);
+        assert!(!capabilities.has_color);
+        assert!(!capabilities.has_effects);
+        assert_eq!(capabilities.component_version("energy_monitoring"), Some(2));
+        assert!(!capabilities.has_component("color"));
+    }
+}
@@ -8,4 +8,8 @@ pub(crate) struct ControlChildResult<T> {
     pub response_data: T,
 }
 
-impl<T> TapoResponseExt for ControlChildResult<T> {}
+impl<T: TapoResponseExt> TapoResponseExt for ControlChildResult<T> {
+    fn unknown_values(&self) -> Vec<&str> {
+        self.response_data.unknown_values()
+    }
+}
@@ -1,9 +1,10 @@
 use serde::{Deserialize, Serialize};
 
-use crate::responses::TapoResponseExt;
+use crate::responses::{OpenMetrics, TapoResponseExt};
 
 /// Contains the current power reading of the device.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 pub struct CurrentPowerResult {
     /// Current power in watts (W).
@@ -11,6 +12,28 @@ pub struct CurrentPowerResult {
 }
 impl TapoResponseExt for CurrentPowerResult {}
 
+impl CurrentPowerResult {
+    /// Returns the current power in the [OpenMetrics](https://openmetrics.io) text format,
+    /// e.g. to be written to a file read by the textfile collector of node_exporter.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the value of the `device` label of the metric, e.g. the nickname of the device
+    pub fn to_openmetrics(&self, device: &str) -> String {
+        let labels = [("device", device)];
+        let mut metrics = OpenMetrics::new(&labels);
+
+        metrics.gauge(
+            "tapo_current_power_watts",
+            Some("watts"),
+            "The current power.",
+            self.current_power,
+        );
+
+        metrics.finish()
+    }
+}
+
 #[cfg(feature = "python")]
 #[pyo3::pymethods]
 impl CurrentPowerResult {
@@ -3,6 +3,8 @@ use base64::{engine::general_purpose, Engine as _};
 use crate::error::Error;
 
 /// Implemented by all Device Info Result variations.
+// Also implemented without the client, by the response types that are built regardless.
+#[cfg_attr(not(any(feature = "client", feature = "fixtures")), allow(dead_code))]
 pub(crate) trait DecodableResultExt
 where
     Self: Sized,
@@ -17,6 +19,7 @@ impl DecodableResultExt for serde_json::Value {
     }
 }
 
+#[cfg_attr(not(any(feature = "client", feature = "fixtures")), allow(dead_code))]
 pub(crate) fn decode_value(value: &str) -> anyhow::Result<String> {
     let decoded_bytes = general_purpose::STANDARD.decode(value)?;
     Ok(std::str::from_utf8(&decoded_bytes)?.to_string())
@@ -0,0 +1,133 @@
+#[cfg(any(feature = "client", feature = "fixtures"))]
+use log::warn;
+#[cfg(any(feature = "client", feature = "fixtures"))]
+use serde::de::Error as _;
+
+#[cfg(any(feature = "client", feature = "fixtures"))]
+use crate::responses::TapoResponseExt;
+use crate::responses::{
+    AlarmVolume, DefaultPowerType, DefaultStateType, LedNightModeType, LedRule, Status,
+    TemperatureUnit, TemperatureUnitKE100, WaterLeakStatus,
+};
+
+/// Controls how the responses of the Tapo API are deserialized.
+///
+/// New firmware versions keep adding values that older versions of this crate don't know about.
+/// In [`DeserializationMode::Lenient`] mode, such values are kept in the `Other` variant of the corresponding enum,
+/// while in [`DeserializationMode::Strict`] mode they make the request fail with [`crate::Error::Deserialization`].
+///
+/// Missing optional fields are tolerated in both modes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeserializationMode {
+    /// Unknown enum values are mapped to the `Other` variant of the corresponding enum.
+    #[default]
+    Lenient,
+    /// Unknown enum values are treated as errors.
+    Strict,
+}
+
+impl DeserializationMode {
+    /// Deserializes `value` into `R`, then checks the values that ended up in an `Other` variant against `self`.
+    #[cfg(any(feature = "client", feature = "fixtures"))]
+    pub(crate) fn deserialize<R>(self, value: &serde_json::Value) -> Result<R, serde_json::Error>
+    where
+        R: serde::de::DeserializeOwned + TapoResponseExt,
+    {
+        let result = R::deserialize(value)?;
+
+        for value in result.unknown_values() {
+            match self {
+                DeserializationMode::Lenient => {
+                    warn!("Unknown value `{value}`, please consider opening an issue");
+                }
+                DeserializationMode::Strict => {
+                    return Err(serde_json::Error::custom(format!(
+                        "unknown value `{value}`"
+                    )));
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Implemented by the enums with an `Other` variant, see [`DeserializationMode`].
+pub(crate) trait UnknownValue {
+    /// Returns the value kept in the `Other` variant, if any.
+    fn unknown_value(&self) -> Option<&str>;
+}
+
+macro_rules! impl_unknown_value {
+    ($($enum:ident),+) => {
+        $(
+            impl UnknownValue for $enum {
+                fn unknown_value(&self) -> Option<&str> {
+                    match self {
+                        Self::Other(value) => Some(value),
+                        _ => None,
+                    }
+                }
+            }
+        )+
+    };
+}
+
+impl_unknown_value!(
+    AlarmVolume,
+    DefaultPowerType,
+    DefaultStateType,
+    LedNightModeType,
+    LedRule,
+    Status,
+    TemperatureUnit,
+    TemperatureUnitKE100,
+    WaterLeakStatus
+);
+
+#[cfg(all(test, any(feature = "client", feature = "fixtures")))]
+mod tests {
+    use super::*;
+    use crate::responses::LedInfoResult;
+
+    #[test]
+    fn lenient_keeps_unknown_values() {
+        let value = serde_json::json!({ "led_rule": "blinking", "led_status": true });
+
+        let result: LedInfoResult = DeserializationMode::Lenient.deserialize(&value).unwrap();
+
+        assert_eq!(result.led_rule, LedRule::Other("blinking".to_string()));
+    }
+
+    #[test]
+    fn unknown_values_are_serialized_as_received() {
+        let value = serde_json::json!("last_on");
+
+        let state_type: DefaultStateType = serde_json::from_value(value.clone()).unwrap();
+
+        assert_eq!(state_type, DefaultStateType::Other("last_on".to_string()));
+        assert_eq!(serde_json::to_value(state_type).unwrap(), value);
+    }
+
+    #[test]
+    fn strict_rejects_unknown_values() {
+        let value = serde_json::json!({
+            "led_rule": "auto",
+            "led_status": true,
+            "night_mode": { "night_mode_type": "always" },
+        });
+
+        let result = DeserializationMode::Strict.deserialize::<LedInfoResult>(&value);
+
+        assert_eq!(result.unwrap_err().to_string(), "unknown value `always`");
+        assert!(matches!(
+            DeserializationMode::Strict.deserialize::<LedInfoResult>(
+                &serde_json::json!({ "led_rule": "auto", "led_status": true })
+            ),
+            Ok(LedInfoResult {
+                led_rule: LedRule::Auto,
+                ..
+            })
+        ));
+    }
+}
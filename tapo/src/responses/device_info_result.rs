@@ -4,6 +4,7 @@ mod default_state;
 mod generic;
 mod hub;
 mod light;
+mod normalized;
 mod plug;
 
 pub use color_light::*;
@@ -12,4 +13,5 @@ pub use default_state::*;
 pub use generic::*;
 pub use hub::*;
 pub use light::*;
+pub use normalized::*;
 pub use plug::*;
@@ -1,10 +1,13 @@
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
-use crate::responses::{decode_value, DecodableResultExt, DefaultStateType, TapoResponseExt};
+use crate::responses::{
+    decode_value, DecodableResultExt, DefaultStateType, TapoResponseExt, UnknownValue,
+};
 
 /// Device info of Tapo L530, L630 and L900. Superset of [`crate::responses::DeviceInfoGenericResult`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct DeviceInfoColorLightResult {
     //
@@ -46,11 +49,21 @@ pub struct DeviceInfoColorLightResult {
     pub hue: Option<u16>,
     pub saturation: Option<u16>,
     pub color_temp: u16,
+    /// The lowest and the highest *color temperature* in Kelvin. Not reported by the older firmware.
+    pub color_temp_range: Option<[u16; 2]>,
     /// The default state of a device to be used when internet connectivity is lost after a power cut.
     pub default_states: DefaultColorLightState,
 }
 
-impl TapoResponseExt for DeviceInfoColorLightResult {}
+impl TapoResponseExt for DeviceInfoColorLightResult {
+    fn unknown_values(&self) -> Vec<&str> {
+        self.default_states
+            .r#type
+            .unknown_value()
+            .into_iter()
+            .collect()
+    }
+}
 
 impl DecodableResultExt for DeviceInfoColorLightResult {
     fn decode(mut self) -> Result<Self, Error> {
@@ -62,7 +75,8 @@ impl DecodableResultExt for DeviceInfoColorLightResult {
 }
 
 /// Color Light Default State.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct DefaultColorLightState {
     pub r#type: DefaultStateType,
@@ -70,7 +84,8 @@ pub struct DefaultColorLightState {
 }
 
 /// Color Light State.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct ColorLightState {
     pub brightness: u8,
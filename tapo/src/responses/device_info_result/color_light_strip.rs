@@ -2,10 +2,13 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
 use crate::requests::LightingEffect;
-use crate::responses::{decode_value, DecodableResultExt, DefaultStateType, TapoResponseExt};
+use crate::responses::{
+    decode_value, DecodableResultExt, DefaultStateType, TapoResponseExt, UnknownValue,
+};
 
 /// Device info of Tapo L920 and L930. Superset of [`crate::responses::DeviceInfoGenericResult`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct DeviceInfoColorLightStripResult {
     //
@@ -27,6 +30,8 @@ pub struct DeviceInfoColorLightStripResult {
     pub specs: String,
     pub lang: String,
     pub device_on: bool,
+    /// The time in seconds this device has been ON since the last state change (ON/OFF).
+    pub on_time: Option<u64>,
     pub overheated: bool,
     pub nickname: String,
     pub avatar: String,
@@ -47,7 +52,15 @@ pub struct DeviceInfoColorLightStripResult {
     pub default_states: DefaultColorLightStripState,
 }
 
-impl TapoResponseExt for DeviceInfoColorLightStripResult {}
+impl TapoResponseExt for DeviceInfoColorLightStripResult {
+    fn unknown_values(&self) -> Vec<&str> {
+        self.default_states
+            .r#type
+            .unknown_value()
+            .into_iter()
+            .collect()
+    }
+}
 
 impl DecodableResultExt for DeviceInfoColorLightStripResult {
     fn decode(mut self) -> Result<Self, Error> {
@@ -59,7 +72,8 @@ impl DecodableResultExt for DeviceInfoColorLightStripResult {
 }
 
 /// Color Light Strip Default State.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct DefaultColorLightStripState {
     pub r#type: DefaultStateType,
@@ -67,7 +81,8 @@ pub struct DefaultColorLightStripState {
 }
 
 /// Color Light Strip State.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[allow(missing_docs)]
 pub struct ColorLightStripState {
     pub brightness: Option<u8>,
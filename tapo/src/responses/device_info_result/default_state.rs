@@ -1,17 +1,43 @@
 use serde::{Deserialize, Serialize};
 
 /// The type of the default state.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
-#[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 #[allow(missing_docs)]
 pub enum DefaultStateType {
     Custom,
     LastStates,
+    /// Catch-all for values that aren't known by this crate, see [`crate::responses::DeserializationMode`].
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// The Python counterpart of [`DefaultStateType`], which passes the values that aren't known by this crate
+/// to Python as plain strings instead.
+#[cfg(feature = "python")]
+#[pyo3::prelude::pyclass(name = "DefaultStateType")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum PyDefaultStateType {
+    Custom,
+    LastStates,
+}
+
+#[cfg(feature = "python")]
+impl pyo3::IntoPy<pyo3::PyObject> for DefaultStateType {
+    fn into_py(self, py: pyo3::Python) -> pyo3::PyObject {
+        match self {
+            Self::Custom => PyDefaultStateType::Custom.into_py(py),
+            Self::LastStates => PyDefaultStateType::LastStates.into_py(py),
+            Self::Other(value) => value.into_py(py),
+        }
+    }
 }
 
 /// Default brightness state.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 #[allow(missing_docs)]
 pub struct DefaultBrightnessState {
@@ -19,12 +45,49 @@ pub struct DefaultBrightnessState {
     pub value: u8,
 }
 
+#[cfg(feature = "python")]
+#[pyo3::pymethods]
+impl DefaultBrightnessState {
+    /// Gets all the properties of this result as a dictionary.
+    pub fn to_dict(&self, py: pyo3::Python) -> pyo3::PyResult<pyo3::Py<pyo3::types::PyDict>> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| pyo3::exceptions::PyException::new_err(e.to_string()))?;
+
+        crate::python::serde_object_to_py_dict(py, &value)
+    }
+}
+
 /// The type of the default power state.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
-#[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 #[allow(missing_docs)]
 pub enum DefaultPowerType {
     AlwaysOn,
     LastStates,
+    /// Catch-all for values that aren't known by this crate, see [`crate::responses::DeserializationMode`].
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// The Python counterpart of [`DefaultPowerType`], which passes the values that aren't known by this crate
+/// to Python as plain strings instead.
+#[cfg(feature = "python")]
+#[pyo3::prelude::pyclass(name = "DefaultPowerType")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum PyDefaultPowerType {
+    AlwaysOn,
+    LastStates,
+}
+
+#[cfg(feature = "python")]
+impl pyo3::IntoPy<pyo3::PyObject> for DefaultPowerType {
+    fn into_py(self, py: pyo3::Python) -> pyo3::PyObject {
+        match self {
+            Self::AlwaysOn => PyDefaultPowerType::AlwaysOn.into_py(py),
+            Self::LastStates => PyDefaultPowerType::LastStates.into_py(py),
+            Self::Other(value) => value.into_py(py),
+        }
+    }
 }
@@ -3,10 +3,12 @@ use serde::{Deserialize, Serialize};
 use crate::error::Error;
 use crate::responses::{
     decode_value, DecodableResultExt, DefaultBrightnessState, DefaultPowerType, TapoResponseExt,
+    UnknownValue,
 };
 
 /// Device info of Tapo L510, L520 and L610. Superset of [`crate::responses::DeviceInfoGenericResult`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 #[allow(missing_docs)]
 pub struct DeviceInfoLightResult {
@@ -65,7 +67,17 @@ impl DeviceInfoLightResult {
     }
 }
 
-impl TapoResponseExt for DeviceInfoLightResult {}
+impl TapoResponseExt for DeviceInfoLightResult {
+    fn unknown_values(&self) -> Vec<&str> {
+        [
+            self.default_states.brightness.r#type.unknown_value(),
+            self.default_states.re_power_type.unknown_value(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
 
 impl DecodableResultExt for DeviceInfoLightResult {
     fn decode(mut self) -> Result<Self, Error> {
@@ -77,10 +89,23 @@ impl DecodableResultExt for DeviceInfoLightResult {
 }
 
 /// Light Default State.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 #[allow(missing_docs)]
 pub struct DefaultLightState {
     pub brightness: DefaultBrightnessState,
     pub re_power_type: DefaultPowerType,
 }
+
+#[cfg(feature = "python")]
+#[pyo3::pymethods]
+impl DefaultLightState {
+    /// Gets all the properties of this result as a dictionary.
+    pub fn to_dict(&self, py: pyo3::Python) -> pyo3::PyResult<pyo3::Py<pyo3::types::PyDict>> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| pyo3::exceptions::PyException::new_err(e.to_string()))?;
+
+        crate::python::serde_object_to_py_dict(py, &value)
+    }
+}
@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+
+use crate::responses::{
+    DeviceInfoColorLightResult, DeviceInfoColorLightStripResult, DeviceInfoGenericResult,
+    DeviceInfoHubResult, DeviceInfoLightResult, DeviceInfoPlugResult, OpenMetrics,
+};
+
+/// A model-independent view of the *device info*, with the same field names for every model,
+/// which allows rendering any device generically.
+/// The model-specific result is kept in [`NormalizedDeviceInfo::model_info`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use tapo::ApiClient;
+/// # use tapo::responses::{ModelDeviceInfo, NormalizedDeviceInfo};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+///     .l530("192.168.1.100")
+///     .await?;
+///
+/// let device_info = NormalizedDeviceInfo::from(device.get_device_info().await?);
+/// println!(
+///     "{}: on={:?}, brightness={:?}",
+///     device_info.nickname, device_info.device_on, device_info.brightness
+/// );
+///
+/// if let ModelDeviceInfo::ColorLight(device_info) = &device_info.model_info {
+///     println!("Default state: {:?}", device_info.default_states);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct NormalizedDeviceInfo {
+    /// The unique ID of the device.
+    pub device_id: String,
+    /// The model of the device, e.g. `P110`.
+    pub model: String,
+    /// The name of the device set in the Tapo app.
+    pub nickname: String,
+    /// Whether the device is on. `None` for the devices that can't be turned on and off, such as hubs.
+    pub device_on: Option<bool>,
+    /// The brightness, between 1 and 100. `None` for the devices that aren't lights.
+    pub brightness: Option<u8>,
+    /// The color of the light. `None` for the devices that don't support colors.
+    pub color: Option<NormalizedColor>,
+    /// The Wi-Fi signal level, between 0 and 3.
+    pub signal_level: u8,
+    /// The Wi-Fi signal strength in dBm.
+    pub rssi: i16,
+    /// The firmware version, e.g. `1.3.0 Build 230905 Rel.152200`.
+    pub firmware_version: String,
+    /// The hardware version, e.g. `1.0`.
+    pub hardware_version: String,
+    /// The model-specific result that the normalized fields have been read from.
+    pub model_info: ModelDeviceInfo,
+}
+
+/// The color of a light in a [`NormalizedDeviceInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum NormalizedColor {
+    /// A color set by hue and saturation.
+    HueSaturation {
+        /// The hue, between 0 and 360.
+        hue: u16,
+        /// The saturation, between 1 and 100.
+        saturation: u16,
+    },
+    /// A white set by color temperature.
+    ColorTemperature {
+        /// The color temperature in Kelvin.
+        color_temp: u16,
+    },
+}
+
+impl NormalizedDeviceInfo {
+    /// Returns the state of the device in the [OpenMetrics](https://openmetrics.io) text format,
+    /// e.g. to be written to a file read by the textfile collector of node_exporter.
+    /// The metrics have a `device` label with the nickname of the device.
+    /// The fields that are `None` are left out.
+    pub fn to_openmetrics(&self) -> String {
+        let labels = [("device", self.nickname.as_str())];
+        let mut metrics = OpenMetrics::new(&labels);
+
+        metrics.info(
+            "tapo_device",
+            "The model and the versions of the device.",
+            &[
+                ("device_id", &self.device_id),
+                ("model", &self.model),
+                ("firmware_version", &self.firmware_version),
+                ("hardware_version", &self.hardware_version),
+            ],
+        );
+        if let Some(device_on) = self.device_on {
+            metrics.gauge(
+                "tapo_device_on",
+                None,
+                "Whether the device is on.",
+                device_on as u8,
+            );
+        }
+        if let Some(brightness) = self.brightness {
+            metrics.gauge(
+                "tapo_brightness_percent",
+                Some("percent"),
+                "The brightness of the light.",
+                brightness,
+            );
+        }
+        metrics.gauge(
+            "tapo_wifi_signal_level",
+            None,
+            "The Wi-Fi signal level, between 0 and 3.",
+            self.signal_level,
+        );
+        metrics.gauge(
+            "tapo_wifi_rssi_dbm",
+            Some("dbm"),
+            "The Wi-Fi signal strength.",
+            self.rssi,
+        );
+
+        metrics.finish()
+    }
+}
+
+impl NormalizedColor {
+    /// The devices report a color temperature of 0 while a hue and saturation color is set.
+    fn new(hue: Option<u16>, saturation: Option<u16>, color_temp: u16) -> Option<Self> {
+        match (hue, saturation) {
+            _ if color_temp > 0 => Some(Self::ColorTemperature { color_temp }),
+            (Some(hue), Some(saturation)) => Some(Self::HueSaturation { hue, saturation }),
+            _ => None,
+        }
+    }
+}
+
+/// The model-specific *device info* of a [`NormalizedDeviceInfo`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum ModelDeviceInfo {
+    ColorLight(Box<DeviceInfoColorLightResult>),
+    ColorLightStrip(Box<DeviceInfoColorLightStripResult>),
+    Generic(Box<DeviceInfoGenericResult>),
+    Hub(Box<DeviceInfoHubResult>),
+    Light(Box<DeviceInfoLightResult>),
+    Plug(Box<DeviceInfoPlugResult>),
+}
+
+macro_rules! impl_from_device_info {
+    ($result:ty, $variant:ident, |$info:ident| $device_on:expr, $brightness:expr, $color:expr) => {
+        impl From<$result> for NormalizedDeviceInfo {
+            fn from($info: $result) -> Self {
+                Self {
+                    device_id: $info.device_id.clone(),
+                    model: $info.model.clone(),
+                    nickname: $info.nickname.clone(),
+                    device_on: $device_on,
+                    brightness: $brightness,
+                    color: $color,
+                    signal_level: $info.signal_level,
+                    rssi: $info.rssi,
+                    firmware_version: $info.fw_ver.clone(),
+                    hardware_version: $info.hw_ver.clone(),
+                    model_info: ModelDeviceInfo::$variant(Box::new($info)),
+                }
+            }
+        }
+
+        impl $result {
+            /// Returns the state of the device in the [OpenMetrics](https://openmetrics.io) text format,
+            /// see [`NormalizedDeviceInfo::to_openmetrics`].
+            pub fn to_openmetrics(&self) -> String {
+                NormalizedDeviceInfo::from(self.clone()).to_openmetrics()
+            }
+        }
+    };
+}
+
+impl_from_device_info!(
+    DeviceInfoColorLightResult,
+    ColorLight,
+    |info| Some(info.device_on),
+    Some(info.brightness),
+    NormalizedColor::new(info.hue, info.saturation, info.color_temp)
+);
+impl_from_device_info!(
+    DeviceInfoColorLightStripResult,
+    ColorLightStrip,
+    |info| Some(info.device_on),
+    Some(info.brightness),
+    NormalizedColor::new(info.hue, info.saturation, info.color_temp)
+);
+impl_from_device_info!(
+    DeviceInfoGenericResult,
+    Generic,
+    |info| info.device_on,
+    None,
+    None
+);
+impl_from_device_info!(DeviceInfoHubResult, Hub, |info| None, None, None);
+impl_from_device_info!(
+    DeviceInfoLightResult,
+    Light,
+    |info| Some(info.device_on),
+    Some(info.brightness),
+    None
+);
+impl_from_device_info!(
+    DeviceInfoPlugResult,
+    Plug,
+    |info| Some(info.device_on),
+    None,
+    None
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_is_normalized() {
+        assert_eq!(
+            NormalizedColor::new(Some(120), Some(80), 0),
+            Some(NormalizedColor::HueSaturation {
+                hue: 120,
+                saturation: 80
+            })
+        );
+        assert_eq!(
+            NormalizedColor::new(Some(120), Some(80), 2700),
+            Some(NormalizedColor::ColorTemperature { color_temp: 2700 })
+        );
+        assert_eq!(NormalizedColor::new(None, None, 0), None);
+    }
+
+    #[test]
+    fn plug_is_normalized() {
+        let result: DeviceInfoPlugResult = serde_json::from_str(include_str!(
+            "../../../fixtures/P110/1.3.0/get_device_info.json"
+        ))
+        .unwrap();
+
+        let device_info = NormalizedDeviceInfo::from(result.clone());
+
+        assert_eq!(device_info.model, "P110");
+        assert_eq!(device_info.device_on, Some(true));
+        assert_eq!(device_info.brightness, None);
+        assert_eq!(device_info.color, None);
+        assert_eq!(device_info.signal_level, 3);
+        assert_eq!(
+            device_info.firmware_version,
+            "1.3.0 Build 230905 Rel.152200"
+        );
+        assert_eq!(
+            device_info.model_info,
+            ModelDeviceInfo::Plug(Box::new(result))
+        );
+    }
+
+    #[test]
+    fn plug_is_exposed_as_openmetrics() {
+        let mut result: DeviceInfoPlugResult = serde_json::from_str(include_str!(
+            "../../../fixtures/P110/1.3.0/get_device_info.json"
+        ))
+        .unwrap();
+        result.nickname = "Kitchen".to_string();
+
+        let text = result.to_openmetrics();
+
+        assert!(text.contains("tapo_device_info{device=\"Kitchen\",device_id=\"0000000000000000000000000000000000000000\",model=\"P110\",firmware_version=\"1.3.0 Build 230905 Rel.152200\",hardware_version=\"1.0\"} 1\n"));
+        assert!(text.contains("tapo_device_on{device=\"Kitchen\"} 1\n"));
+        assert!(text.contains("tapo_wifi_rssi_dbm{device=\"Kitchen\"} -46\n"));
+        assert!(!text.contains("tapo_brightness_percent"));
+        assert!(text.ends_with("# EOF\n"));
+    }
+}
@@ -1,10 +1,13 @@
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
-use crate::responses::{decode_value, DecodableResultExt, DefaultStateType, TapoResponseExt};
+use crate::responses::{
+    decode_value, DecodableResultExt, DefaultStateType, TapoResponseExt, UnknownValue,
+};
 
 /// Device info of Tapo P100, P105, P110 and P115. Superset of [`crate::responses::DeviceInfoGenericResult`].
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 #[allow(missing_docs)]
 pub struct DeviceInfoPlugResult {
@@ -42,6 +45,10 @@ pub struct DeviceInfoPlugResult {
     //
     /// The default state of a device to be used when internet connectivity is lost after a power cut.
     pub default_states: DefaultPlugState,
+    /// Whether the physical button of the device is disabled.
+    /// Only reported by the firmware of some devices, see `get_child_protection` otherwise.
+    #[serde(rename = "child_protection")]
+    pub child_protection_on: Option<bool>,
 }
 
 #[cfg(feature = "python")]
@@ -56,7 +63,15 @@ impl DeviceInfoPlugResult {
     }
 }
 
-impl TapoResponseExt for DeviceInfoPlugResult {}
+impl TapoResponseExt for DeviceInfoPlugResult {
+    fn unknown_values(&self) -> Vec<&str> {
+        self.default_states
+            .r#type
+            .unknown_value()
+            .into_iter()
+            .collect()
+    }
+}
 
 impl DecodableResultExt for DeviceInfoPlugResult {
     fn decode(mut self) -> Result<Self, Error> {
@@ -68,7 +83,8 @@ impl DecodableResultExt for DeviceInfoPlugResult {
 }
 
 /// Plug Default State.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 #[allow(missing_docs)]
 pub struct DefaultPlugState {
@@ -76,10 +92,35 @@ pub struct DefaultPlugState {
     pub state: PlugState,
 }
 
+#[cfg(feature = "python")]
+#[pyo3::pymethods]
+impl DefaultPlugState {
+    /// Gets all the properties of this result as a dictionary.
+    pub fn to_dict(&self, py: pyo3::Python) -> pyo3::PyResult<pyo3::Py<pyo3::types::PyDict>> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| pyo3::exceptions::PyException::new_err(e.to_string()))?;
+
+        crate::python::serde_object_to_py_dict(py, &value)
+    }
+}
+
 /// Plug State.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 #[allow(missing_docs)]
 pub struct PlugState {
     pub on: Option<bool>,
 }
+
+#[cfg(feature = "python")]
+#[pyo3::pymethods]
+impl PlugState {
+    /// Gets all the properties of this result as a dictionary.
+    pub fn to_dict(&self, py: pyo3::Python) -> pyo3::PyResult<pyo3::Py<pyo3::types::PyDict>> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| pyo3::exceptions::PyException::new_err(e.to_string()))?;
+
+        crate::python::serde_object_to_py_dict(py, &value)
+    }
+}
@@ -3,7 +3,8 @@ use serde::{Deserialize, Serialize};
 use super::{TapoResponseExt, UsageByPeriodResult};
 
 /// Contains the time usage, the power consumption, and the energy savings of the device.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 pub struct DeviceUsageEnergyMonitoringResult {
     /// Time usage in minutes.
@@ -3,7 +3,8 @@ use serde::{Deserialize, Serialize};
 use crate::responses::TapoResponseExt;
 
 /// Contains the time usage.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 pub struct DeviceUsageResult {
     /// Time usage in minutes.
@@ -24,7 +25,8 @@ impl DeviceUsageResult {
 }
 
 /// Usage by period result for today, the past 7 days, and the past 30 days.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 pub struct UsageByPeriodResult {
     /// Today.
@@ -34,3 +36,15 @@ pub struct UsageByPeriodResult {
     /// Past 30 days.
     pub past30: u64,
 }
+
+#[cfg(feature = "python")]
+#[pyo3::pymethods]
+impl UsageByPeriodResult {
+    /// Gets all the properties of this result as a dictionary.
+    pub fn to_dict(&self, py: pyo3::Python) -> pyo3::PyResult<pyo3::Py<pyo3::types::PyDict>> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| pyo3::exceptions::PyException::new_err(e.to_string()))?;
+
+        crate::python::serde_object_to_py_dict(py, &value)
+    }
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+use crate::responses::TapoResponseExt;
+
+/// Device found on the local network by [`crate::discover_devices`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[allow(missing_docs)]
+pub struct DiscoveryResult {
+    pub device_id: String,
+    pub device_type: String,
+    pub device_model: String,
+    pub ip: String,
+    pub mac: String,
+    pub hw_ver: Option<String>,
+    pub owner: Option<String>,
+    pub factory_default: Option<bool>,
+    /// The encryption scheme that the device expects its requests to be sent with.
+    #[serde(rename = "mgt_encrypt_schm")]
+    pub encryption_scheme: Option<EncryptionSchemeResult>,
+}
+
+impl TapoResponseExt for DiscoveryResult {}
+
+/// Encryption scheme advertised by a device as part of its [`DiscoveryResult`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[allow(missing_docs)]
+pub struct EncryptionSchemeResult {
+    pub is_support_https: Option<bool>,
+    pub encrypt_type: Option<String>,
+    pub http_port: Option<u16>,
+    pub lv: Option<u8>,
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+use crate::responses::TapoResponseExt;
+
+/// Contains the electrical readings of the device.
+/// Each reading is only present when the firmware of the device reports it.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
+pub struct EmeterDataResult {
+    /// Current power in milliwatts (mW).
+    pub power_mw: Option<u64>,
+    /// Voltage in millivolts (mV).
+    pub voltage_mv: Option<u64>,
+    /// Current in milliamperes (mA).
+    pub current_ma: Option<u64>,
+    /// Total energy usage in watt-hours (Wh).
+    pub energy_wh: Option<u64>,
+}
+impl TapoResponseExt for EmeterDataResult {}
+
+impl EmeterDataResult {
+    /// Returns the power factor, i.e. the ratio between the real power and the apparent power,
+    /// if the power, the voltage and the current are all reported.
+    pub fn power_factor(&self) -> Option<f64> {
+        let apparent_power = self.voltage_mv? as f64 * self.current_ma? as f64 / 1000.0;
+        if apparent_power == 0.0 {
+            return None;
+        }
+
+        Some((self.power_mw? as f64 / apparent_power).min(1.0))
+    }
+}
+
+#[cfg(feature = "python")]
+#[pyo3::pymethods]
+impl EmeterDataResult {
+    /// Returns the power factor, i.e. the ratio between the real power and the apparent power,
+    /// if the power, the voltage and the current are all reported.
+    #[pyo3(name = "power_factor")]
+    pub fn py_power_factor(&self) -> Option<f64> {
+        self.power_factor()
+    }
+
+    /// Gets all the properties of this result as a dictionary.
+    pub fn to_dict(&self, py: pyo3::Python) -> pyo3::PyResult<pyo3::Py<pyo3::types::PyDict>> {
+        let value = serde_json::to_value(self)
+            .map_err(|e| pyo3::exceptions::PyException::new_err(e.to_string()))?;
+
+        crate::python::serde_object_to_py_dict(py, &value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power_factor_requires_all_readings() {
+        let emeter_data: EmeterDataResult = serde_json::from_str(
+            r#"{"power_mw":92000,"voltage_mv":230000,"current_ma":500,"energy_wh":1200}"#,
+        )
+        .unwrap();
+        assert_eq!(emeter_data.power_factor(), Some(0.8));
+
+        let emeter_data: EmeterDataResult = serde_json::from_str(r#"{"power_mw":92000}"#).unwrap();
+        assert_eq!(emeter_data.current_ma, None);
+        assert_eq!(emeter_data.power_factor(), None);
+    }
+}
@@ -1,11 +1,12 @@
-use chrono::NaiveDateTime;
+use chrono::{DateTime, Duration, Months, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::responses::TapoResponseExt;
 use crate::tapo_date_format::der_tapo_datetime_format;
 
 /// Energy data for the requested [`crate::requests::EnergyDataInterval`].
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 pub struct EnergyDataResult {
     /// Local time of the device.
@@ -13,15 +14,40 @@ pub struct EnergyDataResult {
     pub local_time: NaiveDateTime,
     /// Energy data for the given `interval` in watts (W).
     pub data: Vec<u64>,
-    /// Interval start timestamp in milliseconds.
+    /// Interval start timestamp in seconds.
     pub start_timestamp: u64,
-    /// Interval end timestamp in milliseconds.
+    /// Interval end timestamp in seconds.
     pub end_timestamp: u64,
     /// Interval in minutes.
     pub interval: u64,
 }
 impl TapoResponseExt for EnergyDataResult {}
 
+/// The `interval` reported for [`crate::requests::EnergyDataInterval::Monthly`].
+const MONTHLY_INTERVAL: u64 = 43200;
+
+impl EnergyDataResult {
+    /// Returns each value of `data` together with the start of its interval as `(datetime, value)` pairs,
+    /// e.g. to be downsampled with [`crate::aggregation::aggregate`].
+    pub fn series(&self) -> Vec<(DateTime<Utc>, f64)> {
+        let start = DateTime::from_timestamp(self.start_timestamp as i64, 0).unwrap_or_default();
+
+        self.data
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| {
+                let datetime = if self.interval == MONTHLY_INTERVAL {
+                    start.checked_add_months(Months::new(index as u32))?
+                } else {
+                    start + Duration::minutes((index as u64 * self.interval) as i64)
+                };
+
+                Some((datetime, *value as f64))
+            })
+            .collect()
+    }
+}
+
 #[cfg(feature = "python")]
 #[pyo3::pymethods]
 impl EnergyDataResult {
@@ -1,11 +1,13 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
-use crate::responses::TapoResponseExt;
+use crate::responses::{OpenMetrics, TapoResponseExt};
 use crate::tapo_date_format::der_tapo_datetime_format;
 
 /// Contains local time, current power and the energy usage and runtime for today and for the current month.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[cfg_attr(feature = "python", pyo3::prelude::pyclass(get_all))]
 pub struct EnergyUsageResult {
     /// Local time of the device.
@@ -52,6 +54,52 @@ pub struct EnergyUsageResult {
 }
 impl TapoResponseExt for EnergyUsageResult {}
 
+impl EnergyUsageResult {
+    /// Returns the power and the energy usage in the [OpenMetrics](https://openmetrics.io) text format,
+    /// e.g. to be written to a file read by the textfile collector of node_exporter.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the value of the `device` label of the metrics, e.g. the nickname of the device
+    pub fn to_openmetrics(&self, device: &str) -> String {
+        let labels = [("device", device)];
+        let mut metrics = OpenMetrics::new(&labels);
+
+        metrics.gauge(
+            "tapo_current_power_watts",
+            Some("watts"),
+            "The current power.",
+            self.current_power as f64 / 1000.0,
+        );
+        metrics.gauge(
+            "tapo_today_energy_watt_hours",
+            Some("watt_hours"),
+            "The energy used today.",
+            self.today_energy,
+        );
+        metrics.gauge(
+            "tapo_month_energy_watt_hours",
+            Some("watt_hours"),
+            "The energy used in the current month.",
+            self.month_energy,
+        );
+        metrics.gauge(
+            "tapo_today_runtime_seconds",
+            Some("seconds"),
+            "How long the device has been on today.",
+            self.today_runtime * 60,
+        );
+        metrics.gauge(
+            "tapo_month_runtime_seconds",
+            Some("seconds"),
+            "How long the device has been on in the current month.",
+            self.month_runtime * 60,
+        );
+
+        metrics.finish()
+    }
+}
+
 #[cfg(feature = "python")]
 #[pyo3::pymethods]
 impl EnergyUsageResult {
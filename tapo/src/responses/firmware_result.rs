@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+
+use crate::responses::TapoResponseExt;
+
+/// The latest firmware available for a device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[allow(missing_docs)]
+pub struct LatestFirmwareResult {
+    /// Whether the latest firmware is newer than the one installed on the device.
+    pub need_to_upgrade: bool,
+    /// The version of the latest firmware. Empty when the device is up to date.
+    pub fw_ver: String,
+    pub fw_size: Option<u64>,
+    pub release_date: Option<String>,
+    pub release_note: Option<String>,
+    pub r#type: Option<u8>,
+}
+
+impl TapoResponseExt for LatestFirmwareResult {}
+
+/// Progress of a firmware update.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct FirmwareDownloadStateResult {
+    /// The state of the update as reported by the firmware. `0` when no update is in progress.
+    pub status: u8,
+    /// The download progress, from `0` to `100`.
+    pub download_progress: u8,
+    /// The estimated time in seconds that the device needs to reboot after the upgrade.
+    pub reboot_time: u32,
+    /// The estimated time in seconds that the upgrade takes once downloaded.
+    pub upgrade_time: u32,
+    /// Whether the device installs updates automatically.
+    pub auto_upgrade: bool,
+}
+
+impl TapoResponseExt for FirmwareDownloadStateResult {}
+
+impl FirmwareDownloadStateResult {
+    /// Returns `true` once the firmware has been fully downloaded.
+    /// The device installs it and reboots afterwards, which takes about [`FirmwareDownloadStateResult::upgrade_time`]
+    /// plus [`FirmwareDownloadStateResult::reboot_time`] seconds.
+    pub fn is_downloaded(&self) -> bool {
+        self.download_progress >= 100
+    }
+
+    /// Returns `true` while an update is in progress, according to [`FirmwareDownloadStateResult::status`].
+    pub fn is_in_progress(&self) -> bool {
+        self.status != 0
+    }
+}
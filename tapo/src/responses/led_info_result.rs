@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+use crate::responses::{TapoResponseExt, UnknownValue};
+
+/// When the status LED of the device is lit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LedRule {
+    /// The LED is always lit.
+    Always,
+    /// The LED is never lit.
+    Never,
+    /// The LED is lit, except during the [`LedNightModeResult`] hours.
+    Auto,
+    /// Catch-all for values that aren't known by this crate, see [`crate::responses::DeserializationMode`].
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// How the night mode hours of the status LED are defined.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum LedNightModeType {
+    /// From sunset to sunrise at the location of the device, shifted by the offsets.
+    SunriseSunset,
+    /// Between fixed times of the day.
+    Custom,
+    /// Catch-all for values that aren't known by this crate, see [`crate::responses::DeserializationMode`].
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// The night mode hours of the status LED.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct LedNightModeResult {
+    /// How the night mode hours are defined.
+    pub night_mode_type: LedNightModeType,
+    /// Offset from sunrise in minutes, for [`LedNightModeType::SunriseSunset`].
+    #[serde(default)]
+    pub sunrise_offset: i64,
+    /// Offset from sunset in minutes, for [`LedNightModeType::SunriseSunset`].
+    #[serde(default)]
+    pub sunset_offset: i64,
+    /// Start of the night mode in minutes since midnight.
+    #[serde(default)]
+    pub start_time: u64,
+    /// End of the night mode in minutes since midnight.
+    #[serde(default)]
+    pub end_time: u64,
+}
+
+/// Status LED settings of the device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct LedInfoResult {
+    /// When the LED is lit.
+    pub led_rule: LedRule,
+    /// Whether the LED is currently lit.
+    pub led_status: bool,
+    /// The night mode hours, used when `led_rule` is [`LedRule::Auto`].
+    pub night_mode: Option<LedNightModeResult>,
+}
+impl TapoResponseExt for LedInfoResult {
+    fn unknown_values(&self) -> Vec<&str> {
+        [
+            self.led_rule.unknown_value(),
+            self.night_mode
+                .as_ref()
+                .and_then(|night_mode| night_mode.night_mode_type.unknown_value()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+use crate::requests::LightPreset;
+use crate::responses::TapoResponseExt;
+
+/// The presets of a light. Devices without color only report their brightness.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct LightPresetsResult {
+    pub states: Option<Vec<LightPreset>>,
+    pub brightness: Option<Vec<u8>>,
+}
+impl TapoResponseExt for LightPresetsResult {}
+
+impl LightPresetsResult {
+    pub fn into_presets(self) -> Vec<LightPreset> {
+        match (self.states, self.brightness) {
+            (Some(states), _) => states,
+            (None, Some(brightness)) => brightness
+                .into_iter()
+                .map(LightPreset::brightness)
+                .collect(),
+            (None, None) => Vec::new(),
+        }
+    }
+}
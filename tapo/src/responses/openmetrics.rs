@@ -0,0 +1,98 @@
+use std::fmt::{Display, Write};
+
+/// Builds a text exposition in the [OpenMetrics](https://openmetrics.io) format,
+/// which is also accepted by the textfile collector of node_exporter.
+pub(crate) struct OpenMetrics<'a> {
+    labels: &'a [(&'a str, &'a str)],
+    text: String,
+}
+
+impl<'a> OpenMetrics<'a> {
+    /// Returns a new [`OpenMetrics`] whose samples all have the given `labels`.
+    pub fn new(labels: &'a [(&'a str, &'a str)]) -> Self {
+        Self {
+            labels,
+            text: String::new(),
+        }
+    }
+
+    /// Adds a gauge with a single sample. The `name` must end with the `unit`, if any.
+    pub fn gauge(&mut self, name: &str, unit: Option<&str>, help: &str, value: impl Display) {
+        self.metadata(name, "gauge", unit, help);
+        self.sample(name, &[], value);
+    }
+
+    /// Adds an info metric, whose sample has the value 1 and carries the information in `labels`.
+    pub fn info(&mut self, name: &str, help: &str, labels: &[(&str, &str)]) {
+        self.metadata(name, "info", None, help);
+        self.sample(&format!("{name}_info"), labels, 1);
+    }
+
+    /// Returns the exposition, terminated by `# EOF`.
+    pub fn finish(mut self) -> String {
+        self.text.push_str("# EOF\n");
+        self.text
+    }
+
+    fn metadata(&mut self, name: &str, r#type: &str, unit: Option<&str>, help: &str) {
+        let _ = writeln!(self.text, "# TYPE {name} {type}");
+        if let Some(unit) = unit {
+            let _ = writeln!(self.text, "# UNIT {name} {unit}");
+        }
+        let _ = writeln!(self.text, "# HELP {name} {}", escape(help, false));
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl Display) {
+        let labels = self
+            .labels
+            .iter()
+            .chain(labels)
+            .map(|(name, value)| format!("{name}=\"{}\"", escape(value, true)))
+            .collect::<Vec<_>>();
+
+        if labels.is_empty() {
+            let _ = writeln!(self.text, "{name} {value}");
+        } else {
+            let _ = writeln!(self.text, "{name}{{{}}} {value}", labels.join(","));
+        }
+    }
+}
+
+fn escape(value: &str, quotes: bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '"' if quotes => escaped.push_str("\\\""),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exposition_is_formatted() {
+        let mut metrics = OpenMetrics::new(&[("device", "Living \"Room\"")]);
+        metrics.gauge("tapo_power_watts", Some("watts"), "Power.", 1.5);
+        metrics.info("tapo_device", "Device.", &[("model", "P110")]);
+
+        assert_eq!(
+            metrics.finish(),
+            "# TYPE tapo_power_watts gauge\n\
+             # UNIT tapo_power_watts watts\n\
+             # HELP tapo_power_watts Power.\n\
+             tapo_power_watts{device=\"Living \\\"Room\\\"\"} 1.5\n\
+             # TYPE tapo_device info\n\
+             # HELP tapo_device Device.\n\
+             tapo_device_info{device=\"Living \\\"Room\\\"\",model=\"P110\"} 1\n\
+             # EOF\n"
+        );
+    }
+}
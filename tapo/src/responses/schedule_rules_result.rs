@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+use crate::responses::TapoResponseExt;
+
+/// Schedule rules stored on a device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ScheduleRulesResult {
+    pub rule_list: Vec<ScheduleRuleResult>,
+    pub sum: u64,
+}
+
+impl TapoResponseExt for ScheduleRulesResult {}
+
+/// Schedule rule of a KE100 device.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ScheduleRuleResult {
+    pub id: String,
+    pub enable: bool,
+    pub week_day: u8,
+    pub s_min: u16,
+    pub e_min: u16,
+    pub desired_states: ScheduleRuleDesiredStates,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ScheduleRuleDesiredStates {
+    pub target_temp: u8,
+}
@@ -1,30 +1,57 @@
+#[cfg(feature = "client")]
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "client")]
 use crate::error::{Error, TapoResponseError};
 
 /// Implemented by all Tapo Responses.
-pub(crate) trait TapoResponseExt {}
+// Also implemented without the client, by the response types that are built regardless.
+#[cfg_attr(not(feature = "client"), allow(dead_code))]
+pub(crate) trait TapoResponseExt {
+    /// Returns the values of the response that ended up in the `Other` variant of an enum,
+    /// see [`crate::responses::DeserializationMode`].
+    fn unknown_values(&self) -> Vec<&str> {
+        Vec::new()
+    }
+}
 
 impl TapoResponseExt for serde_json::Value {}
 
+#[cfg(feature = "client")]
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct TapoResponse<T: TapoResponseExt> {
     pub error_code: i32,
     pub result: Option<T>,
 }
 
+#[cfg(feature = "client")]
 #[derive(Debug, Deserialize)]
 pub(crate) struct TapoMultipleResponse<T: TapoResponseExt> {
     pub result: TapoMultipleResult<T>,
 }
 
-impl<T> TapoResponseExt for TapoMultipleResponse<T> where T: TapoResponseExt {}
+#[cfg(feature = "client")]
+impl<T> TapoResponseExt for TapoMultipleResponse<T>
+where
+    T: TapoResponseExt,
+{
+    fn unknown_values(&self) -> Vec<&str> {
+        self.result
+            .responses
+            .iter()
+            .filter_map(|response| response.result.as_ref())
+            .flat_map(TapoResponseExt::unknown_values)
+            .collect()
+    }
+}
 
+#[cfg(feature = "client")]
 #[derive(Debug, Deserialize)]
 pub(crate) struct TapoMultipleResult<T: TapoResponseExt> {
     pub responses: Vec<TapoResponse<T>>,
 }
 
+#[cfg(feature = "client")]
 pub(crate) fn validate_response<T: TapoResponseExt>(
     response: &TapoResponse<T>,
 ) -> Result<(), Error> {
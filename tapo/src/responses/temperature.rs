@@ -0,0 +1,149 @@
+use std::fmt;
+use std::ops::{Add, Sub};
+
+use serde::{Deserialize, Serialize};
+
+use crate::responses::TemperatureUnit;
+
+/// Temperature reported by a device.
+///
+/// The devices report temperatures in the unit selected for display in the Tapo app (`temperature_unit`).
+/// To prevent mixing units, the value is always normalized to degrees Celsius when the response is decoded,
+/// and can be read in either unit with [`Temperature::celsius`] and [`Temperature::fahrenheit`].
+/// It is (de)serialized as a number of degrees Celsius.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct Temperature {
+    celsius: f32,
+}
+
+impl Temperature {
+    /// Creates a [`Temperature`] from degrees Celsius.
+    pub fn from_celsius(celsius: f32) -> Self {
+        Self { celsius }
+    }
+
+    /// Creates a [`Temperature`] from degrees Fahrenheit.
+    pub fn from_fahrenheit(fahrenheit: f32) -> Self {
+        Self {
+            celsius: (fahrenheit - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    /// Returns the temperature in degrees Celsius.
+    pub fn celsius(self) -> f32 {
+        self.celsius
+    }
+
+    /// Returns the temperature in degrees Fahrenheit.
+    pub fn fahrenheit(self) -> f32 {
+        self.celsius * 9.0 / 5.0 + 32.0
+    }
+
+    /// Reinterprets a value that was deserialized as-is from a response reported in `unit`.
+    pub(crate) fn normalize(self, unit: &TemperatureUnit) -> Self {
+        match unit {
+            TemperatureUnit::Fahrenheit => Self::from_fahrenheit(self.celsius),
+            _ => self,
+        }
+    }
+}
+
+impl fmt::Display for Temperature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} °C", self.celsius)
+    }
+}
+
+impl Add<TemperatureDelta> for Temperature {
+    type Output = Temperature;
+
+    fn add(self, delta: TemperatureDelta) -> Self::Output {
+        Temperature::from_celsius(self.celsius + delta.celsius)
+    }
+}
+
+impl Sub<TemperatureDelta> for Temperature {
+    type Output = Temperature;
+
+    fn sub(self, delta: TemperatureDelta) -> Self::Output {
+        Temperature::from_celsius(self.celsius - delta.celsius)
+    }
+}
+
+impl Sub for Temperature {
+    type Output = TemperatureDelta;
+
+    fn sub(self, other: Temperature) -> Self::Output {
+        TemperatureDelta::from_celsius(self.celsius - other.celsius)
+    }
+}
+
+/// Difference between two temperatures, e.g. a temperature offset or the distance from the comfort zone.
+///
+/// Unlike [`Temperature`], the conversion between Celsius and Fahrenheit only scales the value.
+/// It is (de)serialized as a number of degrees Celsius.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(transparent)]
+pub struct TemperatureDelta {
+    celsius: f32,
+}
+
+impl TemperatureDelta {
+    /// Creates a [`TemperatureDelta`] from degrees Celsius.
+    pub fn from_celsius(celsius: f32) -> Self {
+        Self { celsius }
+    }
+
+    /// Creates a [`TemperatureDelta`] from degrees Fahrenheit.
+    pub fn from_fahrenheit(fahrenheit: f32) -> Self {
+        Self {
+            celsius: fahrenheit * 5.0 / 9.0,
+        }
+    }
+
+    /// Returns the difference in degrees Celsius.
+    pub fn celsius(self) -> f32 {
+        self.celsius
+    }
+
+    /// Returns the difference in degrees Fahrenheit.
+    pub fn fahrenheit(self) -> f32 {
+        self.celsius * 9.0 / 5.0
+    }
+
+    /// Reinterprets a value that was deserialized as-is from a response reported in `unit`.
+    pub(crate) fn normalize(self, unit: &TemperatureUnit) -> Self {
+        match unit {
+            TemperatureUnit::Fahrenheit => Self::from_fahrenheit(self.celsius),
+            _ => self,
+        }
+    }
+}
+
+impl fmt::Display for TemperatureDelta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} °C", self.celsius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fahrenheit_is_normalized_to_celsius() {
+        let temperature = Temperature::from_celsius(68.0).normalize(&TemperatureUnit::Fahrenheit);
+        let delta = TemperatureDelta::from_celsius(9.0).normalize(&TemperatureUnit::Fahrenheit);
+
+        assert_eq!(temperature.celsius(), 20.0);
+        assert_eq!(delta.celsius(), 5.0);
+        assert_eq!((temperature + delta).fahrenheit(), 77.0);
+        assert_eq!(
+            Temperature::from_celsius(21.5).normalize(&TemperatureUnit::Celsius),
+            Temperature::from_celsius(21.5)
+        );
+    }
+}
@@ -3,7 +3,8 @@ use serde::Deserialize;
 use super::TapoResponseExt;
 
 /// Trigger logs result.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct TriggerLogsResult<T> {
     /// The `id` of the most recent log item that is returned.
     pub start_id: u64,
@@ -14,3 +15,11 @@ pub struct TriggerLogsResult<T> {
 }
 
 impl<T> TapoResponseExt for TriggerLogsResult<T> {}
+
+/// Log item returned by the trigger logs of a child device.
+pub trait TriggerLog {
+    /// The `id` of the log item. It increases with each new log item.
+    fn id(&self) -> u64;
+    /// The time of the event as a Unix timestamp in seconds.
+    fn timestamp(&self) -> u64;
+}
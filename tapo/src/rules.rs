@@ -0,0 +1,19 @@
+//! A small automation engine: rules that run actions on the devices of a [`crate::manager::DeviceManager`]
+//! when a [`crate::events::DeviceEvent`] matches their trigger and their conditions are met.
+//!
+//! Automations that need to keep state between the events are shipped as ready-made policies,
+//! e.g. the [`MotionDimmer`], the [`EntryAlert`] and the [`Dehumidifier`].
+//!
+//! Requires the `rules` feature.
+
+mod dehumidifier;
+mod entry_alert;
+mod motion_dimmer;
+mod rule;
+mod rule_engine;
+
+pub use dehumidifier::*;
+pub use entry_alert::*;
+pub use motion_dimmer::*;
+pub use rule::*;
+pub use rule_engine::*;
@@ -0,0 +1,487 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::error::Error;
+use crate::manager::DeviceManager;
+use crate::readings::{Reading, ReadingKind};
+
+/// Runs a dehumidifier plugged into a plug from the humidity readings of a sensor, e.g. a T310:
+/// the plug is turned *on* when the humidity rises above a threshold, and *off* when it falls below a lower one.
+///
+/// To protect the compressor, the plug stays *on* for at least [`Dehumidifier::with_min_run`]
+/// and *off* for at least [`Dehumidifier::with_min_off`] once switched, so a reading that crosses
+/// a threshold too early only takes effect once that time has passed.
+///
+/// Like the [`crate::rules::RuleEngine`], it doesn't poll the sensor itself:
+/// its readings are fed to [`RunningDehumidifier::handle_reading`], e.g. from the polls of the hub it's paired with.
+/// The plug is left as it is until the first reading that crosses a threshold.
+/// A switch that fails is retried at the next reading, rather than right away.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use chrono::Utc;
+/// # use tapo::manager::DeviceManager;
+/// # use tapo::readings::{Reading, ReadingKind};
+/// # use tapo::rules::Dehumidifier;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+///
+/// let dehumidifier = Dehumidifier::new(manager, "basement-sensor", "basement-dehumidifier")
+///     .with_thresholds(65.0, 55.0)
+///     .start()?;
+///
+/// let reading = Reading::new("basement-sensor", Utc::now(), ReadingKind::HumidityPercent, 68.0);
+/// dehumidifier.handle_reading(&reading);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Dehumidifier {
+    manager: DeviceManager,
+    sensor: String,
+    plug: String,
+    on_above: f64,
+    off_below: f64,
+    min_run: Duration,
+    min_off: Duration,
+}
+
+/// The plug of a started [`Dehumidifier`]. It is left as it is once it is dropped.
+#[derive(Debug)]
+pub struct RunningDehumidifier {
+    task: JoinHandle<()>,
+    sensor: String,
+    control: Arc<Mutex<Control>>,
+    wake: Arc<Notify>,
+}
+
+impl Dehumidifier {
+    /// Returns a [`Dehumidifier`] that turns the plug *on* above 60% and *off* below 50%,
+    /// and keeps it *on* for at least 10 minutes and *off* for at least 5 minutes.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager of the plug
+    /// * `sensor` - the name of the sensor, as found in its readings
+    /// * `plug` - the name of the plug in the [`DeviceManager`]
+    pub fn new(manager: DeviceManager, sensor: impl Into<String>, plug: impl Into<String>) -> Self {
+        Self {
+            manager,
+            sensor: sensor.into(),
+            plug: plug.into(),
+            on_above: 60.0,
+            off_below: 50.0,
+            min_run: Duration::from_secs(10 * 60),
+            min_off: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// Sets the relative humidity, in percent, above which the plug is turned *on*
+    /// and the one below which it is turned *off*, lower than the first.
+    pub fn with_thresholds(mut self, on_above: f64, off_below: f64) -> Self {
+        self.on_above = on_above;
+        self.off_below = off_below;
+        self
+    }
+
+    /// Sets the minimum time the plug stays *on* once turned *on*. Defaults to 10 minutes.
+    pub fn with_min_run(mut self, min_run: Duration) -> Self {
+        self.min_run = min_run;
+        self
+    }
+
+    /// Sets the minimum time the plug stays *off* once turned *off*. Defaults to 5 minutes.
+    pub fn with_min_off(mut self, min_off: Duration) -> Self {
+        self.min_off = min_off;
+        self
+    }
+
+    /// Validates the configuration and starts the dehumidifier.
+    /// Must be called from within a Tokio runtime.
+    pub fn start(self) -> Result<RunningDehumidifier, Error> {
+        let invalid = |field: &str, message: &str| Error::Validation {
+            field: field.to_string(),
+            message: message.to_string(),
+        };
+
+        let percentage = 0.0..=100.0;
+        if !percentage.contains(&self.on_above) {
+            return Err(invalid("on_above", "must be between 0 and 100"));
+        }
+        if !percentage.contains(&self.off_below) {
+            return Err(invalid("off_below", "must be between 0 and 100"));
+        }
+        if self.off_below >= self.on_above {
+            return Err(invalid("off_below", "must be lower than `on_above`"));
+        }
+
+        let control = Arc::new(Mutex::new(Control {
+            on_above: self.on_above,
+            off_below: self.off_below,
+            min_run: self.min_run,
+            min_off: self.min_off,
+            humidity: None,
+            running: None,
+            switched_at: None,
+            failed: false,
+        }));
+        let wake = Arc::new(Notify::new());
+        let sensor = self.sensor.clone();
+        let task = tokio::spawn(self.run(control.clone(), wake.clone()));
+
+        Ok(RunningDehumidifier {
+            task,
+            sensor,
+            control,
+            wake,
+        })
+    }
+
+    async fn run(self, control: Arc<Mutex<Control>>, wake: Arc<Notify>) {
+        let device = self.manager.device(self.plug.as_str());
+
+        loop {
+            let deadline = control
+                .lock()
+                .expect("the lock is never poisoned")
+                .next_deadline();
+            match deadline {
+                Some(deadline) => {
+                    futures_lite::future::or(tokio::time::sleep_until(deadline), wake.notified())
+                        .await
+                }
+                None => wake.notified().await,
+            }
+
+            let Some((on, previous)) = control
+                .lock()
+                .expect("the lock is never poisoned")
+                .update(Instant::now())
+            else {
+                continue;
+            };
+
+            info!(
+                "Turning `{}` {} for the humidity of `{}`",
+                self.plug,
+                if on { "on" } else { "off" },
+                self.sensor,
+            );
+            let result = if on {
+                device.on().await
+            } else {
+                device.off().await
+            };
+
+            if let Err(err) = result {
+                if err.is_maintenance() {
+                    debug!("Not switching `{}`: {err}", self.plug);
+                } else {
+                    warn!("Failed to switch `{}`: {err:?}", self.plug);
+                }
+                control
+                    .lock()
+                    .expect("the lock is never poisoned")
+                    .revert(previous);
+            }
+        }
+    }
+}
+
+impl RunningDehumidifier {
+    /// Handles a reading. The humidity readings of the sensor switch the plug
+    /// once they cross a threshold and the minimum time is over.
+    /// Returns `true` if the reading is such a humidity reading.
+    pub fn handle_reading(&self, reading: &Reading) -> bool {
+        if reading.device != self.sensor || reading.kind != ReadingKind::HumidityPercent {
+            return false;
+        }
+
+        self.control
+            .lock()
+            .expect("the lock is never poisoned")
+            .set_humidity(reading.value);
+        self.wake.notify_one();
+
+        true
+    }
+
+    /// Returns whether the plug has last been turned *on*, or `None` if it hasn't been switched yet.
+    pub fn is_running(&self) -> Option<bool> {
+        self.control
+            .lock()
+            .expect("the lock is never poisoned")
+            .running
+    }
+
+    /// Stops the dehumidifier. The plug is left as it is.
+    pub fn stop(self) {}
+}
+
+impl Drop for RunningDehumidifier {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The state of a [`Control`] before a switch, restored if the switch fails.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Switch {
+    running: Option<bool>,
+    switched_at: Option<Instant>,
+}
+
+/// The switching decisions of a [`Dehumidifier`], apart from the plug.
+#[derive(Debug)]
+struct Control {
+    on_above: f64,
+    off_below: f64,
+    min_run: Duration,
+    min_off: Duration,
+    humidity: Option<f64>,
+    running: Option<bool>,
+    switched_at: Option<Instant>,
+    /// Whether the last switch failed, so that it isn't retried before the next reading.
+    failed: bool,
+}
+
+impl Control {
+    /// Sets the latest humidity, which allows a failed switch to be retried.
+    fn set_humidity(&mut self, humidity: f64) {
+        self.humidity = Some(humidity);
+        self.failed = false;
+    }
+
+    /// Returns whether the plug should be *on* for the latest humidity,
+    /// or `None` if it's between the thresholds or unknown.
+    fn wanted(&self) -> Option<bool> {
+        match self.humidity? {
+            humidity if humidity > self.on_above => Some(true),
+            humidity if humidity < self.off_below => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns when the plug can be switched again, if it's switched at all.
+    fn earliest_switch(&self) -> Option<Instant> {
+        let min = match self.running? {
+            true => self.min_run,
+            false => self.min_off,
+        };
+
+        Some(self.switched_at? + min)
+    }
+
+    /// Returns when [`Control::update`] has to be called next, unless a reading is handled before,
+    /// or `None` if the plug doesn't have to be switched.
+    fn next_deadline(&self) -> Option<Instant> {
+        let wanted = self.wanted()?;
+        if self.failed || self.running == Some(wanted) {
+            return None;
+        }
+
+        self.earliest_switch()
+    }
+
+    /// Returns whether to turn the plug *on* or *off* at `now`, if at all, along with the state before the switch.
+    fn update(&mut self, now: Instant) -> Option<(bool, Switch)> {
+        let wanted = self.wanted()?;
+        if self.failed
+            || self.running == Some(wanted)
+            || self
+                .earliest_switch()
+                .is_some_and(|earliest| now < earliest)
+        {
+            return None;
+        }
+
+        let previous = Switch {
+            running: self.running,
+            switched_at: self.switched_at,
+        };
+        self.running = Some(wanted);
+        self.switched_at = Some(now);
+
+        Some((wanted, previous))
+    }
+
+    /// Restores the state before a switch that failed. It is retried at the next reading.
+    fn revert(&mut self, previous: Switch) {
+        self.running = previous.running;
+        self.switched_at = previous.switched_at;
+        self.failed = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plug_follows_the_humidity() {
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let mut control = Control {
+            on_above: 60.0,
+            off_below: 50.0,
+            min_run: Duration::from_secs(600),
+            min_off: Duration::from_secs(300),
+            humidity: None,
+            running: None,
+            switched_at: None,
+            failed: false,
+        };
+
+        assert_eq!(control.update(at(0)), None);
+        control.set_humidity(55.0);
+        assert_eq!(control.update(at(0)), None, "the plug is left as it is");
+
+        control.set_humidity(65.0);
+        assert!(matches!(control.update(at(10)), Some((true, _))));
+        assert_eq!(control.update(at(20)), None);
+
+        control.set_humidity(45.0);
+        assert_eq!(control.next_deadline(), Some(at(610)));
+        assert_eq!(
+            control.update(at(300)),
+            None,
+            "the plug runs for 10 minutes"
+        );
+        assert!(matches!(control.update(at(610)), Some((false, _))));
+
+        control.set_humidity(70.0);
+        assert_eq!(control.next_deadline(), Some(at(910)));
+        assert_eq!(
+            control.update(at(700)),
+            None,
+            "the plug rests for 5 minutes"
+        );
+
+        let (on, previous) = control.update(at(910)).unwrap();
+        assert!(on);
+        control.revert(previous);
+        assert_eq!(control.running, Some(false));
+        assert_eq!(
+            control.next_deadline(),
+            None,
+            "a failed switch waits for the next reading"
+        );
+        assert_eq!(control.update(at(920)), None);
+
+        control.set_humidity(70.0);
+        assert_eq!(control.next_deadline(), Some(at(910)));
+        assert!(matches!(control.update(at(930)), Some((true, _))));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn plug_is_switched_by_the_readings() {
+        use chrono::Utc;
+
+        use crate::manager::DeviceKind;
+        use crate::simulator::FakeDevice;
+        use crate::ApiClient;
+
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        let plug = FakeDevice::plug();
+        plug.set("device_on", false);
+        manager
+            .register_fake("dehumidifier", DeviceKind::Plug, plug.clone())
+            .await
+            .unwrap();
+
+        let dehumidifier = Dehumidifier::new(manager.clone(), "sensor", "dehumidifier")
+            .with_min_run(Duration::from_millis(300))
+            .with_min_off(Duration::ZERO)
+            .start()
+            .unwrap();
+        assert!(matches!(
+            Dehumidifier::new(manager, "sensor", "dehumidifier")
+                .with_thresholds(50.0, 60.0)
+                .start(),
+            Err(Error::Validation { field, .. }) if field == "off_below"
+        ));
+
+        let humidity =
+            |value| Reading::new("sensor", Utc::now(), ReadingKind::HumidityPercent, value);
+        assert!(!dehumidifier.handle_reading(&Reading::new(
+            "sensor",
+            Utc::now(),
+            ReadingKind::TemperatureCelsius,
+            70.0
+        )));
+
+        assert!(dehumidifier.handle_reading(&humidity(70.0)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(plug.device_info()["device_on"], true);
+
+        dehumidifier.handle_reading(&humidity(40.0));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            plug.device_info()["device_on"],
+            true,
+            "the plug runs for 300 ms"
+        );
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(plug.device_info()["device_on"], false);
+        assert_eq!(dehumidifier.is_running(), Some(false));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn failed_switch_is_retried_at_the_next_reading() {
+        use chrono::Utc;
+
+        use crate::manager::DeviceKind;
+        use crate::simulator::FakeDevice;
+        use crate::ApiClient;
+
+        let manager = DeviceManager::builder(ApiClient::new("username", "password").unwrap())
+            .max_retries(0)
+            .build();
+        let plug = FakeDevice::plug();
+        plug.set("device_on", false);
+        manager
+            .register_fake("dehumidifier", DeviceKind::Plug, plug.clone())
+            .await
+            .unwrap();
+
+        let dehumidifier = Dehumidifier::new(manager, "sensor", "dehumidifier")
+            .with_min_run(Duration::ZERO)
+            .with_min_off(Duration::ZERO)
+            .start()
+            .unwrap();
+        let humidity =
+            |value| Reading::new("sensor", Utc::now(), ReadingKind::HumidityPercent, value);
+
+        dehumidifier.handle_reading(&humidity(70.0));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(plug.device_info()["device_on"], true);
+
+        plug.set_reachable(false);
+        dehumidifier.handle_reading(&humidity(40.0));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(dehumidifier.is_running(), Some(true));
+        let requests = plug.requests().len();
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(
+            plug.requests().len(),
+            requests,
+            "the switch isn't retried before the next reading"
+        );
+
+        plug.set_reachable(true);
+        dehumidifier.handle_reading(&humidity(40.0));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(plug.device_info()["device_on"], false);
+        assert_eq!(dehumidifier.is_running(), Some(false));
+    }
+}
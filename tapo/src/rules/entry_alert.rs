@@ -0,0 +1,473 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::error::Error;
+use crate::events::{DeviceEvent, SensorTrigger};
+use crate::manager::DeviceManager;
+use crate::requests::Ringtone;
+use crate::rules::RuleAction;
+
+/// How long the T110 contact sensors wait before reporting [`SensorTrigger::KeepOpen`].
+const KEEP_OPEN_DELAY: Duration = Duration::from_secs(60);
+
+/// Alerts when a door or a window with a contact sensor, e.g. a T110, is opened:
+/// the hub plays a short chime and the [`EntryAlert::on_open`] actions run, e.g. turning on the hallway light.
+/// If it's still open after [`EntryAlert::alarm_after`], the alarm of the hub goes off
+/// and the [`EntryAlert::on_alarm`] actions run, until all the sensors are closed again.
+///
+/// Like the [`crate::rules::RuleEngine`], it doesn't watch the sensors itself:
+/// their events are fed to [`RunningEntryAlert::handle`], e.g. from their trigger logs.
+///
+/// The alarm plays the ringtone of the alarm configuration of the hub, see [`crate::HubHandler::set_alarm_ringtone`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use tapo::events::{DeviceEvent, SensorTrigger};
+/// # use tapo::manager::DeviceManager;
+/// # use tapo::rules::{EntryAlert, RuleAction};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+///
+/// let alert = EntryAlert::new(manager, "hub")
+///     .sensor("front-door")
+///     .sensor("back-door")
+///     .on_open(RuleAction::TurnOn {
+///         device: "hallway-light".to_string(),
+///     })
+///     .alarm_after(Duration::from_secs(5 * 60))
+///     .start()?;
+///
+/// let event = DeviceEvent::SensorTriggered {
+///     trigger: SensorTrigger::Open,
+///     timestamp: 1700000000,
+/// };
+/// alert.handle("front-door", &event);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct EntryAlert {
+    manager: DeviceManager,
+    hub: String,
+    sensors: BTreeSet<String>,
+    chime: Option<(Ringtone, u64)>,
+    alarm_after: Option<Duration>,
+    open_actions: Vec<RuleAction>,
+    alarm_actions: Vec<RuleAction>,
+}
+
+/// The sensors of a started [`EntryAlert`]. The alarm, if it's playing, isn't stopped once it is dropped.
+#[derive(Debug)]
+pub struct RunningEntryAlert {
+    task: JoinHandle<()>,
+    sensors: BTreeSet<String>,
+    entries: Arc<Mutex<Entries>>,
+    wake: Arc<Notify>,
+}
+
+impl EntryAlert {
+    /// Returns an [`EntryAlert`] without sensors, which chimes with [`Ringtone::DoorbellRing`] 1 for 2 seconds
+    /// and never raises the alarm.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager of the hub and of the devices of the actions
+    /// * `hub` - the name of the hub in the [`DeviceManager`]
+    pub fn new(manager: DeviceManager, hub: impl Into<String>) -> Self {
+        Self {
+            manager,
+            hub: hub.into(),
+            sensors: BTreeSet::new(),
+            chime: Some((Ringtone::DoorbellRing(1), 2)),
+            alarm_after: None,
+            open_actions: Vec::new(),
+            alarm_actions: Vec::new(),
+        }
+    }
+
+    /// Adds a contact sensor, by the name that its events are reported under.
+    pub fn sensor(mut self, sensor: impl Into<String>) -> Self {
+        self.sensors.insert(sensor.into());
+        self
+    }
+
+    /// Sets the chime played when a sensor is opened, or `None` for no chime.
+    ///
+    /// # Arguments
+    ///
+    /// * `chime` - one of the ringtones of [`crate::HubHandler::get_supported_ringtones`],
+    ///   and how long it plays for, in seconds
+    pub fn with_chime(mut self, chime: Option<(Ringtone, u64)>) -> Self {
+        self.chime = chime;
+        self
+    }
+
+    /// Raises the alarm when a sensor has been open for longer than `open_for`.
+    pub fn alarm_after(mut self, open_for: Duration) -> Self {
+        self.alarm_after = Some(open_for);
+        self
+    }
+
+    /// Adds an action run when a sensor is opened. The actions run in the order they have been added.
+    pub fn on_open(mut self, action: RuleAction) -> Self {
+        self.open_actions.push(action);
+        self
+    }
+
+    /// Adds an action run when the alarm is raised. The actions run in the order they have been added.
+    pub fn on_alarm(mut self, action: RuleAction) -> Self {
+        self.alarm_actions.push(action);
+        self
+    }
+
+    /// Validates the configuration and starts the alert. Must be called from within a Tokio runtime.
+    pub fn start(self) -> Result<RunningEntryAlert, Error> {
+        let invalid = |field: &str, message: &str| Error::Validation {
+            field: field.to_string(),
+            message: message.to_string(),
+        };
+
+        if self.sensors.is_empty() {
+            return Err(invalid("sensors", "must contain at least one sensor"));
+        }
+        if self.alarm_after.is_some_and(|open_for| open_for.is_zero()) {
+            return Err(invalid("alarm_after", "must be greater than 0"));
+        }
+        for action in self.open_actions.iter().chain(&self.alarm_actions) {
+            action.validate()?;
+        }
+
+        let entries = Arc::new(Mutex::new(Entries {
+            alarm_after: self.alarm_after,
+            open_since: BTreeMap::new(),
+            alarmed: BTreeSet::new(),
+            pending: Vec::new(),
+        }));
+        let wake = Arc::new(Notify::new());
+        let sensors = self.sensors.clone();
+        let task = tokio::spawn(self.run(entries.clone(), wake.clone()));
+
+        Ok(RunningEntryAlert {
+            task,
+            sensors,
+            entries,
+            wake,
+        })
+    }
+
+    async fn run(self, entries: Arc<Mutex<Entries>>, wake: Arc<Notify>) {
+        loop {
+            let deadline = entries
+                .lock()
+                .expect("the lock is never poisoned")
+                .next_deadline();
+            match deadline {
+                Some(deadline) => {
+                    futures_lite::future::or(tokio::time::sleep_until(deadline), wake.notified())
+                        .await
+                }
+                None => wake.notified().await,
+            }
+
+            let steps = entries
+                .lock()
+                .expect("the lock is never poisoned")
+                .update(Instant::now());
+
+            for step in steps {
+                if let Err(err) = self.apply(&step).await {
+                    if err.is_maintenance() {
+                        debug!("Not applying {step:?}: {err}");
+                    } else {
+                        warn!("Failed to apply {step:?}: {err:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    async fn apply(&self, step: &Step) -> Result<(), Error> {
+        let hub = self.manager.device(self.hub.as_str());
+
+        match step {
+            Step::Chime(sensor) => {
+                info!("`{sensor}` has been opened");
+                let chime = match &self.chime {
+                    Some((ringtone, duration)) => {
+                        hub.play_alarm(Some(ringtone.clone()), Some(*duration))
+                            .await
+                    }
+                    None => Ok(()),
+                };
+                let actions = run_all(&self.manager, &self.open_actions).await;
+                chime.and(actions)
+            }
+            Step::Alarm(sensor) => {
+                info!("Raising the alarm, `{sensor}` has been left open");
+                let alarm = hub.play_alarm(None, None).await;
+                let actions = run_all(&self.manager, &self.alarm_actions).await;
+                alarm.and(actions)
+            }
+            Step::Silence => {
+                info!("Stopping the alarm, all the sensors are closed");
+                hub.stop_alarm().await
+            }
+        }
+    }
+}
+
+/// Runs all the `actions`, even when some fail, and returns the first error.
+async fn run_all(manager: &DeviceManager, actions: &[RuleAction]) -> Result<(), Error> {
+    let mut first_error = None;
+
+    for action in actions {
+        if let Err(err) = action.run(manager).await {
+            first_error.get_or_insert(err);
+        }
+    }
+
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+impl RunningEntryAlert {
+    /// Handles an event reported by `device`. Only the events of the contact sensors are taken into account.
+    /// Returns `true` if the event is such an event.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the device that has reported the event
+    /// * `event` - the event
+    pub fn handle(&self, device: &str, event: &DeviceEvent) -> bool {
+        let DeviceEvent::SensorTriggered { trigger, .. } = event else {
+            return false;
+        };
+        if !self.sensors.contains(device) {
+            return false;
+        }
+
+        let now = Instant::now();
+        let mut entries = self.entries.lock().expect("the lock is never poisoned");
+        match trigger {
+            SensorTrigger::Open => entries.opened(device, now, true),
+            // The opening has been missed, too long ago to chime.
+            SensorTrigger::KeepOpen => entries.opened(
+                device,
+                now.checked_sub(KEEP_OPEN_DELAY).unwrap_or(now),
+                false,
+            ),
+            SensorTrigger::Close => entries.closed(device),
+            _ => return false,
+        }
+        self.wake.notify_one();
+
+        true
+    }
+
+    /// Returns the names of the sensors that are open, as far as their events tell.
+    pub fn open_sensors(&self) -> Vec<String> {
+        self.entries
+            .lock()
+            .expect("the lock is never poisoned")
+            .open_since
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    /// Stops the alert. The alarm, if it's playing, isn't stopped.
+    pub fn stop(self) {}
+}
+
+impl Drop for RunningEntryAlert {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// What to do with the hub and the devices of the actions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Chime(String),
+    Alarm(String),
+    Silence,
+}
+
+/// The alert decisions of an [`EntryAlert`], apart from the devices.
+#[derive(Debug)]
+struct Entries {
+    alarm_after: Option<Duration>,
+    /// When each open sensor has been opened.
+    open_since: BTreeMap<String, Instant>,
+    /// The open sensors that have raised the alarm.
+    alarmed: BTreeSet<String>,
+    /// The steps caused by the events, returned by the next [`Entries::update`].
+    pending: Vec<Step>,
+}
+
+impl Entries {
+    /// Records that `sensor` has been open since `since`, unless it's known to be open already.
+    fn opened(&mut self, sensor: &str, since: Instant, chime: bool) {
+        if self.open_since.contains_key(sensor) {
+            return;
+        }
+
+        self.open_since.insert(sensor.to_string(), since);
+        if chime {
+            self.pending.push(Step::Chime(sensor.to_string()));
+        }
+    }
+
+    /// Records that `sensor` has been closed, which silences the alarm once all the sensors that have raised it are closed.
+    fn closed(&mut self, sensor: &str) {
+        self.open_since.remove(sensor);
+
+        if self.alarmed.remove(sensor) && self.alarmed.is_empty() {
+            self.pending.push(Step::Silence);
+        }
+    }
+
+    /// Returns when [`Entries::update`] has to be called next, unless an event is handled before,
+    /// or `None` if no sensor can raise the alarm.
+    fn next_deadline(&self) -> Option<Instant> {
+        let alarm_after = self.alarm_after?;
+
+        self.open_since
+            .iter()
+            .filter(|(sensor, _)| !self.alarmed.contains(*sensor))
+            .map(|(_, since)| *since + alarm_after)
+            .min()
+    }
+
+    /// Returns the steps caused by the events handled since the last call, and by the sensors left open at `now`.
+    fn update(&mut self, now: Instant) -> Vec<Step> {
+        let mut steps = std::mem::take(&mut self.pending);
+
+        if let Some(alarm_after) = self.alarm_after {
+            for (sensor, since) in &self.open_since {
+                if now.saturating_duration_since(*since) >= alarm_after
+                    && self.alarmed.insert(sensor.clone())
+                {
+                    steps.push(Step::Alarm(sensor.clone()));
+                }
+            }
+        }
+
+        steps
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alarm_is_raised_while_left_open() {
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let mut entries = Entries {
+            alarm_after: Some(Duration::from_secs(300)),
+            open_since: BTreeMap::new(),
+            alarmed: BTreeSet::new(),
+            pending: Vec::new(),
+        };
+
+        entries.opened("front-door", at(0), true);
+        entries.opened("front-door", at(10), true);
+        assert_eq!(
+            entries.update(at(10)),
+            vec![Step::Chime("front-door".to_string())]
+        );
+        assert_eq!(entries.next_deadline(), Some(at(300)));
+
+        entries.opened("back-door", at(100), true);
+        entries.closed("back-door");
+        assert_eq!(
+            entries.update(at(200)),
+            vec![Step::Chime("back-door".to_string())]
+        );
+
+        assert_eq!(
+            entries.update(at(300)),
+            vec![Step::Alarm("front-door".to_string())]
+        );
+        assert_eq!(entries.next_deadline(), None);
+        assert_eq!(entries.update(at(400)), vec![]);
+
+        entries.closed("front-door");
+        assert_eq!(entries.update(at(500)), vec![Step::Silence]);
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn hub_chimes_and_raises_the_alarm() {
+        use serde_json::json;
+
+        use crate::manager::DeviceKind;
+        use crate::simulator::FakeDevice;
+        use crate::ApiClient;
+
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        let hub = FakeDevice::new(json!({ "model": "H100", "in_alarm": false }))
+            .with_components(["device", "alarm"]);
+        hub.on_request("play_alarm", |device_info, _| {
+            device_info["in_alarm"] = true.into()
+        });
+        hub.on_request("stop_alarm", |device_info, _| {
+            device_info["in_alarm"] = false.into()
+        });
+        let light = FakeDevice::light();
+        manager
+            .register_fake("hub", DeviceKind::Hub, hub.clone())
+            .await
+            .unwrap();
+        manager
+            .register_fake("hallway", DeviceKind::ColorLight, light.clone())
+            .await
+            .unwrap();
+
+        let alert = EntryAlert::new(manager, "hub")
+            .sensor("front-door")
+            .on_open(RuleAction::TurnOn {
+                device: "hallway".to_string(),
+            })
+            .alarm_after(Duration::from_millis(300))
+            .start()
+            .unwrap();
+
+        let event = |trigger| DeviceEvent::SensorTriggered {
+            trigger,
+            timestamp: 0,
+        };
+        assert!(alert.handle("front-door", &event(SensorTrigger::Open)));
+        assert!(!alert.handle("front-door", &event(SensorTrigger::Motion)));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            hub.requests()[0].params,
+            json!({ "alarm_type": "Doorbell Ring 1", "alarm_duration": 2 })
+        );
+        assert_eq!(light.device_info()["device_on"], true);
+
+        // The requests to the same device are spaced by the rate limit of the manager.
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(hub.requests().len(), 2);
+        assert_eq!(hub.requests()[1].params, json!({}));
+        assert_eq!(hub.device_info()["in_alarm"], true);
+
+        assert!(alert.handle("front-door", &event(SensorTrigger::Close)));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(hub.device_info()["in_alarm"], false);
+        assert!(alert.open_sensors().is_empty());
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{debug, info, warn};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::error::Error;
+use crate::events::DeviceEvent;
+use crate::manager::{DeviceHandle, DeviceManager};
+use crate::requests::{Brightness, IntoLightProperty};
+use crate::rules::EventPattern;
+
+/// Dims lights when their motion sensors, e.g. T100s, haven't detected motion for a while, turns them *off*
+/// after a longer while, and restores them as they were on the next motion.
+///
+/// Like the [`crate::rules::RuleEngine`], it doesn't watch the sensors itself:
+/// their events are fed to [`RunningMotionDimmer::handle`], e.g. from their trigger logs.
+///
+/// Only the lights that are *on* when they are dimmed are turned *off* and restored later,
+/// with the *brightness* and color they had before being dimmed.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use tapo::events::{DeviceEvent, SensorTrigger};
+/// # use tapo::manager::DeviceManager;
+/// # use tapo::rules::MotionDimmer;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+///
+/// let dimmer = MotionDimmer::new(
+///     manager,
+///     Duration::from_secs(10 * 60),
+///     Duration::from_secs(30 * 60),
+/// )
+/// .sensor("living-room-sensor")
+/// .light("living-room-lamp")
+/// .light("living-room-strip")
+/// .start()?;
+///
+/// let event = DeviceEvent::SensorTriggered {
+///     trigger: SensorTrigger::Motion,
+///     timestamp: 1700000000,
+/// };
+/// dimmer.handle("living-room-sensor", &event);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MotionDimmer {
+    manager: DeviceManager,
+    sensors: BTreeSet<String>,
+    lights: Vec<String>,
+    dim_after: Duration,
+    off_after: Duration,
+    dimmed_brightness: u8,
+}
+
+/// The lights of a started [`MotionDimmer`]. They are left as they are once it is dropped.
+#[derive(Debug)]
+pub struct RunningMotionDimmer {
+    task: JoinHandle<()>,
+    sensors: BTreeSet<String>,
+    dimming: Arc<Mutex<Dimming>>,
+    wake: Arc<Notify>,
+}
+
+impl MotionDimmer {
+    /// Returns a [`MotionDimmer`] without sensors or lights, which dims the lights to 10%.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager of the lights
+    /// * `dim_after` - the time without motion after which the lights are dimmed
+    /// * `off_after` - the time without motion after which the lights are turned *off*, longer than `dim_after`
+    pub fn new(manager: DeviceManager, dim_after: Duration, off_after: Duration) -> Self {
+        Self {
+            manager,
+            sensors: BTreeSet::new(),
+            lights: Vec::new(),
+            dim_after,
+            off_after,
+            dimmed_brightness: 10,
+        }
+    }
+
+    /// Adds a motion sensor, by its name in the [`DeviceManager`].
+    pub fn sensor(mut self, sensor: impl Into<String>) -> Self {
+        self.sensors.insert(sensor.into());
+        self
+    }
+
+    /// Adds a light, by its name in the [`DeviceManager`].
+    pub fn light(mut self, light: impl Into<String>) -> Self {
+        self.lights.push(light.into());
+        self
+    }
+
+    /// Sets the *brightness* of the dimmed lights. The lights that are already dimmer are left as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `brightness` - between 1 and 100, see [`Brightness::RANGE`]
+    pub fn with_dimmed_brightness(
+        mut self,
+        brightness: impl IntoLightProperty<Brightness>,
+    ) -> Result<Self, Error> {
+        self.dimmed_brightness = Brightness::try_from(brightness.into_raw())?.get();
+        Ok(self)
+    }
+
+    /// Validates the configuration and starts the dimmer, as if motion had just been detected.
+    /// Must be called from within a Tokio runtime.
+    pub fn start(self) -> Result<RunningMotionDimmer, Error> {
+        let invalid = |field: &str, message: &str| Error::Validation {
+            field: field.to_string(),
+            message: message.to_string(),
+        };
+
+        if self.sensors.is_empty() {
+            return Err(invalid("sensors", "must contain at least one sensor"));
+        }
+        if self.lights.is_empty() {
+            return Err(invalid("lights", "must contain at least one light"));
+        }
+        if self.off_after <= self.dim_after {
+            return Err(invalid("off_after", "must be longer than `dim_after`"));
+        }
+
+        let dimming = Arc::new(Mutex::new(Dimming {
+            dim_after: self.dim_after,
+            off_after: self.off_after,
+            last_motion: Instant::now(),
+            phase: Phase::Active,
+        }));
+        let wake = Arc::new(Notify::new());
+        let sensors = self.sensors.clone();
+        let task = tokio::spawn(self.run(dimming.clone(), wake.clone()));
+
+        Ok(RunningMotionDimmer {
+            task,
+            sensors,
+            dimming,
+            wake,
+        })
+    }
+
+    async fn run(self, dimming: Arc<Mutex<Dimming>>, wake: Arc<Notify>) {
+        let mut saved = BTreeMap::new();
+
+        loop {
+            let deadline = dimming
+                .lock()
+                .expect("the lock is never poisoned")
+                .next_deadline();
+            match deadline {
+                Some(deadline) => {
+                    futures_lite::future::or(tokio::time::sleep_until(deadline), wake.notified())
+                        .await
+                }
+                None => wake.notified().await,
+            }
+
+            let Some(step) = dimming
+                .lock()
+                .expect("the lock is never poisoned")
+                .update(Instant::now())
+            else {
+                continue;
+            };
+
+            for name in &self.lights {
+                let device = self.manager.device(name.as_str());
+
+                let result = match step {
+                    Step::Dim => self.dim(&device, &mut saved).await,
+                    Step::Off if saved.contains_key(name) => device.off().await,
+                    Step::Off => Ok(()),
+                    Step::Restore => match saved.remove(name) {
+                        Some(light) => light.restore(&device).await,
+                        None => Ok(()),
+                    },
+                };
+
+                if let Err(err) = result {
+                    if err.is_maintenance() {
+                        debug!("Not applying {step:?} to `{name}`: {err}");
+                    } else {
+                        warn!("Failed to apply {step:?} to `{name}`: {err:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Saves the state of the light, if it's *on*, and dims it.
+    async fn dim(
+        &self,
+        device: &DeviceHandle,
+        saved: &mut BTreeMap<String, SavedLight>,
+    ) -> Result<(), Error> {
+        let device_info = device.get_device_info_json().await?;
+        if device_info["device_on"] != true {
+            return Ok(());
+        }
+
+        let light = SavedLight::from_device_info(&device_info);
+        saved.insert(device.name().to_string(), light.clone());
+
+        if light
+            .brightness
+            .is_some_and(|brightness| brightness <= self.dimmed_brightness)
+        {
+            return Ok(());
+        }
+
+        info!("Dimming `{}` after no motion", device.name());
+        device.set_brightness(self.dimmed_brightness).await
+    }
+}
+
+impl RunningMotionDimmer {
+    /// Handles an event reported by `device`. The motion detected by one of the sensors
+    /// restores the dimmed lights and restarts the countdown.
+    /// Returns `true` if the event is such a motion.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the device that has reported the event
+    /// * `event` - the event
+    pub fn handle(&self, device: &str, event: &DeviceEvent) -> bool {
+        if !self.sensors.contains(device) || !EventPattern::Motion.matches(event) {
+            return false;
+        }
+
+        self.dimming
+            .lock()
+            .expect("the lock is never poisoned")
+            .last_motion = Instant::now();
+        self.wake.notify_one();
+
+        true
+    }
+
+    /// Stops the dimmer. The lights are left as they are.
+    pub fn stop(self) {}
+}
+
+impl Drop for RunningMotionDimmer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The state of a light before it has been dimmed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SavedLight {
+    brightness: Option<u8>,
+    color_temperature: Option<u16>,
+    hue_saturation: Option<(u16, u8)>,
+}
+
+impl SavedLight {
+    fn from_device_info(device_info: &serde_json::Value) -> Self {
+        let field = |name: &str| device_info[name].as_u64().filter(|value| *value > 0);
+
+        let color_temperature = field("color_temp").map(|value| value as u16);
+        let hue_saturation = match (color_temperature, field("hue"), field("saturation")) {
+            (None, Some(hue), Some(saturation)) => Some((hue as u16, saturation as u8)),
+            _ => None,
+        };
+
+        Self {
+            brightness: field("brightness").map(|value| value as u8),
+            color_temperature,
+            hue_saturation,
+        }
+    }
+
+    async fn restore(&self, device: &DeviceHandle) -> Result<(), Error> {
+        info!("Restoring `{}` on motion", device.name());
+
+        if let Some(color_temperature) = self.color_temperature {
+            device.set_color_temperature(color_temperature).await?;
+        }
+        if let Some((hue, saturation)) = self.hue_saturation {
+            device.set_hue_saturation(hue, saturation).await?;
+        }
+
+        match self.brightness {
+            Some(brightness) => device.set_brightness(brightness).await,
+            None => device.on().await,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Active,
+    Dimmed,
+    Off,
+}
+
+/// What to do with the lights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Dim,
+    Off,
+    Restore,
+}
+
+/// The dimming decisions of a [`MotionDimmer`], apart from the devices.
+#[derive(Debug)]
+struct Dimming {
+    dim_after: Duration,
+    off_after: Duration,
+    last_motion: Instant,
+    phase: Phase,
+}
+
+impl Dimming {
+    /// Returns when [`Dimming::update`] has to be called next, unless motion is detected before,
+    /// or `None` if the lights are *off*.
+    fn next_deadline(&self) -> Option<Instant> {
+        match self.phase {
+            Phase::Active => Some(self.last_motion + self.dim_after),
+            Phase::Dimmed => Some(self.last_motion + self.off_after),
+            Phase::Off => None,
+        }
+    }
+
+    /// Returns what to do with the lights at `now`.
+    fn update(&mut self, now: Instant) -> Option<Step> {
+        let idle = now.saturating_duration_since(self.last_motion);
+
+        let (phase, step) = match self.phase {
+            Phase::Active if idle >= self.dim_after => (Phase::Dimmed, Step::Dim),
+            Phase::Dimmed | Phase::Off if idle < self.dim_after => (Phase::Active, Step::Restore),
+            Phase::Dimmed if idle >= self.off_after => (Phase::Off, Step::Off),
+            _ => return None,
+        };
+        self.phase = phase;
+
+        Some(step)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lights_follow_the_motion() {
+        let start = Instant::now();
+        let at = |seconds| start + Duration::from_secs(seconds);
+        let mut dimming = Dimming {
+            dim_after: Duration::from_secs(600),
+            off_after: Duration::from_secs(1800),
+            last_motion: start,
+            phase: Phase::Active,
+        };
+
+        assert_eq!(dimming.update(at(300)), None);
+        assert_eq!(dimming.next_deadline(), Some(at(600)));
+        assert_eq!(dimming.update(at(600)), Some(Step::Dim));
+        assert_eq!(dimming.update(at(1200)), None);
+        assert_eq!(dimming.next_deadline(), Some(at(1800)));
+        assert_eq!(dimming.update(at(1800)), Some(Step::Off));
+        assert_eq!(dimming.next_deadline(), None);
+
+        dimming.last_motion = at(2000);
+        assert_eq!(dimming.update(at(2000)), Some(Step::Restore));
+        assert_eq!(dimming.update(at(2100)), None);
+
+        assert_eq!(
+            SavedLight::from_device_info(&serde_json::json!({
+                "brightness": 80, "color_temp": 0, "hue": 120, "saturation": 50,
+            })),
+            SavedLight {
+                brightness: Some(80),
+                color_temperature: None,
+                hue_saturation: Some((120, 50)),
+            }
+        );
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn lights_are_restored_on_motion() {
+        use crate::events::SensorTrigger;
+        use crate::manager::DeviceKind;
+        use crate::simulator::FakeDevice;
+        use crate::ApiClient;
+
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        let lamp = FakeDevice::light();
+        lamp.set("device_on", true);
+        lamp.set("brightness", 80);
+        manager
+            .register_fake("lamp", DeviceKind::ColorLight, lamp.clone())
+            .await
+            .unwrap();
+        manager
+            .register_fake("hallway", DeviceKind::ColorLight, FakeDevice::light())
+            .await
+            .unwrap();
+
+        let dimmer = MotionDimmer::new(
+            manager,
+            Duration::from_millis(100),
+            Duration::from_millis(600),
+        )
+        .sensor("sensor")
+        .light("lamp")
+        .light("hallway")
+        .start()
+        .unwrap();
+
+        // The requests to the same device are spaced by the rate limit of the manager.
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(lamp.device_info()["brightness"], 10);
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(lamp.device_info()["device_on"], false);
+
+        let motion = DeviceEvent::SensorTriggered {
+            trigger: SensorTrigger::Motion,
+            timestamp: 0,
+        };
+        assert!(!dimmer.handle("lamp", &motion));
+        assert!(dimmer.handle("sensor", &motion));
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(lamp.device_info()["device_on"], true);
+        assert_eq!(lamp.device_info()["brightness"], 80);
+        assert_eq!(lamp.device_info()["color_temp"], 2700);
+    }
+}
@@ -0,0 +1,343 @@
+use chrono::{DateTime, Local, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::events::{ButtonPress, DeviceEvent, SensorTrigger};
+use crate::manager::DeviceManager;
+use crate::requests::{must_be_within, Brightness};
+use crate::sun::Location;
+
+/// An automation run by the [`crate::rules::RuleEngine`].
+///
+/// # Example
+///
+/// ```rust
+/// # use chrono::{DateTime, Local, NaiveTime, Utc};
+/// # use tapo::rules::{Condition, EventPattern, Rule, RuleAction};
+/// let rule = Rule::new("Porch light on motion", "porch-sensor", EventPattern::Motion)
+///     .condition(Condition::TimeWindow {
+///         start: NaiveTime::from_hms_opt(19, 0, 0).unwrap(),
+///         end: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+///     })
+///     .action(RuleAction::TurnOn {
+///         device: "porch-light".to_string(),
+///     });
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    /// The name of the rule, used in the logs.
+    pub name: String,
+    /// The event that runs the rule.
+    pub trigger: Trigger,
+    /// The conditions that must all be met for the actions to run.
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    /// The actions, run in order.
+    pub actions: Vec<RuleAction>,
+}
+
+/// The event that runs a [`Rule`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trigger {
+    /// The name of the device that reports the event, as registered with the [`crate::manager::DeviceManager`].
+    pub device: String,
+    /// The kind of event.
+    pub event: EventPattern,
+}
+
+/// A kind of [`DeviceEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[allow(missing_docs)]
+pub enum EventPattern {
+    PowerOn,
+    PowerOff,
+    LightChanged,
+    Motion,
+    Open,
+    Close,
+    KeepOpen,
+    WaterLeak,
+    WaterDry,
+    SingleClick,
+    DoubleClick,
+    Rotation,
+    ThresholdAlert,
+    Offline,
+    Online,
+    BatteryLow,
+}
+
+/// A condition of a [`Rule`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Condition {
+    /// The local time is between `start`, inclusive, and `end`, exclusive.
+    /// `end` can be earlier than `start` to span midnight.
+    TimeWindow {
+        /// The start of the window.
+        start: NaiveTime,
+        /// The end of the window.
+        end: NaiveTime,
+    },
+    /// The device is on or off.
+    DeviceState {
+        /// The name of the device.
+        device: String,
+        /// Whether the device must be on.
+        device_on: bool,
+    },
+    /// The sun is above or below the horizon at the given location, see [`Location::is_daylight`].
+    Daylight {
+        /// The latitude, in degrees.
+        latitude: f64,
+        /// The longitude, in degrees.
+        longitude: f64,
+        /// Whether the sun must be above the horizon.
+        daylight: bool,
+    },
+}
+
+/// An action of a [`Rule`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Turns *on* the device.
+    TurnOn {
+        /// The name of the device.
+        device: String,
+    },
+    /// Turns *off* the device.
+    TurnOff {
+        /// The name of the device.
+        device: String,
+    },
+    /// Sets the *brightness* and turns *on* the device.
+    SetBrightness {
+        /// The name of the device.
+        device: String,
+        /// Between 1 and 100.
+        brightness: u8,
+    },
+}
+
+impl Rule {
+    /// Returns a [`Rule`] without conditions or actions.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the rule
+    /// * `device` - the name of the device that reports the event
+    /// * `event` - the kind of event that runs the rule
+    pub fn new(name: impl Into<String>, device: impl Into<String>, event: EventPattern) -> Self {
+        Self {
+            name: name.into(),
+            trigger: Trigger {
+                device: device.into(),
+                event,
+            },
+            conditions: Vec::new(),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Adds a condition. All the conditions must be met for the actions to run.
+    pub fn condition(mut self, condition: Condition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Adds an action. The actions run in the order they have been added.
+    pub fn action(mut self, action: RuleAction) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    /// Returns `true` if `event`, reported by `device`, runs the rule.
+    pub fn is_triggered_by(&self, device: &str, event: &DeviceEvent) -> bool {
+        self.trigger.device == device && self.trigger.event.matches(event)
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let invalid = |field: &str, message: &str| Error::Validation {
+            field: field.to_string(),
+            message: format!("{message} (rule '{}')", self.name),
+        };
+
+        if self.name.is_empty() {
+            return Err(invalid("name", "must not be empty"));
+        }
+
+        if self.actions.is_empty() {
+            return Err(invalid("actions", "must contain at least one action"));
+        }
+
+        for condition in &self.conditions {
+            match condition {
+                Condition::TimeWindow { start, end } if start == end => {
+                    return Err(invalid("end", "must be different from the start"));
+                }
+                Condition::Daylight {
+                    latitude,
+                    longitude,
+                    ..
+                } => {
+                    Location::new(*latitude, *longitude)?;
+                }
+                _ => {}
+            }
+        }
+
+        for action in &self.actions {
+            action.validate().map_err(|err| match err {
+                Error::Validation { field, message } => invalid(&field, &message),
+                err => err,
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+impl RuleAction {
+    /// Runs the action on the devices of `manager`.
+    pub(crate) async fn run(&self, manager: &DeviceManager) -> Result<(), Error> {
+        match self {
+            RuleAction::TurnOn { device } => manager.device(device).on().await,
+            RuleAction::TurnOff { device } => manager.device(device).off().await,
+            RuleAction::SetBrightness { device, brightness } => {
+                manager.device(device).set_brightness(*brightness).await
+            }
+        }
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        if let RuleAction::SetBrightness { brightness, .. } = self {
+            if !Brightness::RANGE.contains(brightness) {
+                return Err(Error::Validation {
+                    field: "brightness".to_string(),
+                    message: must_be_within(&Brightness::RANGE),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl EventPattern {
+    /// Returns `true` if `event` is of this kind.
+    pub fn matches(&self, event: &DeviceEvent) -> bool {
+        match (self, event) {
+            (Self::PowerOn, DeviceEvent::PowerStateChanged { device_on }) => *device_on,
+            (Self::PowerOff, DeviceEvent::PowerStateChanged { device_on }) => !*device_on,
+            (Self::LightChanged, DeviceEvent::LightStateChanged { .. }) => true,
+            (pattern, DeviceEvent::SensorTriggered { trigger, .. }) => {
+                matches!(
+                    (pattern, trigger),
+                    (Self::Motion, SensorTrigger::Motion)
+                        | (Self::Open, SensorTrigger::Open)
+                        | (Self::Close, SensorTrigger::Close)
+                        | (Self::KeepOpen, SensorTrigger::KeepOpen)
+                        | (Self::WaterLeak, SensorTrigger::WaterLeak)
+                        | (Self::WaterDry, SensorTrigger::WaterDry)
+                )
+            }
+            (pattern, DeviceEvent::ButtonPressed { press, .. }) => {
+                matches!(
+                    (pattern, press),
+                    (Self::SingleClick, ButtonPress::SingleClick)
+                        | (Self::DoubleClick, ButtonPress::DoubleClick)
+                        | (Self::Rotation, ButtonPress::Rotation { .. })
+                )
+            }
+            (Self::ThresholdAlert, DeviceEvent::ThresholdAlert { .. }) => true,
+            (Self::Offline, DeviceEvent::Offline) => true,
+            (Self::Online, DeviceEvent::Online) => true,
+            (Self::BatteryLow, DeviceEvent::BatteryLow { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Condition {
+    /// Returns `true` if the time condition is met at `time`, or `None` for the conditions that don't depend on time.
+    pub(crate) fn is_met_at(&self, time: DateTime<Local>) -> Option<bool> {
+        match self {
+            Condition::TimeWindow { start, end } => {
+                let time = time.time();
+
+                if start <= end {
+                    Some(*start <= time && time < *end)
+                } else {
+                    Some(*start <= time || time < *end)
+                }
+            }
+            Condition::Daylight {
+                latitude,
+                longitude,
+                daylight,
+            } => Location::new(*latitude, *longitude)
+                .ok()
+                .map(|location| location.is_daylight(time.with_timezone(&Utc)) == *daylight),
+            Condition::DeviceState { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn event_patterns_match_their_events() {
+        let rule = Rule::new("Doorbell", "button", EventPattern::SingleClick);
+
+        assert!(rule.is_triggered_by(
+            "button",
+            &DeviceEvent::ButtonPressed {
+                press: ButtonPress::SingleClick,
+                timestamp: 0
+            }
+        ));
+        assert!(!rule.is_triggered_by(
+            "button",
+            &DeviceEvent::ButtonPressed {
+                press: ButtonPress::DoubleClick,
+                timestamp: 0
+            }
+        ));
+        assert!(!rule.is_triggered_by(
+            "other-button",
+            &DeviceEvent::ButtonPressed {
+                press: ButtonPress::SingleClick,
+                timestamp: 0
+            }
+        ));
+        assert!(
+            EventPattern::PowerOff.matches(&DeviceEvent::PowerStateChanged { device_on: false })
+        );
+    }
+
+    #[test]
+    fn time_windows_can_span_midnight() {
+        let time = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+        let night = Condition::TimeWindow {
+            start: time(19),
+            end: time(6),
+        };
+        let time = |hour| {
+            NaiveDate::from_ymd_opt(2024, 1, 1)
+                .unwrap()
+                .and_time(time(hour))
+                .and_local_timezone(Local)
+                .unwrap()
+        };
+
+        assert_eq!(night.is_met_at(time(23)), Some(true));
+        assert_eq!(night.is_met_at(time(2)), Some(true));
+        assert_eq!(night.is_met_at(time(12)), Some(false));
+    }
+}
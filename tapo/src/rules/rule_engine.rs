@@ -0,0 +1,262 @@
+use chrono::{DateTime, Local};
+use log::{debug, warn};
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::events::DeviceEvent;
+use crate::manager::DeviceManager;
+use crate::rules::{Condition, Rule};
+
+/// Runs [`Rule`]s against the devices of a [`DeviceManager`].
+///
+/// The engine doesn't watch the devices itself: the events are fed to [`RuleEngine::handle`],
+/// e.g. from a [`crate::watcher::DeviceWatcher`] or from the trigger logs of the hub children.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use tapo::events::{DeviceEvent, SensorTrigger};
+/// # use tapo::manager::DeviceManager;
+/// # use tapo::rules::RuleEngine;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+///
+/// let engine = RuleEngine::from_toml(
+///     manager,
+///     r#"
+///     [[rules]]
+///     name = "Porch light on motion"
+///     trigger = { device = "porch-sensor", event = "motion" }
+///     conditions = [
+///         { type = "time_window", start = "19:00:00", end = "06:00:00" },
+///         { type = "device_state", device = "porch-light", device_on = false },
+///     ]
+///     actions = [
+///         { type = "set_brightness", device = "porch-light", brightness = 80 },
+///     ]
+///     "#,
+/// )?;
+///
+/// let event = DeviceEvent::SensorTriggered {
+///     trigger: SensorTrigger::Motion,
+///     timestamp: 1700000000,
+/// };
+/// let fired = engine.handle("porch-sensor", &event).await?;
+/// println!("{fired:?}");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RuleEngine {
+    manager: DeviceManager,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RulesFile {
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+impl RuleEngine {
+    /// Returns a [`RuleEngine`] without rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager of the devices referred to by the rules
+    pub fn new(manager: DeviceManager) -> Self {
+        Self {
+            manager,
+            rules: Vec::new(),
+        }
+    }
+
+    /// Returns a [`RuleEngine`] with the rules of a TOML document,
+    /// a `rules` array of tables in the shape of [`Rule`].
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager of the devices referred to by the rules
+    /// * `toml` - the TOML document
+    pub fn from_toml(manager: DeviceManager, toml: &str) -> Result<Self, Error> {
+        let file: RulesFile = toml::from_str(toml).map_err(|err| Error::Validation {
+            field: "rules".to_string(),
+            message: err.message().to_string(),
+        })?;
+
+        file.rules
+            .into_iter()
+            .try_fold(Self::new(manager), |engine, rule| engine.rule(rule))
+    }
+
+    /// Adds a rule. Rules run in the order they have been added.
+    pub fn rule(mut self, rule: Rule) -> Result<Self, Error> {
+        rule.validate()?;
+        self.rules.push(rule);
+        Ok(self)
+    }
+
+    /// Returns the rules.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Runs the rules triggered by `event` whose conditions are met, and returns their names.
+    ///
+    /// A failing rule doesn't prevent the others from running:
+    /// the first error is returned once all the triggered rules have been processed.
+    /// The rules that fail because a device is in maintenance mode are skipped, without being reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the device that has reported the event
+    /// * `event` - the event
+    pub async fn handle(&self, device: &str, event: &DeviceEvent) -> Result<Vec<String>, Error> {
+        self.handle_at(device, event, Local::now()).await
+    }
+
+    async fn handle_at(
+        &self,
+        device: &str,
+        event: &DeviceEvent,
+        time: DateTime<Local>,
+    ) -> Result<Vec<String>, Error> {
+        let mut fired = Vec::new();
+        let mut first_error = None;
+
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| rule.is_triggered_by(device, event))
+        {
+            match self.run(rule, time).await {
+                Ok(true) => fired.push(rule.name.clone()),
+                Ok(false) => debug!("The conditions of rule '{}' are not met", rule.name),
+                Err(err) if err.is_maintenance() => {
+                    debug!("Rule '{}' skipped: {err}", rule.name);
+                }
+                Err(err) => {
+                    warn!("Rule '{}' failed: {err:?}", rule.name);
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
+
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(fired),
+        }
+    }
+
+    async fn run(&self, rule: &Rule, time: DateTime<Local>) -> Result<bool, Error> {
+        for condition in &rule.conditions {
+            if !self.is_met(condition, time).await? {
+                return Ok(false);
+            }
+        }
+
+        debug!("Running rule '{}'...", rule.name);
+
+        for action in &rule.actions {
+            action.run(&self.manager).await?;
+        }
+
+        Ok(true)
+    }
+
+    async fn is_met(&self, condition: &Condition, time: DateTime<Local>) -> Result<bool, Error> {
+        if let Some(is_met) = condition.is_met_at(time) {
+            return Ok(is_met);
+        }
+
+        match condition {
+            Condition::DeviceState { device, device_on } => {
+                let device_info = self.manager.device(device).get_device_info_json().await?;
+
+                Ok(device_info
+                    .get("device_on")
+                    .and_then(|value| value.as_bool())
+                    == Some(*device_on))
+            }
+            Condition::TimeWindow { .. } | Condition::Daylight { .. } => {
+                unreachable!("only depend on time")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::{EventPattern, RuleAction};
+
+    use super::*;
+
+    const RULES: &str = r#"
+        [[rules]]
+        name = "Lamp on motion"
+        trigger = { device = "sensor", event = "motion" }
+        conditions = [
+            { type = "time_window", start = "19:00:00", end = "06:00:00" },
+            { type = "device_state", device = "lamp", device_on = false },
+        ]
+        actions = [{ type = "turn_on", device = "lamp" }]
+    "#;
+
+    #[test]
+    fn rules_are_loaded_from_toml() {
+        let rule = toml::from_str::<RulesFile>(RULES).unwrap().rules.remove(0);
+
+        assert_eq!(rule.trigger.event, EventPattern::Motion);
+        assert_eq!(rule.conditions.len(), 2);
+        assert_eq!(
+            rule.actions,
+            vec![RuleAction::TurnOn {
+                device: "lamp".to_string()
+            }]
+        );
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn triggered_rules_run_their_actions() {
+        use crate::events::SensorTrigger;
+        use crate::manager::DeviceKind;
+        use crate::simulator::SimulatedFleet;
+        use crate::ApiClient;
+
+        let fleet = SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        manager
+            .register("lamp", DeviceKind::Plug, &fleet.addresses()[0])
+            .await
+            .unwrap();
+        manager.device("lamp").off().await.unwrap();
+
+        let engine = RuleEngine::from_toml(manager.clone(), RULES).unwrap();
+        let motion = DeviceEvent::SensorTriggered {
+            trigger: SensorTrigger::Motion,
+            timestamp: 0,
+        };
+        let today = Local::now().date_naive();
+        let night = today.and_hms_opt(23, 0, 0).unwrap();
+        let night = night.and_local_timezone(Local).unwrap();
+        let noon = today.and_hms_opt(12, 0, 0).unwrap();
+        let noon = noon.and_local_timezone(Local).unwrap();
+
+        let fired = engine.handle_at("sensor", &motion, noon).await.unwrap();
+        assert!(fired.is_empty());
+
+        let fired = engine.handle_at("sensor", &motion, night).await.unwrap();
+        assert_eq!(fired, vec!["Lamp on motion".to_string()]);
+
+        let device_info = manager.device("lamp").get_device_info_json().await.unwrap();
+        assert_eq!(device_info["device_on"], true);
+
+        let fired = engine.handle_at("sensor", &motion, night).await.unwrap();
+        assert!(fired.is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+//! A single handle for the background tasks of an application, which shuts them all down cleanly.
+//!
+//! Requires the `manager` feature.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use std::time::Duration;
+//! # use tapo::manager::DeviceManager;
+//! # use tapo::runtime::TapoRuntime;
+//! # use tapo::watcher::DeviceWatcher;
+//! # use tapo::ApiClient;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let runtime = TapoRuntime::new();
+//!
+//! let manager = DeviceManager::from_config("devices.json").await?;
+//! runtime.manage(manager.clone());
+//!
+//! let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+//!     .l530("192.168.1.100")
+//!     .await?;
+//! runtime.spawn(
+//!     DeviceWatcher::new(device, Duration::from_secs(5))
+//!         .run(|change| println!("Change: {change:?}")),
+//! );
+//!
+//! // Later, e.g. on Ctrl+C.
+//! runtime.shutdown().await;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use log::{debug, warn};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::manager::DeviceManager;
+
+/// Owns the background tasks and the [`DeviceManager`]s of an application, see [`crate::runtime`].
+///
+/// It's a cheap handle that can be cloned and shared freely.
+#[derive(Debug, Clone)]
+pub struct TapoRuntime {
+    shutdown: Arc<watch::Sender<bool>>,
+    tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    managers: Arc<Mutex<Vec<DeviceManager>>>,
+}
+
+/// Completes when the [`TapoRuntime`] it comes from starts shutting down,
+/// see [`TapoRuntime::spawn_with_shutdown`].
+#[derive(Debug, Clone)]
+pub struct ShutdownSignal {
+    receiver: watch::Receiver<bool>,
+}
+
+impl Default for TapoRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TapoRuntime {
+    /// Returns a new [`TapoRuntime`] without tasks.
+    pub fn new() -> Self {
+        let (shutdown, _) = watch::channel(false);
+
+        Self {
+            shutdown: Arc::new(shutdown),
+            tasks: Arc::new(Mutex::new(Vec::new())),
+            managers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Spawns `task`, which is cancelled at its next `.await` point when the runtime shuts down.
+    /// Suitable for tasks that have nothing to flush, e.g. [`crate::watcher::DeviceWatcher::run`].
+    /// Must be called from within a Tokio runtime.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let signal = self.signal();
+
+        self.push_task(tokio::spawn(futures_lite::future::or(task, signal.wait())));
+    }
+
+    /// Spawns the task returned by `task`, which is given a [`ShutdownSignal`] and is expected to
+    /// complete on its own once the signal has completed, e.g. after flushing its pending work.
+    /// Must be called from within a Tokio runtime.
+    pub fn spawn_with_shutdown<F, Fut>(&self, task: F)
+    where
+        F: FnOnce(ShutdownSignal) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.push_task(tokio::spawn(task(self.signal())));
+    }
+
+    /// Adds a [`DeviceManager`] to shut down, see [`DeviceManager::shutdown`].
+    pub fn manage(&self, manager: DeviceManager) {
+        self.managers
+            .lock()
+            .expect("the lock is never poisoned")
+            .push(manager);
+    }
+
+    /// Returns a [`ShutdownSignal`] for tasks that are not spawned by the runtime.
+    pub fn signal(&self) -> ShutdownSignal {
+        ShutdownSignal {
+            receiver: self.shutdown.subscribe(),
+        }
+    }
+
+    /// Returns `true` once [`TapoRuntime::shutdown`] has been called.
+    pub fn is_shutting_down(&self) -> bool {
+        *self.shutdown.borrow()
+    }
+
+    /// Signals all the tasks to stop and waits for them to complete, then shuts down the [`DeviceManager`]s,
+    /// which completes their pending requests and drops the device sessions.
+    ///
+    /// The managers are shut down last, so that the requests sent by the tasks as they stop are not lost.
+    pub async fn shutdown(&self) {
+        debug!("Shutting down the runtime");
+        self.shutdown.send_replace(true);
+
+        let tasks = std::mem::take(&mut *self.tasks.lock().expect("the lock is never poisoned"));
+        for task in tasks {
+            if let Err(err) = task.await {
+                warn!("A background task failed: {err:?}");
+            }
+        }
+
+        let managers =
+            std::mem::take(&mut *self.managers.lock().expect("the lock is never poisoned"));
+        for manager in managers {
+            if let Err(err) = manager.shutdown().await {
+                debug!("Failed to shut down a device manager: {err:?}");
+            }
+        }
+    }
+
+    fn push_task(&self, task: JoinHandle<()>) {
+        let mut tasks = self.tasks.lock().expect("the lock is never poisoned");
+        tasks.retain(|task| !task.is_finished());
+        tasks.push(task);
+    }
+}
+
+impl ShutdownSignal {
+    /// Completes once the runtime starts shutting down, or right away if it already has.
+    pub async fn wait(mut self) {
+        // Fails only if the runtime has been dropped, which is as good as a shutdown.
+        let _ = self.receiver.wait_for(|shutdown| *shutdown).await;
+    }
+
+    /// Returns `true` if the runtime has started shutting down.
+    pub fn is_shutdown(&self) -> bool {
+        *self.receiver.borrow()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use crate::ApiClient;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn shutdown_stops_the_tasks_and_the_managers() {
+        let runtime = TapoRuntime::new();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        runtime.manage(manager.clone());
+
+        runtime.spawn(std::future::pending());
+
+        let flushed = Arc::new(AtomicBool::new(false));
+        let task_flushed = flushed.clone();
+        runtime.spawn_with_shutdown(|signal| async move {
+            signal.wait().await;
+            task_flushed.store(true, Ordering::Relaxed);
+        });
+
+        runtime.shutdown().await;
+
+        assert!(runtime.is_shutting_down());
+        assert!(flushed.load(Ordering::Relaxed));
+        assert!(manager.device("kitchen").on().await.is_err());
+        assert!(manager.shutdown().await.is_err());
+    }
+}
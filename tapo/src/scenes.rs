@@ -0,0 +1,12 @@
+//! Named scenes: the desired state of several devices of a [`crate::manager::DeviceManager`], applied together.
+//!
+//! The scenes are kept in TOML or JSON files, see [`SceneBook`],
+//! so that they can be edited without recompiling.
+//!
+//! Requires the `scenes` feature.
+
+mod scene;
+mod scene_book;
+
+pub use scene::*;
+pub use scene_book::*;
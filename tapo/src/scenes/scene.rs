@@ -0,0 +1,239 @@
+use std::collections::BTreeMap;
+
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinSet;
+
+use crate::error::Error;
+use crate::manager::{DeviceHandle, DeviceManager};
+use crate::requests::{must_be_within, Brightness, Hue, Saturation};
+
+/// The state of the devices of a scene, by the name they are registered under with the [`DeviceManager`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Scene {
+    /// The state of every device of the scene, by name.
+    pub devices: BTreeMap<String, SceneState>,
+}
+
+/// The state of a device in a [`Scene`]. Only the properties that are set are changed.
+///
+/// Setting the *brightness* or the color turns *on* the device, so `device_on` is only needed
+/// to turn *off* a device or to turn *on* a device without changing anything else.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(deny_unknown_fields)]
+pub struct SceneState {
+    /// Whether the device is *on*.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_on: Option<bool>,
+    /// The *brightness*, between 1 and 100. Lights only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub brightness: Option<u8>,
+    /// The *hue*, between 1 and 360. Color lights only, together with `saturation`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hue: Option<u16>,
+    /// The *saturation*, between 1 and 100. Color lights only, together with `hue`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saturation: Option<u8>,
+    /// The *color temperature* in Kelvin. Color lights only, instead of `hue` and `saturation`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_temperature: Option<u16>,
+}
+
+impl Scene {
+    /// Checks that the state of every device is consistent and within range.
+    /// The *color temperature* is checked against the range of the device when the scene is applied.
+    pub fn validate(&self) -> Result<(), Error> {
+        for (device, state) in &self.devices {
+            state.validate().map_err(|err| match err {
+                Error::Validation { field, message } => Error::Validation {
+                    field: format!("{device}.{field}"),
+                    message,
+                },
+                err => err,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Applies the scene to the devices of `manager`, concurrently.
+    ///
+    /// A failing device doesn't prevent the others from being updated:
+    /// the first error is returned once all the devices have been processed.
+    /// The devices in maintenance mode are left as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager of the devices referred to by the scene
+    pub async fn apply(&self, manager: &DeviceManager) -> Result<(), Error> {
+        self.validate()?;
+
+        let mut updates = JoinSet::new();
+        for (name, state) in &self.devices {
+            let device = manager.device(name);
+            let state = state.clone();
+
+            updates.spawn(async move {
+                match state.apply(&device).await {
+                    Err(err) if err.is_maintenance() => {
+                        debug!("Not applying the scene to `{}`: {err}", device.name());
+                        Ok(())
+                    }
+                    result => result.map_err(|err| {
+                        warn!("Failed to apply the scene to `{}`: {err:?}", device.name());
+                        err
+                    }),
+                }
+            });
+        }
+
+        let mut first_error = None;
+        while let Some(result) = updates.join_next().await {
+            let result = result.map_err(|err| Error::Other(err.into()));
+            if let Err(err) = result.and_then(|result| result) {
+                first_error.get_or_insert(err);
+            }
+        }
+
+        match first_error {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+impl SceneState {
+    pub(crate) fn validate(&self) -> Result<(), Error> {
+        let invalid = |field: &str, message: &str| Error::Validation {
+            field: field.to_string(),
+            message: message.to_string(),
+        };
+
+        let has_light_settings = self.brightness.is_some()
+            || self.hue.is_some()
+            || self.saturation.is_some()
+            || self.color_temperature.is_some();
+
+        if self.device_on.is_none() && !has_light_settings {
+            return Err(invalid(
+                "device_on",
+                "the state must set at least one property",
+            ));
+        }
+
+        if self.device_on == Some(false) && has_light_settings {
+            return Err(invalid(
+                "device_on",
+                "must not be false when the brightness or the color is set",
+            ));
+        }
+
+        if let Some(brightness) = self.brightness {
+            if !Brightness::RANGE.contains(&brightness) {
+                return Err(invalid("brightness", &must_be_within(&Brightness::RANGE)));
+            }
+        }
+
+        match (self.hue, self.saturation) {
+            (Some(hue), Some(saturation)) => {
+                if !Hue::RANGE.contains(&hue) {
+                    return Err(invalid("hue", &must_be_within(&Hue::RANGE)));
+                }
+                if !Saturation::RANGE.contains(&saturation) {
+                    return Err(invalid("saturation", &must_be_within(&Saturation::RANGE)));
+                }
+                if self.color_temperature.is_some() {
+                    return Err(invalid(
+                        "color_temperature",
+                        "must not be set together with the hue and saturation",
+                    ));
+                }
+            }
+            (Some(_), None) => return Err(invalid("saturation", "must be set with the hue")),
+            (None, Some(_)) => return Err(invalid("hue", "must be set with the saturation")),
+            (None, None) => {}
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn apply(&self, device: &DeviceHandle) -> Result<(), Error> {
+        debug!("Applying {self:?} to `{}`...", device.name());
+
+        if self.device_on == Some(false) {
+            return device.off().await;
+        }
+
+        let mut is_on = false;
+
+        if let Some(brightness) = self.brightness {
+            device.set_brightness(brightness).await?;
+            is_on = true;
+        }
+
+        if let (Some(hue), Some(saturation)) = (self.hue, self.saturation) {
+            device.set_hue_saturation(hue, saturation).await?;
+            is_on = true;
+        }
+
+        if let Some(color_temperature) = self.color_temperature {
+            device.set_color_temperature(color_temperature).await?;
+            is_on = true;
+        }
+
+        if self.device_on == Some(true) && !is_on {
+            device.on().await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inconsistent_states_are_rejected() {
+        let state = |json: serde_json::Value| serde_json::from_value::<SceneState>(json).unwrap();
+        let field_of = |state: SceneState| match state.validate() {
+            Err(Error::Validation { field, .. }) => Some(field),
+            _ => None,
+        };
+
+        assert_eq!(
+            field_of(state(serde_json::json!({ "device_on": false }))),
+            None
+        );
+        assert_eq!(
+            field_of(state(serde_json::json!({ "hue": 30, "saturation": 80 }))),
+            None
+        );
+        assert_eq!(
+            field_of(state(serde_json::json!({}))),
+            Some("device_on".to_string())
+        );
+        assert_eq!(
+            field_of(state(
+                serde_json::json!({ "device_on": false, "brightness": 20 })
+            )),
+            Some("device_on".to_string())
+        );
+        assert_eq!(
+            field_of(state(serde_json::json!({ "brightness": 0 }))),
+            Some("brightness".to_string())
+        );
+        assert_eq!(
+            field_of(state(serde_json::json!({ "hue": 30 }))),
+            Some("saturation".to_string())
+        );
+        assert_eq!(
+            field_of(state(
+                serde_json::json!({ "hue": 30, "saturation": 80, "color_temperature": 2700 })
+            )),
+            Some("color_temperature".to_string())
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::manager::DeviceManager;
+use crate::scenes::Scene;
+
+/// A set of named [`Scene`]s, usually loaded from a file with [`SceneBook::load`].
+///
+/// # Example
+///
+/// The scenes are a `scenes` table of scenes by name, each of them a table of [`crate::scenes::SceneState`]s
+/// by the name of the device.
+///
+/// ```toml
+/// [scenes.movie-night]
+/// living-room = { brightness = 20, color_temperature = 2700 }
+/// tv-backlight = { hue = 240, saturation = 80 }
+/// kitchen = { device_on = false }
+///
+/// [scenes.all-off]
+/// living-room = { device_on = false }
+/// tv-backlight = { device_on = false }
+/// kitchen = { device_on = false }
+/// ```
+///
+/// The same scenes in JSON:
+///
+/// ```json
+/// {
+///     "scenes": {
+///         "movie-night": {
+///             "living-room": { "brightness": 20, "color_temperature": 2700 },
+///             "tv-backlight": { "hue": 240, "saturation": 80 },
+///             "kitchen": { "device_on": false }
+///         }
+///     }
+/// }
+/// ```
+///
+/// ```rust,no_run
+/// # use tapo::manager::DeviceManager;
+/// # use tapo::scenes::SceneBook;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+/// let scenes = SceneBook::load("scenes.toml")?;
+///
+/// scenes.apply(&manager, "movie-night").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SceneBook {
+    /// The scenes, by name.
+    #[serde(default)]
+    pub scenes: BTreeMap<String, Scene>,
+}
+
+impl SceneBook {
+    /// Reads the scenes from the file at `path`, as TOML if its extension is `.toml` and as JSON otherwise.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| anyhow::anyhow!("Failed to read {}: {err}", path.display()))?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("toml") => Self::from_toml(&contents),
+            _ => Self::from_json(&contents),
+        }
+    }
+
+    /// Returns the scenes of a TOML document.
+    ///
+    /// # Arguments
+    ///
+    /// * `toml` - the TOML document
+    pub fn from_toml(toml: &str) -> Result<Self, Error> {
+        let book: Self = toml::from_str(toml).map_err(|err| Error::Validation {
+            field: "scenes".to_string(),
+            message: err.message().to_string(),
+        })?;
+
+        book.validate()?;
+        Ok(book)
+    }
+
+    /// Returns the scenes of a JSON document.
+    ///
+    /// # Arguments
+    ///
+    /// * `json` - the JSON document
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let book: Self = serde_json::from_str(json).map_err(|err| Error::Validation {
+            field: "scenes".to_string(),
+            message: err.to_string(),
+        })?;
+
+        book.validate()?;
+        Ok(book)
+    }
+
+    /// Checks every scene, see [`Scene::validate`].
+    pub fn validate(&self) -> Result<(), Error> {
+        for (name, scene) in &self.scenes {
+            scene.validate().map_err(|err| match err {
+                Error::Validation { field, message } => Error::Validation {
+                    field: format!("scenes.{name}.{field}"),
+                    message,
+                },
+                err => err,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the scene called `name`, if any.
+    pub fn scene(&self, name: &str) -> Option<&Scene> {
+        self.scenes.get(name)
+    }
+
+    /// Applies the scene called `name` to the devices of `manager`, see [`Scene::apply`].
+    /// Returns [`Error::Validation`] if there is no such scene.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager of the devices referred to by the scene
+    /// * `name` - the name of the scene
+    pub async fn apply(&self, manager: &DeviceManager, name: &str) -> Result<(), Error> {
+        let scene = self.scene(name).ok_or_else(|| Error::Validation {
+            field: "scene".to_string(),
+            message: format!("No scene is called `{name}`"),
+        })?;
+
+        info!("Applying scene `{name}`...");
+        scene.apply(manager).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scenes::SceneState;
+
+    use super::*;
+
+    const SCENES: &str = r#"
+        [scenes.movie-night]
+        living-room = { brightness = 20, color_temperature = 2700 }
+        kitchen = { device_on = false }
+
+        [scenes.morning]
+        kitchen = { device_on = true }
+    "#;
+
+    #[test]
+    fn toml_and_json_scenes_are_equivalent() {
+        let book = SceneBook::from_toml(SCENES).unwrap();
+
+        assert_eq!(
+            book.scene("movie-night").unwrap().devices["living-room"],
+            SceneState {
+                brightness: Some(20),
+                color_temperature: Some(2700),
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            SceneBook::from_json(&serde_json::to_string(&book).unwrap()).unwrap(),
+            book
+        );
+    }
+
+    #[test]
+    fn invalid_scenes_are_rejected() {
+        let result = SceneBook::from_toml(
+            r#"
+            [scenes.reading]
+            desk-lamp = { brightness = 120 }
+            "#,
+        );
+        assert!(matches!(
+            result,
+            Err(Error::Validation { field, .. }) if field == "scenes.reading.desk-lamp.brightness"
+        ));
+
+        let result = SceneBook::from_toml(
+            r#"
+            [scenes.reading]
+            desk-lamp = { brightnes = 80 }
+            "#,
+        );
+        assert!(matches!(
+            result,
+            Err(Error::Validation { field, .. }) if field == "scenes"
+        ));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn scenes_are_applied_to_the_devices() {
+        use crate::manager::DeviceKind;
+        use crate::simulator::SimulatedFleet;
+        use crate::ApiClient;
+
+        let fleet = SimulatedFleet::builder("username", "password")
+            .devices(2)
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        for (name, address) in ["kitchen", "hallway"].into_iter().zip(fleet.addresses()) {
+            manager
+                .register(name, DeviceKind::Plug, address)
+                .await
+                .unwrap();
+        }
+        let book = SceneBook::from_toml(
+            r#"
+            [scenes.evening]
+            kitchen = { device_on = false }
+            hallway = { device_on = true }
+            "#,
+        )
+        .unwrap();
+
+        book.apply(&manager, "evening").await.unwrap();
+
+        let kitchen = manager
+            .device("kitchen")
+            .get_device_info_json()
+            .await
+            .unwrap();
+        let hallway = manager
+            .device("hallway")
+            .get_device_info_json()
+            .await
+            .unwrap();
+        assert_eq!(kitchen["device_on"], false);
+        assert_eq!(hallway["device_on"], true);
+
+        assert!(matches!(
+            book.apply(&manager, "morning").await,
+            Err(Error::Validation { field, .. }) if field == "scene"
+        ));
+    }
+}
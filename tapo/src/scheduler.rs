@@ -0,0 +1,347 @@
+//! Cron-style scheduling of actions on the devices of a [`DeviceManager`].
+//!
+//! Requires the `scheduler` feature.
+//!
+//! Schedules use the 6 or 7 fields syntax of the [`cron`](https://docs.rs/cron) crate, starting with the seconds
+//! and optionally ending with the year, and are evaluated in the local time zone.
+//!
+//! Loads that can run at any time of the day, e.g. EV chargers, are moved into the cheapest hours
+//! of a time-of-use tariff by a [`LoadShifter`], and color lights are woken up gradually by a [`SunriseAlarm`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use tapo::manager::DeviceManager;
+//! # use tapo::scheduler::Scheduler;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let manager = DeviceManager::from_config("devices.json").await?;
+//!
+//! let _scheduler = Scheduler::new(manager)
+//!     .every("0 30 7 * * Mon-Fri", |manager| async move {
+//!         manager.device("coffee-machine").on().await
+//!     })?
+//!     .every("0 0 23 * * *", |manager| async move {
+//!         manager.device("living-room").off().await
+//!     })?
+//!     .catch_up_missed_runs("scheduler.json")?
+//!     .start();
+//!
+//! std::future::pending::<()>().await;
+//! # Ok(())
+//! # }
+//! ```
+
+mod load_shifting;
+mod sunrise_alarm;
+
+pub use load_shifting::*;
+pub use sunrise_alarm::*;
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Local, Utc};
+use cron::Schedule;
+use futures_lite::future::Boxed;
+use log::{debug, warn};
+use tokio::task::JoinHandle;
+
+use crate::error::Error;
+use crate::manager::DeviceManager;
+
+type ScheduledAction = Arc<dyn Fn(DeviceManager) -> Boxed<Result<(), Error>> + Send + Sync>;
+
+/// Runs actions on the devices of a [`DeviceManager`] according to cron schedules, see [`crate::scheduler`].
+pub struct Scheduler {
+    manager: DeviceManager,
+    jobs: Vec<Job>,
+    state: Option<Arc<SchedulerState>>,
+}
+
+#[derive(Clone)]
+struct Job {
+    /// Identifies the job in the state file.
+    key: String,
+    schedule: Schedule,
+    action: ScheduledAction,
+}
+
+/// The time of the last run of every job, persisted to a JSON file.
+struct SchedulerState {
+    path: PathBuf,
+    last_runs: Mutex<BTreeMap<String, DateTime<Utc>>>,
+}
+
+/// The jobs of a started [`Scheduler`]. They stop when it is dropped.
+#[derive(Debug)]
+pub struct RunningScheduler {
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("manager", &self.manager)
+            .field(
+                "jobs",
+                &self.jobs.iter().map(|job| &job.key).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Scheduler {
+    /// Returns a [`Scheduler`] without jobs.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager passed to the actions
+    pub fn new(manager: DeviceManager) -> Self {
+        Self {
+            manager,
+            jobs: Vec::new(),
+            state: None,
+        }
+    }
+
+    /// Adds a job that runs `action` at every time matched by `schedule`.
+    /// A run that fails is logged and doesn't affect the next ones.
+    /// A run that fails because a device is in maintenance mode is considered skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `schedule` - the cron expression, e.g. `0 30 7 * * *` for every day at 7:30:00
+    /// * `action` - the action, which is given a clone of the [`DeviceManager`]
+    pub fn every<F, Fut>(mut self, schedule: &str, action: F) -> Result<Self, Error>
+    where
+        F: Fn(DeviceManager) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        let parsed = Schedule::from_str(schedule).map_err(|err| Error::Validation {
+            field: "schedule".to_string(),
+            message: err.to_string(),
+        })?;
+
+        self.jobs.push(Job {
+            key: format!("{} {schedule}", self.jobs.len()),
+            schedule: parsed,
+            action: Arc::new(move |manager| Box::pin(action(manager))),
+        });
+
+        Ok(self)
+    }
+
+    /// Records the time of every run in the JSON file at `path`, and when started, runs once the jobs
+    /// that have missed at least one run since their last recorded run, e.g. while the application was down.
+    ///
+    /// The jobs are identified by their position and their schedule,
+    /// so the runs of a job whose position or schedule changes are forgotten.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - the location of the state file, created if it doesn't exist
+    pub fn catch_up_missed_runs(mut self, path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+
+        let last_runs = if path.exists() {
+            let contents = std::fs::read_to_string(&path).map_err(anyhow::Error::from)?;
+            serde_json::from_str(&contents)?
+        } else {
+            BTreeMap::new()
+        };
+
+        self.state = Some(Arc::new(SchedulerState {
+            path,
+            last_runs: Mutex::new(last_runs),
+        }));
+
+        Ok(self)
+    }
+
+    /// Starts running the jobs. Must be called from within a Tokio runtime.
+    pub fn start(self) -> RunningScheduler {
+        let now = Local::now();
+
+        let tasks = self
+            .jobs
+            .into_iter()
+            .map(|job| {
+                let missed = self
+                    .state
+                    .as_ref()
+                    .is_some_and(|state| state.has_missed_runs(&job, now));
+
+                tokio::spawn(run_job(
+                    job,
+                    self.manager.clone(),
+                    self.state.clone(),
+                    missed,
+                ))
+            })
+            .collect();
+
+        RunningScheduler { tasks }
+    }
+
+    /// Runs the jobs until the returned future is dropped, e.g. by [`crate::runtime::TapoRuntime::spawn`].
+    /// Must be called from within a Tokio runtime.
+    pub async fn run(self) {
+        let _running = self.start();
+        std::future::pending::<()>().await;
+    }
+}
+
+impl RunningScheduler {
+    /// Stops all the jobs. Runs that are in progress are cancelled.
+    pub fn stop(self) {}
+}
+
+impl Drop for RunningScheduler {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl SchedulerState {
+    fn has_missed_runs(&self, job: &Job, now: DateTime<Local>) -> bool {
+        let last_runs = self.last_runs.lock().expect("the lock is never poisoned");
+
+        last_runs.get(&job.key).is_some_and(|last_run| {
+            job.schedule
+                .after(&last_run.with_timezone(&Local))
+                .next()
+                .is_some_and(|next_run| next_run <= now)
+        })
+    }
+
+    fn record_run(&self, job: &Job, time: DateTime<Local>) -> Result<(), Error> {
+        let contents = {
+            let mut last_runs = self.last_runs.lock().expect("the lock is never poisoned");
+            last_runs.insert(job.key.clone(), time.with_timezone(&Utc));
+            serde_json::to_string_pretty(&*last_runs)?
+        };
+
+        crate::persistence::write_atomically(&self.path, contents)
+    }
+}
+
+async fn run_job(
+    job: Job,
+    manager: DeviceManager,
+    state: Option<Arc<SchedulerState>>,
+    missed: bool,
+) {
+    if missed {
+        debug!("Catching up on the missed runs of job `{}`...", job.key);
+        run_once(&job, &manager, state.as_deref(), Local::now()).await;
+    }
+
+    loop {
+        let Some(next_run) = job.schedule.upcoming(Local).next() else {
+            debug!("Job `{}` has no more runs", job.key);
+            return;
+        };
+
+        let delay = (next_run - Local::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(delay).await;
+
+        run_once(&job, &manager, state.as_deref(), next_run).await;
+    }
+}
+
+async fn run_once(
+    job: &Job,
+    manager: &DeviceManager,
+    state: Option<&SchedulerState>,
+    time: DateTime<Local>,
+) {
+    debug!("Running job `{}`...", job.key);
+
+    match (job.action)(manager.clone()).await {
+        Ok(()) => {}
+        Err(err) if err.is_maintenance() => debug!("Job `{}` skipped: {err}", job.key),
+        Err(err) => warn!("Job `{}` failed: {err:?}", job.key),
+    }
+
+    if let Some(state) = state {
+        if let Err(err) = state.record_run(job, time) {
+            warn!("Failed to record the run of job `{}`: {err:?}", job.key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use crate::ApiClient;
+
+    use super::*;
+
+    fn manager() -> DeviceManager {
+        DeviceManager::new(ApiClient::new("username", "password").unwrap())
+    }
+
+    #[tokio::test]
+    async fn jobs_run_on_schedule() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+
+        let scheduler = Scheduler::new(manager())
+            .every("* * * * * *", move |_| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    Ok(())
+                }
+            })
+            .unwrap()
+            .start();
+
+        tokio::time::sleep(Duration::from_millis(2100)).await;
+        scheduler.stop();
+
+        assert!(runs.load(Ordering::Relaxed) >= 2);
+        assert!(Scheduler::new(manager())
+            .every("not cron", |_| async { Ok(()) })
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn missed_runs_are_caught_up() {
+        let path =
+            std::env::temp_dir().join(format!("tapo-scheduler-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"{ "0 0 0 12 * * *": "2000-01-01T00:00:00Z" }"#).unwrap();
+
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+
+        let _scheduler = Scheduler::new(manager())
+            .every("0 0 12 * * *", move |_| {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                    Ok(())
+                }
+            })
+            .unwrap()
+            .catch_up_missed_runs(&path)
+            .unwrap()
+            .start();
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(runs.load(Ordering::Relaxed), 1);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(!contents.contains("2000-01-01"));
+    }
+}
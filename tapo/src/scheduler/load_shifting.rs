@@ -0,0 +1,519 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Local, Timelike};
+use log::{debug, warn};
+use rust_decimal::Decimal;
+use tokio::task::JoinHandle;
+
+use crate::error::Error;
+use crate::manager::{DeviceHandle, DeviceManager};
+use crate::tariff::Tariff;
+
+/// The number of hourly slots that the loads are planned over.
+const PLANNING_HOURS: usize = 24;
+/// The energy below which the rounding errors of the budget aren't planned as an extra hour, in Wh.
+const ENERGY_TOLERANCE_WH: f64 = 1e-6;
+
+/// A load that can run at any time of the day as long as it gets its energy, e.g. an EV trickle charger
+/// or a water heater, plugged into a smart plug, see [`LoadShifter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeferrableLoad {
+    /// The name of the device that powers the load.
+    pub device: String,
+    /// The power drawn by the load while it's on, in W.
+    pub power_w: f64,
+    /// The energy that the load must get every day, in Wh.
+    pub daily_energy_wh: f64,
+}
+
+impl DeferrableLoad {
+    /// Returns a [`DeferrableLoad`].
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the device that powers the load
+    /// * `power_w` - the power drawn by the load while it's on, in W
+    /// * `daily_energy_wh` - the energy budget of the load, in Wh per day
+    pub fn new(device: impl Into<String>, power_w: f64, daily_energy_wh: f64) -> Self {
+        Self {
+            device: device.into(),
+            power_w,
+            daily_energy_wh,
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let invalid = |field: &str, message: &str| Error::Validation {
+            field: format!("loads.{}.{field}", self.device),
+            message: message.to_string(),
+        };
+
+        if !(self.power_w.is_finite() && self.power_w > 0.0) {
+            return Err(invalid("power_w", "must be greater than 0"));
+        }
+        if !(self.daily_energy_wh.is_finite() && self.daily_energy_wh > 0.0) {
+            return Err(invalid("daily_energy_wh", "must be greater than 0"));
+        }
+        if self.daily_energy_wh > self.power_w * PLANNING_HOURS as f64 {
+            return Err(invalid(
+                "daily_energy_wh",
+                "must be deliverable in 24 hours at `power_w`",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the energy of every hour that the load has to be on for, in Wh, the last one being partial.
+    fn hourly_energy_wh(&self) -> Vec<f64> {
+        let hours = ((self.daily_energy_wh - ENERGY_TOLERANCE_WH) / self.power_w).ceil() as usize;
+        let hours = hours.clamp(1, PLANNING_HOURS);
+
+        (0..hours)
+            .map(|hour| (self.daily_energy_wh - hour as f64 * self.power_w).min(self.power_w))
+            .collect()
+    }
+}
+
+/// When a [`DeferrableLoad`] runs over the next 24 hours, and what it costs, see [`LoadShifter::plan`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadShiftPlan {
+    /// The name of the device.
+    pub device: String,
+    /// The start of the hours during which the device is on, in chronological order.
+    pub slots: Vec<DateTime<Local>>,
+    /// The hour of [`LoadShiftPlan::slots`] during which the device is only on for part of the hour,
+    /// and for how long from its start, if the energy budget isn't a whole number of hours.
+    pub partial_slot: Option<(DateTime<Local>, Duration)>,
+    /// The expected cost of the planned hours.
+    pub cost: Decimal,
+    /// The expected cost of running the load as soon as possible instead.
+    pub baseline_cost: Decimal,
+}
+
+impl LoadShiftPlan {
+    /// Returns the expected savings of the plan over running the load as soon as possible.
+    pub fn savings(&self) -> Decimal {
+        self.baseline_cost - self.cost
+    }
+
+    /// Returns `true` if the device is on during the hour that starts at `slot`.
+    pub fn is_on_at(&self, slot: DateTime<Local>) -> bool {
+        self.slots.contains(&slot)
+    }
+
+    /// Returns for how long the device is on from the start of the hour that starts at `slot`,
+    /// the whole hour except for the [`LoadShiftPlan::partial_slot`], or `None` if it's off.
+    pub fn on_duration_at(&self, slot: DateTime<Local>) -> Option<Duration> {
+        match self.partial_slot {
+            Some((partial_slot, duration)) if partial_slot == slot => Some(duration),
+            _ if self.is_on_at(slot) => Some(Duration::hours(1)),
+            _ => None,
+        }
+    }
+}
+
+/// The energy used by a device while the [`LoadShifter`] has kept it on, and what shifting it has saved.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadShiftSavings {
+    /// The energy used, in kWh.
+    pub energy_kwh: Decimal,
+    /// The cost of the energy used.
+    pub cost: Decimal,
+    /// What the energy used would have cost if the load had run as soon as possible,
+    /// priced at the average price of [`LoadShiftPlan::baseline_cost`].
+    pub baseline_cost: Decimal,
+}
+
+impl LoadShiftSavings {
+    /// Returns the realized savings.
+    pub fn savings(&self) -> Decimal {
+        self.baseline_cost - self.cost
+    }
+}
+
+/// Moves deferrable loads into the cheapest hours of a time-of-use [`Tariff`].
+///
+/// Every 24 hours, the cheapest hours needed to deliver the energy budget of each load are planned,
+/// see [`LoadShifter::plan`], and the devices are turned on and off at the start of every hour accordingly,
+/// and off during their last, partial hour once the budget has been delivered.
+/// While they are on, their energy is measured, if they support it, to report the realized savings.
+/// The energy of the devices that don't is estimated from their power.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use chrono::NaiveTime;
+/// # use tapo::manager::DeviceManager;
+/// # use tapo::scheduler::{DeferrableLoad, LoadShifter};
+/// # use tapo::tariff::{Decimal, Tariff};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+/// let tariff = Tariff::builder(Decimal::new(30, 2))
+///     .band(
+///         "night",
+///         NaiveTime::from_hms_opt(0, 30, 0).unwrap(),
+///         NaiveTime::from_hms_opt(4, 30, 0).unwrap(),
+///         Decimal::new(7, 2),
+///     )
+///     .build()?;
+///
+/// // An EV trickle charger drawing 2.3 kW, which needs 7 kWh a day.
+/// let shifter = LoadShifter::new(manager, tariff)
+///     .load(DeferrableLoad::new("ev-charger", 2300.0, 7000.0))?
+///     .start();
+///
+/// tokio::time::sleep(std::time::Duration::from_secs(7 * 24 * 3600)).await;
+/// for (device, savings) in shifter.report() {
+///     println!("{device}: saved {}", savings.savings().round_dp(2));
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LoadShifter {
+    manager: DeviceManager,
+    tariff: Tariff,
+    loads: Vec<DeferrableLoad>,
+}
+
+/// The loads of a started [`LoadShifter`]. They are no longer switched once it is dropped.
+#[derive(Debug)]
+pub struct RunningLoadShifter {
+    task: JoinHandle<()>,
+    savings: Arc<Mutex<BTreeMap<String, LoadShiftSavings>>>,
+}
+
+impl LoadShifter {
+    /// Returns a [`LoadShifter`] without loads.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager of the devices that power the loads
+    /// * `tariff` - the tariff, whose bands are in the local time zone
+    pub fn new(manager: DeviceManager, tariff: Tariff) -> Self {
+        Self {
+            manager,
+            tariff,
+            loads: Vec::new(),
+        }
+    }
+
+    /// Adds a load, replacing the previous load of the same device.
+    /// The energy budget must be deliverable in 24 hours.
+    pub fn load(mut self, load: DeferrableLoad) -> Result<Self, Error> {
+        load.validate()?;
+
+        self.loads.retain(|other| other.device != load.device);
+        self.loads.push(load);
+
+        Ok(self)
+    }
+
+    /// Returns the plan of every load over the 24 hours starting with the hour of `from`.
+    ///
+    /// Each load is given the cheapest hours needed to deliver its energy budget, the earliest first among
+    /// hours of the same price, and its last, partial hour is the most expensive of them.
+    /// The device is turned off during the partial hour once the budget has been delivered.
+    pub fn plan(&self, from: DateTime<Local>) -> Vec<LoadShiftPlan> {
+        let slots = hourly_slots(from);
+        let prices: Vec<_> = slots
+            .iter()
+            .map(|slot| self.tariff.price_at(slot.naive_local()).1)
+            .collect();
+
+        let mut cheapest: Vec<_> = (0..slots.len()).collect();
+        cheapest.sort_by_key(|&slot| prices[slot]);
+
+        self.loads
+            .iter()
+            .map(|load| {
+                let energy = load.hourly_energy_wh();
+                let cost_of = |slots: &[usize]| -> Decimal {
+                    slots
+                        .iter()
+                        .zip(&energy)
+                        .map(|(&slot, &energy_wh)| kwh(energy_wh) * prices[slot])
+                        .sum()
+                };
+
+                let mut planned = cheapest[..energy.len()].to_vec();
+                let cost = cost_of(&planned);
+                let baseline: Vec<_> = (0..energy.len()).collect();
+                let partial_slot = energy
+                    .last()
+                    .filter(|&&energy_wh| energy_wh < load.power_w)
+                    .map(|&energy_wh| {
+                        let on_ms = (energy_wh / load.power_w * 3_600_000.0).round();
+                        (
+                            slots[planned[energy.len() - 1]],
+                            Duration::milliseconds(on_ms as i64),
+                        )
+                    });
+                planned.sort_unstable();
+
+                LoadShiftPlan {
+                    device: load.device.clone(),
+                    slots: planned.into_iter().map(|slot| slots[slot]).collect(),
+                    partial_slot,
+                    cost,
+                    baseline_cost: cost_of(&baseline),
+                }
+            })
+            .collect()
+    }
+
+    /// Starts switching the loads. Must be called from within a Tokio runtime.
+    pub fn start(self) -> RunningLoadShifter {
+        let savings = Arc::new(Mutex::new(BTreeMap::new()));
+        let task = tokio::spawn(run_loads(self, savings.clone()));
+
+        RunningLoadShifter { task, savings }
+    }
+
+    /// Switches the loads until the returned future is dropped, e.g. by [`crate::runtime::TapoRuntime::spawn`].
+    /// Must be called from within a Tokio runtime.
+    pub async fn run(self) {
+        let _running = self.start();
+        std::future::pending::<()>().await;
+    }
+}
+
+impl RunningLoadShifter {
+    /// Returns the energy used and the realized savings of every device so far, by name.
+    pub fn report(&self) -> BTreeMap<String, LoadShiftSavings> {
+        self.savings
+            .lock()
+            .expect("the lock is never poisoned")
+            .clone()
+    }
+
+    /// Stops switching the loads. The devices are left in their current state.
+    pub fn stop(self) {}
+}
+
+impl Drop for RunningLoadShifter {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// The energy counter of a device that is on, read at the start of the hour.
+struct Metering {
+    started_at: DateTime<Local>,
+    today_energy_wh: Option<u64>,
+}
+
+async fn run_loads(shifter: LoadShifter, savings: Arc<Mutex<BTreeMap<String, LoadShiftSavings>>>) {
+    let mut metering: BTreeMap<String, Metering> = BTreeMap::new();
+
+    loop {
+        let plans = shifter.plan(Local::now());
+        debug!("Planned the loads: {plans:?}");
+
+        for slot in hourly_slots(Local::now()) {
+            sleep_until(slot).await;
+
+            let mut partial_offs = Vec::new();
+            for (load, plan) in shifter.loads.iter().zip(&plans) {
+                let device = shifter.manager.device(load.device.as_str());
+
+                if let Some(metering) = metering.remove(&load.device) {
+                    settle(&shifter, &savings, &device, load, plan, &metering).await;
+                }
+
+                let on_duration = plan.on_duration_at(slot);
+                let result = if on_duration.is_some() {
+                    device.on().await
+                } else {
+                    device.off().await
+                };
+
+                match (result, on_duration) {
+                    (Ok(()), Some(on_duration)) => {
+                        let today_energy_wh = match device.get_energy_usage().await {
+                            Ok(energy_usage) => Some(energy_usage.today_energy),
+                            Err(_) => None,
+                        };
+                        metering.insert(
+                            load.device.clone(),
+                            Metering {
+                                started_at: slot,
+                                today_energy_wh,
+                            },
+                        );
+
+                        if on_duration < Duration::hours(1) {
+                            partial_offs.push((slot + on_duration, load, plan));
+                        }
+                    }
+                    (Ok(()), None) => {}
+                    (Err(err), _) => report_switch_error(load, err),
+                }
+            }
+
+            // The loads whose budget ends during this hour are turned off once it has been delivered.
+            partial_offs.sort_by_key(|(off_at, _, _)| *off_at);
+            for (off_at, load, plan) in partial_offs {
+                sleep_until(off_at).await;
+
+                let device = shifter.manager.device(load.device.as_str());
+                if let Some(metering) = metering.remove(&load.device) {
+                    settle(&shifter, &savings, &device, load, plan, &metering).await;
+                }
+                if let Err(err) = device.off().await {
+                    report_switch_error(load, err);
+                }
+            }
+        }
+    }
+}
+
+/// Adds the energy used by `device` while it was on, and its cost, to the savings.
+async fn settle(
+    shifter: &LoadShifter,
+    savings: &Mutex<BTreeMap<String, LoadShiftSavings>>,
+    device: &DeviceHandle,
+    load: &DeferrableLoad,
+    plan: &LoadShiftPlan,
+    metering: &Metering,
+) {
+    let energy_wh = measure(device, load, metering).await;
+    let price = shifter.tariff.price_at(metering.started_at.naive_local()).1;
+    let baseline_price = plan
+        .baseline_cost
+        .checked_div(kwh(load.daily_energy_wh))
+        .unwrap_or(price);
+
+    let mut savings = savings.lock().expect("the lock is never poisoned");
+    let device_savings = savings.entry(load.device.clone()).or_default();
+    device_savings.energy_kwh += kwh(energy_wh);
+    device_savings.cost += kwh(energy_wh) * price;
+    device_savings.baseline_cost += kwh(energy_wh) * baseline_price;
+}
+
+fn report_switch_error(load: &DeferrableLoad, err: Error) {
+    if err.is_maintenance() {
+        debug!("Not switching `{}`: {err}", load.device);
+    } else {
+        warn!("Failed to switch `{}`: {err:?}", load.device);
+    }
+}
+
+async fn sleep_until(at: DateTime<Local>) {
+    let delay = (at - Local::now()).to_std().unwrap_or_default();
+    tokio::time::sleep(delay).await;
+}
+
+/// Returns the energy used by `device` since the start of the hour, in Wh.
+/// Falls back to the power of the load if the device doesn't measure its energy.
+async fn measure(device: &DeviceHandle, load: &DeferrableLoad, metering: &Metering) -> f64 {
+    let hours = (Local::now() - metering.started_at).num_seconds() as f64 / 3600.0;
+    let estimate = load.power_w * hours.min(1.0);
+
+    let (Some(start), Ok(energy_usage)) =
+        (metering.today_energy_wh, device.get_energy_usage().await)
+    else {
+        return estimate;
+    };
+
+    // The counter is reset at midnight.
+    if energy_usage.today_energy >= start {
+        (energy_usage.today_energy - start) as f64
+    } else {
+        energy_usage.today_energy as f64
+    }
+}
+
+/// Returns the start of the [`PLANNING_HOURS`] hours from the hour of `from`.
+fn hourly_slots(from: DateTime<Local>) -> Vec<DateTime<Local>> {
+    let hour = from
+        .with_minute(0)
+        .and_then(|from| from.with_second(0))
+        .and_then(|from| from.with_nanosecond(0))
+        .unwrap_or(from);
+
+    (0..PLANNING_HOURS)
+        .map(|slot| hour + Duration::hours(slot as i64))
+        .collect()
+}
+
+fn kwh(energy_wh: f64) -> Decimal {
+    Decimal::from_f64_retain(energy_wh).unwrap_or_default() / Decimal::ONE_THOUSAND
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveTime, TimeZone};
+
+    use crate::ApiClient;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn loads_are_planned_in_the_cheapest_hours() {
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        let tariff = Tariff::builder(Decimal::new(30, 2))
+            .band(
+                "night",
+                NaiveTime::from_hms_opt(1, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(4, 0, 0).unwrap(),
+                Decimal::new(10, 2),
+            )
+            .build()
+            .unwrap();
+        let shifter = LoadShifter::new(manager, tariff)
+            .load(DeferrableLoad::new("ev-charger", 2000.0, 5000.0))
+            .unwrap();
+        let from = Local.with_ymd_and_hms(2024, 1, 1, 20, 15, 0).unwrap();
+
+        let plans = shifter.plan(from);
+
+        let hours: Vec<_> = plans[0].slots.iter().map(|slot| slot.hour()).collect();
+        assert_eq!(hours, [1, 2, 3]);
+        // 2 kWh + 2 kWh + 1 kWh, against 5 kWh from 20:00 at the default price.
+        assert_eq!(plans[0].cost, Decimal::new(50, 2));
+        assert_eq!(plans[0].baseline_cost, Decimal::new(150, 2));
+        assert_eq!(plans[0].savings(), Decimal::ONE);
+        assert!(LoadShifter::new(shifter.manager, shifter.tariff)
+            .load(DeferrableLoad::new("heater", 100.0, 5000.0))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn partial_hour_is_cut_short_once_the_budget_is_delivered() {
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        let shifter = LoadShifter::new(manager, Tariff::builder(Decimal::ONE).build().unwrap())
+            .load(DeferrableLoad::new("ev-charger", 2000.0, 5000.0))
+            .unwrap()
+            .load(DeferrableLoad::new("heater", 1000.0, 2000.0))
+            .unwrap();
+        let from = Local.with_ymd_and_hms(2024, 1, 1, 20, 15, 0).unwrap();
+
+        let plans = shifter.plan(from);
+
+        let at = |hour| Local.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap();
+        assert_eq!(plans[0].partial_slot, Some((at(22), Duration::minutes(30))));
+        assert_eq!(plans[0].on_duration_at(at(20)), Some(Duration::hours(1)));
+        assert_eq!(plans[0].on_duration_at(at(22)), Some(Duration::minutes(30)));
+        assert_eq!(plans[0].on_duration_at(at(23)), None);
+        assert_eq!(plans[1].partial_slot, None);
+        assert_eq!(plans[1].on_duration_at(at(21)), Some(Duration::hours(1)));
+    }
+
+    #[tokio::test]
+    async fn budget_of_a_whole_day_is_planned_in_every_hour() {
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        // Dividing the budget by the power gives slightly more than 24 hours.
+        let shifter = LoadShifter::new(manager, Tariff::builder(Decimal::ONE).build().unwrap())
+            .load(DeferrableLoad::new("ev-charger", 1.85, 1.85 * 24.0))
+            .unwrap();
+
+        let plans = shifter.plan(Local.with_ymd_and_hms(2024, 1, 1, 20, 15, 0).unwrap());
+
+        assert_eq!(plans[0].slots.len(), PLANNING_HOURS);
+        assert_eq!(plans[0].partial_slot, None);
+    }
+}
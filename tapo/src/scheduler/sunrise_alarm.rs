@@ -0,0 +1,222 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::debug;
+use tokio::sync::watch;
+
+use crate::error::Error;
+use crate::manager::DeviceManager;
+use crate::requests::{Brightness, IntoLightProperty, Kelvin};
+
+/// Wakes up to a simulated sunrise: turns *on* a color light at its lowest *brightness* and warmest
+/// *color temperature*, then raises both in even steps until they reach their target at the end of the ramp.
+///
+/// It's meant to be run by a [`crate::scheduler::Scheduler`] job, e.g. on weekdays at 6:30 for a wake-up at 7:00.
+/// The ramp in progress, if any, is stopped by [`SunriseAlarm::cancel`], e.g. when the alarm is snoozed,
+/// and the light is then left as it is. Clones share the cancellation.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use tapo::manager::DeviceManager;
+/// # use tapo::scheduler::{Scheduler, SunriseAlarm};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+///
+/// let alarm = SunriseAlarm::new("bedroom", Duration::from_secs(30 * 60))
+///     .with_color_temperature(2700, 5000)?;
+///
+/// let _scheduler = Scheduler::new(manager)
+///     .every("0 30 6 * * Mon-Fri", {
+///         let alarm = alarm.clone();
+///         move |manager| alarm.clone().run(manager)
+///     })?
+///     .start();
+///
+/// // Later, e.g. from a button handler.
+/// alarm.cancel();
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SunriseAlarm {
+    device: String,
+    duration: Duration,
+    steps: u32,
+    brightness: (u8, u8),
+    color_temperature: (u16, u16),
+    cancellations: Arc<watch::Sender<u64>>,
+}
+
+impl SunriseAlarm {
+    /// Returns a [`SunriseAlarm`] that raises the *brightness* from 1% to 100%
+    /// and the *color temperature* from 2500 K to 4000 K, in 60 steps.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the color light in the [`DeviceManager`]
+    /// * `duration` - the time between the light turning *on* and reaching its target
+    pub fn new(device: impl Into<String>, duration: Duration) -> Self {
+        let (cancellations, _) = watch::channel(0);
+
+        Self {
+            device: device.into(),
+            duration,
+            steps: 60,
+            brightness: (*Brightness::RANGE.start(), *Brightness::RANGE.end()),
+            color_temperature: (*Kelvin::RANGE.start(), 4000),
+            cancellations: Arc::new(cancellations),
+        }
+    }
+
+    /// Sets the *brightness* at the start and at the end of the ramp.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - between 1 and 100, see [`Brightness::RANGE`]
+    /// * `to` - between 1 and 100
+    pub fn with_brightness(
+        mut self,
+        from: impl IntoLightProperty<Brightness>,
+        to: impl IntoLightProperty<Brightness>,
+    ) -> Result<Self, Error> {
+        self.brightness = (
+            Brightness::try_from(from.into_raw())?.get(),
+            Brightness::try_from(to.into_raw())?.get(),
+        );
+        Ok(self)
+    }
+
+    /// Sets the *color temperature* at the start and at the end of the ramp.
+    /// The devices that support a narrower range than [`Kelvin::RANGE`] reject the values outside of it.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - in Kelvin, between 2500 and 6500
+    /// * `to` - in Kelvin, between 2500 and 6500
+    pub fn with_color_temperature(
+        mut self,
+        from: impl IntoLightProperty<Kelvin>,
+        to: impl IntoLightProperty<Kelvin>,
+    ) -> Result<Self, Error> {
+        self.color_temperature = (
+            Kelvin::try_from(from.into_raw())?.get(),
+            Kelvin::try_from(to.into_raw())?.get(),
+        );
+        Ok(self)
+    }
+
+    /// Sets the number of steps after the first one, spread evenly over the ramp. Defaults to 60, at least 1.
+    pub fn with_steps(mut self, steps: u32) -> Self {
+        self.steps = steps.max(1);
+        self
+    }
+
+    /// Runs the ramp, until it completes or is cancelled, which isn't an error.
+    /// Stops at the first request that fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `manager` - the manager of the light
+    pub async fn run(self, manager: DeviceManager) -> Result<(), Error> {
+        let mut cancelled = self.cancellations.subscribe();
+        let device = manager.device(self.device.as_str());
+        let interval = self.duration / self.steps;
+
+        for step in 0..=self.steps {
+            if step > 0 {
+                let wait = async {
+                    tokio::time::sleep(interval).await;
+                    false
+                };
+                let cancel = async { cancelled.changed().await.is_ok() };
+
+                if futures_lite::future::or(cancel, wait).await {
+                    debug!("The sunrise alarm of `{}` has been cancelled", self.device);
+                    return Ok(());
+                }
+            }
+
+            let (brightness, color_temperature) = self.values_at(step);
+            device.set_brightness(brightness).await?;
+            device.set_color_temperature(color_temperature).await?;
+        }
+
+        debug!("The sunrise alarm of `{}` has completed", self.device);
+
+        Ok(())
+    }
+
+    /// Stops the ramps in progress, leaving the light as it is. The next runs aren't affected.
+    pub fn cancel(&self) {
+        self.cancellations
+            .send_modify(|cancellations| *cancellations += 1);
+    }
+
+    /// Returns the *brightness* and the *color temperature* at `step`, interpolated linearly.
+    fn values_at(&self, step: u32) -> (u8, u16) {
+        let progress = f64::from(step) / f64::from(self.steps);
+        let interpolate = |from: f64, to: f64| (from + (to - from) * progress).round();
+
+        let (brightness_from, brightness_to) = self.brightness;
+        let (color_temperature_from, color_temperature_to) = self.color_temperature;
+
+        (
+            interpolate(brightness_from.into(), brightness_to.into()) as u8,
+            interpolate(color_temperature_from.into(), color_temperature_to.into()) as u16,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_interpolated() {
+        let alarm = SunriseAlarm::new("bedroom", Duration::from_secs(600))
+            .with_color_temperature(2700, 4700)
+            .unwrap()
+            .with_steps(4);
+
+        assert_eq!(alarm.values_at(0), (1, 2700));
+        assert_eq!(alarm.values_at(2), (51, 3700));
+        assert_eq!(alarm.values_at(4), (100, 4700));
+        assert!(matches!(
+            alarm.with_brightness(0, 100),
+            Err(Error::Validation { field, .. }) if field == "brightness"
+        ));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn ramp_stops_when_cancelled() {
+        use crate::manager::DeviceKind;
+        use crate::simulator::FakeDevice;
+        use crate::ApiClient;
+
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        let lamp = FakeDevice::light();
+        manager
+            .register_fake("bedroom", DeviceKind::ColorLight, lamp.clone())
+            .await
+            .unwrap();
+
+        let alarm = SunriseAlarm::new("bedroom", Duration::from_millis(100)).with_steps(2);
+        alarm.clone().run(manager.clone()).await.unwrap();
+        assert_eq!(lamp.device_info()["brightness"], 100);
+        assert_eq!(lamp.device_info()["color_temp"], 4000);
+
+        let alarm = SunriseAlarm::new("bedroom", Duration::from_secs(60)).with_steps(2);
+        alarm.cancel();
+        let ramp = tokio::spawn(alarm.clone().run(manager));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!ramp.is_finished(), "the earlier cancellations don't apply");
+
+        alarm.cancel();
+        ramp.await.unwrap().unwrap();
+        assert_eq!(lamp.device_info()["brightness"], 1);
+    }
+}
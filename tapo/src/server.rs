@@ -0,0 +1,13 @@
+//! An embedded REST API for controlling the devices of a [`crate::manager::DeviceManager`] over HTTP,
+//! for the applications that would otherwise wrap the manager in their own thin web server.
+//!
+//! See [`RestServer`] for the routes.
+//!
+//! Requires the `server` feature.
+
+mod rest_server;
+mod server_error;
+
+pub use rest_server::*;
+
+pub(crate) use server_error::*;
@@ -0,0 +1,556 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Request, State};
+use axum::http::{header, Method, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use log::{debug, info};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::access::{AccessPolicy, Role};
+use crate::error::Error;
+use crate::manager::{DeviceKind, DeviceManager, ManagedDeviceEvent};
+use crate::responses::EnergyUsageResult;
+use crate::scenes::SceneState;
+#[cfg(feature = "openapi")]
+use crate::server::ErrorBody;
+use crate::server::ServerError;
+
+const EVENTS_ROUTE: &str = "/events";
+
+/// Serves a REST API for the devices of a [`DeviceManager`], by the name they are registered under.
+///
+/// | Route                        | Description                                                          |
+/// |------------------------------|----------------------------------------------------------------------|
+/// | `GET /devices`               | The registered devices, e.g. `[{"name": "kitchen", "kind": "plug"}]` |
+/// | `GET /devices/{name}`        | The *device info* of a device, with all the properties it returns    |
+/// | `POST /devices/{name}/on`    | Turns *on* a device                                                  |
+/// | `POST /devices/{name}/off`   | Turns *off* a device                                                 |
+/// | `PUT /devices/{name}/state`  | Changes the state of a device to a [`SceneState`] JSON body          |
+/// | `GET /devices/{name}/energy` | The [`EnergyUsageResult`] of a plug with energy monitoring           |
+/// | `GET /events`                | A WebSocket streaming the [`ManagedDeviceEvent`]s as JSON messages   |
+///
+/// The errors are returned as a JSON object with an `error` field, with the status code
+/// `404` for the devices that aren't registered, `400` for the invalid states,
+/// `501` for the properties that the device doesn't support and `502` for the other errors, e.g. an unreachable device.
+///
+/// The WebSocket sends the events of [`DeviceManager::subscribe`], e.g.
+/// `{"name":"kitchen","event":{"type":"power_state_changed","device_on":true}}`,
+/// so that dashboards get live updates without polling the other routes.
+/// The events missed by a client that can't keep up are skipped.
+///
+/// With [`RestServer::with_access_policy`], every route requires an access token, as an `Authorization: Bearer <token>`
+/// header or, only for the `/events` WebSocket upgrade since browsers can't set headers on WebSockets,
+/// a percent-encoded `access_token` query parameter.
+/// The `GET` routes require [`Role::Read`] and the others [`Role::Control`], and the requests without a valid token
+/// are rejected with `401`, and those with a token that doesn't grant the required role with `403`.
+///
+/// With the `openapi` feature, the OpenAPI document of the routes is served at `GET /openapi.json`,
+/// see [`RestServer::openapi`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use tapo::manager::DeviceManager;
+/// # use tapo::server::RestServer;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manager = DeviceManager::from_config("devices.json").await?;
+///
+/// RestServer::new(manager)
+///     .serve("0.0.0.0:8080".parse()?)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RestServer {
+    manager: DeviceManager,
+    access_policy: Option<Arc<AccessPolicy>>,
+}
+
+/// A device of `GET /devices`.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+struct RegisteredDevice {
+    /// The name that the device is registered under.
+    name: String,
+    kind: DeviceKind,
+}
+
+#[cfg(feature = "openapi")]
+#[derive(utoipa::OpenApi)]
+#[openapi(
+    paths(
+        list_devices,
+        get_device_info,
+        turn_on,
+        turn_off,
+        set_state,
+        get_energy_usage
+    ),
+    components(schemas(RegisteredDevice, DeviceKind, SceneState, EnergyUsageResult, ErrorBody))
+)]
+struct ApiDoc;
+
+impl RestServer {
+    /// Returns a [`RestServer`] for the devices of `manager`.
+    pub fn new(manager: DeviceManager) -> Self {
+        Self {
+            manager,
+            access_policy: None,
+        }
+    }
+
+    /// Requires the access tokens of `policy` for every route, see [`crate::access`].
+    pub fn with_access_policy(mut self, policy: AccessPolicy) -> Self {
+        self.access_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// Returns the routes as an [`axum::Router`], to serve them together with other routes or behind middleware.
+    pub fn router(self) -> Router {
+        let router = Router::new()
+            .route("/devices", get(list_devices))
+            .route("/devices/:name", get(get_device_info))
+            .route("/devices/:name/on", post(turn_on))
+            .route("/devices/:name/off", post(turn_off))
+            .route("/devices/:name/state", put(set_state))
+            .route("/devices/:name/energy", get(get_energy_usage))
+            .route(EVENTS_ROUTE, get(stream_events));
+
+        #[cfg(feature = "openapi")]
+        let router = router.route("/openapi.json", get(|| async { Json(Self::openapi()) }));
+
+        let router = match self.access_policy {
+            Some(policy) => router.route_layer(middleware::from_fn_with_state(policy, authorize)),
+            None => router,
+        };
+
+        router.with_state(self.manager)
+    }
+
+    /// Returns the OpenAPI document of the routes, from which the clients can be generated.
+    /// The `/events` WebSocket can't be described by OpenAPI and is left out.
+    /// Requires the `openapi` feature.
+    #[cfg(feature = "openapi")]
+    pub fn openapi() -> utoipa::openapi::OpenApi {
+        <ApiDoc as utoipa::OpenApi>::openapi()
+    }
+
+    /// Serves the routes on `address` until the server fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - the address to listen on, e.g. `0.0.0.0:8080`
+    pub async fn serve(self, address: SocketAddr) -> Result<(), Error> {
+        info!("Serving the REST API on {address}...");
+
+        let listener = tokio::net::TcpListener::bind(address)
+            .await
+            .map_err(anyhow::Error::from)?;
+        axum::serve(listener, self.router())
+            .await
+            .map_err(anyhow::Error::from)?;
+
+        Ok(())
+    }
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/devices",
+        responses(
+            (status = 200, description = "The registered devices", body = [RegisteredDevice]),
+            (status = 502, description = "The request to the device has failed", body = ErrorBody),
+        )
+    )
+)]
+async fn list_devices(
+    State(manager): State<DeviceManager>,
+) -> Result<Json<Vec<RegisteredDevice>>, ServerError> {
+    let devices = manager
+        .devices()
+        .await?
+        .into_iter()
+        .map(|(name, kind)| RegisteredDevice { name, kind })
+        .collect();
+
+    Ok(Json(devices))
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/devices/{name}",
+        params(("name" = String, Path, description = "The name that the device is registered under")),
+        responses(
+            (status = 200, description = "The device info, with all the properties returned by the device", body = Object),
+            (status = 404, description = "The device isn't registered", body = ErrorBody),
+            (status = 502, description = "The request to the device has failed", body = ErrorBody),
+        )
+    )
+)]
+async fn get_device_info(
+    State(manager): State<DeviceManager>,
+    Path(name): Path<String>,
+) -> Result<Json<serde_json::Value>, ServerError> {
+    Ok(Json(manager.device(name).get_device_info_json().await?))
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/devices/{name}/on",
+        params(("name" = String, Path, description = "The name that the device is registered under")),
+        responses(
+            (status = 204, description = "The device has been turned on"),
+            (status = 404, description = "The device isn't registered", body = ErrorBody),
+            (status = 501, description = "The device doesn't support the request", body = ErrorBody),
+            (status = 502, description = "The request to the device has failed", body = ErrorBody),
+        )
+    )
+)]
+async fn turn_on(
+    State(manager): State<DeviceManager>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ServerError> {
+    manager.device(name).on().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        post,
+        path = "/devices/{name}/off",
+        params(("name" = String, Path, description = "The name that the device is registered under")),
+        responses(
+            (status = 204, description = "The device has been turned off"),
+            (status = 404, description = "The device isn't registered", body = ErrorBody),
+            (status = 501, description = "The device doesn't support the request", body = ErrorBody),
+            (status = 502, description = "The request to the device has failed", body = ErrorBody),
+        )
+    )
+)]
+async fn turn_off(
+    State(manager): State<DeviceManager>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, ServerError> {
+    manager.device(name).off().await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        put,
+        path = "/devices/{name}/state",
+        params(("name" = String, Path, description = "The name that the device is registered under")),
+        request_body = SceneState,
+        responses(
+            (status = 204, description = "The state of the device has been changed"),
+            (status = 400, description = "The state is invalid", body = ErrorBody),
+            (status = 404, description = "The device isn't registered", body = ErrorBody),
+            (status = 501, description = "The device doesn't support the request", body = ErrorBody),
+            (status = 502, description = "The request to the device has failed", body = ErrorBody),
+        )
+    )
+)]
+async fn set_state(
+    State(manager): State<DeviceManager>,
+    Path(name): Path<String>,
+    Json(state): Json<SceneState>,
+) -> Result<StatusCode, ServerError> {
+    state.validate()?;
+    state.apply(&manager.device(name)).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg_attr(
+    feature = "openapi",
+    utoipa::path(
+        get,
+        path = "/devices/{name}/energy",
+        params(("name" = String, Path, description = "The name that the device is registered under")),
+        responses(
+            (status = 200, description = "The energy usage of the device", body = EnergyUsageResult),
+            (status = 404, description = "The device isn't registered", body = ErrorBody),
+            (status = 501, description = "The device doesn't support the request", body = ErrorBody),
+            (status = 502, description = "The request to the device has failed", body = ErrorBody),
+        )
+    )
+)]
+async fn get_energy_usage(
+    State(manager): State<DeviceManager>,
+    Path(name): Path<String>,
+) -> Result<Json<EnergyUsageResult>, ServerError> {
+    Ok(Json(manager.device(name).get_energy_usage().await?))
+}
+
+async fn stream_events(
+    State(manager): State<DeviceManager>,
+    websocket: WebSocketUpgrade,
+) -> Response {
+    let events = manager.subscribe();
+    websocket.on_upgrade(|socket| send_events(socket, events))
+}
+
+async fn authorize(
+    State(policy): State<Arc<AccessPolicy>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ServerError> {
+    let required = match *request.method() {
+        Method::GET | Method::HEAD => Role::Read,
+        _ => Role::Control,
+    };
+
+    match request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok())
+    {
+        Some(authorization) => policy.authorize_bearer(Some(authorization), required)?,
+        None if is_events_upgrade(&request) => {
+            let token = request.uri().query().and_then(|query| {
+                form_urlencoded::parse(query.as_bytes())
+                    .find_map(|(name, value)| (name == "access_token").then_some(value))
+            });
+            policy.authorize(token.as_deref(), required)?
+        }
+        None => policy.authorize(None, required)?,
+    };
+
+    Ok(next.run(request).await)
+}
+
+/// Whether `request` upgrades to the `/events` WebSocket, the only route that accepts the token as a query parameter.
+fn is_events_upgrade(request: &Request) -> bool {
+    request.uri().path() == EVENTS_ROUTE
+        && request
+            .headers()
+            .get(header::UPGRADE)
+            .is_some_and(|upgrade| upgrade.as_bytes().eq_ignore_ascii_case(b"websocket"))
+}
+
+async fn send_events(mut socket: WebSocket, mut events: broadcast::Receiver<ManagedDeviceEvent>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                debug!("A WebSocket client has missed {skipped} events");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        let Ok(event) = serde_json::to_string(&event) else {
+            continue;
+        };
+        if socket.send(Message::Text(event)).await.is_err() {
+            debug!("A WebSocket client has disconnected");
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "openapi")]
+    #[test]
+    fn openapi_document_describes_the_routes() {
+        use super::*;
+
+        let openapi = serde_json::to_value(RestServer::openapi()).unwrap();
+
+        let paths = openapi["paths"].as_object().unwrap();
+        assert_eq!(paths.len(), 6);
+        assert!(paths["/devices/{name}/energy"]["get"]["responses"]["200"].is_object());
+        assert!(paths["/devices/{name}/state"]["put"]["requestBody"].is_object());
+
+        let schemas = openapi["components"]["schemas"].as_object().unwrap();
+        assert!(schemas.contains_key("SceneState"));
+        assert!(schemas.contains_key("EnergyUsageResult"));
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn devices_are_controlled_through_the_routes() {
+        use isahc::{AsyncReadResponseExt, Request, RequestExt};
+
+        use crate::simulator::SimulatedFleet;
+        use crate::ApiClient;
+
+        use super::*;
+
+        let fleet = SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        manager
+            .register(
+                "kitchen",
+                DeviceKind::PlugEnergyMonitoring,
+                &fleet.addresses()[0],
+            )
+            .await
+            .unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let router = RestServer::new(manager).router();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let mut response = isahc::get_async(format!("{url}/devices")).await.unwrap();
+        assert_eq!(
+            response.json::<serde_json::Value>().await.unwrap(),
+            serde_json::json!([{ "name": "kitchen", "kind": "plug_energy_monitoring" }])
+        );
+
+        let response = isahc::post_async(format!("{url}/devices/kitchen/off"), ())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 204);
+        let mut response = isahc::get_async(format!("{url}/devices/kitchen"))
+            .await
+            .unwrap();
+        let device_info = response.json::<serde_json::Value>().await.unwrap();
+        assert_eq!(device_info["device_on"], false);
+
+        let mut response = Request::put(format!("{url}/devices/kitchen/state"))
+            .header("content-type", "application/json")
+            .body(r#"{ "brightness": 50 }"#)
+            .unwrap()
+            .send_async()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 501);
+        let error = response.json::<serde_json::Value>().await.unwrap();
+        assert!(error["error"].as_str().unwrap().contains("Brightness"));
+
+        let response = isahc::get_async(format!("{url}/devices/attic/energy"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn events_are_streamed_over_websocket() {
+        use futures_lite::StreamExt;
+        use tokio_tungstenite::tungstenite::Message;
+
+        use crate::simulator::SimulatedFleet;
+        use crate::ApiClient;
+
+        use super::*;
+
+        let fleet = SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        manager
+            .register("kitchen", DeviceKind::Plug, &fleet.addresses()[0])
+            .await
+            .unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let router = RestServer::new(manager.clone()).router();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{address}/events"))
+            .await
+            .unwrap();
+        manager.device("kitchen").off().await.unwrap();
+
+        let Some(Ok(Message::Text(event))) = socket.next().await else {
+            panic!("expected a text message");
+        };
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&event).unwrap(),
+            serde_json::json!({
+                "name": "kitchen",
+                "event": { "type": "power_state_changed", "device_on": false },
+            })
+        );
+    }
+
+    #[cfg(feature = "simulator")]
+    #[tokio::test]
+    async fn routes_require_a_token_with_the_role() {
+        use isahc::{Request, RequestExt};
+
+        use crate::simulator::SimulatedFleet;
+        use crate::ApiClient;
+
+        use super::*;
+
+        let fleet = SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        let manager = DeviceManager::new(ApiClient::new("username", "password").unwrap());
+        manager
+            .register("freezer", DeviceKind::Plug, &fleet.addresses()[0])
+            .await
+            .unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let policy = AccessPolicy::new()
+            .token("dashboard-token", Role::Read)
+            .token("automation-token", Role::Control);
+        let router = RestServer::new(manager).with_access_policy(policy).router();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let request = |method: &str, route: &str, token: &str| {
+            Request::builder()
+                .method(method)
+                .uri(format!("{url}{route}"))
+                .header("authorization", format!("Bearer {token}"))
+                .body(())
+                .unwrap()
+                .send_async()
+        };
+
+        let response = isahc::get_async(format!("{url}/devices")).await.unwrap();
+        assert_eq!(response.status(), 401);
+        let response = request("GET", "/devices/freezer", "dashboard-token")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let response = isahc::get_async(format!("{url}/devices?access_token=dashboard-token"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 401);
+        let address = url.trim_start_matches("http://");
+        assert!(
+            tokio_tungstenite::connect_async(format!("ws://{address}/events"))
+                .await
+                .is_err()
+        );
+        assert!(tokio_tungstenite::connect_async(format!(
+            "ws://{address}/events?access_token=dashboard%2Dtoken"
+        ))
+        .await
+        .is_ok());
+        let response = request("POST", "/devices/freezer/off", "dashboard-token")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 403);
+        let response = request("POST", "/devices/freezer/off", "automation-token")
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 204);
+    }
+}
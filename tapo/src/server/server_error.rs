@@ -0,0 +1,46 @@
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Serialize;
+
+use crate::error::Error;
+
+/// An [`Error`] returned by a route of the [`crate::server::RestServer`],
+/// as a JSON object with an `error` field and the matching status code.
+#[derive(Debug)]
+pub(crate) struct ServerError(pub Error);
+
+/// The body of the error responses.
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub(crate) struct ErrorBody {
+    /// The description of the error.
+    pub error: String,
+}
+
+impl From<Error> for ServerError {
+    fn from(err: Error) -> Self {
+        Self(err)
+    }
+}
+
+impl IntoResponse for ServerError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            Error::Validation { field, .. } if field == "name" => StatusCode::NOT_FOUND,
+            Error::Validation { .. } => StatusCode::BAD_REQUEST,
+            Error::Unauthenticated => StatusCode::UNAUTHORIZED,
+            Error::Forbidden { .. } => StatusCode::FORBIDDEN,
+            Error::NotSupported { .. } | Error::UnsupportedFirmware { .. } => {
+                StatusCode::NOT_IMPLEMENTED
+            }
+            Error::Maintenance { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            _ => StatusCode::BAD_GATEWAY,
+        };
+
+        let body = ErrorBody {
+            error: self.0.to_string(),
+        };
+        (status, Json(body)).into_response()
+    }
+}
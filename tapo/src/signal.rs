@@ -0,0 +1,311 @@
+//! Wi-Fi signal strength over time, for finding the devices with degraded connectivity,
+//! e.g. after moving the router or adding a mesh node.
+//!
+//! A [`SignalSampler`] collects the RSSI of the devices, from their *device info*, and ranks them
+//! in a [`SignalReport`] by how poor and how quickly degrading their signal is.
+//! The trend of each device is the slope of the least squares line through its samples.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use chrono::{Duration, TimeZone, Utc};
+//! # use tapo::signal::{SignalQuality, SignalSampler};
+//! let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+//! let mut sampler = SignalSampler::new();
+//!
+//! for hour in 0..4 {
+//!     let timestamp = start + Duration::hours(hour);
+//!     sampler.record("kitchen", timestamp, -48);
+//!     sampler.record("garage", timestamp, -66 - 3 * hour as i16);
+//! }
+//!
+//! let report = sampler.report();
+//! assert_eq!(report.devices[0].device, "garage");
+//! assert_eq!(report.devices[0].quality, SignalQuality::Poor);
+//! assert!(report.devices[0].is_degrading);
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+#[cfg(feature = "manager")]
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "manager")]
+use crate::error::Error;
+#[cfg(feature = "manager")]
+use crate::manager::DeviceManager;
+
+/// How good a Wi-Fi signal is, from its mean RSSI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignalQuality {
+    /// -70 dBm or lower, where requests start to time out.
+    Poor,
+    /// Between -70 and -60 dBm.
+    Fair,
+    /// -60 dBm or higher.
+    Good,
+}
+
+impl SignalQuality {
+    /// Returns the [`SignalQuality`] of a signal of `rssi` dBm.
+    pub fn from_rssi(rssi: f64) -> Self {
+        if rssi <= -70.0 {
+            Self::Poor
+        } else if rssi < -60.0 {
+            Self::Fair
+        } else {
+            Self::Good
+        }
+    }
+}
+
+/// The signal of a device over the sampled period, see [`SignalReport`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceSignal {
+    /// The name of the device.
+    pub device: String,
+    /// The number of samples.
+    pub samples: usize,
+    /// The latest RSSI in dBm.
+    pub latest_rssi: i16,
+    /// The weakest RSSI in dBm.
+    pub min_rssi: i16,
+    /// The mean RSSI in dBm.
+    pub mean_rssi: f64,
+    /// How much the RSSI changes per hour, in dB, negative when the signal is getting weaker.
+    /// `None` with fewer than two samples or when all the samples have the same timestamp.
+    pub trend_db_per_hour: Option<f64>,
+    /// The quality of [`DeviceSignal::mean_rssi`].
+    pub quality: SignalQuality,
+    /// Whether the signal gets weaker faster than [`SignalSampler::degrading_threshold`].
+    pub is_degrading: bool,
+}
+
+impl DeviceSignal {
+    /// Returns `true` if the signal is poor or degrading.
+    pub fn is_degraded(&self) -> bool {
+        self.quality == SignalQuality::Poor || self.is_degrading
+    }
+}
+
+/// The signal of every sampled device, worst first, see [`SignalSampler::report`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignalReport {
+    /// The devices, ranked by degrading signal, then quality, then mean RSSI.
+    pub devices: Vec<DeviceSignal>,
+}
+
+impl SignalReport {
+    /// Returns the devices whose signal is poor or degrading, worst first.
+    pub fn degraded(&self) -> impl Iterator<Item = &DeviceSignal> {
+        self.devices.iter().filter(|device| device.is_degraded())
+    }
+}
+
+/// Collects RSSI samples of many devices and ranks them by connectivity, see [`crate::signal`].
+///
+/// Only the latest [`SignalSampler::max_samples`] of each device are kept.
+#[derive(Debug, Clone)]
+pub struct SignalSampler {
+    samples: BTreeMap<String, VecDeque<(DateTime<Utc>, i16)>>,
+    max_samples: usize,
+    degrading_threshold: f64,
+}
+
+impl Default for SignalSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignalSampler {
+    /// Returns an empty [`SignalSampler`] that keeps 1000 samples per device
+    /// and considers a signal degrading when it loses more than 0.5 dB per hour.
+    pub fn new() -> Self {
+        Self {
+            samples: BTreeMap::new(),
+            max_samples: 1000,
+            degrading_threshold: 0.5,
+        }
+    }
+
+    /// Sets how many of the latest samples of each device are kept. Defaults to 1000.
+    pub fn max_samples(mut self, max_samples: usize) -> Self {
+        self.max_samples = max_samples.max(2);
+        self
+    }
+
+    /// Sets how many dB per hour a signal must lose to be degrading. Defaults to 0.5.
+    pub fn degrading_threshold(mut self, db_per_hour: f64) -> Self {
+        self.degrading_threshold = db_per_hour.abs();
+        self
+    }
+
+    /// Adds a sample of `device`.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the device
+    /// * `timestamp` - when the sample was taken
+    /// * `rssi` - the signal strength in dBm
+    pub fn record(&mut self, device: impl Into<String>, timestamp: DateTime<Utc>, rssi: i16) {
+        let samples = self.samples.entry(device.into()).or_default();
+        if samples.len() >= self.max_samples {
+            samples.pop_front();
+        }
+        samples.push_back((timestamp, rssi));
+    }
+
+    /// Adds the `rssi` of the *device info* of `device`, if it has one.
+    /// Returns `true` if a sample has been added.
+    pub fn record_device_info(
+        &mut self,
+        device: impl Into<String>,
+        timestamp: DateTime<Utc>,
+        device_info: &serde_json::Value,
+    ) -> bool {
+        let Some(rssi) = device_info
+            .get("rssi")
+            .and_then(serde_json::Value::as_i64)
+            .and_then(|rssi| i16::try_from(rssi).ok())
+        else {
+            return false;
+        };
+
+        self.record(device, timestamp, rssi);
+        true
+    }
+
+    /// Samples the *device info* of every device registered with `manager`.
+    /// The devices that can't be reached are skipped, the other errors are returned.
+    #[cfg(feature = "manager")]
+    pub async fn sample(&mut self, manager: &DeviceManager) -> Result<(), Error> {
+        for name in manager.devices().await?.into_keys() {
+            let device_info = match manager.device(name.as_str()).get_device_info_json().await {
+                Ok(device_info) => device_info,
+                Err(err) if err.is_unreachable() => {
+                    debug!("Skipping the signal of unreachable device `{name}`");
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            self.record_device_info(name, Utc::now(), &device_info);
+        }
+
+        Ok(())
+    }
+
+    /// Forgets the samples of all the devices, e.g. to start over after moving the router.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Returns the [`SignalReport`] of the samples collected so far.
+    pub fn report(&self) -> SignalReport {
+        let mut devices: Vec<_> = self
+            .samples
+            .iter()
+            .filter_map(|(device, samples)| self.device_signal(device, samples))
+            .collect();
+
+        devices.sort_by(|a, b| {
+            b.is_degrading
+                .cmp(&a.is_degrading)
+                .then(a.quality.cmp(&b.quality))
+                .then(a.mean_rssi.total_cmp(&b.mean_rssi))
+        });
+
+        SignalReport { devices }
+    }
+
+    fn device_signal(
+        &self,
+        device: &str,
+        samples: &VecDeque<(DateTime<Utc>, i16)>,
+    ) -> Option<DeviceSignal> {
+        let (_, latest_rssi) = *samples.back()?;
+        let count = samples.len() as f64;
+        let mean_rssi = samples.iter().map(|(_, rssi)| *rssi as f64).sum::<f64>() / count;
+        let min_rssi = samples.iter().map(|(_, rssi)| *rssi).min()?;
+        let trend_db_per_hour = trend(samples);
+
+        Some(DeviceSignal {
+            device: device.to_string(),
+            samples: samples.len(),
+            latest_rssi,
+            min_rssi,
+            mean_rssi,
+            trend_db_per_hour,
+            quality: SignalQuality::from_rssi(mean_rssi),
+            is_degrading: trend_db_per_hour.is_some_and(|trend| trend < -self.degrading_threshold),
+        })
+    }
+}
+
+/// Returns the slope of the least squares line through `samples`, in dB per hour.
+fn trend(samples: &VecDeque<(DateTime<Utc>, i16)>) -> Option<f64> {
+    let (origin, _) = *samples.front()?;
+    let points: Vec<_> = samples
+        .iter()
+        .map(|(timestamp, rssi)| {
+            let hours = (*timestamp - origin).num_milliseconds() as f64 / 3_600_000.0;
+            (hours, *rssi as f64)
+        })
+        .collect();
+
+    let count = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / count;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / count;
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+
+    (variance > 0.0).then(|| covariance / variance)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn report_ranks_degrading_devices_first() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut sampler = SignalSampler::new().max_samples(3);
+
+        for (hour, rssi) in [-50, -52, -54, -56].into_iter().enumerate() {
+            let timestamp = start + Duration::hours(hour as i64);
+            sampler.record("hallway", timestamp, rssi);
+            sampler.record("attic", timestamp, -75);
+            sampler.record("kitchen", timestamp, -45);
+        }
+        assert!(!sampler.record_device_info("hub", start, &serde_json::json!({})));
+
+        let report = sampler.report();
+        let names: Vec<_> = report
+            .devices
+            .iter()
+            .map(|device| device.device.as_str())
+            .collect();
+        assert_eq!(names, ["hallway", "attic", "kitchen"]);
+
+        let hallway = &report.devices[0];
+        assert_eq!(hallway.samples, 3);
+        assert_eq!(hallway.min_rssi, -56);
+        assert_eq!(hallway.trend_db_per_hour, Some(-2.0));
+        assert_eq!(hallway.quality, SignalQuality::Good);
+
+        let degraded: Vec<_> = report
+            .degraded()
+            .map(|device| device.device.as_str())
+            .collect();
+        assert_eq!(degraded, ["hallway", "attic"]);
+    }
+}
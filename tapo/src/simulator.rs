@@ -0,0 +1,22 @@
+//! Simulated devices for load testing and demos without real hardware.
+//!
+//! A [`SimulatedFleet`] runs any number of simulated P110 plugs on the loopback interface.
+//! They speak the KLAP protocol, or the Passthrough protocol after [`SimulatedFleet::set_protocol`],
+//! so they can be controlled with an unmodified [`crate::ApiClient`],
+//! and they report drifting power readings and accumulating energy usage while they are on.
+//! Like the real devices, they only keep the session of the client that has logged in most recently.
+//!
+//! A [`FakeDevice`] is an in-memory device for unit testing automations instead,
+//! with scriptable state transitions and injectable failures.
+//!
+//! Requires the `simulator` feature.
+
+mod fake_device;
+mod http;
+mod simulated_device;
+mod simulated_fleet;
+
+pub use fake_device::*;
+pub use simulated_fleet::*;
+
+pub(crate) use simulated_device::*;
@@ -0,0 +1,404 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use serde_json::{json, Value};
+
+use crate::error::{Error, TapoResponseError};
+use crate::responses::{Capabilities, ComponentListResult, EnergyUsageResult};
+use crate::watcher::WatchableDevice;
+
+/// The error code returned for the methods that a [`FakeDevice`] doesn't implement, like the real devices do.
+const UNSUPPORTED_METHOD_ERROR_CODE: i32 = -40210;
+
+type Transition = Arc<dyn Fn(&mut Value, &Value) + Send + Sync>;
+
+/// An in-memory device with deterministic behavior, for unit testing automations without a network.
+///
+/// It keeps its *device info* as [`serde_json::Value`] and implements the requests the way the real devices do:
+/// `set_device_info` requests, sent by [`FakeDevice::on`] or [`FakeDevice::set_brightness`],
+/// are merged into the *device info*. On top of that:
+///
+/// * [`FakeDevice::on_request`] scripts additional state transitions, e.g. a relay that doesn't switch
+/// * [`FakeDevice::fail_next`] and [`FakeDevice::set_reachable`] inject failures
+/// * [`FakeDevice::set`] changes the state from the outside, like someone pressing the button of the device
+/// * [`FakeDevice::requests`] returns the requests received, to assert on what an automation has done
+///
+/// A [`FakeDevice`] is cheap to clone and the clones share their state, so a test can keep one
+/// while the other is watched with [`crate::watcher::DeviceWatcher`] or registered with
+/// [`crate::manager::DeviceManager::register_fake`].
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use tapo::simulator::FakeDevice;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), tapo::Error> {
+/// let plug = FakeDevice::plug().with_power(1500.0);
+/// plug.on_request("set_device_info", |device_info, params| {
+///     // The overload protection trips whenever the plug is turned on.
+///     if params["device_on"] == true {
+///         device_info["device_on"] = false.into();
+///     }
+/// });
+///
+/// plug.on().await?;
+/// assert_eq!(plug.device_info()["device_on"], false);
+/// assert_eq!(plug.requests().len(), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct FakeDevice {
+    state: Arc<Mutex<FakeState>>,
+}
+
+struct FakeState {
+    device_info: Value,
+    components: BTreeMap<String, u32>,
+    /// The power drawn while the device is on, in W.
+    power_w: f64,
+    /// In Wh.
+    today_energy_wh: u64,
+    reachable: bool,
+    failures: VecDeque<Error>,
+    transitions: BTreeMap<String, Vec<Transition>>,
+    requests: Vec<FakeRequest>,
+}
+
+/// A request received by a [`FakeDevice`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeRequest {
+    /// The method of the request, e.g. `set_device_info`.
+    pub method: String,
+    /// The parameters of the request, or [`serde_json::Value::Null`] if it has none.
+    pub params: Value,
+}
+
+impl FakeDevice {
+    /// Returns a [`FakeDevice`] with the given *device info*, e.g. recorded from a real device
+    /// with `get_device_info_json`, and without components.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_info` - the initial *device info*, which must be a JSON object
+    pub fn new(device_info: Value) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(FakeState {
+                device_info,
+                components: BTreeMap::new(),
+                power_w: 0.0,
+                today_energy_wh: 0,
+                reachable: true,
+                failures: VecDeque::new(),
+                transitions: BTreeMap::new(),
+                requests: Vec::new(),
+            })),
+        }
+    }
+
+    /// Returns a [`FakeDevice`] that behaves like a P110 plug that is off and draws no power.
+    pub fn plug() -> Self {
+        Self::new(json!({
+            "device_id": "FAKE-P110",
+            "type": "SMART.TAPOPLUG",
+            "model": "P110",
+            "nickname": general_purpose::STANDARD.encode("Fake Plug"),
+            "device_on": false,
+            "on_time": 0,
+            "overheated": false,
+        }))
+        .with_components(["device", "energy_monitoring", "child_protection"])
+    }
+
+    /// Returns a [`FakeDevice`] that behaves like a L530 color light bulb that is off.
+    pub fn light() -> Self {
+        Self::new(json!({
+            "device_id": "FAKE-L530",
+            "type": "SMART.TAPOBULB",
+            "model": "L530",
+            "nickname": general_purpose::STANDARD.encode("Fake Light"),
+            "device_on": false,
+            "brightness": 100,
+            "hue": 0,
+            "saturation": 100,
+            "color_temp": 2700,
+            "overheated": false,
+        }))
+        .with_components(["device", "brightness", "color", "color_temperature"])
+    }
+
+    /// Sets the components negotiated by the device, from which [`FakeDevice::get_capabilities`] is derived.
+    pub fn with_components<I, S>(self, components: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.lock().components = components
+            .into_iter()
+            .map(|component| (component.into(), 1))
+            .collect();
+        self
+    }
+
+    /// Sets the power drawn by the device while it's on, in W, as reported by [`FakeDevice::get_energy_usage`].
+    pub fn with_power(self, power_w: f64) -> Self {
+        self.lock().power_w = power_w;
+        self
+    }
+
+    /// Runs `transition` with the *device info* and the parameters of every request for `method`,
+    /// after the request has been handled. The transitions of a method run in the order in which they've been added.
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - the method of the requests, e.g. `set_device_info`
+    /// * `transition` - changes the *device info* given the parameters of the request
+    pub fn on_request(
+        &self,
+        method: impl Into<String>,
+        transition: impl Fn(&mut Value, &Value) + Send + Sync + 'static,
+    ) {
+        self.lock()
+            .transitions
+            .entry(method.into())
+            .or_default()
+            .push(Arc::new(transition));
+    }
+
+    /// Makes the next request fail with `error` instead of being handled.
+    /// The failures are returned in the order in which they've been added.
+    pub fn fail_next(&self, error: Error) {
+        self.lock().failures.push_back(error);
+    }
+
+    /// Sets whether the device is reachable. The requests to an unreachable device fail with [`Error::Unreachable`].
+    pub fn set_reachable(&self, reachable: bool) {
+        self.lock().reachable = reachable;
+    }
+
+    /// Sets a field of the *device info*, without a request.
+    pub fn set(&self, field: &str, value: impl Into<Value>) {
+        self.lock().device_info[field] = value.into();
+    }
+
+    /// Sets the energy used today, in Wh, as reported by [`FakeDevice::get_energy_usage`].
+    pub fn set_today_energy(&self, today_energy_wh: u64) {
+        self.lock().today_energy_wh = today_energy_wh;
+    }
+
+    /// Returns the current *device info*, without a request.
+    pub fn device_info(&self) -> Value {
+        self.lock().device_info.clone()
+    }
+
+    /// Returns the requests received so far, including those that have failed.
+    pub fn requests(&self) -> Vec<FakeRequest> {
+        self.lock().requests.clone()
+    }
+
+    /// Turns *on* the device.
+    pub async fn on(&self) -> Result<(), Error> {
+        self.set_device_info(json!({ "device_on": true })).await
+    }
+
+    /// Turns *off* the device.
+    pub async fn off(&self) -> Result<(), Error> {
+        self.set_device_info(json!({ "device_on": false })).await
+    }
+
+    /// Sets the *brightness*. Requires the `brightness` component.
+    pub async fn set_brightness(&self, brightness: u8) -> Result<(), Error> {
+        self.require("brightness")?;
+        self.set_device_info(json!({ "brightness": brightness }))
+            .await
+    }
+
+    /// Sets the *hue* and *saturation*. Requires the `color` component.
+    pub async fn set_hue_saturation(&self, hue: u16, saturation: u8) -> Result<(), Error> {
+        self.require("color")?;
+        self.set_device_info(json!({ "hue": hue, "saturation": saturation, "color_temp": 0 }))
+            .await
+    }
+
+    /// Sets the *color temperature*. Requires the `color_temperature` component.
+    pub async fn set_color_temperature(&self, color_temperature: u16) -> Result<(), Error> {
+        self.require("color_temperature")?;
+        self.set_device_info(json!({ "color_temp": color_temperature }))
+            .await
+    }
+
+    /// Returns *device info* as [`serde_json::Value`].
+    pub async fn get_device_info_json(&self) -> Result<Value, Error> {
+        self.send_raw("get_device_info", Value::Null).await
+    }
+
+    /// Returns the [`Capabilities`] derived from the components of the device, see [`FakeDevice::with_components`].
+    pub async fn get_capabilities(&self) -> Result<Capabilities, Error> {
+        let component_list = self.send_raw("component_nego", Value::Null).await?;
+
+        Ok(serde_json::from_value::<ComponentListResult>(component_list)?.capabilities())
+    }
+
+    /// Returns *energy usage* as [`EnergyUsageResult`]. Requires the `energy_monitoring` component.
+    pub async fn get_energy_usage(&self) -> Result<EnergyUsageResult, Error> {
+        self.require("energy_monitoring")?;
+        let energy_usage = self.send_raw("get_energy_usage", Value::Null).await?;
+
+        Ok(serde_json::from_value(energy_usage)?)
+    }
+
+    /// Sends a request for the firmware `method` with the given `params` and returns its result.
+    /// The methods that aren't implemented by the fake fail with [`TapoResponseError::Unknown`],
+    /// unless they have a transition, see [`FakeDevice::on_request`].
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - the method of the request, e.g. `set_device_info`
+    /// * `params` - the parameters of the request, or [`serde_json::Value::Null`]
+    pub async fn send_raw(&self, method: &str, params: Value) -> Result<Value, Error> {
+        self.lock().handle(method, params)
+    }
+
+    async fn set_device_info(&self, params: Value) -> Result<(), Error> {
+        self.send_raw("set_device_info", params).await.map(|_| ())
+    }
+
+    fn require(&self, component: &str) -> Result<(), Error> {
+        if self.lock().components.contains_key(component) {
+            return Ok(());
+        }
+
+        Err(Error::Tapo(TapoResponseError::Unknown(
+            UNSUPPORTED_METHOD_ERROR_CODE,
+        )))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FakeState> {
+        self.state.lock().expect("the lock is never poisoned")
+    }
+}
+
+impl FakeState {
+    fn handle(&mut self, method: &str, params: Value) -> Result<Value, Error> {
+        self.requests.push(FakeRequest {
+            method: method.to_string(),
+            params: params.clone(),
+        });
+
+        if !self.reachable {
+            return Err(isahc::Error::from(isahc::error::ErrorKind::ConnectionFailed).into());
+        }
+        if let Some(error) = self.failures.pop_front() {
+            return Err(error);
+        }
+
+        let transitions = self.transitions.get(method).cloned().unwrap_or_default();
+        let result = match method {
+            "get_device_info" => self.device_info.clone(),
+            "set_device_info" => {
+                if let (Some(device_info), Some(params)) =
+                    (self.device_info.as_object_mut(), params.as_object())
+                {
+                    // Like the real lights, changing the light state also turns them on.
+                    if !params.contains_key("device_on")
+                        && ["brightness", "hue", "saturation", "color_temp"]
+                            .iter()
+                            .any(|field| params.contains_key(*field))
+                    {
+                        device_info.insert("device_on".to_string(), true.into());
+                    }
+                    device_info.extend(params.clone());
+                }
+                Value::Null
+            }
+            "component_nego" => json!({
+                "component_list": self
+                    .components
+                    .iter()
+                    .map(|(id, ver_code)| json!({ "id": id, "ver_code": ver_code }))
+                    .collect::<Vec<_>>(),
+            }),
+            "get_energy_usage" => {
+                let power_w = if self.device_info["device_on"] == true {
+                    self.power_w
+                } else {
+                    0.0
+                };
+                let local_time = chrono::Local::now().naive_local();
+
+                json!({
+                    "local_time": local_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    "current_power": (power_w * 1000.0).round() as u64,
+                    "today_runtime": 0,
+                    "today_energy": self.today_energy_wh,
+                    "month_runtime": 0,
+                    "month_energy": self.today_energy_wh,
+                })
+            }
+            _ if !transitions.is_empty() => Value::Null,
+            _ => {
+                return Err(Error::Tapo(TapoResponseError::Unknown(
+                    UNSUPPORTED_METHOD_ERROR_CODE,
+                )))
+            }
+        };
+
+        for transition in transitions {
+            transition(&mut self.device_info, &params);
+        }
+
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl WatchableDevice for FakeDevice {
+    async fn get_device_info_json(&self) -> Result<Value, Error> {
+        FakeDevice::get_device_info_json(self).await
+    }
+
+    async fn refresh_session(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::watcher::{DeviceChange, DeviceWatcher};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn fake_device_follows_its_script() {
+        let plug = FakeDevice::plug().with_power(1500.0);
+        plug.on_request("set_device_info", |device_info, params| {
+            if params["device_on"] == true {
+                device_info["on_time"] = 1.into();
+            }
+        });
+        let mut watcher = DeviceWatcher::new(plug.clone(), Duration::from_millis(1));
+        assert_eq!(watcher.poll().await.unwrap(), vec![]);
+
+        plug.on().await.unwrap();
+        assert_eq!(plug.device_info()["on_time"], 1);
+        assert_eq!(
+            plug.get_energy_usage().await.unwrap().current_power,
+            1_500_000
+        );
+        assert_eq!(watcher.poll().await.unwrap(), vec![DeviceChange::TurnedOn]);
+
+        plug.fail_next(Error::Tapo(TapoResponseError::SessionTimeout));
+        assert!(plug.off().await.is_err());
+        assert_eq!(plug.device_info()["device_on"], true);
+
+        plug.set_reachable(false);
+        assert!(plug.off().await.unwrap_err().is_unreachable());
+        assert!(plug.set_brightness(50).await.is_err());
+        assert_eq!(plug.requests().len(), 6);
+    }
+}
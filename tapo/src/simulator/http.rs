@@ -0,0 +1,134 @@
+use std::io;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// The parts of an HTTP request used by the simulated devices.
+#[derive(Debug)]
+pub(crate) struct HttpRequest {
+    pub path: String,
+    pub query: Option<String>,
+    pub cookie: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// A response of a simulated device.
+#[derive(Debug)]
+pub(crate) struct HttpResponse {
+    pub status: u16,
+    pub cookie: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn ok(body: Vec<u8>) -> Self {
+        Self {
+            status: 200,
+            cookie: None,
+            body,
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            cookie: None,
+            body: Vec::new(),
+        }
+    }
+}
+
+/// Reads a single HTTP/1.1 request from `stream`.
+pub(crate) async fn read_request(stream: &mut TcpStream) -> io::Result<HttpRequest> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let target = request_line
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid request line"))?;
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), Some(query.to_string())),
+        None => (target.to_string(), None),
+    };
+
+    let mut content_length = 0;
+    let mut cookie = None;
+    let mut expect_continue = false;
+
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).await?;
+        let line = line.trim_end();
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+
+            match name.to_ascii_lowercase().as_str() {
+                "content-length" => {
+                    content_length = value
+                        .parse()
+                        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid length"))?
+                }
+                "cookie" => cookie = Some(value.to_string()),
+                "expect" => expect_continue = value.eq_ignore_ascii_case("100-continue"),
+                _ => {}
+            }
+        }
+    }
+
+    if expect_continue {
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .await?;
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+
+    Ok(HttpRequest {
+        path,
+        query,
+        cookie,
+        body,
+    })
+}
+
+/// Writes `response` to `stream` and asks the client to close the connection.
+pub(crate) async fn write_response(
+    stream: &mut TcpStream,
+    response: HttpResponse,
+) -> io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        reason_phrase(response.status),
+        response.body.len()
+    );
+
+    if let Some(cookie) = response.cookie {
+        head.push_str(&format!("Set-Cookie: {cookie}\r\n"));
+    }
+
+    head.push_str("\r\n");
+
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&response.body).await?;
+    stream.flush().await
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        _ => "Internal Server Error",
+    }
+}
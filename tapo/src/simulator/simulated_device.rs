@@ -0,0 +1,598 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::engine::general_purpose;
+use base64::Engine as _;
+use log::debug;
+use openssl::rsa::{Padding, Rsa};
+use openssl::sha::{sha1, sha256};
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore};
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+
+use crate::protocol::codec::{klap_client_hash, klap_server_hash, KlapCodec, PassthroughCodec};
+use crate::simulator::http::{read_request, write_response, HttpRequest, HttpResponse};
+use crate::simulator::{SimulatedProtocol, SIMULATED_ERROR_CODE};
+
+/// The error code returned for the methods that aren't simulated.
+const UNSUPPORTED_METHOD_ERROR_CODE: i32 = -40210;
+/// The error code returned for requests with missing or malformed params.
+const INVALID_PARAMS_ERROR_CODE: i32 = -1008;
+/// The error code returned by the Passthrough endpoint of devices that only speak KLAP.
+const TRANSPORT_NOT_AVAILABLE_ERROR_CODE: i32 = 1003;
+/// The error code returned for Passthrough requests with invalid credentials.
+const INVALID_CREDENTIALS_ERROR_CODE: i32 = -1501;
+/// The error code returned for Passthrough requests without a valid session or token.
+const SESSION_TIMEOUT_ERROR_CODE: i32 = 9999;
+/// Handshakes that are never completed would otherwise accumulate during long load tests.
+const MAX_SESSIONS: usize = 64;
+const SESSION_COOKIE: &str = "TP_SESSIONID";
+
+/// How a simulated device misbehaves.
+#[derive(Debug, Clone)]
+pub(crate) struct SimulationOptions {
+    pub min_latency: Duration,
+    pub max_latency: Duration,
+    pub error_rate: f64,
+}
+
+/// A simulated P110 plug, served by [`crate::simulator::SimulatedFleet`].
+#[derive(Debug)]
+pub(crate) struct SimulatedDevice {
+    index: usize,
+    auth_hash: Vec<u8>,
+    options: SimulationOptions,
+    state: Mutex<DeviceState>,
+}
+
+#[derive(Debug)]
+struct DeviceState {
+    rng: StdRng,
+    device_on: bool,
+    on_since: Instant,
+    last_update: Instant,
+    /// The power around which the readings drift, in watts.
+    base_power: f64,
+    /// In watts.
+    current_power: f64,
+    /// In watt-hours.
+    today_energy: f64,
+    /// In watt-hours, excluding today.
+    month_energy: f64,
+    today_runtime: Duration,
+    child_protection: bool,
+    /// `always`, `never` or `auto`.
+    led_rule: String,
+    protocol: SimulatedProtocol,
+    next_session_id: u64,
+    sessions: HashMap<String, Session>,
+}
+
+#[derive(Debug)]
+enum Session {
+    Handshake1 {
+        local_seed: Vec<u8>,
+        remote_seed: Vec<u8>,
+        created: Instant,
+    },
+    Established {
+        codec: KlapCodec,
+        created: Instant,
+    },
+    Passthrough {
+        codec: PassthroughCodec,
+        token: Option<String>,
+        created: Instant,
+    },
+}
+
+impl Session {
+    fn created(&self) -> Instant {
+        match self {
+            Session::Handshake1 { created, .. }
+            | Session::Established { created, .. }
+            | Session::Passthrough { created, .. } => *created,
+        }
+    }
+}
+
+impl SimulatedDevice {
+    pub fn new(
+        index: usize,
+        auth_hash: Vec<u8>,
+        options: SimulationOptions,
+        mut rng: StdRng,
+    ) -> Self {
+        let now = Instant::now();
+        let base_power = rng.gen_range(5.0..2000.0);
+        let month_energy = rng.gen_range(0.0..50_000.0);
+
+        Self {
+            index,
+            auth_hash,
+            options,
+            state: Mutex::new(DeviceState {
+                rng,
+                device_on: true,
+                on_since: now,
+                last_update: now,
+                base_power,
+                current_power: base_power,
+                today_energy: 0.0,
+                month_energy,
+                today_runtime: Duration::ZERO,
+                child_protection: false,
+                led_rule: "always".to_string(),
+                protocol: SimulatedProtocol::Klap,
+                next_session_id: 0,
+                sessions: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Switches the protocol, dropping the sessions established with the previous one.
+    pub fn set_protocol(&self, protocol: SimulatedProtocol) {
+        let mut state = self.lock_state();
+        state.protocol = protocol;
+        state.sessions.clear();
+    }
+
+    /// Serves a single request, after the simulated latency.
+    pub async fn serve_connection(&self, stream: &mut TcpStream) -> io::Result<()> {
+        let request = read_request(stream).await?;
+
+        let latency = self.latency();
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+
+        let response = self.handle(request);
+        write_response(stream, response).await
+    }
+
+    fn latency(&self) -> Duration {
+        let SimulationOptions {
+            min_latency,
+            max_latency,
+            ..
+        } = self.options;
+
+        if min_latency >= max_latency {
+            return min_latency;
+        }
+
+        self.lock_state().rng.gen_range(min_latency..=max_latency)
+    }
+
+    fn handle(&self, request: HttpRequest) -> HttpResponse {
+        let protocol = self.lock_state().protocol;
+
+        match (protocol, request.path.as_str()) {
+            (SimulatedProtocol::Klap, "/app") => {
+                json_response(json!({ "error_code": TRANSPORT_NOT_AVAILABLE_ERROR_CODE }))
+            }
+            (SimulatedProtocol::Klap, "/app/handshake1") => self.handshake1(request),
+            (SimulatedProtocol::Klap, "/app/handshake2") => self.handshake2(request),
+            (SimulatedProtocol::Klap, "/app/request") => self.request(request),
+            (SimulatedProtocol::Passthrough, "/app") => self.passthrough(request),
+            _ => HttpResponse::status(404),
+        }
+    }
+
+    fn passthrough(&self, request: HttpRequest) -> HttpResponse {
+        let Ok(payload) = serde_json::from_slice::<Value>(&request.body) else {
+            return HttpResponse::status(400);
+        };
+
+        match payload["method"].as_str().unwrap_or_default() {
+            "handshake" => self.passthrough_handshake(&payload["params"]),
+            "securePassthrough" => self.secure_passthrough(&request, &payload["params"]),
+            method => {
+                let mut state = self.lock_state();
+                json_response(self.result_response(&mut state, method, &payload["params"]))
+            }
+        }
+    }
+
+    fn passthrough_handshake(&self, params: &Value) -> HttpResponse {
+        let Some(public_key) = params["key"]
+            .as_str()
+            .and_then(|key| Rsa::public_key_from_pem(key.as_bytes()).ok())
+        else {
+            return json_response(json!({ "error_code": INVALID_PARAMS_ERROR_CODE }));
+        };
+
+        let mut state = self.lock_state();
+
+        let mut session_key = vec![0; 32];
+        state.rng.fill_bytes(&mut session_key);
+        let mut encrypted_key = vec![0; public_key.size() as usize];
+        let Ok(codec) = PassthroughCodec::new(&session_key) else {
+            return HttpResponse::status(500);
+        };
+        if public_key
+            .public_encrypt(&session_key, &mut encrypted_key, Padding::PKCS1)
+            .is_err()
+        {
+            return HttpResponse::status(500);
+        }
+
+        let session_id = format!("{:08X}{:08X}", self.index, state.next_session_id);
+        state.next_session_id += 1;
+        state.insert_session(
+            session_id.clone(),
+            Session::Passthrough {
+                codec,
+                token: None,
+                created: Instant::now(),
+            },
+        );
+
+        HttpResponse {
+            cookie: Some(format!("{SESSION_COOKIE}={session_id};TIMEOUT=86400")),
+            ..json_response(json!({
+                "error_code": 0,
+                "result": { "key": general_purpose::STANDARD.encode(encrypted_key) },
+            }))
+        }
+    }
+
+    fn secure_passthrough(&self, request: &HttpRequest, params: &Value) -> HttpResponse {
+        let session_timeout = || json_response(json!({ "error_code": SESSION_TIMEOUT_ERROR_CODE }));
+        let Some(session_id) = session_id(request) else {
+            return session_timeout();
+        };
+
+        let mut state = self.lock_state();
+
+        let Some(Session::Passthrough { codec, token, .. }) = state.sessions.get(&session_id)
+        else {
+            return session_timeout();
+        };
+        let codec = codec.clone();
+        let token = token.clone();
+
+        let Some(payload) = params["request"]
+            .as_str()
+            .and_then(|request| codec.decrypt(request).ok())
+            .and_then(|request| serde_json::from_str::<Value>(&request).ok())
+        else {
+            return json_response(json!({ "error_code": INVALID_PARAMS_ERROR_CODE }));
+        };
+
+        let method = payload["method"].as_str().unwrap_or_default();
+        let response = if method == "login_device" {
+            if !self.is_passthrough_login_valid(&payload["params"]) {
+                debug!("Simulated device {}: invalid credentials", self.index);
+                json!({ "error_code": INVALID_CREDENTIALS_ERROR_CODE })
+            } else {
+                let new_token = format!("{session_id}{:016X}", state.rng.next_u64());
+                // Like the real devices, only the most recent session is kept.
+                state.sessions.retain(|id, session| {
+                    *id == session_id
+                        || !matches!(session, Session::Passthrough { token: Some(_), .. })
+                });
+                if let Some(Session::Passthrough { token, .. }) =
+                    state.sessions.get_mut(&session_id)
+                {
+                    *token = Some(new_token.clone());
+                }
+                json!({ "error_code": 0, "result": { "token": new_token } })
+            }
+        } else {
+            let query_token = request
+                .query
+                .as_deref()
+                .and_then(|query| query.strip_prefix("token="));
+            if token.is_none() || query_token != token.as_deref() {
+                return session_timeout();
+            }
+            self.result_response(&mut state, method, &payload["params"])
+        };
+
+        match codec.encrypt(&response.to_string()) {
+            Ok(response) => json_response(json!({
+                "error_code": 0,
+                "result": { "response": response },
+            })),
+            Err(_) => HttpResponse::status(500),
+        }
+    }
+
+    /// The Passthrough login sends the hex SHA-1 digest of the username and the password, both encoded in base64.
+    fn is_passthrough_login_valid(&self, params: &Value) -> bool {
+        let decode = |name: &str| {
+            params[name]
+                .as_str()
+                .and_then(|value| general_purpose::STANDARD.decode(value).ok())
+        };
+        let (Some(username_digest), Some(password)) = (decode("username"), decode("password"))
+        else {
+            return false;
+        };
+        let Ok(username_digest) = base16ct::lower::decode_vec(username_digest) else {
+            return false;
+        };
+
+        sha256(&[username_digest.as_slice(), &sha1(&password)].concat())
+            == self.auth_hash.as_slice()
+    }
+
+    fn handshake1(&self, request: HttpRequest) -> HttpResponse {
+        if request.body.len() != 16 {
+            return HttpResponse::status(400);
+        }
+
+        let mut state = self.lock_state();
+
+        let mut remote_seed = vec![0; 16];
+        state.rng.fill_bytes(&mut remote_seed);
+        let server_hash = klap_server_hash(&request.body, &remote_seed, &self.auth_hash);
+
+        let session_id = format!("{:08X}{:08X}", self.index, state.next_session_id);
+        state.next_session_id += 1;
+        state.insert_session(
+            session_id.clone(),
+            Session::Handshake1 {
+                local_seed: request.body,
+                remote_seed: remote_seed.clone(),
+                created: Instant::now(),
+            },
+        );
+
+        HttpResponse {
+            status: 200,
+            cookie: Some(format!("{SESSION_COOKIE}={session_id};TIMEOUT=86400")),
+            body: [remote_seed.as_slice(), &server_hash].concat(),
+        }
+    }
+
+    fn handshake2(&self, request: HttpRequest) -> HttpResponse {
+        let Some(session_id) = session_id(&request) else {
+            return HttpResponse::status(403);
+        };
+
+        let mut state = self.lock_state();
+
+        let Some(Session::Handshake1 {
+            local_seed,
+            remote_seed,
+            created,
+        }) = state.sessions.remove(&session_id)
+        else {
+            return HttpResponse::status(403);
+        };
+
+        let client_hash = klap_client_hash(&local_seed, &remote_seed, &self.auth_hash);
+        if request.body != client_hash {
+            debug!("Simulated device {}: invalid credentials", self.index);
+            return HttpResponse::status(403);
+        }
+
+        let codec = KlapCodec::new(&local_seed, &remote_seed, &self.auth_hash);
+        // Like the real devices, only the most recent session is kept.
+        state
+            .sessions
+            .retain(|_, session| !matches!(session, Session::Established { .. }));
+        state.insert_session(session_id, Session::Established { codec, created });
+
+        HttpResponse::status(200)
+    }
+
+    fn request(&self, request: HttpRequest) -> HttpResponse {
+        let seq = request
+            .query
+            .as_deref()
+            .and_then(|query| query.strip_prefix("seq="))
+            .and_then(|seq| seq.parse::<i32>().ok());
+        let (Some(seq), Some(session_id)) = (seq, session_id(&request)) else {
+            return HttpResponse::status(400);
+        };
+
+        let mut state = self.lock_state();
+
+        let Some(Session::Established { codec, .. }) = state.sessions.get(&session_id) else {
+            return HttpResponse::status(403);
+        };
+
+        let Ok(payload) = codec.decrypt(seq, &request.body) else {
+            return HttpResponse::status(400);
+        };
+        let Ok(payload) = serde_json::from_str::<Value>(&payload) else {
+            return HttpResponse::status(400);
+        };
+
+        let method = payload["method"].as_str().unwrap_or_default();
+        let response = self.result_response(&mut state, method, &payload["params"]);
+
+        let Some(Session::Established { codec, .. }) = state.sessions.get(&session_id) else {
+            return HttpResponse::status(403);
+        };
+
+        match codec.encrypt(seq, response.to_string().as_bytes()) {
+            Ok(body) => HttpResponse::ok(body),
+            Err(_) => HttpResponse::status(500),
+        }
+    }
+
+    /// Returns the response to `method`, or the injected error according to the error rate.
+    fn result_response(&self, state: &mut DeviceState, method: &str, params: &Value) -> Value {
+        if state.rng.gen_bool(self.options.error_rate) {
+            debug!(
+                "Simulated device {}: injecting an error into {method}",
+                self.index
+            );
+            return json!({ "error_code": SIMULATED_ERROR_CODE });
+        }
+
+        match self.execute(state, method, params) {
+            Ok(Some(result)) => json!({ "error_code": 0, "result": result }),
+            Ok(None) => json!({ "error_code": 0 }),
+            Err(error_code) => json!({ "error_code": error_code }),
+        }
+    }
+
+    fn execute(
+        &self,
+        state: &mut DeviceState,
+        method: &str,
+        params: &Value,
+    ) -> Result<Option<Value>, i32> {
+        state.advance();
+
+        let result = match method {
+            "component_nego" => json!({
+                "component_list": [
+                    { "id": "device", "ver_code": 2 },
+                    { "id": "energy_monitoring", "ver_code": 2 },
+                    { "id": "child_protection", "ver_code": 1 },
+                    { "id": "led", "ver_code": 1 },
+                ]
+            }),
+            "get_device_info" => self.device_info(state),
+            "set_device_info" => {
+                if let Some(device_on) = params["device_on"].as_bool() {
+                    state.set_device_on(device_on);
+                }
+                return Ok(None);
+            }
+            "get_child_protection" => json!({ "child_protection": state.child_protection }),
+            "set_child_protection" => {
+                state.child_protection = params["enable"]
+                    .as_bool()
+                    .ok_or(INVALID_PARAMS_ERROR_CODE)?;
+                return Ok(None);
+            }
+            "get_led_info" => json!({
+                "led_rule": state.led_rule,
+                "led_status": state.led_rule != "never",
+            }),
+            "set_led_info" => {
+                state.led_rule = params["led_rule"]
+                    .as_str()
+                    .ok_or(INVALID_PARAMS_ERROR_CODE)?
+                    .to_string();
+                return Ok(None);
+            }
+            "get_current_power" => json!({ "current_power": state.current_power.round() as u64 }),
+            "get_energy_usage" => {
+                let local_time = chrono::Local::now().naive_local();
+                let today_runtime = state.today_runtime.as_secs() / 60;
+
+                json!({
+                    "local_time": local_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                    "current_power": (state.current_power * 1000.0).round() as u64,
+                    "today_runtime": today_runtime,
+                    "today_energy": state.today_energy.round() as u64,
+                    "month_runtime": today_runtime,
+                    "month_energy": (state.month_energy + state.today_energy).round() as u64,
+                })
+            }
+            _ => return Err(UNSUPPORTED_METHOD_ERROR_CODE),
+        };
+
+        Ok(Some(result))
+    }
+
+    fn device_info(&self, state: &DeviceState) -> Value {
+        let index = self.index;
+        let encode = |value: &str| general_purpose::STANDARD.encode(value);
+
+        json!({
+            "device_id": format!("{index:040X}"),
+            "type": "SMART.TAPOPLUG",
+            "model": "P110",
+            "hw_id": "00000000000000000000000000000000",
+            "hw_ver": "1.0",
+            "fw_id": "00000000000000000000000000000000",
+            "fw_ver": "1.3.0 Build 230905 Rel.152200",
+            "oem_id": "00000000000000000000000000000000",
+            "mac": format!("5C-E9-31-{:02X}-{:02X}-{:02X}", (index >> 16) & 0xFF, (index >> 8) & 0xFF, index & 0xFF),
+            "ip": "127.0.0.1",
+            "ssid": encode("simulator"),
+            "signal_level": 3,
+            "rssi": -40,
+            "specs": "",
+            "lang": "en_US",
+            "device_on": state.device_on,
+            "on_time": if state.device_on { state.on_since.elapsed().as_secs() } else { 0 },
+            "overheated": false,
+            "nickname": encode(&format!("Simulated Plug {}", index + 1)),
+            "avatar": "plug",
+            "has_set_location_info": false,
+            "time_diff": 0,
+            "default_states": {
+                "type": "last_states",
+                "state": {},
+            },
+        })
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, DeviceState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl DeviceState {
+    /// Accumulates the energy used since the last update and lets the power reading drift by up to 5%.
+    fn advance(&mut self) {
+        let now = Instant::now();
+        let elapsed = now - self.last_update;
+        self.last_update = now;
+
+        if !self.device_on {
+            return;
+        }
+
+        self.today_energy += self.current_power * elapsed.as_secs_f64() / 3600.0;
+        self.today_runtime += elapsed;
+
+        let drift = self.rng.gen_range(-0.05..=0.05);
+        self.current_power = (self.current_power * (1.0 + drift))
+            .clamp(self.base_power * 0.5, self.base_power * 1.5);
+    }
+
+    fn set_device_on(&mut self, device_on: bool) {
+        if device_on && !self.device_on {
+            self.on_since = Instant::now();
+            self.current_power = self.base_power;
+        } else if !device_on {
+            self.current_power = 0.0;
+        }
+
+        self.device_on = device_on;
+    }
+
+    fn insert_session(&mut self, session_id: String, session: Session) {
+        if self.sessions.len() >= MAX_SESSIONS {
+            let oldest = self
+                .sessions
+                .iter()
+                .min_by_key(|(_, session)| session.created())
+                .map(|(session_id, _)| session_id.clone());
+
+            if let Some(oldest) = oldest {
+                self.sessions.remove(&oldest);
+            }
+        }
+
+        self.sessions.insert(session_id, session);
+    }
+}
+
+fn json_response(value: Value) -> HttpResponse {
+    HttpResponse::ok(value.to_string().into_bytes())
+}
+
+fn session_id(request: &HttpRequest) -> Option<String> {
+    request
+        .cookie
+        .as_deref()?
+        .split(';')
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
@@ -0,0 +1,295 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::debug;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::error::Error;
+use crate::protocol::codec::klap_auth_hash;
+use crate::simulator::{SimulatedDevice, SimulationOptions};
+
+/// The error code of the failures injected according to [`SimulatedFleetBuilder::error_rate`].
+/// It's reported as [`crate::TapoResponseError::Unknown`].
+pub const SIMULATED_ERROR_CODE: i32 = -1;
+
+/// A fleet of simulated P110 plugs listening on the loopback interface, see [`crate::simulator`].
+/// The devices stop when the fleet is dropped.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use tapo::ApiClient;
+/// # use tapo::simulator::SimulatedFleet;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let fleet = SimulatedFleet::builder("tapo-username@example.com", "tapo-password")
+///     .devices(100)
+///     .latency(Duration::from_millis(20), Duration::from_millis(200))
+///     .error_rate(0.01)
+///     .start()
+///     .await?;
+///
+/// let client = ApiClient::new("tapo-username@example.com", "tapo-password")?;
+///
+/// for address in fleet.addresses() {
+///     let device = client.clone().p110(address).await?;
+///     println!("{:?}", device.get_current_power().await?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct SimulatedFleet {
+    addresses: Vec<String>,
+    devices: Vec<Arc<SimulatedDevice>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+/// The protocol spoken by a simulated device, see [`SimulatedFleet::set_protocol`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimulatedProtocol {
+    /// The protocol of the recent firmware versions. The default.
+    Klap,
+    /// The protocol of the older firmware versions, with an RSA handshake.
+    Passthrough,
+}
+
+impl SimulatedFleet {
+    /// Returns a [`SimulatedFleetBuilder`] for devices that accept the given Tapo credentials.
+    ///
+    /// # Arguments
+    ///
+    /// * `tapo_username` - the Tapo username accepted by the devices
+    /// * `tapo_password` - the Tapo password accepted by the devices
+    pub fn builder(
+        tapo_username: impl Into<String>,
+        tapo_password: impl Into<String>,
+    ) -> SimulatedFleetBuilder {
+        SimulatedFleetBuilder {
+            tapo_username: tapo_username.into(),
+            tapo_password: tapo_password.into(),
+            devices: 1,
+            min_latency: Duration::ZERO,
+            max_latency: Duration::ZERO,
+            error_rate: 0.0,
+            seed: None,
+        }
+    }
+
+    /// Returns the addresses of the devices, in the `ip:port` form accepted by the [`crate::ApiClient`] device builders.
+    pub fn addresses(&self) -> &[String] {
+        &self.addresses
+    }
+
+    /// Switches the device at `index` to `protocol`, like a firmware update would,
+    /// which invalidates the sessions of the connected clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - the index of the device in [`SimulatedFleet::addresses`]
+    /// * `protocol` - the protocol spoken from now on
+    pub fn set_protocol(&self, index: usize, protocol: SimulatedProtocol) -> Result<(), Error> {
+        let device = self.devices.get(index).ok_or_else(|| Error::Validation {
+            field: "index".to_string(),
+            message: format!("Must be less than {}", self.devices.len()),
+        })?;
+
+        device.set_protocol(protocol);
+
+        Ok(())
+    }
+}
+
+impl Drop for SimulatedFleet {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Builder for a [`SimulatedFleet`], see [`SimulatedFleet::builder`].
+#[derive(Debug, Clone)]
+pub struct SimulatedFleetBuilder {
+    tapo_username: String,
+    tapo_password: String,
+    devices: usize,
+    min_latency: Duration,
+    max_latency: Duration,
+    error_rate: f64,
+    seed: Option<u64>,
+}
+
+impl SimulatedFleetBuilder {
+    /// Sets the number of devices. Defaults to 1.
+    pub fn devices(mut self, devices: usize) -> Self {
+        self.devices = devices;
+        self
+    }
+
+    /// Sets the range of the latency added to every response. Defaults to no latency.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_latency` - the smallest latency
+    /// * `max_latency` - the largest latency
+    pub fn latency(mut self, min_latency: Duration, max_latency: Duration) -> Self {
+        self.min_latency = min_latency;
+        self.max_latency = max_latency;
+        self
+    }
+
+    /// Sets the probability, between 0 and 1, of a request failing with [`SIMULATED_ERROR_CODE`]. Defaults to 0.
+    pub fn error_rate(mut self, error_rate: f64) -> Self {
+        self.error_rate = error_rate;
+        self
+    }
+
+    /// Sets the seed of the random number generators, which makes the power readings and the injected errors reproducible.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Binds the devices to random ports of the loopback interface and starts serving them.
+    /// Must be called from within a Tokio runtime.
+    pub async fn start(self) -> Result<SimulatedFleet, Error> {
+        if !(0.0..=1.0).contains(&self.error_rate) {
+            return Err(Error::Validation {
+                field: "error_rate".to_string(),
+                message: "Must be between 0 and 1".to_string(),
+            });
+        }
+
+        if self.min_latency > self.max_latency {
+            return Err(Error::Validation {
+                field: "latency".to_string(),
+                message: "The minimum latency must not be greater than the maximum latency"
+                    .to_string(),
+            });
+        }
+
+        let auth_hash = klap_auth_hash(&self.tapo_username, &self.tapo_password).to_vec();
+        let options = SimulationOptions {
+            min_latency: self.min_latency,
+            max_latency: self.max_latency,
+            error_rate: self.error_rate,
+        };
+
+        let mut addresses = Vec::with_capacity(self.devices);
+        let mut devices = Vec::with_capacity(self.devices);
+        let mut tasks = Vec::with_capacity(self.devices);
+
+        for index in 0..self.devices {
+            let listener = TcpListener::bind("127.0.0.1:0")
+                .await
+                .map_err(anyhow::Error::from)?;
+            addresses.push(
+                listener
+                    .local_addr()
+                    .map_err(anyhow::Error::from)?
+                    .to_string(),
+            );
+
+            let rng = match self.seed {
+                Some(seed) => StdRng::seed_from_u64(seed.wrapping_add(index as u64)),
+                None => StdRng::from_entropy(),
+            };
+            let device = Arc::new(SimulatedDevice::new(
+                index,
+                auth_hash.clone(),
+                options.clone(),
+                rng,
+            ));
+
+            devices.push(device.clone());
+            tasks.push(tokio::spawn(serve(listener, device)));
+        }
+
+        Ok(SimulatedFleet {
+            addresses,
+            devices,
+            tasks,
+        })
+    }
+}
+
+async fn serve(listener: TcpListener, device: Arc<SimulatedDevice>) {
+    loop {
+        let Ok((mut stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let device = device.clone();
+
+        tokio::spawn(async move {
+            if let Err(err) = device.serve_connection(&mut stream).await {
+                debug!("Simulated device connection failed: {err}");
+            }
+        });
+    }
+}
+
+/// Returns a simulated P110 and a handler connected to it. The plug stops when the fleet is dropped.
+#[cfg(test)]
+pub(crate) async fn connected_plug() -> (SimulatedFleet, crate::PlugEnergyMonitoringHandler) {
+    let fleet = SimulatedFleet::builder("username", "password")
+        .start()
+        .await
+        .unwrap();
+    let device = crate::ApiClient::new("username", "password")
+        .unwrap()
+        .p110(&fleet.addresses()[0])
+        .await
+        .unwrap();
+
+    (fleet, device)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ApiClient, TapoResponseError};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn simulated_plug_is_controllable() {
+        let fleet = SimulatedFleet::builder("username", "password")
+            .devices(2)
+            .seed(7)
+            .start()
+            .await
+            .unwrap();
+        let client = ApiClient::new("username", "password").unwrap();
+
+        let device = client.p110(&fleet.addresses()[1]).await.unwrap();
+        device.off().await.unwrap();
+        assert!(!device.get_device_info().await.unwrap().device_on);
+        assert_eq!(device.get_current_power().await.unwrap().current_power, 0);
+
+        device.on().await.unwrap();
+        let device_info = device.get_device_info().await.unwrap();
+        assert!(device_info.device_on);
+        assert_eq!(device_info.nickname, "Simulated Plug 2");
+        assert!(device.get_current_power().await.unwrap().current_power > 0);
+    }
+
+    #[tokio::test]
+    async fn wrong_credentials_are_rejected() {
+        let fleet = SimulatedFleet::builder("username", "password")
+            .start()
+            .await
+            .unwrap();
+        let client = ApiClient::new("username", "wrong-password").unwrap();
+
+        let result = client.p110(&fleet.addresses()[0]).await;
+
+        assert!(matches!(
+            result,
+            Err(crate::Error::Tapo(TapoResponseError::InvalidCredentials))
+        ));
+    }
+}
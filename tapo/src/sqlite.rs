@@ -0,0 +1,387 @@
+//! An embedded history of the devices in a SQLite database, for the installations that don't run a time series database.
+//!
+//! Requires the `sqlite` feature. SQLite is compiled into the crate, so nothing needs to be installed.
+//!
+//! A [`SqliteStore`] keeps the [`Reading`]s and the [`DeviceEvent`]s of the devices, which can be queried
+//! by device and time range, and is a [`Persistence`] for [`crate::watcher::DeviceWatcher::with_persistence`].
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use chrono::{Duration, Utc};
+//! # use tapo::ApiClient;
+//! # use tapo::readings::{Reading, ReadingKind};
+//! # use tapo::sqlite::SqliteStore;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let store = SqliteStore::open("history.db")?;
+//!
+//! let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+//!     .p110("192.168.1.100")
+//!     .await?;
+//! let energy_usage = device.get_energy_usage().await?;
+//! store.insert_readings(&Reading::from_energy_usage("kitchen", Utc::now(), &energy_usage))?;
+//!
+//! let last_day = store.readings("kitchen", ReadingKind::PowerW, Utc::now() - Duration::days(1)..Utc::now())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Mutex;
+
+#[cfg(feature = "client")]
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::Error;
+use crate::events::DeviceEvent;
+use crate::persistence::{PersistedState, Persistence};
+#[cfg(feature = "client")]
+use crate::readings::ReadingSink;
+use crate::readings::{Reading, ReadingKind};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS readings (
+        device TEXT NOT NULL,
+        timestamp_ms INTEGER NOT NULL,
+        kind TEXT NOT NULL,
+        value REAL NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS readings_by_device ON readings (device, kind, timestamp_ms);
+    CREATE TABLE IF NOT EXISTS events (
+        device TEXT NOT NULL,
+        timestamp_ms INTEGER NOT NULL,
+        event TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS events_by_device ON events (device, timestamp_ms);
+    CREATE TABLE IF NOT EXISTS states (
+        key TEXT PRIMARY KEY,
+        state TEXT NOT NULL
+    );
+";
+
+/// The history of the devices in a SQLite database, see [`crate::sqlite`].
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens the database at `path`, creating it and its tables if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::with_connection(Connection::open(path).map_err(anyhow::Error::from)?)
+    }
+
+    /// Opens a database that only lives in memory, e.g. for tests.
+    pub fn open_in_memory() -> Result<Self, Error> {
+        Self::with_connection(Connection::open_in_memory().map_err(anyhow::Error::from)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, Error> {
+        connection
+            .execute_batch(SCHEMA)
+            .map_err(anyhow::Error::from)?;
+
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Adds `readings` to the history, in a single transaction.
+    pub fn insert_readings(&self, readings: &[Reading]) -> Result<(), Error> {
+        let mut connection = self.connection.lock().expect("the lock is never poisoned");
+        let transaction = connection.transaction().map_err(anyhow::Error::from)?;
+
+        {
+            let mut statement = transaction
+                .prepare_cached(
+                    "INSERT INTO readings (device, timestamp_ms, kind, value) VALUES (?1, ?2, ?3, ?4)",
+                )
+                .map_err(anyhow::Error::from)?;
+
+            for reading in readings {
+                statement
+                    .execute(params![
+                        reading.device,
+                        reading.timestamp.timestamp_millis(),
+                        reading.kind.name(),
+                        reading.value
+                    ])
+                    .map_err(anyhow::Error::from)?;
+            }
+        }
+
+        transaction.commit().map_err(anyhow::Error::from)?;
+        Ok(())
+    }
+
+    /// Returns the readings of `kind` of `device` taken within `range`, in chronological order.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the device
+    /// * `kind` - what was measured
+    /// * `range` - the time range, which excludes its end
+    pub fn readings(
+        &self,
+        device: &str,
+        kind: ReadingKind,
+        range: Range<DateTime<Utc>>,
+    ) -> Result<Vec<Reading>, Error> {
+        let connection = self.connection.lock().expect("the lock is never poisoned");
+        let mut statement = connection
+            .prepare_cached(
+                "SELECT timestamp_ms, value FROM readings
+                WHERE device = ?1 AND kind = ?2 AND timestamp_ms >= ?3 AND timestamp_ms < ?4
+                ORDER BY timestamp_ms",
+            )
+            .map_err(anyhow::Error::from)?;
+
+        let rows = statement
+            .query_map(
+                params![
+                    device,
+                    kind.name(),
+                    range.start.timestamp_millis(),
+                    range.end.timestamp_millis()
+                ],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?)),
+            )
+            .map_err(anyhow::Error::from)?;
+
+        rows.map(|row| {
+            let (timestamp_ms, value) = row.map_err(anyhow::Error::from)?;
+            Ok(Reading::new(device, from_millis(timestamp_ms), kind, value))
+        })
+        .collect()
+    }
+
+    /// Returns the most recent reading of `kind` of `device`, if any.
+    pub fn latest_reading(
+        &self,
+        device: &str,
+        kind: ReadingKind,
+    ) -> Result<Option<Reading>, Error> {
+        let connection = self.connection.lock().expect("the lock is never poisoned");
+        let row = connection
+            .query_row(
+                "SELECT timestamp_ms, value FROM readings
+                WHERE device = ?1 AND kind = ?2
+                ORDER BY timestamp_ms DESC LIMIT 1",
+                params![device, kind.name()],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?)),
+            )
+            .optional()
+            .map_err(anyhow::Error::from)?;
+
+        Ok(row.map(|(timestamp_ms, value)| {
+            Reading::new(device, from_millis(timestamp_ms), kind, value)
+        }))
+    }
+
+    /// Adds an event of `device` to the history.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the device
+    /// * `timestamp` - when the event happened
+    /// * `event` - the event
+    pub fn insert_event(
+        &self,
+        device: &str,
+        timestamp: DateTime<Utc>,
+        event: &DeviceEvent,
+    ) -> Result<(), Error> {
+        let event = serde_json::to_string(event)?;
+
+        let connection = self.connection.lock().expect("the lock is never poisoned");
+        connection
+            .execute(
+                "INSERT INTO events (device, timestamp_ms, event) VALUES (?1, ?2, ?3)",
+                params![device, timestamp.timestamp_millis(), event],
+            )
+            .map_err(anyhow::Error::from)?;
+
+        Ok(())
+    }
+
+    /// Returns the events of `device` that happened within `range`, in chronological order,
+    /// as the JSON of the [`DeviceEvent`]s, e.g. `{"type":"power_state_changed","device_on":true}`.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the device
+    /// * `range` - the time range, which excludes its end
+    pub fn events(
+        &self,
+        device: &str,
+        range: Range<DateTime<Utc>>,
+    ) -> Result<Vec<(DateTime<Utc>, serde_json::Value)>, Error> {
+        let connection = self.connection.lock().expect("the lock is never poisoned");
+        let mut statement = connection
+            .prepare_cached(
+                "SELECT timestamp_ms, event FROM events
+                WHERE device = ?1 AND timestamp_ms >= ?2 AND timestamp_ms < ?3
+                ORDER BY timestamp_ms",
+            )
+            .map_err(anyhow::Error::from)?;
+
+        let rows = statement
+            .query_map(
+                params![
+                    device,
+                    range.start.timestamp_millis(),
+                    range.end.timestamp_millis()
+                ],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+            )
+            .map_err(anyhow::Error::from)?;
+
+        rows.map(|row| {
+            let (timestamp_ms, event) = row.map_err(anyhow::Error::from)?;
+            Ok((from_millis(timestamp_ms), serde_json::from_str(&event)?))
+        })
+        .collect()
+    }
+
+    /// Deletes the readings and the events older than `before`, e.g. to keep the last year of history.
+    /// Returns how many rows have been deleted.
+    pub fn prune(&self, before: DateTime<Utc>) -> Result<usize, Error> {
+        let connection = self.connection.lock().expect("the lock is never poisoned");
+        let before = before.timestamp_millis();
+
+        let readings = connection
+            .execute("DELETE FROM readings WHERE timestamp_ms < ?1", [before])
+            .map_err(anyhow::Error::from)?;
+        let events = connection
+            .execute("DELETE FROM events WHERE timestamp_ms < ?1", [before])
+            .map_err(anyhow::Error::from)?;
+
+        Ok(readings + events)
+    }
+}
+
+impl Persistence for SqliteStore {
+    fn load(&self, key: &str) -> Result<Option<PersistedState>, Error> {
+        let connection = self.connection.lock().expect("the lock is never poisoned");
+        let state = connection
+            .query_row("SELECT state FROM states WHERE key = ?1", [key], |row| {
+                row.get::<_, String>(0)
+            })
+            .optional()
+            .map_err(anyhow::Error::from)?;
+
+        Ok(state
+            .map(|state| serde_json::from_str(&state))
+            .transpose()?)
+    }
+
+    fn save(&self, key: &str, state: &PersistedState) -> Result<(), Error> {
+        let state = serde_json::to_string(state)?;
+
+        let connection = self.connection.lock().expect("the lock is never poisoned");
+        connection
+            .execute(
+                "INSERT INTO states (key, state) VALUES (?1, ?2)
+                ON CONFLICT (key) DO UPDATE SET state = excluded.state",
+                params![key, state],
+            )
+            .map_err(anyhow::Error::from)?;
+
+        Ok(())
+    }
+}
+
+/// Writes the batches of a [`crate::readings::SinkPipeline`], in a single transaction each.
+#[cfg(feature = "client")]
+#[async_trait]
+impl ReadingSink for SqliteStore {
+    async fn write_batch(&self, readings: &[Reading]) -> Result<(), Error> {
+        self.insert_readings(readings)
+    }
+}
+
+fn from_millis(timestamp_ms: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_millis(timestamp_ms).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn readings_are_queried_by_range() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let at = |hour| Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap();
+        let readings: Vec<_> = (0..4)
+            .map(|hour| Reading::new("kitchen", at(hour), ReadingKind::PowerW, hour as f64))
+            .chain([Reading::new("garage", at(1), ReadingKind::PowerW, 100.0)])
+            .collect();
+
+        store.insert_readings(&readings).unwrap();
+
+        assert_eq!(
+            store
+                .readings("kitchen", ReadingKind::PowerW, at(1)..at(3))
+                .unwrap(),
+            readings[1..3]
+        );
+        assert_eq!(
+            store
+                .latest_reading("kitchen", ReadingKind::PowerW)
+                .unwrap(),
+            Some(readings[3].clone())
+        );
+        assert_eq!(
+            store
+                .latest_reading("kitchen", ReadingKind::TodayEnergyWh)
+                .unwrap(),
+            None
+        );
+
+        assert_eq!(store.prune(at(2)).unwrap(), 3);
+        assert_eq!(
+            store
+                .readings("kitchen", ReadingKind::PowerW, at(0)..at(4))
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn events_and_states_are_stored() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let now = Utc::now();
+
+        store
+            .insert_event(
+                "kitchen",
+                now,
+                &DeviceEvent::PowerStateChanged { device_on: true },
+            )
+            .unwrap();
+        let events = store
+            .events(
+                "kitchen",
+                now - Duration::minutes(1)..now + Duration::minutes(1),
+            )
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].1["type"], "power_state_changed");
+
+        let state = PersistedState {
+            reachable: Some(true),
+            ..Default::default()
+        };
+        store.save("kitchen", &state).unwrap();
+        store.save("kitchen", &state).unwrap();
+        assert_eq!(store.load("kitchen").unwrap(), Some(state));
+        assert_eq!(store.load("garage").unwrap(), None);
+    }
+}
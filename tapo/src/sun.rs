@@ -0,0 +1,240 @@
+//! Sunrise, sunset and twilight times, computed locally from a latitude and a longitude.
+//!
+//! The times are computed with the NOAA sunrise equation, which is accurate to a minute or two
+//! away from the polar circles, and are returned in UTC so that they can be converted to any time zone.
+//!
+//! # Example
+//!
+//! ```rust
+//! # use chrono::{Local, NaiveDate};
+//! # use tapo::sun::Location;
+//! # fn main() -> Result<(), tapo::Error> {
+//! let london = Location::new(51.5074, -0.1278)?;
+//! let times = london.sun_times(NaiveDate::from_ymd_opt(2024, 6, 21).unwrap());
+//!
+//! if let Some(sunset) = times.sunset {
+//!     println!("The sun sets at {}", sunset.with_timezone(&Local).format("%H:%M"));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::error::Error;
+
+/// The Julian day of 2000-01-01 12:00 UTC.
+const J2000: f64 = 2451545.0;
+/// The Julian day of the Unix epoch.
+const UNIX_EPOCH_JULIAN_DAY: f64 = 2440587.5;
+/// The obliquity of the ecliptic, in degrees.
+const OBLIQUITY: f64 = 23.4397;
+
+/// The elevation of the center of the sun at sunrise and sunset, accounting for refraction and the solar disc.
+const SUNRISE_ELEVATION: f64 = -0.833;
+const CIVIL_TWILIGHT_ELEVATION: f64 = -6.0;
+const NAUTICAL_TWILIGHT_ELEVATION: f64 = -12.0;
+const ASTRONOMICAL_TWILIGHT_ELEVATION: f64 = -18.0;
+
+/// A place on Earth.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    latitude: f64,
+    longitude: f64,
+}
+
+/// The times at which the sun crosses the horizon and the twilight elevations on a given day, in UTC.
+///
+/// A time is `None` when the sun doesn't cross the corresponding elevation on that day,
+/// e.g. during the polar night and the midnight sun.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SunTimes {
+    /// The time at which the sun is at its highest.
+    pub solar_noon: DateTime<Utc>,
+    /// The start of the astronomical twilight, when the sun rises above 18° below the horizon.
+    pub astronomical_dawn: Option<DateTime<Utc>>,
+    /// The start of the nautical twilight, when the sun rises above 12° below the horizon.
+    pub nautical_dawn: Option<DateTime<Utc>>,
+    /// The start of the civil twilight, when the sun rises above 6° below the horizon.
+    pub civil_dawn: Option<DateTime<Utc>>,
+    /// The time at which the upper edge of the sun appears above the horizon.
+    pub sunrise: Option<DateTime<Utc>>,
+    /// The time at which the upper edge of the sun disappears below the horizon.
+    pub sunset: Option<DateTime<Utc>>,
+    /// The end of the civil twilight, when the sun sets below 6° below the horizon.
+    pub civil_dusk: Option<DateTime<Utc>>,
+    /// The end of the nautical twilight, when the sun sets below 12° below the horizon.
+    pub nautical_dusk: Option<DateTime<Utc>>,
+    /// The end of the astronomical twilight, when the sun sets below 18° below the horizon.
+    pub astronomical_dusk: Option<DateTime<Utc>>,
+}
+
+impl Location {
+    /// Returns a new [`Location`].
+    ///
+    /// # Arguments
+    ///
+    /// * `latitude` - in degrees, between -90 (south) and 90 (north)
+    /// * `longitude` - in degrees, between -180 (west) and 180 (east)
+    pub fn new(latitude: f64, longitude: f64) -> Result<Self, Error> {
+        if !(-90.0..=90.0).contains(&latitude) {
+            return Err(Error::Validation {
+                field: "latitude".to_string(),
+                message: "must be between -90 and 90".to_string(),
+            });
+        }
+
+        if !(-180.0..=180.0).contains(&longitude) {
+            return Err(Error::Validation {
+                field: "longitude".to_string(),
+                message: "must be between -180 and 180".to_string(),
+            });
+        }
+
+        Ok(Self {
+            latitude,
+            longitude,
+        })
+    }
+
+    /// Returns the latitude, in degrees.
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    /// Returns the longitude, in degrees.
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    /// Returns the [`SunTimes`] of the solar day that has its noon on `date`.
+    pub fn sun_times(&self, date: NaiveDate) -> SunTimes {
+        let (transit, declination) = self.solar_position(date);
+
+        let crossing = |elevation: f64| {
+            let latitude = self.latitude.to_radians();
+            let cos_hour_angle = (elevation.to_radians().sin()
+                - latitude.sin() * declination.sin())
+                / (latitude.cos() * declination.cos());
+
+            if !(-1.0..=1.0).contains(&cos_hour_angle) {
+                return (None, None);
+            }
+
+            let hour_angle = cos_hour_angle.acos().to_degrees() / 360.0;
+            (
+                Some(from_julian_day(transit - hour_angle)),
+                Some(from_julian_day(transit + hour_angle)),
+            )
+        };
+
+        let (sunrise, sunset) = crossing(SUNRISE_ELEVATION);
+        let (civil_dawn, civil_dusk) = crossing(CIVIL_TWILIGHT_ELEVATION);
+        let (nautical_dawn, nautical_dusk) = crossing(NAUTICAL_TWILIGHT_ELEVATION);
+        let (astronomical_dawn, astronomical_dusk) = crossing(ASTRONOMICAL_TWILIGHT_ELEVATION);
+
+        SunTimes {
+            solar_noon: from_julian_day(transit),
+            astronomical_dawn,
+            nautical_dawn,
+            civil_dawn,
+            sunrise,
+            sunset,
+            civil_dusk,
+            nautical_dusk,
+            astronomical_dusk,
+        }
+    }
+
+    /// Returns `true` if the sun is above the horizon at `time`, i.e. between sunrise and sunset.
+    pub fn is_daylight(&self, time: DateTime<Utc>) -> bool {
+        // The solar day, rather than the UTC day, so that the sunrise and the sunset surround `time`.
+        let solar_date = (time + Duration::seconds((self.longitude * 240.0) as i64)).date_naive();
+        let times = self.sun_times(solar_date);
+
+        match (times.sunrise, times.sunset) {
+            (Some(sunrise), Some(sunset)) => sunrise <= time && time < sunset,
+            // Midnight sun when the sun is over the same hemisphere, polar night otherwise.
+            _ => {
+                let (_, declination) = self.solar_position(solar_date);
+                self.latitude * declination > 0.0
+            }
+        }
+    }
+
+    /// Returns the Julian day of the solar noon and the declination of the sun, in radians.
+    fn solar_position(&self, date: NaiveDate) -> (f64, f64) {
+        let days = (date - NaiveDate::from_ymd_opt(2000, 1, 1).expect("valid date")).num_days();
+        let mean_solar_time = days as f64 - self.longitude / 360.0;
+
+        let mean_anomaly = (357.5291 + 0.98560028 * mean_solar_time).rem_euclid(360.0);
+        let m = mean_anomaly.to_radians();
+        let center = 1.9148 * m.sin() + 0.02 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+        let ecliptic_longitude = (mean_anomaly + center + 180.0 + 102.9372)
+            .rem_euclid(360.0)
+            .to_radians();
+
+        let transit =
+            J2000 + mean_solar_time + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+        let declination = (ecliptic_longitude.sin() * OBLIQUITY.to_radians().sin()).asin();
+
+        (transit, declination)
+    }
+}
+
+fn from_julian_day(julian_day: f64) -> DateTime<Utc> {
+    let seconds = ((julian_day - UNIX_EPOCH_JULIAN_DAY) * 86400.0).round() as i64;
+
+    DateTime::from_timestamp(seconds, 0).expect("the sun times are within the supported range")
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn assert_close(actual: Option<DateTime<Utc>>, expected: DateTime<Utc>) {
+        let difference = (actual.unwrap() - expected).num_seconds().abs();
+        assert!(difference < 180, "{actual:?} is not close to {expected}");
+    }
+
+    #[test]
+    fn sun_times_match_the_almanac() {
+        let london = Location::new(51.5074, -0.1278).unwrap();
+        let times = london.sun_times(NaiveDate::from_ymd_opt(2024, 6, 21).unwrap());
+
+        assert_close(
+            times.sunrise,
+            Utc.with_ymd_and_hms(2024, 6, 21, 3, 43, 0).unwrap(),
+        );
+        assert_close(
+            times.sunset,
+            Utc.with_ymd_and_hms(2024, 6, 21, 20, 21, 0).unwrap(),
+        );
+        assert_close(
+            Some(times.solar_noon),
+            Utc.with_ymd_and_hms(2024, 6, 21, 12, 2, 0).unwrap(),
+        );
+        // The sun stays above 18° below the horizon all night.
+        assert_eq!(times.astronomical_dusk, None);
+
+        assert!(london.is_daylight(Utc.with_ymd_and_hms(2024, 6, 21, 12, 0, 0).unwrap()));
+        assert!(!london.is_daylight(Utc.with_ymd_and_hms(2024, 6, 21, 23, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn polar_days_have_no_sunrise() {
+        let tromso = Location::new(69.6492, 18.9553).unwrap();
+
+        let winter = Utc.with_ymd_and_hms(2024, 12, 21, 11, 0, 0).unwrap();
+        assert_eq!(tromso.sun_times(winter.date_naive()).sunrise, None);
+        assert!(!tromso.is_daylight(winter));
+
+        let summer = Utc.with_ymd_and_hms(2024, 6, 21, 23, 0, 0).unwrap();
+        assert_eq!(tromso.sun_times(summer.date_naive()).sunset, None);
+        assert!(tromso.is_daylight(summer));
+
+        assert!(Location::new(91.0, 0.0).is_err());
+    }
+}
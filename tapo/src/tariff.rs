@@ -0,0 +1,375 @@
+//! Energy cost calculation with flat, time-of-use and per-weekday tariffs.
+//!
+//! Requires the `tariff` feature.
+//!
+//! Prices are [`Decimal`] values per kWh, in any currency. The costs are computed without rounding,
+//! so that they can be rounded once, to the precision of the currency, e.g. with [`Decimal::round_dp`].
+//!
+//! # Example
+//!
+//! ```rust
+//! # use chrono::{NaiveTime, TimeZone, Utc, Weekday};
+//! # use tapo::tariff::{Decimal, Tariff};
+//! # fn main() -> Result<(), tapo::Error> {
+//! let tariff = Tariff::builder(Decimal::new(30, 2))
+//!     .band(
+//!         "night",
+//!         NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+//!         NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+//!         Decimal::new(12, 2),
+//!     )
+//!     .weekday_band(
+//!         "weekend",
+//!         &[Weekday::Sat, Weekday::Sun],
+//!         NaiveTime::MIN,
+//!         NaiveTime::MIN,
+//!         Decimal::new(15, 2),
+//!     )
+//!     .build()?;
+//!
+//! // 1 kWh at 06:00 and 1 kWh at 12:00 on a Monday.
+//! let samples = vec![
+//!     (Utc.with_ymd_and_hms(2024, 1, 1, 6, 0, 0).unwrap(), 1000.0),
+//!     (Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap(), 1000.0),
+//! ];
+//!
+//! let cost = tariff.cost(samples, &Utc);
+//!
+//! assert_eq!(cost.cost, Decimal::new(42, 2));
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, NaiveDateTime, NaiveTime, TimeZone, Utc, Weekday};
+pub use rust_decimal::Decimal;
+
+use crate::error::Error;
+use crate::responses::EnergyDataResult;
+
+/// The name of the price applied outside of all the bands of a [`Tariff`].
+pub const DEFAULT_BAND: &str = "default";
+
+/// The interval, in minutes, of the [`EnergyDataResult`] that time-of-use tariffs can be applied to.
+const HOURLY_INTERVAL: u64 = 60;
+
+/// A price per kWh, with optional time-of-use bands that override it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tariff {
+    default_price: Decimal,
+    bands: Vec<TariffBand>,
+}
+
+/// A period of the day, optionally restricted to some days of the week, with its own price.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TariffBand {
+    /// The name of the band, e.g. `peak` or `off-peak`.
+    pub name: String,
+    /// The days of the week the band applies to, or every day if empty.
+    /// A band that spans midnight belongs to the day on which it starts.
+    pub days: Vec<Weekday>,
+    /// The time at which the band starts, inclusive.
+    pub start: NaiveTime,
+    /// The time at which the band ends, exclusive. The band spans midnight if it's not after `start`.
+    pub end: NaiveTime,
+    /// The price per kWh.
+    pub price_per_kwh: Decimal,
+}
+
+impl TariffBand {
+    fn contains(&self, datetime: NaiveDateTime) -> bool {
+        let applies_on = |day: Weekday| self.days.is_empty() || self.days.contains(&day);
+        let time = datetime.time();
+
+        if self.start < self.end {
+            applies_on(datetime.weekday()) && self.start <= time && time < self.end
+        } else {
+            (applies_on(datetime.weekday()) && time >= self.start)
+                || (applies_on(datetime.weekday().pred()) && time < self.end)
+        }
+    }
+}
+
+impl Tariff {
+    /// Returns a tariff with the same price at all times.
+    ///
+    /// # Arguments
+    ///
+    /// * `price_per_kwh` - the price per kWh
+    pub fn flat(price_per_kwh: Decimal) -> Self {
+        Self {
+            default_price: price_per_kwh,
+            bands: Vec::new(),
+        }
+    }
+
+    /// Returns a [`TariffBuilder`] for a time-of-use tariff.
+    ///
+    /// # Arguments
+    ///
+    /// * `default_price_per_kwh` - the price per kWh outside of all the bands
+    pub fn builder(default_price_per_kwh: Decimal) -> TariffBuilder {
+        TariffBuilder {
+            tariff: Self::flat(default_price_per_kwh),
+        }
+    }
+
+    /// Returns the bands of the tariff, in the order in which they are matched.
+    pub fn bands(&self) -> &[TariffBand] {
+        &self.bands
+    }
+
+    /// Returns the name and the price per kWh that apply at the given local time.
+    pub fn price_at(&self, datetime: NaiveDateTime) -> (&str, Decimal) {
+        self.bands
+            .iter()
+            .find(|band| band.contains(datetime))
+            .map(|band| (band.name.as_str(), band.price_per_kwh))
+            .unwrap_or((DEFAULT_BAND, self.default_price))
+    }
+
+    /// Returns the cost of the energy used in each sample, priced at the start of the sample.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - `(timestamp, energy in Wh)` pairs, e.g. from [`EnergyDataResult::series`]
+    /// * `timezone` - the time zone in which the bands are defined
+    pub fn cost<Tz>(
+        &self,
+        samples: impl IntoIterator<Item = (DateTime<Utc>, f64)>,
+        timezone: &Tz,
+    ) -> EnergyCost
+    where
+        Tz: TimeZone,
+    {
+        let mut bands: BTreeMap<&str, BandCost> = BTreeMap::new();
+
+        for (timestamp, energy_wh) in samples {
+            let Some(energy_wh) = Decimal::from_f64_retain(energy_wh) else {
+                continue;
+            };
+            let energy_kwh = energy_wh / Decimal::ONE_THOUSAND;
+            let (name, price_per_kwh) =
+                self.price_at(timestamp.with_timezone(timezone).naive_local());
+
+            let band = bands.entry(name).or_insert_with(|| BandCost {
+                name: name.to_string(),
+                price_per_kwh,
+                energy_kwh: Decimal::ZERO,
+                cost: Decimal::ZERO,
+            });
+            band.energy_kwh += energy_kwh;
+            band.cost += energy_kwh * price_per_kwh;
+        }
+
+        let bands: Vec<_> = bands.into_values().collect();
+
+        EnergyCost {
+            energy_kwh: bands.iter().map(|band| band.energy_kwh).sum(),
+            cost: bands.iter().map(|band| band.cost).sum(),
+            bands,
+        }
+    }
+
+    /// Returns the cost of the energy reported by [`crate::PlugEnergyMonitoringHandler::get_energy_data`].
+    ///
+    /// Daily and monthly data can only be priced with flat tariffs,
+    /// because their intervals can't be split into the bands of a time-of-use tariff.
+    ///
+    /// # Arguments
+    ///
+    /// * `energy_data` - the energy data
+    /// * `timezone` - the time zone in which the bands are defined
+    pub fn energy_data_cost<Tz>(
+        &self,
+        energy_data: &EnergyDataResult,
+        timezone: &Tz,
+    ) -> Result<EnergyCost, Error>
+    where
+        Tz: TimeZone,
+    {
+        if !self.bands.is_empty() && energy_data.interval > HOURLY_INTERVAL {
+            return Err(Error::Validation {
+                field: "interval".to_string(),
+                message: "Time-of-use tariffs can only be applied to hourly energy data"
+                    .to_string(),
+            });
+        }
+
+        Ok(self.cost(energy_data.series(), timezone))
+    }
+}
+
+/// Builder for a time-of-use [`Tariff`], see [`Tariff::builder`].
+/// When bands overlap, the band that has been added first applies.
+#[derive(Debug, Clone)]
+pub struct TariffBuilder {
+    tariff: Tariff,
+}
+
+impl TariffBuilder {
+    /// Adds a band that applies every day.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the band
+    /// * `start` - the time at which the band starts, inclusive
+    /// * `end` - the time at which the band ends, exclusive; the band spans midnight if it's not after `start`
+    /// * `price_per_kwh` - the price per kWh
+    pub fn band(
+        self,
+        name: impl Into<String>,
+        start: NaiveTime,
+        end: NaiveTime,
+        price_per_kwh: Decimal,
+    ) -> Self {
+        self.weekday_band(name, &[], start, end, price_per_kwh)
+    }
+
+    /// Adds a band that applies on the given days of the week.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the name of the band
+    /// * `days` - the days of the week on which the band starts
+    /// * `start` - the time at which the band starts, inclusive
+    /// * `end` - the time at which the band ends, exclusive; the band spans midnight if it's not after `start`
+    /// * `price_per_kwh` - the price per kWh
+    pub fn weekday_band(
+        mut self,
+        name: impl Into<String>,
+        days: &[Weekday],
+        start: NaiveTime,
+        end: NaiveTime,
+        price_per_kwh: Decimal,
+    ) -> Self {
+        self.tariff.bands.push(TariffBand {
+            name: name.into(),
+            days: days.to_vec(),
+            start,
+            end,
+            price_per_kwh,
+        });
+        self
+    }
+
+    /// Validates the prices and returns the [`Tariff`].
+    pub fn build(self) -> Result<Tariff, Error> {
+        let negative_price = self.tariff.default_price.is_sign_negative()
+            || self
+                .tariff
+                .bands
+                .iter()
+                .any(|band| band.price_per_kwh.is_sign_negative());
+
+        if negative_price {
+            return Err(Error::Validation {
+                field: "price_per_kwh".to_string(),
+                message: "Must not be negative".to_string(),
+            });
+        }
+
+        Ok(self.tariff)
+    }
+}
+
+/// The cost of the energy used over a period, see [`Tariff::cost`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnergyCost {
+    /// The energy used, in kWh.
+    pub energy_kwh: Decimal,
+    /// The cost of the energy used.
+    pub cost: Decimal,
+    /// The energy used and its cost in each band of the tariff that applied, ordered by name.
+    /// The time outside of all the bands is reported as [`DEFAULT_BAND`].
+    pub bands: Vec<BandCost>,
+}
+
+/// The energy used and its cost in one band of a [`Tariff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BandCost {
+    /// The name of the band.
+    pub name: String,
+    /// The price per kWh of the band.
+    pub price_per_kwh: Decimal,
+    /// The energy used, in kWh.
+    pub energy_kwh: Decimal,
+    /// The cost of the energy used.
+    pub cost: Decimal,
+}
+
+impl EnergyCost {
+    /// Returns the average price per kWh, or zero if no energy has been used.
+    pub fn average_price_per_kwh(&self) -> Decimal {
+        self.cost
+            .checked_div(self.energy_kwh)
+            .unwrap_or(Decimal::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn time(hour: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    fn datetime(day: u32, hour: u32) -> NaiveDateTime {
+        // January 1st 2024 is a Monday.
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_time(time(hour))
+    }
+
+    #[test]
+    fn bands_spanning_midnight_belong_to_their_start_day() {
+        let tariff = Tariff::builder(Decimal::new(30, 2))
+            .weekday_band(
+                "friday night",
+                &[Weekday::Fri],
+                time(22),
+                time(6),
+                Decimal::ONE,
+            )
+            .build()
+            .unwrap();
+
+        // Friday 23:00, Saturday 05:00 and Saturday 23:00.
+        assert_eq!(tariff.price_at(datetime(5, 23)).0, "friday night");
+        assert_eq!(tariff.price_at(datetime(6, 5)).0, "friday night");
+        assert_eq!(tariff.price_at(datetime(6, 23)).0, DEFAULT_BAND);
+    }
+
+    #[test]
+    fn cost_is_split_by_band() {
+        let tariff = Tariff::builder(Decimal::new(30, 2))
+            .band("night", time(0), time(7), Decimal::new(10, 2))
+            .build()
+            .unwrap();
+        let samples = [(datetime(1, 3), 500.0), (datetime(1, 12), 1500.0)]
+            .map(|(datetime, energy)| (datetime.and_utc(), energy));
+
+        let cost = tariff.cost(samples, &Utc);
+
+        assert_eq!(cost.energy_kwh, Decimal::TWO);
+        assert_eq!(cost.cost, Decimal::new(50, 2));
+        assert_eq!(cost.bands[0].name, DEFAULT_BAND);
+        assert_eq!(cost.bands[0].cost, Decimal::new(45, 2));
+        assert_eq!(cost.bands[1].name, "night");
+        assert_eq!(cost.bands[1].cost, Decimal::new(5, 2));
+        assert_eq!(cost.average_price_per_kwh(), Decimal::new(25, 2));
+    }
+
+    #[test]
+    fn negative_prices_are_rejected() {
+        let result = Tariff::builder(Decimal::ONE)
+            .band("night", time(0), time(7), Decimal::NEGATIVE_ONE)
+            .build();
+
+        assert!(matches!(result, Err(Error::Validation { .. })));
+    }
+}
@@ -0,0 +1,12 @@
+//! Change detection for devices that don't push their state, based on polling,
+//! and the frost monitoring of the KE100 thermostatic radiator valves paired with a hub.
+
+mod device_change;
+mod device_watcher;
+mod frost_monitor;
+mod watchable_device;
+
+pub use device_change::*;
+pub use device_watcher::*;
+pub use frost_monitor::*;
+pub use watchable_device::*;
@@ -0,0 +1,96 @@
+/// Change of the state of a device, as detected by [`crate::watcher::DeviceWatcher`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceChange {
+    /// The device has been turned *on*.
+    TurnedOn,
+    /// The device has been turned *off*.
+    TurnedOff,
+    /// The *brightness* has changed.
+    BrightnessChanged {
+        /// The previous brightness, if known.
+        previous: Option<u8>,
+        /// The current brightness.
+        current: u8,
+    },
+    /// The *color temperature* has changed.
+    ColorTemperatureChanged {
+        /// The previous color temperature, if known.
+        previous: Option<u16>,
+        /// The current color temperature.
+        current: u16,
+    },
+    /// The *hue* and/or the *saturation* have changed.
+    HueSaturationChanged {
+        /// The current hue.
+        hue: Option<u16>,
+        /// The current saturation.
+        saturation: Option<u8>,
+    },
+    /// The device could not be reached (connection refused or timed out).
+    BecameUnreachable,
+    /// The device can be reached again after having been unreachable.
+    BecameReachable,
+    /// The battery has become low, see [`crate::watcher::DeviceWatcher::with_low_battery_threshold`].
+    BatteryLow {
+        /// The remaining charge, for the devices that report it.
+        percentage: Option<u8>,
+    },
+}
+
+impl DeviceChange {
+    /// Returns the changes between two snapshots of the *device info* of a device.
+    pub(crate) fn diff(previous: &serde_json::Value, current: &serde_json::Value) -> Vec<Self> {
+        let mut changes = Vec::new();
+
+        let bool_field = |value: &serde_json::Value, name: &str| {
+            value.get(name).and_then(serde_json::Value::as_bool)
+        };
+        let number_field = |value: &serde_json::Value, name: &str| {
+            value.get(name).and_then(serde_json::Value::as_u64)
+        };
+
+        if let Some(device_on) = bool_field(current, "device_on") {
+            if bool_field(previous, "device_on") != Some(device_on) {
+                changes.push(if device_on {
+                    DeviceChange::TurnedOn
+                } else {
+                    DeviceChange::TurnedOff
+                });
+            }
+        }
+
+        if let Some(brightness) = number_field(current, "brightness") {
+            let previous = number_field(previous, "brightness");
+            if previous != Some(brightness) {
+                changes.push(DeviceChange::BrightnessChanged {
+                    previous: previous.map(|value| value as u8),
+                    current: brightness as u8,
+                });
+            }
+        }
+
+        if let Some(color_temperature) = number_field(current, "color_temp") {
+            let previous = number_field(previous, "color_temp");
+            if previous != Some(color_temperature) {
+                changes.push(DeviceChange::ColorTemperatureChanged {
+                    previous: previous.map(|value| value as u16),
+                    current: color_temperature as u16,
+                });
+            }
+        }
+
+        let hue = number_field(current, "hue");
+        let saturation = number_field(current, "saturation");
+        if (hue.is_some() || saturation.is_some())
+            && (hue != number_field(previous, "hue")
+                || saturation != number_field(previous, "saturation"))
+        {
+            changes.push(DeviceChange::HueSaturationChanged {
+                hue: hue.map(|value| value as u16),
+                saturation: saturation.map(|value| value as u8),
+            });
+        }
+
+        changes
+    }
+}
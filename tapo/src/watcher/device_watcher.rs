@@ -0,0 +1,344 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use log::{debug, warn};
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::error::{Error, TapoResponseError};
+use crate::events::DeviceEvent;
+use crate::persistence::Persistence;
+use crate::responses::BatteryStatus;
+use crate::watcher::{DeviceChange, WatchableDevice};
+
+/// Polls the *device info* of a device at a fixed interval and reports the changes
+/// between consecutive snapshots as [`DeviceChange`] events.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use tapo::ApiClient;
+/// # use tapo::watcher::DeviceWatcher;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+///     .l530("192.168.1.100")
+///     .await?;
+///
+/// DeviceWatcher::new(device, Duration::from_secs(5))
+///     .run(|change| println!("Change: {change:?}"))
+///     .await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct DeviceWatcher<D> {
+    device: D,
+    interval: Interval,
+    snapshot: Option<serde_json::Value>,
+    reachable: Option<bool>,
+    persistence: Option<(Arc<dyn Persistence>, String)>,
+    low_battery_threshold: u8,
+}
+
+impl<D> DeviceWatcher<D>
+where
+    D: WatchableDevice,
+{
+    /// Returns a new [`DeviceWatcher`] that polls `device` every `interval`.
+    /// The first poll happens immediately and establishes the baseline that the next polls are compared to.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the handler of the device to watch
+    /// * `interval` - the time between two polls
+    pub fn new(device: D, interval: Duration) -> Self {
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        Self {
+            device,
+            interval,
+            snapshot: None,
+            reachable: None,
+            persistence: None,
+            low_battery_threshold: 20,
+        }
+    }
+
+    /// Sets the charge, in percent, at or below which a [`DeviceChange::BatteryLow`] is reported. Defaults to 20.
+    /// The devices that don't report their charge are low once they report it themselves.
+    pub fn with_low_battery_threshold(mut self, threshold: u8) -> Self {
+        self.low_battery_threshold = threshold;
+        self
+    }
+
+    /// Restores the last known state of the device saved under `key`, if any, and saves it after every poll.
+    /// The first poll is then compared to the restored state, which reports the changes
+    /// that happened while the application was down.
+    ///
+    /// # Arguments
+    ///
+    /// * `persistence` - where the state is saved
+    /// * `key` - the key of the device, usually its name
+    pub fn with_persistence(
+        mut self,
+        persistence: Arc<dyn Persistence>,
+        key: impl Into<String>,
+    ) -> Result<Self, Error> {
+        let key = key.into();
+
+        if let Some(state) = persistence.load(&key)? {
+            self.snapshot = state.device_info;
+            self.reachable = state.reachable;
+        }
+
+        self.persistence = Some((persistence, key));
+
+        Ok(self)
+    }
+
+    /// Returns a reference to the watched device handler.
+    pub fn device(&self) -> &D {
+        &self.device
+    }
+
+    /// Consumes the watcher and returns the watched device handler.
+    pub fn into_device(self) -> D {
+        self.device
+    }
+
+    /// Waits for the next poll and returns the changes since the previous one.
+    /// The session is refreshed automatically when it expires.
+    /// A device in maintenance mode is considered unchanged.
+    /// Errors other than the device being unreachable are returned as they are.
+    pub async fn poll(&mut self) -> Result<Vec<DeviceChange>, Error> {
+        let changes = self.poll_device().await?;
+        self.persist();
+
+        Ok(changes)
+    }
+
+    async fn poll_device(&mut self) -> Result<Vec<DeviceChange>, Error> {
+        self.interval.tick().await;
+
+        let device_info = match self.device.get_device_info_json().await {
+            Err(Error::Tapo(TapoResponseError::SessionTimeout)) => {
+                debug!("Session timed out, refreshing...");
+                self.device.refresh_session().await?;
+                self.device.get_device_info_json().await
+            }
+            result => result,
+        };
+
+        match device_info {
+            Ok(device_info) => {
+                let mut changes = Vec::new();
+
+                if self.reachable.replace(true) == Some(false) {
+                    changes.push(DeviceChange::BecameReachable);
+                }
+
+                if let Some(previous) = &self.snapshot {
+                    changes.extend(DeviceChange::diff(previous, &device_info));
+                    changes.extend(self.battery_change(previous, &device_info));
+                }
+                self.snapshot.replace(device_info);
+
+                Ok(changes)
+            }
+            Err(err) if err.is_maintenance() => {
+                debug!("The device is in maintenance, skipping the poll");
+                Ok(Vec::new())
+            }
+            Err(err) if err.is_unreachable() => {
+                if self.reachable.replace(false) == Some(false) {
+                    Ok(Vec::new())
+                } else {
+                    Ok(vec![DeviceChange::BecameUnreachable])
+                }
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns [`DeviceChange::BatteryLow`] if the battery has crossed the threshold since the `previous` snapshot.
+    fn battery_change(
+        &self,
+        previous: &serde_json::Value,
+        current: &serde_json::Value,
+    ) -> Option<DeviceChange> {
+        let threshold = self.low_battery_threshold;
+        let battery = BatteryStatus::from_device_info(current)?;
+        let was_low = BatteryStatus::from_device_info(previous)
+            .is_some_and(|battery| battery.is_low(threshold));
+
+        (battery.is_low(threshold) && !was_low).then_some(DeviceChange::BatteryLow {
+            percentage: battery.percentage,
+        })
+    }
+
+    /// Saves the current state, keeping the other fields of the persisted state, e.g. the energy accumulator.
+    /// Failures are logged, so that they don't interrupt the polling.
+    fn persist(&self) {
+        let Some((persistence, key)) = &self.persistence else {
+            return;
+        };
+
+        let result = persistence.load(key).and_then(|state| {
+            let mut state = state.unwrap_or_default();
+            state.device_info.clone_from(&self.snapshot);
+            state.reachable = self.reachable;
+            state.updated_at = Some(Utc::now());
+
+            persistence.save(key, &state)
+        });
+
+        if let Err(err) = result {
+            warn!("Failed to persist the state of `{key}`: {err:?}");
+        }
+    }
+
+    /// Same as [`DeviceWatcher::poll`], but returns the changes as [`DeviceEvent`]s.
+    pub async fn poll_events(&mut self) -> Result<Vec<DeviceEvent>, Error> {
+        let changes = self.poll().await?;
+
+        Ok(changes.into_iter().map(DeviceEvent::from).collect())
+    }
+
+    /// Polls the device forever and calls `on_change` for every change.
+    /// Errors other than the device being unreachable are logged and the polling continues.
+    pub async fn run(mut self, mut on_change: impl FnMut(DeviceChange)) {
+        loop {
+            match self.poll().await {
+                Ok(changes) => changes.into_iter().for_each(&mut on_change),
+                Err(err) => warn!("Failed to poll the device: {err:?}"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    use crate::persistence::FilePersistence;
+
+    use super::*;
+
+    struct FakeDevice {
+        responses: Mutex<VecDeque<Result<serde_json::Value, Error>>>,
+    }
+
+    #[async_trait]
+    impl WatchableDevice for FakeDevice {
+        async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
+            self.responses.lock().unwrap().pop_front().unwrap()
+        }
+
+        async fn refresh_session(&mut self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn unreachable() -> Result<serde_json::Value, Error> {
+        Err(isahc::Error::from(isahc::error::ErrorKind::ConnectionFailed).into())
+    }
+
+    #[tokio::test]
+    async fn poll_reports_changes() {
+        let device = FakeDevice {
+            responses: Mutex::new(VecDeque::from([
+                Ok(json!({ "device_on": false, "brightness": 10 })),
+                Ok(json!({ "device_on": true, "brightness": 50 })),
+                unreachable(),
+                unreachable(),
+                Ok(json!({ "device_on": true, "brightness": 50 })),
+            ])),
+        };
+        let mut watcher = DeviceWatcher::new(device, Duration::from_millis(1));
+
+        assert_eq!(watcher.poll().await.unwrap(), vec![]);
+        assert_eq!(
+            watcher.poll().await.unwrap(),
+            vec![
+                DeviceChange::TurnedOn,
+                DeviceChange::BrightnessChanged {
+                    previous: Some(10),
+                    current: 50
+                }
+            ]
+        );
+        assert_eq!(
+            watcher.poll().await.unwrap(),
+            vec![DeviceChange::BecameUnreachable]
+        );
+        assert_eq!(watcher.poll().await.unwrap(), vec![]);
+        assert_eq!(
+            watcher.poll().await.unwrap(),
+            vec![DeviceChange::BecameReachable]
+        );
+    }
+
+    #[tokio::test]
+    async fn low_battery_is_reported_once() {
+        let device = FakeDevice {
+            responses: Mutex::new(VecDeque::from([
+                Ok(json!({ "battery_percentage": 30 })),
+                Ok(json!({ "battery_percentage": 25 })),
+                Ok(json!({ "battery_percentage": 24 })),
+                Ok(json!({ "battery_percentage": 23, "at_low_battery": true })),
+                Ok(json!({ "battery_percentage": 100 })),
+                Ok(json!({ "battery_percentage": 100, "at_low_battery": true })),
+            ])),
+        };
+        let mut watcher =
+            DeviceWatcher::new(device, Duration::from_millis(1)).with_low_battery_threshold(25);
+
+        assert_eq!(watcher.poll().await.unwrap(), vec![]);
+        assert_eq!(
+            watcher.poll().await.unwrap(),
+            vec![DeviceChange::BatteryLow {
+                percentage: Some(25)
+            }]
+        );
+        assert_eq!(watcher.poll().await.unwrap(), vec![]);
+        assert_eq!(watcher.poll().await.unwrap(), vec![]);
+        assert_eq!(watcher.poll().await.unwrap(), vec![]);
+        assert_eq!(
+            watcher.poll().await.unwrap(),
+            vec![DeviceChange::BatteryLow {
+                percentage: Some(100)
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn persisted_state_is_restored() {
+        let path = std::env::temp_dir().join(format!("tapo-watcher-{}.json", uuid::Uuid::new_v4()));
+        let persistence = Arc::new(FilePersistence::open(&path).unwrap());
+
+        let device = FakeDevice {
+            responses: Mutex::new(VecDeque::from([Ok(json!({ "device_on": false }))])),
+        };
+        let mut watcher = DeviceWatcher::new(device, Duration::from_millis(1))
+            .with_persistence(persistence.clone(), "lamp")
+            .unwrap();
+        assert_eq!(watcher.poll().await.unwrap(), vec![]);
+
+        let device = FakeDevice {
+            responses: Mutex::new(VecDeque::from([Ok(json!({ "device_on": true }))])),
+        };
+        let mut watcher = DeviceWatcher::new(device, Duration::from_millis(1))
+            .with_persistence(persistence, "lamp")
+            .unwrap();
+        let changes = watcher.poll().await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(changes.unwrap(), vec![DeviceChange::TurnedOn]);
+    }
+}
@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use log::{debug, warn};
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::error::{Error, TapoResponseError};
+use crate::events::DeviceEvent;
+use crate::responses::{
+    ChildDeviceResult, KE100Result, Temperature, TemperatureDelta, TemperatureUnitKE100,
+};
+use crate::HubHandler;
+
+/// Frost protection settings of a room, i.e. of a KE100 thermostatic radiator valve (TRV).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrostRoom {
+    threshold: Temperature,
+    boost: Option<u8>,
+    hysteresis: TemperatureDelta,
+}
+
+impl FrostRoom {
+    /// Returns a [`FrostRoom`] that is too cold below `threshold`, with a hysteresis of 1 °C and no boost.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - the *current temperature* below which the room is too cold
+    pub fn new(threshold: Temperature) -> Self {
+        Self {
+            threshold,
+            boost: None,
+            hysteresis: TemperatureDelta::from_celsius(1.0),
+        }
+    }
+
+    /// Raises the *target temperature* to `target_temperature` while the room is too cold,
+    /// unless it's already higher, and restores it once the room has recovered.
+    /// The devices reject the values outside of their `min_control_temperature` and `max_control_temperature`.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_temperature` - in degrees Celsius
+    pub fn with_boost(mut self, target_temperature: u8) -> Self {
+        self.boost = Some(target_temperature);
+        self
+    }
+
+    /// Sets how far above the threshold the *current temperature* has to rise for the room to recover.
+    /// Defaults to 1 °C, which prevents a reading hovering around the threshold from reporting every poll.
+    pub fn with_hysteresis(mut self, hysteresis: TemperatureDelta) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+}
+
+/// Change of the frost state of a room, as detected by [`FrostMonitor`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrostChange {
+    /// The *current temperature* has fallen below the threshold.
+    TooCold {
+        /// The current temperature.
+        temperature: Temperature,
+        /// The threshold of the room.
+        threshold: Temperature,
+    },
+    /// The *target temperature* has been raised, see [`FrostRoom::with_boost`].
+    Boosted {
+        /// The target temperature before the boost.
+        previous: Temperature,
+        /// The boosted target temperature.
+        current: Temperature,
+    },
+    /// The *current temperature* has risen above the threshold plus the hysteresis.
+    Recovered {
+        /// The current temperature.
+        temperature: Temperature,
+    },
+    /// The *target temperature* from before the boost has been restored.
+    /// It isn't if the target temperature has been changed in the meantime.
+    Restored {
+        /// The restored target temperature.
+        target_temperature: Temperature,
+    },
+}
+
+/// [`FrostChange`] of a KE100 thermostatic radiator valve (TRV).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrostEvent {
+    /// The Device ID of the TRV.
+    pub device_id: String,
+    /// The nickname of the TRV, usually the name of the room.
+    pub nickname: String,
+    /// What has changed.
+    pub change: FrostChange,
+}
+
+impl FrostEvent {
+    /// Returns the [`DeviceEvent::ThresholdAlert`] of a [`FrostChange::TooCold`], e.g. to pass it to a
+    /// [`crate::rules::RuleEngine`] under the name of the TRV. The other changes have no matching event.
+    pub fn to_device_event(&self) -> Option<DeviceEvent> {
+        match self.change {
+            FrostChange::TooCold {
+                temperature,
+                threshold,
+            } => Some(DeviceEvent::ThresholdAlert {
+                metric: "temperature".to_string(),
+                value: temperature.celsius().into(),
+                threshold: threshold.celsius().into(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Polls the child devices of a hub at a fixed interval and reports the KE100 thermostatic radiator valves (TRVs)
+/// whose *current temperature* falls below the threshold of their room as [`FrostEvent`]s,
+/// boosting their *target temperature* if configured to.
+///
+/// All the TRVs paired with the hub are monitored with the default room settings,
+/// unless they have settings of their own, see [`FrostMonitor::room`].
+/// A single request per poll reads the temperatures of all of them.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use tapo::ApiClient;
+/// # use tapo::responses::Temperature;
+/// # use tapo::watcher::{FrostMonitor, FrostRoom};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let hub = ApiClient::new("tapo-username@example.com", "tapo-password")?
+///     .h100("192.168.1.100")
+///     .await?;
+///
+/// FrostMonitor::new(hub, Duration::from_secs(300))
+///     .with_default_room(Some(FrostRoom::new(Temperature::from_celsius(7.0))))
+///     .room(
+///         "ke100-device-id",
+///         FrostRoom::new(Temperature::from_celsius(10.0)).with_boost(18),
+///     )
+///     .run(|event| println!("Event: {event:?}"))
+///     .await;
+/// # Ok(())
+/// # }
+/// ```
+pub struct FrostMonitor {
+    hub: HubHandler,
+    interval: Interval,
+    default_room: Option<FrostRoom>,
+    rooms: HashMap<String, FrostRoom>,
+    states: HashMap<String, RoomState>,
+}
+
+impl FrostMonitor {
+    /// Returns a new [`FrostMonitor`] that polls `hub` every `interval`.
+    /// The TRVs without settings of their own are too cold below 5 °C, with no boost.
+    ///
+    /// # Arguments
+    ///
+    /// * `hub` - the handler of the hub that the TRVs are paired with
+    /// * `interval` - the time between two polls
+    pub fn new(hub: HubHandler, interval: Duration) -> Self {
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        Self {
+            hub,
+            interval,
+            default_room: Some(FrostRoom::new(Temperature::from_celsius(5.0))),
+            rooms: HashMap::new(),
+            states: HashMap::new(),
+        }
+    }
+
+    /// Sets the settings of the TRVs without settings of their own, or `None` to monitor only those that have.
+    pub fn with_default_room(mut self, room: Option<FrostRoom>) -> Self {
+        self.default_room = room;
+        self
+    }
+
+    /// Sets the settings of a single TRV, replacing the previous ones, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `device_id` - the Device ID of the TRV
+    /// * `room` - its settings
+    pub fn room(mut self, device_id: impl Into<String>, room: FrostRoom) -> Self {
+        self.rooms.insert(device_id.into(), room);
+        self
+    }
+
+    /// Returns a reference to the hub handler.
+    pub fn hub(&self) -> &HubHandler {
+        &self.hub
+    }
+
+    /// Returns the Device IDs of the TRVs that are currently too cold.
+    pub fn cold_rooms(&self) -> Vec<&str> {
+        self.states
+            .iter()
+            .filter(|(_, state)| state.cold)
+            .map(|(device_id, _)| device_id.as_str())
+            .collect()
+    }
+
+    /// Waits for the next poll and returns the changes since the previous one.
+    /// The session is refreshed automatically when it expires.
+    /// A hub in maintenance mode is considered unchanged.
+    ///
+    /// A boost or a restore that fails is logged and retried at the next poll.
+    pub async fn poll(&mut self) -> Result<Vec<FrostEvent>, Error> {
+        self.interval.tick().await;
+
+        let devices = match self.hub.get_child_device_list().await {
+            Err(Error::Tapo(TapoResponseError::SessionTimeout)) => {
+                debug!("Session timed out, refreshing...");
+                self.hub.refresh_session().await?;
+                self.hub.get_child_device_list().await
+            }
+            result => result,
+        };
+
+        let devices = match devices {
+            Ok(devices) => devices,
+            Err(err) if err.is_maintenance() => {
+                debug!("The hub is in maintenance, skipping the poll");
+                return Ok(Vec::new());
+            }
+            Err(err) => return Err(err),
+        };
+
+        let mut events = Vec::new();
+
+        for device in devices {
+            let ChildDeviceResult::KE100(trv) = device else {
+                continue;
+            };
+
+            let Some(room) = self
+                .rooms
+                .get(&trv.device_id)
+                .or(self.default_room.as_ref())
+            else {
+                continue;
+            };
+            let room = *room;
+
+            let state = self.states.entry(trv.device_id.clone()).or_default();
+            let steps = state.update(&room, trv.current_temperature, trv.target_temperature);
+
+            for step in steps {
+                if let Some(change) = self.apply(&trv, step).await {
+                    events.push(FrostEvent {
+                        device_id: trv.device_id.clone(),
+                        nickname: trv.nickname.clone(),
+                        change,
+                    });
+                }
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Same as [`FrostMonitor::poll`], but returns the [`FrostChange::TooCold`] changes as [`DeviceEvent`]s,
+    /// along with the nickname of their TRV.
+    pub async fn poll_events(&mut self) -> Result<Vec<(String, DeviceEvent)>, Error> {
+        let events = self.poll().await?;
+
+        Ok(events
+            .into_iter()
+            .filter_map(|event| Some((event.nickname.clone(), event.to_device_event()?)))
+            .collect())
+    }
+
+    /// Polls the hub forever and calls `on_event` for every event.
+    /// Errors are logged and the polling continues.
+    pub async fn run(mut self, mut on_event: impl FnMut(FrostEvent)) {
+        loop {
+            match self.poll().await {
+                Ok(events) => events.into_iter().for_each(&mut on_event),
+                Err(err) => warn!("Failed to poll the hub: {err:?}"),
+            }
+        }
+    }
+
+    /// Sends the request of `step`, if any, and returns the matching change, unless the request failed.
+    async fn apply(&mut self, trv: &KE100Result, step: Step) -> Option<FrostChange> {
+        let (target_temperature, change) = match step {
+            Step::Report(change) => return Some(change),
+            Step::Boost(target_temperature) => (
+                target_temperature,
+                FrostChange::Boosted {
+                    previous: trv.target_temperature,
+                    current: Temperature::from_celsius(target_temperature.into()),
+                },
+            ),
+            Step::Restore(previous, _) => (
+                previous.celsius().round() as u8,
+                FrostChange::Restored {
+                    target_temperature: previous,
+                },
+            ),
+        };
+
+        let result = self
+            .hub
+            .ke100(trv.device_id.as_str())
+            .set_target_temperature(target_temperature, TemperatureUnitKE100::Celsius)
+            .await;
+
+        match result {
+            Ok(()) => Some(change),
+            Err(err) => {
+                warn!(
+                    "Failed to set the target temperature of `{}`: {err:?}",
+                    trv.nickname
+                );
+                if let Some(state) = self.states.get_mut(&trv.device_id) {
+                    state.revert(&step);
+                }
+                None
+            }
+        }
+    }
+}
+
+/// What a poll leads to for a single TRV.
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Report(FrostChange),
+    Boost(u8),
+    /// The target temperature before the boost, and the boosted one.
+    Restore(Temperature, u8),
+}
+
+#[derive(Debug, Default)]
+struct RoomState {
+    cold: bool,
+    /// The target temperature before the boost, and the boosted one.
+    boosted: Option<(Temperature, u8)>,
+}
+
+impl RoomState {
+    /// Updates the state with the latest readings of the TRV and returns what has to be done.
+    fn update(&mut self, room: &FrostRoom, current: Temperature, target: Temperature) -> Vec<Step> {
+        let mut steps = Vec::new();
+
+        if !self.cold && current < room.threshold {
+            self.cold = true;
+            steps.push(Step::Report(FrostChange::TooCold {
+                temperature: current,
+                threshold: room.threshold,
+            }));
+        } else if self.cold && current >= room.threshold + room.hysteresis {
+            self.cold = false;
+            steps.push(Step::Report(FrostChange::Recovered {
+                temperature: current,
+            }));
+        }
+
+        match (self.cold, self.boosted, room.boost) {
+            (true, None, Some(boost)) if target < Temperature::from_celsius(boost.into()) => {
+                self.boosted = Some((target, boost));
+                steps.push(Step::Boost(boost));
+            }
+            (false, Some((previous, boost)), _) => {
+                self.boosted = None;
+                if target == Temperature::from_celsius(boost.into()) {
+                    steps.push(Step::Restore(previous, boost));
+                }
+            }
+            _ => {}
+        }
+
+        steps
+    }
+
+    /// Undoes the effect of a `step` that failed, so that it's retried at the next update.
+    fn revert(&mut self, step: &Step) {
+        match step {
+            Step::Boost(_) => self.boosted = None,
+            Step::Restore(previous, boost) => self.boosted = Some((*previous, *boost)),
+            Step::Report(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn celsius(celsius: f32) -> Temperature {
+        Temperature::from_celsius(celsius)
+    }
+
+    #[test]
+    fn cold_rooms_are_reported_once() {
+        let room = FrostRoom::new(celsius(7.0));
+        let mut state = RoomState::default();
+
+        assert_eq!(state.update(&room, celsius(8.0), celsius(21.0)), vec![]);
+        assert_eq!(
+            state.update(&room, celsius(6.5), celsius(21.0)),
+            vec![Step::Report(FrostChange::TooCold {
+                temperature: celsius(6.5),
+                threshold: celsius(7.0),
+            })]
+        );
+        assert_eq!(state.update(&room, celsius(6.0), celsius(21.0)), vec![]);
+        assert_eq!(state.update(&room, celsius(7.5), celsius(21.0)), vec![]);
+        assert_eq!(
+            state.update(&room, celsius(8.0), celsius(21.0)),
+            vec![Step::Report(FrostChange::Recovered {
+                temperature: celsius(8.0)
+            })]
+        );
+    }
+
+    #[test]
+    fn boosted_target_is_restored() {
+        let room = FrostRoom::new(celsius(7.0)).with_boost(18);
+        let mut state = RoomState::default();
+
+        let steps = state.update(&room, celsius(6.0), celsius(15.0));
+        assert_eq!(steps[1], Step::Boost(18));
+        assert_eq!(state.update(&room, celsius(6.5), celsius(18.0)), vec![]);
+
+        let steps = state.update(&room, celsius(9.0), celsius(18.0));
+        assert_eq!(steps[1], Step::Restore(celsius(15.0), 18));
+
+        state.revert(&steps[1]);
+        assert_eq!(
+            state.update(&room, celsius(9.0), celsius(18.0)),
+            vec![Step::Restore(celsius(15.0), 18)]
+        );
+
+        let steps = state.update(&room, celsius(6.0), celsius(20.0));
+        assert_eq!(steps.len(), 1, "a higher target isn't boosted");
+
+        let steps = state.update(&room, celsius(6.0), celsius(15.0));
+        assert_eq!(steps, vec![Step::Boost(18)]);
+        let steps = state.update(&room, celsius(9.0), celsius(19.0));
+        assert_eq!(steps.len(), 1, "a target changed in the meantime is kept");
+    }
+}
@@ -0,0 +1,135 @@
+use async_trait::async_trait;
+
+use crate::error::Error;
+#[cfg(feature = "manager")]
+use crate::manager::DeviceHandle;
+use crate::{
+    ColorLightHandler, ColorLightStripHandler, GenericDeviceHandler, HubHandler, LightHandler,
+    PlugEnergyMonitoringHandler, PlugHandler,
+};
+
+/// Implemented by the device handlers that can be watched by [`crate::watcher::DeviceWatcher`].
+#[async_trait]
+pub trait WatchableDevice: Send + Sync {
+    /// Returns *device info* as [`serde_json::Value`].
+    async fn get_device_info_json(&self) -> Result<serde_json::Value, Error>;
+
+    /// Refreshes the authentication session.
+    async fn refresh_session(&mut self) -> Result<(), Error>;
+}
+
+macro_rules! impl_watchable_device {
+    ($($handler:ty),+) => {
+        $(
+            #[async_trait]
+            impl WatchableDevice for $handler {
+                async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
+                    <$handler>::get_device_info_json(self).await
+                }
+
+                async fn refresh_session(&mut self) -> Result<(), Error> {
+                    <$handler>::refresh_session(self).await.map(|_| ())
+                }
+            }
+        )+
+    };
+}
+
+impl_watchable_device!(
+    ColorLightHandler,
+    ColorLightStripHandler,
+    GenericDeviceHandler,
+    HubHandler,
+    LightHandler,
+    PlugEnergyMonitoringHandler,
+    PlugHandler
+);
+
+/// A child device of a hub that can be watched by [`crate::watcher::DeviceWatcher`],
+/// e.g. for reporting when its battery becomes low.
+///
+/// Its *device info* is read from the *child device list* of the hub, whatever the model of the device.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// # use std::time::Duration;
+/// # use tapo::ApiClient;
+/// # use tapo::watcher::{DeviceWatcher, WatchableChild};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let hub = ApiClient::new("tapo-username@example.com", "tapo-password")?
+///     .h100("192.168.1.100")
+///     .await?;
+///
+/// DeviceWatcher::new(WatchableChild::new(hub, "t310-device-id"), Duration::from_secs(600))
+///     .with_low_battery_threshold(15)
+///     .run(|change| println!("Change: {change:?}"))
+///     .await;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct WatchableChild {
+    hub: HubHandler,
+    device_id: String,
+}
+
+impl WatchableChild {
+    /// Returns a [`WatchableChild`] for the child device with the given `device_id`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hub` - the handler of the hub that the device is paired with
+    /// * `device_id` - the Device ID of the child device
+    pub fn new(hub: HubHandler, device_id: impl Into<String>) -> Self {
+        Self {
+            hub,
+            device_id: device_id.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl WatchableDevice for WatchableChild {
+    async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
+        let mut list = self.hub.get_child_device_list_json().await?;
+
+        list["child_device_list"]
+            .as_array_mut()
+            .and_then(|devices| {
+                devices
+                    .iter_mut()
+                    .find(|device| device["device_id"] == self.device_id.as_str())
+            })
+            .map(serde_json::Value::take)
+            .ok_or_else(|| Error::Validation {
+                field: "device_id".to_string(),
+                message: format!(
+                    "No child device `{}` is paired with the hub",
+                    self.device_id
+                ),
+            })
+    }
+
+    async fn refresh_session(&mut self) -> Result<(), Error> {
+        self.hub.refresh_session().await.map(|_| ())
+    }
+}
+
+/// Watches a device through its [`crate::manager::DeviceManager`], which rate limits the polls
+/// and refreshes the session itself.
+///
+/// The polls of a device in maintenance mode report no changes,
+/// see [`crate::manager::DeviceManager::set_maintenance`].
+#[cfg(feature = "manager")]
+#[async_trait]
+impl WatchableDevice for DeviceHandle {
+    async fn get_device_info_json(&self) -> Result<serde_json::Value, Error> {
+        DeviceHandle::get_device_info_json(self).await
+    }
+
+    async fn refresh_session(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,344 @@
+//! Delivery of [`DeviceEvent`]s to HTTP endpoints, e.g. n8n, Node-RED or serverless functions.
+//!
+//! Requires the `webhook` feature.
+//!
+//! Each event is sent as the JSON body of a `POST` request:
+//!
+//! ```json
+//! {
+//!   "device": "living-room-lamp",
+//!   "timestamp": "2024-01-01T12:00:00Z",
+//!   "event": { "type": "power_state_changed", "device_on": true }
+//! }
+//! ```
+//!
+//! Requests to the endpoints that have a secret carry the hex-encoded HMAC-SHA256 of the body,
+//! computed with the secret as the key, in the [`SIGNATURE_HEADER`] header as `sha256=<signature>`.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # use std::time::Duration;
+//! # use tapo::ApiClient;
+//! # use tapo::watcher::DeviceWatcher;
+//! # use tapo::webhook::WebhookDispatcher;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let dispatcher = WebhookDispatcher::builder()
+//!     .signed_endpoint("https://n8n.example.com/webhook/tapo", "webhook-secret")
+//!     .build()?;
+//!
+//! let device = ApiClient::new("tapo-username@example.com", "tapo-password")?
+//!     .l530("192.168.1.100")
+//!     .await?;
+//! let mut watcher = DeviceWatcher::new(device, Duration::from_secs(5));
+//!
+//! loop {
+//!     for event in watcher.poll_events().await? {
+//!         dispatcher.dispatch("living-room-lamp", &event).await?;
+//!     }
+//! }
+//! # }
+//! ```
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use isahc::http::header::CONTENT_TYPE;
+use isahc::http::{Request, StatusCode, Uri};
+use isahc::prelude::Configurable;
+use isahc::{AsyncReadResponseExt, HttpClient};
+use log::{debug, warn};
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use serde::Serialize;
+
+use crate::error::Error;
+use crate::events::DeviceEvent;
+
+/// The header that carries the signature of the body, see [`crate::webhook`].
+pub const SIGNATURE_HEADER: &str = "X-Tapo-Signature";
+
+/// The delay between two retries stops doubling once it reaches this, unless the first delay is longer.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Sends [`DeviceEvent`]s to the configured endpoints, see [`crate::webhook`].
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcher {
+    client: HttpClient,
+    endpoints: Vec<WebhookEndpoint>,
+    max_retries: u32,
+    retry_delay: Duration,
+}
+
+#[derive(Clone)]
+struct WebhookEndpoint {
+    url: String,
+    secret: Option<String>,
+}
+
+impl std::fmt::Debug for WebhookEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookEndpoint")
+            .field("url", &self.url)
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct WebhookPayload<'a> {
+    device: &'a str,
+    timestamp: DateTime<Utc>,
+    event: &'a DeviceEvent,
+}
+
+impl WebhookDispatcher {
+    /// Returns a [`WebhookDispatcherBuilder`] without endpoints.
+    pub fn builder() -> WebhookDispatcherBuilder {
+        WebhookDispatcherBuilder {
+            endpoints: Vec::new(),
+            timeout: Duration::from_secs(10),
+            max_retries: 3,
+            retry_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Sends `event` to every endpoint.
+    /// Failed deliveries are retried when the endpoint can't be reached, or responds with a server error
+    /// or `429 Too Many Requests`. The other endpoints are still tried when one of them fails,
+    /// in which case the first error is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - the name of the device the event comes from, sent in the `device` field
+    /// * `event` - the event to send
+    pub async fn dispatch(&self, device: &str, event: &DeviceEvent) -> Result<(), Error> {
+        let body = serde_json::to_vec(&WebhookPayload {
+            device,
+            timestamp: Utc::now(),
+            event,
+        })?;
+
+        let mut first_error = None;
+
+        for endpoint in &self.endpoints {
+            if let Err(err) = self.deliver(endpoint, &body).await {
+                warn!("Failed to deliver the event to {}: {err}", endpoint.url);
+                first_error.get_or_insert(err);
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    async fn deliver(&self, endpoint: &WebhookEndpoint, body: &[u8]) -> Result<(), Error> {
+        let signature = endpoint
+            .secret
+            .as_deref()
+            .map(|secret| sign(secret, body))
+            .transpose()?;
+
+        let mut attempt = 0;
+        let mut delay = self.retry_delay;
+
+        loop {
+            let mut request = Request::post(&endpoint.url).header(CONTENT_TYPE, "application/json");
+            if let Some(signature) = &signature {
+                request = request.header(SIGNATURE_HEADER, format!("sha256={signature}"));
+            }
+            let request = request.body(body.to_vec()).map_err(anyhow::Error::from)?;
+
+            let retryable = match self.client.send_async(request).await {
+                Ok(mut response) if response.status().is_success() => {
+                    debug!("Delivered the event to {}", endpoint.url);
+                    // Drain the body so that the connection can be reused.
+                    let _ = response.consume().await;
+                    return Ok(());
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let error = Error::Other(anyhow::anyhow!(
+                        "The webhook endpoint responded with {status}"
+                    ));
+
+                    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+                        error
+                    } else {
+                        return Err(error);
+                    }
+                }
+                Err(err) => err.into(),
+            };
+
+            if attempt >= self.max_retries {
+                return Err(retryable);
+            }
+
+            attempt += 1;
+            debug!(
+                "Retrying the delivery to {} in {delay:?} ({attempt}/{})",
+                endpoint.url, self.max_retries
+            );
+            tokio::time::sleep(delay).await;
+            delay = delay
+                .saturating_mul(2)
+                .min(MAX_RETRY_DELAY.max(self.retry_delay));
+        }
+    }
+}
+
+/// Builder for a [`WebhookDispatcher`], see [`WebhookDispatcher::builder`].
+#[derive(Debug, Clone)]
+pub struct WebhookDispatcherBuilder {
+    endpoints: Vec<WebhookEndpoint>,
+    timeout: Duration,
+    max_retries: u32,
+    retry_delay: Duration,
+}
+
+impl WebhookDispatcherBuilder {
+    /// Adds an endpoint whose requests aren't signed.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - the URL that the events are posted to
+    pub fn endpoint(mut self, url: impl Into<String>) -> Self {
+        self.endpoints.push(WebhookEndpoint {
+            url: url.into(),
+            secret: None,
+        });
+        self
+    }
+
+    /// Adds an endpoint whose requests are signed with `secret`, see [`SIGNATURE_HEADER`].
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - the URL that the events are posted to
+    /// * `secret` - the key of the HMAC-SHA256 signature, shared with the endpoint
+    pub fn signed_endpoint(mut self, url: impl Into<String>, secret: impl Into<String>) -> Self {
+        self.endpoints.push(WebhookEndpoint {
+            url: url.into(),
+            secret: Some(secret.into()),
+        });
+        self
+    }
+
+    /// Sets the maximum duration of a request. Defaults to 10 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets how many times a failed delivery is retried. Defaults to 3.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry, which doubles with every retry up to 5 minutes. Defaults to 1 second.
+    pub fn retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Builds the [`WebhookDispatcher`].
+    /// Returns [`Error::Validation`] if there are no endpoints or if an URL is invalid.
+    pub fn build(self) -> Result<WebhookDispatcher, Error> {
+        if self.endpoints.is_empty() {
+            return Err(Error::Validation {
+                field: "endpoints".to_string(),
+                message: "must contain at least one endpoint".to_string(),
+            });
+        }
+
+        for endpoint in &self.endpoints {
+            let is_http = endpoint
+                .url
+                .parse::<Uri>()
+                .ok()
+                .and_then(|uri| uri.scheme_str().map(str::to_string))
+                .is_some_and(|scheme| scheme == "http" || scheme == "https");
+
+            if !is_http {
+                return Err(Error::Validation {
+                    field: "endpoints".to_string(),
+                    message: format!("`{}` is not a valid HTTP URL", endpoint.url),
+                });
+            }
+        }
+
+        let client = HttpClient::builder().timeout(self.timeout).build()?;
+
+        Ok(WebhookDispatcher {
+            client,
+            endpoints: self.endpoints,
+            max_retries: self.max_retries,
+            retry_delay: self.retry_delay,
+        })
+    }
+}
+
+/// Returns the hex-encoded HMAC-SHA256 of `body`.
+fn sign(secret: &str, body: &[u8]) -> Result<String, Error> {
+    let key = PKey::hmac(secret.as_bytes()).map_err(anyhow::Error::from)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key).map_err(anyhow::Error::from)?;
+    signer.update(body).map_err(anyhow::Error::from)?;
+    let signature = signer.sign_to_vec().map_err(anyhow::Error::from)?;
+
+    Ok(base16ct::lower::encode_string(&signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_is_hmac_sha256() {
+        // RFC 4231, test case 2.
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?").unwrap(),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn payload_is_tagged_by_event_type() {
+        let event = DeviceEvent::PowerStateChanged { device_on: true };
+        let payload = WebhookPayload {
+            device: "lamp",
+            timestamp: DateTime::default(),
+            event: &event,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&payload).unwrap(),
+            serde_json::json!({
+                "device": "lamp",
+                "timestamp": "1970-01-01T00:00:00Z",
+                "event": { "type": "power_state_changed", "device_on": true },
+            })
+        );
+    }
+
+    #[test]
+    fn secret_is_redacted() {
+        let builder = WebhookDispatcher::builder()
+            .signed_endpoint("https://n8n.example.com/webhook/tapo", "webhook-secret");
+
+        let debug = format!("{builder:?}");
+        assert!(!debug.contains("webhook-secret"));
+        assert!(debug.contains("<redacted>"));
+    }
+
+    #[test]
+    fn build_rejects_invalid_endpoints() {
+        assert!(WebhookDispatcher::builder().build().is_err());
+        assert!(matches!(
+            WebhookDispatcher::builder().endpoint("not a url").build(),
+            Err(Error::Validation { field, .. }) if field == "endpoints"
+        ));
+    }
+}